@@ -0,0 +1,77 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Batch verification benchmarks
+//!
+//! Run them with `cargo bench --bench batch_verify`. For 10, 100 and 1000
+//! proofs over one ring of 5 keys, as a confession board might check:
+//!
+//!   * `batch_verify/sequential/<n>`: calling `verify` for each proof.
+//!   * `batch_verify/many/<n>`: one call to `verify_many`, which prepares
+//!     the ring once for all the proofs.
+//!
+//! The curve operations are the same either way, and dominate.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ringsig::keys::PublicKey;
+use ringsig::testkit;
+use ringsig::Proof;
+use std::hint::black_box;
+
+/// Numbers of proofs to verify
+const PROOF_COUNTS: [usize; 3] = [10, 100, 1000];
+/// Keys in the ring every proof is made over
+const RING_SIZE: usize = 5;
+
+/// The seed of the synthetic keys, the same as `ringsig-cli bench` uses
+const SEED: &[u8] = b"ringsig bench";
+
+fn batch_verify(c: &mut Criterion) {
+    let ring: Vec<PublicKey> = testkit::synthetic_ring(SEED, RING_SIZE);
+    let keypairs = testkit::deterministic_keypairs(SEED, RING_SIZE);
+    let max = PROOF_COUNTS[PROOF_COUNTS.len() - 1];
+    let messages: Vec<Vec<u8>> = (0..max).map(|n| format!("board message {}", n).into_bytes()).collect();
+    let proofs: Vec<Proof> = messages
+        .iter()
+        .enumerate()
+        .map(|(n, message)| ringsig::prove(&ring, message, &keypairs[n % RING_SIZE].secret.secret_key()).unwrap())
+        .collect();
+
+    let mut group = c.benchmark_group("batch_verify");
+    group.sample_size(10);
+    for count in PROOF_COUNTS {
+        let items: Vec<(&Proof, &[PublicKey], &[u8])> =
+            proofs.iter().zip(&messages).take(count).map(|(proof, message)| (proof, &ring[..], &message[..])).collect();
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::new("sequential", count), &items, |b, items| {
+            b.iter(|| {
+                for &(proof, keys, message) in items {
+                    ringsig::verify(black_box(proof), black_box(keys), black_box(message)).unwrap();
+                }
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("many", count), &items, |b, items| {
+            b.iter(|| {
+                let results = ringsig::verify_many(black_box(items));
+                assert!(results.iter().all(Result::is_ok));
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, batch_verify);
+criterion_main!(benches);
@@ -0,0 +1,244 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Benchmarks
+//!
+//! Run them all with `cargo bench`, or some with a filter on the benchmark
+//! name, e.g. `cargo bench -- verify/`. The benchmarks are:
+//!
+//!   * `prove/<n>` and `verify/<n>`: one proof over a ring of `n` keys.
+//!     Both should grow linearly in `n`.
+//!   * `verify_prepared/<n>`: as `verify/<n>`, with a [`Ring`] prepared
+//!     beforehand, so that the difference is the per-call setup it saves.
+//!   * `sign_prepared/<n>`: as `prove/<n>`, with a [`RingSigner`] prepared
+//!     beforehand. Only the message is hashed afresh, so the gap to
+//!     `prove/<n>` is the per-message overhead saved; at 1000 keys it is
+//!     mostly the params hash of the keys.
+//!   * `verify_batch/naive/100` and `verify_batch/batch/100`: 50 proofs over
+//!     one ring of 100 keys, verified one at a time with `verify` and all
+//!     together with `verify_batch`.
+//!   * `verify_batch/parallel-<t>/100`: the same, with `verify_batch_parallel`
+//!     on a pool of `t` threads, for `t` of 1, 2, 4 and 8. Only with the
+//!     `rayon` feature, as `cargo bench --features rayon -- parallel`; on an
+//!     8-core machine the time should roughly halve with each doubling.
+//!   * `prove_parallel/sequential/<n>` and `prove_parallel/parallel/<n>`:
+//!     one proof over a ring of `n` keys, for `n` of 100, 500 and 1000, with
+//!     `prove` and with `prove_parallel` on the default rayon pool. The
+//!     second only with the `rayon` feature. Only hashing the random `s`
+//!     values is spread across threads, so expect little difference.
+//!   * `keys_file/parse/1000`: a keys file of 1000 lines.
+//!   * `armor_decode/bundle/1000`: dearmoring and parsing a confession
+//!     bundle with a ring of 1000 keys.
+//!   * `base64/encode` and `base64/decode`: reported in bytes per second.
+//!
+//! These names and the inputs behind them are kept stable so that results
+//! stay comparable across commits. To look for a regression, save a
+//! baseline before the change with `cargo bench -- --save-baseline before`
+//! and compare against it after with `cargo bench -- --baseline before`;
+//! criterion prints the change in each benchmark and whether it is
+//! significant, and keeps full reports in `target/criterion`. Every input is
+//! derived deterministically, so all machines benchmark the same rings.
+
+use bitcoin_hashes::{sha256, Hash};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ringsig::bundle::Confession;
+use ringsig::format::{self, BUNDLE_BEGIN, BUNDLE_END};
+use ringsig::keyring::KeyRing;
+use ringsig::keys::{PublicKey, SecretKey};
+use ringsig::radix64::{base64_encode, radix64_decode};
+use ringsig::testkit;
+use ringsig::{Proof, Ring, RingSigner};
+use std::hint::black_box;
+
+/// Ring sizes for proving and verifying
+const RING_SIZES: [usize; 4] = [1, 10, 100, 1000];
+/// Ring sizes for comparing sequential and parallel proving
+const PARALLEL_RING_SIZES: [usize; 3] = [100, 500, 1000];
+/// Ring size and number of proofs for the batch verification benchmarks
+const BATCH_RING: usize = 100;
+const BATCH_PROOFS: usize = 50;
+/// Keys in the keys file and the bundle
+const LARGE_RING: usize = 1000;
+/// Bytes of data for the base64 benchmarks
+const BASE64_LEN: usize = 1 << 20;
+
+/// The message proven in every benchmark
+const MESSAGE: &[u8] = b"benchmark message";
+
+/// The seed of the synthetic keys, the same as `ringsig-cli bench` uses
+const SEED: &[u8] = b"ringsig bench";
+
+/// The first synthetic key
+fn synthetic_key() -> SecretKey {
+    testkit::deterministic_keypairs(SEED, 1)[0].secret.secret_key()
+}
+
+/// A synthetic ring of `size` keys, with the key of [`synthetic_key`] first
+fn synthetic_ring(size: usize) -> Vec<PublicKey> {
+    testkit::synthetic_ring(SEED, size)
+}
+
+/// `len` bytes of deterministic data
+fn synthetic_data(len: usize) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(len + 32);
+    let mut block = sha256::Hash::hash(b"ringsig bench data");
+    while ret.len() < len {
+        ret.extend_from_slice(&block[..]);
+        block = sha256::Hash::hash(&block[..]);
+    }
+    ret.truncate(len);
+    ret
+}
+
+fn prove_verify(c: &mut Criterion) {
+    let sk = synthetic_key();
+
+    let mut group = c.benchmark_group("prove");
+    group.sample_size(10);
+    for size in RING_SIZES {
+        let ring = synthetic_ring(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &ring, |b, ring| {
+            b.iter(|| ringsig::prove(black_box(ring), black_box(MESSAGE), &sk).unwrap())
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("verify");
+    group.sample_size(10);
+    for size in RING_SIZES {
+        let ring = synthetic_ring(size);
+        let proof = ringsig::prove(&ring, MESSAGE, &sk).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(size), &ring, |b, ring| {
+            b.iter(|| ringsig::verify(black_box(&proof), black_box(ring), black_box(MESSAGE)).unwrap())
+        });
+    }
+    group.finish();
+
+    // As `verify`, less the per-call sorting, serializing and negating of keys
+    let mut group = c.benchmark_group("verify_prepared");
+    group.sample_size(10);
+    for size in RING_SIZES {
+        let keys = synthetic_ring(size);
+        let proof = ringsig::prove(&keys, MESSAGE, &sk).unwrap();
+        let ring = Ring::new(&keys).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(size), &ring, |b, ring| {
+            b.iter(|| ring.verify(black_box(&proof), black_box(MESSAGE)).unwrap())
+        });
+    }
+    group.finish();
+
+    // As `prove`, less the per-call preparation of the ring and signer
+    let mut group = c.benchmark_group("sign_prepared");
+    group.sample_size(10);
+    for size in RING_SIZES {
+        let signer = RingSigner::new(&synthetic_ring(size), sk.clone()).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(size), &signer, |b, signer| {
+            b.iter(|| signer.sign(black_box(MESSAGE)).unwrap())
+        });
+    }
+    group.finish();
+
+    let ring = synthetic_ring(BATCH_RING);
+    let messages: Vec<Vec<u8>> = (0..BATCH_PROOFS).map(|n| format!("batch message {}", n).into_bytes()).collect();
+    let proofs: Vec<_> = messages.iter().map(|message| ringsig::prove(&ring, message, &sk).unwrap()).collect();
+    let batch: Vec<(&Proof, &[u8])> = proofs.iter().zip(&messages).map(|(proof, msg)| (proof, &msg[..])).collect();
+    let mut group = c.benchmark_group("verify_batch");
+    group.sample_size(10);
+    group.bench_with_input(BenchmarkId::new("naive", BATCH_RING), &ring, |b, ring| {
+        b.iter(|| {
+            for &(proof, message) in &batch {
+                ringsig::verify(black_box(proof), black_box(ring), black_box(message)).unwrap();
+            }
+        })
+    });
+    group.bench_with_input(BenchmarkId::new("batch", BATCH_RING), &ring, |b, ring| {
+        b.iter(|| {
+            let results = ringsig::verify_batch(black_box(&batch), black_box(ring));
+            assert!(results.iter().all(Result::is_ok));
+        })
+    });
+    #[cfg(feature = "rayon")]
+    for threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        let id = BenchmarkId::new(format!("parallel-{}", threads), BATCH_RING);
+        group.bench_with_input(id, &ring, |b, ring| {
+            b.iter(|| {
+                let results = pool.install(|| ringsig::verify_batch_parallel(black_box(&batch), black_box(ring)));
+                assert!(results.iter().all(Result::is_ok));
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("prove_parallel");
+    group.sample_size(10);
+    for size in PARALLEL_RING_SIZES {
+        let ring = synthetic_ring(size);
+        group.bench_with_input(BenchmarkId::new("sequential", size), &ring, |b, ring| {
+            b.iter(|| ringsig::prove(black_box(ring), black_box(MESSAGE), &sk).unwrap())
+        });
+        #[cfg(feature = "rayon")]
+        group.bench_with_input(BenchmarkId::new("parallel", size), &ring, |b, ring| {
+            b.iter(|| ringsig::prove_parallel(black_box(ring), black_box(MESSAGE), &sk).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn parsing(c: &mut Criterion) {
+    let ring = synthetic_ring(LARGE_RING);
+    let lines: Vec<_> = ring.iter().enumerate().map(|(n, pk)| testkit::key_line(pk, &format!("user{}", n))).collect();
+
+    let keys_file = lines.join("\n") + "\n";
+    let mut group = c.benchmark_group("keys_file");
+    group.sample_size(10);
+    group.bench_with_input(BenchmarkId::new("parse", LARGE_RING), &keys_file, |b, keys_file| {
+        b.iter(|| {
+            let keyring = KeyRing::parse(black_box(keys_file));
+            assert_eq!(keyring.keys().len(), LARGE_RING);
+            keyring
+        })
+    });
+    group.finish();
+
+    let mut confession = Confession::new(lines, String::from_utf8(MESSAGE.to_vec()).unwrap());
+    let proof = ringsig::prove(&ring, MESSAGE, &synthetic_key()).unwrap();
+    confession.proof = Some(proof.to_hex());
+    let armored = format::armor(BUNDLE_BEGIN, BUNDLE_END, confession.to_json_string().as_bytes());
+    let mut group = c.benchmark_group("armor_decode");
+    group.throughput(Throughput::Bytes(armored.len() as u64));
+    group.bench_with_input(BenchmarkId::new("bundle", LARGE_RING), &armored, |b, armored| {
+        b.iter(|| {
+            let json = format::dearmor(black_box(armored), BUNDLE_BEGIN, BUNDLE_END).unwrap();
+            Confession::from_json_str(std::str::from_utf8(&json).unwrap()).unwrap()
+        })
+    });
+    group.finish();
+}
+
+fn base64(c: &mut Criterion) {
+    let data = synthetic_data(BASE64_LEN);
+    let encoded = base64_encode(&data);
+
+    let mut group = c.benchmark_group("base64");
+    group.throughput(Throughput::Bytes(BASE64_LEN as u64));
+    group.bench_function("encode", |b| b.iter(|| base64_encode(black_box(&data))));
+    group.bench_function("decode", |b| b.iter(|| radix64_decode(black_box(&encoded)).unwrap()));
+    group.finish();
+}
+
+criterion_group!(benches, prove_verify, parsing, base64);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Parse arbitrary text as each kind of armor the library reads, checking
+//! that nothing panics and that what it writes back reads the same
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ringsig::armor::{FromArmor, OpensshKey};
+use ringsig::format;
+use ringsig::keys::SecretKey;
+use ringsig::sshsig::Signature;
+
+fuzz_target!(|data: &[u8]| {
+    let s = match std::str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+
+    // Secret keys are only read; decrypting is left out since the KDF
+    // rounds come from the input
+    if let Ok(key) = OpensshKey::from_armor(s) {
+        let _ = key.public_key();
+        if !key.is_encrypted() {
+            let _ = SecretKey::from_armor(s);
+        }
+    }
+
+    if let Ok(sig) = Signature::from_armor(s) {
+        let armor = sig.to_armor();
+        assert_eq!(Signature::from_armor(&armor).as_ref(), Ok(&sig), "re-reading {}", armor);
+    }
+
+    for (begin, end) in [(format::PROOF_BEGIN, format::PROOF_END), (format::BUNDLE_BEGIN, format::BUNDLE_END)] {
+        if let Ok(data) = format::dearmor(s, begin, end) {
+            let armor = format::armor(begin, end, &data);
+            assert_eq!(format::dearmor(&armor, begin, end).as_ref(), Ok(&data), "re-reading {}", armor);
+        }
+    }
+});
@@ -0,0 +1,52 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Parse arbitrary text as public keys and keys files, checking that
+//! nothing panics and that keys read the same once written back
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ringsig::keyring::KeyRing;
+use ringsig::keys::PublicKey;
+use ringsig::radix64::base64_encode;
+
+/// The `ssh-ed25519` line for a key, without a comment
+fn pk_line(pk: &PublicKey) -> String {
+    let mut blob = 11u32.to_be_bytes().to_vec();
+    blob.extend_from_slice(b"ssh-ed25519");
+    blob.extend_from_slice(&32u32.to_be_bytes());
+    blob.extend_from_slice(&pk.serialize());
+    format!("ssh-ed25519 {}", base64_encode(&blob))
+}
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(pk) = PublicKey::parse(data) {
+        assert_eq!(PublicKey::parse(&pk.serialize()), Ok(pk));
+    }
+
+    let s = match std::str::from_utf8(data) {
+        Ok(s) => s,
+        Err(_) => return,
+    };
+    if let Ok(pk) = PublicKey::parse_pk_line(s) {
+        assert_eq!(PublicKey::parse_pk_line(&pk_line(&pk)), Ok(pk), "re-reading {}", s);
+    }
+
+    let ring = KeyRing::parse(s);
+    let written = ring.to_string();
+    assert_eq!(KeyRing::parse(&written), ring, "re-reading {}", written);
+});
@@ -0,0 +1,35 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Decode arbitrary text as base64 and encode arbitrary bytes, checking
+//! that nothing panics and that each direction undoes the other
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ringsig::radix64::{base64_encode, radix64_decode};
+
+fuzz_target!(|data: &[u8]| {
+    let encoded = base64_encode(data);
+    assert_eq!(radix64_decode(&encoded).as_deref(), Ok(data), "decoding {}", encoded);
+
+    if let Ok(s) = std::str::from_utf8(data) {
+        if let Ok(decoded) = radix64_decode(s) {
+            let encoded = base64_encode(&decoded);
+            assert_eq!(radix64_decode(&encoded).as_ref(), Ok(&decoded), "decoding {}", encoded);
+        }
+    }
+});
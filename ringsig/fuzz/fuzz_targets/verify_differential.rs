@@ -0,0 +1,98 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Mutate valid proofs and check that the library's verifier agrees with
+//! the naive one
+//!
+//! The first byte chooses the ring size (its low two bits, plus one, capped
+//! at three), whether the proof is linkable (0x80) and whether to drop its
+//! last byte (0x40). The remaining bytes are pairs of a position and a value
+//! to XOR in there, over the proof followed by the key image.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ringsig::keys::{PublicKey, SecretKey};
+use ringsig::Proof;
+use ringsig_fuzz::naive;
+use std::sync::OnceLock;
+
+const MESSAGE: &[u8] = b"fuzzing the verifier";
+const SCOPE: &[u8] = b"fuzz";
+
+struct Case {
+    ring: Vec<PublicKey>,
+    keys: Vec<[u8; 32]>,
+    proof: Proof,
+    linkable_proof: Proof,
+    key_image: [u8; 32],
+}
+
+/// Valid proofs for rings of one to three keys, made once
+fn cases() -> &'static [Case] {
+    static CASES: OnceLock<Vec<Case>> = OnceLock::new();
+    CASES.get_or_init(|| {
+        if cfg!(fuzzing) {
+            panic!("bitcoin_hashes fakes SHA-256 under cfg(fuzzing); run with --no-cfg-fuzzing");
+        }
+        (1..=3u8)
+            .map(|n| {
+                let ring: Vec<_> = (1..=n).map(|i| SecretKey::from_bytes([i; 32]).to_public()).collect();
+                let sk = SecretKey::from_bytes([n; 32]);
+                let proof = ringsig::prove(&ring, MESSAGE, &sk).unwrap();
+                let (linkable_proof, key_image) = ringsig::prove_linkable(&ring, MESSAGE, SCOPE, &sk).unwrap();
+                let keys = ring.iter().map(PublicKey::serialize).collect();
+                Case { ring, keys, proof, linkable_proof, key_image }
+            })
+            .collect()
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let (&mode, mutations) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let case = &cases()[usize::from(mode & 3).min(2)];
+    let linkable = mode & 0x80 != 0;
+    let mut proof = if linkable { &case.linkable_proof } else { &case.proof }.as_bytes().to_vec();
+    let mut key_image = case.key_image;
+    if mode & 0x40 != 0 {
+        proof.pop();
+    }
+    let mutated = mode & 0x40 != 0 || mutations.chunks_exact(2).any(|pair| pair[1] != 0);
+    for pair in mutations.chunks_exact(2) {
+        let pos = usize::from(pair[0]) % (proof.len() + 32);
+        match proof.get_mut(pos) {
+            Some(byte) => *byte ^= pair[1],
+            None => key_image[pos - proof.len()] ^= pair[1],
+        }
+    }
+
+    // Bytes which do not parse as a proof are rejected by the library
+    let parsed = Proof::from_bytes(proof.clone()).ok();
+    let (library, naive) = if linkable {
+        let library = |parsed: Proof| ringsig::verify_linkable(&parsed, &key_image, &case.ring, MESSAGE, SCOPE).is_ok();
+        (parsed.is_some_and(library), naive::verify_linkable(&proof, &key_image, &case.keys, MESSAGE, SCOPE))
+    } else {
+        let library = |parsed: Proof| ringsig::verify(&parsed, &case.ring, MESSAGE).is_ok();
+        (parsed.is_some_and(library), naive::verify(&proof, &case.keys, MESSAGE))
+    };
+    assert_eq!(library, naive, "the verifiers disagree on {:02x?} with key image {:02x?}", proof, key_image);
+    if !mutated {
+        assert!(library, "an unaltered proof failed");
+    }
+});
@@ -0,0 +1,30 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Fuzzing Support
+//!
+//! Helpers shared by the fuzz targets in `fuzz_targets/`, chiefly an
+//! independent verifier to compare the library's against. The targets are
+//! run with `cargo fuzz run <target>` from this directory, starting from the
+//! seeds in `corpus/<target>`.
+//!
+//! Under `cfg(fuzzing)`, which `cargo fuzz` sets by default, `bitcoin_hashes`
+//! replaces SHA-256 with a trivial function so that fuzzers can get past
+//! hash checks. The library's proofs are then not real proofs, so the
+//! differential target must be run with
+//! `cargo fuzz run --no-cfg-fuzzing verify_differential`.
+
+pub mod naive;
@@ -0,0 +1,388 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! A Naive Verifier
+//!
+//! This verifies proofs from the description of the protocol rather than
+//! from the library's code. Field and curve arithmetic follow the reference
+//! code of RFC 8032 on big integers, and hashing uses the `sha2` crate
+//! rather than `bitcoin_hashes`. Nothing here is constant time or fast.
+//!
+//! For a ring of keys `P_0, ..., P_{n-1}`, sorted by their encodings, and a
+//! message `m`, a proof is `e_0 || s_0 || ... || s_{n-1}` and
+//!
+//! ```text
+//! params  = H_Params(n as u32 LE || P_0 || ... || P_{n-1} || len(m) as u64 LE || m)
+//! R_i     = s_i B - e_i P_i
+//! e_{i+1} = H_Challenge(R_i || params)
+//! ```
+//!
+//! and the proof is valid if `e_n = e_0`. A linkable proof in a scope has a
+//! key image `I` and uses the scope point `G'` as a second generator:
+//!
+//! ```text
+//! params  = H_Link(params || G' || I)
+//! R'_i    = s_i G' - e_i I
+//! e_{i+1} = H_Challenge(R_i || R'_i || params)
+//! ```
+//!
+//! where `G'` is eight times the first valid point decoded from
+//! `H_Scope(len(scope) as u64 LE || scope || ctr as u32 LE)` for
+//! `ctr = 0, 1, ...` which is not the identity. `H_Tag` is the BIP-340
+//! tagged SHA-256 with tag `CryptoConfessions-1.0/Tag`. Points are encoded
+//! as in RFC 8032, and scalars are 32-byte little-endian integers with the
+//! top bit ignored, reduced modulo the group order. Keys and key images
+//! must be in the prime-order subgroup, and key images not the identity.
+//!
+//! Decoding is strict, rejecting `y` coordinates which are not reduced. The
+//! library is more lenient, but the only points on which that makes a
+//! difference are aliases of the identity, which neither accepts as a key
+//! image, and hashes to the scope point are vanishingly unlikely to hit them.
+
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256, Sha512};
+use std::sync::OnceLock;
+
+/// The field prime, group order, curve constant and related values
+struct Constants {
+    p: BigUint,
+    l: BigUint,
+    d: BigUint,
+    sqrt_m1: BigUint,
+    base: Point,
+}
+
+fn constants() -> &'static Constants {
+    static CONSTANTS: OnceLock<Constants> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        let p = (BigUint::from(1u8) << 255u32) - 19u32;
+        let l = (BigUint::from(1u8) << 252u32) + "27742317777372353535851937790883648493".parse::<BigUint>().unwrap();
+        let inv = |x: &BigUint| x.modpow(&(&p - 2u32), &p);
+        let d = (&p - BigUint::from(121665u32)) * inv(&BigUint::from(121666u32)) % &p;
+        let sqrt_m1 = BigUint::from(2u8).modpow(&((&p - 1u32) / 4u32), &p);
+        let base_y = BigUint::from(4u8) * inv(&BigUint::from(5u8)) % &p;
+        let mut ret = Constants { p, l, d, sqrt_m1, base: Point::identity() };
+        let base_x = recover_x(&ret, &base_y, false).unwrap();
+        ret.base = Point { t: &base_x * &base_y % &ret.p, x: base_x, y: base_y, z: BigUint::from(1u8) };
+        ret
+    })
+}
+
+fn modp_inv(x: &BigUint) -> BigUint {
+    let c = constants();
+    x.modpow(&(&c.p - 2u32), &c.p)
+}
+
+/// `a - b` modulo `p`, for reduced `a` and `b`
+fn modp_sub(a: &BigUint, b: &BigUint) -> BigUint {
+    let p = &constants().p;
+    (a + p - b) % p
+}
+
+/// Recover the x coordinate from the y coordinate and the sign of x
+fn recover_x(c: &Constants, y: &BigUint, sign: bool) -> Option<BigUint> {
+    let p = &c.p;
+    if y >= p {
+        return None;
+    }
+    let one = BigUint::from(1u8);
+    let y2 = y * y % p;
+    let inv = (&c.d * &y2 + 1u32).modpow(&(p - 2u32), p);
+    let x2 = (&y2 + p - &one) * inv % p;
+    if x2 == BigUint::default() {
+        return if sign { None } else { Some(x2) };
+    }
+    let mut x = x2.modpow(&((p + 3u32) / 8u32), p);
+    if (&x * &x) % p != x2 {
+        x = x * &c.sqrt_m1 % p;
+    }
+    if (&x * &x) % p != x2 {
+        return None;
+    }
+    if x.bit(0) != sign {
+        x = p - x;
+    }
+    Some(x)
+}
+
+/// A point in extended coordinates, `(X:Y:Z:T)` with `x = X/Z`, `y = Y/Z`
+/// and `xy = T/Z`
+#[derive(Clone, Debug)]
+pub struct Point {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+    t: BigUint,
+}
+
+impl Point {
+    fn identity() -> Self {
+        Point { x: BigUint::default(), y: BigUint::from(1u8), z: BigUint::from(1u8), t: BigUint::default() }
+    }
+
+    /// The base point
+    pub fn base() -> Self {
+        constants().base.clone()
+    }
+
+    pub fn add(&self, other: &Point) -> Point {
+        let c = constants();
+        let p = &c.p;
+        let a = modp_sub(&self.y, &self.x) * modp_sub(&other.y, &other.x) % p;
+        let b = (&self.y + &self.x) * (&other.y + &other.x) % p;
+        let cc = BigUint::from(2u8) * &self.t * &other.t * &c.d % p;
+        let d = BigUint::from(2u8) * &self.z * &other.z % p;
+        let (e, f, g, h) = (modp_sub(&b, &a), modp_sub(&d, &cc), (&d + &cc) % p, (&b + &a) % p);
+        Point { x: &e * &f % p, y: &g * &h % p, z: &f * &g % p, t: &e * &h % p }
+    }
+
+    pub fn neg(&self) -> Point {
+        let p = &constants().p;
+        Point { x: (p - &self.x) % p, y: self.y.clone(), z: self.z.clone(), t: (p - &self.t) % p }
+    }
+
+    /// Multiply by a non-negative integer, by double-and-add
+    pub fn mul(&self, s: &BigUint) -> Point {
+        let mut ret = Point::identity();
+        let mut base = self.clone();
+        for i in 0..s.bits() {
+            if s.bit(i) {
+                ret = ret.add(&base);
+            }
+            base = base.add(&base);
+        }
+        ret
+    }
+
+    pub fn equals(&self, other: &Point) -> bool {
+        let p = &constants().p;
+        (&self.x * &other.z) % p == (&other.x * &self.z) % p
+            && (&self.y * &other.z) % p == (&other.y * &self.z) % p
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.equals(&Point::identity())
+    }
+
+    /// Whether the point is in the prime-order subgroup
+    pub fn is_torsion_free(&self) -> bool {
+        self.mul(&constants().l).is_identity()
+    }
+
+    pub fn compress(&self) -> [u8; 32] {
+        let p = &constants().p;
+        let zinv = modp_inv(&self.z);
+        let x = &self.x * &zinv % p;
+        let y = &self.y * &zinv % p;
+        let mut ret = [0; 32];
+        let bytes = y.to_bytes_le();
+        ret[..bytes.len()].copy_from_slice(&bytes);
+        if x.bit(0) {
+            ret[31] |= 0x80;
+        }
+        ret
+    }
+
+    pub fn decompress(bytes: &[u8; 32]) -> Option<Point> {
+        let c = constants();
+        let sign = bytes[31] & 0x80 != 0;
+        let mut y = *bytes;
+        y[31] &= 0x7f;
+        let y = BigUint::from_bytes_le(&y);
+        let x = recover_x(c, &y, sign)?;
+        Some(Point { t: &x * &y % &c.p, x, y, z: BigUint::from(1u8) })
+    }
+}
+
+/// A scalar: 32 little-endian bytes, ignoring the top bit, reduced
+fn scalar(bytes: &[u8]) -> BigUint {
+    let mut bytes = <[u8; 32]>::try_from(bytes).unwrap();
+    bytes[31] &= 0x7f;
+    BigUint::from_bytes_le(&bytes) % &constants().l
+}
+
+/// The BIP-340 tagged hash `CryptoConfessions-1.0/<tag>`
+fn tagged(tag: &str, data: &[&[u8]]) -> [u8; 32] {
+    let tag_hash = Sha256::digest(format!("CryptoConfessions-1.0/{}", tag).as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash);
+    hasher.update(tag_hash);
+    for piece in data {
+        hasher.update(piece);
+    }
+    hasher.finalize().into()
+}
+
+/// The second generator of linkable proofs in a scope
+pub fn scope_point(scope: &[u8]) -> Point {
+    for ctr in 0u32.. {
+        let hash = tagged("Scope", &[&(scope.len() as u64).to_le_bytes(), scope, &ctr.to_le_bytes()]);
+        if let Some(point) = Point::decompress(&hash) {
+            let point = point.mul(&BigUint::from(8u8));
+            if !point.is_identity() {
+                return point;
+            }
+        }
+    }
+    unreachable!("about half of all hashes are valid points")
+}
+
+/// Verify a proof
+pub fn verify(proof: &[u8], keys: &[[u8; 32]], message: &[u8]) -> bool {
+    verify_inner(proof, keys, message, None)
+}
+
+/// Verify a linkable proof with the given key image
+pub fn verify_linkable(proof: &[u8], key_image: &[u8; 32], keys: &[[u8; 32]], message: &[u8], scope: &[u8]) -> bool {
+    match Point::decompress(key_image) {
+        Some(image) if !image.is_identity() && image.is_torsion_free() => {
+            verify_inner(proof, keys, message, Some((scope_point(scope), image)))
+        }
+        _ => false,
+    }
+}
+
+fn verify_inner(proof: &[u8], keys: &[[u8; 32]], message: &[u8], link: Option<(Point, Point)>) -> bool {
+    let n = keys.len();
+    if n == 0 || proof.len() != 32 * (n + 1) {
+        return false;
+    }
+    let mut keys = keys.to_vec();
+    keys.sort();
+    let mut points = vec![];
+    for key in &keys {
+        match Point::decompress(key) {
+            Some(point) if point.is_torsion_free() => points.push(point),
+            _ => return false,
+        }
+    }
+
+    let mut data = vec![(n as u32).to_le_bytes().to_vec()];
+    data.extend(keys.iter().map(|key| key.to_vec()));
+    data.push((message.len() as u64).to_le_bytes().to_vec());
+    data.push(message.to_vec());
+    let mut params = tagged("Params", &data.iter().map(Vec::as_slice).collect::<Vec<_>>());
+    if let Some((ref base, ref image)) = link {
+        params = tagged("Link", &[&params, &base.compress(), &image.compress()]);
+    }
+
+    let mut e = <[u8; 32]>::try_from(&proof[..32]).unwrap();
+    for (i, point) in points.iter().enumerate() {
+        let s = scalar(&proof[32 * (i + 1)..32 * (i + 2)]);
+        let e_scalar = scalar(&e);
+        let r = Point::base().mul(&s).add(&point.mul(&e_scalar).neg());
+        e = match link {
+            None => tagged("Challenge", &[&r.compress(), &params]),
+            Some((ref base, ref image)) => {
+                let r_link = base.mul(&s).add(&image.mul(&e_scalar).neg());
+                tagged("Challenge", &[&r.compress(), &r_link.compress(), &params])
+            }
+        };
+    }
+    e[..] == proof[..32]
+}
+
+/// The public key of an RFC 8032 secret key, for testing the arithmetic
+/// against published vectors
+pub fn ed25519_public_key(secret: &[u8; 32]) -> [u8; 32] {
+    let hash = Sha512::digest(secret);
+    let mut a = <[u8; 32]>::try_from(&hash[..32]).unwrap();
+    a[0] &= 248;
+    a[31] &= 127;
+    a[31] |= 64;
+    Point::base().mul(&BigUint::from_bytes_le(&a)).compress()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringsig::keys::{PublicKey, SecretKey};
+    use ringsig::Proof;
+
+    fn unhex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn key(n: u8) -> (SecretKey, [u8; 32]) {
+        let sk = SecretKey::from_bytes([n; 32]);
+        let pk = sk.to_public().serialize();
+        (sk, pk)
+    }
+
+    #[test]
+    fn arithmetic() {
+        let base = unhex("5866666666666666666666666666666666666666666666666666666666666666");
+        assert_eq!(Point::base().compress()[..], base);
+        assert!(Point::base().mul(&constants().l).is_identity());
+        assert!(Point::base().add(&Point::base()).equals(&Point::base().mul(&BigUint::from(2u8))));
+        assert!(Point::base().add(&Point::base().neg()).is_identity());
+
+        // RFC 8032, section 7.1, test 1
+        let secret = unhex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60");
+        let public = ed25519_public_key(&secret.try_into().unwrap());
+        assert_eq!(public[..], unhex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a"));
+        assert_eq!(Point::decompress(&public).unwrap().compress(), public);
+    }
+
+    #[test]
+    fn strict_decoding() {
+        // y = 2^255 - 1 is not reduced
+        let mut unreduced = [0xff; 32];
+        unreduced[31] = 0x7f;
+        assert!(Point::decompress(&unreduced).is_none());
+        // x = 0 with the sign bit set
+        let mut negative_zero = [0; 32];
+        negative_zero[0] = 1;
+        negative_zero[31] = 0x80;
+        assert!(Point::decompress(&negative_zero).is_none());
+        // The point of order 4, (sqrt(-1), 0), is decoded but not torsion free
+        let order_four = Point::decompress(&[0; 32]).unwrap();
+        assert!(!order_four.is_torsion_free());
+    }
+
+    #[test]
+    fn agrees_with_library() {
+        let (sk, pk) = key(1);
+        let keys = [key(2).1, pk, key(3).1];
+        let ring: Vec<_> = keys.iter().map(|key| PublicKey::parse(key).unwrap()).collect();
+        let proof = ringsig::prove(&ring, b"naive", &sk).unwrap();
+        let bytes = proof.as_bytes();
+        assert!(verify(bytes, &keys, b"naive"));
+        assert!(!verify(bytes, &keys, b"naive!"));
+        assert!(!verify(bytes, &keys[..2], b"naive"));
+        let mut flipped = bytes.to_vec();
+        flipped[40] ^= 1;
+        assert!(!verify(&flipped, &keys, b"naive"));
+        let flipped = Proof::from_bytes(flipped).unwrap();
+        assert!(ringsig::verify(&flipped, &ring, b"naive").is_err());
+
+        // Scalars are reduced, so adding the group order to one changes nothing
+        let mut unreduced = bytes.to_vec();
+        let s = BigUint::from_bytes_le(&bytes[32..64]) + &constants().l;
+        if s.bits() < 255 {
+            unreduced[32..64].copy_from_slice(&s.to_bytes_le());
+            assert!(verify(&unreduced, &keys, b"naive"));
+            let unreduced = Proof::from_bytes(unreduced).unwrap();
+            assert!(ringsig::verify(&unreduced, &ring, b"naive").is_ok());
+        }
+
+        let (proof, image) = ringsig::prove_linkable(&ring, b"naive", b"scope", &sk).unwrap();
+        assert!(verify_linkable(proof.as_bytes(), &image, &keys, b"naive", b"scope"));
+        assert!(!verify_linkable(proof.as_bytes(), &image, &keys, b"naive", b"other scope"));
+        assert!(!verify_linkable(proof.as_bytes(), &key(4).1, &keys, b"naive", b"scope"));
+        assert!(!verify(proof.as_bytes(), &keys, b"naive"));
+    }
+}
@@ -0,0 +1,316 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Signed Attributes
+//!
+//! A proof may commit to a signing time, an expiry time and the confession
+//! it replies to alongside its message. Rather than signing the message directly, such a proof signs a
+//! tagged hash of the message and the attributes, so neither can be changed
+//! without invalidating the proof. A proof with no attributes signs the bare
+//! message, exactly as before attributes existed.
+//!
+//! Times are seconds since the Unix epoch, in UTC. A reply refers to its
+//! parent by [`Confession::bundle_hash`](crate::bundle::Confession::bundle_hash);
+//! see [`crate::thread`].
+
+use bitcoin_hashes::{Hash, HashEngine};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::hashes::{AttributesHash, BundleHash};
+
+/// Field tag for the signing time in the committed encoding
+const TAG_TIMESTAMP: u8 = 1;
+/// Field tag for the expiry time in the committed encoding
+const TAG_EXPIRES: u8 = 2;
+/// Field tag for the hash of the parent confession in the committed encoding
+const TAG_REPLY_TO: u8 = 3;
+
+/// Seconds in a day
+const DAY: u64 = 86400;
+
+/// Problems with attributes or times
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The proof expired at `expires`, before the time `at` it was checked at
+    Expired { expires: u64, at: u64 },
+    /// The proof was made at `timestamp`, after the time `at` it was checked at
+    NotYetSigned { timestamp: u64, at: u64 },
+    /// The expiry is not after the signing time
+    ExpiresBeforeSigning,
+    /// A time or duration did not parse
+    BadTime(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Expired { expires, at } => {
+                write!(f, "proof expired at {} (checked at {})", format_time(expires), format_time(at))
+            }
+            Error::NotYetSigned { timestamp, at } => {
+                write!(f, "proof was made at {}, after {}", format_time(timestamp), format_time(at))
+            }
+            Error::ExpiresBeforeSigning => f.write_str("expiry time is not after the signing time"),
+            Error::BadTime(ref s) => write!(
+                f,
+                "could not parse time `{}` (expected YYYY-MM-DD, YYYY-MM-DDTHH:MM:SSZ, \
+                 or a duration like 30d, 12h or 2w)",
+                s,
+            ),
+        }
+    }
+}
+
+/// Attributes covered by a proof's signature
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct SignedAttributes {
+    /// When the proof was made
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// When the proof stops being valid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    /// The hash of the confession this one replies to
+    #[serde(rename = "replyTo", default, skip_serializing_if = "Option::is_none", with = "hex_hash")]
+    #[cfg_attr(feature = "schema", schemars(with = "Option<String>"))]
+    pub reply_to: Option<BundleHash>,
+}
+
+/// Serializing a hash as hex, as the other hashes in confessions are
+mod hex_hash {
+    use bitcoin_hashes::hex::{FromHex, ToHex};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::hashes::BundleHash;
+
+    pub fn serialize<S: Serializer>(hash: &Option<BundleHash>, s: S) -> Result<S::Ok, S::Error> {
+        match hash {
+            Some(hash) => s.serialize_some(&hash.to_hex()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<BundleHash>, D::Error> {
+        match Option::<String>::deserialize(d)? {
+            Some(hex) => BundleHash::from_hex(&hex).map(Some).map_err(|_| D::Error::custom("not 32 bytes of hex")),
+            None => Ok(None),
+        }
+    }
+}
+
+impl SignedAttributes {
+    /// Whether there are no attributes, so the bare message is signed
+    pub fn is_empty(&self) -> bool {
+        self.timestamp.is_none() && self.expires.is_none() && self.reply_to.is_none()
+    }
+
+    /// The bytes actually signed for `message` with these attributes
+    pub fn signed_message(&self, message: &[u8]) -> Vec<u8> {
+        if self.is_empty() {
+            return message.to_vec();
+        }
+        let mut eng = AttributesHash::engine();
+        eng.input(&(u64::try_from(message.len()).unwrap().to_le_bytes()));
+        eng.input(message);
+        for (tag, value) in [(TAG_TIMESTAMP, self.timestamp), (TAG_EXPIRES, self.expires)] {
+            if let Some(value) = value {
+                eng.input(&[tag]);
+                eng.input(&value.to_le_bytes());
+            }
+        }
+        if let Some(parent) = self.reply_to {
+            eng.input(&[TAG_REPLY_TO]);
+            eng.input(&parent[..]);
+        }
+        AttributesHash::from_engine(eng).into_inner().to_vec()
+    }
+
+    /// Check that the expiry, if any, is after the signing time, if any
+    pub fn check_consistent(&self) -> Result<(), Error> {
+        match (self.timestamp, self.expires) {
+            (Some(timestamp), Some(expires)) if expires <= timestamp => Err(Error::ExpiresBeforeSigning),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that the proof is valid at time `at`: made no later than it,
+    /// and not yet expired
+    pub fn check_valid_at(&self, at: u64) -> Result<(), Error> {
+        if let Some(timestamp) = self.timestamp.filter(|&timestamp| timestamp > at) {
+            return Err(Error::NotYetSigned { timestamp, at });
+        }
+        match self.expires {
+            Some(expires) if expires <= at => Err(Error::Expired { expires, at }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Days since the epoch of a proleptic Gregorian date
+///
+/// This is Howard Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400) as u64;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe as i64 - 719468
+}
+
+/// The date of a number of days since the epoch; the inverse of
+/// [`days_from_civil`]
+fn civil_from_days(days: i64) -> (i64, u64, u64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days.rem_euclid(146097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe as i64 + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Format a time as RFC 3339, e.g. `2025-12-31T00:00:00Z`
+pub fn format_time(time: u64) -> String {
+    let (year, month, day) = civil_from_days((time / DAY) as i64);
+    let secs = time % DAY;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Parse a fixed-width decimal field
+fn field(s: &str, range: std::ops::Range<usize>, max: u64) -> Option<u64> {
+    let digits = s.get(range)?;
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok().filter(|&n| n <= max)
+}
+
+/// Parse an absolute time, `YYYY-MM-DD` (midnight UTC) or
+/// `YYYY-MM-DDTHH:MM:SSZ`
+fn parse_absolute(s: &str) -> Option<u64> {
+    let bytes = s.as_bytes();
+    if bytes.len() < 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let year = field(s, 0..4, 9999)?;
+    let month = field(s, 5..7, 12).filter(|&m| m >= 1)?;
+    let day = field(s, 8..10, 31).filter(|&d| d >= 1)?;
+    let secs = match &bytes[10..] {
+        [] => 0,
+        [b'T', .., b'Z'] if bytes.len() == 20 && bytes[13] == b':' && bytes[16] == b':' => {
+            field(s, 11..13, 23)? * 3600 + field(s, 14..16, 59)? * 60 + field(s, 17..19, 59)?
+        }
+        _ => return None,
+    };
+    let days = days_from_civil(year as i64, month, day);
+    // Reject dates like February 30th, which would silently roll over
+    if civil_from_days(days) != (year as i64, month, day) {
+        return None;
+    }
+    u64::try_from(days).ok().map(|days| days * DAY + secs)
+}
+
+/// Parse a duration such as `30d`, in seconds
+fn parse_duration(s: &str) -> Option<u64> {
+    let unit = match s.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => DAY,
+        'w' => 7 * DAY,
+        _ => return None,
+    };
+    let count = &s[..s.len() - 1];
+    if count.is_empty() || !count.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    count.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+/// Parse a time given either absolutely (see [`format_time`]; the time of
+/// day may be omitted) or as a duration after `now`
+pub fn parse_time(s: &str, now: u64) -> Result<u64, Error> {
+    parse_absolute(s)
+        .or_else(|| parse_duration(s).and_then(|duration| now.checked_add(duration)))
+        .ok_or_else(|| Error::BadTime(s.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("1970-01-01", 5), Ok(0));
+        assert_eq!(parse_time("2000-03-01T12:34:56Z", 0), Ok(951914096));
+        assert_eq!(format_time(951914096), "2000-03-01T12:34:56Z");
+        assert_eq!(parse_time("2024-02-29", 0).map(format_time), Ok("2024-02-29T00:00:00Z".into()));
+        assert_eq!(parse_time("30d", 1000), Ok(1000 + 30 * DAY));
+        assert_eq!(parse_time("2w", 0), Ok(14 * DAY));
+        for bad in ["", "d", "-1d", "30x", "2023-02-29", "2023-13-01", "2023-01-01T24:00:00Z", "2023-01-01T00:00:00"] {
+            assert_eq!(parse_time(bad, 0), Err(Error::BadTime(bad.into())), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn validity() {
+        let attrs = SignedAttributes { timestamp: Some(100), expires: Some(200), ..Default::default() };
+        assert_eq!(attrs.check_consistent(), Ok(()));
+        assert_eq!(attrs.check_valid_at(100), Ok(()));
+        assert_eq!(attrs.check_valid_at(199), Ok(()));
+        assert_eq!(attrs.check_valid_at(200), Err(Error::Expired { expires: 200, at: 200 }));
+        assert_eq!(attrs.check_valid_at(99), Err(Error::NotYetSigned { timestamp: 100, at: 99 }));
+        let attrs = SignedAttributes { timestamp: Some(200), expires: Some(200), ..Default::default() };
+        assert_eq!(attrs.check_consistent(), Err(Error::ExpiresBeforeSigning));
+        assert_eq!(SignedAttributes::default().check_valid_at(0), Ok(()));
+    }
+
+    #[test]
+    fn commitment() {
+        let none = SignedAttributes::default();
+        assert!(none.is_empty());
+        assert_eq!(none.signed_message(b"hello"), b"hello");
+
+        let stamped = SignedAttributes { timestamp: Some(1), ..Default::default() };
+        let expiring = SignedAttributes { expires: Some(1), ..Default::default() };
+        assert_eq!(stamped.signed_message(b"hello").len(), 32);
+        assert_ne!(stamped.signed_message(b"hello"), expiring.signed_message(b"hello"));
+        assert_ne!(stamped.signed_message(b"hello"), stamped.signed_message(b"hello!"));
+
+        let json = serde_json::to_string(&stamped).unwrap();
+        assert_eq!(json, r#"{"timestamp":1}"#);
+        assert_eq!(serde_json::from_str::<SignedAttributes>(&json).unwrap(), stamped);
+
+        let reply = SignedAttributes { reply_to: Some(BundleHash::hash(b"parent")), ..Default::default() };
+        assert!(!reply.is_empty());
+        assert_ne!(reply.signed_message(b"hello"), b"hello");
+        let other = SignedAttributes { reply_to: Some(BundleHash::hash(b"other")), ..Default::default() };
+        assert_ne!(reply.signed_message(b"hello"), other.signed_message(b"hello"));
+        let json = serde_json::to_string(&reply).unwrap();
+        assert_eq!(json, format!(r#"{{"replyTo":"{}"}}"#, BundleHash::hash(b"parent")));
+        assert_eq!(serde_json::from_str::<SignedAttributes>(&json).unwrap(), reply);
+        assert!(serde_json::from_str::<SignedAttributes>(r#"{"replyTo":"00"}"#).is_err());
+    }
+}
@@ -0,0 +1,330 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Verification badges
+//!
+//! A badge is a small SVG image, in the style of the status badges shown on
+//! project pages, saying whether a confession or bundle verified, e.g.
+//! `verified: 1 of 6 keys · ring 3f2a9c1b`. The ring is identified by the
+//! start of its [`crate::ring_id`].
+//!
+//! A badge is always rendered, even for a bundle which does not parse: a
+//! failure is shown in red, striped, with the word `FAILED`, so that a badge
+//! regenerated after a failed check shows the failure rather than going
+//! stale. The SVG is written out by hand and has no external references.
+
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use serde_json::json;
+
+use crate::armor::FromArmor;
+use crate::bundle::{Confession, MultiBundle, MULTI_VERSION};
+use crate::hashes::RingHash;
+use crate::keys::PublicKey;
+use crate::sshsig::{self, Signature};
+use crate::Proof;
+
+/// The label shown when none is given
+pub const DEFAULT_LABEL: &str = "confession";
+/// Number of hex characters of the ring id shown
+const SHORT_RING_ID_LEN: usize = 8;
+
+/// Approximate width of a character of 11px Verdana, in pixels
+const CHAR_WIDTH: usize = 7;
+/// Horizontal padding around each half of the badge, in pixels
+const PADDING: usize = 10;
+
+const PASS_COLOR: &str = "#4c1";
+const FAIL_COLOR: &str = "#e05d44";
+
+/// A ring which proofs were checked against
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ring {
+    /// Its id, or `None` if some of its keys did not parse
+    pub id: Option<RingHash>,
+    /// The number of distinct keys, or of lines if some did not parse
+    pub size: usize,
+}
+
+/// The outcome of checking a confession or bundle, ready to render
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Badge {
+    pub label: String,
+    /// The number of proofs checked
+    pub proofs: usize,
+    /// The number of those which verified
+    pub passed: usize,
+    /// The distinct rings, in the order they first appear
+    pub rings: Vec<Ring>,
+    /// The first problem found, if any
+    pub error: Option<String>,
+}
+
+impl Badge {
+    /// Verify a version 1 confession or version 2 bundle, checking any
+    /// signed expiry at the time `at`
+    ///
+    /// This never fails; problems, including JSON which does not parse, are
+    /// recorded in the badge.
+    pub fn check(json: &str, at: u64, label: Option<&str>) -> Badge {
+        let mut ret = Badge {
+            label: label.unwrap_or(DEFAULT_LABEL).to_owned(),
+            proofs: 0,
+            passed: 0,
+            rings: vec![],
+            error: None,
+        };
+        let value: serde_json::Value = match serde_json::from_str(json) {
+            Ok(value) => value,
+            Err(e) => {
+                ret.error = Some(format!("parsing JSON: {}", e));
+                return ret;
+            }
+        };
+        if value.get("version") == Some(&json!(MULTI_VERSION)) {
+            match MultiBundle::from_json_value(value) {
+                Ok(bundle) => {
+                    for verdict in bundle.verify() {
+                        let lines = verdict.public_keys.unwrap_or_default();
+                        ret.record(&lines, verdict.result.map_err(|e| e.to_string()));
+                    }
+                }
+                Err(e) => ret.error = Some(e.to_string()),
+            }
+        } else {
+            match Confession::from_json_value(value) {
+                Ok(confession) => ret.record(&confession.public_keys, verify_confession(&confession, at)),
+                Err(e) => ret.error = Some(e.to_string()),
+            }
+        }
+        ret
+    }
+
+    /// Record the outcome of checking one proof against the ring `lines`
+    fn record(&mut self, lines: &[String], result: Result<(), String>) {
+        let keys: Result<Vec<_>, _> = lines.iter().map(|line| PublicKey::parse_pk_line(line)).collect();
+        let ring = match keys {
+            Ok(mut keys) => {
+                keys.sort_by_key(PublicKey::serialize);
+                keys.dedup();
+                Ring { id: Some(crate::ring_id(&keys)), size: keys.len() }
+            }
+            Err(_) => Ring { id: None, size: lines.len() },
+        };
+        if !self.rings.contains(&ring) {
+            self.rings.push(ring);
+        }
+        self.proofs += 1;
+        match result {
+            Ok(()) => self.passed += 1,
+            Err(e) => {
+                self.error.get_or_insert(e);
+            }
+        }
+    }
+
+    /// Whether there was at least one proof, and everything verified
+    pub fn verified(&self) -> bool {
+        self.error.is_none() && self.proofs > 0 && self.passed == self.proofs
+    }
+
+    /// The text on the right of the badge
+    pub fn text(&self) -> String {
+        let mut ret = if self.verified() { "verified".to_owned() } else { "FAILED".to_owned() };
+        let keys = |size: usize| format!("{} key{}", size, if size == 1 { "" } else { "s" });
+        match (self.proofs, &self.rings[..]) {
+            (0, _) => return ret,
+            (1, [ring]) if self.verified() => ret += &format!(": 1 of {}", keys(ring.size)),
+            (1, [ring]) => ret += &format!(": ring of {}", keys(ring.size)),
+            (proofs, [ring]) if self.verified() => ret += &format!(": {} proofs, {}", proofs, keys(ring.size)),
+            (proofs, rings) if self.verified() => ret += &format!(": {} proofs, {} rings", proofs, rings.len()),
+            (proofs, _) => ret += &format!(": {} of {} proofs", proofs - self.passed, proofs),
+        }
+        if let [Ring { id: Some(id), .. }] = self.rings[..] {
+            ret += &format!(" · ring {}", &id.to_hex()[..SHORT_RING_ID_LEN]);
+        }
+        ret
+    }
+
+    /// Render the badge
+    pub fn svg(&self) -> String {
+        let label = escape_xml(&self.label);
+        let text = escape_xml(&self.text());
+        let label_width = width(&self.label);
+        let text_width = width(&self.text());
+        let total = label_width + text_width;
+        let (color, stripes) = if self.verified() {
+            (PASS_COLOR, String::new())
+        } else {
+            let stripes = format!(r##"<rect x="{}" width="{}" height="20" fill="url(#f)"/>"##, label_width, text_width);
+            (FAIL_COLOR, format!("\n    {}", stripes))
+        };
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{total}" height="20" role="img" aria-label="{label}: {text}">
+  <title>{label}: {text}</title>
+  <linearGradient id="s" x2="0" y2="100%">
+    <stop offset="0" stop-color="#bbb" stop-opacity=".1"/>
+    <stop offset="1" stop-opacity=".1"/>
+  </linearGradient>
+  <pattern id="f" width="6" height="6" patternUnits="userSpaceOnUse" patternTransform="rotate(45)">
+    <rect width="3" height="6" fill="#000" fill-opacity=".15"/>
+  </pattern>
+  <clipPath id="r">
+    <rect width="{total}" height="20" rx="3" fill="#fff"/>
+  </clipPath>
+  <g clip-path="url(#r)">
+    <rect width="{label_width}" height="20" fill="#555"/>
+    <rect x="{label_width}" width="{text_width}" height="20" fill="{color}"/>{stripes}
+    <rect width="{total}" height="20" fill="url(#s)"/>
+  </g>
+  <g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">
+    <text x="{label_x}" y="14">{label}</text>
+    <text x="{text_x}" y="14">{text}</text>
+  </g>
+</svg>
+"##,
+            label_x = label_width / 2,
+            text_x = label_width + text_width / 2,
+        )
+    }
+
+    /// The badge as a JSON object, without the SVG
+    pub fn to_json(&self) -> serde_json::Value {
+        let rings: Vec<_> = self
+            .rings
+            .iter()
+            .map(|ring| json!({ "id": ring.id.map(|id| id.to_hex()), "size": ring.size }))
+            .collect();
+        json!({
+            "verified": self.verified(),
+            "label": self.label,
+            "text": self.text(),
+            "proofs": self.proofs,
+            "passed": self.passed,
+            "rings": rings,
+            "error": self.error,
+        })
+    }
+}
+
+/// Verify a version 1 confession, including its signed attributes or an
+/// OpenSSH signature in place of a proof
+pub(crate) fn verify_confession(confession: &Confession, at: u64) -> Result<(), String> {
+    let keys = confession
+        .public_keys
+        .iter()
+        .map(|line| PublicKey::parse_pk_line(line).map_err(|e| format!("parsing key {}: {}", line, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let message = confession.message.as_bytes();
+    if let Some(ref armor) = confession.sshsig {
+        let sig = Signature::from_armor(armor).map_err(|e| format!("parsing sshsig: {}", e))?;
+        if keys != [sig.public_key] {
+            return Err("the sshsig was not made by the ring's only key".into());
+        }
+        return Ok(sig.verify(sshsig::CONFESSION_NAMESPACE, message)?);
+    }
+
+    let attributes = confession.attributes.unwrap_or_default();
+    attributes.check_valid_at(at).map_err(|e| e.to_string())?;
+    let signed = attributes.signed_message(message);
+    let proof = Proof::from_hex(confession.proof.as_deref().ok_or("the confession has no proof")?)
+        .map_err(|e| e.to_string())?;
+    match (&confession.scope, &confession.key_image) {
+        (None, None) => crate::verify(&proof, &keys, &signed).map_err(|e| e.to_string()),
+        (Some(scope), Some(key_image)) => {
+            let key_image = <[u8; 32]>::from_hex(key_image).map_err(|_| "the key image is not valid hex")?;
+            crate::verify_linkable(&proof, &key_image, &keys, &signed, scope.as_bytes()).map_err(|e| e.to_string())
+        }
+        _ => Err("the confession has only one of scope and keyImage".into()),
+    }
+}
+
+/// The width of one half of the badge holding `text`
+fn width(text: &str) -> usize {
+    // Even, so that the text is centred on a whole pixel
+    let width = text.chars().count() * CHAR_WIDTH + 2 * PADDING;
+    width + width % 2
+}
+
+/// Escape text for use in XML element content or attribute values
+fn escape_xml(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => ret += "&amp;",
+            '<' => ret += "&lt;",
+            '>' => ret += "&gt;",
+            '"' => ret += "&quot;",
+            '\'' => ret += "&apos;",
+            ch => ret.push(ch),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// A time before the test fixtures expire
+    const AT: u64 = 1_700_000_000;
+
+    /// Compare `svg` with the snapshot in testdata/, or update the snapshot
+    /// if `RINGSIG_UPDATE_SNAPSHOTS` is set
+    fn check_snapshot(name: &str, svg: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata").join(name);
+        if std::env::var_os("RINGSIG_UPDATE_SNAPSHOTS").is_some() {
+            std::fs::write(&path, svg).unwrap();
+        }
+        assert_eq!(svg, std::fs::read_to_string(&path).unwrap(), "{} differs", name);
+    }
+
+    #[test]
+    fn pass_and_fail_snapshots() {
+        let json = include_str!("../testdata/test-verify.json");
+        let badge = Badge::check(json, AT, Some("release <v1.0> & co"));
+        assert!(badge.verified(), "{:?}", badge);
+        assert_eq!(badge.text(), "verified: 1 of 3 keys · ring 099be536");
+        check_snapshot("badge-pass.svg", &badge.svg());
+
+        let tampered = json.replace("this is an example text", "this is another text");
+        let badge = Badge::check(&tampered, AT, Some("release <v1.0> & co"));
+        assert!(!badge.verified());
+        assert_eq!(badge.error.as_deref(), Some("bad proof"));
+        assert_eq!(badge.text(), "FAILED: ring of 3 keys · ring 099be536");
+        check_snapshot("badge-fail.svg", &badge.svg());
+    }
+
+    #[test]
+    fn bundles() {
+        let badge = Badge::check(include_str!("../testdata/timestamp-bundle.json"), AT, None);
+        assert!(badge.verified(), "{:?}", badge);
+        assert_eq!(badge.label, DEFAULT_LABEL);
+        assert_eq!(badge.rings.len(), 1);
+        assert_eq!(badge.to_json()["rings"][0]["size"], 2);
+
+        let badge = Badge::check("{", AT, None);
+        assert!(!badge.verified());
+        assert_eq!(badge.text(), "FAILED");
+        assert!(badge.svg().contains(FAIL_COLOR));
+        assert!(badge.error.unwrap().starts_with("parsing JSON"));
+
+        let bad_key = r#"{"version": 2, "message": "m", "publicKeys": ["nope"], "proofs": [{"proof": "00"}, {"proof": "00"}]}"#;
+        let badge = Badge::check(bad_key, AT, None);
+        assert_eq!(badge.rings, [Ring { id: None, size: 1 }]);
+        assert_eq!(badge.text(), "FAILED: 2 of 2 proofs");
+    }
+}
@@ -0,0 +1,283 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! `freeze-vectors`: writing the frozen artifacts in testdata/compat/
+//!
+//! The compatibility tests in tests/compat.rs check that confessions and
+//! bundles made by earlier releases still verify. They read artifacts which
+//! were made once and checked in; proofs use fresh randomness, so making
+//! them again would give different bytes, and the point is that the old
+//! bytes keep verifying.
+//!
+//! This writes each artifact in [`VECTORS`] which does not exist yet, and
+//! never touches existing ones. When a new format or option ships, add an
+//! entry for it here, run `cargo run --bin freeze-vectors`, and add the new
+//! file and its hash to tests/compat.rs, which prints the hash if it is
+//! missing.
+//!
+//! The keys are derived from fixed seeds, so the rings are the same every
+//! time; nobody should ever use them for anything else.
+
+use bitcoin_hashes::hex::ToHex;
+use bitcoin_hashes::{sha256, Hash};
+use ringsig::attributes::SignedAttributes;
+use ringsig::bundle::{Confession, Entry, MultiBundle, SkippedKey, MULTI_VERSION};
+use ringsig::format::{self, Clearsigned};
+use ringsig::keys::{ExpandedSecretKey, PublicKey};
+use ringsig::radix64::base64_encode;
+use ringsig::revocation;
+use ringsig::sshsig::{self, CONFESSION_NAMESPACE};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+/// Makes the contents of an artifact
+type Make = fn() -> String;
+
+/// Every artifact, by file name, with the function which makes it
+const VECTORS: &[(&str, Make)] = &[
+    ("v1-ring-1.json", || ring_of(1, 0)),
+    ("v1-ring-2.json", || ring_of(2, 1)),
+    ("v1-ring-3.json", || ring_of(3, 1)),
+    ("v1-ring-16.json", || ring_of(16, 11)),
+    ("v1-ring-64.json", || ring_of(64, 40)),
+    ("v1-empty-message.json", || confession(&ring(3), "", 2)),
+    ("v1-message-oddities.json", || confession(&ring(3), ODD_MESSAGE, 0)),
+    ("v1-key-oddities.json", key_oddities),
+    ("v1-linkable.json", linkable),
+    ("v1-attributes.json", attributes),
+    ("v1-skipped-keys.json", skipped_keys),
+    ("v1-sshsig.json", sshsig),
+    ("v1-revocation.json", revocation),
+    ("v1-supersession.json", supersession),
+    ("v2-bundle.json", bundle),
+    ("v2-bundle-mixed.json", bundle_mixed),
+    ("clearsign.txt", clearsign),
+    ("armored-bundle.txt", armored_bundle),
+];
+
+/// A message with line endings, unicode, tabs and lines starting with `-`
+const ODD_MESSAGE: &str = "first line\r\nsecond line\n\n\tindented ünïcödé ✓\n- dash\n-----END OF MESSAGE-----\n";
+/// A signing time, 2023-11-14T22:13:20Z
+const TIMESTAMP: u64 = 1_700_000_000;
+/// An expiry far enough away that the artifact stays valid, 2100-01-01
+const EXPIRES: u64 = 4_102_444_800;
+
+/// The `n`th key
+fn secret(n: usize) -> ExpandedSecretKey {
+    let seed = sha256::Hash::hash(format!("ringsig compatibility vector key {}", n).as_bytes());
+    ExpandedSecretKey::from_seed(&seed.into_inner())
+}
+
+fn public(n: usize) -> PublicKey {
+    secret(n).secret_key().to_public()
+}
+
+/// The base64 part of the ssh public key line of the `n`th key
+fn key_base64(n: usize) -> String {
+    let mut blob = vec![];
+    blob.extend_from_slice(&11u32.to_be_bytes());
+    blob.extend_from_slice(b"ssh-ed25519");
+    blob.extend_from_slice(&32u32.to_be_bytes());
+    blob.extend_from_slice(&public(n).serialize());
+    base64_encode(&blob).replace('\n', "")
+}
+
+/// The first `size` keys, as ssh public key lines
+fn ring(size: usize) -> Vec<String> {
+    (0..size).map(|n| format!("ssh-ed25519 {} key-{}@compat", key_base64(n), n)).collect()
+}
+
+fn parse(lines: &[String]) -> Vec<PublicKey> {
+    lines.iter().map(|line| PublicKey::parse_pk_line(line).expect("valid key line")).collect()
+}
+
+/// A proof of `message` over `lines` by the key `signer`
+fn prove(lines: &[String], message: &str, signer: usize) -> String {
+    ringsig::prove(&parse(lines), message.as_bytes(), &secret(signer).secret_key()).expect("proving").to_hex()
+}
+
+fn confession(lines: &[String], message: &str, signer: usize) -> String {
+    let mut ret = Confession::new(lines.to_vec(), message.to_owned());
+    ret.proof = Some(prove(lines, message, signer));
+    ret.to_json_string()
+}
+
+fn ring_of(size: usize, signer: usize) -> String {
+    confession(&ring(size), &format!("a confession over a ring of {}", size), signer)
+}
+
+/// Key lines with no comment, tabs, runs of spaces, trailing and leading
+/// whitespace, unicode, and a comment which looks like another key
+fn key_oddities() -> String {
+    let lines = vec![
+        format!("ssh-ed25519 {}", key_base64(0)),
+        format!("ssh-ed25519\t{}\tcomment\twith tabs", key_base64(1)),
+        format!("ssh-ed25519   {}   spaced   out   ", key_base64(2)),
+        format!("  ssh-ed25519 {} leading whitespace", key_base64(3)),
+        format!("ssh-ed25519 {} ünïcödé ✓ comment", key_base64(4)),
+        format!("ssh-ed25519 {} ssh-ed25519 {}", key_base64(5), key_base64(6)),
+    ];
+    confession(&lines, "keys with odd comments and whitespace", 3)
+}
+
+fn linkable() -> String {
+    let lines = ring(4);
+    let (message, scope) = ("a linkable confession", "compat scope");
+    let (proof, key_image) =
+        ringsig::prove_linkable(&parse(&lines), message.as_bytes(), scope.as_bytes(), &secret(2).secret_key())
+            .expect("proving");
+    let mut ret = Confession::new(lines, message.to_owned());
+    ret.proof = Some(proof.to_hex());
+    ret.scope = Some(scope.to_owned());
+    ret.key_image = Some(key_image.to_hex());
+    ret.to_json_string()
+}
+
+fn attributes() -> String {
+    let lines = ring(3);
+    let message = "a confession with a signing time and an expiry";
+    let attributes = SignedAttributes { timestamp: Some(TIMESTAMP), expires: Some(EXPIRES), ..Default::default() };
+    let proof = ringsig::prove(&parse(&lines), &attributes.signed_message(message.as_bytes()), &secret(0).secret_key());
+    let mut ret = Confession::new(lines, message.to_owned());
+    ret.proof = Some(proof.expect("proving").to_hex());
+    ret.attributes = Some(attributes);
+    ret.to_json_string()
+}
+
+fn skipped_keys() -> String {
+    let lines = ring(2);
+    let mut ret = Confession::new(lines.clone(), "a confession which left a key out".to_owned());
+    ret.proof = Some(prove(&lines, &ret.message, 0));
+    ret.skipped_keys = Some(vec![SkippedKey {
+        source: "team.keys".into(),
+        line: 2,
+        key: "ssh-rsa AAAAB3NzaC1yc2E= rsa@compat".into(),
+        reason: "not an ed25519 key (ssh-rsa)".into(),
+    }]);
+    ret.to_json_string()
+}
+
+fn sshsig() -> String {
+    let mut ret = Confession::new(ring(1), "a signature by the only key in the ring".to_owned());
+    ret.sshsig = Some(sshsig::sign_sshsig(&secret(0), CONFESSION_NAMESPACE, ret.message.as_bytes()));
+    ret.to_json_string()
+}
+
+/// A revocation of `v1-sshsig.json`, which is deterministic and so can be
+/// made again here to find its hash
+fn revocation() -> String {
+    let original = Confession::from_json_str(&sshsig()).expect("valid confession");
+    let ret = revocation::revoke(&original, &ring(1), &secret(0).secret_key()).expect("revoking");
+    ret.to_json_string()
+}
+
+/// A supersession of `v1-sshsig.json` by another OpenSSH signature
+fn supersession() -> String {
+    let original = Confession::from_json_str(&sshsig()).expect("valid confession");
+    let mut replacement = Confession::new(ring(1), "a corrected signature by the only key in the ring".to_owned());
+    replacement.sshsig = Some(sshsig::sign_sshsig(&secret(0), CONFESSION_NAMESPACE, replacement.message.as_bytes()));
+    let ret = revocation::supersede(&original, &replacement, &ring(1), &secret(0).secret_key()).expect("superseding");
+    ret.to_json_string()
+}
+
+/// Two statements by the same ring
+fn bundle() -> String {
+    let lines = ring(3);
+    let entry = |message: &str, context: &str| Entry {
+        message: Some(message.to_owned()),
+        proof: prove(&lines, message, 1),
+        context: Some(context.to_owned()),
+        ..Entry::default()
+    };
+    let entries = vec![entry("the first statement", "first"), entry("the second statement", "second")];
+    MultiBundle { version: MULTI_VERSION, message: None, public_keys: Some(lines.clone()), proofs: entries, timestamps: None }
+        .to_json_string()
+}
+
+/// Entries which use the default message, their own ring, and a linkable
+/// proof
+fn bundle_mixed() -> String {
+    let (lines, message) = (ring(2), "the default message");
+    let own_ring: Vec<String> = (2..7).map(|n| format!("ssh-ed25519 {} other-{}", key_base64(n), n)).collect();
+    let (proof, key_image) =
+        ringsig::prove_linkable(&parse(&lines), message.as_bytes(), b"bundle scope", &secret(1).secret_key())
+            .expect("proving");
+    let entries = vec![
+        Entry { proof: prove(&lines, message, 0), ..Entry::default() },
+        Entry {
+            message: Some(String::new()),
+            public_keys: Some(own_ring.clone()),
+            proof: prove(&own_ring, "", 4),
+            context: Some("its own ring and an empty message".into()),
+            ..Entry::default()
+        },
+        Entry {
+            proof: proof.to_hex(),
+            scope: Some("bundle scope".into()),
+            key_image: Some(key_image.to_hex()),
+            ..Entry::default()
+        },
+    ];
+    MultiBundle {
+        version: MULTI_VERSION,
+        message: Some(message.to_owned()),
+        public_keys: Some(lines),
+        proofs: entries,
+        timestamps: None,
+    }
+    .to_json_string()
+}
+
+fn clearsign() -> String {
+    let lines = ring(3);
+    let proof = ringsig::prove(&parse(&lines), ODD_MESSAGE.as_bytes(), &secret(1).secret_key()).expect("proving").into_bytes();
+    Clearsigned { message: ODD_MESSAGE.to_owned(), public_keys: lines, proof }.encode()
+}
+
+fn armored_bundle() -> String {
+    let json = confession(&ring(3), "a confession in an armored bundle", 2);
+    format::armor(format::BUNDLE_BEGIN, format::BUNDLE_END, json.as_bytes())
+}
+
+fn main() {
+    let dir = match env::args().nth(1) {
+        Some(dir) if dir.starts_with('-') => {
+            eprintln!("Usage: {} [directory (default testdata/compat)]", env::args().next().unwrap());
+            process::exit(2);
+        }
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/compat"),
+    };
+    if let Err(e) = fs::create_dir_all(&dir) {
+        eprintln!("error: creating {}: {}", dir.display(), e);
+        process::exit(1);
+    }
+    for (name, make) in VECTORS {
+        let path = dir.join(name);
+        if path.exists() {
+            continue;
+        }
+        let mut contents = make();
+        if !contents.ends_with('\n') {
+            contents.push('\n');
+        }
+        if let Err(e) = fs::write(&path, &contents) {
+            eprintln!("error: writing {}: {}", path.display(), e);
+            process::exit(1);
+        }
+        println!("wrote {} (sha256 {})", path.display(), sha256::Hash::hash(contents.as_bytes()));
+    }
+}
@@ -0,0 +1,54 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! `gen-vectors`: writing testdata/vectors/vectors.json
+//!
+//! Unlike the artifacts of `freeze-vectors`, the test vectors are made
+//! without randomness, so this writes the same file every time. It only
+//! needs running when the vectors themselves are changed in
+//! [`ringsig::vectors`]; a test fails if the file is not what they make.
+//! Give `-` to write them to standard output instead.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io, process};
+
+fn main() {
+    let contents = ringsig::vectors::generate().to_json_string();
+    let path = match env::args().nth(1) {
+        Some(arg) if arg == "-" => {
+            if let Err(e) = io::stdout().write_all(contents.as_bytes()) {
+                eprintln!("error: writing to stdout: {}", e);
+                process::exit(1);
+            }
+            return;
+        }
+        Some(arg) if arg.starts_with('-') => {
+            eprintln!(
+                "Usage: {} [file, or - for stdout (default testdata/vectors/vectors.json)]",
+                env::args().next().unwrap(),
+            );
+            process::exit(2);
+        }
+        Some(arg) => PathBuf::from(arg),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/vectors/vectors.json"),
+    };
+    if let Err(e) = fs::write(&path, &contents) {
+        eprintln!("error: writing {}: {}", path.display(), e);
+        process::exit(1);
+    }
+    println!("wrote {}", path.display());
+}
@@ -0,0 +1,121 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Minimal ssh-agent client
+//!
+//! An agent never reveals secret keys, and its ed25519 signatures cannot be
+//! turned into ring signatures, so all we ask it for is the list of public
+//! keys it holds. That tells us which key files the user is actively using.
+
+use ringsig::keys::PublicKey;
+use ringsig::radix64::base64_encode;
+
+/// Something which can list the public keys held by an agent
+pub trait Agent {
+    fn identities(&self) -> Result<Vec<PublicKey>, String>;
+}
+
+/// The agent named by `$SSH_AUTH_SOCK`
+pub struct SshAgent;
+
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+
+/// Parse an identities answer (without its length prefix), returning the
+/// ed25519 keys and skipping all others
+fn parse_identities(mut msg: &[u8]) -> Result<Vec<PublicKey>, String> {
+    fn take<'a>(msg: &mut &'a [u8], n: usize) -> Result<&'a [u8], String> {
+        if msg.len() < n {
+            return Err("truncated reply from agent".into());
+        }
+        let (ret, rest) = msg.split_at(n);
+        *msg = rest;
+        Ok(ret)
+    }
+    fn string<'a>(msg: &mut &'a [u8]) -> Result<&'a [u8], String> {
+        let len = u32::from_be_bytes(take(msg, 4)?.try_into().unwrap());
+        take(msg, len as usize)
+    }
+
+    if take(&mut msg, 1)? != [SSH_AGENT_IDENTITIES_ANSWER] {
+        return Err("unexpected reply from agent".into());
+    }
+    let count = u32::from_be_bytes(take(&mut msg, 4)?.try_into().unwrap());
+    let mut ret = vec![];
+    for _ in 0..count {
+        let blob = string(&mut msg)?;
+        string(&mut msg)?; // comment
+        let line = format!("ssh-ed25519 {}", base64_encode(blob).replace('\n', ""));
+        if let Ok(pk) = PublicKey::parse_pk_line(&line) {
+            ret.push(pk);
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(unix)]
+impl Agent for SshAgent {
+    fn identities(&self) -> Result<Vec<PublicKey>, String> {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+
+        let path = std::env::var_os("SSH_AUTH_SOCK").ok_or("SSH_AUTH_SOCK is not set")?;
+        let mut sock = UnixStream::connect(path).map_err(|e| e.to_string())?;
+        sock.write_all(&[0, 0, 0, 1, SSH_AGENTC_REQUEST_IDENTITIES]).map_err(|e| e.to_string())?;
+        let mut len = [0; 4];
+        sock.read_exact(&mut len).map_err(|e| e.to_string())?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len > 256 * 1024 {
+            return Err("reply from agent is too large".into());
+        }
+        let mut msg = vec![0; len];
+        sock.read_exact(&mut msg).map_err(|e| e.to_string())?;
+        parse_identities(&msg)
+    }
+}
+
+#[cfg(not(unix))]
+impl Agent for SshAgent {
+    fn identities(&self) -> Result<Vec<PublicKey>, String> {
+        Err("talking to an ssh agent is not supported on this platform".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identities_answer() {
+        let mut msg = vec![SSH_AGENT_IDENTITIES_ANSWER, 0, 0, 0, 2];
+        // An RSA key, which is skipped
+        msg.extend_from_slice(&[0, 0, 0, 11]);
+        msg.extend_from_slice(b"\0\0\0\x07ssh-rsa");
+        msg.extend_from_slice(&[0, 0, 0, 3]);
+        msg.extend_from_slice(b"rsa");
+        // An ed25519 key
+        let pk = PublicKey::parse_pk_line(include_str!("../../../testdata/test-key.pub")).unwrap();
+        msg.extend_from_slice(&[0, 0, 0, 51, 0, 0, 0, 11]);
+        msg.extend_from_slice(b"ssh-ed25519");
+        msg.extend_from_slice(&[0, 0, 0, 32]);
+        msg.extend_from_slice(&pk.serialize());
+        msg.extend_from_slice(&[0, 0, 0, 0]);
+
+        assert_eq!(parse_identities(&msg), Ok(vec![pk]));
+        assert!(parse_identities(&msg[..msg.len() - 1]).is_err());
+        assert!(parse_identities(&[5]).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Command-line parsing
+//!
+//! We deliberately avoid pulling in an argument-parsing framework. Every
+//! option has the form `--name value`, `--name=value` or, for switches,
+//! just `--name`. Anything else is a positional argument; a lone `-`
+//! (meaning standard input) is positional, and `--` ends option parsing.
+
+/// Whether an option takes a value
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Arity {
+    Switch,
+    Value,
+}
+
+/// Every option the tool understands
+const OPTIONS: &[(&str, Arity)] = &[
+    ("key", Arity::Value),
+    ("fingerprint", Arity::Value),
+    ("ring", Arity::Value),
+    ("github", Arity::Value),
+    ("keys-url", Arity::Value),
+    ("authorized-keys", Arity::Value),
+    ("dns-keys", Arity::Value),
+    ("doh-resolver", Arity::Value),
+    ("allow-http", Arity::Switch),
+    ("format", Arity::Value),
+    ("verbose", Arity::Switch),
+    ("normalize", Arity::Value),
+    ("min-ring", Arity::Value),
+    ("dry-run", Arity::Switch),
+    ("quiet", Arity::Switch),
+    ("to", Arity::Value),
+    ("message", Arity::Value),
+    ("message-file", Arity::Value),
+    ("out", Arity::Value),
+    ("force", Arity::Switch),
+    ("host-filter", Arity::Value),
+    ("include-hashed", Arity::Switch),
+    ("out-dir", Arity::Value),
+    ("jobs", Arity::Value),
+    ("sizes", Arity::Value),
+    ("iters", Arity::Value),
+    ("share", Arity::Value),
+    ("check-github", Arity::Value),
+    ("strict", Arity::Switch),
+    ("explain", Arity::Switch),
+    ("any", Arity::Switch),
+    ("linkable", Arity::Switch),
+    ("scope", Arity::Value),
+    ("first-match", Arity::Switch),
+    ("encrypt-to", Arity::Value),
+    ("decrypt-identity", Arity::Value),
+    ("no-agent", Arity::Switch),
+    ("agent-only", Arity::Switch),
+    ("no-prompt", Arity::Switch),
+    ("timestamp", Arity::Switch),
+    ("no-timestamp", Arity::Switch),
+    ("expires", Arity::Value),
+    ("at", Arity::Value),
+    ("sshsig-when-single", Arity::Switch),
+    ("armor", Arity::Switch),
+    ("versioned-proof", Arity::Switch),
+    ("tsa", Arity::Value),
+    ("check-timestamps", Arity::Switch),
+    ("repo-name", Arity::Value),
+    ("label", Arity::Value),
+    ("supersede", Arity::Value),
+    ("reply-to", Arity::Value),
+    ("revocations", Arity::Value),
+];
+
+/// Parsed command line
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Args {
+    /// Positional arguments, in order, including the subcommand
+    pub positional: Vec<String>,
+    /// Options, in the order they appeared
+    options: Vec<(&'static str, Option<String>)>,
+}
+
+impl Args {
+    /// Parse a command line, not including the program name
+    pub fn parse<I: IntoIterator<Item = String>>(iter: I) -> Result<Args, String> {
+        let mut ret = Args::default();
+        let mut iter = iter.into_iter();
+        while let Some(arg) = iter.next() {
+            if arg == "--" {
+                ret.positional.extend(iter);
+                break;
+            }
+            let stripped = match arg.strip_prefix("--") {
+                Some(stripped) => stripped,
+                None => {
+                    ret.positional.push(arg);
+                    continue;
+                }
+            };
+            let (name, inline_value) = match stripped.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (stripped, None),
+            };
+            let (name, arity) = OPTIONS
+                .iter()
+                .find(|(opt, _)| *opt == name)
+                .ok_or_else(|| format!("unknown option --{}", name))?;
+            let value = match (arity, inline_value) {
+                (Arity::Switch, None) => None,
+                (Arity::Switch, Some(_)) => return Err(format!("option --{} does not take a value", name)),
+                (Arity::Value, Some(value)) => Some(value),
+                (Arity::Value, None) => {
+                    Some(iter.next().ok_or_else(|| format!("option --{} requires a value", name))?)
+                }
+            };
+            ret.options.push((name, value));
+        }
+        Ok(ret)
+    }
+
+    /// The value of an option; if it was given several times, the last one wins
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.values(name).pop()
+    }
+
+    /// All values given for a repeatable option
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(opt, _)| *opt == name)
+            .filter_map(|(_, value)| value.as_deref())
+            .collect()
+    }
+
+    /// Whether a switch was given
+    pub fn switch(&self, name: &str) -> bool {
+        self.options.iter().any(|(opt, _)| *opt == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Args, String> {
+        Args::parse(s.split(' ').map(String::from))
+    }
+
+    #[test]
+    fn options_and_positionals() {
+        let args = parse("prove - --ring a.keys --ring=b.keys --format json sk").unwrap();
+        assert_eq!(args.positional, ["prove", "-", "sk"]);
+        assert_eq!(args.values("ring"), ["a.keys", "b.keys"]);
+        assert_eq!(args.value("format"), Some("json"));
+        assert_eq!(args.value("key"), None);
+
+        let args = parse("verify x --allow-http").unwrap();
+        assert!(args.switch("allow-http"));
+        assert!(!args.switch("format"));
+
+        let args = parse("verify -- --format").unwrap();
+        assert_eq!(args.positional, ["verify", "--format"]);
+    }
+
+    #[test]
+    fn bad_options() {
+        assert!(parse("prove --frobnicate").is_err());
+        assert!(parse("prove --format").is_err());
+        assert!(parse("prove --allow-http=yes").is_err());
+    }
+}
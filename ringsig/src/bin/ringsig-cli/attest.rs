@@ -0,0 +1,92 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Attesting to git commits
+//!
+//! `attest-commit` proves that a member of the ring approved a commit, and
+//! `verify-commit` checks such an attestation against the commit it should
+//! be for. See [`ringsig::git`] for the statement proven. This requires the
+//! `git` feature; without it both fail with an explanatory error.
+
+use ringsig::bundle::Confession;
+use ringsig::keys::SecretKey;
+use serde_json::json;
+use std::path::Path;
+
+use crate::config::Format;
+use crate::error::Error;
+
+/// Attest to the commit `rev` of the repository at `repo`, naming the
+/// repository `name` if given
+#[cfg(feature = "git")]
+pub fn attest(repo: &Path, rev: &str, name: Option<&str>, ring: &[String], sk: &SecretKey) -> Result<Confession, Error> {
+    let repository = ringsig::git::Repository::open(repo).map_err(|e| e.to_string())?;
+    let commit = repository.resolve_commit(rev).map_err(|e| e.to_string())?;
+    let name = match name {
+        Some(name) => name.to_owned(),
+        None => repository.name().map_err(|e| e.to_string())?,
+    };
+    Ok(ringsig::git::attest(&commit, &name, ring, sk).map_err(|e| e.to_string())?)
+}
+
+/// Check an attestation of `sha`, and that it names the repository `name`
+/// if given, returning the repository it names
+#[cfg(feature = "git")]
+pub fn verify(confession: &Confession, sha: &str, name: Option<&str>) -> Result<String, Error> {
+    let repository = ringsig::git::verify_commit_attestation(confession, sha).map_err(|e| e.to_string())?;
+    match name {
+        Some(name) if name != repository => {
+            Err(format!("the attestation is for the repository {:?}, not {:?}", repository, name).into())
+        }
+        _ => Ok(repository),
+    }
+}
+
+#[cfg(not(feature = "git"))]
+pub fn attest(_: &Path, _: &str, _: Option<&str>, _: &[String], _: &SecretKey) -> Result<Confession, Error> {
+    Err("this build does not support git attestations; rebuild with `--features git`".into())
+}
+
+#[cfg(not(feature = "git"))]
+pub fn verify(_: &Confession, _: &str, _: Option<&str>) -> Result<String, Error> {
+    Err("this build does not support git attestations; rebuild with `--features git`".into())
+}
+
+/// Render a successful verification
+pub fn report(format: Format, confession: &Confession, sha: &str, repository: &str) -> String {
+    let sha = sha.to_ascii_lowercase();
+    let key_image = confession.key_image.as_deref().unwrap_or_default();
+    match format {
+        Format::Text => format!(
+            "SUCCESSFULLY VERIFIED ATTESTATION of commit {} in {} by one of {} keys\n\
+             key image {} (the same for every attestation of this commit by the same key)\n",
+            sha,
+            repository,
+            confession.public_keys.len(),
+            key_image,
+        ),
+        Format::Json => {
+            let report = json!({
+                "verified": true,
+                "commit": sha,
+                "repository": repository,
+                "publicKeys": confession.public_keys,
+                "keyImage": key_image,
+            });
+            format!("{}\n", report)
+        }
+    }
+}
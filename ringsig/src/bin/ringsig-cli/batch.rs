@@ -0,0 +1,174 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Batch proving from a manifest
+//!
+//! A manifest is a JSONL file; each line is an object with an `id` and
+//! either a `message` or a `messageFile` (relative to the manifest). Each
+//! item produces `<id>.json` in the output directory. A bad item is
+//! reported and skipped without affecting the others.
+
+use ringsig::bundle::Confession;
+use ringsig::keys::SecretKey;
+use ringsig::Proof;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::config::Normalize;
+use crate::platform;
+use crate::ringcheck::Checked;
+
+#[derive(Deserialize)]
+struct ManifestLine {
+    id: String,
+    message: Option<String>,
+    #[serde(rename = "messageFile")]
+    message_file: Option<String>,
+}
+
+/// A manifest item which is ready to prove
+pub struct Item {
+    pub id: String,
+    pub message: String,
+}
+
+/// The outcome of one manifest item
+pub struct Outcome {
+    /// The item's id, or its line number if it had none
+    pub id: String,
+    pub result: Result<PathBuf, String>,
+}
+
+/// Whether an id can safely be used as a file name
+fn valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && !id.starts_with('.')
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Parse a manifest, returning the usable items and the failures
+pub fn parse_manifest(data: &str, base: &Path, normalize: Normalize) -> (Vec<Item>, Vec<Outcome>) {
+    let mut items = vec![];
+    let mut failures = vec![];
+    let mut seen = HashSet::new();
+    for (n, line) in data.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let parsed: ManifestLine = match serde_json::from_str(line) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                failures.push(Outcome { id: format!("line {}", n + 1), result: Err(e.to_string()) });
+                continue;
+            }
+        };
+        let mut fail = |e: String| failures.push(Outcome { id: parsed.id.clone(), result: Err(e) });
+        if !valid_id(&parsed.id) {
+            fail("ids may only contain letters, digits, `-`, `_` and `.`, and may not start with `.`".into());
+            continue;
+        }
+        if !seen.insert(parsed.id.clone()) {
+            fail("duplicate id".into());
+            continue;
+        }
+        let message = match (&parsed.message, &parsed.message_file) {
+            (Some(message), None) => message.clone(),
+            (None, Some(file)) => match fs::read_to_string(base.join(file)) {
+                Ok(message) => message,
+                Err(e) => {
+                    fail(format!("reading {}: {}", file, e));
+                    continue;
+                }
+            },
+            _ => {
+                fail("exactly one of `message` and `messageFile` must be given".into());
+                continue;
+            }
+        };
+        items.push(Item { id: parsed.id, message: normalize.apply(&message) });
+    }
+    (items, failures)
+}
+
+/// Prove every item, using up to `jobs` threads where there are threads,
+/// and write the results to `out_dir`
+pub fn prove_items(
+    items: &[Item],
+    ring: &Checked,
+    sk: &SecretKey,
+    out_dir: &Path,
+    jobs: usize,
+) -> Vec<Outcome> {
+    let jobs = if platform::HAS_THREADS { jobs.max(1) } else { 1 };
+    let chunk_size = items.len().div_ceil(jobs).max(1);
+    let prove_chunk = |chunk: &[Item]| {
+        let messages: Vec<&str> = chunk.iter().map(|item| &item.message[..]).collect();
+        ringsig::prove_many(&ring.keys, &messages, sk)
+    };
+    let proofs: Vec<Result<Vec<Proof>, ringsig::ProveError>> = if jobs == 1 {
+        items.chunks(chunk_size).map(prove_chunk).collect()
+    } else {
+        thread::scope(|scope| {
+            let handles: Vec<_> =
+                items.chunks(chunk_size).map(|chunk| scope.spawn(move || prove_chunk(chunk))).collect();
+            handles.into_iter().map(|h| h.join().expect("proving thread panicked")).collect()
+        })
+    };
+
+    let mut ret = vec![];
+    for (chunk, proofs) in items.chunks(chunk_size).zip(proofs) {
+        for (n, item) in chunk.iter().enumerate() {
+            let result = proofs.as_ref().map_err(|e| e.to_string()).and_then(|proofs| {
+                let contents = Confession {
+                    proof: Some(proofs[n].to_hex()),
+                    skipped_keys: Some(ring.skipped.clone()).filter(|skipped| !skipped.is_empty()),
+                    ..Confession::new(ring.lines.clone(), item.message.clone())
+                };
+                let path = out_dir.join(format!("{}.json", item.id));
+                let json = serde_json::to_value(&contents).expect("serializing JSON").to_string();
+                fs::write(&path, json + "\n").map_err(|e| format!("writing {}: {}", path.display(), e))?;
+                Ok(path)
+            });
+            ret.push(Outcome { id: item.id.clone(), result });
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_errors() {
+        let manifest = "\
+            {\"id\": \"a\", \"message\": \"hello\\r\\n\"}\n\
+            \n\
+            {\"id\": \"../b\", \"message\": \"x\"}\n\
+            {\"id\": \"a\", \"message\": \"again\"}\n\
+            {\"id\": \"c\"}\n\
+            {\"id\": \"d\", \"messageFile\": \"does-not-exist\"}\n\
+            not json\n";
+        let (items, failures) = parse_manifest(manifest, Path::new("."), Normalize::Lf);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].message, "hello\n");
+        let ids: Vec<_> = failures.iter().map(|f| &f.id[..]).collect();
+        assert_eq!(ids, ["../b", "a", "c", "d", "line 7"]);
+    }
+}
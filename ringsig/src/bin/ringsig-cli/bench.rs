@@ -0,0 +1,178 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! The `bench` subcommand
+//!
+//! Times proving and verifying over synthetic rings of several sizes, so
+//! users can see how large a ring is practical on their own hardware. Each
+//! size is timed both cold, from a slice of keys as `prove` and `verify` do,
+//! and with a precomputed [`Ring`](ringsig::Ring), whose one-off setup is
+//! timed separately. The keys come from [`ringsig::testkit`], so this needs
+//! the `testkit` feature.
+
+// Without it, only the rendering is left
+#![cfg_attr(not(feature = "testkit"), allow(dead_code))]
+
+#[cfg(feature = "testkit")]
+use ringsig::{testkit, Ring};
+use serde_json::json;
+use std::time::Duration;
+#[cfg(feature = "testkit")]
+use std::time::Instant;
+
+use crate::config::Format;
+
+/// Ring sizes to time when none are given
+pub const DEFAULT_SIZES: [usize; 3] = [10, 100, 1000];
+/// Iterations per size when not given
+pub const DEFAULT_ITERS: usize = 5;
+
+/// The message proven in every iteration
+const MESSAGE: &[u8] = b"benchmark message";
+/// The seed of the synthetic keys
+const SEED: &[u8] = b"ringsig bench";
+
+/// Median times for one ring size
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Timing {
+    pub size: usize,
+    pub prove: Duration,
+    pub verify: Duration,
+    pub ring_setup: Duration,
+    pub ring_prove: Duration,
+    pub ring_verify: Duration,
+}
+
+/// Parse a comma-separated list of ring sizes
+pub fn parse_sizes(s: &str) -> Result<Vec<usize>, String> {
+    s.split(',')
+        .map(|n| n.trim().parse().ok().filter(|&n| n > 0).ok_or_else(|| format!("ring sizes must be positive integers, got `{}`", n)))
+        .collect()
+}
+
+/// The median of some durations, which must not be empty
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort();
+    times[times.len() / 2]
+}
+
+/// Time `f`, which must succeed
+#[cfg(feature = "testkit")]
+fn time<T, E: std::fmt::Display, F: FnOnce() -> Result<T, E>>(f: F) -> Result<(T, Duration), String> {
+    let start = Instant::now();
+    let ret = f().map_err(|e| format!("benchmark failed: {}", e))?;
+    Ok((ret, start.elapsed()))
+}
+
+/// Time each size, taking the median of `iters` iterations
+#[cfg(feature = "testkit")]
+pub fn run(sizes: &[usize], iters: usize) -> Result<Vec<Timing>, String> {
+    let iters = iters.max(1);
+    let mut ret = vec![];
+    for &size in sizes {
+        let keypairs = testkit::deterministic_keypairs(SEED, size);
+        let keys: Vec<_> = keypairs.iter().map(|keypair| keypair.public).collect();
+        // Sign with a key from the middle, as a typical signer would be
+        let sk = keypairs[size / 2].secret.secret_key();
+
+        let mut times: [Vec<Duration>; 5] = Default::default();
+        for _ in 0..iters {
+            let (proof, t) = time(|| ringsig::prove(&keys, MESSAGE, &sk))?;
+            times[0].push(t);
+            times[1].push(time(|| ringsig::verify(&proof, &keys, MESSAGE))?.1);
+
+            let (ring, t) = time(|| Ring::new(&keys))?;
+            times[2].push(t);
+            let (proof, t) = time(|| ring.prove(MESSAGE, &sk))?;
+            times[3].push(t);
+            times[4].push(time(|| ring.verify(&proof, MESSAGE))?.1);
+        }
+        let [prove, verify, ring_setup, ring_prove, ring_verify] = times.map(median);
+        ret.push(Timing { size, prove, verify, ring_setup, ring_prove, ring_verify });
+    }
+    Ok(ret)
+}
+
+#[cfg(not(feature = "testkit"))]
+pub fn run(_: &[usize], _: usize) -> Result<Vec<Timing>, String> {
+    Err("this build does not support benchmarking; rebuild with `--features testkit`".into())
+}
+
+/// Milliseconds, for output
+fn ms(d: Duration) -> f64 {
+    d.as_secs_f64() * 1000.0
+}
+
+/// Render the timings as a table or JSON
+pub fn report(timings: &[Timing], iters: usize, format: Format) -> String {
+    match format {
+        Format::Text => {
+            let mut ret = format!("median of {} iteration{}, in milliseconds\n", iters, if iters == 1 { "" } else { "s" });
+            ret += &format!(
+                "{:>8} {:>10} {:>10} {:>12} {:>12} {:>12}\n",
+                "keys", "prove", "verify", "ring setup", "ring prove", "ring verify",
+            );
+            for t in timings {
+                ret += &format!(
+                    "{:>8} {:>10.3} {:>10.3} {:>12.3} {:>12.3} {:>12.3}\n",
+                    t.size,
+                    ms(t.prove),
+                    ms(t.verify),
+                    ms(t.ring_setup),
+                    ms(t.ring_prove),
+                    ms(t.ring_verify),
+                );
+            }
+            ret
+        }
+        Format::Json => {
+            let results: Vec<_> = timings
+                .iter()
+                .map(|t| {
+                    json!({
+                        "size": t.size,
+                        "proveMs": ms(t.prove),
+                        "verifyMs": ms(t.verify),
+                        "ringSetupMs": ms(t.ring_setup),
+                        "ringProveMs": ms(t.ring_prove),
+                        "ringVerifyMs": ms(t.ring_verify),
+                    })
+                })
+                .collect();
+            format!("{}\n", json!({ "iterations": iters, "results": results }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_sizes("10, 100,1000"), Ok(vec![10, 100, 1000]));
+        assert!(parse_sizes("10,0").is_err());
+        assert!(parse_sizes("10,").is_err());
+        assert!(parse_sizes("ten").is_err());
+    }
+
+    #[test]
+    fn medians() {
+        let ms = |n| Duration::from_millis(n);
+        assert_eq!(median(vec![ms(3), ms(1), ms(2)]), ms(2));
+        assert_eq!(median(vec![ms(5)]), ms(5));
+    }
+}
@@ -0,0 +1,481 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Configuration file
+//!
+//! Defaults for most command-line options may be set in a TOML file, by
+//! default `~/.config/ringsig/config.toml`, or whatever file is named by
+//! the `RINGSIG_CONFIG` environment variable. Config keys are named after
+//! the corresponding command-line options, e.g.
+//!
+//! ```toml
+//! key = "~/.ssh/id_ed25519"
+//! ring = ["~/team.keys"]
+//! format = "json"
+//! min-ring = 3
+//! ```
+//!
+//! Options given on the command line always take precedence over the
+//! config file. Relative paths in the config file are relative to the
+//! directory containing it.
+
+use crate::args::Args;
+use crate::net;
+use crate::platform;
+use serde_json::json;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::env;
+
+/// Where the effective value of a setting came from
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Source {
+    /// Built-in default
+    Default,
+    /// The config file
+    Config,
+    /// The command line
+    Flag,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Source::Default => "default",
+            Source::Config => "config",
+            Source::Flag => "flag",
+        })
+    }
+}
+
+/// A single setting along with its provenance
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn default(value: T) -> Self {
+        Setting { value, source: Source::Default }
+    }
+
+    fn set(&mut self, value: T, source: Source) {
+        self.value = value;
+        self.source = source;
+    }
+
+    /// Override the setting as though it had been given as a flag
+    pub fn set_flag(&mut self, value: T) {
+        self.set(value, Source::Flag);
+    }
+}
+
+/// Output format for human-facing commands
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+impl Format {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            other => Err(format!("unknown format `{}` (expected `text` or `json`)", other)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Format::Text => "text",
+            Format::Json => "json",
+        })
+    }
+}
+
+/// Normalization applied to the message before it is signed or verified
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Normalize {
+    /// Sign the message bytes exactly as given
+    None,
+    /// Convert CRLF line endings to LF
+    Lf,
+}
+
+impl Normalize {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "none" => Ok(Normalize::None),
+            "lf" => Ok(Normalize::Lf),
+            other => Err(format!("unknown normalization `{}` (expected `none` or `lf`)", other)),
+        }
+    }
+
+    /// Apply the normalization to a message
+    pub fn apply(&self, message: &str) -> String {
+        match *self {
+            Normalize::None => message.to_owned(),
+            Normalize::Lf => message.replace("\r\n", "\n"),
+        }
+    }
+}
+
+impl fmt::Display for Normalize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Normalize::None => "none",
+            Normalize::Lf => "lf",
+        })
+    }
+}
+
+/// The effective configuration, merged from defaults, config file and flags
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Config {
+    /// The config file which was read, if any
+    pub path: Option<PathBuf>,
+    /// Secret key file to sign with
+    pub key: Setting<Option<PathBuf>>,
+    /// Fingerprint selecting among auto-discovered secret keys
+    pub fingerprint: Setting<Option<String>>,
+    /// Files of public keys to add to the ring
+    pub ring: Setting<Vec<PathBuf>>,
+    /// GitHub users whose keys are added to the ring
+    pub github: Setting<Vec<String>>,
+    /// URLs of keys files to add to the ring
+    pub keys_url: Setting<Vec<String>>,
+    /// Email addresses and host names whose keys are looked up in DNS
+    pub dns_keys: Setting<Vec<String>>,
+    /// DNS-over-HTTPS resolver for the DNS lookups
+    pub doh_resolver: Setting<String>,
+    /// Output format
+    pub format: Setting<Format>,
+    /// Message normalization
+    pub normalize: Setting<Normalize>,
+    /// Smallest ring the tool will sign with or accept
+    pub min_ring: Setting<usize>,
+    /// Non-fatal problems encountered while reading the config file
+    pub warnings: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            path: None,
+            key: Setting::default(None),
+            fingerprint: Setting::default(None),
+            ring: Setting::default(vec![]),
+            github: Setting::default(vec![]),
+            keys_url: Setting::default(vec![]),
+            dns_keys: Setting::default(vec![]),
+            doh_resolver: Setting::default(net::DEFAULT_DOH_RESOLVER.to_owned()),
+            format: Setting::default(Format::Text),
+            normalize: Setting::default(Normalize::None),
+            min_ring: Setting::default(1),
+            warnings: vec![],
+        }
+    }
+}
+
+/// Expand a leading `~/` and resolve relative paths against `base`
+fn resolve_path(s: &str, base: Option<&Path>) -> PathBuf {
+    if let Some(path) = platform::expand_tilde(s, platform::home_dir().as_deref()) {
+        return path;
+    }
+    match base {
+        Some(base) => base.join(s),
+        None => PathBuf::from(s),
+    }
+}
+
+fn parse_min_ring(s: &str) -> Result<usize, String> {
+    s.parse().map_err(|_| format!("min-ring must be a non-negative integer, got `{}`", s))
+}
+
+impl Config {
+    /// The config file to read: `$RINGSIG_CONFIG` if set, else the default
+    /// location. The boolean indicates whether the file was explicitly
+    /// requested, in which case it is an error for it to be missing.
+    fn locate() -> Option<(PathBuf, bool)> {
+        if let Some(path) = env::var_os("RINGSIG_CONFIG") {
+            return Some((PathBuf::from(path), true));
+        }
+        let mut path = platform::home_dir()?;
+        path.push(".config");
+        path.push("ringsig");
+        path.push("config.toml");
+        Some((path, false))
+    }
+
+    /// Load the config file (if any) and apply command-line flags on top
+    pub fn load(args: &Args) -> Result<Config, String> {
+        let mut ret = Config::default();
+        if let Some((path, explicit)) = Config::locate() {
+            match platform::read_text(&path) {
+                Ok(s) => {
+                    ret.apply_toml(&s, path.parent())
+                        .map_err(|e| format!("config file {}: {}", path.display(), e))?;
+                    ret.path = Some(path);
+                }
+                Err(e) if explicit || e.kind() != std::io::ErrorKind::NotFound => {
+                    return Err(format!("reading config file {}: {}", path.display(), e));
+                }
+                Err(_) => {}
+            }
+        }
+        ret.apply_args(args)?;
+        Ok(ret)
+    }
+
+    /// Apply the contents of a config file
+    fn apply_toml(&mut self, s: &str, base: Option<&Path>) -> Result<(), String> {
+        let table = match s.parse::<toml::Value>().map_err(|e| e.to_string())? {
+            toml::Value::Table(table) => table,
+            _ => return Err("expected a table".into()),
+        };
+
+        fn string<'a>(key: &str, val: &'a toml::Value) -> Result<&'a str, String> {
+            val.as_str().ok_or_else(|| format!("`{}` must be a string", key))
+        }
+        fn strings<'a>(key: &str, val: &'a toml::Value) -> Result<Vec<&'a str>, String> {
+            match val {
+                toml::Value::String(s) => Ok(vec![s]),
+                toml::Value::Array(arr) => arr.iter().map(|v| string(key, v)).collect(),
+                _ => Err(format!("`{}` must be a string or a list of strings", key)),
+            }
+        }
+
+        for (key, val) in &table {
+            match &key[..] {
+                "key" => self.key.set(Some(resolve_path(string(key, val)?, base)), Source::Config),
+                "fingerprint" => self.fingerprint.set(Some(string(key, val)?.to_owned()), Source::Config),
+                "ring" => {
+                    let files = strings(key, val)?.into_iter().map(|s| resolve_path(s, base)).collect();
+                    self.ring.set(files, Source::Config);
+                }
+                "github" => {
+                    let users = strings(key, val)?.into_iter().map(String::from).collect();
+                    self.github.set(users, Source::Config);
+                }
+                "keys-url" => {
+                    let urls = strings(key, val)?.into_iter().map(String::from).collect();
+                    self.keys_url.set(urls, Source::Config);
+                }
+                "dns-keys" => {
+                    let names = strings(key, val)?.into_iter().map(String::from).collect();
+                    self.dns_keys.set(names, Source::Config);
+                }
+                "doh-resolver" => self.doh_resolver.set(string(key, val)?.to_owned(), Source::Config),
+                "format" => self.format.set(Format::parse(string(key, val)?)?, Source::Config),
+                "normalize" => self.normalize.set(Normalize::parse(string(key, val)?)?, Source::Config),
+                "min-ring" => {
+                    let n = val
+                        .as_integer()
+                        .and_then(|n| usize::try_from(n).ok())
+                        .ok_or_else(|| "`min-ring` must be a non-negative integer".to_owned())?;
+                    self.min_ring.set(n, Source::Config);
+                }
+                other => self.warnings.push(format!("ignoring unknown config key `{}`", other)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply command-line flags
+    fn apply_args(&mut self, args: &Args) -> Result<(), String> {
+        if let Some(key) = args.value("key") {
+            self.key.set(Some(PathBuf::from(key)), Source::Flag);
+        }
+        if let Some(fp) = args.value("fingerprint") {
+            self.fingerprint.set(Some(fp.to_owned()), Source::Flag);
+        }
+        let ring = args.values("ring");
+        if !ring.is_empty() {
+            self.ring.set(ring.into_iter().map(PathBuf::from).collect(), Source::Flag);
+        }
+        let github = args.values("github");
+        if !github.is_empty() {
+            // Usernames cannot contain commas, so `--github a,b` lists two
+            let users = github.into_iter().flat_map(|users| users.split(',')).map(str::trim).filter(|u| !u.is_empty());
+            self.github.set(users.map(String::from).collect(), Source::Flag);
+        }
+        let keys_url = args.values("keys-url");
+        if !keys_url.is_empty() {
+            self.keys_url.set(keys_url.into_iter().map(String::from).collect(), Source::Flag);
+        }
+        let dns_keys = args.values("dns-keys");
+        if !dns_keys.is_empty() {
+            // Neither email addresses nor host names can contain commas
+            let names = dns_keys.into_iter().flat_map(|names| names.split(',')).map(str::trim).filter(|n| !n.is_empty());
+            self.dns_keys.set(names.map(String::from).collect(), Source::Flag);
+        }
+        if let Some(resolver) = args.value("doh-resolver") {
+            self.doh_resolver.set(resolver.to_owned(), Source::Flag);
+        }
+        if let Some(format) = args.value("format") {
+            self.format.set(Format::parse(format)?, Source::Flag);
+        }
+        if let Some(normalize) = args.value("normalize") {
+            self.normalize.set(Normalize::parse(normalize)?, Source::Flag);
+        }
+        if let Some(min_ring) = args.value("min-ring") {
+            self.min_ring.set(parse_min_ring(min_ring)?, Source::Flag);
+        }
+        Ok(())
+    }
+
+    /// Render the effective configuration, with provenance, for `config show`
+    pub fn show(&self) -> String {
+        fn opt<T: fmt::Display>(x: &Option<T>) -> String {
+            match *x {
+                Some(ref x) => x.to_string(),
+                None => "-".to_owned(),
+            }
+        }
+        fn list<T: fmt::Display>(xs: &[T]) -> String {
+            if xs.is_empty() {
+                "-".to_owned()
+            } else {
+                xs.iter().map(T::to_string).collect::<Vec<_>>().join(", ")
+            }
+        }
+
+        match self.format.value {
+            Format::Json => {
+                fn entry<T: fmt::Display>(value: Option<T>, source: Source) -> serde_json::Value {
+                    json!({ "value": value.map(|v| v.to_string()), "source": source.to_string() })
+                }
+                let paths = |xs: &[PathBuf]| xs.iter().map(|p| p.display().to_string()).collect::<Vec<_>>();
+                json!({
+                    "configFile": self.path.as_ref().map(|p| p.display().to_string()),
+                    "key": entry(self.key.value.as_ref().map(|p| p.display()), self.key.source),
+                    "fingerprint": entry(self.fingerprint.value.as_ref(), self.fingerprint.source),
+                    "ring": { "value": paths(&self.ring.value), "source": self.ring.source.to_string() },
+                    "github": { "value": self.github.value, "source": self.github.source.to_string() },
+                    "keys-url": { "value": self.keys_url.value, "source": self.keys_url.source.to_string() },
+                    "dns-keys": { "value": self.dns_keys.value, "source": self.dns_keys.source.to_string() },
+                    "doh-resolver": entry(Some(&self.doh_resolver.value), self.doh_resolver.source),
+                    "format": entry(Some(self.format.value), self.format.source),
+                    "normalize": entry(Some(self.normalize.value), self.normalize.source),
+                    "min-ring": { "value": self.min_ring.value, "source": self.min_ring.source.to_string() },
+                })
+                .to_string()
+            }
+            Format::Text => {
+                let rows = [
+                    ("key", opt(&self.key.value.as_ref().map(|p| p.display())), self.key.source),
+                    ("fingerprint", opt(&self.fingerprint.value), self.fingerprint.source),
+                    (
+                        "ring",
+                        list(&self.ring.value.iter().map(|p| p.display()).collect::<Vec<_>>()),
+                        self.ring.source,
+                    ),
+                    ("github", list(&self.github.value), self.github.source),
+                    ("keys-url", list(&self.keys_url.value), self.keys_url.source),
+                    ("dns-keys", list(&self.dns_keys.value), self.dns_keys.source),
+                    ("doh-resolver", self.doh_resolver.value.clone(), self.doh_resolver.source),
+                    ("format", self.format.value.to_string(), self.format.source),
+                    ("normalize", self.normalize.value.to_string(), self.normalize.source),
+                    ("min-ring", self.min_ring.value.to_string(), self.min_ring.source),
+                ];
+                let mut ret = format!(
+                    "config file: {}\n",
+                    self.path.as_ref().map(|p| p.display().to_string()).unwrap_or_else(|| "(none)".into()),
+                );
+                for (name, value, source) in rows {
+                    ret += &format!("{:<12} {:<40} ({})\n", name, value, source);
+                }
+                ret
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Args {
+        Args::parse(s.split_whitespace().map(String::from)).unwrap()
+    }
+
+    fn config(toml: &str, cmdline: &str) -> Config {
+        let mut ret = Config::default();
+        ret.apply_toml(toml, Some(Path::new("/etc/ringsig"))).unwrap();
+        ret.apply_args(&args(cmdline)).unwrap();
+        ret
+    }
+
+    #[test]
+    fn precedence() {
+        let cfg = config("", "verify x");
+        assert_eq!(cfg.format, Setting { value: Format::Text, source: Source::Default });
+        assert_eq!(cfg.min_ring, Setting { value: 1, source: Source::Default });
+
+        let toml = "format = \"json\"\nmin-ring = 3\nring = [\"a.keys\", \"/b.keys\"]\nkey = \"id\"";
+        let cfg = config(toml, "verify x");
+        assert_eq!(cfg.format, Setting { value: Format::Json, source: Source::Config });
+        assert_eq!(cfg.min_ring, Setting { value: 3, source: Source::Config });
+        assert_eq!(cfg.ring.value, [PathBuf::from("/etc/ringsig/a.keys"), PathBuf::from("/b.keys")]);
+        assert_eq!(cfg.key.value, Some(PathBuf::from("/etc/ringsig/id")));
+
+        let cfg = config(toml, "verify x --format text --ring c.keys --min-ring 5");
+        assert_eq!(cfg.format, Setting { value: Format::Text, source: Source::Flag });
+        assert_eq!(cfg.min_ring, Setting { value: 5, source: Source::Flag });
+        assert_eq!(cfg.ring, Setting { value: vec![PathBuf::from("c.keys")], source: Source::Flag });
+        assert_eq!(cfg.key.source, Source::Config);
+    }
+
+    #[test]
+    fn unknown_keys_warn() {
+        let cfg = config("formatt = \"json\"\nnormalize = \"lf\"", "");
+        assert_eq!(cfg.warnings, ["ignoring unknown config key `formatt`"]);
+        assert_eq!(cfg.normalize.value, Normalize::Lf);
+
+        let mut cfg = Config::default();
+        assert!(cfg.apply_toml("format = \"yaml\"", None).is_err());
+        assert!(cfg.apply_toml("min-ring = -1", None).is_err());
+    }
+
+    #[test]
+    fn show() {
+        let cfg = config("github = \"apoelstra\"\nmin-ring = 2", "--format text --normalize lf");
+        assert_eq!(
+            cfg.show(),
+            "config file: (none)\n\
+             key          -                                        (default)\n\
+             fingerprint  -                                        (default)\n\
+             ring         -                                        (default)\n\
+             github       apoelstra                                (config)\n\
+             keys-url     -                                        (default)\n\
+             dns-keys     -                                        (default)\n\
+             doh-resolver https://cloudflare-dns.com/dns-query     (default)\n\
+             format       text                                     (flag)\n\
+             normalize    lf                                       (flag)\n\
+             min-ring     2                                        (config)\n",
+        );
+
+        let cfg = config("github = \"apoelstra\"", "--format json");
+        let json: serde_json::Value = serde_json::from_str(&cfg.show()).unwrap();
+        assert_eq!(json["github"], json!({ "value": ["apoelstra"], "source": "config" }));
+        assert_eq!(json["format"], json!({ "value": "json", "source": "flag" }));
+        assert_eq!(json["key"], json!({ "value": null, "source": "default" }));
+    }
+}
@@ -0,0 +1,156 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Conversion between proof formats
+//!
+//! The JSON, clearsigned and bundle formats all carry the message, ring and
+//! proof, and convert losslessly into one another. The hex and armor formats
+//! carry only the proof; converting them to a richer format needs the ring
+//! and message to be supplied separately. Only JSON and bundles have room
+//! for the key image of a linkable proof or a proof's signed attributes.
+
+use bitcoin_hashes::hex::ToHex;
+use ringsig::attributes::SignedAttributes;
+use ringsig::bundle::Confession;
+use ringsig::format::{self, Clearsigned, Kind};
+
+
+/// Parse the name of a format
+pub fn parse_kind(s: &str) -> Result<Kind, String> {
+    match s {
+        "hex" => Ok(Kind::Hex),
+        "armor" => Ok(Kind::Armor),
+        "json" => Ok(Kind::Json),
+        "clearsign" => Ok(Kind::Clearsign),
+        "bundle" => Ok(Kind::Bundle),
+        other => Err(format!(
+            "unknown format `{}` (expected hex, armor, json, clearsign or bundle)",
+            other,
+        )),
+    }
+}
+
+/// Everything which might be recovered from an input
+struct Parts {
+    message: Option<String>,
+    pks: Option<Vec<String>>,
+    proof: Vec<u8>,
+    /// The scope and key image of a linkable proof
+    link: Option<(String, String)>,
+    /// Any signed attributes, which the proof commits to
+    attributes: Option<SignedAttributes>,
+}
+
+fn parse_json(s: &str) -> Result<Parts, String> {
+    let contents = Confession::from_json_str(s).map_err(|e| e.to_string())?;
+    let proof = contents.proof.ok_or("the JSON has no proof to convert")?;
+    Ok(Parts {
+        message: Some(contents.message),
+        pks: Some(contents.public_keys),
+        proof: format::from_hex(&proof).map_err(|e| format!("parsing proof: {}", e))?,
+        link: match (contents.scope, contents.key_image) {
+            (Some(scope), Some(key_image)) => Some((scope, key_image)),
+            (None, None) => None,
+            _ => return Err("the JSON must have both or neither of scope and keyImage".into()),
+        },
+        attributes: contents.attributes,
+    })
+}
+
+fn parse(s: &str) -> Result<Parts, String> {
+    match format::classify(s) {
+        None => Err("could not recognize the input format".into()),
+        Some(Kind::Hex) => Ok(Parts {
+            message: None,
+            pks: None,
+            proof: format::from_hex(s).map_err(|e| format!("parsing hex: {}", e))?,
+            link: None,
+            attributes: None,
+        }),
+        Some(Kind::Armor) => Ok(Parts {
+            message: None,
+            pks: None,
+            proof: format::dearmor(s, format::PROOF_BEGIN, format::PROOF_END)
+                .map_err(|e| format!("parsing armor: {}", e))?,
+            link: None,
+            attributes: None,
+        }),
+        Some(Kind::Json) => parse_json(s),
+        Some(Kind::Clearsign) => {
+            let cs = Clearsigned::decode(s).map_err(|e| format!("parsing clearsigned message: {}", e))?;
+            Ok(Parts { message: Some(cs.message), pks: Some(cs.public_keys), proof: cs.proof, link: None, attributes: None })
+        }
+        Some(Kind::Bundle) => {
+            let data = format::dearmor(s, format::BUNDLE_BEGIN, format::BUNDLE_END)
+                .map_err(|e| format!("parsing bundle: {}", e))?;
+            let json = String::from_utf8(data).map_err(|_| "bundle does not contain UTF-8 JSON")?;
+            parse_json(&json)
+        }
+    }
+}
+
+/// Convert `input` to the format `to`
+///
+/// `pks` and `message` supply the ring and message when the input lacks
+/// them; it is an error to supply them when it does not.
+pub fn convert(input: &str, to: Kind, pks: Option<Vec<String>>, message: Option<String>) -> Result<String, String> {
+    let mut parts = parse(input)?;
+    match (&parts.pks, pks) {
+        (Some(_), Some(_)) => return Err("the input already contains a ring; do not pass --ring".into()),
+        (None, pks) => parts.pks = pks,
+        (Some(_), None) => {}
+    }
+    match (&parts.message, message) {
+        (Some(_), Some(_)) => return Err("the input already contains a message; do not pass --message-file".into()),
+        (None, message) => parts.message = message,
+        (Some(_), None) => {}
+    }
+
+    // Only the JSON formats have room for the key image and attributes
+    if parts.link.is_some() && !matches!(to, Kind::Json | Kind::Bundle) {
+        return Err("a linkable proof can only be converted to json or bundle".into());
+    }
+    if parts.attributes.is_some() && !matches!(to, Kind::Json | Kind::Bundle) {
+        return Err("a proof with signed attributes can only be converted to json or bundle".into());
+    }
+
+    let full = || -> Result<Confession, String> {
+        match (&parts.pks, &parts.message) {
+            (Some(pks), Some(message)) => Ok(Confession {
+                proof: Some(parts.proof.to_hex()),
+                scope: parts.link.as_ref().map(|link| link.0.clone()),
+                key_image: parts.link.as_ref().map(|link| link.1.clone()),
+                attributes: parts.attributes,
+                ..Confession::new(pks.clone(), message.clone())
+            }),
+            (None, _) => Err("the input has no ring; pass it with --ring".into()),
+            (_, None) => Err("the input has no message; pass it with --message-file".into()),
+        }
+    };
+    Ok(match to {
+        Kind::Hex => parts.proof.to_hex() + "\n",
+        Kind::Armor => format::armor(format::PROOF_BEGIN, format::PROOF_END, &parts.proof),
+        Kind::Json => full()?.to_json_string() + "\n",
+        Kind::Clearsign => {
+            let contents = full()?;
+            Clearsigned { message: contents.message, public_keys: contents.public_keys, proof: parts.proof }.encode()
+        }
+        Kind::Bundle => {
+            let json = full()?.to_json_string();
+            format::armor(format::BUNDLE_BEGIN, format::BUNDLE_END, json.as_bytes())
+        }
+    })
+}
@@ -0,0 +1,310 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Choosing a secret key to sign with
+//!
+//! When no key is given explicitly, every OpenSSH secret key in ~/.ssh whose
+//! public key is in the ring is a candidate. They are tried in three stages:
+//!
+//!   1. agent: unencrypted keys which the ssh agent also holds, since those
+//!      are the keys the user is actively using;
+//!   2. files: any other unencrypted keys;
+//!   3. prompt: encrypted keys, agent-held ones first, asking for each
+//!      passphrase in turn.
+//!
+//! `--no-agent` skips asking the agent, `--agent-only` considers only keys
+//! the agent holds, and `--no-prompt` skips encrypted keys. If nothing is
+//! found, the error lists what was tried at each stage.
+
+use ringsig::armor::{Error as ArmorError, FromArmor, OpensshKey};
+use ringsig::keys::{ExpandedSecretKey, PublicKey};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::agent::Agent;
+use crate::platform;
+use crate::report::comment;
+
+/// A secret key file whose public key is in the ring
+pub struct Candidate {
+    pub path: PathBuf,
+    pub key: OpensshKey,
+}
+
+/// Which stages to run
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Options {
+    pub no_agent: bool,
+    pub agent_only: bool,
+    pub no_prompt: bool,
+}
+
+/// Read every OpenSSH secret key in a directory, skipping anything else
+pub fn read_dir(dir: &Path) -> Result<Vec<(PathBuf, OpensshKey)>, String> {
+    let mut ret = vec![];
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("reading {}: {}", dir.display(), e))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .collect();
+    entries.sort();
+    for path in entries {
+        // Skip anything unreadable, e.g. directories or binary files
+        if let Some(key) = platform::read_text(&path).ok().and_then(|s| OpensshKey::from_armor(&s).ok()) {
+            ret.push((path, key));
+        }
+    }
+    Ok(ret)
+}
+
+/// If the candidates hold more than one distinct key, a message listing
+/// them, for the user to choose between
+///
+/// This only looks at public keys, so needs no passphrases.
+pub fn ambiguity(candidates: &[Candidate]) -> Option<String> {
+    let mut distinct: Vec<&Candidate> = vec![];
+    for c in candidates {
+        if !distinct.iter().any(|d| d.key.public_key() == c.key.public_key()) {
+            distinct.push(c);
+        }
+    }
+    if distinct.len() < 2 {
+        return None;
+    }
+    let mut ret = format!(
+        "{} different secret keys in ~/.ssh are in the ring; choose one with --fingerprint, \
+         or pass --first-match to use the first found:",
+        distinct.len(),
+    );
+    for c in distinct {
+        // The comment lives in the public key file alongside, if there is one
+        let mut pub_path = c.path.clone().into_os_string();
+        pub_path.push(".pub");
+        let comment = platform::read_text(Path::new(&pub_path))
+            .ok()
+            .and_then(|line| comment(line.trim()).map(String::from))
+            .unwrap_or_else(|| "(no comment)".into());
+        ret += &format!("\n  {}  {}  ({})", c.key.public_key().fingerprint(), comment, c.path.display());
+    }
+    Some(ret)
+}
+
+/// Decrypt a key, prompting for its passphrase if needed
+pub fn unlock<P>(path: &Path, key: &OpensshKey, no_prompt: bool, prompt: &mut P) -> Result<ExpandedSecretKey, String>
+where
+    P: FnMut(&Path) -> Result<String, String>,
+{
+    if !key.is_encrypted() {
+        return key.decrypt_expanded(b"").map_err(|e| format!("reading secret key file {}: {}", path.display(), e));
+    }
+    if no_prompt {
+        return Err(format!("secret key file {} is encrypted and --no-prompt was given", path.display()));
+    }
+    let passphrase = prompt(path)?;
+    match key.decrypt_expanded(passphrase.as_bytes()) {
+        Ok(sk) => Ok(sk),
+        Err(ArmorError::BadPassphrase) => Err(format!("wrong passphrase for {}", path.display())),
+        Err(e) => Err(format!("reading secret key file {}: {}", path.display(), e)),
+    }
+}
+
+/// Choose a key from the candidates, following the stages described in the
+/// module documentation. On failure, returns a summary of what was tried.
+pub fn select<P>(
+    candidates: Vec<Candidate>,
+    agent: &dyn Agent,
+    options: Options,
+    mut prompt: P,
+) -> Result<(ExpandedSecretKey, PathBuf), String>
+where
+    P: FnMut(&Path) -> Result<String, String>,
+{
+    let mut tried = vec![];
+    let held: Vec<PublicKey> = if options.no_agent {
+        tried.push("agent: not consulted (--no-agent)".to_owned());
+        vec![]
+    } else {
+        match agent.identities() {
+            Ok(ids) => {
+                let in_ring = candidates.iter().filter(|c| ids.contains(&c.key.public_key())).count();
+                tried.push(format!(
+                    "agent: {} identit{}, {} with a key file in the ring",
+                    ids.len(),
+                    if ids.len() == 1 { "y" } else { "ies" },
+                    if in_ring == 0 { "none".to_owned() } else { in_ring.to_string() },
+                ));
+                ids
+            }
+            Err(e) => {
+                tried.push(format!("agent: unavailable ({})", e));
+                vec![]
+            }
+        }
+    };
+    let is_held = |c: &Candidate| held.contains(&c.key.public_key());
+
+    // Stages 1 and 2: unencrypted keys, agent-held ones first
+    let mut plain: Vec<&Candidate> = candidates.iter().filter(|c| !c.key.is_encrypted()).collect();
+    plain.sort_by_key(|c| !is_held(c));
+    if options.agent_only {
+        plain.retain(|c| is_held(c));
+    }
+    if let Some(c) = plain.first() {
+        let sk = c.key.decrypt_expanded(b"");
+        let sk = sk.map_err(|e| format!("reading secret key file {}: {}", c.path.display(), e))?;
+        return Ok((sk, c.path.clone()));
+    }
+
+    // Stage 3: encrypted keys
+    let mut encrypted: Vec<&Candidate> = candidates.iter().filter(|c| c.key.is_encrypted()).collect();
+    encrypted.sort_by_key(|c| !is_held(c));
+    if options.agent_only {
+        encrypted.retain(|c| is_held(c));
+    }
+    let mut failures = vec![];
+    if !options.no_prompt {
+        for c in &encrypted {
+            match unlock(&c.path, &c.key, false, &mut prompt) {
+                Ok(sk) => return Ok((sk, c.path.clone())),
+                Err(e) => failures.push(e),
+            }
+        }
+    }
+
+    let scope = if options.agent_only { " held by the agent" } else { "" };
+    let mut files = format!(
+        "files: {} unencrypted and {} encrypted key{} in the ring{}",
+        plain.len(),
+        encrypted.len(),
+        if encrypted.len() == 1 { "" } else { "s" },
+        scope,
+    );
+    if options.no_prompt && !encrypted.is_empty() {
+        files += " (encrypted keys skipped; drop --no-prompt to be asked for passphrases)";
+    }
+    tried.push(files);
+    tried.extend(failures);
+    Err(tried.join("; "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN_1: &str = include_str!("../../../testdata/test-key.priv");
+    const PLAIN_2: &str = include_str!("../../../testdata/test-key-2.priv");
+    const ENCRYPTED: &str = include_str!("../../../testdata/test-key-encrypted.priv");
+    #[cfg(feature = "encrypted-keys")]
+    const PASSPHRASE: &str = "correct horse";
+
+    struct MockAgent(Result<Vec<PublicKey>, String>);
+
+    impl Agent for MockAgent {
+        fn identities(&self) -> Result<Vec<PublicKey>, String> {
+            self.0.clone()
+        }
+    }
+
+    fn candidates(files: &[&str]) -> Vec<Candidate> {
+        files
+            .iter()
+            .enumerate()
+            .map(|(n, armor)| Candidate { path: PathBuf::from(format!("key{}", n)), key: OpensshKey::from_armor(armor).unwrap() })
+            .collect()
+    }
+
+    fn public(armor: &str) -> PublicKey {
+        OpensshKey::from_armor(armor).unwrap().public_key()
+    }
+
+    fn no_prompt(_: &Path) -> Result<String, String> {
+        panic!("should not prompt")
+    }
+
+    #[test]
+    fn ambiguous() {
+        assert_eq!(ambiguity(&candidates(&[PLAIN_1, PLAIN_1])), None);
+        let message = ambiguity(&candidates(&[PLAIN_1, ENCRYPTED, PLAIN_1])).unwrap();
+        assert!(message.starts_with("2 different secret keys"), "{}", message);
+        assert!(message.contains(&public(PLAIN_1).fingerprint()), "{}", message);
+        assert!(message.contains(&public(ENCRYPTED).fingerprint()), "{}", message);
+    }
+
+    #[test]
+    fn agent_held_key_preferred() {
+        let agent = MockAgent(Ok(vec![public(PLAIN_2)]));
+        let (sk, path) = select(candidates(&[PLAIN_1, PLAIN_2]), &agent, Options::default(), no_prompt).unwrap();
+        assert_eq!(sk.to_public(), public(PLAIN_2));
+        assert_eq!(path, Path::new("key1"));
+
+        // Without the agent, the first file wins
+        let options = Options { no_agent: true, ..Options::default() };
+        let (sk, _) = select(candidates(&[PLAIN_1, PLAIN_2]), &agent, options, no_prompt).unwrap();
+        assert_eq!(sk.to_public(), public(PLAIN_1));
+    }
+
+    #[test]
+    fn unencrypted_before_prompt() {
+        let agent = MockAgent(Err("SSH_AUTH_SOCK is not set".into()));
+        let (sk, _) = select(candidates(&[ENCRYPTED, PLAIN_1]), &agent, Options::default(), no_prompt).unwrap();
+        assert_eq!(sk.to_public(), public(PLAIN_1));
+    }
+
+    #[test]
+    #[cfg(feature = "encrypted-keys")]
+    fn prompt_for_encrypted() {
+        let agent = MockAgent(Ok(vec![]));
+        let mut asked = vec![];
+        let (sk, _) = select(candidates(&[ENCRYPTED]), &agent, Options::default(), |path: &Path| {
+            asked.push(path.to_owned());
+            Ok(PASSPHRASE.to_owned())
+        })
+        .unwrap();
+        assert_eq!(sk.to_public(), public(ENCRYPTED));
+        assert_eq!(asked, [Path::new("key0")]);
+
+        let err = select(candidates(&[ENCRYPTED]), &agent, Options::default(), |_: &Path| Ok("nope".into()))
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "agent: 0 identities, none with a key file in the ring; \
+             files: 0 unencrypted and 1 encrypted key in the ring; \
+             wrong passphrase for key0",
+        );
+    }
+
+    #[test]
+    fn failure_summaries() {
+        let agent = MockAgent(Ok(vec![public(PLAIN_2), public(PLAIN_1)]));
+        let options = Options { no_prompt: true, ..Options::default() };
+        let err = select(candidates(&[ENCRYPTED]), &agent, options, no_prompt).map(|_| ()).unwrap_err();
+        assert_eq!(
+            err,
+            "agent: 2 identities, none with a key file in the ring; \
+             files: 0 unencrypted and 1 encrypted key in the ring \
+             (encrypted keys skipped; drop --no-prompt to be asked for passphrases)",
+        );
+
+        let options = Options { agent_only: true, ..Options::default() };
+        let err = select(candidates(&[ENCRYPTED]), &MockAgent(Ok(vec![])), options, no_prompt).map(|_| ()).unwrap_err();
+        assert_eq!(
+            err,
+            "agent: 0 identities, none with a key file in the ring; \
+             files: 0 unencrypted and 0 encrypted keys in the ring held by the agent",
+        );
+    }
+}
@@ -0,0 +1,125 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Errors and exit codes
+
+use ringsig::bundle;
+use ringsig::{ProofError, ProveError, VerifyError};
+
+/// Exit code for any failure without a more specific code
+pub const EXIT_FAILURE: i32 = 1;
+/// Exit code for a malformed command line
+pub const EXIT_USAGE: i32 = 2;
+/// Exit code when the signing key is not a member of the ring
+pub const EXIT_NOT_IN_RING: i32 = 3;
+/// Exit code when several secret keys are in the ring and none was chosen
+pub const EXIT_AMBIGUOUS_KEY: i32 = 4;
+/// Exit code when a proof has expired, or was made after the time it is
+/// checked at
+pub const EXIT_EXPIRED: i32 = 5;
+/// Exit code when `verify --strict` finds a non-canonical scalar in a proof
+pub const EXIT_STRICT_NON_CANONICAL: i32 = 10;
+/// Exit code when `verify --strict` finds a confession with no version
+pub const EXIT_STRICT_LEGACY: i32 = 11;
+/// Exit code when `verify --strict` finds a key repeated in the ring
+pub const EXIT_STRICT_DUPLICATE_KEY: i32 = 12;
+/// Exit code when `verify --strict` finds a JSON field it does not know
+pub const EXIT_STRICT_UNKNOWN_FIELD: i32 = 13;
+
+/// An error, together with the code the process should exit with
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Error {
+    pub code: i32,
+    pub message: String,
+}
+
+impl Error {
+    /// Construct an error with a specific exit code
+    pub fn new<S: Into<String>>(code: i32, message: S) -> Self {
+        Error { code, message: message.into() }
+    }
+}
+
+impl From<ProveError> for Error {
+    fn from(e: ProveError) -> Self {
+        match e {
+            ProveError::KeyNotInRing { sk_public } => Error::new(
+                EXIT_NOT_IN_RING,
+                format!("secret key {} is not a member of the ring", sk_public.fingerprint()),
+            ),
+            e => Error::new(EXIT_FAILURE, e.to_string()),
+        }
+    }
+}
+
+impl From<ProofError> for Error {
+    fn from(e: ProofError) -> Self {
+        VerifyError::from(e).into()
+    }
+}
+
+impl From<VerifyError> for Error {
+    fn from(e: VerifyError) -> Self {
+        match e {
+            VerifyError::WrongProofLength { expected, proof_ring_size: Some(size), .. } => {
+                let provided = expected / 32 - 1;
+                Error::new(
+                    EXIT_FAILURE,
+                    format!(
+                        "proof wrong length: proof was created for a ring of {} key{} but {} {} provided",
+                        size,
+                        if size == 1 { "" } else { "s" },
+                        provided,
+                        if provided == 1 { "key was" } else { "keys were" },
+                    ),
+                )
+            }
+            VerifyError::WrongProofLength { expected, got, proof_ring_size: None } => Error::new(
+                EXIT_FAILURE,
+                format!(
+                    "proof wrong length: a ring of {} keys needs {} bytes, got {}, which is not a whole proof; \
+                     it may have been cut short",
+                    expected / 32 - 1,
+                    expected,
+                    got,
+                ),
+            ),
+            e => Error::new(EXIT_FAILURE, e.to_string()),
+        }
+    }
+}
+
+impl From<bundle::Error> for Error {
+    fn from(e: bundle::Error) -> Self {
+        match e {
+            bundle::Error::Prove(e) => e.into(),
+            bundle::Error::Proof(e) => e.into(),
+            e => Error::new(EXIT_FAILURE, e.to_string()),
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::new(EXIT_FAILURE, message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::new(EXIT_FAILURE, message)
+    }
+}
@@ -0,0 +1,74 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! The `init` subcommand, which writes an unproven confession file
+
+use ringsig::bundle::Confession;
+use std::io;
+use std::path::Path;
+
+use crate::platform;
+
+/// The message used when none is given, to be edited before proving
+pub const PLACEHOLDER_MESSAGE: &str = "REPLACE THIS WITH THE MESSAGE TO CONFESS";
+
+/// Render a confession file with the given ring and message, and no proof
+///
+/// The result is parsed back before it is returned, so that a file which
+/// `prove` would reject is never written.
+pub fn scaffold(pks: &[String], message: Option<&str>) -> Result<String, String> {
+    if pks.is_empty() {
+        return Err("the ring is empty; give keys with --ring, --github or --keys-url".into());
+    }
+    let contents = Confession::new(pks.to_vec(), message.unwrap_or(PLACEHOLDER_MESSAGE).to_owned());
+    let json = serde_json::to_string_pretty(&contents).expect("serializing JSON") + "\n";
+    match Confession::from_json_str(&json) {
+        Ok(ref parsed) if *parsed == contents => Ok(json),
+        _ => Err("internal error: the confession file did not parse back".into()),
+    }
+}
+
+/// Write a confession to `path`, refusing to replace an existing file unless
+/// `force` is set
+pub fn write<D: AsRef<[u8]>>(path: &Path, data: D, force: bool) -> Result<(), String> {
+    match path.symlink_metadata() {
+        Ok(_) if !force => {
+            return Err(format!("{} already exists; pass --force to overwrite it", path.display()))
+        }
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            return Err(format!("checking {}: {}", path.display(), e))
+        }
+        _ => {}
+    }
+    platform::write_atomic(path, data.as_ref()).map_err(|e| format!("writing {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaffold_shape() {
+        let pks = vec!["ssh-ed25519 AAAA a".to_owned()];
+        let json: serde_json::Value = serde_json::from_str(&scaffold(&pks, Some("hi \"there\"")).unwrap()).unwrap();
+        assert_eq!(json, serde_json::json!({ "version": 1, "publicKeys": pks, "message": "hi \"there\"" }));
+
+        let json: serde_json::Value = serde_json::from_str(&scaffold(&pks, None).unwrap()).unwrap();
+        assert_eq!(json["message"], PLACEHOLDER_MESSAGE);
+
+        assert!(scaffold(&[], None).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! The `link-scan` subcommand
+//!
+//! Linkable proofs carry a key image which depends only on the signer and
+//! the scope. Two proofs in the same scope with the same key image were made
+//! by the same person, whichever ring member that is. This checks every
+//! bundle in a directory and reports such repeats. Bundles which are not
+//! linkable, or do not verify, are listed separately and otherwise ignored.
+
+use bitcoin_hashes::hex::FromHex;
+use ringsig::bundle::Confession;
+use ringsig::keys::PublicKey;
+use ringsig::Proof;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+
+/// Length of the abbreviated key images in text output
+const SHORT_IMAGE_LEN: usize = 16;
+
+/// What a single file turned out to be
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Entry {
+    Linkable { scope: String, key_image: String },
+    Plain,
+    Invalid(String),
+}
+
+/// Check a single bundle, verifying it if it is linkable
+fn check(data: &str) -> Entry {
+    let contents = match Confession::from_json_str(data) {
+        Ok(contents) => contents,
+        Err(e) => return Entry::Invalid(e.to_string()),
+    };
+    let (scope, key_image) = match (contents.scope, contents.key_image) {
+        (Some(scope), Some(key_image)) => (scope, key_image),
+        (None, None) => return Entry::Plain,
+        _ => return Entry::Invalid("has only one of scope and keyImage".into()),
+    };
+    let result = (|| -> Result<(), String> {
+        let proof = Proof::from_hex(contents.proof.as_deref().ok_or("no proof")?).map_err(|e| e.to_string())?;
+        let image = <[u8; 32]>::from_hex(&key_image).map_err(|e| format!("parsing keyImage: {}", e))?;
+        let keys = contents
+            .public_keys
+            .iter()
+            .map(|line| PublicKey::parse_pk_line(line).map_err(|e| format!("parsing key {}: {}", line, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let message = contents.attributes.unwrap_or_default().signed_message(contents.message.as_bytes());
+        ringsig::verify_linkable(&proof, &image, &keys, &message, scope.as_bytes()).map_err(|e| e.to_string())
+    })();
+    match result {
+        Ok(()) => Entry::Linkable { scope, key_image },
+        Err(e) => Entry::Invalid(e),
+    }
+}
+
+/// The results of a scan
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Scan {
+    /// For each scope, each key image and the files carrying it
+    scopes: BTreeMap<String, BTreeMap<String, Vec<String>>>,
+    /// Files in scopes other than the one asked for
+    other_scope: Vec<String>,
+    plain: Vec<String>,
+    invalid: Vec<(String, String)>,
+}
+
+impl Scan {
+    /// Sort checked files into a scan, keeping only `scope` if given
+    fn new(entries: Vec<(String, Entry)>, scope: Option<&str>) -> Self {
+        let mut ret = Scan::default();
+        for (file, entry) in entries {
+            match entry {
+                Entry::Linkable { scope: s, .. } if scope.is_some_and(|scope| scope != s) => ret.other_scope.push(file),
+                Entry::Linkable { scope, key_image } => {
+                    ret.scopes.entry(scope).or_default().entry(key_image).or_default().push(file)
+                }
+                Entry::Plain => ret.plain.push(file),
+                Entry::Invalid(e) => ret.invalid.push((file, e)),
+            }
+        }
+        ret
+    }
+
+    /// Check every `.json` file in a directory
+    pub fn dir(dir: &Path, scope: Option<&str>) -> Result<Self, String> {
+        let mut files: Vec<_> = fs::read_dir(dir)
+            .map_err(|e| format!("reading {}: {}", dir.display(), e))?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        files.sort();
+        let entries = files
+            .iter()
+            .map(|path| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                let entry = match fs::read_to_string(path) {
+                    Ok(data) => check(&data),
+                    Err(e) => Entry::Invalid(e.to_string()),
+                };
+                (name, entry)
+            })
+            .collect();
+        Ok(Scan::new(entries, scope))
+    }
+
+    /// Key images which appear in more than one file, by scope
+    fn linked(&self) -> impl Iterator<Item = (&String, &String, &Vec<String>)> {
+        self.scopes.iter().flat_map(|(scope, images)| {
+            images.iter().filter(|(_, files)| files.len() > 1).map(move |(image, files)| (scope, image, files))
+        })
+    }
+
+    pub fn text(&self) -> String {
+        let mut ret = String::new();
+        if self.scopes.is_empty() {
+            ret += "no linkable bundles found\n";
+        }
+        for (scope, images) in &self.scopes {
+            let bundles: usize = images.values().map(Vec::len).sum();
+            ret += &format!(
+                "scope {:?}: {} bundle{} from {} signer{}\n",
+                scope,
+                bundles,
+                if bundles == 1 { "" } else { "s" },
+                images.len(),
+                if images.len() == 1 { "" } else { "s" },
+            );
+            for (s, image, files) in self.linked() {
+                if s == scope {
+                    ret += &format!("  SAME SIGNER (key image {}): {}\n", &image[..SHORT_IMAGE_LEN], files.join(", "));
+                }
+            }
+        }
+        if !self.other_scope.is_empty() {
+            ret += &format!("note: skipped bundles in other scopes: {}\n", self.other_scope.join(", "));
+        }
+        if !self.plain.is_empty() {
+            ret += &format!("note: skipped bundles which are not linkable: {}\n", self.plain.join(", "));
+        }
+        for (file, e) in &self.invalid {
+            ret += &format!("note: skipped {}: {}\n", file, e);
+        }
+        ret
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        let scopes: Vec<_> = self
+            .scopes
+            .iter()
+            .map(|(scope, images)| {
+                let linked: Vec<_> = self
+                    .linked()
+                    .filter(|(s, _, _)| *s == scope)
+                    .map(|(_, image, files)| json!({ "keyImage": image, "files": files }))
+                    .collect();
+                json!({
+                    "scope": scope,
+                    "bundles": images.values().map(Vec::len).sum::<usize>(),
+                    "signers": images.len(),
+                    "linked": linked,
+                })
+            })
+            .collect();
+        let invalid: Vec<_> = self.invalid.iter().map(|(file, e)| json!({ "file": file, "error": e })).collect();
+        json!({
+            "scopes": scopes,
+            "otherScope": self.other_scope,
+            "notLinkable": self.plain,
+            "invalid": invalid,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linkable(scope: &str, image: &str) -> Entry {
+        Entry::Linkable { scope: scope.into(), key_image: image.repeat(32) }
+    }
+
+    #[test]
+    fn grouping() {
+        let entries = vec![
+            ("a.json".to_owned(), linkable("vote", "aa")),
+            ("b.json".to_owned(), linkable("vote", "bb")),
+            ("c.json".to_owned(), linkable("vote", "aa")),
+            ("d.json".to_owned(), linkable("poll", "aa")),
+            ("e.json".to_owned(), Entry::Plain),
+            ("f.json".to_owned(), Entry::Invalid("bad proof".into())),
+        ];
+        let scan = Scan::new(entries.clone(), None);
+        assert_eq!(
+            scan.text(),
+            "scope \"poll\": 1 bundle from 1 signer\n\
+             scope \"vote\": 3 bundles from 2 signers\n  \
+               SAME SIGNER (key image aaaaaaaaaaaaaaaa): a.json, c.json\n\
+             note: skipped bundles which are not linkable: e.json\n\
+             note: skipped f.json: bad proof\n",
+        );
+
+        let scan = Scan::new(entries, Some("poll"));
+        assert_eq!(scan.linked().count(), 0);
+        assert_eq!(scan.json()["otherScope"], json!(["a.json", "b.json", "c.json"]));
+    }
+}
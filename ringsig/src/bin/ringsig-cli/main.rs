@@ -0,0 +1,1129 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+mod agent;
+mod args;
+mod attest;
+mod batch;
+mod bench;
+mod config;
+mod convert;
+mod discovery;
+mod error;
+mod init;
+mod linkscan;
+mod multi;
+mod net;
+mod platform;
+mod progress;
+mod provenance;
+mod report;
+mod revoke;
+mod ringcheck;
+mod ringfile;
+mod seal;
+mod share;
+mod single;
+mod stamp;
+mod strict;
+mod thread;
+
+// Under WASI there is no network and no terminal, and the only files are
+// those in preopened directories; see `platform`
+#[cfg(all(target_os = "wasi", any(feature = "net", feature = "age")))]
+compile_error!("the `net` and `age` features are not available under WASI");
+
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use ringsig::armor::{FromArmor, OpensshKey};
+use ringsig::attributes::{self, SignedAttributes};
+use ringsig::badge::Badge;
+use ringsig::bundle::{Confession, MultiBundle};
+use ringsig::dns;
+use ringsig::keys::{self, ExpandedSecretKey, PublicKey};
+use ringsig::revocation;
+use ringsig::sshsig;
+use ringsig::format::{self, PROOF_BEGIN, PROOF_END};
+use ringsig::{Proof, ProofEncoding};
+use serde_json::json;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs, io, process};
+
+use crate::agent::SshAgent;
+use crate::args::Args;
+use crate::config::{Config, Format};
+use crate::error::{
+    Error, EXIT_AMBIGUOUS_KEY, EXIT_EXPIRED, EXIT_FAILURE, EXIT_NOT_IN_RING, EXIT_STRICT_DUPLICATE_KEY, EXIT_STRICT_LEGACY, EXIT_STRICT_NON_CANONICAL,
+    EXIT_STRICT_UNKNOWN_FIELD, EXIT_USAGE,
+};
+use crate::net::Fetcher;
+use crate::progress::Progress;
+use crate::report::RingReport;
+use crate::ringcheck::{Checked, KeyLine};
+use crate::share::Share;
+
+fn usage() -> Result<(), Error> {
+    let name = env::args().next().unwrap();
+    eprintln!("Usage: {} init --out <json file> [--message <text> | --message-file <file>] [options]", name);
+    eprintln!("Usage: {} prove <json file> [secret key file] [options]", name);
+    eprintln!("Usage: {} prove-batch <manifest.jsonl> --out-dir <dir> [options]", name);
+    eprintln!("Usage: {} verify <json file or URL> [options]", name);
+    eprintln!("Usage: {} convert <input> --to <format> [options]", name);
+    eprintln!("Usage: {} link-scan <directory> [--scope <scope>] [options]", name);
+    eprintln!("Usage: {} thread verify <directory> [options]", name);
+    eprintln!("Usage: {} timestamp <version 2 bundle> --tsa <url> [options]", name);
+    eprintln!("Usage: {} attest-commit <git repository> [commit] [options]", name);
+    eprintln!("Usage: {} verify-commit <json file> <commit sha> [options]", name);
+    eprintln!("Usage: {} badge <json file or URL> [--out <svg file>] [--label <text>] [options]", name);
+    eprintln!("Usage: {} revoke <json file> [secret key file] [--supersede <json file>] [options]", name);
+    eprintln!("Usage: {} ring add <keys file, key line or github:user> --ring <file>", name);
+    eprintln!("Usage: {} ring remove --fingerprint <fp> --ring <file>", name);
+    eprintln!("Usage: {} ring import-known-hosts <known_hosts file> --ring <file> [options]", name);
+    eprintln!("Usage: {} ring list --ring <file>", name);
+    eprintln!("Usage: {} bench [--sizes <n,n,...>] [--iters <n>] [options]", name);
+    eprintln!("Usage: {} config show [options]", name);
+    eprintln!();
+    eprintln!("Here <json file> is a text file containing a JSON object with the");
+    eprintln!("fields `publicKeys`, `message`, and (for verification) `proof`. If");
+    eprintln!("the filename provided is `-` then standard input will be used. When");
+    eprintln!("verifying, an https:// URL may be given instead of a filename.");
+    eprintln!();
+    eprintln!("`verify` also accepts version 2 bundles of several proofs, with a");
+    eprintln!("`proofs` array whose entries may override the top-level `message` and");
+    eprintln!("`publicKeys`. Each entry is reported separately, and all must verify.");
+    eprintln!();
+    eprintln!("`timestamp` asks an RFC 3161 time-stamp authority to attest that a");
+    eprintln!("version 2 bundle exists now, and adds the attestation to the bundle's");
+    eprintln!("`timestamps`. `verify --check-timestamps` checks that each one is for");
+    eprintln!("the bundle and reports its time, but does not check the authority's");
+    eprintln!("signature; use `openssl ts -verify` on the token for that.");
+    eprintln!();
+    eprintln!("`attest-commit` proves that a member of the ring approved a commit (by");
+    eprintln!("default HEAD) of a git repository, with a linkable proof whose key image");
+    eprintln!("is the same for every attestation of that commit by the same key.");
+    eprintln!("`verify-commit` checks that an attestation is for the given commit. Both");
+    eprintln!("need the `git` feature.");
+    eprintln!();
+    eprintln!("`badge` verifies a confession or bundle and renders an SVG badge showing");
+    eprintln!("the outcome, the ring size and the start of the ring id, to embed in a");
+    eprintln!("project page. A failure is rendered too, as a red badge, and then the");
+    eprintln!("tool exits with {} once the badge is written.", EXIT_FAILURE);
+    eprintln!();
+    eprintln!("`revoke` makes a revocation of a confession: a confession over the same");
+    eprintln!("ring whose message says that the original, identified by its hash, is");
+    eprintln!("withdrawn, or with --supersede replaced by another confession. Any ring");
+    eprintln!("member may revoke an ordinary confession; a linkable one may only be");
+    eprintln!("revoked by its own signer. `verify --revocations <dir>` notes whether any");
+    eprintln!("confession in the directory revokes or supersedes the one verified.");
+    eprintln!();
+    eprintln!("--dns-keys looks keys up in DNS, through a DNS-over-HTTPS resolver, and");
+    eprintln!("warns of any answer the resolver did not validate with DNSSEC. SSHFP");
+    eprintln!("records hold only fingerprints, so they cannot add keys to the ring; they");
+    eprintln!("confirm keys added by the other options, such as a host's known_hosts key.");
+    eprintln!();
+    eprintln!("`init` writes a <json file> ready for `prove`, with the ring from the");
+    eprintln!("--ring, --github and --keys-url options and no proof. Without a message");
+    eprintln!("a placeholder is used, which should be edited before proving.");
+    eprintln!();
+    eprintln!("`prove-batch` proves every line of a JSONL manifest, each of the form");
+    eprintln!("{{\"id\": ..., \"message\": ...}} or {{\"id\": ..., \"messageFile\": ...}},");
+    eprintln!("writing <id>.json to the output directory. The ring comes from the");
+    eprintln!("--ring, --github and --keys-url options.");
+    eprintln!();
+    eprintln!("`convert` rewrites a proof in another format: hex, armor, json,");
+    eprintln!("clearsign or bundle. The input format is detected automatically.");
+    eprintln!("Converting a bare proof (hex or armor) to a format which includes the");
+    eprintln!("ring and message requires --ring and --message-file.");
+    eprintln!();
+    eprintln!("With --linkable, `prove` also records a key image which is the same for");
+    eprintln!("every linkable proof by the same key in the same scope, whatever the ring.");
+    eprintln!("`link-scan` verifies the linkable bundles in a directory and reports any");
+    eprintln!("key image which appears more than once, without revealing whose it is.");
+    eprintln!();
+    eprintln!("With --reply-to, `prove` makes the confession a reply to another, by");
+    eprintln!("committing to its hash. `thread verify` verifies the confessions in a");
+    eprintln!("directory as a thread, and fails if any does not verify or replies to one");
+    eprintln!("which is not there.");
+    eprintln!();
+    eprintln!("A ring of one key gives no anonymity. With --sshsig-when-single, `prove`");
+    eprintln!("then writes a plain signature in the `sshsig` field instead of a proof,");
+    eprintln!("which `ssh-keygen -Y verify -n cryptoconfessions` also accepts; it covers");
+    eprintln!("only the message, so is never timestamped. `verify` checks either.");
+    eprintln!();
+    eprintln!("`bench` times proving and verifying over synthetic rings of each size,");
+    eprintln!("both from scratch and with the ring prepared in advance.");
+    eprintln!();
+    eprintln!("The `ring` subcommands edit a keys file, keeping comments intact.");
+    eprintln!("Keys already present are not added again, and unusable keys are");
+    eprintln!("refused unless --force is given. `ring import-known-hosts` adds the");
+    eprintln!("ed25519 host keys from an OpenSSH known_hosts file, with their hosts as");
+    eprintln!("comments, and reports the entries it skips.");
+    eprintln!();
+    eprintln!("If <secret key file> is provided this will be used as the signing key;");
+    eprintln!("`-` reads it from standard input, unless the json file is read from there.");
+    eprintln!("Otherwise, when proving, the tool looks for secret keys in ~/.ssh which");
+    eprintln!("are members of the ring. Unencrypted keys held by the ssh agent are");
+    eprintln!("preferred, then other unencrypted keys, and finally encrypted keys,");
+    eprintln!("whose passphrases are prompted for. The agent itself cannot sign.");
+    eprintln!("If several different keys are in the ring, none is used unless chosen");
+    eprintln!("with --fingerprint, or --first-match is given.");
+    eprintln!();
+    eprintln!("Built for WASI, the tool can only read files in preopened directories,");
+    eprintln!("and has no ~/.ssh, config file, ssh agent, network or terminal, so the");
+    eprintln!("secret key must be given explicitly and cannot be encrypted.");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --key <file>          secret key file to sign with");
+    eprintln!("  --fingerprint <fp>    only use the secret key with this SHA256:... fingerprint");
+    eprintln!("  --ring <file>         add the keys in <file> to the ring (repeatable); when");
+    eprintln!("                        verifying, only used if the JSON has no publicKeys");
+    eprintln!("  --authorized-keys <file>");
+    eprintln!("                        add the keys in an authorized_keys file to the ring");
+    eprintln!("                        (repeatable), even when verifying a JSON with publicKeys");
+    eprintln!("  --github <user>       add the GitHub users' keys to the ring (repeatable,");
+    eprintln!("                        or comma-separated)");
+    eprintln!("  --keys-url <url>      add the keys file at <url> to the ring (repeatable)");
+    eprintln!("  --dns-keys <name,...> add the ed25519 keys in the OPENPGPKEY records of these");
+    eprintln!("                        email addresses, and require a ring key to match the");
+    eprintln!("                        SSHFP records of these host names (repeatable)");
+    eprintln!("  --doh-resolver <url>  DNS-over-HTTPS resolver for --dns-keys (default");
+    eprintln!("                        {})", net::DEFAULT_DOH_RESOLVER);
+    eprintln!("  --allow-http          permit fetching plain http:// URLs");
+    eprintln!("  --format text|json    output format");
+    eprintln!("  --share markdown|html after verifying, print a snippet to paste elsewhere");
+    eprintln!("  --check-github <user[=SHA256:fp],...>");
+    eprintln!("                        after verifying, check which ring members these GitHub");
+    eprintln!("                        users still publish (does not affect the verdict)");
+    eprintln!("  --verbose             show the full key line of every ring member");
+    eprintln!("  --normalize none|lf   normalize line endings of the message before use");
+    eprintln!("  --min-ring <n>        refuse rings with fewer than <n> keys");
+    eprintln!("  --dry-run             when proving, check the key and ring but do not prove");
+    eprintln!("  --quiet               never show a progress indicator for large rings");
+    eprintln!("  --to <format>         format to convert to");
+    eprintln!("  --message <text>      message for `init`");
+    eprintln!("  --message-file <file> message for `init`, or for `convert` if the input lacks one");
+    eprintln!("  --out <file>          file for `init`, `attest-commit`, `badge`, `revoke` or");
+    eprintln!("                        `prove --encrypt-to` to write");
+    eprintln!("  --force               use a ring even though some of its keys are unusable and");
+    eprintln!("                        had to be dropped, add such keys to a ring file, or let");
+    eprintln!("                        `init` or `--encrypt-to` overwrite an existing file");
+    eprintln!("  --host-filter <glob>  only import known_hosts entries for matching hosts");
+    eprintln!("  --include-hashed      also import known_hosts entries with hashed host names");
+    eprintln!("  --out-dir <dir>       directory for the output of `prove-batch`");
+    eprintln!("  --jobs <n>            number of threads for `prove-batch` (default 1; WASI has none)");
+    eprintln!("  --sizes <n,n,...>     ring sizes for `bench` (default {})", bench::DEFAULT_SIZES.map(|n| n.to_string()).join(","));
+    eprintln!("  --iters <n>           iterations per size for `bench`, reporting the median");
+    eprintln!("                        (default {})", bench::DEFAULT_ITERS);
+    eprintln!("  --linkable            make a linkable proof; requires a scope");
+    eprintln!("  --scope <scope>       scope of a linkable proof, or the scope for `link-scan`");
+    eprintln!("  --strict              when verifying, also reject malleable proofs, confessions");
+    eprintln!("                        with no version or unknown fields, and repeated keys");
+    eprintln!("  --explain             when a ring proof fails to verify, describe what verifying");
+    eprintln!("                        found, e.g. the recomputed challenge; a debugging aid only");
+    eprintln!("  --any                 when verifying a version 2 bundle, succeed if any entry does");
+    eprintln!("  --first-match         if several secret keys are in the ring, use the first");
+    eprintln!("  --encrypt-to <age1...,...>");
+    eprintln!("                        when proving, encrypt the result to these age recipients");
+    eprintln!("                        and write it to --out, or <json file>.age (needs the");
+    eprintln!("                        `age` feature); the plaintext is never written to disk");
+    eprintln!("  --decrypt-identity <file>");
+    eprintln!("                        when verifying, first decrypt with this age identity file");
+    eprintln!("  --no-agent            do not ask the ssh agent which keys it holds");
+    eprintln!("  --agent-only          only use secret keys which the ssh agent holds");
+    eprintln!("  --no-prompt           never prompt for passphrases; skip encrypted keys");
+    eprintln!("  --timestamp           when proving, sign the current time (the default)");
+    eprintln!("  --no-timestamp        when proving, do not sign the current time");
+    eprintln!("  --expires <time>      when proving, sign an expiry time: YYYY-MM-DD,");
+    eprintln!("                        YYYY-MM-DDTHH:MM:SSZ, or a duration like 30d, 12h or 2w");
+    eprintln!("  --at <time>           when verifying or making a badge, check expiry at this time");
+    eprintln!("                        instead of now");
+    eprintln!("  --sshsig-when-single  when proving over a ring of one key, make an OpenSSH");
+    eprintln!("                        signature instead (see above)");
+    eprintln!("  --armor               when proving, print only the proof, in ASCII armor, instead");
+    eprintln!("                        of the JSON; it signs no timestamp (see `convert`)");
+    eprintln!("  --versioned-proof     when proving, write the proof with a leading version byte,");
+    eprintln!("                        which older verifiers cannot read");
+    eprintln!("  --tsa <url>           time-stamp authority for `timestamp`; `file:<path>`");
+    eprintln!("                        uses a reply saved earlier, for a request without a nonce");
+    eprintln!("  --check-timestamps    when verifying a version 2 bundle, also check its");
+    eprintln!("                        timestamps, and fail if it has none");
+    eprintln!("  --repo-name <name>    for `attest-commit`, name the repository this instead of");
+    eprintln!("                        its origin URL; for `verify-commit`, require this name");
+    eprintln!("  --label <text>        the left-hand text of a badge (default `{}`)", ringsig::badge::DEFAULT_LABEL);
+    eprintln!("  --reply-to <file>     when proving, reply to the confession in <file>");
+    eprintln!("  --supersede <file>    for `revoke`, replace the confession with the one in <file>");
+    eprintln!("  --revocations <dir>   when verifying a version 1 confession, also report whether");
+    eprintln!("                        any confession in <dir> revokes or supersedes it");
+    eprintln!();
+    eprintln!("Defaults for all options may be set in ~/.config/ringsig/config.toml");
+    eprintln!("(or the file named by $RINGSIG_CONFIG); see `config show`.");
+    eprintln!();
+    eprintln!("Exit codes: 0 on success, {} for bad arguments, {} if the signing key", EXIT_USAGE, EXIT_NOT_IN_RING);
+    eprintln!("is not in the ring, {} if several keys are and none was chosen,", EXIT_AMBIGUOUS_KEY);
+    eprintln!("{} if the proof has expired, and {} for any other failure. With --strict,", EXIT_EXPIRED, EXIT_FAILURE);
+    eprintln!(
+        "{} means a non-canonical scalar, {} no version, {} a repeated key and {} an",
+        EXIT_STRICT_NON_CANONICAL, EXIT_STRICT_LEGACY, EXIT_STRICT_DUPLICATE_KEY, EXIT_STRICT_UNKNOWN_FIELD,
+    );
+    eprintln!("unknown field.");
+    Err(Error::new(EXIT_USAGE, "invalid-command-line-args"))
+}
+
+/// Read a whole file, or standard input if the name is `-`
+fn read_input(name: &str) -> Result<String, String> {
+    let mut ret = String::new();
+    if name == "-" {
+        io::stdin().read_to_string(&mut ret).map_err(|e| format!("reading standard input: {}", e))?;
+    } else {
+        ret = fs::read_to_string(name).map_err(|e| format!("reading {}: {}", name, e))?;
+    }
+    Ok(ret)
+}
+
+/// Read the numbered public key lines out of a keys file
+fn read_keys_file(path: &Path) -> Result<Vec<(usize, String)>, String> {
+    let data = platform::read_text(path).map_err(|e| format!("reading keys file {}: {}", path.display(), e))?;
+    Ok(keys::authorized_keys_lines(&data).map(|(n, line)| (n, line.to_owned())).collect())
+}
+
+/// The key lines of the `--authorized-keys` files, unchecked
+///
+/// Unlike the other ring sources, these are added to the confession's own
+/// ring when verifying too.
+fn authorized_keys_sources(args: &Args) -> Result<Vec<KeyLine>, String> {
+    let mut ret = vec![];
+    for path in args.values("authorized-keys") {
+        ret.extend(KeyLine::numbered(path, read_keys_file(Path::new(path))?));
+    }
+    Ok(ret)
+}
+
+/// The key lines from the configured ring sources, unchecked
+fn ring_sources(config: &Config, fetcher: &Fetcher) -> Result<Vec<KeyLine>, String> {
+    let mut ret = vec![];
+    for path in &config.ring.value {
+        ret.extend(KeyLine::numbered(&path.display().to_string(), read_keys_file(path)?));
+    }
+    let mut urls = vec![];
+    for user in &config.github.value {
+        urls.push(net::github_keys_url(user)?);
+    }
+    urls.extend(config.keys_url.value.iter().cloned());
+    for url in urls {
+        let data = fetcher.get(&url)?;
+        ret.extend(KeyLine::numbered(&url, keys::authorized_keys_lines(&data).map(|(n, line)| (n, line.to_owned()))));
+    }
+    dns_sources(config, fetcher, &mut ret)?;
+    Ok(ret)
+}
+
+/// Add the keys in the OPENPGPKEY records of the email addresses in
+/// `--dns-keys` to `lines`, and check that the SSHFP records of each host
+/// name in it match a key among `lines`
+///
+/// Both are looked up with DNS-over-HTTPS; see [`ringsig::dns`].
+fn dns_sources(config: &Config, fetcher: &Fetcher, lines: &mut Vec<KeyLine>) -> Result<(), String> {
+    let resolver = &config.doh_resolver.value;
+    let lookup = |name: &str, rtype| -> Result<dns::Response, String> {
+        let response = fetcher.dns_lookup(resolver, name, rtype)?;
+        if !response.authenticated {
+            eprintln!("warning: {} did not validate {} with DNSSEC", resolver, name);
+        }
+        Ok(response)
+    };
+    let (emails, hosts): (Vec<_>, Vec<_>) = config.dns_keys.value.iter().partition(|name| name.contains('@'));
+    for email in emails {
+        let name = dns::openpgpkey_name(email).map_err(|e| e.to_string())?;
+        let records = lookup(&name, dns::OPENPGPKEY)?.records;
+        let members = dns::openpgpkey_members(email, &records).map_err(|e| format!("OPENPGPKEY {}: {}", email, e))?;
+        if members.is_empty() {
+            return Err(format!("{} publishes no ed25519 OpenPGP key in DNS", email));
+        }
+        let source = format!("OPENPGPKEY {}", email);
+        lines.extend(KeyLine::numbered(&source, members.iter().enumerate().map(|(n, m)| (n + 1, m.key_line()))));
+    }
+    // SSHFP records only hold fingerprints, so the keys must come from elsewhere
+    let candidates: Vec<PublicKey> = lines.iter().filter_map(|line| PublicKey::parse_pk_line(&line.text).ok()).collect();
+    for host in hosts {
+        let records = lookup(host, dns::SSHFP)?.records;
+        let members = dns::sshfp_members(host, &records, &candidates).map_err(|e| format!("SSHFP {}: {}", host, e))?;
+        if members.is_empty() {
+            return Err(format!("no key in the ring matches the {} SSHFP records of {}", records.len(), host));
+        }
+        for member in members {
+            eprintln!("{} is confirmed by {}", member.key.fingerprint(), member.provenance);
+        }
+    }
+    Ok(())
+}
+
+/// The ring to use, after checking its lines as described in [`ringcheck`]
+/// and that it is big enough
+fn effective_ring(config: &Config, args: &Args, lines: &[KeyLine], keep_repeats: bool) -> Result<Checked, Error> {
+    let checked = ringcheck::check(lines, keep_repeats);
+    checked.enforce(args.switch("force"))?;
+    if checked.keys.len() < config.min_ring.value {
+        return Err(format!(
+            "ring has {} keys, fewer than the minimum of {}",
+            checked.keys.len(),
+            config.min_ring.value,
+        )
+        .into());
+    }
+    Ok(checked)
+}
+
+/// The ring file named on the command line for the `ring` subcommands
+///
+/// This deliberately ignores any rings from the config file.
+fn ring_file(args: &Args) -> Result<PathBuf, Error> {
+    match args.values("ring")[..] {
+        [path] => Ok(PathBuf::from(path)),
+        _ => Err(Error::new(EXIT_USAGE, "exactly one --ring file must be given")),
+    }
+}
+
+/// The key lines named by the argument to `ring add`: a GitHub user, a
+/// single key line, or a keys file
+fn source_lines(source: &str, fetcher: &Fetcher) -> Result<Vec<String>, String> {
+    if let Some(user) = source.strip_prefix("github:") {
+        let data = fetcher.get(&net::github_keys_url(user)?)?;
+        // As with --github, only the ed25519 keys are usable
+        Ok(keys::authorized_keys_lines(&data).filter(|(_, ln)| ln.starts_with("ssh-ed25519 ")).map(|(_, ln)| ln.to_owned()).collect())
+    } else if source.starts_with("ssh-") || source.starts_with("sk-ssh-") {
+        Ok(vec![source.to_owned()])
+    } else {
+        Ok(read_keys_file(Path::new(source))?.into_iter().map(|(_, line)| line).collect())
+    }
+}
+
+/// Find a secret key to sign with, either the configured one or by searching
+/// ~/.ssh as described in [`discovery`], returning it along with the file it
+/// came from
+///
+/// A configured key is returned whether or not it is in the ring; keys found
+/// by searching always are.
+fn find_secret_key(args: &Args, config: &Config, ring: &[PublicKey]) -> Result<(ExpandedSecretKey, PathBuf), Error> {
+    let options = discovery::Options {
+        no_agent: args.switch("no-agent"),
+        agent_only: args.switch("agent-only"),
+        no_prompt: args.switch("no-prompt"),
+    };
+    if let Some(ref path) = config.key.value {
+        let sk_str = match path.to_str() {
+            Some("-") => read_input("-")?,
+            _ => platform::read_text(path).map_err(|e| format!("reading secret key file {}: {}", path.display(), e))?,
+        };
+        let key = OpensshKey::from_armor(&sk_str).map_err(|e| format!("reading secret key file {}: {}", path.display(), e))?;
+        let sk = discovery::unlock(path, &key, options.no_prompt, &mut prompt_passphrase)?;
+        return Ok((sk, path.clone()));
+    }
+
+    let ssh_dir = match platform::ssh_dir() {
+        Some(dir) => dir,
+        None if cfg!(target_os = "wasi") => {
+            return Err(Error::new(EXIT_USAGE, "under WASI the secret key file must be given, or `-` for standard input"))
+        }
+        None => return Err("Unknown home directory. Please specify a secret key file on the command line.".into()),
+    };
+    let mut found = discovery::read_dir(&ssh_dir)?;
+    found.retain(|(_, key)| config.fingerprint.value.as_ref().is_none_or(|fp| key.public_key().fingerprint() == *fp));
+    let public_keys: Vec<_> = found.iter().map(|(_, key)| key.public_key()).collect();
+    let members = ringsig::ring_members(ring, &public_keys);
+    let candidates: Vec<_> = found
+        .into_iter()
+        .enumerate()
+        .filter(|(n, _)| members.contains(n))
+        .map(|(_, (path, key))| discovery::Candidate { path, key })
+        .collect();
+    if !args.switch("first-match") {
+        if let Some(message) = discovery::ambiguity(&candidates) {
+            return Err(Error::new(EXIT_AMBIGUOUS_KEY, message));
+        }
+    }
+    discovery::select(candidates, &SshAgent, options, prompt_passphrase).map_err(|tried| match config.fingerprint.value {
+        Some(ref fp) => Error::new(
+            EXIT_NOT_IN_RING,
+            format!("no usable secret key in ~/.ssh has fingerprint {} and is in the ring ({})", fp, tried),
+        ),
+        None => Error::new(EXIT_NOT_IN_RING, format!("no-sk-found ({})", tried)),
+    })
+}
+
+/// Ask for the passphrase of an encrypted key on the terminal
+#[cfg(not(target_os = "wasi"))]
+fn prompt_passphrase(path: &Path) -> Result<String, String> {
+    rpassword::prompt_password(format!("passphrase for {}: ", path.display()))
+        .map_err(|e| format!("reading passphrase for {}: {}", path.display(), e))
+}
+
+/// There is no terminal to ask on under WASI
+#[cfg(target_os = "wasi")]
+fn prompt_passphrase(path: &Path) -> Result<String, String> {
+    Err(format!("{} is encrypted, and there is no terminal to ask for its passphrase under WASI", path.display()))
+}
+
+/// Report what proving would do, without computing a proof
+///
+/// Fails with [`EXIT_NOT_IN_RING`] if the key is not a ring member.
+fn dry_run(format: Format, ring: &RingReport, pk: &PublicKey, key_path: &Path) -> Result<(), Error> {
+    let position = ring.members.iter().position(|m| m.key == *pk);
+    match format {
+        Format::Text => {
+            println!("would sign with {} ({})", pk.fingerprint(), key_path.display());
+            match position {
+                Some(idx) => println!("key is member {} of {}", idx + 1, ring.text(false).lines().next().unwrap()),
+                None => println!("key is not a member of {}", ring.text(false).lines().next().unwrap()),
+            }
+            for note in ring.notes() {
+                println!("note: {}", note);
+            }
+            if position.is_some() {
+                println!("proving would succeed");
+            }
+        }
+        Format::Json => {
+            let report = json!({
+                "dryRun": true,
+                "wouldSucceed": position.is_some(),
+                "key": {
+                    "fingerprint": pk.fingerprint(),
+                    "file": key_path.display().to_string(),
+                },
+                "position": position,
+                "ring": {
+                    "id": ring.id.to_hex(),
+                    "size": ring.members.len(),
+                },
+                "warnings": ring.notes(),
+            });
+            println!("{}", report);
+        }
+    }
+    match position {
+        Some(_) => Ok(()),
+        None => Err(not_in_ring(pk)),
+    }
+}
+
+fn not_in_ring(pk: &PublicKey) -> Error {
+    Error::new(EXIT_NOT_IN_RING, format!("secret key {} is not a member of the ring", pk.fingerprint()))
+}
+
+/// The scope to prove in, if `--linkable` was given
+///
+/// The scope comes from `--scope` or the confession's own `scope` field.
+fn linkable_scope(args: &Args, contents: &Confession) -> Result<Option<String>, Error> {
+    if !args.switch("linkable") {
+        if args.value("scope").is_some() {
+            return Err(Error::new(EXIT_USAGE, "--scope only makes sense with --linkable"));
+        }
+        return Ok(None);
+    }
+    match (args.value("scope"), &contents.scope) {
+        (Some(flag), Some(file)) if flag != file => {
+            Err(format!("--scope {:?} does not match the confession's scope {:?}", flag, file).into())
+        }
+        (Some(scope), _) => Ok(Some(scope.to_owned())),
+        (None, Some(scope)) => Ok(Some(scope.clone())),
+        (None, None) => Err(Error::new(EXIT_USAGE, "--linkable requires --scope")),
+    }
+}
+
+/// The current time, in seconds since the epoch
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Parse a time given on the command line
+fn parse_time_arg(name: &str, value: &str, now: u64) -> Result<u64, Error> {
+    attributes::parse_time(value, now).map_err(|e| Error::new(EXIT_USAGE, format!("--{}: {}", name, e)))
+}
+
+/// The attributes for `prove` to sign, from `--timestamp`, `--no-timestamp`
+/// and `--expires`
+fn signed_attributes(args: &Args, now: u64) -> Result<SignedAttributes, Error> {
+    if args.switch("timestamp") && args.switch("no-timestamp") {
+        return Err(Error::new(EXIT_USAGE, "give only one of --timestamp and --no-timestamp"));
+    }
+    let reply_to = match args.value("reply-to") {
+        Some(path) => {
+            let parent = Confession::from_json_str(&read_input(path)?).map_err(|e| format!("reading {}: {}", path, e))?;
+            Some(parent.bundle_hash())
+        }
+        None => None,
+    };
+    let attributes = SignedAttributes {
+        timestamp: Some(now).filter(|_| !args.switch("no-timestamp") && !args.switch("armor")),
+        expires: args.value("expires").map(|s| parse_time_arg("expires", s, now)).transpose()?,
+        reply_to,
+    };
+    attributes.check_consistent().map_err(|e| Error::new(EXIT_USAGE, format!("--expires: {}", e)))?;
+    if let Some(expires) = attributes.expires.filter(|&expires| expires <= now) {
+        eprintln!("warning: the proof expires at {}, which has already passed", attributes::format_time(expires));
+    }
+    Ok(attributes)
+}
+
+/// Parse a hex key image from a confession
+fn parse_key_image(s: &str) -> Result<[u8; 32], String> {
+    <[u8; 32]>::from_hex(s).map_err(|e| format!("parsing keyImage: {}", e))
+}
+
+/// The `prove-batch` subcommand
+fn prove_batch(args: &Args, config: &Config, fetcher: &Fetcher, manifest: &Path) -> Result<(), Error> {
+    let out_dir = Path::new(args.value("out-dir").ok_or("prove-batch requires --out-dir")?);
+    let jobs = match args.value("jobs") {
+        Some(n) => n.parse().ok().filter(|&n| n > 0).ok_or_else(|| format!("--jobs must be a positive integer, got `{}`", n))?,
+        None => 1,
+    };
+    if jobs > 1 && !platform::HAS_THREADS {
+        eprintln!("warning: ignoring --jobs, since there are no threads under WASI");
+    }
+
+    let ring = effective_ring(config, args, &ring_sources(config, fetcher)?, false)?;
+    let keys = &ring.keys;
+    let sk = find_secret_key(args, config, keys)?.0.secret_key();
+    if !keys.contains(&sk.to_public()) {
+        return Err(not_in_ring(&sk.to_public()));
+    }
+
+    let data = fs::read_to_string(manifest).map_err(|e| format!("reading {}: {}", manifest.display(), e))?;
+    let base = manifest.parent().unwrap_or(Path::new("."));
+    let (items, mut outcomes) = batch::parse_manifest(&data, base, config.normalize.value);
+    fs::create_dir_all(out_dir).map_err(|e| format!("creating {}: {}", out_dir.display(), e))?;
+    outcomes.extend(batch::prove_items(&items, &ring, &sk, out_dir, jobs));
+
+    let failed = outcomes.iter().filter(|o| o.result.is_err()).count();
+    for outcome in &outcomes {
+        match outcome.result {
+            Ok(ref path) => println!("ok      {} -> {}", outcome.id, path.display()),
+            Err(ref e) => println!("FAILED  {}: {}", outcome.id, e),
+        }
+    }
+    println!("{} succeeded, {} failed", outcomes.len() - failed, failed);
+    if failed > 0 {
+        return Err(format!("{} of {} manifest items failed", failed, outcomes.len()).into());
+    }
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e.message);
+        process::exit(e.code);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let args = Args::parse(env::args().skip(1)).map_err(|e| Error::new(EXIT_USAGE, e))?;
+    let mut config = Config::load(&args)?;
+    for warning in &config.warnings {
+        eprintln!("warning: {}", warning);
+    }
+
+    let fetcher = Fetcher::new(args.switch("allow-http"));
+    let pos: Vec<&str> = args.positional.iter().map(String::as_str).collect();
+    match pos[..] {
+        ["config", "show"] => {
+            println!("{}", config.show().trim_end());
+            return Ok(());
+        }
+        _ if args.switch("dry-run") && pos.first() != Some(&"prove") => usage()?,
+        _ if args.switch("strict") && pos.first() != Some(&"verify") => usage()?,
+        _ if args.switch("explain") && pos.first() != Some(&"verify") => usage()?,
+        _ if args.value("encrypt-to").is_some() && pos.first() != Some(&"prove") => usage()?,
+        _ if args.value("decrypt-identity").is_some() && pos.first() != Some(&"verify") => usage()?,
+        _ if args.switch("any") && pos.first() != Some(&"verify") => usage()?,
+        _ if args.value("at").is_some() && !matches!(pos.first(), Some(&"verify" | &"badge")) => usage()?,
+        _ if args.value("label").is_some() && pos.first() != Some(&"badge") => usage()?,
+        _ if args.value("reply-to").is_some() && pos.first() != Some(&"prove") => usage()?,
+        _ if args.value("supersede").is_some() && pos.first() != Some(&"revoke") => usage()?,
+        _ if args.value("revocations").is_some() && pos.first() != Some(&"verify") => usage()?,
+        _ if args.switch("sshsig-when-single") && pos.first() != Some(&"prove") => usage()?,
+        _ if args.switch("armor") && pos.first() != Some(&"prove") => usage()?,
+        _ if args.switch("versioned-proof") && pos.first() != Some(&"prove") => usage()?,
+        _ if args.value("authorized-keys").is_some() && !matches!(pos.first(), Some(&"prove" | &"verify")) => usage()?,
+        _ if args.value("tsa").is_some() && pos.first() != Some(&"timestamp") => usage()?,
+        _ if args.switch("check-timestamps") && pos.first() != Some(&"verify") => usage()?,
+        _ if args.value("repo-name").is_some() && !matches!(pos.first(), Some(&"attest-commit" | &"verify-commit")) => {
+            usage()?
+        }
+        _ if (args.switch("timestamp") || args.switch("no-timestamp") || args.value("expires").is_some())
+            && pos.first() != Some(&"prove") =>
+        {
+            usage()?
+        }
+        ["convert", input] => {
+            let to = convert::parse_kind(args.value("to").ok_or("convert requires --to")?)?;
+            let mut pks = None;
+            for path in args.values("ring") {
+                pks.get_or_insert_with(Vec::new).extend(read_keys_file(Path::new(path))?.into_iter().map(|(_, line)| line));
+            }
+            let message = match args.value("message-file") {
+                Some(path) => Some(fs::read_to_string(path).map_err(|e| format!("reading {}: {}", path, e))?),
+                None => None,
+            };
+            print!("{}", convert::convert(&read_input(input)?, to, pks, message)?);
+            return Ok(());
+        }
+        ["init"] => {
+            let message = match (args.value("message"), args.value("message-file")) {
+                (Some(_), Some(_)) => return Err(Error::new(EXIT_USAGE, "give only one of --message and --message-file")),
+                (Some(message), None) => Some(message.to_owned()),
+                (None, Some(path)) => Some(read_input(path)?),
+                (None, None) => None,
+            };
+            let message = message.map(|m| config.normalize.value.apply(&m));
+            let ring = effective_ring(&config, &args, &ring_sources(&config, &fetcher)?, false)?;
+            let json = init::scaffold(&ring.lines, message.as_deref())?;
+            return match args.value("out") {
+                Some(path) => Ok(init::write(Path::new(path), &json, args.switch("force"))?),
+                None => Err(Error::new(EXIT_USAGE, "init requires --out")),
+            };
+        }
+        ["link-scan", dir] => {
+            let scan = linkscan::Scan::dir(Path::new(dir), args.value("scope"))?;
+            match config.format.value {
+                Format::Text => print!("{}", scan.text()),
+                Format::Json => println!("{}", scan.json()),
+            }
+            return Ok(());
+        }
+        ["thread", "verify", dir] => {
+            let thread = thread::Thread::dir(Path::new(dir))?;
+            match config.format.value {
+                Format::Text => print!("{}", thread.text()),
+                Format::Json => println!("{}", thread.json()),
+            }
+            return thread.outcome();
+        }
+        ["bench"] => {
+            let sizes = match args.value("sizes") {
+                Some(sizes) => bench::parse_sizes(sizes).map_err(|e| Error::new(EXIT_USAGE, e))?,
+                None => bench::DEFAULT_SIZES.to_vec(),
+            };
+            let iters = match args.value("iters") {
+                Some(n) => n
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| Error::new(EXIT_USAGE, format!("--iters must be a positive integer, got `{}`", n)))?,
+                None => bench::DEFAULT_ITERS,
+            };
+            let timings = bench::run(&sizes, iters)?;
+            print!("{}", bench::report(&timings, iters, config.format.value));
+            return Ok(());
+        }
+        ["prove-batch", manifest] => return prove_batch(&args, &config, &fetcher, Path::new(manifest)),
+        ["timestamp", bundle] => {
+            let tsa = args.value("tsa").ok_or_else(|| Error::new(EXIT_USAGE, "timestamp requires --tsa"))?;
+            return stamp::stamp(Path::new(bundle), tsa, &fetcher, config.format.value);
+        }
+        ["attest-commit", repo] | ["attest-commit", repo, _] => {
+            let rev = pos.get(2).copied().unwrap_or("HEAD");
+            let ring = effective_ring(&config, &args, &ring_sources(&config, &fetcher)?, false)?;
+            let sk = find_secret_key(&args, &config, &ring.keys)?.0.secret_key();
+            if !ring.keys.contains(&sk.to_public()) {
+                return Err(not_in_ring(&sk.to_public()));
+            }
+            let mut confession = attest::attest(Path::new(repo), rev, args.value("repo-name"), &ring.lines, &sk)?;
+            confession.skipped_keys = Some(ring.skipped).filter(|skipped| !skipped.is_empty());
+            let json = confession.to_json_string();
+            return match args.value("out") {
+                Some(path) => Ok(init::write(Path::new(path), format!("{}\n", json), args.switch("force"))?),
+                None => {
+                    println!("{}", json);
+                    Ok(())
+                }
+            };
+        }
+        ["verify-commit", input, sha] => {
+            let confession = Confession::from_json_str(&read_input(input)?).map_err(|e| e.to_string())?;
+            let repository = attest::verify(&confession, sha, args.value("repo-name"))?;
+            print!("{}", attest::report(config.format.value, &confession, sha, &repository));
+            return Ok(());
+        }
+        ["badge", input] => {
+            let data = if net::is_url(input) { fetcher.get(input)? } else { read_input(input)? };
+            let at = match args.value("at") {
+                Some(at) => parse_time_arg("at", at, now())?,
+                None => now(),
+            };
+            let badge = Badge::check(&data, at, args.value("label"));
+            let svg = badge.svg();
+            if let Some(path) = args.value("out") {
+                platform::write_atomic(Path::new(path), svg.as_bytes()).map_err(|e| format!("writing {}: {}", path, e))?;
+            }
+            match (config.format.value, args.value("out")) {
+                (Format::Text, None) => print!("{}", svg),
+                (Format::Text, Some(path)) => println!("wrote {}: {}", path, badge.text()),
+                (Format::Json, out) => {
+                    let mut report = badge.to_json();
+                    match out {
+                        Some(path) => report["file"] = json!(path),
+                        None => report["svg"] = json!(svg),
+                    }
+                    println!("{}", report);
+                }
+            }
+            return match badge.error {
+                _ if badge.verified() => Ok(()),
+                Some(e) => Err(format!("verification failed: {}", e).into()),
+                None => Err("verification failed".into()),
+            };
+        }
+        ["revoke", input] | ["revoke", input, _] => {
+            if let Some(sk_file) = pos.get(2) {
+                if input == "-" && *sk_file == "-" {
+                    let e = "the json file and the secret key cannot both be read from standard input";
+                    return Err(Error::new(EXIT_USAGE, e));
+                }
+                config.key.set_flag(Some(sk_file.into()));
+            }
+            let original = Confession::from_json_str(&read_input(input)?).map_err(|e| e.to_string())?;
+            let keys = original
+                .public_keys
+                .iter()
+                .map(|line| PublicKey::parse_pk_line(line).map_err(|e| format!("parsing key {}: {}", line, e)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let sk = find_secret_key(&args, &config, &keys)?.0.secret_key();
+            if !keys.contains(&sk.to_public()) {
+                return Err(not_in_ring(&sk.to_public()));
+            }
+            let revocation = match args.value("supersede") {
+                Some(path) => {
+                    let replacement = Confession::from_json_str(&read_input(path)?).map_err(|e| e.to_string())?;
+                    revocation::supersede(&original, &replacement, &original.public_keys, &sk)
+                }
+                None => revocation::revoke(&original, &original.public_keys, &sk),
+            };
+            let json = revocation.map_err(|e| e.to_string())?.to_json_string();
+            return match args.value("out") {
+                Some(path) => Ok(init::write(Path::new(path), format!("{}\n", json), args.switch("force"))?),
+                None => {
+                    println!("{}", json);
+                    Ok(())
+                }
+            };
+        }
+        ["ring", "add", source] => {
+            let lines = source_lines(source, &fetcher)?;
+            return Ok(ringfile::add(&ring_file(&args)?, source, &lines, args.switch("force"))?);
+        }
+        ["ring", "remove"] => {
+            let fingerprint = args.value("fingerprint").ok_or("ring remove requires --fingerprint")?;
+            return Ok(ringfile::remove(&ring_file(&args)?, fingerprint)?);
+        }
+        ["ring", "import-known-hosts", known_hosts] => {
+            let data = read_input(known_hosts)?;
+            let options = ringfile::HostOptions {
+                host_filter: args.value("host-filter"),
+                include_hashed: args.switch("include-hashed"),
+            };
+            return Ok(ringfile::import_known_hosts(&ring_file(&args)?, &data, options)?);
+        }
+        ["ring", "list"] => return Ok(ringfile::list(&ring_file(&args)?, config.format.value)?),
+        ["prove", _] | ["verify", _] => {}
+        ["prove", _, sk_file] => config.key.set_flag(Some(sk_file.into())),
+        _ => usage()?,
+    }
+
+    if pos[0] == "prove" && pos[1] == "-" && config.key.value.as_deref() == Some(Path::new("-")) {
+        return Err(Error::new(EXIT_USAGE, "the json file and the secret key cannot both be read from standard input"));
+    }
+
+    // Parse JSON, keeping the raw object for `--strict`
+    let raw: serde_json::Value = if let (true, Some(identity)) = (pos[0] == "verify", args.value("decrypt-identity")) {
+        let mut sealed = vec![];
+        if pos[1] == "-" {
+            io::stdin().read_to_end(&mut sealed).map_err(|e| format!("reading standard input: {}", e))?;
+        } else {
+            sealed = fs::read(pos[1]).map_err(|e| format!("reading {}: {}", pos[1], e))?;
+        }
+        let identity = platform::read_text(Path::new(identity)).map_err(|e| format!("reading {}: {}", identity, e))?;
+        let data = seal::decrypt(&identity, &sealed)?;
+        serde_json::from_slice(&data).map_err(|e| format!("parsing decrypted confession: {}", e))?
+    } else if pos[0] == "verify" && net::is_url(pos[1]) {
+        let data = fetcher.get(pos[1])?;
+        serde_json::from_str(&data).map_err(|e| format!("parsing {}: {}", pos[1], e))?
+    } else {
+        let file: Box<dyn Read> = if pos[1] == "-" {
+            Box::new(io::stdin())
+        } else {
+            Box::new(fs::File::open(pos[1]).map_err(|e| e.to_string())?)
+        };
+        serde_json::from_reader(file).map_err(|e| e.to_string())?
+    };
+
+    // Bundles of several proofs have their own schema and report
+    if raw.get("version") == Some(&json!(ringsig::bundle::MULTI_VERSION)) {
+        if pos[0] == "prove" {
+            return Err(Error::new(EXIT_USAGE, "cannot prove into a version 2 bundle; prove each entry separately"));
+        }
+        if args.switch("strict") {
+            return Err(Error::new(EXIT_USAGE, "--strict does not support version 2 bundles"));
+        }
+        if args.value("revocations").is_some() {
+            return Err(Error::new(EXIT_USAGE, "--revocations only applies to version 1 confessions"));
+        }
+        if args.switch("explain") {
+            return Err(Error::new(EXIT_USAGE, "--explain only applies to version 1 confessions"));
+        }
+        let mut bundle = MultiBundle::from_json_value(raw).map_err(|e| e.to_string())?;
+        // Check these before the bundle is filled in and normalized below
+        let timestamps = args.switch("check-timestamps").then(|| stamp::check(&bundle));
+        if bundle.public_keys.is_none() && bundle.proofs.iter().any(|entry| entry.public_keys.is_none()) {
+            let ring = effective_ring(&config, &args, &ring_sources(&config, &fetcher)?, false)?;
+            bundle.public_keys = Some(ring.lines).filter(|pks| !pks.is_empty());
+        }
+        let normalize = config.normalize.value;
+        bundle.message = bundle.message.map(|m| normalize.apply(&m));
+        for entry in &mut bundle.proofs {
+            entry.message = entry.message.take().map(|m| normalize.apply(&m));
+        }
+        let verdicts = bundle.verify();
+        print!("{}", multi::report(&verdicts, timestamps.as_deref(), config.format.value));
+        multi::outcome(&verdicts, args.switch("any"))?;
+        return timestamps.map_or(Ok(()), |checks| stamp::outcome(&checks));
+    }
+    if args.switch("any") {
+        return Err(Error::new(EXIT_USAGE, "--any only makes sense when verifying a version 2 bundle"));
+    }
+    if args.switch("check-timestamps") {
+        return Err(Error::new(EXIT_USAGE, "only version 2 bundles carry timestamps to check"));
+    }
+
+    let mut contents = Confession::from_json_value(raw.clone()).map_err(|e| e.to_string())?;
+    // Revocations refer to the confession as given, before the changes below
+    let revocations = match args.value("revocations") {
+        Some(dir) => Some(revoke::check(&contents, Path::new(dir))?),
+        None => None,
+    };
+
+    // When verifying, the confession's own ring is authoritative, and any
+    // repeated keys in it are covered by the proof
+    let mut lines = KeyLine::numbered("publicKeys", (1..).zip(contents.public_keys.iter().cloned()));
+    let keep_repeats = pos[0] == "verify" && !contents.public_keys.is_empty();
+    if pos[0] == "prove" || contents.public_keys.is_empty() {
+        lines.extend(ring_sources(&config, &fetcher)?);
+    }
+    lines.extend(authorized_keys_sources(&args)?);
+    contents.message = config.normalize.value.apply(&contents.message);
+
+    let checked = effective_ring(&config, &args, &lines, keep_repeats)?;
+    contents.public_keys = checked.lines;
+    let keys = checked.keys;
+
+    // Obtain secret key for proving
+    if pos[0] == "prove" {
+        // A bare proof has nowhere to put a key image or signed attributes
+        let attributes = args.switch("timestamp") || args.value("expires").is_some() || args.value("reply-to").is_some();
+        if args.switch("armor")
+            && (args.switch("linkable") || attributes || args.value("encrypt-to").is_some() || args.switch("sshsig-when-single"))
+        {
+            return Err(Error::new(
+                EXIT_USAGE,
+                "--armor writes only the proof; it cannot be linkable, timestamped, expire, reply or be encrypted",
+            ));
+        }
+        let (esk, key_path) = find_secret_key(&args, &config, &keys)?;
+        let pk = esk.to_public();
+        if args.switch("dry-run") {
+            let ring = RingReport::new(&contents.public_keys, &keys);
+            return dry_run(config.format.value, &ring, &pk, &key_path);
+        }
+        if !keys.contains(&pk) {
+            return Err(not_in_ring(&pk));
+        }
+
+        // A ring of one hides nothing, so sign in a form anybody can check
+        let scope = linkable_scope(&args, &contents)?;
+        if args.switch("sshsig-when-single") && keys.len() == 1 {
+            let attributes = args.switch("timestamp") || args.value("expires").is_some() || args.value("reply-to").is_some();
+            if scope.is_some() || attributes {
+                return Err(Error::new(
+                    EXIT_USAGE,
+                    "an sshsig covers only the message; it cannot be linkable, timestamped, expire or reply",
+                ));
+            }
+            contents.sshsig = Some(sshsig::sign_sshsig(&esk, single::NAMESPACE, contents.message.as_bytes()));
+        } else {
+            let sk = esk.secret_key();
+            contents.scope = scope;
+            contents.attributes = Some(signed_attributes(&args, now())?).filter(|attributes| !attributes.is_empty());
+            let mut progress = Progress::stderr("proving", args.switch("quiet"), keys.len());
+            let result = contents.prove_with_progress(&sk, |done, total| progress.update(done, total));
+            progress.finish();
+            result?;
+            let proof = Proof::from_hex(contents.proof.as_deref().expect("just proven"))?;
+            let encoding = match args.switch("versioned-proof") {
+                true => ProofEncoding::Versioned,
+                false => ProofEncoding::Legacy,
+            };
+            if args.switch("armor") {
+                print!("{}", format::armor(PROOF_BEGIN, PROOF_END, &proof.encode(encoding)));
+                return Ok(());
+            }
+            contents.proof = Some(proof.encode(encoding).to_hex());
+        }
+        contents.skipped_keys = Some(checked.skipped.clone()).filter(|skipped| !skipped.is_empty());
+        let json = serde_json::to_value(&contents).expect("serializing JSON").to_string();
+        match args.value("encrypt-to") {
+            // The plaintext is only ever held in memory
+            Some(recipients) => {
+                let out = match (args.value("out"), pos[1]) {
+                    (Some(out), _) => PathBuf::from(out),
+                    (None, "-") => return Err(Error::new(EXIT_USAGE, "--encrypt-to with standard input requires --out")),
+                    (None, input) => PathBuf::from(format!("{}.age", input)),
+                };
+                let sealed = seal::encrypt(recipients, format!("{}\n", json).as_bytes())?;
+                init::write(&out, &sealed, args.switch("force"))?;
+                println!("wrote encrypted confession to {}", out.display());
+            }
+            None => println!("{}", json),
+        }
+    }
+
+    // Obtain proof for verifying
+    if pos[0] == "verify" {
+        if let Some(ref sshsig) = contents.sshsig {
+            if contents.proof.is_some() {
+                return Err("the JSON must have only one of proof and sshsig".into());
+            }
+            if args.switch("strict") || args.value("share").is_some() || args.value("check-github").is_some() {
+                return Err(Error::new(EXIT_USAGE, "--strict, --share and --check-github only apply to ring proofs"));
+            }
+            if args.switch("explain") {
+                return Err(Error::new(EXIT_USAGE, "--explain only applies to ring proofs"));
+            }
+            let signer = single::verify(sshsig, &keys, &contents.message)?;
+            let report = single::report(config.format.value, &contents.message, &contents.public_keys, &signer);
+            match revocations {
+                Some(revocations) => print!("{}", revocations.annotate(config.format.value, report)),
+                None => print!("{}", report),
+            }
+            return Ok(());
+        }
+        let proof = contents.proof.as_ref().ok_or("missing proof in JSON")?;
+        let proof = Proof::from_hex(proof)?;
+        if args.switch("strict") {
+            strict::check(&raw, &keys, proof.as_bytes())?;
+        }
+        // Checked before verifying, for clearer messages than the library's
+        match (&contents.scope, &contents.key_image) {
+            (Some(_), Some(key_image)) => {
+                parse_key_image(key_image)?;
+            }
+            (None, None) => {}
+            _ => return Err("the JSON must have both or neither of scope and keyImage".into()),
+        }
+        let now = now();
+        let at = match args.value("at") {
+            Some(at) => parse_time_arg("at", at, now)?,
+            None => now,
+        };
+        let attributes = contents.attributes.unwrap_or_default();
+        let mut progress = Progress::stderr("verifying", args.switch("quiet"), keys.len());
+        let result = contents.verify_with_progress(|done, total| progress.update(done, total));
+        progress.finish();
+        if result.is_err() && args.switch("explain") {
+            match contents.scope {
+                Some(_) => eprintln!("--explain does not cover linkable proofs"),
+                None => eprint!("{}", ringsig::verify_detailed(&proof, &keys, &contents.signed_message())),
+            }
+        }
+        result?;
+        // Only a proof which verified can be said to have expired
+        attributes.check_valid_at(at).map_err(|e| Error::new(EXIT_EXPIRED, e.to_string()))?;
+        let ring = RingReport::new(&contents.public_keys, &keys);
+        let params_hash = contents.params_hash()?;
+        let mut skipped = contents.skipped_keys.clone().unwrap_or_default();
+        skipped.extend(checked.skipped);
+        if let Some(share) = args.value("share") {
+            print!("{}", Share::parse(share)?.render(&contents.message, &ring, proof.as_bytes()));
+            return Ok(());
+        }
+        let provenance = match args.value("check-github") {
+            Some(claims) => {
+                let claims = provenance::parse_claims(claims)?;
+                Some(provenance::check(&ring, &claims, |user| fetcher.get(&net::github_keys_url(user)?)))
+            }
+            None => None,
+        };
+        match config.format.value {
+            Format::Text => {
+                println!("{}", contents.message);
+                println!("-----END OF MESSAGE-----");
+                print!("SUCCESSFULLY VERIFIED PROOF against {}", ring.text(args.switch("verbose")));
+                println!("params hash {}", params_hash);
+                if let (Some(scope), Some(key_image)) = (&contents.scope, &contents.key_image) {
+                    println!("linkable in scope {:?} with key image {}", scope, key_image);
+                }
+                for s in &skipped {
+                    println!("note: {} line {} was left out of the ring: {}", s.source, s.line, s.reason);
+                }
+                if let Some(timestamp) = attributes.timestamp {
+                    println!("signed at {}", attributes::format_time(timestamp));
+                }
+                if let Some(expires) = attributes.expires {
+                    println!("expires at {}", attributes::format_time(expires));
+                }
+                if let Some(parent) = attributes.reply_to {
+                    println!("in reply to the confession with hash {}", parent);
+                }
+                if let Some(provenance) = provenance {
+                    print!("{}", provenance.text());
+                }
+                if let Some(revocations) = revocations {
+                    print!("{}", revocations.text());
+                }
+            }
+            Format::Json => {
+                let mut report = json!({
+                    "verified": true,
+                    "message": contents.message,
+                    "publicKeys": contents.public_keys,
+                    "ring": ring.json(),
+                    "paramsHash": params_hash.to_hex(),
+                });
+                if let (Some(scope), Some(key_image)) = (&contents.scope, &contents.key_image) {
+                    report["linkable"] = json!({ "scope": scope, "keyImage": key_image });
+                }
+                if !skipped.is_empty() {
+                    report["skippedKeys"] = json!(skipped);
+                }
+                if !attributes.is_empty() {
+                    report["attributes"] = json!({
+                        "timestamp": attributes.timestamp.map(attributes::format_time),
+                        "expires": attributes.expires.map(attributes::format_time),
+                        "replyTo": attributes.reply_to.map(|parent| parent.to_hex()),
+                    });
+                }
+                if let Some(provenance) = provenance {
+                    report["provenance"] = provenance.json();
+                }
+                if let Some(revocations) = revocations {
+                    report["revocation"] = revocations.json();
+                }
+                println!("{}", report);
+            }
+        }
+    }
+
+    Ok(())
+}
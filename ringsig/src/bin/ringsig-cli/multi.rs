@@ -0,0 +1,99 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Verifying version 2 bundles, which hold several proofs
+//!
+//! Every entry gets its own verdict. The bundle as a whole verifies only if
+//! every entry does, or with `--any`, if at least one does.
+
+use ringsig::bundle::{self, Verdict};
+
+use crate::config::Format;
+use crate::error::{Error, EXIT_FAILURE};
+use crate::stamp;
+
+/// A label for an entry in text output
+fn label(n: usize, verdict: &Verdict) -> String {
+    match verdict.context {
+        Some(ref context) => format!("entry {} ({:?})", n + 1, context),
+        None => format!("entry {}", n + 1),
+    }
+}
+
+/// Render the verdicts, and the timestamp checks if there were any
+pub fn report(verdicts: &[Verdict], timestamps: Option<&[stamp::Check]>, format: Format) -> String {
+    let passed = verdicts.iter().filter(|v| v.result.is_ok()).count();
+    match format {
+        Format::Text => {
+            let mut ret = String::new();
+            for (n, verdict) in verdicts.iter().enumerate() {
+                match verdict.result {
+                    Ok(()) => {
+                        let keys = verdict.public_keys.as_ref().map_or(0, Vec::len);
+                        ret += &format!("{}: VERIFIED against {} key{}\n", label(n, verdict), keys, if keys == 1 { "" } else { "s" });
+                    }
+                    Err(ref e) => ret += &format!("{}: FAILED: {}\n", label(n, verdict), e),
+                }
+            }
+            ret += &format!("{} of {} entries verified\n", passed, verdicts.len());
+            if let Some(checks) = timestamps {
+                ret += &stamp::text(checks);
+            }
+            ret
+        }
+        Format::Json => {
+            let mut report = bundle::report(verdicts);
+            if let Some(checks) = timestamps {
+                report["timestamps"] = stamp::json(checks);
+            }
+            format!("{}\n", report)
+        }
+    }
+}
+
+/// The overall outcome: every entry must pass, or with `any`, at least one
+pub fn outcome(verdicts: &[Verdict], any: bool) -> Result<(), Error> {
+    let passed = verdicts.iter().filter(|v| v.result.is_ok()).count();
+    if passed == verdicts.len() || (any && passed > 0) {
+        Ok(())
+    } else {
+        Err(Error::new(EXIT_FAILURE, format!("{} of {} entries failed", verdicts.len() - passed, verdicts.len())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringsig::bundle::Error as BundleError;
+
+    fn verdict(context: Option<&str>, result: Result<(), BundleError>) -> Verdict {
+        Verdict { context: context.map(String::from), message: Some("hi".into()), public_keys: Some(vec!["k".into()]), result }
+    }
+
+    #[test]
+    fn verdicts() {
+        let verdicts = [verdict(Some("first"), Ok(())), verdict(None, Err(BundleError::Proof(ringsig::VerifyError::BadProof)))];
+        assert_eq!(
+            report(&verdicts, None, Format::Text),
+            "entry 1 (\"first\"): VERIFIED against 1 key\n\
+             entry 2: FAILED: bad proof\n\
+             1 of 2 entries verified\n",
+        );
+        assert!(outcome(&verdicts, false).is_err());
+        assert!(outcome(&verdicts, true).is_ok());
+        assert!(outcome(&verdicts[1..], true).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Network access
+//!
+//! Keys files and confessions may be fetched from URLs. This requires the
+//! `net` feature; without it every fetch fails with an explanatory error.
+//! Only HTTPS is allowed unless the user explicitly passes `--allow-http`,
+//! and this holds for every redirect as well as for the URL given.
+//! DNS lookups, for `--dns-keys`, are made with DNS-over-HTTPS (RFC 8484)
+//! through the same client.
+
+use ringsig::dns;
+#[cfg(feature = "net")]
+use std::io::Read;
+#[cfg(feature = "net")]
+use std::time::Duration;
+
+/// Largest response we are willing to download
+#[cfg(feature = "net")]
+pub const MAX_RESPONSE_SIZE: usize = 4 * 1024 * 1024;
+
+/// Most redirects which will be followed for a single request
+#[cfg(feature = "net")]
+const MAX_REDIRECTS: usize = 5;
+
+/// The DNS-over-HTTPS resolver used unless another is configured
+pub const DEFAULT_DOH_RESOLVER: &str = "https://cloudflare-dns.com/dns-query";
+
+/// Why every request fails without the `net` feature
+#[cfg(not(feature = "net"))]
+const NO_NET: &str = "this build does not support network access (rebuild with `--features net`)";
+
+/// Whether a command-line argument looks like a URL rather than a path
+pub fn is_url(s: &str) -> bool {
+    s.starts_with("https://") || s.starts_with("http://")
+}
+
+/// The URL at which GitHub publishes a user's ssh keys
+pub fn github_keys_url(user: &str) -> Result<String, String> {
+    let valid = !user.is_empty() && user.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+        return Err(format!("`{}` is not a valid GitHub username", user));
+    }
+    Ok(format!("https://github.com/{}.keys", user))
+}
+
+/// Downloads small documents
+pub struct Fetcher {
+    /// Whether plain `http://` URLs are permitted
+    pub allow_http: bool,
+    /// Largest response, in bytes, which will be accepted
+    #[cfg(feature = "net")]
+    pub max_size: usize,
+    #[cfg(feature = "net")]
+    agent: ureq::Agent,
+}
+
+impl Fetcher {
+    /// Construct a new fetcher
+    pub fn new(allow_http: bool) -> Self {
+        Fetcher {
+            allow_http,
+            #[cfg(feature = "net")]
+            max_size: MAX_RESPONSE_SIZE,
+            #[cfg(feature = "net")]
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(10))
+                .timeout(Duration::from_secs(30))
+                // Redirects are followed by `call`, which checks each target
+                .redirects(0)
+                .build(),
+        }
+    }
+
+    /// Refuse URLs which may not be used
+    fn check_url(&self, url: &str) -> Result<(), String> {
+        if url.starts_with("http://") && !self.allow_http {
+            return Err("refusing to use plain http (pass --allow-http to override)".into());
+        }
+        if !is_url(url) {
+            return Err("only http(s) URLs are supported".into());
+        }
+        Ok(())
+    }
+
+    /// Fetch a URL, returning the body as a string
+    pub fn get(&self, url: &str) -> Result<String, String> {
+        self.check_url(url).and_then(|()| self.get_inner(url)).map_err(|e| format!("fetching {}: {}", url, e))
+    }
+
+    /// Post `body` to a URL, returning the response body
+    pub fn post(&self, url: &str, content_type: &str, body: &[u8]) -> Result<Vec<u8>, String> {
+        self.check_url(url)
+            .and_then(|()| self.post_inner(url, content_type, body))
+            .map_err(|e| format!("posting to {}: {}", url, e))
+    }
+
+    /// Look up the records of type `rtype` at `name` by posting a query to
+    /// a DNS-over-HTTPS resolver
+    pub fn dns_lookup(&self, resolver: &str, name: &str, rtype: u16) -> Result<dns::Response, String> {
+        let query = dns::query(name, rtype).map_err(|e| e.to_string())?;
+        let response = self.post(resolver, "application/dns-message", &query)?;
+        dns::parse_response(&response, rtype).map_err(|e| format!("looking up {} with {}: {}", name, resolver, e))
+    }
+
+    #[cfg(feature = "net")]
+    fn get_inner(&self, url: &str) -> Result<String, String> {
+        let body = self.call(url, None)?;
+        String::from_utf8(body).map_err(|_| "response is not valid UTF-8".to_owned())
+    }
+
+    #[cfg(feature = "net")]
+    fn post_inner(&self, url: &str, content_type: &str, body: &[u8]) -> Result<Vec<u8>, String> {
+        self.call(url, Some((content_type, body)))
+    }
+
+    /// Make a request, a POST of `body` with its content type if there is
+    /// one and a GET otherwise, following redirects to URLs which pass
+    /// `check_url`, and read the final response body
+    ///
+    /// A POST redirected with 301, 302 or 303 is followed with a GET and no
+    /// body, as browsers do. Any other redirect of a POST would send the body
+    /// again, to a server the user never named, so is refused.
+    #[cfg(feature = "net")]
+    fn call(&self, url: &str, mut body: Option<(&str, &[u8])>) -> Result<Vec<u8>, String> {
+        let mut url = url.to_owned();
+        for _ in 0..=MAX_REDIRECTS {
+            let sent = match body {
+                Some((content_type, bytes)) => self.agent.post(&url).set("Content-Type", content_type).send_bytes(bytes),
+                None => self.agent.get(&url).call(),
+            };
+            let resp = sent.map_err(describe)?;
+            if !(300..400).contains(&resp.status()) {
+                return self.read_response(resp);
+            }
+            let location = resp
+                .header("Location")
+                .ok_or_else(|| format!("server returned HTTP {} {} without a location", resp.status(), resp.status_text()))?;
+            let target = url::Url::parse(&url)
+                .and_then(|base| base.join(location))
+                .map_err(|e| format!("server redirected to invalid URL `{}`: {}", location, e))?;
+            self.check_url(target.as_str()).map_err(|e| format!("server redirected to {}: {}", target, e))?;
+            if body.is_some() {
+                if !matches!(resp.status(), 301..=303) {
+                    return Err(format!(
+                        "server redirected to {} with HTTP {} {}, which would send the request body again",
+                        target,
+                        resp.status(),
+                        resp.status_text(),
+                    ));
+                }
+                body = None;
+            }
+            url = target.into();
+        }
+        Err(format!("server redirected more than {} times", MAX_REDIRECTS))
+    }
+
+    /// Read a response body, up to the size limit
+    #[cfg(feature = "net")]
+    fn read_response(&self, resp: ureq::Response) -> Result<Vec<u8>, String> {
+        let mut body = Vec::new();
+        resp.into_reader()
+            .take(self.max_size as u64 + 1)
+            .read_to_end(&mut body)
+            .map_err(|e| e.to_string())?;
+        if body.len() > self.max_size {
+            return Err(format!("response is larger than the limit of {} bytes", self.max_size));
+        }
+        Ok(body)
+    }
+
+    #[cfg(not(feature = "net"))]
+    fn get_inner(&self, _: &str) -> Result<String, String> {
+        Err(NO_NET.into())
+    }
+
+    #[cfg(not(feature = "net"))]
+    fn post_inner(&self, _: &str, _: &str, _: &[u8]) -> Result<Vec<u8>, String> {
+        Err(NO_NET.into())
+    }
+}
+
+/// Describe a failed request
+#[cfg(feature = "net")]
+fn describe(e: ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(code, resp) => format!("server returned HTTP {} {}", code, resp.status_text()),
+        ureq::Error::Transport(t) => t.to_string(),
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    #[cfg(feature = "net")]
+    use std::io::{BufRead, BufReader, Read, Write};
+    #[cfg(feature = "net")]
+    use std::net::TcpListener;
+    #[cfg(feature = "net")]
+    use std::sync::mpsc;
+    #[cfg(feature = "net")]
+    use std::thread;
+
+    /// Serve a single canned HTTP response on an ephemeral local port,
+    /// returning the URL to request
+    #[cfg(feature = "net")]
+    pub fn serve_once(status: &'static str, body: Vec<u8>) -> String {
+        serve_once_with(status, String::new(), body)
+    }
+
+    /// As `serve_once`, adding the given header lines to the response
+    #[cfg(feature = "net")]
+    fn serve_once_with(status: &'static str, headers: String, body: Vec<u8>) -> String {
+        serve_recording(status, headers, body).0
+    }
+
+    /// As `serve_once_with`, also passing on the request line and body the
+    /// server was sent
+    #[cfg(feature = "net")]
+    fn serve_recording(status: &'static str, headers: String, body: Vec<u8>) -> (String, mpsc::Receiver<(String, Vec<u8>)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut line = String::new();
+            let mut request_len = 0;
+            while reader.read_line(&mut line).unwrap() > 2 {
+                if let Some(len) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    request_len = len.trim().parse().unwrap();
+                }
+                line.clear();
+            }
+            // Read any request body, so that closing does not reset the connection
+            let mut request_body = vec![0; request_len];
+            reader.read_exact(&mut request_body).unwrap();
+            let _ = write!(stream, "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n", status, headers, body.len());
+            let _ = stream.write_all(&body);
+            let _ = tx.send((request_line.trim_end().to_owned(), request_body));
+        });
+        (format!("http://{}/keys", addr), rx)
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn fetch_ok() {
+        let url = serve_once("200 OK", b"ssh-ed25519 AAAA".to_vec());
+        assert_eq!(Fetcher::new(true).get(&url), Ok("ssh-ed25519 AAAA".to_owned()));
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn fetch_404() {
+        let url = serve_once("404 Not Found", b"no".to_vec());
+        let err = Fetcher::new(true).get(&url).unwrap_err();
+        assert!(err.starts_with(&format!("fetching {}: ", url)), "{}", err);
+        assert!(err.contains("HTTP 404"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn fetch_oversize() {
+        let url = serve_once("200 OK", vec![b'a'; 1025]);
+        let mut fetcher = Fetcher::new(true);
+        fetcher.max_size = 1024;
+        let err = fetcher.get(&url).unwrap_err();
+        assert!(err.contains("larger than the limit of 1024 bytes"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn post_ok() {
+        let url = serve_once("200 OK", vec![0x30, 0x00]);
+        assert_eq!(Fetcher::new(true).post(&url, "application/octet-stream", b"request"), Ok(vec![0x30, 0x00]));
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn dns_lookup() {
+        // An SSHFP response for example.com, without the AD flag
+        let mut response = vec![0, 0, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        response.extend_from_slice(b"\x07example\x03com\x00\x00\x2c\x00\x01");
+        response.extend_from_slice(b"\xc0\x0c\x00\x2c\x00\x01\x00\x00\x0e\x10\x00\x04\x04\x02\xab\xcd");
+        let url = serve_once("200 OK", response);
+        let found = Fetcher::new(true).dns_lookup(&url, "example.com", dns::SSHFP).unwrap();
+        assert!(!found.authenticated);
+        assert_eq!(found.records, [vec![4, 2, 0xab, 0xcd]]);
+
+        let url = serve_once("200 OK", vec![0, 0, 0x81, 0x82, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let err = Fetcher::new(true).dns_lookup(&url, "example.com", dns::SSHFP).unwrap_err();
+        assert_eq!(err, format!("looking up example.com with {}: server failure (is the DNSSEC signature broken?)", url));
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn fetch_redirect() {
+        let target = serve_once("200 OK", b"ssh-ed25519 AAAA".to_vec());
+        let url = serve_once_with("302 Found", format!("Location: {}\r\n", target), Vec::new());
+        assert_eq!(Fetcher::new(true).get(&url), Ok("ssh-ed25519 AAAA".to_owned()));
+
+        // The test server has no TLS, so skip `get`'s check of the first URL
+        // and see that the plain http redirect alone is refused
+        let url = serve_once_with("302 Found", format!("Location: {}\r\n", target), Vec::new());
+        let err = Fetcher::new(false).get_inner(&url).unwrap_err();
+        assert_eq!(err, format!("server redirected to {}: refusing to use plain http (pass --allow-http to override)", target));
+
+        let url = serve_once_with("302 Found", "Location: ftp://example.com/keys\r\n".into(), Vec::new());
+        let err = Fetcher::new(true).get(&url).unwrap_err();
+        assert!(err.ends_with("server redirected to ftp://example.com/keys: only http(s) URLs are supported"), "{}", err);
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn post_redirect() {
+        // Followed with a GET, without the body
+        for status in ["301 Moved Permanently", "302 Found", "303 See Other"] {
+            let (target, requests) = serve_recording("200 OK", String::new(), vec![0x30, 0x00]);
+            let (url, first) = serve_recording(status, format!("Location: {}\r\n", target), Vec::new());
+            assert_eq!(Fetcher::new(true).post(&url, "application/octet-stream", b"request"), Ok(vec![0x30, 0x00]));
+            assert_eq!(first.recv().unwrap(), ("POST /keys HTTP/1.1".to_owned(), b"request".to_vec()));
+            assert_eq!(requests.recv().unwrap(), ("GET /keys HTTP/1.1".to_owned(), Vec::new()));
+        }
+
+        // Refused, rather than posting the body somewhere else
+        for status in ["307 Temporary Redirect", "308 Permanent Redirect"] {
+            let target = "http://127.0.0.1:1/keys";
+            let url = serve_once_with(status, format!("Location: {}\r\n", target), Vec::new());
+            let err = Fetcher::new(true).post(&url, "application/octet-stream", b"request").unwrap_err();
+            let expected = format!("server redirected to {} with HTTP {}, which would send the request body again", target, status);
+            assert!(err.ends_with(&expected), "{}", err);
+        }
+    }
+
+    #[test]
+    fn fetch_http_refused() {
+        let err = Fetcher::new(false).get("http://127.0.0.1:1/keys").unwrap_err();
+        assert!(err.contains("--allow-http"), "{}", err);
+    }
+
+    #[test]
+    fn github_url() {
+        assert_eq!(github_keys_url("apoelstra").unwrap(), "https://github.com/apoelstra.keys");
+        assert!(github_keys_url("../evil").is_err());
+    }
+}
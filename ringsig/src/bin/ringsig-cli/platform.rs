@@ -0,0 +1,117 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Platform differences
+//!
+//! Everything which behaves differently on Windows and Unix lives here, so
+//! that the rest of the tool need not care.
+//!
+//! The tool also builds for WASI, where it runs sandboxed: only preopened
+//! directories can be read, there is no home directory to find keys or a
+//! config file in, and there is no terminal to ask for a passphrase on.
+//! Every input must then be named explicitly, as a path under a preopened
+//! directory or as standard input.
+
+use std::{fs, io};
+use std::path::{Path, PathBuf};
+
+use crate::config::Normalize;
+
+/// The user's home directory: `%USERPROFILE%` on Windows, `$HOME` elsewhere
+#[cfg(not(target_os = "wasi"))]
+pub fn home_dir() -> Option<PathBuf> {
+    #[cfg(windows)]
+    {
+        if let Some(profile) = std::env::var_os("USERPROFILE").filter(|p| !p.is_empty()) {
+            return Some(PathBuf::from(profile));
+        }
+    }
+    home::home_dir()
+}
+
+/// There is no home directory under WASI, even if `$HOME` is passed in,
+/// since it would not be preopened
+#[cfg(target_os = "wasi")]
+pub fn home_dir() -> Option<PathBuf> {
+    None
+}
+
+/// The directory searched for secret keys
+pub fn ssh_dir() -> Option<PathBuf> {
+    home_dir().map(|home| home.join(".ssh"))
+}
+
+/// Expand a leading `~/` (or `~\` on Windows) to the given home directory
+pub fn expand_tilde(s: &str, home: Option<&Path>) -> Option<PathBuf> {
+    let rest = s.strip_prefix("~/");
+    #[cfg(windows)]
+    let rest = rest.or_else(|| s.strip_prefix("~\\"));
+    Some(home?.join(rest?))
+}
+
+/// Whether threads can be spawned; WASI preview 1 has none
+pub const HAS_THREADS: bool = cfg!(not(target_os = "wasi"));
+
+/// Read a text file, converting CRLF line endings to LF
+///
+/// Keys files and secret keys edited on Windows end up with CRLF endings.
+/// Parsing mostly copes with these by skipping whitespace, but we would
+/// rather not rely on that.
+pub fn read_text(path: &Path) -> io::Result<String> {
+    let data = fs::read_to_string(path)?;
+    Ok(Normalize::Lf.apply(&data))
+}
+
+/// Replace the contents of a file, so that a crash leaves either the old
+/// contents or the new ones but never a mixture
+///
+/// The data is written to a temporary file alongside the target, which is
+/// then renamed over it. On Windows the rename also replaces an existing
+/// file.
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tilde() {
+        let home = Path::new("home");
+        assert_eq!(expand_tilde("~/a/b", Some(home)), Some(home.join("a/b")));
+        assert_eq!(expand_tilde("~/a", None), None);
+        assert_eq!(expand_tilde("a/~/b", Some(home)), None);
+        assert_eq!(expand_tilde("~user/a", Some(home)), None);
+        #[cfg(windows)]
+        assert_eq!(expand_tilde("~\\a", Some(home)), Some(home.join("a")));
+    }
+
+    #[test]
+    fn crlf_files() {
+        let path = std::env::temp_dir().join(format!("ringsig-crlf-test-{}", std::process::id()));
+        fs::write(&path, "line one\r\nline two\r\n").unwrap();
+        let text = read_text(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(text.unwrap(), "line one\nline two\n");
+    }
+}
@@ -0,0 +1,114 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Progress indicator for large rings
+//!
+//! The indicator is only ever written to stderr, and only when stderr is a
+//! terminal, so piped output is unaffected.
+
+use std::io::{self, IsTerminal, Write};
+
+/// Rings smaller than this finish quickly enough not to need an indicator
+pub const PROGRESS_THRESHOLD: usize = 1000;
+
+/// Whether to show an indicator for a ring of the given size
+pub fn should_show(quiet: bool, is_tty: bool, ring_size: usize) -> bool {
+    !quiet && is_tty && ring_size >= PROGRESS_THRESHOLD
+}
+
+/// A `keys processed / total` counter, redrawn in place
+pub struct Progress<W: Write> {
+    out: Option<W>,
+    label: &'static str,
+    /// Last percentage drawn, so we only redraw when it changes
+    last: Option<usize>,
+    /// Length of the last line drawn
+    width: usize,
+}
+
+impl Progress<io::Stderr> {
+    /// An indicator on stderr, enabled only if appropriate
+    pub fn stderr(label: &'static str, quiet: bool, ring_size: usize) -> Self {
+        let stderr = io::stderr();
+        let show = should_show(quiet, stderr.is_terminal(), ring_size);
+        Progress::new(if show { Some(stderr) } else { None }, label)
+    }
+}
+
+impl<W: Write> Progress<W> {
+    /// An indicator writing to `out`, or doing nothing if `out` is `None`
+    pub fn new(out: Option<W>, label: &'static str) -> Self {
+        Progress { out, label, last: None, width: 0 }
+    }
+
+    /// Record that `done` out of `total` keys have been processed
+    pub fn update(&mut self, done: usize, total: usize) {
+        let out = match self.out {
+            Some(ref mut out) => out,
+            None => return,
+        };
+        let percent = done * 100 / total.max(1);
+        if self.last != Some(percent) {
+            self.last = Some(percent);
+            let line = format!("{}: {}/{} keys", self.label, done, total);
+            self.width = line.len();
+            let _ = write!(out, "\r{}", line);
+            let _ = out.flush();
+        }
+    }
+
+    /// Erase the indicator, if one was drawn
+    ///
+    /// This overwrites with spaces rather than using an ANSI escape, which
+    /// older Windows consoles print literally.
+    pub fn finish(&mut self) {
+        if let (Some(out), Some(_)) = (self.out.as_mut(), self.last) {
+            let _ = write!(out, "\r{:width$}\r", "", width = self.width);
+            let _ = out.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suppression() {
+        assert!(should_show(false, true, PROGRESS_THRESHOLD));
+        assert!(!should_show(true, true, PROGRESS_THRESHOLD)); // --quiet
+        assert!(!should_show(false, false, PROGRESS_THRESHOLD)); // not a TTY
+        assert!(!should_show(false, true, PROGRESS_THRESHOLD - 1)); // small ring
+    }
+
+    #[test]
+    fn redraws_on_percentage_change() {
+        let mut buf = vec![];
+        let mut progress = Progress::new(Some(&mut buf), "proving");
+        for done in 1..=400 {
+            progress.update(done, 400);
+        }
+        progress.finish();
+        let out = String::from_utf8(buf).unwrap();
+        assert_eq!(out.matches('\r').count(), 103); // 0% through 100%, then the erase
+        assert!(out.starts_with("\rproving: 1/400 keys\rproving: 4/400 keys"));
+        assert!(out.ends_with(&format!("\rproving: 400/400 keys\r{}\r", " ".repeat(21))));
+
+        let mut disabled = Progress::new(None::<Vec<u8>>, "proving");
+        disabled.update(1, 1);
+        disabled.finish();
+    }
+}
@@ -0,0 +1,255 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Checking ring members against the keys people currently publish
+//!
+//! A proof only shows that one of the ring's keys signed. Whether those keys
+//! belong to the people they are claimed to belong to is a separate
+//! question, and the answer changes as keys are rotated. This re-fetches
+//! each named GitHub user's keys and reports on every ring member. It never
+//! affects whether the proof verified.
+
+use ringsig::keys::PublicKey;
+use serde_json::json;
+use std::fmt;
+
+use crate::report::{short_id, RingReport};
+
+/// A user named in `--check-github`, optionally with the fingerprint of
+/// the key they are believed to have in the ring
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Claim {
+    pub user: String,
+    pub fingerprint: Option<String>,
+}
+
+/// Parse the argument to `--check-github`: `user1=SHA256:...,user2,...`
+pub fn parse_claims(s: &str) -> Result<Vec<Claim>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| match item.split_once('=') {
+            Some((user, fp)) if fp.starts_with("SHA256:") => {
+                Ok(Claim { user: user.to_owned(), fingerprint: Some(fp.to_owned()) })
+            }
+            Some(_) => Err(format!("in `{}`, expected a fingerprint of the form SHA256:...", item)),
+            None => Ok(Claim { user: item.to_owned(), fingerprint: None }),
+        })
+        .collect()
+}
+
+/// What we found out about one ring member
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Status {
+    /// The key is currently published by the user
+    Published(String),
+    /// The key was attributed to the user, who no longer publishes it
+    Absent(String),
+    /// The key was attributed to the user, whose keys could not be fetched
+    Unknown(String),
+    /// No named user publishes or was attributed the key
+    Unattributed,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Status::Published(ref user) => write!(f, "published by {}", user),
+            Status::Absent(ref user) => write!(f, "no longer published by {}", user),
+            Status::Unknown(ref user) => write!(f, "could not check {}", user),
+            Status::Unattributed => f.write_str("not attributable to any named user"),
+        }
+    }
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match *self {
+            Status::Published(_) => "published",
+            Status::Absent(_) => "absent",
+            Status::Unknown(_) => "unknown",
+            Status::Unattributed => "unattributed",
+        }
+    }
+
+    fn user(&self) -> Option<&str> {
+        match *self {
+            Status::Published(ref u) | Status::Absent(ref u) | Status::Unknown(ref u) => Some(u),
+            Status::Unattributed => None,
+        }
+    }
+}
+
+/// The results of a provenance check
+pub struct Provenance {
+    /// One entry per ring member, in canonical order
+    pub members: Vec<(PublicKey, Status)>,
+    /// Problems fetching keys, or claims which do not match the ring
+    pub notes: Vec<String>,
+}
+
+/// Check every ring member against the claims, using `fetch_keys` to get
+/// a user's current keys file
+pub fn check<F>(ring: &RingReport, claims: &[Claim], mut fetch_keys: F) -> Provenance
+where
+    F: FnMut(&str) -> Result<String, String>,
+{
+    let mut notes = vec![];
+    // (claim, the user's current keys if we could get them)
+    let mut published = vec![];
+    for claim in claims {
+        match fetch_keys(&claim.user) {
+            Ok(data) => {
+                let keys: Vec<PublicKey> = data.lines().filter_map(|ln| PublicKey::parse_pk_line(ln).ok()).collect();
+                published.push((claim, Some(keys)));
+            }
+            Err(e) => {
+                notes.push(format!("could not fetch keys for {}: {}", claim.user, e));
+                published.push((claim, None));
+            }
+        }
+        if let Some(ref fp) = claim.fingerprint {
+            if !ring.members.iter().any(|m| m.key.fingerprint() == *fp) {
+                notes.push(format!("{} is attributed {}, which is not in the ring", claim.user, fp));
+            }
+        }
+    }
+
+    let members = ring
+        .members
+        .iter()
+        .map(|m| {
+            let fp = m.key.fingerprint();
+            let status = published
+                .iter()
+                .find_map(|(claim, keys)| {
+                    let claimed = claim.fingerprint.as_ref() == Some(&fp);
+                    let user = claim.user.clone();
+                    match keys {
+                        Some(keys) if keys.contains(&m.key) => Some(Status::Published(user)),
+                        Some(_) if claimed => Some(Status::Absent(user)),
+                        None if claimed => Some(Status::Unknown(user)),
+                        _ => None,
+                    }
+                })
+                .unwrap_or(Status::Unattributed);
+            (m.key, status)
+        })
+        .collect();
+    Provenance { members, notes }
+}
+
+impl Provenance {
+    pub fn text(&self) -> String {
+        let mut ret = String::from("provenance:\n");
+        for (key, status) in &self.members {
+            ret += &format!("  {}  {}\n", short_id(key), status);
+        }
+        for note in &self.notes {
+            ret += &format!("note: {}\n", note);
+        }
+        ret
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        let members: Vec<_> = self
+            .members
+            .iter()
+            .map(|(key, status)| {
+                json!({ "fingerprint": key.fingerprint(), "status": status.name(), "user": status.user() })
+            })
+            .collect();
+        json!({ "members": members, "notes": self.notes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALICE_OLD: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAyxLYuvSF14BJXejP+Qx6yLH1MXr/HGOcLZU55TodKS";
+    const ALICE_NEW: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGMiyoNWxKsdbuZ9EeJA+QTTaKHYtpCrRBlvCez8ykRl";
+    const BOB: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10";
+    const CAROL: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1";
+
+    fn fp(line: &str) -> String {
+        PublicKey::parse_pk_line(line).unwrap().fingerprint()
+    }
+
+    fn ring() -> RingReport {
+        let lines: Vec<String> = [ALICE_OLD, BOB, CAROL].iter().map(|s| s.to_string()).collect();
+        let keys: Vec<_> = lines.iter().map(|l| PublicKey::parse_pk_line(l).unwrap()).collect();
+        RingReport::new(&lines, &keys)
+    }
+
+    fn status_of<'a>(prov: &'a Provenance, line: &str) -> &'a Status {
+        let pk = PublicKey::parse_pk_line(line).unwrap();
+        &prov.members.iter().find(|(key, _)| *key == pk).unwrap().1
+    }
+
+    #[test]
+    fn claims() {
+        let claims = parse_claims("alice=SHA256:abc, bob,").unwrap();
+        assert_eq!(
+            claims,
+            [
+                Claim { user: "alice".into(), fingerprint: Some("SHA256:abc".into()) },
+                Claim { user: "bob".into(), fingerprint: None },
+            ],
+        );
+        assert!(parse_claims("alice=abc").is_err());
+    }
+
+    #[test]
+    fn present_absent_rotated() {
+        // alice rotated their key; bob still publishes his; nobody claims carol's
+        let claims = parse_claims(&format!("alice={},bob", fp(ALICE_OLD))).unwrap();
+        let prov = check(&ring(), &claims, |user| match user {
+            "alice" => Ok(format!("{}\n", ALICE_NEW)),
+            "bob" => Ok(format!("ssh-rsa AAAA\n{}\n", BOB)),
+            _ => unreachable!(),
+        });
+        assert_eq!(*status_of(&prov, ALICE_OLD), Status::Absent("alice".into()));
+        assert_eq!(*status_of(&prov, BOB), Status::Published("bob".into()));
+        assert_eq!(*status_of(&prov, CAROL), Status::Unattributed);
+        assert!(prov.notes.is_empty());
+        assert_eq!(prov.json()["members"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn fetch_failure_and_bad_claim() {
+        let claims = parse_claims(&format!("alice={},bob={}", fp(ALICE_OLD), fp(ALICE_NEW))).unwrap();
+        let prov = check(&ring(), &claims, |user| match user {
+            "alice" => Err("HTTP 404".into()),
+            _ => Ok(String::new()),
+        });
+        assert_eq!(*status_of(&prov, ALICE_OLD), Status::Unknown("alice".into()));
+        assert_eq!(prov.notes.len(), 2);
+        assert!(prov.notes[0].contains("HTTP 404"));
+        assert!(prov.notes[1].contains("not in the ring"));
+    }
+
+    #[test]
+    #[cfg(feature = "net")]
+    fn over_http() {
+        use crate::net::{tests::serve_once, Fetcher};
+
+        let url = serve_once("200 OK", format!("{}\n", BOB).into_bytes());
+        let claims = parse_claims(&format!("bob={}", fp(BOB))).unwrap();
+        let prov = check(&ring(), &claims, |_| Fetcher::new(true).get(&url));
+        assert_eq!(*status_of(&prov, BOB), Status::Published("bob".into()));
+    }
+}
@@ -0,0 +1,212 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Human- and machine-readable descriptions of rings
+
+use bitcoin_hashes::hex::ToHex;
+use ringsig::hashes::RingHash;
+use ringsig::keys::PublicKey;
+use serde_json::json;
+
+/// Number of fingerprint characters used as a member's short id
+const SHORT_ID_LEN: usize = 8;
+/// Number of hex characters of the ring id shown in text output
+const SHORT_RING_ID_LEN: usize = 16;
+
+/// Short identifier for a key: the start of its SHA256 fingerprint
+pub fn short_id(pk: &PublicKey) -> String {
+    let fp = pk.fingerprint();
+    fp["SHA256:".len()..][..SHORT_ID_LEN].to_owned()
+}
+
+/// The comment, if any, following the key data on an ssh public key line
+pub fn comment(line: &str) -> Option<&str> {
+    let mut rest = line.trim();
+    for _ in 0..2 {
+        let end = rest.find(|c: char| c.is_ascii_whitespace())?;
+        rest = rest[end..].trim_start();
+    }
+    if rest.is_empty() {
+        None
+    } else {
+        Some(rest)
+    }
+}
+
+/// A member of the canonical ring
+pub struct Member {
+    pub key: PublicKey,
+    /// The first line in the input which gave this key
+    pub line: String,
+    /// That line's comment, which usually says whose key it is
+    pub comment: Option<String>,
+}
+
+/// Description of the canonical ring corresponding to a list of key lines
+pub struct RingReport {
+    pub id: RingHash,
+    /// Members, sorted and deduplicated
+    pub members: Vec<Member>,
+    /// Number of input lines which repeated an earlier key
+    pub duplicates: usize,
+    /// Whether the input was in a different order from the canonical ring
+    pub reordered: bool,
+}
+
+impl RingReport {
+    /// Construct a report from key lines and their parsed keys
+    pub fn new(lines: &[String], keys: &[PublicKey]) -> Self {
+        let mut members: Vec<Member> = vec![];
+        for (line, key) in lines.iter().zip(keys) {
+            if !members.iter().any(|m| m.key == *key) {
+                let comment = PublicKey::parse_pk_line_with_comment(line).ok().and_then(|(_, comment)| comment);
+                members.push(Member { key: *key, line: line.clone(), comment });
+            }
+        }
+        let duplicates = keys.len() - members.len();
+        let reordered = members.windows(2).any(|w| w[0].key.serialize() > w[1].key.serialize());
+        members.sort_by_key(|m| m.key.serialize());
+        RingReport { id: ringsig::ring_id(keys), members, duplicates, reordered }
+    }
+
+    /// Text rendering: a header line, one line per member, and any notes
+    pub fn text(&self, verbose: bool) -> String {
+        let mut ret = format!(
+            "ring {} ({} member{})\n",
+            &self.id.to_hex()[..SHORT_RING_ID_LEN],
+            self.members.len(),
+            if self.members.len() == 1 { "" } else { "s" },
+        );
+        for m in &self.members {
+            ret += &format!("  {}  {}\n", short_id(&m.key), m.comment.as_deref().unwrap_or("(no comment)"));
+            if verbose {
+                ret += &format!("      {}\n", m.line);
+            }
+        }
+        for note in self.notes() {
+            ret += &format!("note: {}\n", note);
+        }
+        ret
+    }
+
+    /// Anything surprising about the input, e.g. repeated keys
+    pub fn notes(&self) -> Vec<String> {
+        let mut ret = vec![];
+        if self.duplicates > 0 {
+            ret.push(format!(
+                "publicKeys repeats {} key{}; the ring contains each key once",
+                self.duplicates,
+                if self.duplicates == 1 { "" } else { "s" },
+            ));
+        }
+        if self.reordered {
+            ret.push("publicKeys is not in canonical order; the ring is listed sorted".to_owned());
+        }
+        ret
+    }
+
+    /// JSON rendering
+    pub fn json(&self) -> serde_json::Value {
+        let members: Vec<_> = self
+            .members
+            .iter()
+            .map(|m| {
+                json!({
+                    "fingerprint": m.key.fingerprint(),
+                    "comment": m.comment,
+                    "line": m.line,
+                })
+            })
+            .collect();
+        json!({
+            "id": self.id.to_hex(),
+            "size": self.members.len(),
+            "members": members,
+            "duplicates": self.duplicates,
+            "reordered": self.reordered,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ring from testdata/test-verify.json
+    const FIXTURE_RING: [&str; 3] = [
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAyxLYuvSF14BJXejP+Qx6yLH1MXr/HGOcLZU55TodKS",
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10",
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana",
+    ];
+
+    fn report(lines: &[&str]) -> RingReport {
+        let lines: Vec<String> = lines.iter().map(|s| s.to_string()).collect();
+        let keys: Vec<_> = lines.iter().map(|l| PublicKey::parse_pk_line(l).unwrap()).collect();
+        RingReport::new(&lines, &keys)
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(comment(FIXTURE_RING[0]), None);
+        assert_eq!(comment(FIXTURE_RING[2]), Some("apoelstra@sultana"));
+        assert_eq!(comment("  ssh-ed25519\tAAAA  two  words "), Some("two  words"));
+    }
+
+    #[test]
+    fn fixture_text() {
+        assert_eq!(
+            report(&FIXTURE_RING).text(false),
+            "ring 099be536a336dc8b (3 members)\n\
+             \x20 k/ZdsVp1  (no comment)\n\
+             \x20 F+kgkEB9  apoelstra@sultana\n\
+             \x20 py4F80rP  (no comment)\n\
+             note: publicKeys is not in canonical order; the ring is listed sorted\n",
+        );
+    }
+
+    #[test]
+    fn fixture_text_verbose_duplicates() {
+        let ring = [FIXTURE_RING[0], FIXTURE_RING[2], FIXTURE_RING[0]];
+        assert_eq!(
+            report(&ring).text(true),
+            format!(
+                "ring d0eada9fc3b7f07e (2 members)\n\
+                 \x20 k/ZdsVp1  (no comment)\n\
+                 \x20     {}\n\
+                 \x20 F+kgkEB9  apoelstra@sultana\n\
+                 \x20     {}\n\
+                 note: publicKeys repeats 1 key; the ring contains each key once\n",
+                FIXTURE_RING[0], FIXTURE_RING[2],
+            ),
+        );
+    }
+
+    #[test]
+    fn fixture_json() {
+        let json = report(&FIXTURE_RING).json();
+        assert_eq!(json["id"], "099be536a336dc8b7725f15d3888f9148e782b6240086645099f93dbe840a367");
+        assert_eq!(json["size"], 3);
+        assert_eq!(json["reordered"], true);
+        assert_eq!(
+            json["members"][1],
+            json!({
+                "fingerprint": "SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus",
+                "comment": "apoelstra@sultana",
+                "line": FIXTURE_RING[2],
+            }),
+        );
+    }
+}
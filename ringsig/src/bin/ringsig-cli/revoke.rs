@@ -0,0 +1,107 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Revocations, as made by `revoke` and checked by `verify --revocations`
+//!
+//! See [`ringsig::revocation`] for what a revocation is. `verify` reads every
+//! `.json` file in the directory it is given, ignores those which are not
+//! valid revocations of the confession being verified, and notes whether
+//! any of the rest revokes or supersedes it. It still verifies either way.
+
+use bitcoin_hashes::hex::ToHex;
+use ringsig::bundle::Confession;
+use ringsig::revocation::{self, RevocationStatus};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::config::Format;
+use crate::error::Error;
+
+/// The outcome of checking a directory of revocations
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Checked {
+    pub status: RevocationStatus,
+    /// The files which were read, in the order checked
+    pub files: Vec<String>,
+}
+
+/// Check the confessions in `dir` for revocations of `confession`
+///
+/// Files which are not version 1 confessions are skipped with a warning.
+pub fn check(confession: &Confession, dir: &Path) -> Result<Checked, Error> {
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("reading {}: {}", dir.display(), e))?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+    let mut files = vec![];
+    let mut revocations = vec![];
+    for path in paths {
+        let data = fs::read_to_string(&path).map_err(|e| format!("reading {}: {}", path.display(), e))?;
+        match Confession::from_json_str(&data) {
+            Ok(revocation) => {
+                files.push(path.file_name().unwrap().to_string_lossy().into_owned());
+                revocations.push(revocation);
+            }
+            Err(e) => eprintln!("warning: skipping {}: {}", path.display(), e),
+        }
+    }
+    Ok(Checked { status: revocation::check_revocations(confession, &revocations), files })
+}
+
+impl Checked {
+    pub fn text(&self) -> String {
+        match self.status {
+            RevocationStatus::Standing => {
+                format!("not revoked by any of the {} confessions checked\n", self.files.len())
+            }
+            RevocationStatus::Revoked { by } => format!("REVOKED by {}\n", self.files[by]),
+            RevocationStatus::Superseded { by, replacement } => {
+                format!("SUPERSEDED by {}, in favour of the confession with hash {}\n", self.files[by], replacement)
+            }
+        }
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        match self.status {
+            RevocationStatus::Standing => json!({ "status": "standing", "checked": self.files.len() }),
+            RevocationStatus::Revoked { by } => {
+                json!({ "status": "revoked", "checked": self.files.len(), "by": self.files[by] })
+            }
+            RevocationStatus::Superseded { by, replacement } => json!({
+                "status": "superseded",
+                "checked": self.files.len(),
+                "by": self.files[by],
+                "replacement": replacement.to_hex(),
+            }),
+        }
+    }
+
+    /// Add this to a rendered verification report
+    pub fn annotate(&self, format: Format, report: String) -> String {
+        match format {
+            Format::Text => report + &self.text(),
+            Format::Json => {
+                let mut report: serde_json::Value = serde_json::from_str(&report).expect("reports are JSON");
+                report["revocation"] = self.json();
+                format!("{}\n", report)
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Checking the keys of a ring before use
+//!
+//! A ring is assembled from a confession's `publicKeys` and from keys files,
+//! GitHub and URLs. Some lines may not be usable: they may repeat an earlier
+//! key, fail to parse, be torsioned, or not be ed25519 keys at all. Each such
+//! line gets a warning. Lines which cannot be used are dropped, and since
+//! the ring is then not the one the user gave, that is an error unless
+//! `--force` is given, in which case the dropped lines are recorded.
+//!
+//! Repeated keys are dropped when proving, which leaves the ring unchanged
+//! as a set. When verifying they are kept, so that verification refuses the
+//! ring as the library does.
+
+use ringsig::bundle::SkippedKey;
+use ringsig::keys::{Error as KeyError, PublicKey};
+
+use crate::error::Error;
+
+/// A line of a ring, and where it came from
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct KeyLine {
+    /// A file name, URL, or `publicKeys`
+    pub source: String,
+    /// One-based line number within the source
+    pub line: usize,
+    pub text: String,
+}
+
+impl KeyLine {
+    /// Number the lines of a source
+    pub fn numbered<I: IntoIterator<Item = (usize, String)>>(source: &str, lines: I) -> Vec<KeyLine> {
+        lines.into_iter().map(|(line, text)| KeyLine { source: source.to_owned(), line, text }).collect()
+    }
+}
+
+/// The ring which will actually be used
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Checked {
+    /// The lines of the ring, in order
+    pub lines: Vec<String>,
+    pub keys: Vec<PublicKey>,
+    /// Warnings about every problematic line
+    pub warnings: Vec<String>,
+    /// Lines left out of the ring
+    pub skipped: Vec<SkippedKey>,
+    /// How many of the skipped lines were unusable, rather than repeats
+    pub dropped: usize,
+}
+
+/// Why a key line cannot be used
+fn unusable(e: &KeyError) -> String {
+    match *e {
+        KeyError::WrongKeyType { ref got, .. } => format!("not an ed25519 key ({})", got),
+        KeyError::TorsionKey(_) => "key has a torsion component".to_owned(),
+        KeyError::WeakKey(_) => "key is the identity point, for which anyone can sign".to_owned(),
+        KeyError::InvalidKey(_) => "key is not a valid curve point".to_owned(),
+        ref e => format!("key does not parse ({})", e),
+    }
+}
+
+/// Check the lines of a ring, dropping any which cannot be used, and
+/// repeated keys unless `keep_repeats` is set
+pub fn check(lines: &[KeyLine], keep_repeats: bool) -> Checked {
+    let mut ret = Checked::default();
+    let mut first_seen: Vec<(PublicKey, &KeyLine)> = vec![];
+    for line in lines {
+        let reason = match PublicKey::parse_pk_line(&line.text) {
+            Ok(key) => match first_seen.iter().find(|(k, _)| *k == key) {
+                Some((_, first)) => {
+                    let reason = format!("repeats {} line {} ({})", first.source, first.line, key.fingerprint());
+                    if keep_repeats {
+                        ret.warnings.push(format!("{} line {}: {}", line.source, line.line, reason));
+                        ret.lines.push(line.text.clone());
+                        ret.keys.push(key);
+                        continue;
+                    }
+                    reason
+                }
+                None => {
+                    first_seen.push((key, line));
+                    ret.lines.push(line.text.clone());
+                    ret.keys.push(key);
+                    continue;
+                }
+            },
+            Err(e) => {
+                ret.dropped += 1;
+                unusable(&e)
+            }
+        };
+        ret.warnings.push(format!("{} line {}: {}", line.source, line.line, reason));
+        ret.skipped.push(SkippedKey {
+            source: line.source.clone(),
+            line: line.line,
+            key: line.text.clone(),
+            reason,
+        });
+    }
+    ret
+}
+
+impl Checked {
+    /// Print the warnings, and fail if any line was dropped without `force`
+    pub fn enforce(&self, force: bool) -> Result<(), Error> {
+        if !self.warnings.is_empty() {
+            eprintln!(
+                "warning: {} problem{} with the ring:",
+                self.warnings.len(),
+                if self.warnings.len() == 1 { "" } else { "s" },
+            );
+            for warning in &self.warnings {
+                eprintln!("  {}", warning);
+            }
+        }
+        if self.dropped > 0 && !force {
+            return Err(format!(
+                "{} key{} could not be used, so the ring is not the one given; \
+                 pass --force to use the remaining {} key{}",
+                self.dropped,
+                if self.dropped == 1 { "" } else { "s" },
+                self.keys.len(),
+                if self.keys.len() == 1 { "" } else { "s" },
+            )
+            .into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_1: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10";
+    const KEY_2: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana";
+    const TORSION: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAII0PQoSjaDulROj7qwNNsJ1cCa+sqlWsKs3e8nemW9J+ apoelstra-torsion";
+    const RSA: &str = "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAgQC7 someone";
+
+    fn lines() -> Vec<KeyLine> {
+        let mut ret = KeyLine::numbered("publicKeys", [(1, KEY_1.into()), (2, KEY_2.into())]);
+        ret.extend(KeyLine::numbered("ring.keys", [(3, KEY_1.into()), (5, TORSION.into()), (6, RSA.into())]));
+        ret
+    }
+
+    #[test]
+    fn proving() {
+        let checked = check(&lines(), false);
+        assert_eq!(checked.lines, [KEY_1, KEY_2]);
+        assert_eq!(checked.dropped, 2);
+        let reasons: Vec<_> = checked.skipped.iter().map(|s| (s.line, &s.reason[..])).collect();
+        assert_eq!(
+            reasons,
+            [
+                (3, "repeats publicKeys line 1 (SHA256:py4F80rPWegqoDRY42NJ6tNZO1tzaSltC9G7wQkb2dQ)"),
+                (5, "key has a torsion component"),
+                (6, "not an ed25519 key (ssh-rsa)"),
+            ],
+        );
+        assert_eq!(checked.warnings[1], "ring.keys line 5: key has a torsion component");
+        assert!(checked.enforce(false).is_err());
+        assert!(checked.enforce(true).is_ok());
+    }
+
+    #[test]
+    fn verifying_keeps_repeats() {
+        let checked = check(&lines()[..3], true);
+        assert_eq!(checked.lines, [KEY_1, KEY_2, KEY_1]);
+        assert_eq!(checked.warnings.len(), 1);
+        assert!(checked.skipped.is_empty());
+        assert!(checked.enforce(false).is_ok());
+    }
+}
@@ -0,0 +1,175 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! The `ring` subcommands, which edit keys files
+
+use ringsig::keyring::KeyRing;
+use ringsig::known_hosts;
+use serde_json::json;
+use std::io;
+use std::path::Path;
+
+use crate::config::Format;
+use crate::platform;
+use crate::report::comment;
+
+/// Load a ring file, treating a missing file as an empty ring
+fn load(path: &Path) -> Result<KeyRing, String> {
+    match platform::read_text(path) {
+        Ok(s) => Ok(KeyRing::parse(&s)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(KeyRing::new()),
+        Err(e) => Err(format!("reading ring file {}: {}", path.display(), e)),
+    }
+}
+
+fn save(path: &Path, ring: &KeyRing) -> Result<(), String> {
+    platform::write_atomic(path, ring.to_string().as_bytes())
+        .map_err(|e| format!("writing ring file {}: {}", path.display(), e))
+}
+
+fn describe(line: &str) -> String {
+    match ringsig::keys::PublicKey::parse_pk_line(line) {
+        Ok(pk) => format!("{}  {}", pk.fingerprint(), comment(line).unwrap_or("(no comment)")),
+        Err(_) => line.to_owned(),
+    }
+}
+
+/// Add the key lines from `source` to the ring file
+///
+/// Nothing is written unless every line can be added, or `force` is set.
+pub fn add(path: &Path, source: &str, lines: &[String], force: bool) -> Result<(), String> {
+    let mut ring = load(path)?;
+    let mut added = 0;
+    for line in lines {
+        let result = if force {
+            Ok(ring.add_line_unchecked(line))
+        } else {
+            ring.add_line(line)
+        };
+        match result {
+            Ok(true) => {
+                println!("added {}", describe(line));
+                added += 1;
+            }
+            Ok(false) => println!("already present: {}", describe(line)),
+            Err(e) => {
+                return Err(format!(
+                    "refusing to add key from {} ({}); pass --force to add it anyway: {}",
+                    source, e, line,
+                ))
+            }
+        }
+    }
+    if added > 0 {
+        save(path, &ring)?;
+    }
+    Ok(())
+}
+
+/// Which known_hosts entries to import
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct HostOptions<'a> {
+    /// Only import entries with a host matching this glob
+    pub host_filter: Option<&'a str>,
+    /// Import entries whose host names are hashed
+    pub include_hashed: bool,
+}
+
+/// Add the ed25519 host keys from a known_hosts file to the ring file,
+/// each with its hosts as the comment
+///
+/// Entries which are skipped are reported, except those not matching the
+/// host filter.
+pub fn import_known_hosts(path: &Path, known_hosts: &str, options: HostOptions) -> Result<(), String> {
+    let mut ring = load(path)?;
+    let (entries, malformed) = known_hosts::parse(known_hosts);
+    let mut added = 0;
+    let mut skipped = vec![];
+    for entry in &entries {
+        let skip = |why: &str| (entry.line_number, format!("{} ({} {})", why, entry.key_type, entry.hosts));
+        if let Some(ref marker) = entry.marker {
+            skipped.push(skip(&format!("@{} entry", marker)));
+        } else if entry.is_hashed() && !options.include_hashed {
+            skipped.push(skip("hashed host name; pass --include-hashed to import it"));
+        } else if entry.is_hashed() && options.host_filter.is_some() {
+            skipped.push(skip("hashed host name cannot match --host-filter"));
+        } else if options.host_filter.is_some_and(|filter| !entry.matches(filter)) {
+            // Filtered out as asked; not worth reporting
+        } else if entry.key_type != "ssh-ed25519" {
+            skipped.push(skip("not an ed25519 key"));
+        } else {
+            let line = entry.key_line();
+            match ring.add_line(&line) {
+                Ok(true) => {
+                    println!("added {}", describe(&line));
+                    added += 1;
+                }
+                Ok(false) => println!("already present: {}", describe(&line)),
+                Err(e) => skipped.push(skip(&format!("unusable key, {}", e))),
+            }
+        }
+    }
+    skipped.extend(malformed.into_iter().map(|(n, _)| (n, "malformed line".to_owned())));
+    skipped.sort();
+    for (n, why) in &skipped {
+        println!("skipped line {}: {}", n, why);
+    }
+    if added > 0 {
+        save(path, &ring)?;
+    }
+    Ok(())
+}
+
+/// Remove every key with a given fingerprint from the ring file
+pub fn remove(path: &Path, fingerprint: &str) -> Result<(), String> {
+    let mut ring = load(path)?;
+    match ring.remove_fingerprint(fingerprint) {
+        0 => Err(format!("no key in {} has fingerprint {}", path.display(), fingerprint)),
+        n => {
+            save(path, &ring)?;
+            println!("removed {} line{} with fingerprint {}", n, if n == 1 { "" } else { "s" }, fingerprint);
+            Ok(())
+        }
+    }
+}
+
+/// List the keys in a ring file
+pub fn list(path: &Path, format: Format) -> Result<(), String> {
+    let ring = platform::read_text(path).map_err(|e| format!("reading ring file {}: {}", path.display(), e))?;
+    let ring = KeyRing::parse(&ring);
+    match format {
+        Format::Text => {
+            for (_, line) in ring.entries() {
+                println!("{}", describe(line));
+            }
+            for (n, _, e) in ring.invalid() {
+                println!("line {}: unusable key ({})", n + 1, e);
+            }
+        }
+        Format::Json => {
+            let keys: Vec<_> = ring
+                .entries()
+                .map(|(pk, line)| json!({ "fingerprint": pk.fingerprint(), "comment": comment(line), "line": line }))
+                .collect();
+            let invalid: Vec<_> = ring
+                .invalid()
+                .map(|(n, line, e)| json!({ "lineNumber": n + 1, "line": line, "error": e.to_string() }))
+                .collect();
+            println!("{}", json!({ "keys": keys, "invalid": invalid }));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Encrypting confessions to age recipients
+//!
+//! A confession which should not be public yet can be encrypted to one or
+//! more age X25519 recipients, who can decrypt and verify it later. This
+//! requires the `age` feature; without it encrypting or decrypting fails
+//! with an explanatory error.
+
+/// Encrypt a confession to a comma-separated list of `age1...` recipients
+#[cfg(feature = "age")]
+pub fn encrypt(recipients: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Write;
+
+    let recipients = recipients
+        .split(',')
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .map(|r| r.parse::<age::x25519::Recipient>().map_err(|e| format!("parsing age recipient `{}`: {}", r, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let encryptor = age::Encryptor::with_recipients(recipients.iter().map(|r| r as &dyn age::Recipient))
+        .map_err(|e| format!("encrypting: {}", e))?;
+    let mut ret = vec![];
+    let mut writer = encryptor.wrap_output(&mut ret).map_err(|e| format!("encrypting: {}", e))?;
+    writer.write_all(plaintext).map_err(|e| format!("encrypting: {}", e))?;
+    writer.finish().map_err(|e| format!("encrypting: {}", e))?;
+    Ok(ret)
+}
+
+/// Decrypt a confession with the identities in an age identity file
+#[cfg(feature = "age")]
+pub fn decrypt(identity_file: &str, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    let identities = age::IdentityFile::from_buffer(identity_file.as_bytes())
+        .map_err(|e| format!("parsing identity file: {}", e))?
+        .into_identities()
+        .map_err(|e| format!("parsing identity file: {}", e))?;
+    let decryptor = age::Decryptor::new_buffered(ciphertext).map_err(|e| format!("reading age file: {}", e))?;
+    let mut reader = match decryptor.decrypt(identities.iter().map(|i| i.as_ref() as &dyn age::Identity)) {
+        Ok(reader) => reader,
+        Err(age::DecryptError::NoMatchingKeys) => {
+            return Err("the identity does not match any recipient the confession was encrypted to".into())
+        }
+        Err(e) => return Err(format!("decrypting: {}", e)),
+    };
+    let mut ret = vec![];
+    reader.read_to_end(&mut ret).map_err(|e| format!("decrypting: {}", e))?;
+    Ok(ret)
+}
+
+#[cfg(not(feature = "age"))]
+pub fn encrypt(_: &str, _: &[u8]) -> Result<Vec<u8>, String> {
+    Err("this build does not support encryption; rebuild with `--features age`".into())
+}
+
+#[cfg(not(feature = "age"))]
+pub fn decrypt(_: &str, _: &[u8]) -> Result<Vec<u8>, String> {
+    Err("this build does not support decryption; rebuild with `--features age`".into())
+}
+
+#[cfg(all(test, feature = "age"))]
+mod tests {
+    use super::*;
+
+    const IDENTITY: &str = include_str!("../../../testdata/age-identity.txt");
+    const RECIPIENT: &str = "age1h6ddxn98maayz7d42kv3s8dxjmrnv4mvc893py2xdwld6fxv04sqx4qt9r";
+    const OTHER_IDENTITY: &str = include_str!("../../../testdata/age-identity-2.txt");
+    const OTHER_RECIPIENT: &str = "age1ywaekwd7ypfnmx9hklsy099wx4zmla6xhj00yzlh82tskm5gpc9sqwgmfl";
+
+    #[test]
+    fn roundtrip() {
+        let sealed = encrypt(&format!("{}, {}", RECIPIENT, OTHER_RECIPIENT), b"{}").unwrap();
+        assert_eq!(decrypt(IDENTITY, &sealed).unwrap(), b"{}");
+        assert_eq!(decrypt(OTHER_IDENTITY, &sealed).unwrap(), b"{}");
+
+        let sealed = encrypt(RECIPIENT, b"{}").unwrap();
+        assert!(decrypt(OTHER_IDENTITY, &sealed).unwrap_err().contains("does not match"));
+        assert!(encrypt("age1nope", b"{}").is_err());
+        assert!(encrypt("", b"{}").is_err());
+    }
+}
@@ -0,0 +1,186 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Copy-pasteable snippets describing a verified confession
+//!
+//! The message and key comments are attacker-controlled, so everything
+//! taken from the confession is escaped for the target format.
+
+use bitcoin_hashes::hex::ToHex;
+use ringsig::format;
+
+use crate::report::{short_id, RingReport};
+
+/// Format of a shareable snippet
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Share {
+    Markdown,
+    Html,
+}
+
+impl Share {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "markdown" => Ok(Share::Markdown),
+            "html" => Ok(Share::Html),
+            other => Err(format!("unknown share format `{}` (expected `markdown` or `html`)", other)),
+        }
+    }
+
+    /// Render a snippet for a verified confession
+    pub fn render(self, message: &str, ring: &RingReport, proof: &[u8]) -> String {
+        match self {
+            Share::Markdown => markdown(message, ring, proof),
+            Share::Html => html(message, ring, proof),
+        }
+    }
+}
+
+/// Escape text so that Markdown renders it literally
+fn escape_markdown(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for ch in s.chars() {
+        if "\\`*_{}[]<>()#+-.!|~&".contains(ch) {
+            ret.push('\\');
+        }
+        ret.push(ch);
+    }
+    ret
+}
+
+/// Escape text for use in HTML element content or attribute values
+fn escape_html(s: &str) -> String {
+    let mut ret = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => ret += "&amp;",
+            '<' => ret += "&lt;",
+            '>' => ret += "&gt;",
+            '"' => ret += "&quot;",
+            '\'' => ret += "&#39;",
+            ch => ret.push(ch),
+        }
+    }
+    ret
+}
+
+fn header(ring: &RingReport) -> (String, usize) {
+    (ring.id.to_hex()[..16].to_owned(), ring.members.len())
+}
+
+fn markdown(message: &str, ring: &RingReport, proof: &[u8]) -> String {
+    let (id, size) = header(ring);
+    let mut ret = String::new();
+    for line in message.lines() {
+        ret += "> ";
+        ret += &escape_markdown(line);
+        ret += "\n";
+    }
+    ret += &format!("\nVerified: signed by one of the {} keys in ring `{}`:\n\n", size, id);
+    for m in &ring.members {
+        let comment = m.comment.as_deref().map(escape_markdown).unwrap_or_else(|| "(no comment)".into());
+        ret += &format!("- `{}` {}\n", short_id(&m.key), comment);
+    }
+    ret += "\n<details><summary>Proof</summary>\n\n```\n";
+    ret += &format::armor(format::PROOF_BEGIN, format::PROOF_END, proof);
+    ret += "```\n\n</details>\n";
+    ret
+}
+
+fn html(message: &str, ring: &RingReport, proof: &[u8]) -> String {
+    let (id, size) = header(ring);
+    let mut ret = String::from("<div class=\"credible-confession\">\n");
+    ret += &format!("<blockquote><pre>{}</pre></blockquote>\n", escape_html(message));
+    ret += &format!(
+        "<p>Verified: signed by one of the {} keys in ring <code>{}</code>:</p>\n<ul>\n",
+        size, id,
+    );
+    for m in &ring.members {
+        let comment = m.comment.as_deref().unwrap_or("(no comment)");
+        ret += &format!("<li><code>{}</code> {}</li>\n", escape_html(&short_id(&m.key)), escape_html(comment));
+    }
+    ret += "</ul>\n<details><summary>Proof</summary>\n<pre>";
+    ret += &format::armor(format::PROOF_BEGIN, format::PROOF_END, proof);
+    ret += "</pre>\n</details>\n</div>\n";
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ringsig::keys::PublicKey;
+
+    const MESSAGE: &str = "<script>alert(\"pwned\" & 'x')</script>\n# *not* a [heading](http://x)\n";
+
+    fn ring() -> RingReport {
+        let lines = [
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAyxLYuvSF14BJXejP+Qx6yLH1MXr/HGOcLZU55TodKS <b>bold</b>_x_",
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana",
+        ];
+        let lines: Vec<String> = lines.iter().map(|s| s.to_string()).collect();
+        let keys: Vec<_> = lines.iter().map(|l| PublicKey::parse_pk_line(l).unwrap()).collect();
+        RingReport::new(&lines, &keys)
+    }
+
+    #[test]
+    fn markdown_snapshot() {
+        assert_eq!(
+            Share::Markdown.render(MESSAGE, &ring(), &[0; 6]),
+            "> \\<script\\>alert\\(\"pwned\" \\& 'x'\\)\\</script\\>\n\
+             > \\# \\*not\\* a \\[heading\\]\\(http://x\\)\n\
+             \n\
+             Verified: signed by one of the 2 keys in ring `d0eada9fc3b7f07e`:\n\
+             \n\
+             - `k/ZdsVp1` \\<b\\>bold\\</b\\>\\_x\\_\n\
+             - `F+kgkEB9` apoelstra@sultana\n\
+             \n\
+             <details><summary>Proof</summary>\n\
+             \n\
+             ```\n\
+             -----BEGIN CREDIBLE CONFESSION-----\n\
+             AAAAAAAA\n\
+             =iPW7\n\
+             -----END CREDIBLE CONFESSION-----\n\
+             ```\n\
+             \n\
+             </details>\n",
+        );
+    }
+
+    #[test]
+    fn html_snapshot() {
+        assert_eq!(
+            Share::Html.render(MESSAGE, &ring(), &[0; 6]),
+            "<div class=\"credible-confession\">\n\
+             <blockquote><pre>&lt;script&gt;alert(&quot;pwned&quot; &amp; &#39;x&#39;)&lt;/script&gt;\n\
+             # *not* a [heading](http://x)\n\
+             </pre></blockquote>\n\
+             <p>Verified: signed by one of the 2 keys in ring <code>d0eada9fc3b7f07e</code>:</p>\n\
+             <ul>\n\
+             <li><code>k/ZdsVp1</code> &lt;b&gt;bold&lt;/b&gt;_x_</li>\n\
+             <li><code>F+kgkEB9</code> apoelstra@sultana</li>\n\
+             </ul>\n\
+             <details><summary>Proof</summary>\n\
+             <pre>-----BEGIN CREDIBLE CONFESSION-----\n\
+             AAAAAAAA\n\
+             =iPW7\n\
+             -----END CREDIBLE CONFESSION-----\n\
+             </pre>\n\
+             </details>\n\
+             </div>\n",
+        );
+    }
+}
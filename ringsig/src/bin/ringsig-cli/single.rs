@@ -0,0 +1,79 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Confessions over a ring of one key
+//!
+//! Such a ring hides nothing, so with `--sshsig-when-single` the confession
+//! carries an OpenSSH signature in its `sshsig` field instead of a proof.
+//! Anybody can check it by saving the field to `confession.sig` and running
+//!
+//! ```text
+//! ssh-keygen -Y verify -f allowed_signers -I <identity> -n cryptoconfessions \
+//!     -s confession.sig < message
+//! ```
+//!
+//! where the message file holds the exact message, with no trailing newline.
+
+use ringsig::armor::FromArmor;
+use ringsig::keys::PublicKey;
+use ringsig::sshsig::Signature;
+use serde_json::json;
+
+use crate::config::Format;
+use crate::error::Error;
+
+/// The SSHSIG namespace of confessions
+pub const NAMESPACE: &str = ringsig::sshsig::CONFESSION_NAMESPACE;
+
+/// Check an armored signature of `message` by the only key of the ring,
+/// returning that key
+pub fn verify(armor: &str, keys: &[PublicKey], message: &str) -> Result<PublicKey, Error> {
+    let sig = Signature::from_armor(armor).map_err(|e| format!("parsing sshsig: {}", e))?;
+    match *keys {
+        [key] if key == sig.public_key => {}
+        [_] => {
+            let signer = sig.public_key.fingerprint();
+            return Err(format!("sshsig was made by {}, which is not the ring's key", signer).into());
+        }
+        _ => return Err(format!("an sshsig needs a ring of exactly one key, not {}", keys.len()).into()),
+    }
+    sig.verify(NAMESPACE, message.as_bytes())?;
+    Ok(sig.public_key)
+}
+
+/// Render a successful verification
+pub fn report(format: Format, message: &str, pks: &[String], signer: &PublicKey) -> String {
+    match format {
+        Format::Text => format!(
+            "{}\n-----END OF MESSAGE-----\n\
+             SUCCESSFULLY VERIFIED SSHSIG SIGNATURE by {}, the only key in the ring (not anonymous)\n",
+            message,
+            signer.fingerprint(),
+        ),
+        Format::Json => {
+            let report = json!({
+                "verified": true,
+                "message": message,
+                "publicKeys": pks,
+                "sshsig": {
+                    "namespace": NAMESPACE,
+                    "signer": signer.fingerprint(),
+                },
+            });
+            format!("{}\n", report)
+        }
+    }
+}
@@ -0,0 +1,177 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Timestamping version 2 bundles
+//!
+//! `timestamp` asks an RFC 3161 time-stamp authority to attest to a bundle
+//! and appends the attestation to its `timestamps`; `verify
+//! --check-timestamps` checks them. Neither checks the authority's own
+//! signature; see [`ringsig::timestamping`] for what is checked.
+
+use bitcoin_hashes::hex::ToHex;
+use ringsig::attributes::format_time;
+use ringsig::bundle::MultiBundle;
+use ringsig::timestamping::{self, Rfc3161, TimestampProvider, RFC3161_REQUEST_TYPE};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::config::Format;
+use crate::error::{Error, EXIT_FAILURE};
+use crate::net::Fetcher;
+use crate::platform;
+
+/// The authority at `tsa`: an http(s) URL, or `file:<path>` for a reply
+/// saved beforehand from a request without a nonce
+pub fn provider<'a>(tsa: &'a str, fetcher: &'a Fetcher) -> Result<Box<dyn TimestampProvider + 'a>, String> {
+    match tsa.strip_prefix("file:") {
+        Some(path) => {
+            let reply = fs::read(path).map_err(|e| format!("reading {}: {}", path, e))?;
+            Ok(Box::new(Rfc3161::canned(reply)))
+        }
+        None => {
+            let transport = move |request: &[u8]| fetcher.post(tsa, RFC3161_REQUEST_TYPE, request);
+            Ok(Box::new(Rfc3161::new(transport).with_service(tsa)))
+        }
+    }
+}
+
+/// Timestamp the bundle at `path` in place
+pub fn stamp(path: &Path, tsa: &str, fetcher: &Fetcher, format: Format) -> Result<(), Error> {
+    let data = fs::read_to_string(path).map_err(|e| format!("reading {}: {}", path.display(), e))?;
+    let mut bundle = MultiBundle::from_json_str(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let provider = provider(tsa, fetcher)?;
+    let hash = bundle.bundle_hash();
+    let attestation = timestamping::timestamp(&*provider, &hash).map_err(|e| e.to_string())?;
+    let time = provider.verify(&hash, &attestation).map_err(|e| e.to_string())?;
+    let timestamps = bundle.timestamps.get_or_insert_with(Vec::new);
+    timestamps.push(attestation);
+    let count = timestamps.len();
+
+    let json = serde_json::to_string_pretty(&bundle).expect("serializing JSON") + "\n";
+    platform::write_atomic(path, json.as_bytes()).map_err(|e| format!("writing {}: {}", path.display(), e))?;
+    match format {
+        Format::Text => {
+            println!("timestamped {} at {} (it now has {})", path.display(), format_time(time), plural(count))
+        }
+        Format::Json => {
+            let report = json!({ "file": path, "bundleHash": hash.to_hex(), "time": time, "timestamps": count });
+            println!("{}", report)
+        }
+    }
+    Ok(())
+}
+
+fn plural(count: usize) -> String {
+    format!("{} timestamp{}", count, if count == 1 { "" } else { "s" })
+}
+
+/// The outcome of checking one timestamp
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Check {
+    pub kind: String,
+    pub service: Option<String>,
+    /// The time attested to
+    pub result: Result<u64, timestamping::Error>,
+}
+
+/// Check every timestamp of a bundle, which must not have been altered
+/// since it was read
+pub fn check(bundle: &MultiBundle) -> Vec<Check> {
+    let attestations = bundle.timestamps.iter().flatten();
+    attestations
+        .zip(bundle.verify_timestamps())
+        .map(|(attestation, result)| Check {
+            kind: attestation.kind.clone(),
+            service: attestation.service.clone(),
+            result,
+        })
+        .collect()
+}
+
+/// Render the checks as lines of text
+pub fn text(checks: &[Check]) -> String {
+    let mut ret = String::new();
+    for (n, check) in checks.iter().enumerate() {
+        let from = check.service.as_ref().map(|service| format!(" from {}", service)).unwrap_or_default();
+        match check.result {
+            Ok(time) => {
+                ret += &format!(
+                    "timestamp {}: {}{} ({}; the authority's signature was not checked)\n",
+                    n + 1,
+                    format_time(time),
+                    from,
+                    check.kind,
+                )
+            }
+            Err(ref e) => ret += &format!("timestamp {}{}: FAILED: {}\n", n + 1, from, e),
+        }
+    }
+    if checks.is_empty() {
+        ret += "the bundle has no timestamps\n";
+    }
+    ret
+}
+
+/// Render the checks as JSON
+pub fn json(checks: &[Check]) -> serde_json::Value {
+    let checks: Vec<_> = checks
+        .iter()
+        .map(|check| match check.result {
+            Ok(time) => json!({ "kind": check.kind, "service": check.service, "time": time, "verified": true }),
+            Err(ref e) => json!({
+                "kind": check.kind,
+                "service": check.service,
+                "verified": false,
+                "error": e.to_string(),
+                "code": e.code(),
+            }),
+        })
+        .collect();
+    checks.into()
+}
+
+/// The overall outcome: there must be a timestamp, and every one must pass
+pub fn outcome(checks: &[Check]) -> Result<(), Error> {
+    let failed = checks.iter().filter(|check| check.result.is_err()).count();
+    match (checks.len(), failed) {
+        (0, _) => Err(Error::new(EXIT_FAILURE, "the bundle has no timestamps")),
+        (_, 0) => Ok(()),
+        (total, failed) => Err(Error::new(EXIT_FAILURE, format!("{} of {} timestamps failed", failed, total))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outcomes() {
+        let good = Check { kind: "rfc3161".into(), service: Some("https://tsa.example".into()), result: Ok(0) };
+        let bad = Check { kind: "ots".into(), service: None, result: Err(timestamping::Error::UnknownKind("ots".into())) };
+        let checks = [good, bad];
+        assert_eq!(
+            text(&checks),
+            "timestamp 1: 1970-01-01T00:00:00Z from https://tsa.example \
+             (rfc3161; the authority's signature was not checked)\n\
+             timestamp 2: FAILED: unknown kind of timestamp `ots`\n",
+        );
+        assert_eq!(json(&checks)[1]["code"], "unknown_timestamp_kind");
+        assert!(outcome(&checks[..1]).is_ok());
+        assert_eq!(outcome(&checks).unwrap_err().message, "1 of 2 timestamps failed");
+        assert_eq!(outcome(&[]).unwrap_err().message, "the bundle has no timestamps");
+    }
+}
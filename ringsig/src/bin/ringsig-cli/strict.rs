@@ -0,0 +1,57 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! The extra checks made by `verify --strict`
+//!
+//! By default verification accepts anything which proves the statement.
+//! Strict mode additionally rejects, each with its own exit code:
+//!
+//!   * proofs with a non-canonical (unreduced) scalar, which are malleable;
+//!   * confessions with no `version` field, from before it was required;
+//!   * rings listing the same key more than once;
+//!   * JSON fields other than the ones this tool writes.
+//!
+//! Keys with a torsion component are rejected in every mode.
+
+use ringsig::keys::PublicKey;
+
+use crate::error::{
+    Error, EXIT_STRICT_DUPLICATE_KEY, EXIT_STRICT_LEGACY, EXIT_STRICT_NON_CANONICAL, EXIT_STRICT_UNKNOWN_FIELD,
+};
+
+/// The fields of a confession's JSON object
+const KNOWN_FIELDS: [&str; 8] =
+    ["version", "publicKeys", "message", "proof", "scope", "keyImage", "attributes", "skippedKeys"];
+
+/// Check a confession, given as JSON, with its parsed ring and proof
+pub fn check(json: &serde_json::Value, keys: &[PublicKey], proof: &[u8]) -> Result<(), Error> {
+    let object = json.as_object().ok_or("confession is not a JSON object")?;
+    if let Some(field) = object.keys().find(|field| !KNOWN_FIELDS.contains(&&field[..])) {
+        return Err(Error::new(EXIT_STRICT_UNKNOWN_FIELD, format!("strict: unknown field `{}`", field)));
+    }
+    if !object.contains_key("version") {
+        return Err(Error::new(EXIT_STRICT_LEGACY, "strict: confession has no version"));
+    }
+    for (n, key) in keys.iter().enumerate() {
+        if keys[..n].contains(key) {
+            return Err(Error::new(
+                EXIT_STRICT_DUPLICATE_KEY,
+                format!("strict: key {} appears more than once in the ring", key.fingerprint()),
+            ));
+        }
+    }
+    ringsig::check_canonical(proof).map_err(|e| Error::new(EXIT_STRICT_NON_CANONICAL, format!("strict: {}", e)))
+}
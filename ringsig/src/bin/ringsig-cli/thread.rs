@@ -0,0 +1,136 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! The `thread verify` subcommand
+//!
+//! This verifies every confession in a directory as one thread of replies,
+//! made with `prove --reply-to`; see [`ringsig::thread`]. Files which are not
+//! version 1 confessions are skipped with a warning.
+
+use bitcoin_hashes::hex::ToHex;
+use ringsig::bundle::Confession;
+use ringsig::thread::{self, Link, ThreadReport};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+
+/// Length of the abbreviated hashes in text output
+const SHORT_HASH_LEN: usize = 16;
+
+/// A checked thread, with the file each confession came from
+pub struct Thread {
+    files: Vec<String>,
+    report: ThreadReport,
+}
+
+impl Thread {
+    /// Read and verify every confession in `dir`
+    pub fn dir(dir: &Path) -> Result<Self, Error> {
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .map_err(|e| format!("reading {}: {}", dir.display(), e))?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        let mut files = vec![];
+        let mut confessions = vec![];
+        for path in paths {
+            let data = fs::read_to_string(&path).map_err(|e| format!("reading {}: {}", path.display(), e))?;
+            match Confession::from_json_str(&data) {
+                Ok(confession) => {
+                    files.push(path.file_name().unwrap().to_string_lossy().into_owned());
+                    confessions.push(confession);
+                }
+                Err(e) => eprintln!("warning: skipping {}: {}", path.display(), e),
+            }
+        }
+        Ok(Thread { report: thread::verify_thread(&confessions), files })
+    }
+
+    pub fn text(&self) -> String {
+        let mut ret = String::new();
+        for (file, entry) in self.files.iter().zip(&self.report.entries) {
+            let verified = match entry.verified {
+                Ok(()) => "VERIFIED".to_owned(),
+                Err(ref e) => format!("FAILED: {}", e),
+            };
+            let link = match (&entry.link, entry.depth) {
+                (Link::Root, _) => "thread root".to_owned(),
+                (Link::Parent(parent), Some(depth)) => format!("replies to {} (depth {})", self.files[*parent], depth),
+                (Link::Parent(parent), None) => format!("replies to {} (whose thread is broken)", self.files[*parent]),
+                (Link::MissingParent(parent), _) => {
+                    format!("BROKEN: replies to {}..., which is missing", &parent.to_hex()[..SHORT_HASH_LEN])
+                }
+                (Link::Cycle, _) => "BROKEN: part of a cycle of replies".to_owned(),
+            };
+            ret += &format!("{}: {}, {}\n", file, verified, link);
+        }
+        match self.report.breaks.len() {
+            0 => ret += &format!("thread intact: {} confessions\n", self.files.len()),
+            1 => ret += "thread broken in 1 place\n",
+            n => ret += &format!("thread broken in {} places\n", n),
+        }
+        ret
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        let entries: Vec<_> = self
+            .files
+            .iter()
+            .zip(&self.report.entries)
+            .map(|(file, entry)| {
+                let mut ret = json!({
+                    "file": file,
+                    "hash": entry.hash.to_hex(),
+                    "verified": entry.verified.is_ok(),
+                    "depth": entry.depth,
+                });
+                if let Err(ref e) = entry.verified {
+                    ret["error"] = json!(e);
+                }
+                match entry.link {
+                    Link::Root => ret["link"] = json!("root"),
+                    Link::Parent(parent) => {
+                        ret["link"] = json!("reply");
+                        ret["parent"] = json!(self.files[parent]);
+                    }
+                    Link::MissingParent(parent) => {
+                        ret["link"] = json!("missing-parent");
+                        ret["parentHash"] = json!(parent.to_hex());
+                    }
+                    Link::Cycle => ret["link"] = json!("cycle"),
+                }
+                ret
+            })
+            .collect();
+        json!({
+            "intact": self.report.is_intact(),
+            "breaks": self.report.breaks.len(),
+            "confessions": entries,
+        })
+    }
+
+    /// Fail unless the thread is intact
+    pub fn outcome(&self) -> Result<(), Error> {
+        match self.report.breaks.len() {
+            0 => Ok(()),
+            n => Err(format!("the thread is broken in {} place(s)", n).into()),
+        }
+    }
+}
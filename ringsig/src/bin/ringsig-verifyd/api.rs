@@ -0,0 +1,163 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! The HTTP API, independent of the server which carries it
+//!
+//! Every response body is JSON. Failures have the form
+//!
+//! ```json
+//! { "error": { "code": "bad_json", "message": "..." } }
+//! ```
+//!
+//! where the code is one of the library's error codes, or for problems with
+//! the request itself `not_found`, `method_not_allowed`, `too_large` or
+//! `bad_request`. A bundle which parses but does not verify is not a
+//! failure: its report says which entries failed, and why.
+
+use bitcoin_hashes::hex::ToHex;
+use ringsig::bundle::{self, MultiBundle};
+use ringsig::keys::PublicKey;
+use serde::Deserialize;
+use serde_json::json;
+
+/// A response: an HTTP status and a JSON body
+#[derive(Clone, PartialEq, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: serde_json::Value,
+}
+
+/// A failure response
+pub fn error(status: u16, code: &str, message: &str) -> Response {
+    Response { status, body: json!({ "error": { "code": code, "message": message } }) }
+}
+
+/// The body of a `/ring/inspect` request
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InspectRequest {
+    #[serde(rename = "publicKeys")]
+    public_keys: Vec<String>,
+}
+
+/// Route a request
+pub fn handle(method: &str, path: &str, body: &[u8]) -> Response {
+    match (method, path) {
+        ("GET", "/healthz") => {
+            Response { status: 200, body: json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }) }
+        }
+        ("POST", "/verify") => verify(body),
+        ("POST", "/ring/inspect") => inspect(body),
+        (_, "/healthz") => error(405, "method_not_allowed", "use GET"),
+        (_, "/verify" | "/ring/inspect") => error(405, "method_not_allowed", "use POST"),
+        _ => error(404, "not_found", "no such endpoint"),
+    }
+}
+
+/// Verify a version 2 bundle
+///
+/// Entries must carry their rings; keys are never fetched or read from
+/// anywhere else.
+fn verify(body: &[u8]) -> Response {
+    let body = match std::str::from_utf8(body) {
+        Ok(body) => body,
+        Err(_) => return error(400, "bad_request", "body is not UTF-8"),
+    };
+    match MultiBundle::from_json_str(body) {
+        Ok(bundle) => Response { status: 200, body: bundle::report(&bundle.verify()) },
+        Err(e) => error(400, e.code(), &e.to_string()),
+    }
+}
+
+/// Report the members of a ring, and any keys which cannot be used
+fn inspect(body: &[u8]) -> Response {
+    let request: InspectRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return error(400, "bad_json", &format!("parsing request: {}", e)),
+    };
+    let mut members: Vec<(usize, PublicKey)> = vec![];
+    let mut problems = vec![];
+    for (index, line) in request.public_keys.iter().enumerate() {
+        match PublicKey::parse_pk_line(line) {
+            Ok(key) => match members.iter().find(|(_, k)| *k == key) {
+                Some((first, _)) => problems.push(json!({
+                    "index": index,
+                    "code": "duplicate_key",
+                    "message": format!("repeats the key at index {}", first),
+                })),
+                None => members.push((index, key)),
+            },
+            Err(e) => problems.push(json!({
+                "index": index,
+                "code": e.code(),
+                "message": e.to_string(),
+            })),
+        }
+    }
+    let keys: Vec<PublicKey> = members.iter().map(|&(_, key)| key).collect();
+    let members: Vec<_> =
+        members.iter().map(|(index, key)| json!({ "index": index, "fingerprint": key.fingerprint() })).collect();
+    let report = json!({
+        "id": ringsig::ring_id(&keys).to_hex(),
+        "size": keys.len(),
+        "members": members,
+        "problems": problems,
+    });
+    Response { status: 200, body: report }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10";
+
+    #[test]
+    fn routing() {
+        assert_eq!(handle("GET", "/healthz", b"").status, 200);
+        assert_eq!(handle("POST", "/healthz", b"").status, 405);
+        assert_eq!(handle("GET", "/verify", b"").status, 405);
+        let response = handle("GET", "/", b"");
+        assert_eq!(response.status, 404);
+        assert_eq!(response.body["error"]["code"], "not_found");
+    }
+
+    #[test]
+    fn bad_bundles() {
+        let response = handle("POST", "/verify", b"{");
+        assert_eq!(response.status, 400);
+        assert_eq!(response.body["error"]["code"], "bad_json");
+        let response = handle("POST", "/verify", br#"{"version": 3, "proofs": []}"#);
+        assert_eq!(response.body["error"]["code"], "bad_version");
+        let response = handle("POST", "/verify", br#"{"version": 2, "proofs": []}"#);
+        assert_eq!(response.body["error"]["code"], "no_proofs");
+    }
+
+    #[test]
+    fn inspection() {
+        let body = json!({ "publicKeys": [KEY, "ssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAgQC7", KEY] });
+        let response = handle("POST", "/ring/inspect", body.to_string().as_bytes());
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["size"], 1);
+        assert_eq!(response.body["members"][0]["fingerprint"], "SHA256:py4F80rPWegqoDRY42NJ6tNZO1tzaSltC9G7wQkb2dQ");
+        assert_eq!(response.body["problems"][0]["code"], "wrong_key_type");
+        assert_eq!(response.body["problems"][1]["index"], 2);
+        assert_eq!(response.body["problems"][1]["message"], "repeats the key at index 0");
+
+        let response = handle("POST", "/ring/inspect", br#"{"keys": []}"#);
+        assert_eq!(response.body["error"]["code"], "bad_json");
+    }
+}
@@ -0,0 +1,143 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! `ringsig-verifyd`: verifying confessions over HTTP
+//!
+//! A small service for pages which want to check confessions without
+//! shipping the wasm build. It serves
+//!
+//!   * `POST /verify`, taking a version 2 bundle and returning its report;
+//!   * `POST /ring/inspect`, taking `{"publicKeys": [...]}` and describing
+//!     the ring, including any keys which cannot be used;
+//!   * `GET /healthz`.
+//!
+//! It never touches the filesystem or the network beyond its own socket, and
+//! refuses request bodies over a size limit. See [`api`] for the responses.
+
+mod api;
+
+use std::io::{Read, Write};
+use std::sync::Arc;
+use std::{env, process, thread};
+
+use tiny_http::{Header, Request, Server};
+
+/// Address to listen on when none is given
+const DEFAULT_LISTEN: &str = "127.0.0.1:8080";
+/// Largest request body accepted when no limit is given
+const DEFAULT_MAX_BODY: usize = 1 << 20;
+/// Requests handled at once when not given
+const DEFAULT_THREADS: usize = 4;
+
+struct Options {
+    listen: String,
+    max_body: usize,
+    threads: usize,
+}
+
+fn usage() -> ! {
+    let name = env::args().next().unwrap();
+    eprintln!("Usage: {} [--listen <addr:port>] [--max-body <bytes>] [--threads <n>]", name);
+    eprintln!();
+    eprintln!("Serves POST /verify, POST /ring/inspect and GET /healthz.");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --listen <addr:port>  address to listen on (default {}); port 0 picks any", DEFAULT_LISTEN);
+    eprintln!("  --max-body <bytes>    refuse larger request bodies (default {})", DEFAULT_MAX_BODY);
+    eprintln!("  --threads <n>         requests to handle at once (default {})", DEFAULT_THREADS);
+    process::exit(2);
+}
+
+fn parse_args() -> Options {
+    let mut ret = Options { listen: DEFAULT_LISTEN.to_owned(), max_body: DEFAULT_MAX_BODY, threads: DEFAULT_THREADS };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| usage());
+        match &arg[..] {
+            "--listen" => ret.listen = value,
+            "--max-body" => ret.max_body = value.parse().unwrap_or_else(|_| usage()),
+            "--threads" => ret.threads = value.parse().ok().filter(|&n| n > 0).unwrap_or_else(|| usage()),
+            _ => usage(),
+        }
+    }
+    ret
+}
+
+/// Read the body of a request, up to `max` bytes
+fn read_body(request: &mut Request, max: usize) -> Result<Vec<u8>, api::Response> {
+    let too_large = || api::error(413, "too_large", &format!("request bodies are limited to {} bytes", max));
+    if request.body_length().is_some_and(|len| len > max) {
+        return Err(too_large());
+    }
+    // The length may be absent, e.g. for chunked bodies, so enforce it here too
+    let mut body = vec![];
+    request
+        .as_reader()
+        .take(max as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| api::error(400, "bad_request", &format!("reading request: {}", e)))?;
+    if body.len() > max {
+        return Err(too_large());
+    }
+    Ok(body)
+}
+
+fn serve(mut request: Request, max_body: usize) {
+    let response = match read_body(&mut request, max_body) {
+        Ok(body) => {
+            let path = request.url().split('?').next().unwrap_or("");
+            api::handle(request.method().as_str(), path, &body)
+        }
+        Err(response) => response,
+    };
+    let content_type = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    let reply = tiny_http::Response::from_string(format!("{}\n", response.body))
+        .with_status_code(response.status)
+        .with_header(content_type);
+    // The client may have gone away; there is nobody to tell
+    let _ = request.respond(reply);
+}
+
+fn main() {
+    let options = parse_args();
+    let server = match Server::http(&options.listen) {
+        Ok(server) => Arc::new(server),
+        Err(e) => {
+            eprintln!("listening on {}: {}", options.listen, e);
+            process::exit(1);
+        }
+    };
+    match server.server_addr().to_ip() {
+        Some(addr) => println!("listening on http://{}", addr),
+        None => println!("listening on {}", options.listen),
+    }
+    let _ = std::io::stdout().flush();
+
+    let workers: Vec<_> = (0..options.threads)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let max_body = options.max_body;
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    serve(request, max_body);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("server thread panicked");
+    }
+}
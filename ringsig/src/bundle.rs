@@ -0,0 +1,922 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Confessions and Bundles
+//!
+//! This module defines the JSON formats in which proofs are exchanged. They
+//! are distinguished by their `version` field.
+//!
+//! A version 1 [`Confession`] carries a single proof:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "publicKeys": ["ssh-ed25519 AAAA... comment", "..."],
+//!   "message": "the message",
+//!   "proof": "hex",
+//!   "scope": "linkable proofs only",
+//!   "keyImage": "linkable proofs only, hex",
+//!   "attributes": { "timestamp": 1700000000, "expires": 1800000000, "replyTo": "hex" },
+//!   "skippedKeys": [{ "source": "ring.keys", "line": 3, "key": "...", "reason": "..." }],
+//!   "sshsig": "an OpenSSH signature, instead of a proof, for a ring of one key"
+//! }
+//! ```
+//!
+//! Only `message` is required; a confession without a `proof` is ready to
+//! be proven. Confessions written before versioning have no `version`
+//! field, and are read as version 1. Fields which are not understood are
+//! ignored, so that older readers can read newer confessions.
+//!
+//! A version 2 [`MultiBundle`] carries any number of proofs, for statements
+//! made by several rings or several statements by one ring:
+//!
+//! ```json
+//! {
+//!   "version": 2,
+//!   "message": "default message",
+//!   "publicKeys": ["default ring", "..."],
+//!   "proofs": [
+//!     { "proof": "hex", "context": "optional label" },
+//!     { "proof": "hex", "message": "its own message", "publicKeys": ["..."] }
+//!   ],
+//!   "timestamps": [{ "kind": "rfc3161", "token": "hex", "service": "optional" }]
+//! }
+//! ```
+//!
+//! Each entry's `message` and `publicKeys` default to the top-level ones,
+//! which may be omitted if every entry has its own. Linkable entries also
+//! carry `scope` and `keyImage`, as in version 1 confessions. The optional
+//! `timestamps` attest that the rest of the bundle existed at some time;
+//! see [`crate::timestamping`].
+//!
+//! Both formats are described by JSON Schemas in `schema/`, generated from
+//! the types here; see [`schema_json`]. They are committed, and a test built
+//! with the `schema` feature checks that they are up to date. After changing
+//! the types, regenerate them with
+//! `RINGSIG_UPDATE_SCHEMAS=1 cargo test --features schema schemas`.
+
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use bitcoin_hashes::Hash;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fmt;
+
+use crate::attributes::SignedAttributes;
+use crate::hashes::{BundleHash, ParamsHash};
+use crate::keys::{self, PublicKey, SecretKey};
+use crate::timestamping::{self, Attestation};
+use crate::Proof;
+
+/// The version number of confessions holding a single proof
+pub const CONFESSION_VERSION: usize = 1;
+/// The version number of bundles with several proofs
+pub const MULTI_VERSION: usize = 2;
+
+/// Problems with a bundle or one of its entries
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The JSON did not parse, or did not match the schema
+    Json(String),
+    /// The version was not [`MULTI_VERSION`]
+    Version(usize),
+    /// The version of a confession was not [`CONFESSION_VERSION`]
+    ConfessionVersion(usize),
+    /// A confession has a field which version 2 bundles cannot represent
+    NotMigratable(&'static str),
+    /// The bundle has no entries
+    NoProofs,
+    /// Neither the entry nor the bundle has a message
+    NoMessage,
+    /// Neither the entry nor the bundle has a ring
+    NoPublicKeys,
+    /// A key in the ring did not parse
+    BadKey(String, keys::Error),
+    /// The proof or key image was not hex of the right length
+    BadHex,
+    /// An entry has only one of `scope` and `keyImage`
+    PartialLink,
+    /// The proof did not verify
+    Proof(crate::VerifyError),
+    /// No proof could be made
+    Prove(crate::ProveError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Json(ref e) => write!(f, "parsing JSON: {}", e),
+            Error::Version(v) => write!(f, "bundle version was {}, not {}", v, MULTI_VERSION),
+            Error::ConfessionVersion(v) => {
+                write!(f, "confession version was {}, not {}", v, CONFESSION_VERSION)
+            }
+            Error::NotMigratable(field) => write!(f, "version 2 bundles cannot represent `{}`", field),
+            Error::NoProofs => f.write_str("bundle contains no proofs"),
+            Error::NoMessage => f.write_str("no message, and the bundle has no default"),
+            Error::NoPublicKeys => f.write_str("no publicKeys, and the bundle has no default"),
+            Error::BadKey(ref line, ref e) => write!(f, "parsing key {}: {}", line, e),
+            Error::BadHex => f.write_str("proof or key image is not valid hex"),
+            Error::PartialLink => f.write_str("has only one of scope and keyImage"),
+            Error::Proof(ref e) => fmt::Display::fmt(e, f),
+            Error::Prove(ref e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Error::Json(_) => "bad_json",
+            Error::Version(_) | Error::ConfessionVersion(_) => "bad_version",
+            Error::NotMigratable(_) => "not_migratable",
+            Error::NoProofs => "no_proofs",
+            Error::NoMessage => "no_message",
+            Error::NoPublicKeys => "no_public_keys",
+            Error::BadKey(_, ref e) => e.code(),
+            Error::BadHex => "bad_hex",
+            Error::PartialLink => "partial_link",
+            Error::Proof(ref e) => e.code(),
+            Error::Prove(ref e) => e.code(),
+        }
+    }
+}
+
+impl From<crate::VerifyError> for Error {
+    fn from(e: crate::VerifyError) -> Self {
+        Error::Proof(e)
+    }
+}
+
+impl From<crate::ProveError> for Error {
+    fn from(e: crate::ProveError) -> Self {
+        Error::Prove(e)
+    }
+}
+
+/// A problem with one field of a confession or bundle
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct FieldError {
+    /// Where the problem is, e.g. `publicKeys[2]` or `proofs[0].keyImage`;
+    /// empty for the whole document
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Collects [`FieldError`]s
+struct Problems(Vec<FieldError>);
+
+impl Problems {
+    fn push<S: Into<String>>(&mut self, path: String, message: S) {
+        self.0.push(FieldError { path, message: message.into() })
+    }
+
+    fn check_keys(&mut self, path: &str, keys: &[String]) {
+        for (n, line) in keys.iter().enumerate() {
+            if let Err(e) = PublicKey::parse_pk_line(line) {
+                self.push(format!("{}[{}]", path, n), e.to_string());
+            }
+        }
+    }
+
+    /// Check the proof and link fields shared by confessions and entries
+    fn check_proof(&mut self, prefix: &str, proof: Option<&str>, scope: Option<&str>, key_image: Option<&str>) {
+        if let Some(proof) = proof {
+            match Vec::<u8>::from_hex(proof) {
+                Ok(bytes) if !bytes.is_empty() && bytes.len() % 32 == 0 => {}
+                Ok(_) => self.push(format!("{}proof", prefix), "not a whole number of 32-byte scalars"),
+                Err(_) => self.push(format!("{}proof", prefix), "not valid hex"),
+            }
+        }
+        if let Some(key_image) = key_image {
+            if <[u8; 32]>::from_hex(key_image).is_err() {
+                self.push(format!("{}keyImage", prefix), "not 32 bytes of hex");
+            }
+        }
+        match (scope, key_image) {
+            (Some(_), None) => self.push(format!("{}keyImage", prefix), "required when scope is given"),
+            (None, Some(_)) => self.push(format!("{}scope", prefix), "required when keyImage is given"),
+            _ => {}
+        }
+    }
+}
+
+/// The JSON Schema of version 1 confessions
+const CONFESSION_SCHEMA: &str = include_str!("../schema/confession-v1.schema.json");
+/// The JSON Schema of version 2 bundles
+const BUNDLE_SCHEMA: &str = include_str!("../schema/bundle-v2.schema.json");
+
+/// The JSON Schema of confessions or bundles of the given version, or `None`
+/// for unknown versions
+pub fn schema_json(version: usize) -> Option<&'static str> {
+    match version {
+        CONFESSION_VERSION => Some(CONFESSION_SCHEMA),
+        MULTI_VERSION => Some(BUNDLE_SCHEMA),
+        _ => None,
+    }
+}
+
+/// Check that `json` is a confession or bundle of the given version,
+/// returning every field which does not conform to its schema
+///
+/// If it conforms, the problems found by [`Confession::validate`] or
+/// [`MultiBundle::validate`] are returned instead. Fails only if `json` is
+/// not JSON at all, or the version is unknown.
+pub fn validate(json: &str, version: usize) -> Result<Vec<FieldError>, Error> {
+    let schema = match schema_json(version) {
+        Some(schema) => serde_json::from_str(schema).expect("parsing bundled schema"),
+        None => return Err(Error::Version(version)),
+    };
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| Error::Json(e.to_string()))?;
+    let problems = crate::schema::check(&schema, &value);
+    if !problems.is_empty() {
+        return Ok(problems);
+    }
+    match version {
+        CONFESSION_VERSION => Ok(Confession::from_json_value(value)?.validate()),
+        _ => Ok(MultiBundle::from_json_value(value)?.validate()),
+    }
+}
+
+/// A key left out of the ring when proving
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SkippedKey {
+    /// A file name, URL, or `publicKeys`
+    pub source: String,
+    /// One-based line number within the source
+    pub line: usize,
+    pub key: String,
+    pub reason: String,
+}
+
+/// A version 1 confession, holding at most one proof
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Confession {
+    /// Confessions from before versioning have none; they are version 1
+    #[serde(default = "legacy_version")]
+    #[cfg_attr(feature = "schema", schemars(range(min = 1, max = 1)))]
+    pub version: usize,
+    #[serde(rename = "publicKeys", default)]
+    pub public_keys: Vec<String>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof: Option<String>,
+    /// For linkable proofs, the scope in which they link
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// For linkable proofs, the signer's key image in the scope
+    #[serde(rename = "keyImage", skip_serializing_if = "Option::is_none")]
+    pub key_image: Option<String>,
+    /// Signing and expiry times, and the confession replied to, which the
+    /// proof commits to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<SignedAttributes>,
+    /// Lines left out of the ring when proving; not covered by the proof
+    #[serde(rename = "skippedKeys", skip_serializing_if = "Option::is_none")]
+    pub skipped_keys: Option<Vec<SkippedKey>>,
+    /// An OpenSSH signature, made instead of a proof for a ring of one key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sshsig: Option<String>,
+}
+
+fn legacy_version() -> usize {
+    CONFESSION_VERSION
+}
+
+impl Confession {
+    /// A confession of `message` over a ring, not yet proven
+    pub fn new(public_keys: Vec<String>, message: String) -> Self {
+        Confession {
+            version: CONFESSION_VERSION,
+            public_keys,
+            message,
+            proof: None,
+            scope: None,
+            key_image: None,
+            attributes: None,
+            skipped_keys: None,
+            sshsig: None,
+        }
+    }
+
+    /// Parse a confession from JSON, reading an unversioned one as version 1
+    pub fn from_json_str(s: &str) -> Result<Self, Error> {
+        Self::from_json_value(serde_json::from_str(s).map_err(|e| Error::Json(e.to_string()))?)
+    }
+
+    /// Parse a confession from already-parsed JSON
+    pub fn from_json_value(value: serde_json::Value) -> Result<Self, Error> {
+        let ret: Confession = serde_json::from_value(value).map_err(|e| Error::Json(e.to_string()))?;
+        if ret.version != CONFESSION_VERSION {
+            return Err(Error::ConfessionVersion(ret.version));
+        }
+        Ok(ret)
+    }
+
+    /// Serialize the confession as JSON
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(self).expect("serializing JSON")
+    }
+
+    /// Make this a reply to the confession with hash `parent`, before it is
+    /// proven; see [`crate::thread`]
+    pub fn reply_to(&mut self, parent: &BundleHash) {
+        self.attributes.get_or_insert_with(SignedAttributes::default).reply_to = Some(*parent);
+    }
+
+    /// The hash of the confession this replies to, if any
+    pub fn parent(&self) -> Option<BundleHash> {
+        self.attributes.and_then(|attributes| attributes.reply_to)
+    }
+
+    /// The ring, parsed from `publicKeys`
+    pub fn keys(&self) -> Result<Vec<PublicKey>, Error> {
+        parse_keys(&self.public_keys)
+    }
+
+    /// The bytes the proof signs: the message, and the attributes if any
+    pub fn signed_message(&self) -> Vec<u8> {
+        self.attributes.unwrap_or_default().signed_message(self.message.as_bytes())
+    }
+
+    /// Prove the confession with `sk`, replacing any proof it had
+    ///
+    /// If it has a `scope`, the proof is linkable and its key image is
+    /// filled in too. The proof covers the `attributes`, so they must be set
+    /// first.
+    pub fn prove(&mut self, sk: &SecretKey) -> Result<(), Error> {
+        self.prove_with_progress(sk, |_, _| {})
+    }
+
+    /// Prove the confession as [`Confession::prove`] does, reporting progress
+    /// as [`crate::prove_with_progress`] does
+    pub fn prove_with_progress<F: FnMut(usize, usize)>(&mut self, sk: &SecretKey, progress: F) -> Result<(), Error> {
+        let keys = self.keys()?;
+        let message = self.signed_message();
+        let (proof, key_image) = match self.scope {
+            Some(ref scope) => {
+                let (proof, key_image) = crate::prove_linkable_with_progress(&keys, &message, scope.as_bytes(), sk, progress)?;
+                (proof, Some(key_image.to_hex()))
+            }
+            None => (crate::prove_with_progress(&keys, &message, sk, progress)?, None),
+        };
+        self.proof = Some(proof.to_hex());
+        self.key_image = key_image;
+        Ok(())
+    }
+
+    /// Verify the proof over the ring and message, and the attributes if any
+    ///
+    /// Whether the confession has expired is for the caller to check, with
+    /// [`SignedAttributes::check_valid_at`].
+    pub fn verify(&self) -> Result<(), Error> {
+        self.verify_with_progress(|_, _| {})
+    }
+
+    /// Verify the proof as [`Confession::verify`] does, reporting progress
+    /// as [`crate::verify_with_progress`] does
+    pub fn verify_with_progress<F: FnMut(usize, usize)>(&self, progress: F) -> Result<(), Error> {
+        let proof = self.proof.as_deref().ok_or(Error::NoProofs)?;
+        let link = (self.scope.as_deref(), self.key_image.as_deref());
+        verify_proof(proof, link, &self.keys()?, &self.signed_message(), progress)
+    }
+
+    /// The params hash of the ring and signed message, for comparing with
+    /// another verifier's; see [`crate::params_hash`]
+    pub fn params_hash(&self) -> Result<ParamsHash, Error> {
+        Ok(crate::params_hash(&self.keys()?, &self.signed_message())?)
+    }
+
+    /// The hash which revocations and replies refer to, of the confession's
+    /// JSON as serialized by [`Confession::to_json_string`], so that it does
+    /// not depend on the whitespace or field order of the file it was read
+    /// from
+    pub fn bundle_hash(&self) -> BundleHash {
+        BundleHash::hash(self.to_json_string().as_bytes())
+    }
+
+    /// Check every field, without verifying the proof, returning all the
+    /// problems found
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut problems = Problems(vec![]);
+        if self.version != CONFESSION_VERSION {
+            problems.push("version".into(), format!("unsupported version {}", self.version));
+        }
+        problems.check_keys("publicKeys", &self.public_keys);
+        problems.check_proof("", self.proof.as_deref(), self.scope.as_deref(), self.key_image.as_deref());
+        if let Some(Err(e)) = self.attributes.map(|attributes| attributes.check_consistent()) {
+            problems.push("attributes".into(), e.to_string());
+        }
+        if self.sshsig.is_some() && self.proof.is_some() {
+            problems.push("sshsig".into(), "only one of proof and sshsig may be given");
+        }
+        problems.0
+    }
+
+    /// Convert a proven confession into an equivalent version 2 bundle of one
+    /// entry
+    pub fn into_bundle(self) -> Result<MultiBundle, Error> {
+        if self.attributes.is_some() {
+            return Err(Error::NotMigratable("attributes"));
+        }
+        if self.sshsig.is_some() {
+            return Err(Error::NotMigratable("sshsig"));
+        }
+        let entry = Entry {
+            proof: self.proof.ok_or(Error::NoProofs)?,
+            scope: self.scope,
+            key_image: self.key_image,
+            ..Entry::default()
+        };
+        Ok(MultiBundle {
+            version: MULTI_VERSION,
+            message: Some(self.message),
+            public_keys: Some(self.public_keys),
+            proofs: vec![entry],
+            timestamps: None,
+        })
+    }
+}
+
+/// One proof in a bundle
+#[derive(Clone, PartialEq, Eq, Debug, Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Entry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(rename = "publicKeys", skip_serializing_if = "Option::is_none")]
+    pub public_keys: Option<Vec<String>>,
+    pub proof: String,
+    /// A free-form label for the entry, e.g. which statement it is
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(rename = "keyImage", skip_serializing_if = "Option::is_none")]
+    pub key_image: Option<String>,
+}
+
+/// A bundle of several proofs
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct MultiBundle {
+    #[cfg_attr(feature = "schema", schemars(range(min = 2, max = 2)))]
+    pub version: usize,
+    /// Default message for entries without their own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Default ring for entries without their own
+    #[serde(rename = "publicKeys", skip_serializing_if = "Option::is_none")]
+    pub public_keys: Option<Vec<String>>,
+    pub proofs: Vec<Entry>,
+    /// Attestations that the rest of the bundle existed at some time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamps: Option<Vec<Attestation>>,
+}
+
+/// The outcome of verifying one entry
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Verdict {
+    pub context: Option<String>,
+    /// The message the entry was checked against, if it had one
+    pub message: Option<String>,
+    /// The ring the entry was checked against, if it had one
+    pub public_keys: Option<Vec<String>>,
+    pub result: Result<(), Error>,
+}
+
+impl Verdict {
+    /// The verdict as a JSON object, with an `error` and its `code` if the
+    /// entry failed
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut ret = json!({
+            "verified": self.result.is_ok(),
+            "message": self.message,
+            "publicKeys": self.public_keys,
+        });
+        if let Some(ref context) = self.context {
+            ret["context"] = json!(context);
+        }
+        if let Err(ref e) = self.result {
+            ret["error"] = json!(e.to_string());
+            ret["code"] = json!(e.code());
+        }
+        ret
+    }
+}
+
+/// A JSON report of the verdicts on a whole bundle, which verifies only if
+/// every entry does
+pub fn report(verdicts: &[Verdict]) -> serde_json::Value {
+    let passed = verdicts.iter().filter(|v| v.result.is_ok()).count();
+    json!({
+        "verified": passed == verdicts.len(),
+        "passed": passed,
+        "total": verdicts.len(),
+        "entries": verdicts.iter().map(Verdict::to_json).collect::<Vec<_>>(),
+    })
+}
+
+impl MultiBundle {
+    /// Parse a bundle from JSON
+    pub fn from_json_str(s: &str) -> Result<Self, Error> {
+        Self::from_json_value(serde_json::from_str(s).map_err(|e| Error::Json(e.to_string()))?)
+    }
+
+    /// Parse a bundle from already-parsed JSON
+    pub fn from_json_value(value: serde_json::Value) -> Result<Self, Error> {
+        let ret: MultiBundle = serde_json::from_value(value).map_err(|e| Error::Json(e.to_string()))?;
+        if ret.version != MULTI_VERSION {
+            return Err(Error::Version(ret.version));
+        }
+        if ret.proofs.is_empty() {
+            return Err(Error::NoProofs);
+        }
+        Ok(ret)
+    }
+
+    /// Serialize the bundle as JSON
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(self).expect("serializing JSON")
+    }
+
+    /// Check every field, without verifying the proofs, returning all the
+    /// problems found
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut problems = Problems(vec![]);
+        if self.version != MULTI_VERSION {
+            problems.push("version".into(), format!("unsupported version {}", self.version));
+        }
+        if self.proofs.is_empty() {
+            problems.push("proofs".into(), "no proofs");
+        }
+        if let Some(ref keys) = self.public_keys {
+            problems.check_keys("publicKeys", keys);
+        }
+        for (n, entry) in self.proofs.iter().enumerate() {
+            let prefix = format!("proofs[{}].", n);
+            if entry.message.is_none() && self.message.is_none() {
+                problems.push(format!("{}message", prefix), "missing, and the bundle has no default");
+            }
+            match entry.public_keys {
+                Some(ref keys) => problems.check_keys(&format!("{}publicKeys", prefix), keys),
+                None if self.public_keys.is_none() => {
+                    problems.push(format!("{}publicKeys", prefix), "missing, and the bundle has no default")
+                }
+                None => {}
+            }
+            problems.check_proof(&prefix, Some(&entry.proof), entry.scope.as_deref(), entry.key_image.as_deref());
+        }
+        problems.0
+    }
+
+    /// The hash which timestamps attest to, of the bundle without them
+    pub fn bundle_hash(&self) -> BundleHash {
+        let unstamped = MultiBundle { timestamps: None, ..self.clone() };
+        BundleHash::hash(unstamped.to_json_string().as_bytes())
+    }
+
+    /// Check every timestamp, in order, returning the times they attest to
+    ///
+    /// See [`crate::timestamping`] for what is not checked.
+    pub fn verify_timestamps(&self) -> Vec<Result<u64, timestamping::Error>> {
+        let hash = self.bundle_hash();
+        self.timestamps.iter().flatten().map(|attestation| timestamping::verify(&hash, attestation)).collect()
+    }
+
+    /// Verify every entry, in order
+    pub fn verify(&self) -> Vec<Verdict> {
+        self.proofs
+            .iter()
+            .map(|entry| {
+                let message = entry.message.as_ref().or(self.message.as_ref());
+                let public_keys = entry.public_keys.as_ref().or(self.public_keys.as_ref());
+                Verdict {
+                    context: entry.context.clone(),
+                    message: message.cloned(),
+                    public_keys: public_keys.cloned(),
+                    result: verify_entry(entry, message, public_keys),
+                }
+            })
+            .collect()
+    }
+}
+
+fn verify_entry(entry: &Entry, message: Option<&String>, public_keys: Option<&Vec<String>>) -> Result<(), Error> {
+    let message = message.ok_or(Error::NoMessage)?;
+    let keys = parse_keys(public_keys.ok_or(Error::NoPublicKeys)?)?;
+    let link = (entry.scope.as_deref(), entry.key_image.as_deref());
+    verify_proof(&entry.proof, link, &keys, message.as_bytes(), |_, _| {})
+}
+
+fn parse_keys(lines: &[String]) -> Result<Vec<PublicKey>, Error> {
+    lines.iter().map(|line| PublicKey::parse_pk_line(line).map_err(|e| Error::BadKey(line.clone(), e))).collect()
+}
+
+/// Verify a hex proof, linkable if the scope and key image are given
+fn verify_proof<F: FnMut(usize, usize)>(
+    proof: &str,
+    link: (Option<&str>, Option<&str>),
+    keys: &[PublicKey],
+    message: &[u8],
+    progress: F,
+) -> Result<(), Error> {
+    let proof = Proof::from_hex(proof).map_err(|e| match e {
+        crate::ProofError::InvalidHex(_) => Error::BadHex,
+        e => Error::Proof(e.into()),
+    })?;
+    match link {
+        (None, None) => Ok(crate::verify_with_progress(&proof, keys, message, progress)?),
+        (Some(scope), Some(key_image)) => {
+            let key_image = <[u8; 32]>::from_hex(key_image).map_err(|_| Error::BadHex)?;
+            Ok(crate::verify_linkable_with_progress(&proof, &key_image, keys, message, scope.as_bytes(), progress)?)
+        }
+        _ => Err(Error::PartialLink),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::SecretKey;
+
+    const RING: [&str; 3] = [
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10",
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGMiyoNWxKsdbuZ9EeJA+QTTaKHYtpCrRBlvCez8ykRl",
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana",
+    ];
+
+    #[test]
+    fn two_proofs_one_failing() {
+        // Matches the last key of the ring
+        let sk = SecretKey::from_bytes([
+            0x60, 0xb0, 0x7c, 0x0a, 0xb3, 0xfc, 0xc3, 0xb0, 0x29, 0x54, 0xd0, 0xee, 0x5c, 0x5b,
+            0xdd, 0xe5, 0xa0, 0x7d, 0x1f, 0xd1, 0x4e, 0xf4, 0x29, 0x5f, 0xfe, 0x13, 0xec, 0x00,
+            0xdd, 0xc4, 0xa8, 0x5c,
+        ]);
+        let lines: Vec<String> = RING.iter().map(|s| s.to_string()).collect();
+        let ring: Vec<_> = RING.iter().map(|line| PublicKey::parse_pk_line(line).unwrap()).collect();
+        let good = crate::prove(&ring, b"shared", &sk).unwrap();
+        let other = crate::prove(&ring, b"something else", &sk).unwrap();
+
+        let bundle = MultiBundle {
+            version: MULTI_VERSION,
+            message: Some("shared".into()),
+            public_keys: Some(lines.clone()),
+            proofs: vec![
+                Entry { proof: good.to_hex(), context: Some("first".into()), ..Entry::default() },
+                Entry { proof: other.to_hex(), ..Entry::default() },
+                Entry { proof: other.to_hex(), message: Some("something else".into()), ..Entry::default() },
+            ],
+            timestamps: None,
+        };
+        let parsed = MultiBundle::from_json_str(&bundle.to_json_string()).unwrap();
+        assert_eq!(parsed, bundle);
+        let verdicts = parsed.verify();
+        assert_eq!(verdicts[0].context.as_deref(), Some("first"));
+        assert_eq!(verdicts[0].result, Ok(()));
+        assert_eq!(verdicts[1].result, Err(Error::Proof(crate::VerifyError::BadProof)));
+        assert_eq!(verdicts[2].result, Ok(()));
+        assert_eq!(verdicts[2].public_keys.as_ref(), Some(&lines));
+
+        let report = report(&verdicts);
+        assert_eq!(report["verified"], false);
+        assert_eq!(report["passed"], 2);
+        assert_eq!(report["entries"][0]["context"], "first");
+        assert_eq!(report["entries"][1]["code"], "bad_proof");
+    }
+
+    #[test]
+    fn schema_errors() {
+        assert!(matches!(MultiBundle::from_json_str("{}"), Err(Error::Json(_))));
+        assert_eq!(MultiBundle::from_json_str(r#"{"version": 1, "proofs": [{"proof": ""}]}"#), Err(Error::Version(1)));
+        assert_eq!(MultiBundle::from_json_str(r#"{"version": 2, "proofs": []}"#), Err(Error::NoProofs));
+        assert!(matches!(
+            MultiBundle::from_json_str(r#"{"version": 2, "proofs": [{"proof": "", "extra": 1}]}"#),
+            Err(Error::Json(_)),
+        ));
+
+        let bundle = MultiBundle::from_json_str(r#"{"version": 2, "proofs": [{"proof": "00"}]}"#).unwrap();
+        assert_eq!(bundle.verify()[0].result, Err(Error::NoMessage));
+        let bundle = MultiBundle::from_json_str(r#"{"version": 2, "message": "", "proofs": [{"proof": "00"}]}"#).unwrap();
+        assert_eq!(bundle.verify()[0].result, Err(Error::NoPublicKeys));
+    }
+
+    #[test]
+    fn confession_snapshot() {
+        let confession = Confession {
+            proof: Some("00".repeat(64)),
+            skipped_keys: Some(vec![SkippedKey {
+                source: "ring.keys".into(),
+                line: 3,
+                key: "ssh-rsa AAAA".into(),
+                reason: "not an ed25519 key (ssh-rsa)".into(),
+            }]),
+            ..Confession::new(vec![RING[0].into()], "hello".into())
+        };
+        let json = confession.to_json_string();
+        assert_eq!(
+            json,
+            format!(
+                concat!(
+                    r#"{{"version":1,"publicKeys":["{}"],"message":"hello","proof":"{}","#,
+                    r#""skippedKeys":[{{"source":"ring.keys","line":3,"key":"ssh-rsa AAAA","#,
+                    r#""reason":"not an ed25519 key (ssh-rsa)"}}]}}"#,
+                ),
+                RING[0],
+                "00".repeat(64),
+            ),
+        );
+        assert_eq!(Confession::from_json_str(&json), Ok(confession));
+
+        let linkable = Confession {
+            proof: Some("01".repeat(64)),
+            scope: Some("vote".into()),
+            key_image: Some("02".repeat(32)),
+            attributes: Some(SignedAttributes { timestamp: Some(1700000000), ..Default::default() }),
+            ..Confession::new(vec![], "hi".into())
+        };
+        assert_eq!(
+            linkable.to_json_string(),
+            format!(
+                concat!(
+                    r#"{{"version":1,"publicKeys":[],"message":"hi","proof":"{}","scope":"vote","keyImage":"{}","#,
+                    r#""attributes":{{"timestamp":1700000000}}}}"#,
+                ),
+                "01".repeat(64),
+                "02".repeat(32),
+            ),
+        );
+    }
+
+    #[test]
+    fn bundle_snapshot() {
+        let bundle = MultiBundle {
+            version: MULTI_VERSION,
+            message: Some("shared".into()),
+            public_keys: None,
+            proofs: vec![
+                Entry { proof: "00".into(), context: Some("first".into()), ..Entry::default() },
+                Entry { proof: "01".into(), public_keys: Some(vec![RING[1].into()]), ..Entry::default() },
+            ],
+            timestamps: None,
+        };
+        assert_eq!(
+            bundle.to_json_string(),
+            format!(
+                concat!(
+                    r#"{{"version":2,"message":"shared","proofs":[{{"proof":"00","context":"first"}},"#,
+                    r#"{{"publicKeys":["{}"],"proof":"01"}}]}}"#,
+                ),
+                RING[1],
+            ),
+        );
+    }
+
+    #[test]
+    fn confession_versions() {
+        // Unversioned confessions are version 1, and unknown fields are ignored
+        let legacy = Confession::from_json_str(r#"{"publicKeys": [], "message": "old", "future": true}"#).unwrap();
+        assert_eq!(legacy, Confession::new(vec![], "old".into()));
+        assert_eq!(
+            Confession::from_json_str(r#"{"version": 3, "message": ""}"#),
+            Err(Error::ConfessionVersion(3)),
+        );
+        assert!(matches!(Confession::from_json_str(r#"{"version": 1}"#), Err(Error::Json(_))));
+    }
+
+    #[test]
+    fn migration() {
+        let sk = SecretKey::from_bytes([
+            0x60, 0xb0, 0x7c, 0x0a, 0xb3, 0xfc, 0xc3, 0xb0, 0x29, 0x54, 0xd0, 0xee, 0x5c, 0x5b,
+            0xdd, 0xe5, 0xa0, 0x7d, 0x1f, 0xd1, 0x4e, 0xf4, 0x29, 0x5f, 0xfe, 0x13, 0xec, 0x00,
+            0xdd, 0xc4, 0xa8, 0x5c,
+        ]);
+        let lines: Vec<String> = RING.iter().map(|s| s.to_string()).collect();
+        let ring: Vec<_> = RING.iter().map(|line| PublicKey::parse_pk_line(line).unwrap()).collect();
+        let proof = crate::prove(&ring, b"migrated", &sk).unwrap();
+        let confession = Confession { proof: Some(proof.to_hex()), ..Confession::new(lines, "migrated".into()) };
+
+        let bundle = confession.clone().into_bundle().unwrap();
+        assert_eq!(bundle.version, MULTI_VERSION);
+        assert_eq!(bundle.verify()[0].result, Ok(()));
+
+        assert_eq!(Confession { proof: None, ..confession.clone() }.into_bundle(), Err(Error::NoProofs));
+        let signed = Confession { attributes: Some(SignedAttributes::default()), ..confession };
+        assert_eq!(signed.into_bundle(), Err(Error::NotMigratable("attributes")));
+    }
+
+    #[test]
+    fn prove_and_verify() {
+        let sk = SecretKey::from_bytes([
+            0x60, 0xb0, 0x7c, 0x0a, 0xb3, 0xfc, 0xc3, 0xb0, 0x29, 0x54, 0xd0, 0xee, 0x5c, 0x5b,
+            0xdd, 0xe5, 0xa0, 0x7d, 0x1f, 0xd1, 0x4e, 0xf4, 0x29, 0x5f, 0xfe, 0x13, 0xec, 0x00,
+            0xdd, 0xc4, 0xa8, 0x5c,
+        ]);
+        let lines: Vec<String> = RING.iter().map(|s| s.to_string()).collect();
+        let mut confession = Confession::new(lines.clone(), "proven".into());
+        assert_eq!(confession.verify(), Err(Error::NoProofs));
+        confession.prove(&sk).unwrap();
+        assert_eq!(confession.key_image, None);
+        let parsed = Confession::from_json_str(&confession.to_json_string()).unwrap();
+        assert_eq!(parsed.verify(), Ok(()));
+        assert_eq!(parsed.params_hash(), Ok(crate::params_hash(&parsed.keys().unwrap(), b"proven").unwrap()));
+        let moved = Confession { message: "moved".into(), ..parsed };
+        assert_eq!(moved.verify(), Err(Error::Proof(crate::VerifyError::BadProof)));
+
+        // The attributes and the scope are covered too
+        let attributes = SignedAttributes { timestamp: Some(1_700_000_000), ..SignedAttributes::default() };
+        let mut signed = Confession { attributes: Some(attributes), scope: Some("vote".into()), ..confession.clone() };
+        signed.prove(&sk).unwrap();
+        assert_eq!(signed.verify(), Ok(()));
+        assert_ne!(signed.params_hash(), confession.params_hash());
+        assert_eq!(signed.key_image.as_ref().map(String::len), Some(64));
+        assert_eq!(Confession { attributes: None, ..signed.clone() }.verify(), Err(Error::Proof(crate::VerifyError::BadProof)));
+        assert_eq!(Confession { key_image: None, ..signed }.verify(), Err(Error::PartialLink));
+
+        let mut outside = Confession::new(lines[..2].to_vec(), "proven".into());
+        assert!(matches!(outside.prove(&sk), Err(Error::Prove(crate::ProveError::KeyNotInRing { .. }))));
+        let mut garbled = Confession::new(vec!["ssh-ed25519 AAAA".into()], "proven".into());
+        assert!(matches!(garbled.prove(&sk), Err(Error::BadKey(..))));
+    }
+
+    #[test]
+    fn field_errors() {
+        let confession = Confession {
+            proof: Some("0g".into()),
+            scope: Some("vote".into()),
+            attributes: Some(SignedAttributes { timestamp: Some(20), expires: Some(10), ..Default::default() }),
+            sshsig: Some("-----BEGIN SSH SIGNATURE-----".into()),
+            ..Confession::new(vec![RING[0].into(), "ssh-ed25519".into()], "".into())
+        };
+        let paths: Vec<_> = confession.validate().into_iter().map(|problem| problem.path).collect();
+        assert_eq!(paths, ["publicKeys[1]", "proof", "keyImage", "attributes", "sshsig"]);
+
+        let bundle = MultiBundle {
+            version: MULTI_VERSION,
+            message: None,
+            public_keys: Some(vec!["".into()]),
+            proofs: vec![
+                Entry { proof: "00".repeat(32), message: Some("ok".into()), ..Entry::default() },
+                Entry { proof: "00".into(), key_image: Some("00".into()), ..Entry::default() },
+            ],
+            timestamps: None,
+        };
+        let problems: Vec<_> = bundle.validate().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            problems,
+            [
+                "publicKeys[0]: empty key".to_owned(),
+                "proofs[1].message: missing, and the bundle has no default".to_owned(),
+                "proofs[1].proof: not a whole number of 32-byte scalars".to_owned(),
+                "proofs[1].keyImage: not 32 bytes of hex".to_owned(),
+                "proofs[1].scope: required when keyImage is given".to_owned(),
+            ],
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "schema")]
+    fn schemas_up_to_date() {
+        let generated = [
+            (CONFESSION_VERSION, "confession-v1.schema.json", schemars::schema_for!(Confession)),
+            (MULTI_VERSION, "bundle-v2.schema.json", schemars::schema_for!(MultiBundle)),
+        ];
+        for (version, file, schema) in generated {
+            let schema = serde_json::to_string_pretty(&schema).unwrap() + "\n";
+            if std::env::var_os("RINGSIG_UPDATE_SCHEMAS").is_some() {
+                let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("schema").join(file);
+                std::fs::write(path, &schema).unwrap();
+            } else {
+                assert_eq!(schema_json(version), Some(&schema[..]), "schema/{} is out of date", file);
+            }
+        }
+    }
+}
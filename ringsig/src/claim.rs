@@ -0,0 +1,279 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Claiming authorship of a proof
+//!
+//! A proof does not show which member of its ring made it, even to someone
+//! with every member's secret key: any member could have chosen the same
+//! `s` values. So a signer cannot step forward afterwards unless the proof
+//! was made to allow it, with [`prove_claimable`].
+//!
+//! In a claimable proof, the `s` value at the index after the signer's,
+//! which would otherwise be random, is a tagged hash of the params, the
+//! signer's nonce point, and a claim secret which is a tagged hash of the
+//! params and the signer's secret key. A [`Claim`] reveals the claim secret,
+//! which opens that commitment at the claimant's index, and is signed by
+//! the claimant's key with a Schnorr signature over the proof. Another
+//! member cannot open the commitment at their own index, nor can the
+//! signer at any index but theirs. In a ring of one key there is no other
+//! `s` value, and the proof names its signer already, so the claim is just
+//! the signature.
+//!
+//! Without the secret key, a commitment looks as random as any other `s`
+//! value, so a claimable proof is as anonymous as any other and cannot be
+//! told from one. But anyone who later learns the secret key can compute
+//! the claim secret, and so find which claimable proofs it made, where a
+//! plain proof stays anonymous even then. That is why proofs are only made
+//! claimable when asked.
+
+use bitcoin_hashes::{Hash, HashEngine};
+use curve25519_dalek::constants;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::hashes::{ChallengeHash, ClaimChallengeHash, ClaimCommitmentHash, ClaimSecretHash, NonceHash};
+use crate::keys::{PublicKey, SecretKey};
+use crate::{
+    find_signer, hash_to_scalar, next_challenge, proof_len, prove_sorted_with, randomness_from, Proof, ProveError,
+    ProveFlags, Ring, VerifyError,
+};
+
+/// A claim of authorship of a proof, as made by [`claim`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Claim {
+    /// The claim secret, opening the commitment in the proof
+    secret: [u8; 32],
+    /// The nonce point of the signature
+    nonce: [u8; 32],
+    /// The `s` value of the signature
+    s: [u8; 32],
+}
+
+impl Claim {
+    /// Parse a claim from the bytes given by [`Claim::to_bytes`]
+    pub fn from_bytes(bytes: &[u8; 96]) -> Self {
+        let mut ret = Claim { secret: [0; 32], nonce: [0; 32], s: [0; 32] };
+        ret.secret.copy_from_slice(&bytes[..32]);
+        ret.nonce.copy_from_slice(&bytes[32..64]);
+        ret.s.copy_from_slice(&bytes[64..]);
+        ret
+    }
+
+    /// The claim secret, then the signature's nonce point and `s` value
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut ret = [0; 96];
+        ret[..32].copy_from_slice(&self.secret);
+        ret[32..64].copy_from_slice(&self.nonce);
+        ret[64..].copy_from_slice(&self.s);
+        ret
+    }
+}
+
+/// Produce a proof as [`prove`](crate::prove) does, which the signer can
+/// later claim with [`claim`]
+///
+/// See the [module documentation](self) for what this costs: anyone who
+/// learns `sk` can tell that it made the proof.
+pub fn prove_claimable(pks: &[PublicKey], message: &[u8], sk: &SecretKey) -> Result<Proof, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let my_idx = find_signer(&ring.pks, sk)?;
+    let params = ring.params(message)?;
+    let flags = ProveFlags { claimable: true, ..ProveFlags::default() };
+    prove_sorted_with(&ring.negated, my_idx, params, sk, None, |_, _| {}, randomness_from(&mut OsRng), flags)
+}
+
+/// Claim authorship of a proof made by [`prove_claimable`] with `sk`
+///
+/// This fails with [`ProveError::NotTheSigner`] if the proof was made with
+/// another key, or is not claimable, or is not of `message` over `pks`.
+pub fn claim(proof: &Proof, pks: &[PublicKey], message: &[u8], sk: &SecretKey) -> Result<Claim, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let index = find_signer(&ring.pks, sk)?;
+    let params = ring.params(message)?.into_inner();
+    let secret = claim_secret(sk, &params);
+    if !opens(&ring, &params, proof.as_bytes(), index, &secret) {
+        return Err(ProveError::NotTheSigner);
+    }
+
+    let mut rng = [0; 32];
+    OsRng.try_fill_bytes(&mut rng).map_err(|_| ProveError::Rng)?;
+    let mut nonce_eng = NonceHash::engine();
+    nonce_eng.input(&secret);
+    nonce_eng.input(sk.as_bytes());
+    nonce_eng.input(proof.as_bytes());
+    nonce_eng.input(&rng);
+    let r = Scalar::from_bytes_mod_order(NonceHash::from_engine(nonce_eng).into_inner());
+    let nonce = (&r * &constants::ED25519_BASEPOINT_TABLE).compress().to_bytes();
+    let e = claim_challenge(&nonce, &ring.pks[index], &secret, &params, proof.as_bytes());
+    Ok(Claim { secret, nonce, s: (r + e * sk.0).to_bytes() })
+}
+
+/// Verify that `pk` made `proof`, a proof of `message` over `pks`, as
+/// `claim` says
+///
+/// The proof is verified too, so on success it is valid and by `pk`.
+pub fn verify_claim(
+    claim: &Claim,
+    proof: &Proof,
+    pks: &[PublicKey],
+    message: &[u8],
+    pk: &PublicKey,
+) -> Result<(), VerifyError> {
+    let ring = Ring::prepare::<VerifyError>(pks)?;
+    ring.verify(proof, message)?;
+    let index = ring.index_of(pk).ok_or(VerifyError::ClaimantNotInRing)?;
+    let params = ring.params(message)?.into_inner();
+    if !opens(&ring, &params, proof.as_bytes(), index, &claim.secret) {
+        return Err(VerifyError::BadClaim);
+    }
+    let s = Scalar::from_canonical_bytes(claim.s).ok_or(VerifyError::BadClaim)?;
+    let e = claim_challenge(&claim.nonce, pk, &claim.secret, &params, proof.as_bytes());
+    // Comparing encodings rejects a nonce given in any but the canonical one
+    match EdwardsPoint::vartime_double_scalar_mul_basepoint(&e, &-pk.0, &s).compress().to_bytes() == claim.nonce {
+        true => Ok(()),
+        false => Err(VerifyError::BadClaim),
+    }
+}
+
+/// The claim secret of `sk` for a proof with `params`
+pub(crate) fn claim_secret(sk: &SecretKey, params: &[u8; 32]) -> [u8; 32] {
+    let mut eng = ClaimSecretHash::engine();
+    eng.input(params);
+    eng.input(sk.as_bytes());
+    ClaimSecretHash::from_engine(eng).into_inner()
+}
+
+/// The `s` value which follows the signer's in a claimable proof
+pub(crate) fn commitment(params: &[u8; 32], secret: &[u8; 32], pubnonce: &EdwardsPoint) -> Scalar {
+    let mut eng = ClaimCommitmentHash::engine();
+    eng.input(params);
+    eng.input(secret);
+    eng.input(&pubnonce.compress().to_bytes());
+    // Reduced, as the random `s` values are
+    Scalar::from_bytes_mod_order(ClaimCommitmentHash::from_engine(eng).into_inner())
+}
+
+/// The challenge of a claim's signature
+fn claim_challenge(nonce: &[u8; 32], pk: &PublicKey, secret: &[u8; 32], params: &[u8; 32], proof: &[u8]) -> Scalar {
+    let mut eng = ClaimChallengeHash::engine();
+    eng.input(nonce);
+    eng.input(&pk.serialize());
+    eng.input(secret);
+    eng.input(params);
+    eng.input(proof);
+    Scalar::from_bytes_mod_order(ClaimChallengeHash::from_engine(eng).into_inner())
+}
+
+/// Whether `secret` opens the commitment to the nonce point at `index` of a
+/// proof, which need not be valid, over the sorted ring
+fn opens(ring: &Ring, params: &[u8; 32], proof: &[u8], index: usize, secret: &[u8; 32]) -> bool {
+    let n = ring.negated.len();
+    if proof.len() != proof_len(n) {
+        return false;
+    }
+    if n == 1 {
+        return true;
+    }
+    let s_at = |i: usize| NonceHash::from_slice(&proof[32 * (i + 1)..32 * (i + 2)]).unwrap();
+    let mut e_i = ChallengeHash::from_slice(&proof[..32]).unwrap();
+    for (i, neg_pk) in ring.negated[..index].iter().enumerate() {
+        e_i = next_challenge(e_i, s_at(i), neg_pk, None, params);
+    }
+    let (e_i, s_i) = (hash_to_scalar(e_i.as_inner()), hash_to_scalar(s_at(index).as_inner()));
+    let pubnonce = EdwardsPoint::vartime_double_scalar_mul_basepoint(&e_i, &ring.negated[index], &s_i);
+    commitment(params, secret, &pubnonce).to_bytes() == s_at((index + 1) % n).into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+    use crate::{prove, verify};
+
+    #[test]
+    fn round_trip() {
+        let (sks, pks) = testkit::deterministic_keys(b"claim tests", 5);
+        for sk in &sks {
+            let proof = prove_claimable(&pks, b"msg", sk).unwrap();
+            verify(&proof, &pks, b"msg").unwrap();
+            let claimed = claim(&proof, &pks, b"msg", sk).unwrap();
+            verify_claim(&claimed, &proof, &pks, b"msg", &sk.to_public()).unwrap();
+            let parsed = Claim::from_bytes(&claimed.to_bytes());
+            assert_eq!(parsed, claimed);
+            verify_claim(&parsed, &proof, &pks, b"msg", &sk.to_public()).unwrap();
+        }
+
+        // The lone key of a ring made every proof over it
+        let proof = prove_claimable(&pks[..1], b"msg", &sks[0]).unwrap();
+        let claimed = claim(&proof, &pks[..1], b"msg", &sks[0]).unwrap();
+        verify_claim(&claimed, &proof, &pks[..1], b"msg", &pks[0]).unwrap();
+    }
+
+    #[test]
+    fn others_cannot_claim() {
+        let (sks, pks) = testkit::deterministic_keys(b"claim tests", 5);
+        let proof = prove_claimable(&pks, b"msg", &sks[1]).unwrap();
+        let claimed = claim(&proof, &pks, b"msg", &sks[1]).unwrap();
+
+        // A third member cannot claim it, whether through `claim` or by
+        // signing with the claim secret their own key would give
+        assert_eq!(claim(&proof, &pks, b"msg", &sks[3]), Err(ProveError::NotTheSigner));
+        let params = crate::params_hash(&pks, b"msg").unwrap().into_inner();
+        let forged = Claim { secret: claim_secret(&sks[3], &params), ..signed(&claimed, &sks[3], &pks, &proof) };
+        assert_eq!(verify_claim(&forged, &proof, &pks, b"msg", &pks[3]), Err(VerifyError::BadClaim));
+        // Nor by reusing the real claim secret, which opens only the
+        // signer's index
+        let forged = signed(&claimed, &sks[3], &pks, &proof);
+        assert_eq!(verify_claim(&forged, &proof, &pks, b"msg", &pks[3]), Err(VerifyError::BadClaim));
+        // The claim is the signer's alone
+        assert_eq!(verify_claim(&claimed, &proof, &pks, b"msg", &pks[3]), Err(VerifyError::BadClaim));
+        let outsider = testkit::synthetic_ring(b"claim outsider", 1)[0];
+        assert_eq!(verify_claim(&claimed, &proof, &pks, b"msg", &outsider), Err(VerifyError::ClaimantNotInRing));
+
+        // The signature must be by the claimant, over this proof
+        let mut bytes = claimed.to_bytes();
+        bytes[64] ^= 1;
+        let tampered = Claim::from_bytes(&bytes);
+        assert_eq!(verify_claim(&tampered, &proof, &pks, b"msg", &pks[1]), Err(VerifyError::BadClaim));
+        let other = prove_claimable(&pks, b"msg", &sks[1]).unwrap();
+        assert_eq!(verify_claim(&claimed, &other, &pks, b"msg", &pks[1]), Err(VerifyError::BadClaim));
+        assert_eq!(verify_claim(&claimed, &proof, &pks, b"other", &pks[1]), Err(VerifyError::BadProof));
+    }
+
+    /// `claim` with its signature replaced by one of `sk`'s, as any member
+    /// could make
+    fn signed(claim: &Claim, sk: &SecretKey, pks: &[PublicKey], proof: &Proof) -> Claim {
+        let params = crate::params_hash(pks, b"msg").unwrap().into_inner();
+        let r = Scalar::from_bytes_mod_order([3; 32]);
+        let nonce = (&r * &constants::ED25519_BASEPOINT_TABLE).compress().to_bytes();
+        let e = claim_challenge(&nonce, &sk.to_public(), &claim.secret, &params, proof.as_bytes());
+        Claim { secret: claim.secret, nonce, s: (r + e * sk.0).to_bytes() }
+    }
+
+    #[test]
+    fn plain_proofs() {
+        let (sks, pks) = testkit::deterministic_keys(b"claim tests", 5);
+        let proof = prove(&pks, b"msg", &sks[2]).unwrap();
+        assert_eq!(claim(&proof, &pks, b"msg", &sks[2]), Err(ProveError::NotTheSigner));
+        let claimable = prove_claimable(&pks, b"msg", &sks[2]).unwrap();
+        assert_eq!(claim(&claimable, &pks, b"other", &sks[2]), Err(ProveError::NotTheSigner));
+        assert_eq!(claim(&claimable, &pks[..4], b"msg", &sks[2]), Err(ProveError::NotTheSigner));
+        assert_eq!(ProveError::NotTheSigner.code(), "not_signer");
+        assert_eq!(VerifyError::BadClaim.code(), "bad_claim");
+    }
+}
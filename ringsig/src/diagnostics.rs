@@ -0,0 +1,243 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Diagnosing proofs which fail to verify
+//!
+//! "bad proof" says nothing of why. [`verify_detailed`] verifies as
+//! [`crate::verify`] does, and also reports what it found along the way:
+//! the ring size the proof's length implies, any `s` value which is not a
+//! canonical scalar, the params hash, and the challenge after each key,
+//! ending with the one which should equal the proof's first challenge.
+//!
+//! Each challenge is a hash of the one before, so a proof alone cannot say
+//! where its chain went wrong: a changed message, a missing key and one
+//! corrupted byte all give a chain which fails to close. Comparing the
+//! challenges with those of another run, such as a known-good proof or
+//! another implementation's trace, finds the first key at which they part;
+//! see [`VerifyDiagnostics::first_divergence`].
+//!
+//! **This is a debugging aid, not a security oracle.** Only
+//! [`VerifyDiagnostics::result`] says whether a proof is valid, and it is
+//! exactly what [`crate::verify`] returns. Nothing else here is secret, as
+//! anyone with the proof and ring can compute it, but nor should anything
+//! else be used to decide whether to accept a proof.
+
+use std::fmt;
+use std::ops::ControlFlow;
+
+use bitcoin_hashes::hex::ToHex;
+use bitcoin_hashes::Hash;
+
+use crate::hashes::{ChallengeHash, NonceHash, ParamsHash};
+use crate::keys::PublicKey;
+use crate::{check_canonical, link_params, next_challenge, proof_len, verify_inner, Proof, Ring, VerifyError};
+
+/// What verifying a proof found, as made by [`verify_detailed`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct VerifyDiagnostics {
+    /// The result of verifying, as [`crate::verify`] gives it
+    pub result: Result<(), VerifyError>,
+    /// The number of keys given
+    pub ring_size: usize,
+    /// The number of keys the proof's length is for
+    pub proof_ring_size: usize,
+    /// The index of the first 32-byte chunk of the proof which should be a
+    /// canonical scalar but is not
+    pub non_canonical: Option<usize>,
+    /// The params hash, if the ring and message have one
+    pub params_hash: Option<ParamsHash>,
+    /// The first challenge, as the proof gives it
+    pub provided_challenge: [u8; 32],
+    /// The challenge after each key of the sorted ring, the last of which
+    /// is the recomputed first challenge; empty if the proof is for a ring
+    /// of another size, or the ring cannot be verified against
+    pub challenges: Vec<[u8; 32]>,
+}
+
+impl VerifyDiagnostics {
+    /// The first challenge as recomputed from the rest of the proof, if it
+    /// could be
+    pub fn recomputed_challenge(&self) -> Option<[u8; 32]> {
+        self.challenges.last().copied()
+    }
+
+    /// Whether the chain of challenges closes, i.e. the recomputed first
+    /// challenge is the one the proof gives
+    pub fn closes(&self) -> bool {
+        self.recomputed_challenge() == Some(self.provided_challenge)
+    }
+
+    /// The index of the first key after which this chain's challenge
+    /// differs from `other`'s, or `None` if they agree as far as both go
+    ///
+    /// Against a known-good proof over the same ring and message, which
+    /// shares its first challenge, this is the index of the first corrupted
+    /// `s` value.
+    pub fn first_divergence(&self, other: &VerifyDiagnostics) -> Option<usize> {
+        self.challenges.iter().zip(&other.challenges).position(|(ours, theirs)| ours != theirs)
+    }
+}
+
+impl fmt::Display for VerifyDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "ring of {} key{}; the proof is for a ring of {} key{}",
+            self.ring_size,
+            if self.ring_size == 1 { "" } else { "s" },
+            self.proof_ring_size,
+            if self.proof_ring_size == 1 { "" } else { "s" },
+        )?;
+        if let Some(params_hash) = self.params_hash {
+            writeln!(f, "params hash {}", params_hash.to_hex())?;
+        }
+        if let Some(index) = self.non_canonical {
+            writeln!(f, "the value at index {} is not a canonical scalar", index)?;
+        }
+        writeln!(f, "challenge in proof   {}", self.provided_challenge.to_hex())?;
+        match self.recomputed_challenge() {
+            Some(recomputed) => writeln!(f, "challenge recomputed {}", recomputed.to_hex())?,
+            None => writeln!(f, "challenge not recomputed")?,
+        }
+        if !self.closes() && !self.challenges.is_empty() {
+            writeln!(
+                f,
+                "the chain of challenges does not close: the message, a key or a value of the proof is not \
+                 what was signed, which the proof alone cannot tell apart",
+            )?;
+        }
+        match self.result {
+            Ok(()) => writeln!(f, "result: verified"),
+            Err(ref e) => writeln!(f, "result: {}", e),
+        }
+    }
+}
+
+/// Verify a proof as [`crate::verify`] does, reporting what was found along
+/// the way; see the [module documentation](self) for what it can and cannot
+/// tell
+pub fn verify_detailed(proof: &Proof, pks: &[PublicKey], message: &[u8]) -> VerifyDiagnostics {
+    let proof = proof.as_bytes();
+    let mut provided_challenge = [0; 32];
+    provided_challenge.copy_from_slice(&proof[..32]);
+    let mut ret = VerifyDiagnostics {
+        result: verify_inner(proof, pks, message, None, |_, _| ControlFlow::Continue(())),
+        ring_size: pks.len(),
+        proof_ring_size: proof.len() / 32 - 1,
+        non_canonical: None,
+        params_hash: None,
+        provided_challenge,
+        challenges: vec![],
+    };
+    if let Err(VerifyError::NonCanonicalScalar { index }) = check_canonical(proof) {
+        ret.non_canonical = Some(index);
+    }
+    let ring = match Ring::prepare::<VerifyError>(pks) {
+        Ok(ring) => ring,
+        Err(_) => return ret,
+    };
+    let params = match ring.params(message) {
+        Ok(params) => params,
+        Err(_) => return ret,
+    };
+    ret.params_hash = Some(params);
+    if proof.len() != proof_len(ring.negated.len()) {
+        return ret;
+    }
+
+    let params = link_params(params, None);
+    let mut e_i = ChallengeHash::from_slice(&proof[..32]).unwrap();
+    for (s_i, neg_pk) in proof[32..].chunks_exact(32).zip(&ring.negated) {
+        e_i = next_challenge(e_i, NonceHash::from_slice(s_i).unwrap(), neg_pk, None, &params);
+        ret.challenges.push(e_i.into_inner());
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    #[test]
+    fn valid() {
+        let (sks, keys) = testkit::deterministic_keys(b"diagnostics tests", 5);
+        let proof = crate::prove(&keys, b"msg", &sks[4]).unwrap();
+        let diagnostics = verify_detailed(&proof, &keys, b"msg");
+        assert_eq!(diagnostics.result, Ok(()));
+        assert_eq!((diagnostics.ring_size, diagnostics.proof_ring_size), (5, 5));
+        assert_eq!(diagnostics.params_hash, Some(crate::params_hash(&keys, b"msg").unwrap()));
+        assert_eq!(diagnostics.challenges.len(), 5);
+        assert!(diagnostics.closes());
+        assert_eq!(diagnostics.non_canonical, None);
+        assert!(diagnostics.to_string().ends_with("\nresult: verified\n"));
+    }
+
+    #[test]
+    fn corrupted() {
+        let (sks, keys) = testkit::deterministic_keys(b"diagnostics tests", 5);
+        let proof = crate::prove(&keys, b"msg", &sks[4]).unwrap();
+        let good = verify_detailed(&proof, &keys, b"msg");
+        for index in 1..=5 {
+            // Flipping a low bit keeps the value canonical
+            let mut bytes = proof.as_bytes().to_vec();
+            bytes[32 * index] ^= 1;
+            let diagnostics = verify_detailed(&Proof::from_bytes(bytes).unwrap(), &keys, b"msg");
+            assert_eq!(diagnostics.result, Err(VerifyError::BadProof));
+            assert!(!diagnostics.closes());
+            assert_eq!(diagnostics.first_divergence(&good), Some(index - 1));
+            assert!(diagnostics.to_string().contains("does not close"));
+        }
+
+        // The first challenge starts the chain, so it differs at once
+        let mut bytes = proof.as_bytes().to_vec();
+        bytes[5] ^= 0x10;
+        let diagnostics = verify_detailed(&Proof::from_bytes(bytes).unwrap(), &keys, b"msg");
+        assert_eq!(diagnostics.first_divergence(&good), Some(0));
+
+        // A value which is not a scalar at all can be pointed at directly
+        let mut bytes = proof.as_bytes().to_vec();
+        bytes[32 * 3 + 31] = 0xff;
+        let diagnostics = verify_detailed(&Proof::from_bytes(bytes).unwrap(), &keys, b"msg");
+        assert_eq!(diagnostics.non_canonical, Some(3));
+        assert_eq!(diagnostics.result, Err(VerifyError::BadProof));
+        assert!(diagnostics.to_string().contains("the value at index 3 is not a canonical scalar"));
+    }
+
+    #[test]
+    fn wrong_ring_or_message() {
+        let (sks, keys) = testkit::deterministic_keys(b"diagnostics tests", 5);
+        let proof = crate::prove(&keys, b"msg", &sks[4]).unwrap();
+        let good = verify_detailed(&proof, &keys, b"msg");
+
+        let other = verify_detailed(&proof, &keys, b"msg2");
+        assert_eq!(other.result, Err(VerifyError::BadProof));
+        assert_ne!(other.params_hash, good.params_hash);
+        assert_eq!(other.first_divergence(&good), Some(0));
+
+        let fewer = verify_detailed(&proof, &keys[1..], b"msg");
+        assert_eq!((fewer.ring_size, fewer.proof_ring_size), (4, 5));
+        assert!(fewer.challenges.is_empty());
+        assert!(!fewer.closes());
+        assert!(fewer.to_string().starts_with("ring of 4 keys; the proof is for a ring of 5 keys\n"));
+        assert!(fewer.to_string().contains("challenge not recomputed\n"));
+
+        let repeated = [&keys[..], &keys[..1]].concat();
+        let diagnostics = verify_detailed(&proof, &repeated, b"msg");
+        assert!(matches!(diagnostics.result, Err(VerifyError::DuplicateKey(_))));
+        assert_eq!(diagnostics.params_hash, None);
+    }
+}
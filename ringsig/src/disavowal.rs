@@ -0,0 +1,244 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Disavowing linkable proofs
+//!
+//! A ring member accused of making a proof may want to show that they did
+//! not. For a plain proof they cannot: it was made so that any member
+//! could have made it, even as seen by someone with every member's secret
+//! key, so nothing a non-signer knows sets them apart from the signer.
+//!
+//! A linkable proof comes with its signer's key image for the scope, the
+//! secret key times a point derived from the scope. A [`Disavowal`] gives
+//! the disavowing key's own key image for the scope, with a Chaum-Pedersen
+//! proof that it has the same discrete logarithm as the key. If that key
+//! image differs from the proof's, the key did not make the proof. The
+//! signer's key image is the proof's, so they cannot disavow it.
+//!
+//! Disavowing reveals the key image, so any other linkable proof made with
+//! the key in the same scope can then be attributed to it. Before
+//! disavowing one proof in a scope, make sure you have made none in it.
+
+use bitcoin_hashes::{Hash, HashEngine};
+use curve25519_dalek::constants;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::scalar::Scalar;
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+use crate::hashes::{DisavowalHash, NonceHash};
+use crate::keys::{PublicKey, SecretKey};
+use crate::{key_image_point, link_of, scope_point, verify_linkable, Proof, ProveError, Ring, VerifyError};
+
+/// A proof that a key did not make a linkable proof, as made by
+/// [`disavow`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Disavowal {
+    /// The disavowing key's key image for the proof's scope
+    key_image: [u8; 32],
+    /// The challenge of the Chaum-Pedersen proof
+    challenge: [u8; 32],
+    /// The response of the Chaum-Pedersen proof
+    response: [u8; 32],
+}
+
+impl Disavowal {
+    /// Parse a disavowal from the bytes given by [`Disavowal::to_bytes`]
+    pub fn from_bytes(bytes: &[u8; 96]) -> Self {
+        let mut ret = Disavowal { key_image: [0; 32], challenge: [0; 32], response: [0; 32] };
+        ret.key_image.copy_from_slice(&bytes[..32]);
+        ret.challenge.copy_from_slice(&bytes[32..64]);
+        ret.response.copy_from_slice(&bytes[64..]);
+        ret
+    }
+
+    /// The key image, then the challenge and response
+    pub fn to_bytes(&self) -> [u8; 96] {
+        let mut ret = [0; 96];
+        ret[..32].copy_from_slice(&self.key_image);
+        ret[32..64].copy_from_slice(&self.challenge);
+        ret[64..].copy_from_slice(&self.response);
+        ret
+    }
+
+    /// The disavowing key's key image for the proof's scope
+    pub fn key_image(&self) -> &[u8; 32] {
+        &self.key_image
+    }
+}
+
+/// Show that `sk` did not make `proof`, a linkable proof of `message` over
+/// `pks` in `scope` with the key image `key_image`
+///
+/// See the [module documentation](self) for what this reveals. Fails with
+/// [`ProveError::IsTheSigner`] if `sk` made the proof. The proof itself is
+/// not checked, though [`verify_disavowal`] checks it.
+pub fn disavow(
+    proof: &Proof,
+    key_image: &[u8; 32],
+    pks: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+    sk: &SecretKey,
+) -> Result<Disavowal, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let base = scope_point(scope)?;
+    let own_image = (sk.0 * base).compress().to_bytes();
+    if own_image == *key_image {
+        return Err(ProveError::IsTheSigner);
+    }
+    // The proof's key image is only hashed, so need not be a valid point
+    let params = ring.params(message)?;
+
+    let mut rng = [0; 32];
+    OsRng.try_fill_bytes(&mut rng).map_err(|_| ProveError::Rng)?;
+    let mut nonce_eng = NonceHash::engine();
+    nonce_eng.input(&own_image);
+    nonce_eng.input(sk.as_bytes());
+    nonce_eng.input(proof.as_bytes());
+    nonce_eng.input(&rng);
+    let r = Scalar::from_bytes_mod_order(NonceHash::from_engine(nonce_eng).into_inner());
+    let nonces = [&r * &constants::ED25519_BASEPOINT_TABLE, r * base];
+    let c = challenge(&sk.to_public(), &own_image, &nonces, &base, key_image, &params.into_inner(), proof);
+    Ok(Disavowal { key_image: own_image, challenge: c.to_bytes(), response: (r + c * sk.0).to_bytes() })
+}
+
+/// Verify that `pk` did not make `proof`, a linkable proof of `message`
+/// over `pks` in `scope` with the key image `key_image`, as `disavowal`
+/// says
+///
+/// The proof is verified too, so on success it is valid and by some other
+/// member of the ring.
+pub fn verify_disavowal(
+    disavowal: &Disavowal,
+    proof: &Proof,
+    key_image: &[u8; 32],
+    pks: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+    pk: &PublicKey,
+) -> Result<(), VerifyError> {
+    verify_linkable(proof, key_image, pks, message, scope)?;
+    let link = link_of(key_image, scope)?;
+    let own_image = key_image_point(&disavowal.key_image).map_err(|_| VerifyError::BadDisavowal)?;
+    // Compare points, as several encodings may decode to the same one
+    if own_image == link.image {
+        return Err(VerifyError::BadDisavowal);
+    }
+    let c = Scalar::from_canonical_bytes(disavowal.challenge).ok_or(VerifyError::BadDisavowal)?;
+    let s = Scalar::from_canonical_bytes(disavowal.response).ok_or(VerifyError::BadDisavowal)?;
+    let nonces = [EdwardsPoint::vartime_double_scalar_mul_basepoint(&c, &-pk.0, &s), s * link.base - c * own_image];
+    let params = Ring::prepare::<VerifyError>(pks)?.params(message)?;
+    match challenge(pk, &disavowal.key_image, &nonces, &link.base, key_image, &params.into_inner(), proof) == c {
+        true => Ok(()),
+        false => Err(VerifyError::BadDisavowal),
+    }
+}
+
+/// The challenge of a disavowal's Chaum-Pedersen proof, committing to the
+/// key and its key image, the nonces, and the linkable proof with its scope
+/// point, key image, and the params of its ring and message
+fn challenge(
+    pk: &PublicKey,
+    own_image: &[u8; 32],
+    nonces: &[EdwardsPoint; 2],
+    base: &EdwardsPoint,
+    key_image: &[u8; 32],
+    params: &[u8; 32],
+    proof: &Proof,
+) -> Scalar {
+    let mut eng = DisavowalHash::engine();
+    eng.input(&pk.serialize());
+    eng.input(own_image);
+    for nonce in nonces {
+        eng.input(&nonce.compress().to_bytes());
+    }
+    eng.input(&base.compress().to_bytes());
+    eng.input(key_image);
+    eng.input(params);
+    eng.input(proof.as_bytes());
+    Scalar::from_bytes_mod_order(DisavowalHash::from_engine(eng).into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prove_linkable, testkit};
+
+    #[test]
+    fn round_trip() {
+        let (sks, pks) = testkit::deterministic_keys(b"disavowal tests", 5);
+        let (proof, image) = prove_linkable(&pks, b"msg", b"scope", &sks[1]).unwrap();
+        for (n, sk) in sks.iter().enumerate().filter(|&(n, _)| n != 1) {
+            let disavowal = disavow(&proof, &image, &pks, b"msg", b"scope", sk).unwrap();
+            verify_disavowal(&disavowal, &proof, &image, &pks, b"msg", b"scope", &pks[n]).unwrap();
+            let parsed = Disavowal::from_bytes(&disavowal.to_bytes());
+            assert_eq!(parsed, disavowal);
+            verify_disavowal(&parsed, &proof, &image, &pks, b"msg", b"scope", &pks[n]).unwrap();
+
+            // It is the key image of a proof the key would make in the scope
+            let (_, own) = prove_linkable(&pks, b"other", b"scope", sk).unwrap();
+            assert_eq!(disavowal.key_image(), &own);
+        }
+        // Someone outside the ring did not make the proof either
+        let outsider = testkit::deterministic_keys(b"disavowal outsider", 1).0.remove(0);
+        let disavowal = disavow(&proof, &image, &pks, b"msg", b"scope", &outsider).unwrap();
+        verify_disavowal(&disavowal, &proof, &image, &pks, b"msg", b"scope", &outsider.to_public()).unwrap();
+    }
+
+    #[test]
+    fn signer_cannot_disavow() {
+        let (sks, pks) = testkit::deterministic_keys(b"disavowal tests", 5);
+        let (proof, image) = prove_linkable(&pks, b"msg", b"scope", &sks[1]).unwrap();
+        assert_eq!(disavow(&proof, &image, &pks, b"msg", b"scope", &sks[1]), Err(ProveError::IsTheSigner));
+
+        // Nor by borrowing another member's disavowal, nor by offering a key
+        // image of another scope, whose proof is then of the wrong relation
+        let check = |d: &Disavowal| verify_disavowal(d, &proof, &image, &pks, b"msg", b"scope", &pks[1]);
+        let other = disavow(&proof, &image, &pks, b"msg", b"scope", &sks[3]).unwrap();
+        assert_eq!(check(&other), Err(VerifyError::BadDisavowal));
+        let (_, elsewhere) = prove_linkable(&pks, b"msg", b"other scope", &sks[1]).unwrap();
+        let mut bytes = other.to_bytes();
+        bytes[..32].copy_from_slice(&elsewhere);
+        assert_eq!(check(&Disavowal::from_bytes(&bytes)), Err(VerifyError::BadDisavowal));
+        // Giving the proof's own key image is never a disavowal
+        bytes[..32].copy_from_slice(&image);
+        assert_eq!(check(&Disavowal::from_bytes(&bytes)), Err(VerifyError::BadDisavowal));
+    }
+
+    #[test]
+    fn bound_to_the_proof() {
+        let (sks, pks) = testkit::deterministic_keys(b"disavowal tests", 5);
+        let (proof, image) = prove_linkable(&pks, b"msg", b"scope", &sks[1]).unwrap();
+        let disavowal = disavow(&proof, &image, &pks, b"msg", b"scope", &sks[3]).unwrap();
+        let result = verify_disavowal(&disavowal, &proof, &image, &pks, b"msg", b"scope", &pks[2]);
+        assert_eq!(result, Err(VerifyError::BadDisavowal));
+
+        let (again, again_image) = prove_linkable(&pks, b"msg", b"scope", &sks[1]).unwrap();
+        assert_eq!(again_image, image);
+        let result = verify_disavowal(&disavowal, &again, &image, &pks, b"msg", b"scope", &pks[3]);
+        assert_eq!(result, Err(VerifyError::BadDisavowal));
+        let result = verify_disavowal(&disavowal, &proof, &image, &pks, b"other", b"scope", &pks[3]);
+        assert_eq!(result, Err(VerifyError::BadProof));
+
+        let mut bytes = disavowal.to_bytes();
+        bytes[64] ^= 1;
+        let result = verify_disavowal(&Disavowal::from_bytes(&bytes), &proof, &image, &pks, b"msg", b"scope", &pks[3]);
+        assert_eq!(result, Err(VerifyError::BadDisavowal));
+        assert_eq!(VerifyError::BadDisavowal.code(), "bad_disavowal");
+        assert_eq!(ProveError::IsTheSigner.code(), "is_signer");
+    }
+}
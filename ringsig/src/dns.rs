@@ -0,0 +1,584 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Keys Published in DNS
+//!
+//! People may publish their keys in DNS in two ways:
+//!
+//! * An OPENPGPKEY record (RFC 7929) at `<hash>._openpgpkey.<domain>`, for
+//!   the email address `<local>@<domain>`, holds an OpenPGP public key. Its
+//!   ed25519 primary key and subkeys may be used directly as ring members.
+//! * SSHFP records (RFC 4255) at a host name hold only the fingerprints of
+//!   the host's ssh keys, so they can confirm keys obtained elsewhere, such
+//!   as from a known_hosts file, but cannot supply them.
+//!
+//! This module does not do any networking. It builds DNS queries and parses
+//! the responses and record data, which the caller must fetch, for example
+//! with DNS-over-HTTPS. Nothing here checks DNSSEC signatures; a response
+//! is only as trustworthy as the resolver which produced it, which reports
+//! whether it validated the answer in [`Response::authenticated`].
+
+use bitcoin_hashes::hex::ToHex;
+use bitcoin_hashes::{sha1, sha256, Hash};
+use std::fmt;
+
+use crate::keys::{self, PublicKey};
+use crate::radix64::base64_encode;
+
+/// The record type of OPENPGPKEY records
+pub const OPENPGPKEY: u16 = 61;
+/// The record type of SSHFP records
+pub const SSHFP: u16 = 44;
+
+/// The record class of everything on the internet
+const CLASS_IN: u16 = 1;
+/// SSHFP algorithm number of ed25519 keys
+const SSHFP_ED25519: u8 = 4;
+/// OpenPGP public key packet tag
+const TAG_PUBLIC_KEY: u8 = 6;
+/// OpenPGP public subkey packet tag
+const TAG_PUBLIC_SUBKEY: u8 = 14;
+/// OpenPGP algorithm number of EdDSA, identifying the curve by OID
+const ALGO_EDDSA_LEGACY: u8 = 22;
+/// OpenPGP algorithm number of Ed25519 (RFC 9580)
+const ALGO_ED25519: u8 = 27;
+/// The OID of Ed25519 as it appears in EdDSA keys
+const ED25519_OID: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x0f, 0x01];
+
+/// DNS-related error
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// A name could not be put in a DNS query
+    BadName(String),
+    /// An OPENPGPKEY lookup was given something other than an email address
+    BadEmail(String),
+    /// A message, record or packet ended in the middle of a field
+    Truncated,
+    /// A DNS message was not a response, or was truncated by the server
+    BadResponse(&'static str),
+    /// The server reported an error, with this response code
+    Rcode(u8),
+    /// The OpenPGP data did not parse
+    BadPacket(&'static str),
+    /// An OpenPGP key claimed to be ed25519 but was not a usable point
+    BadKey(keys::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::BadName(ref name) => write!(f, "`{}` is not a valid DNS name", name),
+            Error::BadEmail(ref email) => write!(f, "`{}` is not an email address", email),
+            Error::Truncated => f.write_str("data ended unexpectedly"),
+            Error::BadResponse(e) => f.write_str(e),
+            Error::Rcode(2) => f.write_str("server failure (is the DNSSEC signature broken?)"),
+            Error::Rcode(5) => f.write_str("the server refused the query"),
+            Error::Rcode(n) => write!(f, "the server returned response code {}", n),
+            Error::BadPacket(e) => write!(f, "bad OpenPGP key: {}", e),
+            Error::BadKey(ref e) => write!(f, "bad OpenPGP ed25519 key: {}", e),
+        }
+    }
+}
+
+impl Error {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Error::BadName(_) => "bad_name",
+            Error::BadEmail(_) => "bad_email",
+            Error::Truncated => "truncated",
+            Error::BadResponse(_) => "bad_response",
+            Error::Rcode(_) => "dns_error",
+            Error::BadPacket(_) => "bad_packet",
+            Error::BadKey(ref e) => e.code(),
+        }
+    }
+}
+
+/// A key found in DNS, and where it came from
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Member {
+    pub key: PublicKey,
+    /// A description of the record, e.g. `SSHFP example.com (SHA-256)`
+    pub provenance: String,
+}
+
+impl Member {
+    /// The key as an ssh public key line, with the provenance as its comment
+    pub fn key_line(&self) -> String {
+        format!("ssh-ed25519 {} {}", base64_encode(&self.key.ssh_blob()), self.provenance)
+    }
+}
+
+/// The name at which the OPENPGPKEY record for an email address is published
+pub fn openpgpkey_name(email: &str) -> Result<String, Error> {
+    match email.rsplit_once('@') {
+        Some((local, domain)) if !local.is_empty() && !domain.is_empty() => {
+            let hash = sha256::Hash::hash(local.as_bytes());
+            Ok(format!("{}._openpgpkey.{}", hash[..28].to_hex(), domain))
+        }
+        _ => Err(Error::BadEmail(email.to_owned())),
+    }
+}
+
+/// Encode a query for the records of type `rtype` at `name`
+///
+/// The ID is zero, as RFC 8484 recommends for DNS-over-HTTPS, and the query
+/// asks for recursion and for the server to say whether it validated the
+/// answer with DNSSEC.
+pub fn query(name: &str, rtype: u16) -> Result<Vec<u8>, Error> {
+    let bad_name = || Error::BadName(name.to_owned());
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() || name.len() > 253 {
+        return Err(bad_name());
+    }
+    // ID, flags RD and AD, one question
+    let mut ret = vec![0, 0, 0x01, 0x20, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(bad_name());
+        }
+        ret.push(label.len() as u8);
+        ret.extend_from_slice(label.as_bytes());
+    }
+    ret.push(0);
+    ret.extend_from_slice(&rtype.to_be_bytes());
+    ret.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(ret)
+}
+
+/// The records in a DNS response
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Response {
+    /// Whether the server claims to have validated the answer with DNSSEC
+    pub authenticated: bool,
+    /// The data of each record of the requested type
+    pub records: Vec<Vec<u8>>,
+}
+
+/// Parse a DNS response, returning the data of the answers of type `rtype`
+///
+/// A response saying that the name does not exist is treated as having no
+/// records.
+pub fn parse_response(msg: &[u8], rtype: u16) -> Result<Response, Error> {
+    let mut r = Reader(msg);
+    let (_id, flags, qdcount, ancount) = (r.u16()?, r.u16()?, r.u16()?, r.u16()?);
+    r.take(4)?;
+    if flags & 0x8000 == 0 {
+        return Err(Error::BadResponse("message is a query, not a response"));
+    }
+    if flags & 0x0200 != 0 {
+        return Err(Error::BadResponse("response was truncated by the server"));
+    }
+    let mut ret = Response { authenticated: flags & 0x0020 != 0, records: vec![] };
+    match (flags & 0xf) as u8 {
+        0 => {}
+        // NXDOMAIN
+        3 => return Ok(ret),
+        rcode => return Err(Error::Rcode(rcode)),
+    }
+    for _ in 0..qdcount {
+        r.skip_name()?;
+        r.take(4)?;
+    }
+    for _ in 0..ancount {
+        r.skip_name()?;
+        let (ty, class) = (r.u16()?, r.u16()?);
+        r.take(4)?;
+        let len = r.u16()?;
+        let data = r.take(len as usize)?;
+        // Anything else, such as a CNAME leading to the records or their
+        // RRSIGs, is skipped
+        if ty == rtype && class == CLASS_IN {
+            ret.records.push(data.to_vec());
+        }
+    }
+    Ok(ret)
+}
+
+/// Reads big-endian fields from the front of a slice
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.0.len() < n {
+            return Err(Error::Truncated);
+        }
+        let (ret, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(ret)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Skip a possibly-compressed domain name
+    fn skip_name(&mut self) -> Result<(), Error> {
+        loop {
+            match self.u8()? {
+                0 => return Ok(()),
+                // A pointer to the rest of the name, which always ends it
+                len if len & 0xc0 == 0xc0 => return self.take(1).map(|_| ()),
+                len if len & 0xc0 == 0 => {
+                    self.take(len as usize)?;
+                }
+                _ => return Err(Error::BadResponse("name has an unknown label type")),
+            }
+        }
+    }
+}
+
+/// An ed25519 key from an OpenPGP public key
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OpenpgpKey {
+    pub key: PublicKey,
+    /// The OpenPGP fingerprint, in upper-case hex as gpg displays it
+    pub fingerprint: String,
+    /// Whether this is a subkey, rather than the primary key
+    pub subkey: bool,
+}
+
+/// Extract the ed25519 keys from a sequence of OpenPGP packets, such as
+/// the data of an OPENPGPKEY record
+///
+/// Keys of other types, and all other packets, are ignored; in particular
+/// none of the self-signatures are checked, nor whether the keys have
+/// expired or been revoked.
+pub fn openpgp_keys(data: &[u8]) -> Result<Vec<OpenpgpKey>, Error> {
+    let mut r = Reader(data);
+    let mut ret = vec![];
+    while !r.0.is_empty() {
+        let (tag, body) = packet(&mut r)?;
+        if tag == TAG_PUBLIC_KEY || tag == TAG_PUBLIC_SUBKEY {
+            if let Some((key, fingerprint)) = public_key_packet(body)? {
+                ret.push(OpenpgpKey { key, fingerprint, subkey: tag == TAG_PUBLIC_SUBKEY });
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Read an OpenPGP packet, in either the old or the new format, returning
+/// its tag and body
+fn packet<'a>(r: &mut Reader<'a>) -> Result<(u8, &'a [u8]), Error> {
+    let header = r.u8()?;
+    if header & 0x80 == 0 {
+        return Err(Error::BadPacket("packet header does not have its high bit set"));
+    }
+    let (tag, len) = if header & 0x40 != 0 {
+        let len = match r.u8()? {
+            n @ 0..=191 => n as usize,
+            n @ 192..=223 => ((n as usize - 192) << 8) + r.u8()? as usize + 192,
+            255 => r.u32()? as usize,
+            _ => return Err(Error::BadPacket("partial body lengths are not allowed in keys")),
+        };
+        (header & 0x3f, len)
+    } else {
+        let len = match header & 3 {
+            0 => r.u8()? as usize,
+            1 => r.u16()? as usize,
+            2 => r.u32()? as usize,
+            _ => r.0.len(),
+        };
+        ((header >> 2) & 0xf, len)
+    };
+    Ok((tag, r.take(len)?))
+}
+
+/// Parse the body of a public key or subkey packet, returning the key and
+/// its fingerprint if it is an ed25519 key of a version we understand
+fn public_key_packet(body: &[u8]) -> Result<Option<(PublicKey, String)>, Error> {
+    let mut r = Reader(body);
+    let fingerprint = match r.u8()? {
+        4 => {
+            let mut prefix = vec![0x99];
+            prefix.extend_from_slice(&(body.len() as u16).to_be_bytes());
+            sha1::Hash::hash(&[&prefix[..], body].concat()).to_hex()
+        }
+        6 => {
+            let mut prefix = vec![0x9b];
+            prefix.extend_from_slice(&(body.len() as u32).to_be_bytes());
+            sha256::Hash::hash(&[&prefix[..], body].concat()).to_hex()
+        }
+        _ => return Ok(None),
+    };
+    let version = body[0];
+    let _created = r.u32()?;
+    let algorithm = r.u8()?;
+    if version == 6 {
+        let _material_len = r.u32()?;
+    }
+    let point = match algorithm {
+        ALGO_ED25519 => r.take(32)?,
+        ALGO_EDDSA_LEGACY => {
+            let oid_len = r.u8()?;
+            if r.take(oid_len as usize)? != ED25519_OID {
+                return Ok(None);
+            }
+            // An MPI of the point prefixed with 0x40, meaning "native form"
+            let bits = r.u16()?;
+            match r.take((bits as usize).div_ceil(8))? {
+                [0x40, point @ ..] if point.len() == 32 => point,
+                _ => return Err(Error::BadPacket("EdDSA point is not 32 bytes in native form")),
+            }
+        }
+        _ => return Ok(None),
+    };
+    let key = PublicKey::parse(point).map_err(Error::BadKey)?;
+    Ok(Some((key, fingerprint.to_ascii_uppercase())))
+}
+
+/// The ring members published in the OPENPGPKEY records of `email`
+pub fn openpgpkey_members(email: &str, records: &[Vec<u8>]) -> Result<Vec<Member>, Error> {
+    let mut ret: Vec<Member> = vec![];
+    for record in records {
+        for key in openpgp_keys(record)? {
+            if ret.iter().any(|member| member.key == key.key) {
+                continue;
+            }
+            let kind = if key.subkey { "subkey" } else { "key" };
+            ret.push(Member { key: key.key, provenance: format!("OPENPGPKEY {} {} {}", email, kind, key.fingerprint) });
+        }
+    }
+    Ok(ret)
+}
+
+/// An SSHFP record
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Sshfp {
+    /// The key algorithm; 4 is ed25519
+    pub algorithm: u8,
+    /// The hash algorithm; 1 is SHA-1 and 2 is SHA-256
+    pub fingerprint_type: u8,
+    pub fingerprint: Vec<u8>,
+}
+
+impl Sshfp {
+    /// Parse the data of an SSHFP record
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        match *data {
+            [algorithm, fingerprint_type, ref fingerprint @ ..] => {
+                Ok(Sshfp { algorithm, fingerprint_type, fingerprint: fingerprint.to_vec() })
+            }
+            _ => Err(Error::Truncated),
+        }
+    }
+
+    /// The name of the hash algorithm, if it is one we know
+    pub fn hash_name(&self) -> Option<&'static str> {
+        match self.fingerprint_type {
+            1 => Some("SHA-1"),
+            2 => Some("SHA-256"),
+            _ => None,
+        }
+    }
+
+    /// Whether the record is a fingerprint of `key`
+    pub fn matches(&self, key: &PublicKey) -> bool {
+        if self.algorithm != SSHFP_ED25519 {
+            return false;
+        }
+        let blob = key.ssh_blob();
+        match self.fingerprint_type {
+            1 => sha1::Hash::hash(&blob)[..] == self.fingerprint[..],
+            2 => sha256::Hash::hash(&blob)[..] == self.fingerprint[..],
+            _ => false,
+        }
+    }
+}
+
+/// The keys among `candidates` which match the SSHFP records of `host`
+pub fn sshfp_members(host: &str, records: &[Vec<u8>], candidates: &[PublicKey]) -> Result<Vec<Member>, Error> {
+    let records = records.iter().map(|data| Sshfp::parse(data)).collect::<Result<Vec<_>, _>>()?;
+    let mut ret: Vec<Member> = vec![];
+    for key in candidates {
+        if ret.iter().any(|member| member.key == *key) {
+            continue;
+        }
+        if let Some(record) = records.iter().find(|record| record.matches(key)) {
+            let hash = record.hash_name().unwrap_or("unknown hash");
+            ret.push(Member { key: *key, provenance: format!("SSHFP {} ({})", host, hash) });
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_hashes::hex::FromHex;
+
+    /// `gpg --export --export-options export-minimal` of a key with an
+    /// ed25519 primary key, an ed25519 signing subkey and a cv25519
+    /// encryption subkey
+    const ALICE: &[u8] = include_bytes!("../testdata/alice.openpgpkey");
+
+    fn key(line: &str) -> PublicKey {
+        PublicKey::parse_pk_line(line).unwrap()
+    }
+
+    #[test]
+    fn openpgpkey_names() {
+        // From RFC 7929
+        assert_eq!(
+            openpgpkey_name("hugh@example.com").unwrap(),
+            "c93f1e400f26708f98cb19d936620da35eec8f72e57f9eec01c1afd6._openpgpkey.example.com",
+        );
+        assert_eq!(openpgpkey_name("example.com"), Err(Error::BadEmail("example.com".into())));
+        assert!(openpgpkey_name("@example.com").is_err());
+        assert!(openpgpkey_name("hugh@").is_err());
+    }
+
+    #[test]
+    fn openpgp_gpg_export() {
+        let keys = openpgp_keys(ALICE).unwrap();
+        assert_eq!(keys.len(), 2);
+        // As printed by `gpg --export-ssh-key <fingerprint>!`
+        assert_eq!(keys[0].key, key("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFhqB2EgGx9ioLUHm8FCXANzr4fnjPpaJRdcvZ5ukiVq"));
+        assert_eq!(keys[0].fingerprint, "D0BB80DCCFD39A1414286F780A6885CEC221B538");
+        assert!(!keys[0].subkey);
+        assert_eq!(keys[1].key, key("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAwenUr9Pk0uAsjd8UvdffJGvYXHcEy3RGvBoteo/OXd"));
+        assert_eq!(keys[1].fingerprint, "3F5594DAB7945774BED3F6D8E41900AC34C29AC9");
+        assert!(keys[1].subkey);
+
+        let members = openpgpkey_members("alice@example.com", &[ALICE.to_vec(), ALICE.to_vec()]).unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].provenance, "OPENPGPKEY alice@example.com key D0BB80DCCFD39A1414286F780A6885CEC221B538");
+        assert_eq!(members[1].provenance, "OPENPGPKEY alice@example.com subkey 3F5594DAB7945774BED3F6D8E41900AC34C29AC9");
+        assert_eq!(
+            members[1].key_line(),
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAwenUr9Pk0uAsjd8UvdffJGvYXHcEy3RGvBoteo/OXd \
+             OPENPGPKEY alice@example.com subkey 3F5594DAB7945774BED3F6D8E41900AC34C29AC9",
+        );
+
+        assert_eq!(openpgp_keys(&ALICE[..ALICE.len() - 1]), Err(Error::Truncated));
+        assert_eq!(openpgp_keys(&ALICE[1..]).unwrap_err().code(), "bad_packet");
+    }
+
+    #[test]
+    fn openpgp_v6() {
+        let point = key("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1");
+        // A version 6 Ed25519 public key packet with a new-format header,
+        // then a subkey packet with an old-format header, then a user ID
+        let mut body = vec![6, 0x65, 0x00, 0x00, 0x00, ALGO_ED25519, 0, 0, 0, 32];
+        body.extend_from_slice(&point.serialize());
+        let mut data = vec![0xc0 | TAG_PUBLIC_KEY, body.len() as u8];
+        data.extend_from_slice(&body);
+        data.extend_from_slice(&[0x80 | (TAG_PUBLIC_SUBKEY << 2), body.len() as u8]);
+        data.extend_from_slice(&body);
+        data.extend_from_slice(b"\xcd\x05alice");
+
+        let keys = openpgp_keys(&data).unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!((keys[0].key, keys[0].subkey), (point, false));
+        assert_eq!((keys[1].key, keys[1].subkey), (point, true));
+        assert_eq!(keys[0].fingerprint.len(), 64);
+        // Only one member per key
+        assert_eq!(openpgpkey_members("alice@example.com", &[data.clone()]).unwrap().len(), 1);
+
+        // A point of order 2
+        let mut bad = data.clone();
+        bad[12..44].copy_from_slice(&[0xff; 32]);
+        bad[12] = 0xec;
+        bad[43] = 0x7f;
+        assert_eq!(openpgp_keys(&bad).unwrap_err().code(), "torsion_key");
+        // Version 5 keys are skipped
+        data[2] = 5;
+        assert_eq!(openpgp_keys(&data).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn sshfp() {
+        // `ssh-keygen -r example.com -f testdata/test-key.pub`
+        let test_key = key("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1");
+        let sha1 = Vec::<u8>::from_hex("04010f48a5c6f7b62b6dfca4ff4c9d5eb5361ceae499").unwrap();
+        let sha256 = Vec::<u8>::from_hex("040217e92090407d0b6e23c88967fe31fe5204661d1dad509939f0bcbcf511c822eb").unwrap();
+        let other = key("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIFhqB2EgGx9ioLUHm8FCXANzr4fnjPpaJRdcvZ5ukiVq");
+
+        let record = Sshfp::parse(&sha256).unwrap();
+        assert_eq!((record.algorithm, record.fingerprint_type, record.hash_name()), (4, 2, Some("SHA-256")));
+        assert!(record.matches(&test_key));
+        assert!(!record.matches(&other));
+        assert!(Sshfp::parse(&sha1).unwrap().matches(&test_key));
+        // The same fingerprint, claimed to be of an RSA key
+        let mut rsa = sha256.clone();
+        rsa[0] = 1;
+        assert!(!Sshfp::parse(&rsa).unwrap().matches(&test_key));
+        assert_eq!(Sshfp::parse(&[4]), Err(Error::Truncated));
+
+        let members = sshfp_members("example.com", &[sha1, sha256, rsa], &[other, test_key, test_key]).unwrap();
+        assert_eq!(members, [Member { key: test_key, provenance: "SSHFP example.com (SHA-1)".into() }]);
+        assert!(sshfp_members("example.com", &[], &[test_key]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn queries() {
+        assert_eq!(
+            query("example.com.", SSHFP).unwrap(),
+            b"\x00\x00\x01\x20\x00\x01\x00\x00\x00\x00\x00\x00\x07example\x03com\x00\x00\x2c\x00\x01",
+        );
+        assert!(query("example..com", SSHFP).is_err());
+        assert!(query("", SSHFP).is_err());
+        assert!(query(&format!("{}.com", "a".repeat(64)), SSHFP).is_err());
+    }
+
+    #[test]
+    fn responses() {
+        // A response to `query("example.com", SSHFP)`, with the AD flag, a
+        // SHA-1 SSHFP record, an RRSIG record (truncated to its type
+        // covered field) and a SHA-256 SSHFP record, all using a pointer
+        // to the question's name
+        let response = Vec::<u8>::from_hex(
+            "0000 81a0 0001 0003 0000 0000 076578616d706c6503636f6d00 002c 0001 \
+             c00c 002c 0001 00000e10 0016 04010f48a5c6f7b62b6dfca4ff4c9d5eb5361ceae499 \
+             c00c 002e 0001 00000e10 0002 002c \
+             c00c 002c 0001 00000e10 0022 040217e92090407d0b6e23c88967fe31fe5204661d1dad509939f0bcbcf511c822eb"
+                .replace(' ', "")
+                .as_str(),
+        )
+        .unwrap();
+        let parsed = parse_response(&response, SSHFP).unwrap();
+        assert!(parsed.authenticated);
+        assert_eq!(parsed.records.len(), 2);
+        assert_eq!(Sshfp::parse(&parsed.records[1]).unwrap().fingerprint_type, 2);
+        assert!(parse_response(&response, OPENPGPKEY).unwrap().records.is_empty());
+        assert_eq!(parse_response(&response[..response.len() - 1], SSHFP), Err(Error::Truncated));
+
+        let flags = |hi: u8, lo: u8| {
+            let mut msg = response.clone();
+            msg[2] = hi;
+            msg[3] = lo;
+            parse_response(&msg, SSHFP)
+        };
+        // NXDOMAIN, without AD
+        assert_eq!(flags(0x81, 0x83), Ok(Response::default()));
+        assert_eq!(flags(0x81, 0x82), Err(Error::Rcode(2)));
+        assert_eq!(flags(0x01, 0x20).unwrap_err().code(), "bad_response");
+        assert_eq!(flags(0x83, 0x80).unwrap_err().code(), "bad_response");
+    }
+}
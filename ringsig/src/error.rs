@@ -14,7 +14,320 @@
 // If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
 //
 
-/// Error type for the entire library
-pub enum Error {
+use std::fmt;
+
+use crate::keys::PublicKey;
+use crate::ristretto::RistrettoPublicKey;
+
+/// Error producing a ring signature
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ProveError {
+    /// The ring has no public keys
+    EmptyKeySet,
+    /// The secret key, whose public key is given, is not in the ring
+    ///
+    /// The key is boxed to keep the error small, as an unpacked curve point
+    /// is 160 bytes.
+    KeyNotInRing { sk_public: Box<PublicKey> },
+    /// The ring lists this key more than once
+    DuplicateKey(Box<PublicKey>),
+    /// The secret key is not the key at this index of the sorted ring
+    WrongSignerIndex { index: usize },
+    /// The ring has more than `u32::MAX` keys
+    TooManyKeys,
+    /// A message or scope is longer than `u64::MAX` bytes
+    InputTooLong,
+    /// The ring contains the identity point, for which anyone can sign
+    WeakKey,
+    /// The operating system's random number generator failed
+    Rng,
+    /// A threshold proof was asked for with a threshold of zero
+    ZeroThreshold,
+    /// A threshold proof needs exactly as many secret keys as its threshold
+    SignerCount { threshold: usize, got: usize },
+    /// The same secret key was given twice for a threshold proof
+    DuplicateSigner,
+    /// The proof to be claimed was not made claimable with this secret key
+    NotTheSigner,
+    /// The proof to be disavowed has this secret key's key image, so was
+    /// made with it
+    IsTheSigner,
+    /// The proof to extend to a larger ring does not verify
+    OldProof(VerifyError),
+    /// The proof to extend to a larger ring has another key's key image
+    KeyImageMismatch,
+    /// A key of the old ring is missing from the larger ring
+    KeyDropped(Box<PublicKey>),
+    /// The secret key's Ristretto255 public key, given, is not in the ring
+    RistrettoKeyNotInRing { sk_public: Box<RistrettoPublicKey> },
+    /// The Ristretto255 ring lists this key more than once
+    DuplicateRistrettoKey(Box<RistrettoPublicKey>),
+    /// Reading a streamed message failed
+    Read(String),
+}
+
+impl fmt::Display for ProveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProveError::EmptyKeySet => f.write_str("no public keys"),
+            ProveError::KeyNotInRing { ref sk_public } => {
+                write!(f, "secret key {} did not match any public key", sk_public.fingerprint())
+            }
+            ProveError::DuplicateKey(ref pk) => write!(f, "key {} is in the ring more than once", pk.fingerprint()),
+            ProveError::RistrettoKeyNotInRing { ref sk_public } => {
+                write!(f, "secret key {} did not match any public key", sk_public)
+            }
+            ProveError::DuplicateRistrettoKey(ref pk) => write!(f, "key {} is in the ring more than once", pk),
+            ProveError::WrongSignerIndex { index } => {
+                write!(f, "secret key is not the key at index {} of the sorted ring", index)
+            }
+            ProveError::TooManyKeys => f.write_str("too many public keys"),
+            ProveError::InputTooLong => f.write_str("input too long"),
+            ProveError::WeakKey => f.write_str("the ring contains the identity point"),
+            ProveError::Rng => f.write_str("rng error"),
+            ProveError::ZeroThreshold => f.write_str("threshold must be at least 1"),
+            ProveError::SignerCount { threshold, got } => {
+                write!(f, "threshold of {} needs that many secret keys, got {}", threshold, got)
+            }
+            ProveError::DuplicateSigner => f.write_str("the same secret key was given twice"),
+            ProveError::NotTheSigner => f.write_str("the proof was not made claimable with this secret key"),
+            ProveError::IsTheSigner => f.write_str("the proof was made with this secret key, so cannot be disavowed"),
+            ProveError::OldProof(ref e) => write!(f, "the proof to extend does not verify: {}", e),
+            ProveError::KeyImageMismatch => f.write_str("the proof to extend was not made with this secret key"),
+            ProveError::KeyDropped(ref pk) => {
+                write!(f, "key {} of the old ring is not in the new one", pk.fingerprint())
+            }
+            ProveError::Read(ref e) => write!(f, "reading message: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ProveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            ProveError::OldProof(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl ProveError {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        match *self {
+            ProveError::EmptyKeySet => "empty_ring",
+            ProveError::KeyNotInRing { .. } => "signer_not_in_ring",
+            ProveError::DuplicateKey(_) => "duplicate_key",
+            ProveError::RistrettoKeyNotInRing { .. } => "signer_not_in_ring",
+            ProveError::DuplicateRistrettoKey(_) => "duplicate_key",
+            ProveError::WrongSignerIndex { .. } => "wrong_signer_index",
+            ProveError::TooManyKeys => "too_many_keys",
+            ProveError::InputTooLong => "input_too_long",
+            ProveError::WeakKey => "weak_key",
+            ProveError::Rng => "rng",
+            ProveError::ZeroThreshold => "zero_threshold",
+            ProveError::SignerCount { .. } => "signer_count",
+            ProveError::DuplicateSigner => "duplicate_signer",
+            ProveError::NotTheSigner => "not_signer",
+            ProveError::IsTheSigner => "is_signer",
+            ProveError::OldProof(_) => "bad_old_proof",
+            ProveError::KeyImageMismatch => "key_image_mismatch",
+            ProveError::KeyDropped(_) => "key_dropped",
+            ProveError::Read(_) => "read_failed",
+        }
+    }
+}
+
+/// Error parsing a [`crate::Proof`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ProofError {
+    /// The proof is not a multiple of 32 bytes, or is too short, so is the
+    /// wrong length for any ring
+    WrongLength { got: usize },
+    /// The proof is not valid hex
+    InvalidHex(String),
+    /// The proof's version byte is not one this library knows
+    UnknownVersion(u8),
+    /// The threshold proof is not a key image and proof for each of its
+    /// `threshold` signers, all of the same length
+    WrongThresholdLength { threshold: u32, got: usize },
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProofError::WrongLength { got } => {
+                write!(f, "proof wrong length: {} bytes is not 32 bytes for each key plus 32", got)
+            }
+            ProofError::InvalidHex(ref e) => write!(f, "proof is not valid hex: {}", e),
+            ProofError::UnknownVersion(version) => write!(f, "unknown proof version {}", version),
+            ProofError::WrongThresholdLength { threshold, got } => write!(
+                f,
+                "threshold proof wrong length: {} bytes is not a key image and proof for each of {} signers",
+                got, threshold,
+            ),
+        }
+    }
 }
 
+impl std::error::Error for ProofError {}
+
+impl ProofError {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        match *self {
+            ProofError::WrongLength { .. } | ProofError::WrongThresholdLength { .. } => "proof_length",
+            ProofError::InvalidHex(_) => "bad_hex",
+            ProofError::UnknownVersion(_) => "proof_version",
+        }
+    }
+}
+
+/// Error verifying a ring signature
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum VerifyError {
+    /// The ring has no public keys
+    EmptyKeySet,
+    /// The ring lists this key more than once
+    ///
+    /// The key is boxed to keep the error small, as an unpacked curve point
+    /// is 160 bytes.
+    DuplicateKey(Box<PublicKey>),
+    /// The Ristretto255 ring lists this key more than once
+    DuplicateRistrettoKey(Box<RistrettoPublicKey>),
+    /// The proof is the wrong length for the ring
+    ///
+    /// If the length is that of a proof for some other ring, the size of
+    /// that ring is given, as the ring or the proof is then likely the
+    /// wrong one; otherwise the proof was probably cut short.
+    WrongProofLength { expected: usize, got: usize, proof_ring_size: Option<usize> },
+    /// A message given in pieces was not the length promised for it
+    WrongMessageLength { expected: u64, got: u64 },
+    /// The proof could not be parsed
+    Malformed(ProofError),
+    /// The `s` value at this index, counting in 32-byte chunks of the proof
+    /// from the challenge at index 0, is not fully reduced
+    NonCanonicalScalar { index: usize },
+    /// The proof did not verify
+    BadProof,
+    /// The key image is not a curve point
+    KeyImageNotOnCurve,
+    /// The key image is not in the prime-order subgroup
+    KeyImageTorsion,
+    /// The ring has more than `u32::MAX` keys
+    TooManyKeys,
+    /// A message or scope is longer than `u64::MAX` bytes
+    InputTooLong,
+    /// The ring contains the identity point, for which anyone can sign
+    WeakKey,
+    /// The progress callback asked to stop before the proof was checked
+    Cancelled,
+    /// The threshold proof is by fewer signers than needed
+    ThresholdNotMet { needed: usize, got: usize },
+    /// Two key images of a threshold proof are the same, so one signer
+    /// signed twice
+    DuplicateKeyImage,
+    /// The key claiming a proof is not in its ring
+    ClaimantNotInRing,
+    /// The claim is not by the proof's signer, or is not valid at all
+    BadClaim,
+    /// The disavowal is not valid, or is by the proof's signer
+    BadDisavowal,
+    /// A key of the old ring is missing from the larger ring
+    KeyDropped(Box<PublicKey>),
+    /// Reading a streamed message failed
+    Read(String),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VerifyError::EmptyKeySet => f.write_str("no public keys"),
+            VerifyError::DuplicateKey(ref pk) => write!(f, "key {} is in the ring more than once", pk.fingerprint()),
+            VerifyError::DuplicateRistrettoKey(ref pk) => write!(f, "key {} is in the ring more than once", pk),
+            VerifyError::WrongProofLength { expected, got, proof_ring_size: Some(size) } => write!(
+                f,
+                "proof wrong length: expected {} bytes, got {}, which is a proof for a ring of {} key{} not {}",
+                expected,
+                got,
+                size,
+                if size == 1 { "" } else { "s" },
+                expected / 32 - 1,
+            ),
+            VerifyError::WrongProofLength { expected, got, proof_ring_size: None } => {
+                write!(f, "proof wrong length: expected {} bytes, got {}, which is no ring's proof", expected, got)
+            }
+            VerifyError::WrongMessageLength { expected, got } => {
+                write!(f, "message wrong length: expected {} bytes, got {}", expected, got)
+            }
+            VerifyError::Malformed(ref e) => fmt::Display::fmt(e, f),
+            VerifyError::NonCanonicalScalar { index } => {
+                write!(f, "proof contains a non-canonical scalar at index {}", index)
+            }
+            VerifyError::BadProof => f.write_str("bad proof"),
+            VerifyError::KeyImageNotOnCurve => f.write_str("key image is not a curve point"),
+            VerifyError::KeyImageTorsion => f.write_str("key image is not in the prime-order subgroup"),
+            VerifyError::TooManyKeys => f.write_str("too many public keys"),
+            VerifyError::InputTooLong => f.write_str("input too long"),
+            VerifyError::WeakKey => f.write_str("the ring contains the identity point"),
+            VerifyError::Cancelled => f.write_str("verification cancelled"),
+            VerifyError::ThresholdNotMet { needed, got } => {
+                write!(f, "proof is by {} signers, {} needed", got, needed)
+            }
+            VerifyError::DuplicateKeyImage => f.write_str("two key images are the same"),
+            VerifyError::ClaimantNotInRing => f.write_str("the claiming key is not in the ring"),
+            VerifyError::BadClaim => f.write_str("bad claim"),
+            VerifyError::BadDisavowal => f.write_str("bad disavowal"),
+            VerifyError::KeyDropped(ref pk) => {
+                write!(f, "key {} of the old ring is not in the new one", pk.fingerprint())
+            }
+            VerifyError::Read(ref e) => write!(f, "reading message: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            VerifyError::Malformed(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProofError> for VerifyError {
+    fn from(e: ProofError) -> Self {
+        VerifyError::Malformed(e)
+    }
+}
+
+impl VerifyError {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        match *self {
+            VerifyError::EmptyKeySet => "empty_ring",
+            VerifyError::DuplicateKey(_) => "duplicate_key",
+            VerifyError::DuplicateRistrettoKey(_) => "duplicate_key",
+            VerifyError::WrongProofLength { .. } => "proof_length",
+            VerifyError::WrongMessageLength { .. } => "message_length",
+            VerifyError::Malformed(ref e) => e.code(),
+            VerifyError::NonCanonicalScalar { .. } => "non_canonical",
+            VerifyError::BadProof => "bad_proof",
+            VerifyError::KeyImageNotOnCurve | VerifyError::KeyImageTorsion => "bad_key_image",
+            VerifyError::TooManyKeys => "too_many_keys",
+            VerifyError::InputTooLong => "input_too_long",
+            VerifyError::WeakKey => "weak_key",
+            VerifyError::Cancelled => "cancelled",
+            VerifyError::ThresholdNotMet { .. } => "threshold_not_met",
+            VerifyError::DuplicateKeyImage => "duplicate_key_image",
+            VerifyError::ClaimantNotInRing => "claimant_not_in_ring",
+            VerifyError::BadClaim => "bad_claim",
+            VerifyError::BadDisavowal => "bad_disavowal",
+            VerifyError::KeyDropped(_) => "key_dropped",
+            VerifyError::Read(_) => "read_failed",
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Extending a linkable proof to a larger ring
+//!
+//! The author of a confession may later want a larger anonymity set for it,
+//! while showing that the new proof is theirs and not an impersonator's. A
+//! key image depends only on the secret key and the scope, so a linkable
+//! proof of the same message in the same scope over a larger ring has the
+//! same key image, and [`extend_ring`] makes one. [`verify_extension`]
+//! checks both proofs with the one key image, and that every key of the old
+//! ring is in the new one.
+//!
+//! The old proof stays valid, and its smaller ring is still public, so the
+//! author remains among its keys: extending widens the set for those who
+//! see only the new proof.
+
+use std::collections::HashSet;
+
+use crate::keys::{PublicKey, SecretKey};
+use crate::linkable::{self, prove_linkable, verify_linkable};
+use crate::{Proof, ProveError, VerifyError};
+
+/// Make a linkable proof of `message` over `new_ring` in `scope`, with the
+/// same key image as `old_proof`, a linkable proof by `sk` of the same
+/// message over `old_ring` in the same scope
+///
+/// Every key of `old_ring` must be in `new_ring`. The old proof must
+/// verify, so an extension with another message or scope is refused, and
+/// its key image must be that of `sk`.
+pub fn extend_ring(
+    old_proof: &Proof,
+    key_image: &[u8; 32],
+    old_ring: &[PublicKey],
+    new_ring: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+    sk: &SecretKey,
+) -> Result<Proof, ProveError> {
+    verify_linkable(old_proof, key_image, old_ring, message, scope).map_err(ProveError::OldProof)?;
+    if linkable::key_image(sk, scope) != *key_image {
+        return Err(ProveError::KeyImageMismatch);
+    }
+    if let Some(pk) = dropped(old_ring, new_ring) {
+        return Err(ProveError::KeyDropped(Box::new(pk)));
+    }
+    let (proof, new_image) = prove_linkable(new_ring, message, scope, sk)?;
+    debug_assert_eq!(new_image, *key_image);
+    Ok(proof)
+}
+
+/// Verify that `new_proof` extends `old_proof`, as made by [`extend_ring`]
+///
+/// Both must be linkable proofs of `message` in `scope` with `key_image`,
+/// over `old_ring` and `new_ring` respectively, and every key of
+/// `old_ring` must be in `new_ring`.
+pub fn verify_extension(
+    old_proof: &Proof,
+    new_proof: &Proof,
+    key_image: &[u8; 32],
+    old_ring: &[PublicKey],
+    new_ring: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+) -> Result<(), VerifyError> {
+    if let Some(pk) = dropped(old_ring, new_ring) {
+        return Err(VerifyError::KeyDropped(Box::new(pk)));
+    }
+    verify_linkable(old_proof, key_image, old_ring, message, scope)?;
+    verify_linkable(new_proof, key_image, new_ring, message, scope)
+}
+
+/// A key of `old_ring` which is not in `new_ring`, if there is one
+fn dropped(old_ring: &[PublicKey], new_ring: &[PublicKey]) -> Option<PublicKey> {
+    let new_keys: HashSet<[u8; 32]> = new_ring.iter().map(PublicKey::serialize).collect();
+    old_ring.iter().find(|pk| !new_keys.contains(&pk.serialize())).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    #[test]
+    fn extend() {
+        let (sks, mut new_ring) = testkit::deterministic_keys(b"extension tests", 8);
+        let old_ring = new_ring[..3].to_vec();
+        new_ring.reverse();
+        let (old, image) = prove_linkable(&old_ring, b"msg", b"scope", &sks[1]).unwrap();
+        let new = extend_ring(&old, &image, &old_ring, &new_ring, b"msg", b"scope", &sks[1]).unwrap();
+        verify_linkable(&new, &image, &new_ring, b"msg", b"scope").unwrap();
+        verify_extension(&old, &new, &image, &old_ring, &new_ring, b"msg", b"scope").unwrap();
+
+        // An extension to the same ring is pointless, but not wrong
+        let same = extend_ring(&old, &image, &old_ring, &old_ring, b"msg", b"scope", &sks[1]).unwrap();
+        verify_extension(&old, &same, &image, &old_ring, &old_ring, b"msg", b"scope").unwrap();
+    }
+
+    #[test]
+    fn refused() {
+        let (sks, new_ring) = testkit::deterministic_keys(b"extension tests", 8);
+        let old_ring = new_ring[..3].to_vec();
+        let (old, image) = prove_linkable(&old_ring, b"msg", b"scope", &sks[1]).unwrap();
+        let extend = |ring: &[PublicKey], message: &[u8], sk| {
+            extend_ring(&old, &image, &old_ring, ring, message, b"scope", sk)
+        };
+
+        // Every old key must stay
+        let dropped = [&new_ring[..2], &new_ring[3..]].concat();
+        assert_eq!(extend(&dropped, b"msg", &sks[1]), Err(ProveError::KeyDropped(Box::new(old_ring[2]))));
+        // The message must be the same
+        assert_eq!(extend(&new_ring, b"other", &sks[1]), Err(ProveError::OldProof(VerifyError::BadProof)));
+        // Another member cannot extend it
+        assert_eq!(extend(&new_ring, b"msg", &sks[0]), Err(ProveError::KeyImageMismatch));
+        assert_eq!(extend(&new_ring, b"msg", &sks[6]), Err(ProveError::KeyImageMismatch));
+        assert_eq!(ProveError::KeyImageMismatch.code(), "key_image_mismatch");
+
+        // Nor pass off a proof of their own as an extension
+        let (impostor, other_image) = prove_linkable(&new_ring, b"msg", b"scope", &sks[6]).unwrap();
+        assert_ne!(other_image, image);
+        let result = verify_extension(&old, &impostor, &image, &old_ring, &new_ring, b"msg", b"scope");
+        assert_eq!(result, Err(VerifyError::BadProof));
+        let result = verify_extension(&old, &impostor, &other_image, &old_ring, &new_ring, b"msg", b"scope");
+        assert_eq!(result, Err(VerifyError::BadProof));
+
+        // Verifying checks the rings and message as well
+        let new = extend(&new_ring, b"msg", &sks[1]).unwrap();
+        let result = verify_extension(&old, &new, &image, &old_ring, &dropped, b"msg", b"scope");
+        assert_eq!(result, Err(VerifyError::KeyDropped(Box::new(old_ring[2]))));
+        let other = prove_linkable(&new_ring, b"other", b"scope", &sks[1]).unwrap().0;
+        let result = verify_extension(&old, &other, &image, &old_ring, &new_ring, b"msg", b"scope");
+        assert_eq!(result, Err(VerifyError::BadProof));
+        let result = verify_extension(&old, &other, &image, &old_ring, &new_ring, b"other", b"scope");
+        assert_eq!(result, Err(VerifyError::BadProof));
+    }
+}
@@ -0,0 +1,263 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Text Formats
+//!
+//! Proofs can be written down in several ways:
+//!
+//!   * `Hex`: the bare proof as a hex string.
+//!   * `Armor`: the bare proof in ASCII armor, with a CRC24 checksum.
+//!   * `Json`: a JSON object with the ring, message and proof.
+//!   * `Clearsign`: the message in the clear, followed by the ring and the
+//!     armored proof, in the style of PGP cleartext signatures.
+//!   * `Bundle`: the JSON object in ASCII armor, for channels which mangle
+//!     whitespace or quotes.
+//!
+//! This module recognizes each of these and converts between the text-only
+//! ones. Interpreting the JSON is left to the caller.
+
+use crate::radix64::{self, base64_encode, crc24_bytes, radix64_decode_with_crc};
+use bitcoin_hashes::hex::FromHex;
+use std::fmt;
+
+pub const PROOF_BEGIN: &str = "-----BEGIN CREDIBLE CONFESSION-----";
+pub const PROOF_END: &str = "-----END CREDIBLE CONFESSION-----";
+pub const BUNDLE_BEGIN: &str = "-----BEGIN CREDIBLE CONFESSION BUNDLE-----";
+pub const BUNDLE_END: &str = "-----END CREDIBLE CONFESSION BUNDLE-----";
+pub const SIGNED_MESSAGE_BEGIN: &str = "-----BEGIN CREDIBLE CONFESSION SIGNED MESSAGE-----";
+pub const RING_BEGIN: &str = "-----BEGIN CREDIBLE CONFESSION RING-----";
+pub const RING_END: &str = "-----END CREDIBLE CONFESSION RING-----";
+
+/// Width of the base64 lines inside armor
+const ARMOR_WIDTH: usize = 64;
+
+/// Text format parsing error
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The begin string did not appear
+    NoBeginStr(&'static str),
+    /// The end string did not appear
+    NoEndStr(&'static str),
+    /// There was no `=XXXX` checksum line
+    NoChecksum,
+    /// The checksum did not match the data
+    BadChecksum,
+    /// The hex was malformed
+    BadHex,
+    /// Radix-64 parsing
+    Radix64(crate::radix64::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NoBeginStr(s) => write!(f, "no {} line", s),
+            Error::NoEndStr(s) => write!(f, "no {} line", s),
+            Error::NoChecksum => f.write_str("no checksum line"),
+            Error::BadChecksum => f.write_str("checksum does not match the data"),
+            Error::BadHex => f.write_str("malformed hex"),
+            Error::Radix64(ref e) => write!(f, "bad radix-64: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Radix64(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<crate::radix64::Error> for Error {
+    fn from(e: crate::radix64::Error) -> Self {
+        Error::Radix64(e)
+    }
+}
+
+/// The formats which [`classify`] recognizes
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Hex,
+    Armor,
+    Json,
+    Clearsign,
+    Bundle,
+}
+
+/// Guess the format of some text, or `None` if it is not a known format
+///
+/// This only looks at the overall shape of the text; the result may still
+/// fail to parse.
+pub fn classify(s: &str) -> Option<Kind> {
+    let s = s.trim();
+    if s.starts_with('{') {
+        Some(Kind::Json)
+    } else if s.starts_with(SIGNED_MESSAGE_BEGIN) {
+        Some(Kind::Clearsign)
+    } else if s.starts_with(BUNDLE_BEGIN) {
+        Some(Kind::Bundle)
+    } else if s.starts_with(PROOF_BEGIN) {
+        Some(Kind::Armor)
+    } else if !s.is_empty() && s.len().is_multiple_of(2) && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        Some(Kind::Hex)
+    } else {
+        None
+    }
+}
+
+/// Wrap data in ASCII armor with a CRC24 checksum line
+pub fn armor(begin: &str, end: &str, data: &[u8]) -> String {
+    let b64 = base64_encode(data).replace('\n', "");
+    let mut ret = String::with_capacity(b64.len() * 65 / 64 + begin.len() + end.len() + 10);
+    ret += begin;
+    ret.push('\n');
+    for line in b64.as_bytes().chunks(ARMOR_WIDTH) {
+        ret += std::str::from_utf8(line).unwrap(); // base64 is ASCII
+        ret.push('\n');
+    }
+    ret.push('=');
+    ret += &base64_encode(&crc24_bytes(data));
+    ret.push('\n');
+    ret += end;
+    ret.push('\n');
+    ret
+}
+
+/// Extract and checksum the data from ASCII armor produced by [`armor`]
+pub fn dearmor(s: &str, begin: &'static str, end: &'static str) -> Result<Vec<u8>, Error> {
+    let start_idx = s.find(begin).ok_or(Error::NoBeginStr(begin))? + begin.len();
+    let end_idx = start_idx + s[start_idx..].find(end).ok_or(Error::NoEndStr(end))?;
+    match radix64_decode_with_crc(&s[start_idx..end_idx]) {
+        Err(radix64::Error::MissingCrc) => Err(Error::NoChecksum),
+        Err(radix64::Error::CrcMismatch { .. }) => Err(Error::BadChecksum),
+        result => Ok(result?),
+    }
+}
+
+/// Decode a hex-encoded proof
+pub fn from_hex(s: &str) -> Result<Vec<u8>, Error> {
+    Vec::<u8>::from_hex(s.trim()).map_err(|_| Error::BadHex)
+}
+
+/// A cleartext-signed message: the message itself, the ring as ssh public
+/// key lines, and the proof
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Clearsigned {
+    pub message: String,
+    pub public_keys: Vec<String>,
+    pub proof: Vec<u8>,
+}
+
+impl Clearsigned {
+    /// Render in the cleartext format
+    ///
+    /// As in RFC 4880 cleartext signatures, message lines which start with
+    /// `-` are escaped by prefixing `- `, so that they cannot be confused
+    /// with the markers. The message is followed by a newline which is not
+    /// part of it.
+    pub fn encode(&self) -> String {
+        let mut ret = String::new();
+        ret += SIGNED_MESSAGE_BEGIN;
+        ret += "\n\n";
+        for line in self.message.split('\n') {
+            if line.starts_with('-') {
+                ret += "- ";
+            }
+            ret += line;
+            ret.push('\n');
+        }
+        ret += RING_BEGIN;
+        ret.push('\n');
+        for line in &self.public_keys {
+            ret += line;
+            ret.push('\n');
+        }
+        ret += RING_END;
+        ret.push('\n');
+        ret += &armor(PROOF_BEGIN, PROOF_END, &self.proof);
+        ret
+    }
+
+    /// Parse text produced by [`Clearsigned::encode`]
+    pub fn decode(s: &str) -> Result<Self, Error> {
+        let start = s.find(SIGNED_MESSAGE_BEGIN).ok_or(Error::NoBeginStr(SIGNED_MESSAGE_BEGIN))?;
+        let rest = &s[start + SIGNED_MESSAGE_BEGIN.len()..];
+        let rest = rest.strip_prefix("\n\n").ok_or(Error::NoBeginStr(SIGNED_MESSAGE_BEGIN))?;
+        let ring_idx = rest.find(&format!("\n{}\n", RING_BEGIN)).ok_or(Error::NoBeginStr(RING_BEGIN))?;
+
+        let message = rest[..ring_idx]
+            .split('\n')
+            .map(|line| line.strip_prefix("- ").unwrap_or(line))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let rest = &rest[ring_idx + RING_BEGIN.len() + 2..];
+        let ring_end = rest.find(RING_END).ok_or(Error::NoEndStr(RING_END))?;
+        let public_keys = rest[..ring_end]
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+        let proof = dearmor(&rest[ring_end..], PROOF_BEGIN, PROOF_END)?;
+        Ok(Clearsigned { message, public_keys, proof })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn armor_roundtrip() {
+        for len in [0, 1, 2, 3, 64, 65, 200] {
+            let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let armored = armor(PROOF_BEGIN, PROOF_END, &data);
+            assert!(armored.lines().all(|line| line.len() <= ARMOR_WIDTH || line.starts_with("-----")));
+            assert_eq!(classify(&armored), Some(Kind::Armor));
+            assert_eq!(dearmor(&armored, PROOF_BEGIN, PROOF_END), Ok(data));
+        }
+
+        let armored = armor(PROOF_BEGIN, PROOF_END, b"some proof bytes");
+        let corrupted = armored.replacen("c29", "c39", 1);
+        assert_eq!(dearmor(&corrupted, PROOF_BEGIN, PROOF_END), Err(Error::BadChecksum));
+        assert_eq!(dearmor(&armored, BUNDLE_BEGIN, BUNDLE_END), Err(Error::NoBeginStr(BUNDLE_BEGIN)));
+    }
+
+    #[test]
+    fn clearsign_roundtrip() {
+        for message in ["", "one line", "trailing newline\n", "-----BEGIN CREDIBLE CONFESSION RING-----\n- x\n\n"] {
+            let cs = Clearsigned {
+                message: message.to_owned(),
+                public_keys: vec!["ssh-ed25519 AAAA a".into(), "ssh-ed25519 BBBB".into()],
+                proof: vec![1, 2, 3],
+            };
+            let encoded = cs.encode();
+            assert_eq!(classify(&encoded), Some(Kind::Clearsign));
+            assert_eq!(Clearsigned::decode(&encoded), Ok(cs));
+        }
+    }
+
+    #[test]
+    fn classification() {
+        assert_eq!(classify(" {\"version\": 1}\n"), Some(Kind::Json));
+        assert_eq!(classify("00ff\n"), Some(Kind::Hex));
+        assert_eq!(classify("00f"), None);
+        assert_eq!(classify("hello"), None);
+        assert_eq!(classify(&armor(BUNDLE_BEGIN, BUNDLE_END, b"{}")), Some(Kind::Bundle));
+    }
+}
@@ -14,7 +14,7 @@
 // If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
 //
 
-use bitcoin_hashes::sha256t_hash_newtype;
+use bitcoin_hashes::{sha256, sha256t_hash_newtype, Hash, HashEngine};
 
 const MIDSTATE_CHALLENGE_HASH: [u8; 32] = [
     0xe8, 0xb2, 0x2d, 0x66, 0xaf, 0x38, 0xce, 0x01, 0xa6, 0x7e, 0x49, 0x04, 0xec, 0x70, 0x25, 0xac,
@@ -31,6 +31,91 @@ const MIDSTATE_NONCE_HASH: [u8; 32] = [
     0x6c, 0x46, 0x3b, 0xd2, 0xf2, 0xc0, 0xec, 0x08, 0x4e, 0x2d, 0xda, 0x6d, 0x81, 0xf0, 0xbd, 0xcc,
 ];
 
+const MIDSTATE_RING_HASH: [u8; 32] = [
+    0x20, 0x06, 0x25, 0x8b, 0x2a, 0x4d, 0x33, 0xca, 0x00, 0xf3, 0x37, 0x03, 0x62, 0x31, 0x6f, 0xdc,
+    0x97, 0x98, 0x72, 0xa4, 0xb5, 0x1e, 0x98, 0x3c, 0xf3, 0xa2, 0x0e, 0xf6, 0x17, 0xfc, 0xbf, 0x58,
+];
+
+const MIDSTATE_SCOPE_HASH: [u8; 32] = [
+    0x17, 0xcd, 0x06, 0x47, 0x81, 0x66, 0xb7, 0x4c, 0xc3, 0x86, 0xcb, 0xe9, 0x16, 0xb8, 0x01, 0x79,
+    0x42, 0x1b, 0x58, 0xe0, 0xb2, 0xaa, 0xff, 0x65, 0x5c, 0x7f, 0xd5, 0x78, 0xbc, 0xee, 0xab, 0x95,
+];
+
+const MIDSTATE_LINK_HASH: [u8; 32] = [
+    0xaf, 0x2d, 0x36, 0x0f, 0x6a, 0xd1, 0x38, 0x3c, 0xc2, 0x32, 0x14, 0x5d, 0xa7, 0x74, 0x83, 0x0e,
+    0x96, 0x0a, 0xeb, 0x08, 0x9c, 0xe1, 0x08, 0x45, 0x5e, 0x28, 0xac, 0xf9, 0x8b, 0x4e, 0xbe, 0x59,
+];
+
+const MIDSTATE_ATTRIBUTES_HASH: [u8; 32] = [
+    0x96, 0xe9, 0x27, 0xb7, 0x9a, 0xdd, 0xe4, 0x3d, 0x35, 0x4b, 0xc9, 0x83, 0x49, 0xee, 0xd6, 0xb5,
+    0xda, 0xb6, 0x24, 0x8f, 0xc1, 0xd2, 0x0a, 0x34, 0x56, 0x8d, 0x6a, 0x8a, 0xd8, 0xfb, 0x23, 0x24,
+];
+
+const MIDSTATE_BUNDLE_HASH: [u8; 32] = [
+    0x86, 0xce, 0x96, 0x80, 0xec, 0x3f, 0xcf, 0x19, 0x02, 0xa3, 0xa8, 0x6e, 0xda, 0x74, 0xe3, 0x6e,
+    0x7f, 0x5a, 0x8d, 0x36, 0xb9, 0x86, 0xde, 0x3f, 0xed, 0xbe, 0x05, 0x94, 0xeb, 0xcd, 0xff, 0x11,
+];
+
+const MIDSTATE_MESSAGE_DIGEST: [u8; 32] = [
+    0x8f, 0xa6, 0x4a, 0x4a, 0x4e, 0xb6, 0xeb, 0x32, 0x33, 0x6c, 0x22, 0x5f, 0xfa, 0x7b, 0x5b, 0x19,
+    0x0d, 0xf8, 0xde, 0xaa, 0xa5, 0xfc, 0x0d, 0x3b, 0x94, 0x99, 0xba, 0x1b, 0xd5, 0xe1, 0xc5, 0x1d,
+];
+
+const MIDSTATE_PREHASHED_PARAMS_HASH: [u8; 32] = [
+    0x94, 0x04, 0x56, 0xc1, 0xaa, 0xd6, 0xef, 0x03, 0xbb, 0xff, 0x59, 0xe7, 0xb8, 0x4a, 0xfe, 0x8e,
+    0x5e, 0xce, 0x8b, 0xe9, 0x55, 0xc1, 0x72, 0x2f, 0xb2, 0x40, 0x54, 0x43, 0x9d, 0x39, 0xdc, 0xf0,
+];
+
+const MIDSTATE_THRESHOLD_PARAMS_HASH: [u8; 32] = [
+    0x5f, 0xb8, 0x48, 0x13, 0x29, 0x48, 0x53, 0x86, 0xa8, 0x42, 0x05, 0x9e, 0x2c, 0xf8, 0xef, 0xa2,
+    0xe8, 0x97, 0x0f, 0x28, 0x58, 0x65, 0x92, 0x06, 0x9f, 0xf7, 0xb1, 0x4b, 0x4f, 0xed, 0xa1, 0xb8,
+];
+
+const MIDSTATE_THRESHOLD_BASE_HASH: [u8; 32] = [
+    0xbc, 0x8e, 0x06, 0xe8, 0x05, 0xb9, 0xd9, 0xf7, 0xbd, 0x5e, 0x47, 0xfc, 0x40, 0xf7, 0xd0, 0x22,
+    0x23, 0x3c, 0x99, 0x2d, 0x40, 0x06, 0x0a, 0x8b, 0xc2, 0x98, 0x8c, 0x1a, 0x54, 0x4f, 0xc9, 0x1e,
+];
+
+const MIDSTATE_CLAIM_SECRET_HASH: [u8; 32] = [
+    0xcd, 0x30, 0x67, 0xd8, 0xe1, 0xbf, 0xc9, 0x8d, 0x70, 0x66, 0x2a, 0x7d, 0x05, 0xb0, 0x9e, 0x1b,
+    0xd3, 0xa2, 0xc1, 0x00, 0x00, 0x13, 0xd6, 0x2a, 0xd3, 0x28, 0xdb, 0xa7, 0xe9, 0x62, 0x4c, 0xe6,
+];
+
+const MIDSTATE_CLAIM_COMMITMENT_HASH: [u8; 32] = [
+    0xcc, 0xea, 0x0b, 0x79, 0x68, 0xa8, 0x09, 0xda, 0xb2, 0x16, 0xbc, 0x3f, 0xcc, 0xf6, 0x14, 0x12,
+    0x4c, 0x6d, 0xad, 0x90, 0x43, 0xf0, 0x60, 0x17, 0x01, 0xba, 0x37, 0x0d, 0xfb, 0x3a, 0x6b, 0xac,
+];
+
+const MIDSTATE_CLAIM_CHALLENGE_HASH: [u8; 32] = [
+    0x1c, 0x93, 0x70, 0xde, 0x5c, 0x96, 0x8c, 0xf4, 0x79, 0x6c, 0x5e, 0xc8, 0x1c, 0xc4, 0x1c, 0x07,
+    0xee, 0xab, 0xf0, 0xa4, 0x3c, 0x2f, 0x80, 0x66, 0xe6, 0xc0, 0xee, 0x67, 0xc3, 0x1b, 0x7d, 0x56,
+];
+
+const MIDSTATE_KEY_BINDING_HASH: [u8; 32] = [
+    0x03, 0x5c, 0x37, 0x0c, 0x92, 0xdd, 0x87, 0x0d, 0xb2, 0x14, 0xd9, 0x86, 0x84, 0x31, 0x1f, 0xbf,
+    0x08, 0xa0, 0x77, 0x32, 0x66, 0xed, 0xc1, 0xdf, 0x44, 0x93, 0xa8, 0xe6, 0xfd, 0x39, 0x36, 0x9b,
+];
+
+const MIDSTATE_DISAVOWAL_HASH: [u8; 32] = [
+    0xa9, 0xe7, 0x0b, 0x4a, 0xd3, 0x63, 0xd4, 0xd5, 0x28, 0x37, 0xce, 0xef, 0xdf, 0x2b, 0x19, 0x72,
+    0xbb, 0xa0, 0xae, 0xe5, 0x7e, 0xc7, 0x19, 0x24, 0xe9, 0xbd, 0x99, 0x70, 0xad, 0xc1, 0x81, 0x4a,
+];
+
+const MIDSTATE_RISTRETTO_PARAMS_HASH: [u8; 32] = [
+    0x53, 0x0a, 0xa7, 0x96, 0x62, 0x57, 0x11, 0x09, 0x36, 0xb2, 0xb4, 0x09, 0x96, 0x9d, 0x68, 0x21,
+    0x84, 0xde, 0xc1, 0x91, 0xc5, 0xae, 0xfb, 0xc8, 0xb1, 0x90, 0xd3, 0x2c, 0xf4, 0xa2, 0xe8, 0xd3,
+];
+
+const MIDSTATE_RISTRETTO_NONCE_HASH: [u8; 32] = [
+    0xa7, 0x7b, 0x57, 0xec, 0xc6, 0x9b, 0x18, 0x29, 0x6a, 0x68, 0xbd, 0xbc, 0xf5, 0x26, 0x57, 0xd4,
+    0x86, 0x50, 0xb4, 0xf2, 0x65, 0x08, 0xfd, 0x94, 0xff, 0xc2, 0x01, 0xd2, 0x8c, 0xf5, 0x8b, 0x29,
+];
+
+const MIDSTATE_RISTRETTO_CHALLENGE_HASH: [u8; 32] = [
+    0xf3, 0x09, 0xd4, 0x9f, 0x00, 0xbe, 0xd0, 0x3c, 0x0e, 0x77, 0x69, 0x44, 0x01, 0x29, 0xb2, 0xc2,
+    0xc3, 0x98, 0x53, 0xc2, 0xdd, 0xd2, 0x95, 0xb1, 0xfb, 0x20, 0xba, 0xc1, 0xf0, 0x3f, 0x56, 0xfe,
+];
+
 sha256t_hash_newtype!(
     ChallengeHash,
     ChallengeHashTag,
@@ -58,10 +143,178 @@ sha256t_hash_newtype!(
     false // whether to reverse the hash when serializing
 );
 
+sha256t_hash_newtype!(
+    RingHash,
+    RingHashTag,
+    MIDSTATE_RING_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions ring identifiers (sorted, deduplicated pks)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    ScopeHash,
+    ScopeHashTag,
+    MIDSTATE_SCOPE_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions linkable scopes (hashing to a curve point)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    LinkHash,
+    LinkHashTag,
+    MIDSTATE_LINK_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions linkable param hash (params, scope point and key image)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    AttributesHash,
+    AttributesHashTag,
+    MIDSTATE_ATTRIBUTES_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions signed attributes (message, timestamp and expiry)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    BundleHash,
+    BundleHashTag,
+    MIDSTATE_BUNDLE_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions bundles (the JSON of a bundle without its timestamps)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    MessageDigest,
+    MessageDigestTag,
+    MIDSTATE_MESSAGE_DIGEST,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions messages which are proven pre-hashed",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    PrehashedParamsHash,
+    PrehashedParamsHashTag,
+    MIDSTATE_PREHASHED_PARAMS_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions ringsig param hash (pks and message digest)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    ThresholdParamsHash,
+    ThresholdParamsHashTag,
+    MIDSTATE_THRESHOLD_PARAMS_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions threshold param hash (params and threshold)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    ThresholdBaseHash,
+    ThresholdBaseHashTag,
+    MIDSTATE_THRESHOLD_BASE_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions threshold proofs' shared key image base (hashing to a curve point)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    ClaimSecretHash,
+    ClaimSecretHashTag,
+    MIDSTATE_CLAIM_SECRET_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions claim secrets (params and secret key)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    ClaimCommitmentHash,
+    ClaimCommitmentHashTag,
+    MIDSTATE_CLAIM_COMMITMENT_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions claim commitments (params, claim secret and signer's nonce)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    ClaimChallengeHash,
+    ClaimChallengeHashTag,
+    MIDSTATE_CLAIM_CHALLENGE_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions claim signatures (nonce, key, claim secret, params and proof)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    KeyBindingHash,
+    KeyBindingHashTag,
+    MIDSTATE_KEY_BINDING_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions bindings of keys to other data",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    DisavowalHash,
+    DisavowalHashTag,
+    MIDSTATE_DISAVOWAL_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions disavowals (key, key images, nonces, linkable params and proof)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    RistrettoParamsHash,
+    RistrettoParamsHashTag,
+    MIDSTATE_RISTRETTO_PARAMS_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions Ristretto255 ringsig param hash (pks and message)",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    RistrettoNonceHash,
+    RistrettoNonceHashTag,
+    MIDSTATE_RISTRETTO_NONCE_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions Ristretto255 ringsig nonces and other secret data",
+    false // whether to reverse the hash when serializing
+);
+
+sha256t_hash_newtype!(
+    RistrettoChallengeHash,
+    RistrettoChallengeHashTag,
+    MIDSTATE_RISTRETTO_CHALLENGE_HASH,
+    64,
+    doc = "BIP-340 tagged hash for Crypto Confessions Ristretto255 ringsig challenge hashes",
+    false // whether to reverse the hash when serializing
+);
+
+/// The BIP-340 tagged hash of `data` under `tag`, i.e. the SHA256 of the
+/// SHA256 of `tag` twice, then `data`
+///
+/// Every hash type here is one of these, with its tag's prefix already
+/// hashed; this is for protocols built around them which need tags of
+/// their own.
+pub fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = sha256::Hash::hash(tag.as_bytes());
+    let mut engine = sha256::Hash::engine();
+    engine.input(&tag_hash[..]);
+    engine.input(&tag_hash[..]);
+    engine.input(data);
+    sha256::Hash::from_engine(engine).into_inner()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use bitcoin_hashes::{hex::ToHex, sha256, Hash, HashEngine};
+    use bitcoin_hashes::hex::ToHex;
 
     // Lifted from rust-bitcoin
     fn tag_engine(tag_name: &str) -> sha256::HashEngine {
@@ -97,5 +350,154 @@ mod tests {
                 .into_inner()[..]
                 .to_hex(),
         );
+
+        assert_eq!(
+            MIDSTATE_RING_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/Ring")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_SCOPE_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/Scope")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_LINK_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/Link")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_ATTRIBUTES_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/Attributes")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_BUNDLE_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/Bundle")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_MESSAGE_DIGEST[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/MessageDigest")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_PREHASHED_PARAMS_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/PrehashedParams")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_THRESHOLD_PARAMS_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/ThresholdParams")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_THRESHOLD_BASE_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/ThresholdBase")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_CLAIM_SECRET_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/ClaimSecret")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_CLAIM_COMMITMENT_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/ClaimCommitment")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_CLAIM_CHALLENGE_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/ClaimChallenge")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_KEY_BINDING_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/KeyBinding")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_DISAVOWAL_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/Disavowal")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_RISTRETTO_PARAMS_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/RistrettoParams")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_RISTRETTO_NONCE_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/RistrettoNonce")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+
+        assert_eq!(
+            MIDSTATE_RISTRETTO_CHALLENGE_HASH[..].to_hex(),
+            tag_engine("CryptoConfessions-1.0/RistrettoChallenge")
+                .midstate()
+                .into_inner()[..]
+                .to_hex(),
+        );
+    }
+
+    #[test]
+    fn tagged_hash_matches_types() {
+        let data = b"some data to hash";
+        assert_eq!(tagged_hash("CryptoConfessions-1.0/KeyBinding", data), KeyBindingHash::hash(data).into_inner());
+        assert_eq!(tagged_hash("CryptoConfessions-1.0/Params", data), ParamsHash::hash(data).into_inner());
+        assert_eq!(tagged_hash("CryptoConfessions-1.0/Nonce", b""), NonceHash::hash(b"").into_inner());
+        assert_ne!(tagged_hash("CryptoConfessions-1.0/Nonce", data), NonceHash::hash(b"").into_inner());
+        // Any tag, not only ours
+        let mut engine = tag_engine("BIP0340/challenge");
+        engine.input(data);
+        assert_eq!(tagged_hash("BIP0340/challenge", data), sha256::Hash::from_engine(engine).into_inner());
     }
 }
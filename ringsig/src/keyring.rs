@@ -0,0 +1,210 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Key Rings
+//!
+//! A keys file lists ssh public keys one per line, in the same format as
+//! `authorized_keys` or GitHub's `.keys` files. Blank lines and lines
+//! starting with `#` are ignored. [`KeyRing`] edits such a file while
+//! keeping everything it does not understand, including comments, intact.
+
+use std::fmt;
+
+use crate::keys::{Error, PublicKey};
+
+/// A single line of a keys file
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Line {
+    /// A public key, and the line it came from
+    Key { key: PublicKey, text: String },
+    /// A line which should contain a key but did not parse
+    Invalid { text: String, error: Error },
+    /// A blank line or a comment
+    Comment(String),
+}
+
+/// The contents of a keys file
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct KeyRing {
+    lines: Vec<Line>,
+}
+
+/// Whether a line of a keys file is a comment or blank
+fn is_comment(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#')
+}
+
+impl KeyRing {
+    /// An empty key ring
+    pub fn new() -> Self {
+        KeyRing::default()
+    }
+
+    /// Parse the contents of a keys file
+    ///
+    /// This does not fail; lines which cannot be parsed are kept as
+    /// [`Line::Invalid`] and may be found with [`KeyRing::invalid`].
+    pub fn parse(s: &str) -> Self {
+        let lines = s
+            .lines()
+            .map(|line| {
+                let text = line.trim_end().to_owned();
+                if is_comment(&text) {
+                    return Line::Comment(text);
+                }
+                match PublicKey::parse_pk_line(&text) {
+                    Ok(key) => Line::Key { key, text },
+                    Err(error) => Line::Invalid { text, error },
+                }
+            })
+            .collect();
+        KeyRing { lines }
+    }
+
+    /// Every line, in order
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// The keys, in order, along with the lines they came from
+    pub fn entries(&self) -> impl Iterator<Item = (&PublicKey, &str)> {
+        self.lines.iter().filter_map(|line| match *line {
+            Line::Key { ref key, ref text } => Some((key, &text[..])),
+            _ => None,
+        })
+    }
+
+    /// The keys, in order
+    pub fn keys(&self) -> Vec<PublicKey> {
+        self.entries().map(|(key, _)| *key).collect()
+    }
+
+    /// The lines which did not parse, with their (zero-based) line numbers
+    pub fn invalid(&self) -> impl Iterator<Item = (usize, &str, &Error)> {
+        self.lines.iter().enumerate().filter_map(|(n, line)| match *line {
+            Line::Invalid { ref text, ref error } => Some((n, &text[..], error)),
+            _ => None,
+        })
+    }
+
+    /// Whether the ring contains a key
+    pub fn contains(&self, pk: &PublicKey) -> bool {
+        self.entries().any(|(key, _)| key == pk)
+    }
+
+    /// Add a key from an ssh public key line
+    ///
+    /// Returns `Ok(false)`, leaving the ring unchanged, if the key is
+    /// already present.
+    pub fn add_line(&mut self, line: &str) -> Result<bool, Error> {
+        let text = line.trim().to_owned();
+        let key = PublicKey::parse_pk_line(&text)?;
+        if self.contains(&key) {
+            return Ok(false);
+        }
+        self.lines.push(Line::Key { key, text });
+        Ok(true)
+    }
+
+    /// Add a line without checking that it is a usable key
+    ///
+    /// Lines which do parse are still deduplicated, as with [`KeyRing::add_line`].
+    pub fn add_line_unchecked(&mut self, line: &str) -> bool {
+        match self.add_line(line) {
+            Ok(added) => added,
+            Err(error) => {
+                let text = line.trim().to_owned();
+                let present = self.lines.iter().any(|l| matches!(l, Line::Invalid { text: t, .. } if *t == text));
+                if !present {
+                    self.lines.push(Line::Invalid { text, error });
+                }
+                !present
+            }
+        }
+    }
+
+    /// Remove every line with the given key fingerprint, returning how many
+    /// were removed
+    pub fn remove_fingerprint(&mut self, fingerprint: &str) -> usize {
+        let before = self.lines.len();
+        self.lines.retain(|line| match *line {
+            Line::Key { ref key, .. } => key.fingerprint() != fingerprint,
+            _ => true,
+        });
+        before - self.lines.len()
+    }
+}
+
+impl fmt::Display for KeyRing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            match *line {
+                Line::Key { ref text, .. } | Line::Invalid { ref text, .. } | Line::Comment(ref text) => {
+                    writeln!(f, "{}", text)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_1: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10";
+    const KEY_2: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana";
+    const TORSION: &str =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAII0PQoSjaDulROj7qwNNsJ1cCa+sqlWsKs3e8nemW9J+ apoelstra-torsion";
+
+    #[test]
+    fn roundtrip_preserves_comments() {
+        let file = format!("# the team\n{}\n\n# interns\n{}\n", KEY_1, KEY_2);
+        let ring = KeyRing::parse(&file);
+        assert_eq!(ring.to_string(), file);
+        assert_eq!(ring.keys().len(), 2);
+        assert_eq!(ring.invalid().count(), 0);
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let mut ring = KeyRing::parse(&format!("# comment\n{}\n", KEY_1));
+        assert_eq!(ring.add_line(KEY_1), Ok(false));
+        assert_eq!(ring.add_line(&format!("  {} with a comment", KEY_1)), Ok(false));
+        assert_eq!(ring.add_line(KEY_2), Ok(true));
+        assert!(matches!(ring.add_line(TORSION), Err(Error::TorsionKey(_))));
+        assert_eq!(ring.to_string(), format!("# comment\n{}\n{}\n", KEY_1, KEY_2));
+
+        let fp = PublicKey::parse_pk_line(KEY_1).unwrap().fingerprint();
+        assert_eq!(ring.remove_fingerprint(&fp), 1);
+        assert_eq!(ring.remove_fingerprint(&fp), 0);
+        assert_eq!(ring.to_string(), format!("# comment\n{}\n", KEY_2));
+    }
+
+    #[test]
+    fn unchecked() {
+        let mut ring = KeyRing::new();
+        assert!(ring.add_line_unchecked(TORSION));
+        assert!(!ring.add_line_unchecked(TORSION));
+        assert!(ring.add_line_unchecked(KEY_1));
+        assert!(!ring.add_line_unchecked(KEY_1));
+        assert_eq!(ring.keys().len(), 1);
+        assert_eq!(ring.invalid().map(|(n, _, _)| n).collect::<Vec<_>>(), [0]);
+        assert_eq!(KeyRing::parse(&ring.to_string()), ring);
+    }
+}
@@ -15,13 +15,27 @@
 //
 
 use crate::armor::FromArmor;
+use crate::radix64::base64_encode;
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use bitcoin_hashes::{sha256, sha512, Hash};
 use curve25519_dalek::{
     constants,
     edwards::{CompressedEdwardsY, EdwardsPoint},
     scalar::Scalar,
+    traits::IsIdentity,
 };
 
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash;
+use std::ops::Deref;
+use std::path::Path;
+use std::{fs, io};
+use std::str::FromStr;
 use subtle::{ConstantTimeEq, Choice};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, ZeroizeOnDrop};
 
 /// Key-related error
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -42,12 +56,66 @@ pub enum Error {
     },
     /// Key was not in the prime order group
     TorsionKey(Vec<u8>),
+    /// Key was the identity point, perhaps encoded non-canonically, for which
+    /// anyone can sign
+    WeakKey(Vec<u8>),
     /// Key did not parse as a public key (e.g. point not on curve)
     InvalidKey(Vec<u8>),
     /// Radix-64 parsing
     Radix64(crate::radix64::Error),
     /// ASCII-armor related error (stringified to avoid a loop in the error types
     Armor(String),
+    /// A JSON Web Key was not valid JSON, or lacked a field
+    InvalidJwk(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::EmptyKey => f.write_str("empty key"),
+            Error::NoKey => f.write_str("key type with no key data"),
+            Error::WrongKeyType { ref expected, ref got } => {
+                write!(f, "wrong key type: expected {}, got {}", expected, got)
+            }
+            Error::WrongKeyLength { expected, got } => {
+                write!(f, "wrong key length: expected {} bytes, got {}", expected, got)
+            }
+            Error::TorsionKey(ref key) => write!(f, "key {} is not in the prime-order group", key.to_hex()),
+            Error::WeakKey(ref key) => write!(f, "key {} is the identity point", key.to_hex()),
+            Error::InvalidKey(ref key) => write!(f, "key {} is not a valid curve point", key.to_hex()),
+            Error::Radix64(ref e) => write!(f, "decoding key: {}", e),
+            Error::Armor(ref e) => write!(f, "parsing key: {}", e),
+            Error::InvalidJwk(ref e) => write!(f, "invalid JWK: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Radix64(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Error::EmptyKey => "empty_key",
+            Error::NoKey => "no_key",
+            Error::WrongKeyType { .. } => "wrong_key_type",
+            Error::WrongKeyLength { .. } => "wrong_key_length",
+            Error::TorsionKey(_) => "torsion_key",
+            Error::WeakKey(_) => "weak_key",
+            Error::InvalidKey(_) => "invalid_key",
+            Error::Radix64(_) => "bad_base64",
+            Error::Armor(_) => "bad_armor",
+            Error::InvalidJwk(_) => "bad_jwk",
+        }
+    }
 }
 
 impl From<crate::radix64::Error> for Error {
@@ -56,6 +124,71 @@ impl From<crate::radix64::Error> for Error {
     }
 }
 
+/// A format which [`PublicKey::parse_any`] tries
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum FormatName {
+    /// An "id_ed25519.pub" line, as [`PublicKey::parse_pk_line`] parses
+    SshLine,
+    /// The base64 blob of such a line, without the key type or comment
+    BareBase64,
+    /// The 32-byte key as 64 lowercase hex digits
+    Hex,
+}
+
+impl fmt::Display for FormatName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatName::SshLine => f.write_str("an SSH public key line"),
+            FormatName::BareBase64 => f.write_str("bare base64"),
+            FormatName::Hex => f.write_str("hex"),
+        }
+    }
+}
+
+/// A public key which [`PublicKey::parse_any`] could not parse in any format
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseAnyError {
+    /// Every format tried, in order, and why it failed
+    pub tried: Vec<(FormatName, Error)>,
+}
+
+impl fmt::Display for ParseAnyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("not a recognised public key")?;
+        for (n, (format, e)) in self.tried.iter().enumerate() {
+            write!(f, "{}as {}, {}", if n == 0 { ": " } else { "; " }, format, e)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseAnyError {}
+
+impl ParseAnyError {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        "unrecognised_key"
+    }
+}
+
+/// The DER encoding of an Ed25519 SubjectPublicKeyInfo up to the key: a
+/// SEQUENCE of the algorithm identifier, OID 1.3.101.112 with no
+/// parameters, and a BIT STRING of the key with no unused bits (RFC 8410)
+const ED25519_DER_HEADER: [u8; 12] = [0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
+/// The key type of an OpenSSH certificate for an ed25519 key
+pub const CERT_KEY_TYPE: &str = "ssh-ed25519-cert-v01@openssh.com";
+
+/// Details of the OpenSSH certificate a public key was given in
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CertificateInfo {
+    /// The serial number the CA gave the certificate
+    pub serial: u64,
+    /// The CA's free-form identifier for the key, e.g. its owner's email
+    pub key_id: String,
+}
+
 /// A public key
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct PublicKey(pub(crate) EdwardsPoint);
@@ -66,6 +199,37 @@ impl PublicKey {
         self.0.compress().to_bytes()
     }
 
+    /// The SSH wire encoding of the key, i.e. the blob which appears
+    /// base64-encoded in the "id_ed25519.pub" format
+    pub(crate) fn ssh_blob(&self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(51);
+        ret.extend_from_slice(&11u32.to_be_bytes());
+        ret.extend_from_slice(b"ssh-ed25519");
+        ret.extend_from_slice(&32u32.to_be_bytes());
+        ret.extend_from_slice(&self.serialize());
+        ret
+    }
+
+    /// The key as a line of an "id_ed25519.pub" file, with a comment if given
+    pub fn to_ssh_line(&self, comment: Option<&str>) -> String {
+        match comment {
+            Some(comment) => format!("ssh-ed25519 {} {}", base64_encode(&self.ssh_blob()), comment),
+            None => format!("ssh-ed25519 {}", base64_encode(&self.ssh_blob())),
+        }
+    }
+
+    /// The key's fingerprint, in the `SHA256:...` form displayed by `ssh-keygen -l`
+    pub fn fingerprint(&self) -> String {
+        let b64 = base64_encode(&self.fingerprint_bytes());
+        format!("SHA256:{}", b64.trim_end_matches('='))
+    }
+
+    /// The hash behind [`PublicKey::fingerprint`]: SHA256 of the key's SSH
+    /// wire encoding, as OpenSSH computes it, rather than of the bare point
+    pub fn fingerprint_bytes(&self) -> [u8; 32] {
+        sha256::Hash::hash(&self.ssh_blob()).into_inner()
+    }
+
     /// Parse a public key from 32 bytes
     pub fn parse(data: &[u8]) -> Result<Self, Error> {
         if data.len() != 32 {
@@ -73,7 +237,12 @@ impl PublicKey {
         }
         match CompressedEdwardsY::from_slice(data).decompress() {
             Some(pt) => {
-                if pt.is_torsion_free() {
+                // The identity is torsion-free, but is the public key of the
+                // zero secret key. It has several encodings, with a sign bit
+                // or `y` of 1 + p, so this checks the point rather than bytes
+                if pt.is_identity() {
+                    Err(Error::WeakKey(data.to_vec()))
+                } else if pt.is_torsion_free() {
                     Ok(PublicKey(pt))
                 } else {
                     Err(Error::TorsionKey(data.to_vec()))
@@ -83,8 +252,134 @@ impl PublicKey {
         }
     }
 
+    /// Parse a public key from its DER SubjectPublicKeyInfo, as found in
+    /// X.509 certificates and `openssl pkey -pubout -outform DER` output
+    ///
+    /// Every Ed25519 key has the same 12-byte header, so anything else,
+    /// such as a key of another algorithm, is refused as the wrong type.
+    pub fn from_der(data: &[u8]) -> Result<Self, Error> {
+        if data.len() != 44 {
+            return Err(Error::WrongKeyLength { expected: 44, got: data.len() });
+        }
+        let (header, key) = data.split_at(ED25519_DER_HEADER.len());
+        if header != ED25519_DER_HEADER {
+            return Err(Error::WrongKeyType {
+                expected: "Ed25519 SubjectPublicKeyInfo".to_string(),
+                got: format!("DER beginning {}", header.to_hex()),
+            });
+        }
+        PublicKey::parse(key)
+    }
+
+    /// The key's DER SubjectPublicKeyInfo, as [`PublicKey::from_der`] parses
+    pub fn to_der(&self) -> [u8; 44] {
+        let mut ret = [0; 44];
+        ret[..12].copy_from_slice(&ED25519_DER_HEADER);
+        ret[12..].copy_from_slice(&self.serialize());
+        ret
+    }
+
+    /// Parse a public key from a JSON Web Key, as in RFC 8037:
+    /// `{"kty":"OKP","crv":"Ed25519","x":"<base64url key>"}`
+    ///
+    /// Other members, such as `kid` or `use`, are ignored.
+    #[cfg(feature = "jwk")]
+    pub fn from_jwk(s: &str) -> Result<Self, Error> {
+        #[derive(serde::Deserialize)]
+        struct Jwk {
+            kty: String,
+            crv: String,
+            x: String,
+        }
+
+        let jwk: Jwk = serde_json::from_str(s).map_err(|e| Error::InvalidJwk(e.to_string()))?;
+        if jwk.kty != "OKP" || jwk.crv != "Ed25519" {
+            return Err(Error::WrongKeyType {
+                expected: "OKP Ed25519".to_string(),
+                got: format!("{} {}", jwk.kty, jwk.crv),
+            });
+        }
+        let key = crate::radix64::base64url_decode(&jwk.x).map_err(|e| Error::InvalidJwk(format!("x: {}", e)))?;
+        PublicKey::parse(&key)
+    }
+
+    /// The key as a compact JSON Web Key, with its members in the order of
+    /// RFC 7638, so that its SHA256 is the key's JWK thumbprint
+    #[cfg(feature = "jwk")]
+    pub fn to_jwk(&self) -> String {
+        format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, crate::radix64::base64url_encode(&self.serialize()))
+    }
+
     /// Parse a public key from the "id_ed25519.pub" format
+    ///
+    /// An OpenSSH certificate, as in an "id_ed25519-cert.pub" file, gives
+    /// the key it certifies.
     pub fn parse_pk_line(data: &str) -> Result<Self, Error> {
+        Self::parse_pk_line_with_cert(data).map(|(pk, _)| pk)
+    }
+
+    /// Parse a public key in whichever format it was given, for keys pasted
+    /// by someone who may not know which they have
+    ///
+    /// This tries an "id_ed25519.pub" line, then the bare base64 blob of
+    /// one, then the key in hex, and gives the first key which parses. If
+    /// none does, the error says why each failed.
+    pub fn parse_any(s: &str) -> Result<Self, ParseAnyError> {
+        let s = s.trim();
+        let mut tried = Vec::with_capacity(3);
+        match PublicKey::parse_pk_line(s) {
+            Ok(pk) => return Ok(pk),
+            Err(e) => tried.push((FormatName::SshLine, e)),
+        }
+        match PublicKey::from_armor(s) {
+            Ok(pk) => return Ok(pk),
+            Err(crate::armor::Error::Key(e)) => tried.push((FormatName::BareBase64, e)),
+            Err(e) => tried.push((FormatName::BareBase64, Error::Armor(e.to_string()))),
+        }
+        match PublicKey::from_hex(s) {
+            Ok(pk) => return Ok(pk),
+            Err(e) => tried.push((FormatName::Hex, e)),
+        }
+        Err(ParseAnyError { tried })
+    }
+
+    /// Parse a public key from 64 lowercase hex digits
+    fn from_hex(s: &str) -> Result<Self, Error> {
+        if let Some(c) = s.chars().find(|c| !matches!(c, '0'..='9' | 'a'..='f')) {
+            return Err(Error::WrongKeyType { expected: "lowercase hex".to_string(), got: format!("{:?}", c) });
+        }
+        if s.len() % 2 == 1 {
+            return Err(Error::WrongKeyType {
+                expected: "lowercase hex".to_string(),
+                got: "an odd number of digits".to_string(),
+            });
+        }
+        if s.len() != 64 {
+            return Err(Error::WrongKeyLength { expected: 32, got: s.len() / 2 });
+        }
+        PublicKey::parse(&Vec::<u8>::from_hex(s).expect("checked hex"))
+    }
+
+    /// Parse a public key from the "id_ed25519.pub" format, along with its
+    /// comment, e.g. `user@host`, if it has one
+    ///
+    /// The comment is everything after the key data, from the third
+    /// whitespace-delimited field on, since OpenSSH allows spaces in it.
+    pub fn parse_pk_line_with_comment(data: &str) -> Result<(Self, Option<String>), Error> {
+        let pk = PublicKey::parse_pk_line(data)?;
+        let mut rest = data.trim();
+        for _ in 0..2 {
+            match rest.find(|c: char| c.is_ascii_whitespace()) {
+                Some(end) => rest = rest[end..].trim_start(),
+                None => return Ok((pk, None)),
+            }
+        }
+        Ok((pk, Some(rest.to_owned())))
+    }
+
+    /// Parse a public key from the "id_ed25519.pub" format, or from an
+    /// OpenSSH certificate along with the certificate's details
+    pub fn parse_pk_line_with_cert(data: &str) -> Result<(Self, Option<CertificateInfo>), Error> {
         let pieces: Vec<_> = data
             .split(|c: char| c.is_ascii_whitespace())
             .filter(|frag| !frag.is_empty())
@@ -93,28 +388,166 @@ impl PublicKey {
             return Err(Error::EmptyKey);
         }
         // There are several allowable prefixes, all of which have ed25519 in them, according to the ssh source
-        if !pieces[0].contains("ssh-ed25519") {
+        // but of the certificates only plain ed25519 ones are understood
+        let is_cert = pieces[0] == CERT_KEY_TYPE;
+        if !pieces[0].contains("ssh-ed25519") || (pieces[0].contains("-cert-") && !is_cert) {
             return Err(Error::WrongKeyType { expected: "ssh-ed25519".to_string(), got: pieces[0].to_string() });
         }
         if pieces.len() < 2 {
             return Err(Error::NoKey);
         }
-        match PublicKey::from_armor(&pieces[1]) {
-            Ok(pk) => Ok(pk),
+        let parsed = match is_cert {
+            true => crate::armor::parse_certificate(pieces[1]).map(|(pk, cert)| (pk, Some(cert))),
+            false => PublicKey::from_armor(pieces[1]).map(|pk| (pk, None)),
+        };
+        match parsed {
+            Ok(parsed) => Ok(parsed),
             Err(crate::armor::Error::Key(err)) => Err(err),
-            Err(other) => Err(Error::Armor(format!("{:?}", other))), // FIXME do not use debug output here
+            Err(other) => Err(Error::Armor(other.to_string())),
         }
     }
 }
 
+/// Displays the key as [`PublicKey::to_ssh_line`] does, without a comment
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_ssh_line(None))
+    }
+}
+
+/// Parses the key as [`PublicKey::parse_pk_line`] does
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        PublicKey::parse_pk_line(s)
+    }
+}
+
+/// Serializes the key as an SSH public key line without a comment, as
+/// [`PublicKey::to_ssh_line`] writes it
+#[cfg(feature = "serde")]
+impl serde::Serialize for PublicKey {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+/// Deserializes the key from an SSH public key line, whose comment is
+/// dropped
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PublicKey {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let line = <std::borrow::Cow<str>>::deserialize(d)?;
+        line.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A public key with the comment of the line it was read from, so that
+/// people can tell whose it is
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PublicKeyWithComment {
+    pub key: PublicKey,
+    pub comment: Option<String>,
+}
+
+impl Deref for PublicKeyWithComment {
+    type Target = PublicKey;
+
+    fn deref(&self) -> &PublicKey {
+        &self.key
+    }
+}
+
+/// Displays the key as [`PublicKey::to_ssh_line`] does, with its comment
+impl fmt::Display for PublicKeyWithComment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.key.to_ssh_line(self.comment.as_deref()))
+    }
+}
+
+/// Parses the key as [`PublicKey::parse_pk_line_with_comment`] does
+impl FromStr for PublicKeyWithComment {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (key, comment) = PublicKey::parse_pk_line_with_comment(s)?;
+        Ok(PublicKeyWithComment { key, comment })
+    }
+}
+
+/// Hashes the key's 32-byte encoding, as [`PublicKey::serialize`] gives it
+impl hash::Hash for PublicKey {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        hash::Hash::hash(&self.serialize(), state);
+    }
+}
+
+/// Orders keys by their 32-byte encodings, as they are sorted in a ring
+///
+/// Each comparison compresses both points, so to sort many keys it is
+/// quicker to sort by [`PublicKey::serialize`] with `sort_by_cached_key`.
+impl Ord for PublicKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.serialize().cmp(&other.serialize())
+    }
+}
+
+impl PartialOrd for PublicKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl ConstantTimeEq for PublicKey {
     fn ct_eq(&self, other: &Self) -> Choice {
         self.0.ct_eq(&other.0)
     }
 }
 
+/// The key lines of an `authorized_keys` file, trimmed, with their
+/// (one-based) line numbers, skipping blank lines and `#` comments
+pub fn authorized_keys_lines(data: &str) -> impl Iterator<Item = (usize, &str)> {
+    data.lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| (n + 1, line))
+}
+
+/// Parse every key line of an `authorized_keys` file, in order, with
+/// [`PublicKey::parse_pk_line`]
+///
+/// Lines with options before the key type, such as `from="..."`, do not
+/// parse, and neither do keys of other types. To edit a keys file while
+/// keeping its comments, see [`crate::keyring::KeyRing`].
+pub fn parse_authorized_keys(data: &str) -> Vec<Result<PublicKey, Error>> {
+    authorized_keys_lines(data).map(|(_, line)| PublicKey::parse_pk_line(line)).collect()
+}
+
+/// Parse the key lines of an `authorized_keys` file as
+/// [`parse_authorized_keys`] does, failing with the (one-based) line number
+/// of the first which does not parse
+pub fn parse_authorized_keys_strict(data: &str) -> Result<Vec<PublicKey>, (usize, Error)> {
+    authorized_keys_lines(data).map(|(n, line)| PublicKey::parse_pk_line(line).map_err(|e| (n, e))).collect()
+}
+
+/// Read an `authorized_keys` file and parse each of its key lines as
+/// [`parse_authorized_keys`] does, giving each result with its (one-based)
+/// line number
+///
+/// Only reading the file fails the whole; a line which does not parse is
+/// left for the caller to report or skip.
+pub fn read_authorized_keys_file(path: &Path) -> io::Result<Vec<(usize, Result<PublicKey, Error>)>> {
+    let data = fs::read_to_string(path)?;
+    Ok(authorized_keys_lines(&data).map(|(n, line)| (n, PublicKey::parse_pk_line(line))).collect())
+}
+
 /// A secret key
-#[derive(Copy, Clone)]
+///
+/// The key is wiped from memory when dropped. It is not `Copy`, so that
+/// every copy is one made deliberately, with `clone`, and is wiped too.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
 #[cfg_attr(test, derive(PartialEq, Eq, Debug))]
 pub struct SecretKey(pub(crate) Scalar);
 
@@ -124,6 +557,23 @@ impl SecretKey {
         SecretKey(Scalar::from_bits(data))
     }
 
+    /// Generate a fresh secret key using the operating system's randomness
+    pub fn generate() -> Self {
+        SecretKey::generate_with_rng(&mut OsRng)
+    }
+
+    /// Generate a fresh secret key using randomness from `rng`
+    ///
+    /// The key is made as ed25519 makes one from a random seed, so it is
+    /// clamped like every key read from an OpenSSH key file.
+    pub fn generate_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        let mut seed = [0; 32];
+        rng.fill_bytes(&mut seed);
+        let ret = ExpandedSecretKey::from_seed(&seed).secret_key();
+        seed.zeroize();
+        ret
+    }
+
     /// Output bytes
     pub fn as_bytes(&self) -> &[u8] {
         self.0.as_bytes()
@@ -134,3 +584,447 @@ impl SecretKey {
         PublicKey(&self.0 * &constants::ED25519_BASEPOINT_TABLE)
     }
 }
+
+/// An ed25519 secret key as expanded from its 32-byte seed: the scalar used
+/// for ring signatures, and the prefix which ed25519 uses to derive nonces
+///
+/// Ring signatures ignore the prefix. It is kept so that ordinary ed25519
+/// signatures, which must match what OpenSSH would produce, can be made.
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct ExpandedSecretKey {
+    sk: SecretKey,
+    pub(crate) prefix: [u8; 32],
+}
+
+/// Clamp the first half of an expanded ed25519 seed into a secret key
+fn clamp_scalar_bytes(mut raw: [u8; 32]) -> SecretKey {
+    // DANGER WILL ROBINSON
+    // We need to mangle the secret key prior to use because the ed25519 public
+    // key is actually derived from the mangled key rather than from the original.
+    // This means that these keys are biased and strictly speaking no security
+    // argument for AOS (or Schnorr for that matter..) goes through
+    raw[0] &= 0xf8;
+    raw[31] &= 0x7f;
+    raw[31] |= 0x40;
+    // end DANGER
+    let ret = SecretKey::from_bytes(raw);
+    raw.zeroize();
+    ret
+}
+
+impl ExpandedSecretKey {
+    /// Expand a seed, as stored in OpenSSH secret key files
+    pub fn from_seed(seed: &[u8; 32]) -> Self {
+        let mut extsk = sha512::Hash::hash(seed).into_inner();
+        let mut sk = [0; 32];
+        sk.copy_from_slice(&extsk[..32]);
+        let mut prefix = [0; 32];
+        prefix.copy_from_slice(&extsk[32..]);
+        extsk.zeroize();
+        ExpandedSecretKey { sk: clamp_scalar_bytes(sk), prefix }
+    }
+
+    /// The secret key, for use in ring signatures
+    pub fn secret_key(&self) -> SecretKey {
+        self.sk.clone()
+    }
+
+    /// Convert to a public key
+    pub fn to_public(&self) -> PublicKey {
+        self.sk.to_public()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bitcoin_hashes::hex::FromHex;
+
+    #[test]
+    fn fingerprint() {
+        // Expected values from `ssh-keygen -lf`
+        let pk = PublicKey::parse_pk_line(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana"
+        ).unwrap();
+        assert_eq!(pk.fingerprint(), "SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus");
+        let pk = PublicKey::parse_pk_line(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10"
+        ).unwrap();
+        assert_eq!(pk.fingerprint(), "SHA256:py4F80rPWegqoDRY42NJ6tNZO1tzaSltC9G7wQkb2dQ");
+        assert_eq!(pk.fingerprint_bytes().to_hex(), "a72e05f34acf59e82aa03458e36349ead3593b5b7369296d0bd1bbc1091bd9d4");
+
+        // The rest of the keys of the `multi_key_proof` test in lib.rs
+        for (key, expected) in MULTI_KEY_FINGERPRINTS {
+            let pk = PublicKey::parse_pk_line(&format!("ssh-ed25519 {}", key)).unwrap();
+            assert_eq!(pk.fingerprint(), expected);
+            assert_eq!(base64_encode(&pk.fingerprint_bytes()), format!("{}=", &expected["SHA256:".len()..]));
+        }
+    }
+
+    #[test]
+    fn ssh_lines() {
+        for (key, _) in MULTI_KEY_FINGERPRINTS {
+            let pk: PublicKey = format!("ssh-ed25519 {} comment", key).parse().unwrap();
+            assert_eq!(pk.to_string(), format!("ssh-ed25519 {}", key));
+            assert_eq!(pk.to_string().parse::<PublicKey>(), Ok(pk));
+            assert_eq!(pk.to_ssh_line(Some("user@host")), format!("ssh-ed25519 {} user@host", key));
+            assert_eq!(pk.to_ssh_line(Some("user@host")).parse::<PublicKey>(), Ok(pk));
+        }
+        let lines = MULTI_KEY_FINGERPRINTS.map(|(key, _)| format!("ssh-ed25519 {}", key));
+        let keys = lines.iter().map(|line| line.parse::<PublicKey>()).collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(keys.len(), 4);
+        assert_eq!("ssh-rsa AAAA".parse::<PublicKey>().unwrap_err().code(), "wrong_key_type");
+    }
+
+    #[test]
+    fn weak_keys() {
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        // 1 + p, which decodes as 1
+        let mut unreduced = [0xff; 32];
+        unreduced[0] = 0xee;
+        unreduced[31] = 0x7f;
+        for mut key in [identity, unreduced] {
+            assert_eq!(PublicKey::parse(&key), Err(Error::WeakKey(key.to_vec())));
+            // x is zero, so setting its sign bit changes nothing
+            key[31] |= 0x80;
+            assert_eq!(PublicKey::parse(&key), Err(Error::WeakKey(key.to_vec())));
+        }
+        assert_eq!(PublicKey::parse(&identity).unwrap_err().code(), "weak_key");
+
+        let mut blob = SecretKey::from_bytes([9; 32]).to_public().ssh_blob();
+        blob[19..].copy_from_slice(&identity);
+        let e = PublicKey::parse_pk_line(&format!("ssh-ed25519 {}", base64_encode(&blob))).unwrap_err();
+        assert_eq!(e.to_string(), format!("key {} is the identity point", identity.to_hex()));
+        assert_eq!(SecretKey::from_bytes([0; 32]).to_public().serialize(), identity);
+    }
+
+    #[test]
+    fn multi_key_lines() {
+        // The ring of the `multi_key_proof` test in lib.rs, as written there
+        let lines = [
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10",
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGMiyoNWxKsdbuZ9EeJA+QTTaKHYtpCrRBlvCez8ykRl",
+            "ssh-ed25519\tAAAAC3NzaC1lZDI1NTE5AAAAIDgiq1etF0aD94rG/UVmYEt4ij5K8MvHZwb4wIUi6Ihr",
+            "  ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHptEpqs57lhnHkfa+0SQgXQ4A63/YGV2cNTcGMQW+Jt",
+            "ssh-ed25519    AAAAC3NzaC1lZDI1NTE5AAAAICUrHXT71TxmXQA5jDLjPF8QsZ4txhRffAu9SG/dNt8+",
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana",
+        ];
+        for line in lines {
+            let pk = PublicKey::parse_pk_line(line).unwrap();
+            let written = pk.to_ssh_line(None);
+            assert_eq!(PublicKey::parse_pk_line(&written), Ok(pk));
+            // The same base64, with the whitespace and comment normalized away
+            let base64 = line.split_ascii_whitespace().nth(1).unwrap();
+            assert_eq!(written, format!("ssh-ed25519 {}", base64));
+            let commented = pk.to_ssh_line(Some("someone on github"));
+            assert_eq!(commented, format!("ssh-ed25519 {} someone on github", base64));
+            assert_eq!(PublicKey::parse_pk_line(&commented), Ok(pk));
+        }
+    }
+
+    #[test]
+    fn comments() {
+        let line = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana";
+        let (pk, comment) = PublicKey::parse_pk_line_with_comment(line).unwrap();
+        assert_eq!(pk, PublicKey::parse_pk_line(line).unwrap());
+        assert_eq!(comment.as_deref(), Some("apoelstra@sultana"));
+
+        let base64 = line.split_ascii_whitespace().nth(1).unwrap();
+        let bare = format!("  ssh-ed25519\t{}  ", base64);
+        assert_eq!(PublicKey::parse_pk_line_with_comment(&bare), Ok((pk, None)));
+        let spaced = format!("ssh-ed25519 {}\ttwo  words ", base64);
+        assert_eq!(PublicKey::parse_pk_line_with_comment(&spaced), Ok((pk, Some("two  words".into()))));
+        assert_eq!(PublicKey::parse_pk_line_with_comment("ssh-ed25519"), Err(Error::NoKey));
+
+        let with_comment: PublicKeyWithComment = line.parse().unwrap();
+        assert_eq!(with_comment.fingerprint(), pk.fingerprint());
+        assert_eq!(with_comment.to_string(), line);
+        let without: PublicKeyWithComment = bare.parse().unwrap();
+        assert_eq!(without, PublicKeyWithComment { key: pk, comment: None });
+        assert_eq!(without.to_string(), pk.to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let line = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana";
+        let pk = PublicKey::parse_pk_line(line).unwrap();
+        let json = serde_json::to_string(&pk).unwrap();
+        assert_eq!(json, format!("\"{}\"", pk.to_ssh_line(None)));
+        assert_eq!(serde_json::from_str::<PublicKey>(&json).unwrap(), pk);
+        // A comment is accepted, and dropped
+        let keys: Vec<PublicKey> = serde_json::from_value(serde_json::json!([line, pk.to_string()])).unwrap();
+        assert_eq!(keys, [pk, pk]);
+        let e = serde_json::from_str::<PublicKey>("\"ssh-rsa AAAA\"").unwrap_err();
+        assert!(e.to_string().starts_with("wrong key type: expected ssh-ed25519, got ssh-rsa"), "{}", e);
+    }
+
+    #[test]
+    fn parse_any() {
+        let line = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana";
+        let pk = PublicKey::parse_pk_line(line).unwrap();
+        let base64 = line.split_ascii_whitespace().nth(1).unwrap();
+        for given in [line, base64, &pk.serialize().to_hex(), &format!("  {}\n", base64)] {
+            assert_eq!(PublicKey::parse_any(given), Ok(pk), "{}", given);
+        }
+
+        // Every format is tried, and each says why it failed
+        let e = PublicKey::parse_any(&pk.serialize().to_hex().to_uppercase()).unwrap_err();
+        let formats: Vec<FormatName> = e.tried.iter().map(|(format, _)| *format).collect();
+        assert_eq!(formats, [FormatName::SshLine, FormatName::BareBase64, FormatName::Hex]);
+        assert!(matches!(e.tried[0].1, Error::WrongKeyType { .. }));
+        assert_eq!(e.tried[2].1, Error::WrongKeyType { expected: "lowercase hex".into(), got: "'D'".into() });
+        assert_eq!(e.code(), "unrecognised_key");
+        assert!(e.to_string().starts_with("not a recognised public key: as an SSH public key line, wrong key type"));
+
+        let short = &pk.serialize().to_hex()[..62];
+        let e = PublicKey::parse_any(short).unwrap_err();
+        assert_eq!(e.tried[2].1, Error::WrongKeyLength { expected: 32, got: 31 });
+        let e = PublicKey::parse_any(&short[1..]).unwrap_err();
+        assert!(e.to_string().ends_with("; as hex, wrong key type: expected lowercase hex, got an odd number of digits"));
+        let e = PublicKey::parse_any("").unwrap_err();
+        assert_eq!(e.tried[0], (FormatName::SshLine, Error::EmptyKey));
+
+        // A key in a recognised format which is unusable is still refused
+        let identity = SecretKey::from_bytes([0; 32]).to_public().serialize().to_hex();
+        assert_eq!(PublicKey::parse_any(&identity).unwrap_err().tried[2].1, Error::WeakKey(Vec::from_hex(&identity).unwrap()));
+    }
+
+    #[cfg(feature = "jwk")]
+    #[test]
+    fn jwk() {
+        // The key of the `multi_key_proof` test in lib.rs with a comment
+        let pk = PublicKey::parse_pk_line(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIDdtluGSY0vvzgcdU3GTIfWtrr8KMSk8Y1i9NJfRCkV1 apoelstra@sultana"
+        ).unwrap();
+        let jwk = r#"{"crv":"Ed25519","kty":"OKP","x":"N22W4ZJjS-_OBx1TcZMh9a2uvwoxKTxjWL00l9EKRXU"}"#;
+        assert_eq!(pk.to_jwk(), jwk);
+        assert_eq!(PublicKey::from_jwk(jwk), Ok(pk));
+        let spaced = r#"{ "kty": "OKP", "crv": "Ed25519", "kid": "apoelstra", "x": "N22W4ZJjS-_OBx1TcZMh9a2uvwoxKTxjWL00l9EKRXU" }"#;
+        assert_eq!(PublicKey::from_jwk(spaced), Ok(pk));
+        for (key, _) in MULTI_KEY_FINGERPRINTS {
+            let pk = PublicKey::parse_pk_line(&format!("ssh-ed25519 {}", key)).unwrap();
+            assert_eq!(PublicKey::from_jwk(&pk.to_jwk()), Ok(pk));
+        }
+        // The example of RFC 8037, appendix A.2
+        let rfc = r#"{"kty":"OKP","crv":"Ed25519","x":"11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo"}"#;
+        let pk = PublicKey::from_jwk(rfc).unwrap();
+        assert_eq!(pk.serialize().to_hex(), "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a");
+        // and its thumbprint, from appendix A.3
+        let thumbprint = crate::radix64::base64url_encode(&sha256::Hash::hash(pk.to_jwk().as_bytes())[..]);
+        assert_eq!(thumbprint, "kPrK_qmxVWaYVA9wwBF6Iuo3vVzz7TxHCTwXBygrS4k");
+
+        let x25519 = r#"{"kty":"OKP","crv":"X25519","x":"hSDwCYkwp1R0i33ctD73Wg2_Og0mOBr066SpjqqbTmo"}"#;
+        assert_eq!(PublicKey::from_jwk(x25519).unwrap_err().code(), "wrong_key_type");
+        let short = r#"{"kty":"OKP","crv":"Ed25519","x":"N22W4ZJjS-_OBx1TcZMh9a2uvwoxKTxjWL00l9EKRQ"}"#;
+        assert_eq!(PublicKey::from_jwk(short), Err(Error::WrongKeyLength { expected: 32, got: 31 }));
+        for bad in [r#"{"kty":"OKP","crv":"Ed25519"}"#, "not json", r#"{"kty":"OKP","crv":"Ed25519","x":"a+b/"}"#] {
+            assert_eq!(PublicKey::from_jwk(bad).unwrap_err().code(), "bad_jwk", "{}", bad);
+        }
+    }
+
+    #[test]
+    fn ordering_and_hashing() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let keys: Vec<_> = (1..=8u8).map(|i| SecretKey::from_bytes([i; 32]).to_public()).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        let mut by_bytes = keys.clone();
+        by_bytes.sort_by_key(PublicKey::serialize);
+        assert_eq!(sorted, by_bytes);
+        assert!(sorted.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(keys[0].cmp(&keys[0]), Ordering::Equal);
+        // The order of a ring, whose proofs are made over the sorted keys
+        assert_eq!(crate::Ring::new(&keys).unwrap().keys(), &sorted[..]);
+
+        let by_key: HashMap<PublicKey, usize> = keys.iter().enumerate().map(|(n, pk)| (*pk, n)).collect();
+        assert_eq!(by_key.len(), 8);
+        for (n, pk) in keys.iter().enumerate() {
+            assert_eq!(by_key[pk], n);
+            // The same key parsed anew is the same map key
+            assert_eq!(by_key[&PublicKey::parse(&pk.serialize()).unwrap()], n);
+        }
+        let in_order: BTreeMap<PublicKey, usize> = by_key.into_iter().collect();
+        assert!(in_order.keys().eq(sorted.iter()));
+    }
+
+    #[test]
+    fn der() {
+        for (key, _) in MULTI_KEY_FINGERPRINTS {
+            let pk = PublicKey::parse_pk_line(&format!("ssh-ed25519 {}", key)).unwrap();
+            let der = pk.to_der();
+            assert_eq!(der[..12], ED25519_DER_HEADER);
+            assert_eq!(der[12..], pk.serialize());
+            assert_eq!(PublicKey::from_der(&der), Ok(pk));
+        }
+
+        // The key of a self-signed certificate from
+        // `openssl req -x509 -newkey ed25519`, whose SubjectPublicKeyInfo
+        // `openssl x509 -pubkey | openssl pkey -pubin -outform DER` gives
+        let pem = include_str!("../testdata/ed25519-cert.pem");
+        let body: String = pem.lines().filter(|line| !line.starts_with("-----")).collect();
+        let cert = crate::radix64::radix64_decode(&body).unwrap();
+        let start = cert.windows(12).position(|window| window == ED25519_DER_HEADER).unwrap();
+        let pk = PublicKey::from_der(&cert[start..start + 44]).unwrap();
+        assert_eq!(pk.serialize().to_hex(), "48457a9e9ba5dc66a6a512278095cd4c50637e969f8057a56e502c282bcb63cc");
+        assert_eq!(pk.to_der()[..], cert[start..start + 44]);
+
+        let mut der = pk.to_der();
+        assert_eq!(PublicKey::from_der(&der[..43]), Err(Error::WrongKeyLength { expected: 44, got: 43 }));
+        // X25519, OID 1.3.101.110, has the same layout
+        der[8] = 0x6e;
+        assert_eq!(PublicKey::from_der(&der).unwrap_err().code(), "wrong_key_type");
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        let weak = [&ED25519_DER_HEADER[..], &identity].concat();
+        assert_eq!(PublicKey::from_der(&weak), Err(Error::WeakKey(identity.to_vec())));
+        // A point of order 8
+        let torsion = "26e8958fc2b227b045c3f489f2ef98f0d5dfac05d3c63339b13802886d53fc05";
+        let torsion = [&ED25519_DER_HEADER[..], &Vec::<u8>::from_hex(torsion).unwrap()].concat();
+        assert_eq!(PublicKey::from_der(&torsion).unwrap_err().code(), "torsion_key");
+    }
+
+    #[test]
+    fn certificates() {
+        // From `ssh-keygen -s ca -I alice@example.com -n alice -z 4242 id_ed25519.pub`
+        let line = concat!(
+            "ssh-ed25519-cert-v01@openssh.com ",
+            "AAAAIHNzaC1lZDI1NTE5LWNlcnQtdjAxQG9wZW5zc2guY29tAAAAIGI9GPCPs+D0x+DYXqKjLTe0qsoAoKGK7ySXJOzE9ZzKAAAA",
+            "IHlWdYdqA4STcgyuMdQmAyOf47Z4+CipOjJrHRaVmpo/AAAAAAAAEJIAAAABAAAAEWFsaWNlQGV4YW1wbGUuY29tAAAACQAAAAVh",
+            "bGljZQAAAABpVbkAAAAAAHwkXwAAAAAAAAAAggAAABVwZXJtaXQtWDExLWZvcndhcmRpbmcAAAAAAAAAF3Blcm1pdC1hZ2VudC1m",
+            "b3J3YXJkaW5nAAAAAAAAABZwZXJtaXQtcG9ydC1mb3J3YXJkaW5nAAAAAAAAAApwZXJtaXQtcHR5AAAAAAAAAA5wZXJtaXQtdXNl",
+            "ci1yYwAAAAAAAAAAAAAAMwAAAAtzc2gtZWQyNTUxOQAAACBZ7eF45S33wYNf8Wb33oBooHJ+0vuhmwcuNovccbQuZQAAAFMAAAAL",
+            "c3NoLWVkMjU1MTkAAABACsgoWoAXafZbtnF8suZgR31AbF1m7FG814kxaD4Rths2s7CkIiwnfSEnxV/jBjjwyOwHGRlvQ33zS6Rz",
+            "4Ep1AQ==",
+            " alice@laptop",
+        );
+        let plain = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHlWdYdqA4STcgyuMdQmAyOf47Z4+CipOjJrHRaVmpo/ alice@laptop";
+        let (pk, cert) = PublicKey::parse_pk_line_with_cert(line).unwrap();
+        assert_eq!(cert, Some(CertificateInfo { serial: 4242, key_id: "alice@example.com".to_owned() }));
+        assert_eq!(pk.fingerprint(), "SHA256:XjzSay3CmInhZzkUdRDu8Npr7gqftwapV2nrJcAUTtA");
+        assert_eq!(PublicKey::parse_pk_line(line), Ok(pk));
+        assert_eq!(PublicKey::parse_pk_line_with_cert(plain), Ok((pk, None)));
+
+        // The key type of the line must match the blob
+        let blob = line.split_ascii_whitespace().nth(1).unwrap();
+        assert_eq!(PublicKey::parse_pk_line(&format!("ssh-ed25519 {}", blob)).unwrap_err().code(), "bad_armor");
+        let plain_blob = plain.split_ascii_whitespace().nth(1).unwrap();
+        assert_eq!(PublicKey::parse_pk_line(&format!("{} {}", CERT_KEY_TYPE, plain_blob)).unwrap_err().code(), "bad_armor");
+        let truncated = base64_encode(&crate::radix64::radix64_decode(blob).unwrap()[..200]);
+        assert_eq!(PublicKey::parse_pk_line(&format!("{} {}", CERT_KEY_TYPE, truncated)).unwrap_err().code(), "bad_armor");
+        let e = PublicKey::parse_pk_line(&format!("sk-ssh-ed25519-cert-v01@openssh.com {}", blob)).unwrap_err();
+        assert_eq!(e.code(), "wrong_key_type");
+    }
+
+    #[test]
+    fn authorized_keys() {
+        let (key_1, key_2) = (MULTI_KEY_FINGERPRINTS[0].0, MULTI_KEY_FINGERPRINTS[1].0);
+        let data = format!(
+            "# the team\n\nssh-ed25519 {} alice\n  ssh-ed25519 {}\t\r\n   # indented comment\nssh-rsa AAAAB3NzaC1yc2E bob\n",
+            key_1, key_2,
+        );
+        let parsed = parse_authorized_keys(&data);
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(parsed[0], format!("ssh-ed25519 {}", key_1).parse());
+        assert_eq!(parsed[1], format!("ssh-ed25519 {}", key_2).parse());
+        assert!(matches!(parsed[2], Err(Error::WrongKeyType { .. })));
+        assert!(matches!(parse_authorized_keys_strict(&data), Err((6, Error::WrongKeyType { .. }))));
+
+        let good: String = data.lines().take(5).map(|line| format!("{}\n", line)).collect();
+        let keys = parse_authorized_keys_strict(&good).unwrap();
+        assert_eq!(keys, [key_1, key_2].map(|key| format!("ssh-ed25519 {}", key).parse().unwrap()));
+        assert_eq!(parse_authorized_keys_strict("# nobody\n"), Ok(vec![]));
+
+        let path = std::env::temp_dir().join(format!("ringsig-keys-test-{}", std::process::id()));
+        fs::write(&path, &data).unwrap();
+        let read = read_authorized_keys_file(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(read.iter().map(|(n, _)| *n).collect::<Vec<_>>(), [3, 4, 6]);
+        assert_eq!(read.into_iter().map(|(_, key)| key).collect::<Vec<_>>(), parsed);
+        assert_eq!(read_authorized_keys_file(&path).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    /// Keys and their fingerprints, from `ssh-keygen -lf`
+    const MULTI_KEY_FINGERPRINTS: [(&str, &str); 4] = [
+        ("AAAAC3NzaC1lZDI1NTE5AAAAIGMiyoNWxKsdbuZ9EeJA+QTTaKHYtpCrRBlvCez8ykRl", "SHA256:mVtjX1XMyOcBLNBDfIR2qpi3TB+fsTaWPyrQtE7yvJQ"),
+        ("AAAAC3NzaC1lZDI1NTE5AAAAIDgiq1etF0aD94rG/UVmYEt4ij5K8MvHZwb4wIUi6Ihr", "SHA256:aPITuO3NO2Qnv3uMHIvro+vLs1bWp8Y80X++xkjhlz4"),
+        ("AAAAC3NzaC1lZDI1NTE5AAAAIHptEpqs57lhnHkfa+0SQgXQ4A63/YGV2cNTcGMQW+Jt", "SHA256:mUYk7EtGTR5KG0Nhea3mD9QXjrz25yiKZU/ujJl7nTM"),
+        ("AAAAC3NzaC1lZDI1NTE5AAAAICUrHXT71TxmXQA5jDLjPF8QsZ4txhRffAu9SG/dNt8+", "SHA256:cshXWoZfLICeolXu/Gz/TIFt4spWZc8C8cAdo91zL3w"),
+    ];
+
+    /// Randomness from a hash chain, so that tests are repeatable
+    struct TestRng(sha256::Hash);
+
+    impl RngCore for TestRng {
+        fn next_u32(&mut self) -> u32 {
+            let mut bytes = [0; 4];
+            self.fill_bytes(&mut bytes);
+            u32::from_le_bytes(bytes)
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            let mut bytes = [0; 8];
+            self.fill_bytes(&mut bytes);
+            u64::from_le_bytes(bytes)
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            for chunk in dest.chunks_mut(32) {
+                self.0 = sha256::Hash::hash(&self.0[..]);
+                chunk.copy_from_slice(&self.0[..chunk.len()]);
+            }
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for TestRng {}
+
+    #[test]
+    fn generate() {
+        let sk = SecretKey::generate_with_rng(&mut TestRng(sha256::Hash::hash(b"generate")));
+        assert!(sk.to_public().0.is_torsion_free());
+        assert_eq!(sk.as_bytes()[0] & 0x07, 0);
+        assert_eq!(sk.as_bytes()[31] & 0xc0, 0x40);
+        let again = SecretKey::generate_with_rng(&mut TestRng(sha256::Hash::hash(b"generate")));
+        assert_eq!(sk, again);
+
+        let fresh = SecretKey::generate();
+        assert!(fresh.to_public().0.is_torsion_free());
+        assert_ne!(fresh, sk);
+        assert_ne!(SecretKey::generate(), fresh);
+    }
+
+    #[test]
+    fn wiped_on_drop() {
+        let mut sk = std::mem::ManuallyDrop::new(SecretKey::from_bytes([0x55; 32]));
+        let ptr: *mut SecretKey = &mut *sk;
+        // The memory is still ours after dropping the key in place, since it
+        // belongs to the `ManuallyDrop`
+        let bytes = unsafe {
+            std::ptr::drop_in_place(ptr);
+            std::ptr::read(ptr as *const [u8; 32])
+        };
+        assert_eq!(bytes, [0; 32]);
+
+        let expanded = ExpandedSecretKey::from_seed(&[1; 32]);
+        let mut copy = expanded.clone();
+        copy.zeroize();
+        assert_eq!(copy.secret_key().as_bytes(), [0; 32]);
+        assert_eq!(copy.prefix, [0; 32]);
+        assert_ne!(expanded.secret_key().as_bytes(), [0; 32]);
+    }
+
+    #[test]
+    fn error_messages() {
+        let e = PublicKey::parse_pk_line("ssh-rsa AAAAB3NzaC1yc2E").unwrap_err();
+        assert_eq!(e.to_string(), "wrong key type: expected ssh-ed25519, got ssh-rsa");
+        let e = PublicKey::parse_pk_line("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAA!").unwrap_err();
+        assert_eq!(e.to_string(), "parsing key: bad radix-64: character 0x21 is not valid radix-64");
+    }
+}
@@ -0,0 +1,177 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! OpenSSH known_hosts Files
+//!
+//! Each line of a known_hosts file has the form
+//!
+//! ```text
+//! [@marker] host1,host2,... keytype base64 [comment]
+//! ```
+//!
+//! where the hosts may instead be a single hashed entry `|1|salt|hash`,
+//! which does not reveal the host name. Blank lines and lines starting with
+//! `#` are ignored. This parses such files so that the host keys may be used
+//! as a ring.
+
+use crate::keys::{self, PublicKey};
+
+/// Prefix of a hashed host entry
+const HASHED_PREFIX: &str = "|1|";
+
+/// A single host key line of a known_hosts file
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Entry {
+    /// The (one-based) line number in the file
+    pub line_number: usize,
+    /// A marker such as `@revoked` or `@cert-authority`, without the `@`
+    pub marker: Option<String>,
+    /// The hosts field, as written
+    pub hosts: String,
+    /// The key type, e.g. `ssh-ed25519`
+    pub key_type: String,
+    /// The base64-encoded key
+    pub key: String,
+}
+
+impl Entry {
+    /// Whether the hosts field is hashed
+    pub fn is_hashed(&self) -> bool {
+        self.hosts.starts_with(HASHED_PREFIX)
+    }
+
+    /// The host names or patterns, or none if the entry is hashed
+    pub fn hostnames(&self) -> impl Iterator<Item = &str> {
+        let hosts = if self.is_hashed() { "" } else { &self.hosts[..] };
+        hosts.split(',').filter(|host| !host.is_empty())
+    }
+
+    /// Whether any host matches a glob pattern, where `*` matches any
+    /// sequence of characters and `?` any single character
+    ///
+    /// A host written `[name]:port` matches if either it or `name` does.
+    pub fn matches(&self, pattern: &str) -> bool {
+        self.hostnames().any(|host| {
+            let bare = host.strip_prefix('[').and_then(|h| h.split_once("]:")).map(|(name, _)| name);
+            glob_match(pattern.as_bytes(), host.as_bytes())
+                || bare.is_some_and(|name| glob_match(pattern.as_bytes(), name.as_bytes()))
+        })
+    }
+
+    /// The key as an ssh public key line, with the hosts as its comment
+    pub fn key_line(&self) -> String {
+        format!("{} {} {}", self.key_type, self.key, self.hosts)
+    }
+
+    /// Parse the key, if it is an ed25519 key usable in a ring
+    pub fn public_key(&self) -> Result<PublicKey, keys::Error> {
+        PublicKey::parse_pk_line(&self.key_line())
+    }
+}
+
+/// Match `text` against a glob `pattern`
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match (pattern.first(), text.first()) {
+        (None, _) => text.is_empty(),
+        (Some(b'*'), _) => glob_match(&pattern[1..], text) || (!text.is_empty() && glob_match(pattern, &text[1..])),
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &text[1..]),
+        (Some(p), Some(t)) if p.eq_ignore_ascii_case(t) => glob_match(&pattern[1..], &text[1..]),
+        _ => false,
+    }
+}
+
+/// Parse a known_hosts file
+///
+/// Returns the entries and, separately, the line numbers and text of any
+/// lines which are not comments but do not have enough fields to be entries.
+pub fn parse(s: &str) -> (Vec<Entry>, Vec<(usize, String)>) {
+    let mut entries = vec![];
+    let mut malformed = vec![];
+    for (n, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let mut first = fields.next();
+        let marker = match first.and_then(|f| f.strip_prefix('@')) {
+            Some(marker) => {
+                first = fields.next();
+                Some(marker.to_owned())
+            }
+            None => None,
+        };
+        match (first, fields.next(), fields.next()) {
+            (Some(hosts), Some(key_type), Some(key)) => entries.push(Entry {
+                line_number: n + 1,
+                marker,
+                hosts: hosts.to_owned(),
+                key_type: key_type.to_owned(),
+                key: key.to_owned(),
+            }),
+            _ => malformed.push((n + 1, line.to_owned())),
+        }
+    }
+    (entries, malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ED25519: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10";
+
+    #[test]
+    fn parsing() {
+        let file = format!(
+            "# comment\n\
+             web1.example.com,10.0.0.1 ssh-ed25519 {0}\n\
+             |1|c2FsdA==|aGFzaA== ssh-ed25519 {0}\n\
+             [db.example.com]:2222 ssh-rsa AAAAB3NzaC1yc2E=\n\
+             @revoked bad.example.com ssh-ed25519 {0}\n\
+             truncated ssh-ed25519\n",
+            ED25519,
+        );
+        let (entries, malformed) = parse(&file);
+        assert_eq!(malformed, [(6, "truncated ssh-ed25519".to_owned())]);
+        assert_eq!(entries.len(), 4);
+
+        assert_eq!(entries[0].line_number, 2);
+        assert_eq!(entries[0].hostnames().collect::<Vec<_>>(), ["web1.example.com", "10.0.0.1"]);
+        assert_eq!(entries[0].key_line(), format!("ssh-ed25519 {} web1.example.com,10.0.0.1", ED25519));
+        assert!(entries[0].public_key().is_ok());
+
+        assert!(entries[1].is_hashed());
+        assert_eq!(entries[1].hostnames().count(), 0);
+        assert!(!entries[1].matches("*"));
+
+        assert!(entries[2].public_key().is_err());
+        assert!(entries[2].matches("db.*"));
+        assert!(entries[2].matches("[db.example.com]:2222"));
+
+        assert_eq!(entries[3].marker.as_deref(), Some("revoked"));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_match(b"*.example.com", b"web1.example.com"));
+        assert!(glob_match(b"WEB?.example.com", b"web1.example.com"));
+        assert!(!glob_match(b"web?.example.com", b"web12.example.com"));
+        assert!(glob_match(b"*", b""));
+        assert!(!glob_match(b"", b"x"));
+        assert!(glob_match(b"10.0.*.1", b"10.0.200.1"));
+    }
+}
@@ -15,136 +15,1459 @@
 //
 
 pub mod armor;
+pub mod attributes;
+pub mod badge;
+pub mod bundle;
+mod claim;
+mod diagnostics;
+mod disavowal;
+pub mod dns;
+mod error;
+mod extension;
+pub mod format;
+#[cfg(feature = "git")]
+pub mod git;
 pub mod hashes;
+pub mod keyring;
 pub mod keys;
+pub mod known_hosts;
+pub mod linkable;
+#[cfg(feature = "uniffi")]
+pub mod mobile;
+mod proof;
 pub mod radix64;
+pub mod revocation;
+pub mod ringset;
+mod ristretto;
+mod schema;
+pub mod sshsig;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+pub mod thread;
+mod threshold;
+pub mod timestamping;
+#[cfg(any(test, feature = "testkit"))]
+pub mod vectors;
+// Browser bindings; under WASI there is no JavaScript host to call them
+#[cfg(not(target_os = "wasi"))]
 pub mod wasm;
 
-use bitcoin_hashes::{Hash, HashEngine};
-use curve25519_dalek::{constants, edwards::EdwardsPoint, scalar::Scalar};
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
 
-use crate::hashes::{ChallengeHash, NonceHash, ParamsHash};
+use bitcoin_hashes::{sha256, Hash, HashEngine};
+use curve25519_dalek::{
+    constants,
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+    traits::{Identity, IsIdentity},
+};
+
+pub use crate::claim::{claim, prove_claimable, verify_claim, Claim};
+pub use crate::diagnostics::{verify_detailed, VerifyDiagnostics};
+pub use crate::disavowal::{disavow, verify_disavowal, Disavowal};
+pub use crate::error::{ProofError, ProveError, VerifyError};
+pub use crate::extension::{extend_ring, verify_extension};
+#[allow(deprecated)]
+pub use crate::linkable::{
+    prove_linkable, prove_linkable_with_progress, verify_linkable, verify_linkable_cancellable,
+    verify_linkable_slice, verify_linkable_with_progress,
+};
+pub use crate::proof::{Proof, ProofEncoding, PROOF_V1};
+pub use crate::ristretto::{prove_ristretto, verify_ristretto, RistrettoPublicKey};
+pub use crate::threshold::{prove_threshold, verify_threshold, ThresholdProof};
+use crate::hashes::{
+    ChallengeHash, LinkHash, MessageDigest, NonceHash, ParamsHash, PrehashedParamsHash, RingHash, ScopeHash,
+};
 use crate::keys::{PublicKey, SecretKey};
 
-use subtle::{ConstantTimeEq, CtOption};
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::collections::HashMap;
+use std::io;
+use std::ops::ControlFlow;
+use subtle::{Choice, ConditionallySelectable, ConstantTimeEq, CtOption};
+
+/// A count or length which is too large to be committed to
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum TooLarge {
+    Keys,
+    Input,
+}
+
+impl From<TooLarge> for ProveError {
+    fn from(e: TooLarge) -> Self {
+        match e {
+            TooLarge::Keys => ProveError::TooManyKeys,
+            TooLarge::Input => ProveError::InputTooLong,
+        }
+    }
+}
+
+/// A ring contains the identity point, for which anyone can sign
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct IdentityInRing;
+
+impl From<IdentityInRing> for ProveError {
+    fn from(_: IdentityInRing) -> Self {
+        ProveError::WeakKey
+    }
+}
+
+impl From<IdentityInRing> for VerifyError {
+    fn from(_: IdentityInRing) -> Self {
+        VerifyError::WeakKey
+    }
+}
+
+/// A ring lists this key more than once
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct DuplicateInRing(PublicKey);
+
+impl From<DuplicateInRing> for ProveError {
+    fn from(e: DuplicateInRing) -> Self {
+        ProveError::DuplicateKey(Box::new(e.0))
+    }
+}
+
+impl From<DuplicateInRing> for VerifyError {
+    fn from(e: DuplicateInRing) -> Self {
+        VerifyError::DuplicateKey(Box::new(e.0))
+    }
+}
+
+impl From<TooLarge> for VerifyError {
+    fn from(e: TooLarge) -> Self {
+        match e {
+            TooLarge::Keys => VerifyError::TooManyKeys,
+            TooLarge::Input => VerifyError::InputTooLong,
+        }
+    }
+}
+
+/// The number of keys in a ring, as it is committed to: a little-endian u32
+fn ring_size_le(len: usize) -> Result<[u8; 4], TooLarge> {
+    u32::try_from(len).map(u32::to_le_bytes).map_err(|_| TooLarge::Keys)
+}
+
+/// The length of a message or scope, as it is committed to: a
+/// little-endian u64
+fn length_le(len: usize) -> Result<[u8; 8], TooLarge> {
+    u64::try_from(len).map(u64::to_le_bytes).map_err(|_| TooLarge::Input)
+}
+
+/// The params hash of a ring and the digest of a pre-hashed message
+///
+/// This is a different tagged hash from [`params_hash`], so a proof of a
+/// digest can never pass as a proof of a raw message, nor the reverse.
+fn prehashed_param_hash(keys: &[[u8; 32]], digest: &MessageDigest) -> Result<ParamsHash, TooLarge> {
+    let mut eng = PrehashedParamsHash::engine();
+    eng.input(&ring_size_le(keys.len())?);
+    for key in keys {
+        eng.input(key);
+    }
+    eng.input(&digest[..]);
+    Ok(ParamsHash::from_inner(PrehashedParamsHash::from_engine(eng).into_inner()))
+}
 
-fn param_hash(pks: &[PublicKey], message: &[u8]) -> ParamsHash {
+/// The params hash engine with the (serialized, sorted) keys input
+fn params_engine(keys: &[[u8; 32]]) -> Result<sha256::HashEngine, TooLarge> {
     let mut eng = ParamsHash::engine();
-    eng.input(&(u32::try_from(pks.len()).unwrap().to_le_bytes()));
-    for pk in pks {
-        eng.input(&pk.serialize());
+    eng.input(&ring_size_le(keys.len())?);
+    for key in keys {
+        eng.input(key);
     }
-    eng.input(&(u64::try_from(message.len()).unwrap().to_le_bytes()));
+    Ok(eng)
+}
+
+/// Complete the params hash by inputting the message, then the context if
+/// there is one
+///
+/// The message is length-prefixed, so the context's length and bytes after
+/// it cannot be mistaken for more of the message. An empty context inputs
+/// nothing, so gives the params of proofs made without one.
+fn finish_param_hash(mut eng: sha256::HashEngine, message: &[u8], context: &[u8]) -> Result<ParamsHash, TooLarge> {
+    eng.input(&length_le(message.len())?);
     eng.input(message);
-    ParamsHash::from_engine(eng)
+    if !context.is_empty() {
+        eng.input(&length_le(context.len())?);
+        eng.input(context);
+    }
+    Ok(ParamsHash::from_engine(eng))
 }
 
-/// Helper function to save typing
-fn hash_to_sc<T: Hash<Inner = [u8; 32]>>(inp: T) -> Scalar {
-    Scalar::from_bits(inp.into_inner())
+/// Complete the params hash by inputting the rest of the message read from
+/// `reader`, as [`finish_param_hash`] would for those bytes
+///
+/// The length prefix comes before the message, so it is measured first by
+/// seeking to the end. Only that many bytes are read, and it is an error
+/// if there are fewer.
+fn finish_param_hash_reader<R: io::Read + io::Seek>(
+    mut eng: sha256::HashEngine,
+    mut reader: R,
+) -> io::Result<ParamsHash> {
+    let start = reader.stream_position()?;
+    let len = reader.seek(io::SeekFrom::End(0))?.saturating_sub(start);
+    reader.seek(io::SeekFrom::Start(start))?;
+    eng.input(&len.to_le_bytes());
+    if io::copy(&mut reader.take(len), &mut eng)? != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "message shrank while being read"));
+    }
+    Ok(ParamsHash::from_engine(eng))
 }
 
-pub fn verify(proof: &[u8], pks: &[PublicKey], message: &[u8]) -> Result<(), &'static str> {
-    if pks.is_empty() {
-        return Err("no public keys");
+/// A ring of public keys, prepared for proving or verifying many times
+///
+/// Proving or verifying against a slice of keys sorts the keys, serializes
+/// and negates them, and hashes them, compressing every point once more. A
+/// `Ring` does that work once, so that each proof or verification only
+/// hashes its message before the per-key curve operations. The free
+/// functions such as [`prove`] and [`verify`] prepare a `Ring` and use it
+/// once, so its proofs are the same as theirs.
+#[derive(Clone)]
+pub struct Ring {
+    /// The keys, sorted
+    pks: Vec<PublicKey>,
+    /// The keys, serialized
+    keys: Vec<[u8; 32]>,
+    /// The keys, negated, as the per-key curve operations use them
+    negated: Vec<EdwardsPoint>,
+    /// The params hash engine with the key count and keys already input
+    params: sha256::HashEngine,
+}
+
+impl Ring {
+    /// Prepare a ring, which fails only if it has more than `u32::MAX` keys,
+    /// lists a key more than once, or contains the identity point
+    pub fn new(pks: &[PublicKey]) -> Result<Self, ProveError> {
+        Ring::prepare(pks)
+    }
+
+    fn prepare<E: From<TooLarge> + From<IdentityInRing> + From<DuplicateInRing>>(pks: &[PublicKey]) -> Result<Self, E> {
+        let mut sorted: Vec<([u8; 32], PublicKey)> = pks.iter().map(|pk| (pk.serialize(), *pk)).collect();
+        sorted.sort_unstable_by_key(|(key, _)| *key);
+        // Whichever of two equal keys comes first, the error is the same
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(DuplicateInRing(pair[0].1).into());
+        }
+        let keys: Vec<[u8; 32]> = sorted.iter().map(|(key, _)| *key).collect();
+        check_not_identity(&keys)?;
+        let params = params_engine(&keys)?;
+        let pks: Vec<PublicKey> = sorted.into_iter().map(|(_, pk)| pk).collect();
+        let negated = pks.iter().map(|pk| -pk.0).collect();
+        Ok(Ring { pks, keys, negated, params })
+    }
+
+    /// The keys, in sorted order
+    pub fn keys(&self) -> &[PublicKey] {
+        &self.pks
+    }
+
+    /// Produce a proof, as [`prove`]
+    pub fn prove(&self, message: &[u8], sk: &SecretKey) -> Result<Proof, ProveError> {
+        let my_idx = find_signer(&self.pks, sk)?;
+        prove_sorted(&self.negated, my_idx, self.params(message)?, sk, None, |_, _| {})
+    }
+
+    /// Verify a proof, as [`verify`]
+    pub fn verify(&self, proof: &Proof, message: &[u8]) -> Result<(), VerifyError> {
+        let params = self.params(message)?;
+        verify_sorted(proof.as_bytes(), self.negated.iter().copied(), params, None, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// Produce a proof with the key at `index` of [`Ring::keys`], as
+    /// [`prove_with_index`]
+    pub fn prove_with_index(&self, message: &[u8], sk: &SecretKey, index: usize) -> Result<Proof, ProveError> {
+        check_signer(&self.pks, sk, index)?;
+        prove_sorted(&self.negated, index, self.params(message)?, sk, None, |_, _| {})
+    }
+
+    /// The index of `pk` in [`Ring::keys`], if it is a member
+    pub fn index_of(&self, pk: &PublicKey) -> Option<usize> {
+        self.keys.binary_search(&pk.serialize()).ok()
+    }
+
+    /// Produce a proof bound to `context`, as [`prove_with_context`]
+    pub fn prove_with_context(&self, message: &[u8], context: &[u8], sk: &SecretKey) -> Result<Proof, ProveError> {
+        let my_idx = find_signer(&self.pks, sk)?;
+        prove_sorted(&self.negated, my_idx, self.params_in(message, context)?, sk, None, |_, _| {})
+    }
+
+    /// Verify a proof bound to `context`, as [`verify_with_context`]
+    pub fn verify_with_context(&self, proof: &Proof, message: &[u8], context: &[u8]) -> Result<(), VerifyError> {
+        let params = self.params_in(message, context)?;
+        verify_sorted(proof.as_bytes(), self.negated.iter().copied(), params, None, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// Verify many proofs, each of its own message, giving the result of
+    /// each in order
+    pub fn verify_batch(&self, proofs: &[(&Proof, &[u8])]) -> Vec<Result<(), VerifyError>> {
+        proofs.iter().map(|&(proof, message)| self.verify(proof, message)).collect()
+    }
+
+    /// Verify many proofs as [`Ring::verify_batch`], spread across threads
+    /// with the `rayon` feature
+    ///
+    /// Without the feature, or on wasm32, this is [`Ring::verify_batch`].
+    /// Either way the results are in the order of `proofs`.
+    pub fn verify_batch_parallel(&self, proofs: &[(&Proof, &[u8])]) -> Vec<Result<(), VerifyError>> {
+        #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+        {
+            use rayon::prelude::*;
+            proofs.par_iter().map(|&(proof, message)| self.verify(proof, message)).collect()
+        }
+        #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+        self.verify_batch(proofs)
+    }
+
+    /// The params hash of the ring and `message`
+    fn params(&self, message: &[u8]) -> Result<ParamsHash, TooLarge> {
+        self.params_in(message, &[])
+    }
+
+    /// The params hash of the ring, `message` and `context`
+    fn params_in(&self, message: &[u8], context: &[u8]) -> Result<ParamsHash, TooLarge> {
+        finish_param_hash(self.params.clone(), message, context)
+    }
+}
+
+/// A [`Ring`] with a message, prepared for proving or verifying it many
+/// times
+///
+/// A `Ring` hashes each message afresh, which for a long message is much
+/// of the work of verifying a proof over a small ring. This keeps the
+/// message's [`params_hash`] instead, so servers checking many proofs of
+/// one message over one ring hash neither the keys nor the message again.
+#[derive(Clone)]
+pub struct RingMessage {
+    ring: Ring,
+    message: Vec<u8>,
+    params: ParamsHash,
+}
+
+impl RingMessage {
+    /// Prepare the ring of `pks` with `message`, which fails as
+    /// [`params_hash`] does
+    pub fn new(pks: &[PublicKey], message: &[u8]) -> Result<Self, VerifyError> {
+        Ring::prepare::<VerifyError>(pks)?.with_message(message)
+    }
+
+    /// The ring
+    pub fn ring(&self) -> &Ring {
+        &self.ring
+    }
+
+    /// The message
+    pub fn message(&self) -> &[u8] {
+        &self.message
+    }
+
+    /// The params hash of the ring and message, as [`params_hash`]
+    pub fn params_hash(&self) -> ParamsHash {
+        self.params
+    }
+
+    /// Produce a proof of the message, as [`prove`]
+    pub fn prove(&self, sk: &SecretKey) -> Result<Proof, ProveError> {
+        let my_idx = find_signer(&self.ring.pks, sk)?;
+        prove_sorted(&self.ring.negated, my_idx, self.params, sk, None, |_, _| {})
+    }
+
+    /// Verify a proof of the message, as [`verify`]
+    pub fn verify(&self, proof: &Proof) -> Result<(), VerifyError> {
+        let negated = self.ring.negated.iter().copied();
+        verify_sorted(proof.as_bytes(), negated, self.params, None, |_, _| ControlFlow::Continue(()))
+    }
+}
+
+/// A [`Ring`] with the secret key of one of its members, prepared for
+/// signing many messages
+///
+/// On top of what a `Ring` saves, this finds the signer's index once, so
+/// each proof costs hashing its message and the walk around the ring. Its
+/// proofs are those of [`prove`].
+#[derive(Clone)]
+pub struct RingSigner {
+    ring: Ring,
+    sk: SecretKey,
+    /// The index of the signer in the sorted ring
+    index: usize,
+}
+
+impl RingSigner {
+    /// Prepare to sign with `sk` over the ring of `pks`, which fails if
+    /// [`Ring::new`] would or if `sk` is not the secret key of a member
+    pub fn new(pks: &[PublicKey], sk: SecretKey) -> Result<Self, ProveError> {
+        Ring::new(pks)?.signer(sk)
+    }
+
+    /// The ring being signed over
+    pub fn ring(&self) -> &Ring {
+        &self.ring
+    }
+
+    /// The index of the signer in [`Ring::keys`]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Produce a proof of `message`, as [`prove`]
+    pub fn sign(&self, message: &[u8]) -> Result<Proof, ProveError> {
+        prove_sorted(&self.ring.negated, self.index, self.ring.params(message)?, &self.sk, None, |_, _| {})
+    }
+}
+
+/// Verifies a proof of a message which arrives in pieces, as [`verify`]
+/// would of the whole message
+///
+/// Each piece is hashed as it is given to [`Verifier::update`], so the
+/// message need not be held in memory, e.g. when it is read from a socket
+/// or a decompressor. The params hash commits to the message's length
+/// before its bytes, so the length must be known up front, much as
+/// [`verify_streaming`] finds it by seeking; [`Verifier::finish`] fails if
+/// the pieces add up to another length.
+#[derive(Clone)]
+pub struct Verifier {
+    proof: Proof,
+    ring: Ring,
+    /// The params hash engine, with the message so far input
+    params: sha256::HashEngine,
+    message_len: u64,
+    /// The number of message bytes input so far
+    got: u64,
+}
+
+impl Verifier {
+    /// Prepare to verify `proof` over the ring of `pks`, of a message of
+    /// `message_len` bytes
+    ///
+    /// This fails as [`verify`] would if the ring cannot be verified
+    /// against, or the proof is the wrong length for it.
+    pub fn new(proof: Proof, pks: &[PublicKey], message_len: u64) -> Result<Self, VerifyError> {
+        let ring = Ring::prepare::<VerifyError>(pks)?;
+        check_proof_len(proof.as_bytes(), ring.negated.len())?;
+        let mut params = ring.params.clone();
+        params.input(&message_len.to_le_bytes());
+        Ok(Verifier { proof, ring, params, message_len, got: 0 })
+    }
+
+    /// Hash the next piece of the message
+    pub fn update(&mut self, data: &[u8]) {
+        self.got = self.got.saturating_add(data.len() as u64);
+        self.params.input(data);
+    }
+
+    /// Verify the proof of the message given so far
+    pub fn finish(self) -> Result<(), VerifyError> {
+        if self.got != self.message_len {
+            return Err(VerifyError::WrongMessageLength { expected: self.message_len, got: self.got });
+        }
+        let params = ParamsHash::from_engine(self.params);
+        let negated = self.ring.negated.iter().copied();
+        verify_sorted(self.proof.as_bytes(), negated, params, None, |_, _| ControlFlow::Continue(()))
+    }
+}
+
+impl io::Write for Verifier {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Ring {
+    /// Prepare to prove or verify `message` over the ring many times, which
+    /// fails only if the message is too long to commit to
+    pub fn with_message(&self, message: &[u8]) -> Result<RingMessage, VerifyError> {
+        let params = self.params(message)?;
+        Ok(RingMessage { ring: self.clone(), message: message.to_vec(), params })
     }
 
-    let mut pks = pks.to_owned();
-    pks.sort_by_key(|pk| pk.serialize());
-    if proof.len() != 32 * (pks.len() + 1) {
-        return Err("proof wrong length");
+    /// Prepare to sign with `sk`, which must be the secret key of a member
+    pub fn signer(self, sk: SecretKey) -> Result<RingSigner, ProveError> {
+        let index = find_signer(&self.pks, &sk)?;
+        Ok(RingSigner { ring: self, sk, index })
     }
+}
+
+/// Fail if any of the serialized keys is the identity point
+///
+/// [`PublicKey::parse`] refuses the identity, but it is the public key of
+/// the zero secret key, so may still turn up in a ring, for which anyone
+/// could then sign.
+fn check_not_identity(keys: &[[u8; 32]]) -> Result<(), IdentityInRing> {
+    let identity = CompressedEdwardsY::identity().to_bytes();
+    match keys.contains(&identity) {
+        true => Err(IdentityInRing),
+        false => Ok(()),
+    }
+}
+
+/// Identifier of a ring of public keys
+///
+/// This is a tagged hash of the sorted, deduplicated keys, so two lists of
+/// keys have the same identifier exactly when they contain the same keys,
+/// regardless of order or repetition. A list which repeats a key still has
+/// an identifier, though [`prove`] and [`verify`] refuse it.
+pub fn ring_id(pks: &[PublicKey]) -> RingHash {
+    let mut keys: Vec<[u8; 32]> = pks.iter().map(PublicKey::serialize).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    let mut eng = RingHash::engine();
+    // Every key follows, so saturating the count cannot make two rings
+    // collide; no ring that large fits in memory anyway
+    eng.input(&u32::try_from(keys.len()).unwrap_or(u32::MAX).to_le_bytes());
+    for key in &keys {
+        eng.input(key);
+    }
+    RingHash::from_engine(eng)
+}
+
+/// Identifier of a ring of public keys and a message
+///
+/// This is the params hash which every proof of the message over the ring
+/// commits to, so where two verifiers' values agree, they are checking
+/// proofs of the same bytes over the same ring, and any disagreement about
+/// a proof is down to the proof. Like [`ring_id`] it does not depend on the
+/// order of the keys, but a list which repeats a key has none.
+pub fn params_hash(pks: &[PublicKey], message: &[u8]) -> Result<ParamsHash, VerifyError> {
+    Ok(Ring::prepare::<VerifyError>(pks)?.params(message)?)
+}
+
+/// Convert a hash to a scalar, as every challenge and nonce of a proof is
+///
+/// The bytes are read little-endian with the top bit cleared, and are not
+/// reduced: arithmetic with the scalar reduces it. Protocols built around
+/// these proofs should use this to agree with them on the scalar of a hash.
+pub fn hash_to_scalar(bytes: &[u8; 32]) -> Scalar {
+    Scalar::from_bits(*bytes)
+}
 
-    let params = param_hash(&pks, message);
+/// Verify a proof of `message` by one of the keys `pks`, in any order
+///
+/// A list which repeats a key is refused with [`VerifyError::DuplicateKey`]
+/// rather than taken as the set of its keys, as [`prove`] refuses it.
+pub fn verify(proof: &Proof, pks: &[PublicKey], message: &[u8]) -> Result<(), VerifyError> {
+    verify_with_progress(proof, pks, message, |_, _| {})
+}
+
+/// Verify a proof as [`verify`] does, over keys which are already sorted
+///
+/// `sorted_pks` must be in the order of [`Ring::keys`], that is ascending
+/// by [`PublicKey::serialize`]. This is not checked, except by an
+/// assertion in debug builds: keys out of order give a different params
+/// hash, so a valid proof fails with [`VerifyError::BadProof`]. Keys which
+/// are repeated or the identity are still refused, as by [`verify`].
+///
+/// Unlike [`verify`], this neither copies nor sorts the keys, so suits
+/// callers which keep their rings sorted. A [`Ring`] saves more, if it can
+/// be kept.
+pub fn verify_presorted(proof: &Proof, sorted_pks: &[PublicKey], message: &[u8]) -> Result<(), VerifyError> {
+    debug_assert!(
+        sorted_pks.windows(2).all(|w| w[0].serialize() <= w[1].serialize()),
+        "keys passed to verify_presorted are not sorted",
+    );
+    let params = sorted_params::<_, VerifyError>(sorted_pks.iter().map(|pk| (pk.serialize(), pk)), message, &[])?;
+    verify_sorted(proof.as_bytes(), sorted_pks.iter().map(|pk| -pk.0), params, None, |_, _| ControlFlow::Continue(()))
+}
+
+/// Verify a proof given as bytes, as [`verify`]
+#[deprecated(note = "parse the bytes with `Proof::from_bytes` and use `verify`")]
+pub fn verify_slice(proof: &[u8], pks: &[PublicKey], message: &[u8]) -> Result<(), VerifyError> {
+    verify_inner(proof, pks, message, None, continuing(|_, _| {}))
+}
+
+/// Verify a proof, calling `progress` with the number of keys processed so
+/// far and the total after each key
+pub fn verify_with_progress<F: FnMut(usize, usize)>(
+    proof: &Proof,
+    pks: &[PublicKey],
+    message: &[u8],
+    progress: F,
+) -> Result<(), VerifyError> {
+    verify_inner(proof.as_bytes(), pks, message, None, continuing(progress))
+}
+
+/// Verify a proof, calling `progress` as in [`verify_with_progress`], and
+/// giving up with [`VerifyError::Cancelled`] if it returns
+/// [`ControlFlow::Break`]
+///
+/// For large rings in interactive programs, which may want to stop waiting.
+pub fn verify_cancellable<F: FnMut(usize, usize) -> ControlFlow<()>>(
+    proof: &Proof,
+    pks: &[PublicKey],
+    message: &[u8],
+    progress: F,
+) -> Result<(), VerifyError> {
+    verify_inner(proof.as_bytes(), pks, message, None, progress)
+}
+
+/// Verify many proofs against the same ring, each of its own message, giving
+/// the result of each in order
+///
+/// This sorts and serializes the keys once for the whole batch, as a
+/// [`Ring`] does, rather than once per proof as [`verify`] would. A proof of
+/// the wrong length for the ring is rejected before any curve arithmetic.
+pub fn verify_batch(proofs: &[(&Proof, &[u8])], pks: &[PublicKey]) -> Vec<Result<(), VerifyError>> {
+    match Ring::prepare::<VerifyError>(pks) {
+        Ok(ring) => ring.verify_batch(proofs),
+        Err(e) => vec![Err(e); proofs.len()],
+    }
+}
+
+/// Verify many proofs against the same ring, as [`verify_batch`], spread
+/// across threads with the `rayon` feature
+///
+/// See [`Ring::verify_batch_parallel`].
+pub fn verify_batch_parallel(proofs: &[(&Proof, &[u8])], pks: &[PublicKey]) -> Vec<Result<(), VerifyError>> {
+    match Ring::prepare::<VerifyError>(pks) {
+        Ok(ring) => ring.verify_batch_parallel(proofs),
+        Err(e) => vec![Err(e); proofs.len()],
+    }
+}
+
+/// Verify many proofs, each with its own ring and message, giving the
+/// result of each in order
+///
+/// Each ring is prepared once, however many of the proofs use it, as
+/// [`verify_batch`] does for a single ring; rings are recognized as the same
+/// when they are the same slice. The curve operations are those of
+/// [`verify`]: each step of a proof hashes the point computed by the step
+/// before, so unlike Schnorr signatures the points cannot be combined into
+/// one multiscalar multiplication.
+pub fn verify_many(items: &[(&Proof, &[PublicKey], &[u8])]) -> Vec<Result<(), VerifyError>> {
+    let mut rings: HashMap<(*const PublicKey, usize), Result<Ring, VerifyError>> = HashMap::new();
+    items
+        .iter()
+        .map(|&(proof, pks, message)| {
+            let ring = rings.entry((pks.as_ptr(), pks.len())).or_insert_with(|| Ring::prepare(pks));
+            ring.as_ref().map_err(Clone::clone)?.verify(proof, message)
+        })
+        .collect()
+}
+
+/// The length in bytes of a proof for a ring of `ring_size` keys: a
+/// challenge and an `s` value for each key, of 32 bytes each
+pub fn proof_len(ring_size: usize) -> usize {
+    32 * (ring_size + 1)
+}
+
+/// Check that every `s` value in a proof is a canonical (fully reduced)
+/// scalar
+///
+/// [`verify`] accepts unreduced values, as proofs made by older versions of
+/// this library have them, so adding the group order to any of them gives
+/// another valid proof of the same statement. Proofs made by this library
+/// now have always-reduced values; this rejects any others, as verifying
+/// with [`VerifyOptions::strict`] does. [`Proof::canonicalize`] gives the
+/// one encoding of a proof which passes, e.g. to deduplicate proofs.
+pub fn check_canonical(proof: &[u8]) -> Result<(), VerifyError> {
+    if proof.is_empty() || !proof.len().is_multiple_of(32) {
+        return Err(ProofError::WrongLength { got: proof.len() }.into());
+    }
+    for (index, s_i) in proof.chunks_exact(32).enumerate().skip(1) {
+        let mut bytes = [0; 32];
+        bytes.copy_from_slice(s_i);
+        if Scalar::from_canonical_bytes(bytes).is_none() {
+            return Err(VerifyError::NonCanonicalScalar { index });
+        }
+    }
+    Ok(())
+}
+
+/// A progress callback which never cancels, from one which cannot
+fn continuing<F: FnMut(usize, usize)>(mut progress: F) -> impl FnMut(usize, usize) -> ControlFlow<()> {
+    move |done, total| {
+        progress(done, total);
+        ControlFlow::Continue(())
+    }
+}
+
+/// The link a linkable proof is verified against, checking the key image
+fn link_of(key_image: &[u8; 32], scope: &[u8]) -> Result<Link, VerifyError> {
+    Ok(Link { base: scope_point(scope)?, image: key_image_point(key_image)? })
+}
+
+/// Decode a key image, which must be in the prime-order subgroup
+fn key_image_point(key_image: &[u8; 32]) -> Result<EdwardsPoint, VerifyError> {
+    let image = CompressedEdwardsY(*key_image).decompress().ok_or(VerifyError::KeyImageNotOnCurve)?;
+    // A torsion component would let one signer produce several distinct
+    // key images for the same scope, defeating linking
+    if image.is_identity() || !image.is_torsion_free() {
+        return Err(VerifyError::KeyImageTorsion);
+    }
+    Ok(image)
+}
+
+fn verify_inner<F: FnMut(usize, usize) -> ControlFlow<()>>(
+    proof: &[u8],
+    pks: &[PublicKey],
+    message: &[u8],
+    link: Option<Link>,
+    progress: F,
+) -> Result<(), VerifyError> {
+    let mut sorted: Vec<([u8; 32], PublicKey)> = pks.iter().map(|pk| (pk.serialize(), *pk)).collect();
+    sorted.sort_unstable_by_key(|(key, _)| *key);
+    let params = sorted_params::<_, VerifyError>(sorted.iter().map(|(key, pk)| (*key, pk)), message, &[])?;
+    verify_sorted(proof, sorted.iter().map(|(_, pk)| -pk.0), params, link, progress)
+}
+
+/// The params hash of a sorted ring, given as each key serialized with the
+/// key itself, `message` and `context`
+///
+/// This refuses the rings [`Ring::new`] does, with the same errors, but
+/// needs no more memory than the hash engine.
+fn sorted_params<'a, I, E>(keys: I, message: &[u8], context: &[u8]) -> Result<ParamsHash, E>
+where
+    I: ExactSizeIterator<Item = ([u8; 32], &'a PublicKey)>,
+    E: From<TooLarge> + From<IdentityInRing> + From<DuplicateInRing>,
+{
+    let identity = CompressedEdwardsY::identity().to_bytes();
+    let mut eng = ParamsHash::engine();
+    eng.input(&ring_size_le(keys.len())?);
+    let mut prev = None;
+    let mut has_identity = false;
+    for (key, pk) in keys {
+        if prev == Some(key) {
+            return Err(DuplicateInRing(*pk).into());
+        }
+        // A repeated key is reported ahead of the identity, as by Ring::new
+        has_identity |= key == identity;
+        eng.input(&key);
+        prev = Some(key);
+    }
+    if has_identity {
+        return Err(IdentityInRing.into());
+    }
+    Ok(finish_param_hash(eng, message, context)?)
+}
+
+/// Verify a proof given the negated keys of a sorted ring and its params hash
+fn verify_sorted<K: ExactSizeIterator<Item = EdwardsPoint>, F: FnMut(usize, usize) -> ControlFlow<()>>(
+    proof: &[u8],
+    negated: K,
+    params: ParamsHash,
+    link: Option<Link>,
+    mut progress: F,
+) -> Result<(), VerifyError> {
+    let n = negated.len();
+    check_proof_len(proof, n)?;
+
+    let params = link_params(params, link);
     let mut e_i = ChallengeHash::from_slice(&proof[..32]).unwrap();
-    for idx in 0..pks.len() {
+    for (idx, neg_pk) in negated.enumerate() {
         let s_i = NonceHash::from_slice(&proof[32 * (idx + 1)..32 * (idx + 2)]).unwrap();
-        let pubnonce = EdwardsPoint::vartime_double_scalar_mul_basepoint(&hash_to_sc(e_i), &-pks[idx].0, &hash_to_sc(s_i));
+        e_i = next_challenge(e_i, s_i, &neg_pk, link, &params);
+        if progress(idx + 1, n).is_break() {
+            return Err(VerifyError::Cancelled);
+        }
+    }
+    if e_i[..] != proof[..32] {
+        return Err(VerifyError::BadProof);
+    }
+    Ok(())
+}
 
-        let mut challenge_eng = ChallengeHash::engine();
-        challenge_eng.input(&pubnonce.compress().to_bytes());
-        challenge_eng.input(&params[..]);
-        e_i = ChallengeHash::from_engine(challenge_eng);
+/// Check that a proof is the right length for a ring of `n` keys
+fn check_proof_len(proof: &[u8], n: usize) -> Result<(), VerifyError> {
+    if n == 0 {
+        return Err(VerifyError::EmptyKeySet);
     }
-    if &e_i[..] != &proof[..32] {
-        return Err("bad proof");
+    let expected = proof_len(n);
+    if proof.len() != expected {
+        let got = proof.len();
+        let proof_ring_size = match got.is_multiple_of(32) && got >= proof_len(1) {
+            true => Some(got / 32 - 1),
+            false => None,
+        };
+        return Err(VerifyError::WrongProofLength { expected, got, proof_ring_size });
     }
     Ok(())
 }
 
-pub fn prove(pks: &[PublicKey], message: &[u8], sk: SecretKey) -> Result<Vec<u8>, &'static str> {
-    let mut pks = pks.to_owned();
-    pks.sort_by_key(|pk| pk.serialize());
-    let params = param_hash(&pks, message);
+/// Produce a proof of `message` by one of the keys `pks`, in any order,
+/// with the secret key of one of them
+///
+/// A list which repeats a key is refused with [`ProveError::DuplicateKey`],
+/// since it is almost certainly a mistake, and the same list is refused
+/// when verifying.
+pub fn prove(pks: &[PublicKey], message: &[u8], sk: &SecretKey) -> Result<Proof, ProveError> {
+    prove_with_rng(pks, message, sk, &mut OsRng)
+}
+
+/// Produce a proof as [`prove`] does, taking the randomness mixed into the
+/// nonce and every `s` value from `rng` rather than the operating system
+///
+/// The randomness is hashed with the params and secret key, so a weak
+/// `rng` gives proofs no worse than [`prove_with_aux_rand`]'s. A seeded
+/// `rng` makes reproducible proofs, e.g. for tests.
+pub fn prove_with_rng<R: CryptoRng + RngCore>(
+    pks: &[PublicKey],
+    message: &[u8],
+    sk: &SecretKey,
+    rng: &mut R,
+) -> Result<Proof, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let my_idx = find_signer(&ring.pks, sk)?;
+    let flags = ProveFlags::default();
+    prove_sorted_with(&ring.negated, my_idx, ring.params(message)?, sk, None, |_, _| {}, randomness_from(rng), flags)
+}
+
+/// Produce a proof as [`prove`] does, hashing the random `s` values across
+/// threads with the `rayon` feature
+///
+/// Without the feature, or on wasm32, this is [`prove`]. The walk around
+/// the ring, which takes nearly all the time, is sequential either way, so
+/// this saves little; see the `prove_parallel` benchmark.
+pub fn prove_parallel(pks: &[PublicKey], message: &[u8], sk: &SecretKey) -> Result<Proof, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let my_idx = find_signer(&ring.pks, sk)?;
+    let params = ring.params(message)?;
+    let flags = ProveFlags { parallel: true, ..ProveFlags::default() };
+    prove_sorted_with(&ring.negated, my_idx, params, sk, None, |_, _| {}, randomness_from(&mut OsRng), flags)
+}
+
+/// Produce a proof, calling `progress` with the number of keys processed so
+/// far and the total after each key
+pub fn prove_with_progress<F: FnMut(usize, usize)>(
+    pks: &[PublicKey],
+    message: &[u8],
+    sk: &SecretKey,
+    progress: F,
+) -> Result<Proof, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let my_idx = find_signer(&ring.pks, sk)?;
+    prove_sorted(&ring.negated, my_idx, ring.params(message)?, sk, None, progress)
+}
+
+/// Produce a proof which verifies only in `context`, with
+/// [`verify_with_context`]
+///
+/// The context names where the proof is meant to be used, e.g. one site or
+/// deployment, so that a proof made for one cannot be replayed on another
+/// with the same ring and message. It is committed to after the ring and
+/// message, length-prefixed. The empty context is no context: its proofs
+/// are those of [`prove`], and verify with [`verify`].
+pub fn prove_with_context(
+    pks: &[PublicKey],
+    message: &[u8],
+    context: &[u8],
+    sk: &SecretKey,
+) -> Result<Proof, ProveError> {
+    Ring::prepare::<ProveError>(pks)?.prove_with_context(message, context, sk)
+}
+
+/// Verify a proof made by [`prove_with_context`] in `context`
+pub fn verify_with_context(
+    proof: &Proof,
+    pks: &[PublicKey],
+    message: &[u8],
+    context: &[u8],
+) -> Result<(), VerifyError> {
+    Ring::prepare::<VerifyError>(pks)?.verify_with_context(proof, message, context)
+}
+
+/// Options for [`prove_with`]
+///
+/// The default options give the proofs [`prove`] does; each option moves
+/// them towards those of another entry point, e.g.
+/// `ProveOptions::new().aux_rand(aux).context(b"example.com")`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct ProveOptions<'a> {
+    aux_rand: Option<[u8; 32]>,
+    context: &'a [u8],
+    assume_sorted: bool,
+    encoding: ProofEncoding,
+}
+
+impl<'a> ProveOptions<'a> {
+    /// The default options
+    pub fn new() -> Self {
+        ProveOptions::default()
+    }
+
+    /// Use `aux` in place of fresh randomness, as [`prove_with_aux_rand`]
+    pub fn aux_rand(mut self, aux: [u8; 32]) -> Self {
+        self.aux_rand = Some(aux);
+        self
+    }
+
+    /// Bind the proof to `context`, as [`prove_with_context`]
+    pub fn context(mut self, context: &'a [u8]) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Take the keys to be sorted already, as [`verify_presorted`] does,
+    /// rather than sorting a copy of them
+    pub fn assume_sorted(mut self) -> Self {
+        self.assume_sorted = true;
+        self
+    }
+
+    /// Write the proof as `encoding` says, e.g. [`ProofEncoding::Versioned`]
+    /// for one with a [`PROOF_V1`] version byte
+    ///
+    /// Either verifies alike; see [`Proof::encoding`].
+    pub fn proof_version(mut self, encoding: ProofEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+}
+
+/// Options for [`verify_with`], those of [`ProveOptions`] which bear on
+/// verifying
+///
+/// The default options verify as [`verify`] does.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub struct VerifyOptions<'a> {
+    context: &'a [u8],
+    assume_sorted: bool,
+    strict: bool,
+}
+
+impl<'a> VerifyOptions<'a> {
+    /// The default options
+    pub fn new() -> Self {
+        VerifyOptions::default()
+    }
+
+    /// Verify a proof bound to `context`, as [`verify_with_context`]
+    pub fn context(mut self, context: &'a [u8]) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Take the keys to be sorted already, as [`verify_presorted`] does
+    pub fn assume_sorted(mut self) -> Self {
+        self.assume_sorted = true;
+        self
+    }
+
+    /// Refuse proofs with an unreduced `s` value, as [`check_canonical`]
+    /// does, so that each proof has only one encoding
+    ///
+    /// Proofs made by older versions of this library have such values, so
+    /// fail with [`VerifyError::NonCanonicalScalar`] unless canonicalized.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+}
+
+/// Produce a proof as [`prove`] does, with the changes `options` gives
+///
+/// With [`ProveOptions::assume_sorted`] the keys must be sorted as for
+/// [`verify_presorted`], which is checked only in debug builds; otherwise
+/// the proof is over another ring than intended, and will not verify.
+pub fn prove_with(
+    pks: &[PublicKey],
+    message: &[u8],
+    sk: &SecretKey,
+    options: &ProveOptions,
+) -> Result<Proof, ProveError> {
+    let mut rng = OsRng;
+    let randomness = |bytes: &mut [u8; 32]| match options.aux_rand {
+        Some(aux) => {
+            *bytes = aux;
+            Ok(())
+        }
+        None => rng.try_fill_bytes(bytes).map_err(|_| ProveError::Rng),
+    };
+    let proof = if options.assume_sorted {
+        debug_assert!(
+            pks.windows(2).all(|w| w[0].serialize() <= w[1].serialize()),
+            "keys assumed sorted are not sorted",
+        );
+        let keys = pks.iter().map(|pk| (pk.serialize(), pk));
+        let params = sorted_params::<_, ProveError>(keys, message, options.context)?;
+        let my_idx = find_signer(pks, sk)?;
+        let negated: Vec<EdwardsPoint> = pks.iter().map(|pk| -pk.0).collect();
+        prove_sorted_with(&negated, my_idx, params, sk, None, |_, _| {}, randomness, ProveFlags::default())?
+    } else {
+        let ring = Ring::prepare::<ProveError>(pks)?;
+        let my_idx = find_signer(&ring.pks, sk)?;
+        let params = ring.params_in(message, options.context)?;
+        prove_sorted_with(&ring.negated, my_idx, params, sk, None, |_, _| {}, randomness, ProveFlags::default())?
+    };
+    Ok(proof.with_encoding(options.encoding))
+}
+
+/// Verify a proof as [`verify`] does, with the changes `options` gives
+pub fn verify_with(
+    proof: &Proof,
+    pks: &[PublicKey],
+    message: &[u8],
+    options: &VerifyOptions,
+) -> Result<(), VerifyError> {
+    if options.strict {
+        check_proof_len(proof.as_bytes(), pks.len())?;
+        // The challenge need not be checked: it must equal a hash output
+        // byte for byte, so it has only one encoding, reduced or not
+        check_canonical(proof.as_bytes())?;
+    }
+    if options.assume_sorted {
+        debug_assert!(
+            pks.windows(2).all(|w| w[0].serialize() <= w[1].serialize()),
+            "keys assumed sorted are not sorted",
+        );
+        let keys = pks.iter().map(|pk| (pk.serialize(), pk));
+        let params = sorted_params::<_, VerifyError>(keys, message, options.context)?;
+        let negated = pks.iter().map(|pk| -pk.0);
+        return verify_sorted(proof.as_bytes(), negated, params, None, |_, _| ControlFlow::Continue(()));
+    }
+    Ring::prepare::<VerifyError>(pks)?.verify_with_context(proof, message, options.context)
+}
+
+/// Hash a message read from `reader`, for [`prove_prehashed`] and
+/// [`verify_prehashed`]
+///
+/// The message is read a block at a time, so it need not fit in memory. To
+/// hash one which arrives some other way, input it to
+/// `MessageDigest::engine()` and finish with `MessageDigest::from_engine`.
+pub fn digest_reader<R: io::Read>(mut reader: R) -> io::Result<MessageDigest> {
+    let mut eng = MessageDigest::engine();
+    io::copy(&mut reader, &mut eng)?;
+    Ok(MessageDigest::from_engine(eng))
+}
+
+/// Produce a proof of the rest of the message read from `message`
+///
+/// The message is read a block at a time, so it need not fit in memory,
+/// and the proof is the same as [`prove`] makes of the same bytes. It must
+/// be seekable, as its length is committed to before its contents.
+pub fn prove_streaming<R: io::Read + io::Seek>(
+    pks: &[PublicKey],
+    message: R,
+    sk: &SecretKey,
+) -> Result<Proof, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let my_idx = find_signer(&ring.pks, sk)?;
+    let params = finish_param_hash_reader(ring.params.clone(), message).map_err(|e| ProveError::Read(e.to_string()))?;
+    prove_sorted(&ring.negated, my_idx, params, sk, None, |_, _| {})
+}
+
+/// Verify a proof of the rest of the message read from `message`, as
+/// [`verify`] would of the same bytes
+pub fn verify_streaming<R: io::Read + io::Seek>(
+    proof: &Proof,
+    pks: &[PublicKey],
+    message: R,
+) -> Result<(), VerifyError> {
+    let ring = Ring::prepare::<VerifyError>(pks)?;
+    let params = finish_param_hash_reader(ring.params.clone(), message).map_err(|e| VerifyError::Read(e.to_string()))?;
+    verify_sorted(proof.as_bytes(), ring.negated.iter().copied(), params, None, |_, _| ControlFlow::Continue(()))
+}
+
+/// Produce a proof of a message given only its digest
+///
+/// Such a proof verifies only with [`verify_prehashed`], never with
+/// [`verify`] as a proof of the message or of the digest's bytes.
+pub fn prove_prehashed(pks: &[PublicKey], digest: &MessageDigest, sk: &SecretKey) -> Result<Proof, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let my_idx = find_signer(&ring.pks, sk)?;
+    prove_sorted(&ring.negated, my_idx, prehashed_param_hash(&ring.keys, digest)?, sk, None, |_, _| {})
+}
+
+/// Verify a proof made by [`prove_prehashed`]
+pub fn verify_prehashed(proof: &Proof, pks: &[PublicKey], digest: &MessageDigest) -> Result<(), VerifyError> {
+    let ring = Ring::prepare::<VerifyError>(pks)?;
+    verify_sorted(proof.as_bytes(), ring.negated.iter().copied(), prehashed_param_hash(&ring.keys, digest)?, None, |_, _| ControlFlow::Continue(()))
+}
+
+/// Produce a proof using `aux` in place of fresh randomness
+///
+/// [`prove`] mixes randomness from the operating system into the nonce and
+/// every `s` value, after the params hash and secret key, so each of its
+/// proofs differs. This mixes in `aux` instead, for callers with their own
+/// source of randomness or which need reproducible proofs: the proof is
+/// then a function of the ring, message, key and `aux` alone, and is the
+/// same every time for the same inputs, as an ed25519 signature is.
+pub fn prove_with_aux_rand(
+    pks: &[PublicKey],
+    message: &[u8],
+    sk: &SecretKey,
+    aux: &[u8; 32],
+) -> Result<Proof, ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let my_idx = find_signer(&ring.pks, sk)?;
+    let randomness = |rng: &mut [u8; 32]| {
+        *rng = *aux;
+        Ok(())
+    };
+    let flags = ProveFlags::default();
+    prove_sorted_with(&ring.negated, my_idx, ring.params(message)?, sk, None, |_, _| {}, randomness, flags)
+}
+
+/// Produce proofs of several messages with the same ring and key
+///
+/// This sorts the ring and locates the signer once, rather than once per
+/// message, as a [`RingSigner`] does. Either every proof is produced or
+/// none are.
+pub fn prove_many<M: AsRef<[u8]>>(
+    pks: &[PublicKey],
+    messages: &[M],
+    sk: &SecretKey,
+) -> Result<Vec<Proof>, ProveError> {
+    let signer = RingSigner::new(pks, sk.clone())?;
+    messages.iter().map(|message| signer.sign(message.as_ref())).collect()
+}
+
+/// The indices of those `keys` which are members of `ring`
+///
+/// Callers choosing a secret key to prove with can use this to notice when
+/// more than one of theirs would do, rather than silently using the first:
+/// the choice is visible to anyone who later learns the signer's key.
+pub fn ring_members(ring: &[PublicKey], keys: &[PublicKey]) -> Vec<usize> {
+    keys.iter().enumerate().filter(|(_, key)| ring.contains(key)).map(|(n, _)| n).collect()
+}
+
+/// Produce a proof as [`prove`] does, with the secret key of the key at
+/// `index` of the sorted ring
+///
+/// The index is that given by [`ring_index_of`] for the public key, for
+/// callers which know it already and so need not search the ring for the
+/// secret key's public key. It must be right: this checks only that the
+/// key at `index` is the secret key's.
+pub fn prove_with_index(pks: &[PublicKey], message: &[u8], sk: &SecretKey, index: usize) -> Result<Proof, ProveError> {
+    Ring::prepare::<ProveError>(pks)?.prove_with_index(message, sk, index)
+}
+
+/// The index of `pk` in the sorted ring of `pks`, for [`prove_with_index`],
+/// or `None` if it is not a member or the ring cannot be proven over, e.g.
+/// as it repeats a key
+pub fn ring_index_of(pks: &[PublicKey], pk: &PublicKey) -> Option<usize> {
+    Ring::prepare::<ProveError>(pks).ok()?.index_of(pk)
+}
+
+/// Check that the key at `index` of a sorted ring is the public key of
+/// `sk`, without the comparison taking time depending on the keys
+fn check_signer(pks: &[PublicKey], sk: &SecretKey, index: usize) -> Result<(), ProveError> {
+    match pks.get(index) {
+        Some(pk) if bool::from(pk.ct_eq(&sk.to_public())) => Ok(()),
+        _ => Err(ProveError::WrongSignerIndex { index }),
+    }
+}
+
+/// Find the index of the signer in a sorted ring, without revealing it
+/// through timing
+///
+/// Fails if the ring is empty.
+fn find_signer(pks: &[PublicKey], sk: &SecretKey) -> Result<usize, ProveError> {
+    if pks.is_empty() {
+        return Err(ProveError::EmptyKeySet);
+    }
     let my_pk = sk.to_public();
-    let mut my_idx_opt = CtOption::new(0 as u64, ! my_pk.ct_eq(&my_pk));
+    let mut my_idx_opt = CtOption::new(0u64, !my_pk.ct_eq(&my_pk));
     for (i, pk) in pks.iter().enumerate() {
-        my_idx_opt = my_idx_opt.or_else(| | CtOption::new(i as u64, pk.ct_eq(&my_pk)) );
+        let i = u64::try_from(i).map_err(|_| ProveError::TooManyKeys)?;
+        my_idx_opt = my_idx_opt.or_else(| | CtOption::new(i, pk.ct_eq(&my_pk)) );
     }
-    let my_idx_u64 = match Option::<u64>::from(my_idx_opt) {
-        Some(idx) => idx,
-        None => return Err("secret key did not match any public key"),
+    match Option::<u64>::from(my_idx_opt) {
+        Some(idx) => usize::try_from(idx).map_err(|_| ProveError::TooManyKeys),
+        None => Err(ProveError::KeyNotInRing { sk_public: Box::new(my_pk) }),
+    }
+}
+
+/// The second generator and key image of a linkable proof
+#[derive(Copy, Clone)]
+struct Link {
+    base: EdwardsPoint,
+    image: EdwardsPoint,
+}
+
+/// Hash a linkable scope to a point in the prime-order subgroup, whose
+/// discrete log nobody knows
+fn scope_point(scope: &[u8]) -> Result<EdwardsPoint, TooLarge> {
+    let scope_len = length_le(scope.len())?;
+    Ok(hash_to_point(|ctr| {
+        let mut eng = ScopeHash::engine();
+        eng.input(&scope_len);
+        eng.input(scope);
+        eng.input(&ctr.to_le_bytes());
+        ScopeHash::from_engine(eng).into_inner()
+    }))
+}
+
+/// Find a point in the prime-order subgroup from `hash` of a counter
+fn hash_to_point<H: FnMut(u32) -> [u8; 32]>(mut hash: H) -> EdwardsPoint {
+    // Try-and-increment; the input is public so variable time is fine
+    for ctr in 0u32.. {
+        if let Some(point) = CompressedEdwardsY(hash(ctr)).decompress() {
+            let point = point.mul_by_cofactor();
+            if !point.is_identity() {
+                return point;
+            }
+        }
+    }
+    unreachable!("about half of all hashes are valid points")
+}
+
+/// For linkable proofs, commit to the scope point and key image as well as
+/// the ring and message. Plain proofs use the params unchanged.
+fn link_params(params: ParamsHash, link: Option<Link>) -> [u8; 32] {
+    match link {
+        None => params.into_inner(),
+        Some(link) => {
+            let mut eng = LinkHash::engine();
+            eng.input(&params[..]);
+            eng.input(&link.base.compress().to_bytes());
+            eng.input(&link.image.compress().to_bytes());
+            LinkHash::from_engine(eng).into_inner()
+        }
+    }
+}
+
+/// The challenge which follows `e_i` in a proof, given the `s` value and
+/// negated key at that position of the ring
+fn next_challenge(
+    e_i: ChallengeHash,
+    s_i: NonceHash,
+    neg_pk: &EdwardsPoint,
+    link: Option<Link>,
+    params: &[u8; 32],
+) -> ChallengeHash {
+    let (e_i, s_i) = (hash_to_scalar(e_i.as_inner()), hash_to_scalar(s_i.as_inner()));
+    let pubnonce = EdwardsPoint::vartime_double_scalar_mul_basepoint(&e_i, neg_pk, &s_i);
+    let link_nonce = link.map(|link| s_i * link.base - e_i * link.image);
+    challenge(&pubnonce, link_nonce.as_ref(), params)
+}
+
+fn challenge(pubnonce: &EdwardsPoint, link_nonce: Option<&EdwardsPoint>, params: &[u8; 32]) -> ChallengeHash {
+    let mut challenge_eng = ChallengeHash::engine();
+    challenge_eng.input(&pubnonce.compress().to_bytes());
+    if let Some(link_nonce) = link_nonce {
+        challenge_eng.input(&link_nonce.compress().to_bytes());
+    }
+    challenge_eng.input(&params[..]);
+    ChallengeHash::from_engine(challenge_eng)
+}
+
+/// Rotate `items` left by `shift`, which may be up to `items.len()`,
+/// without the time taken or the memory accessed depending on `shift`
+fn rotate_left_ct<T: ConditionallySelectable>(items: &mut [T], shift: usize) {
+    let n = items.len();
+    let mut rotated = items.to_vec();
+    let mut step = 1;
+    // Rotate by each power of two in `shift` in turn, touching every item
+    // whether or not the bit is set
+    while step <= n {
+        let bit = Choice::from(((shift & step) != 0) as u8);
+        for (i, item) in rotated.iter_mut().enumerate() {
+            *item = T::conditional_select(&items[i], &items[(i + step) % n], bit);
+        }
+        items.copy_from_slice(&rotated);
+        step *= 2;
+    }
+}
+
+/// Produce a proof given the negated keys of a sorted ring, the signer's
+/// index in it, and the params hash
+fn prove_sorted<F: FnMut(usize, usize)>(
+    negated: &[EdwardsPoint],
+    my_idx: usize,
+    params: ParamsHash,
+    sk: &SecretKey,
+    link: Option<Link>,
+    progress: F,
+) -> Result<Proof, ProveError> {
+    prove_sorted_with(negated, my_idx, params, sk, link, progress, randomness_from(&mut OsRng), ProveFlags::default())
+}
+
+/// The random `s` value for each index but the signer's, hashed from the
+/// params, nonce, index and that index's randomness
+///
+/// The nonce is already a secret hash of the secret key, so hashing it
+/// rather than the key keeps the key's bytes out of all but one hash.
+///
+/// With `parallel` and the `rayon` feature, not on wasm32, the hashing is
+/// spread across threads. Each value depends only on its own inputs, so
+/// they are the same either way.
+fn random_s_values(rngs: &[[u8; 32]], params: &[u8; 32], nonce: &NonceHash, parallel: bool) -> Result<Vec<Scalar>, ProveError> {
+    let s_value = |(idx, rng): (usize, &[u8; 32])| {
+        let mut s_eng = NonceHash::engine();
+        // Big-endian, unlike the committed lengths; only the signer ever
+        // computes this, so it need not match anything else
+        s_eng.input(&u64::try_from(idx).map_err(|_| ProveError::TooManyKeys)?.to_be_bytes());
+        s_eng.input(&params[..]);
+        s_eng.input(&nonce[..]);
+        s_eng.input(rng);
+        // Reduce it, since our own s value will be reduced and the others
+        // must look the same
+        Ok(Scalar::from_bytes_mod_order(NonceHash::from_engine(s_eng).into_inner()))
     };
-    let my_idx = my_idx_u64 as usize;
+    #[cfg(all(feature = "rayon", not(target_arch = "wasm32")))]
+    if parallel {
+        use rayon::prelude::*;
+        return rngs.par_iter().enumerate().map(s_value).collect();
+    }
+    #[cfg(not(all(feature = "rayon", not(target_arch = "wasm32"))))]
+    let _ = parallel;
+    rngs.iter().enumerate().map(s_value).collect()
+}
+
+/// The randomness for [`prove_sorted_with`], drawn from `rng`
+fn randomness_from<R: CryptoRng + RngCore>(rng: &mut R) -> impl FnMut(&mut [u8; 32]) -> Result<(), ProveError> + '_ {
+    move |bytes| rng.try_fill_bytes(bytes).map_err(|_| ProveError::Rng)
+}
+
+/// Flags for [`prove_sorted_with`], none of which changes whether or how
+/// the proof verifies
+#[derive(Copy, Clone, Default)]
+struct ProveFlags {
+    /// Hash the random `s` values as [`random_s_values`] describes, giving
+    /// the same proof
+    parallel: bool,
+    /// Commit to the nonce for [`claim`], as [`prove_claimable`] does
+    claimable: bool,
+}
+
+/// Produce a proof as [`prove_sorted`] does, taking the randomness mixed
+/// into the nonce and every `s` value from `randomness`
+///
+/// The nonce also hashes the params and the secret key, and each `s` value
+/// hashes the params and the nonce, so a repeated `randomness` (possible
+/// only through [`prove_with_aux_rand`] and [`prove_with_rng`]) does not
+/// leak the key.
+#[allow(clippy::too_many_arguments)]
+fn prove_sorted_with<F: FnMut(usize, usize), R: FnMut(&mut [u8; 32]) -> Result<(), ProveError>>(
+    negated: &[EdwardsPoint],
+    my_idx: usize,
+    params: ParamsHash,
+    sk: &SecretKey,
+    link: Option<Link>,
+    progress: F,
+    mut randomness: R,
+    flags: ProveFlags,
+) -> Result<Proof, ProveError> {
+    let params = link_params(params, link);
+    let n = negated.len();
 
-    let mut ret = vec![0; 32 * (pks.len() + 1)];
     let mut rng = [0; 32];
 
     let mut nonce_eng = NonceHash::engine();
     nonce_eng.input(&params[..]);
     nonce_eng.input(sk.as_bytes());
-    getrandom::getrandom(&mut rng).map_err(|_| "rng error")?;
+    randomness(&mut rng)?;
     nonce_eng.input(&rng);
     let nonce = NonceHash::from_engine(nonce_eng);
 
-    // Compute all the `s` values for indices greater than our own.
-    // Note that this does not actually use any secret data anywhere.
-    let mut pubnonce = &hash_to_sc(nonce) * &constants::ED25519_BASEPOINT_TABLE;
-    for idx in (my_idx + 1..pks.len()).chain(0..my_idx) {
-        // Hash the nonce before the params since the nonce is non-constant (in fact,
-        // it is hard for an attacker to control at all). Assuming SHA256 is secure,
-        // this accomplishes nothing except preventing the verifier from caching any
-        // part of the hash computation. But if SHA2 were to be broken this would
-        // plausibly save us.
-        let mut challenge_eng = ChallengeHash::engine();
-        challenge_eng.input(&pubnonce.compress().to_bytes());
-        challenge_eng.input(&params[..]);
-        let e_i = ChallengeHash::from_engine(challenge_eng);
-
-        if idx == 0 {
-            ret[0..32].copy_from_slice(&e_i[..]);
-        }
-
-        // Compute random s value, save it to proof
-        let mut s_eng = NonceHash::engine();
-        s_eng.input(&(idx as u64).to_be_bytes());
-        s_eng.input(&params[..]);
-        s_eng.input(sk.as_bytes());
-        getrandom::getrandom(&mut rng).map_err(|_| "rng error")?;
-        s_eng.input(&rng);
-        let s_i = NonceHash::from_engine(s_eng);
-        ret[32 * (1 + idx)..32 * (2 + idx)].copy_from_slice(&s_i[..]);
-        // Compute next R value as though we were a verifier
-        pubnonce = EdwardsPoint::vartime_double_scalar_mul_basepoint(&hash_to_sc(e_i), &-pks[idx].0, &hash_to_sc(s_i));
-    }
-    // Now, we have filled in every s value except that at our own index. This one
-    // we have to compute rather than randomly generating
-    let mut challenge_eng = ChallengeHash::engine();
-    challenge_eng.input(&pubnonce.compress().to_bytes());
-    challenge_eng.input(&params[..]);
-    let e_i = ChallengeHash::from_engine(challenge_eng);
-    let s_i = &hash_to_sc(nonce) + (&hash_to_sc(e_i) * &sk.0);
-    ret[32 * (1 + my_idx)..32 * (2 + my_idx)].copy_from_slice(s_i.as_bytes());
-    if my_idx == 0 {
-        ret[0..32].copy_from_slice(&e_i[..]);
+    // Choose a random `s` value for every index, our own included, so that
+    // which one is ours makes no difference to the work done. Ours is
+    // replaced once it can be computed. The randomness is drawn in order
+    // first, so that hashing it on many threads gives the same values.
+    let mut rngs = vec![[0; 32]; n];
+    for rng in &mut rngs {
+        randomness(rng)?;
+    }
+    let s = random_s_values(&rngs, &params, &nonce, flags.parallel)?;
+
+    let nonce = hash_to_scalar(nonce.as_inner());
+    let pubnonce = &nonce * &constants::ED25519_BASEPOINT_TABLE;
+    // The `s` value after our own, which starts the walk, commits to our
+    // nonce point. Without our secret key it looks as random as the others.
+    let first_s = match flags.claimable && n > 1 {
+        true => Some(claim::commitment(&params, &claim::claim_secret(sk, &params), &pubnonce)),
+        false => None,
+    };
+    let start = (pubnonce, link.map(|link| nonce * link.base));
+    // Hash the nonce before the params since the nonce is non-constant (in fact,
+    // it is hard for an attacker to control at all). Assuming SHA256 is secure,
+    // this accomplishes nothing except preventing the verifier from caching any
+    // part of the hash computation. But if SHA2 were to be broken this would
+    // plausibly save us.
+    let hash = |&(pubnonce, link_nonce): &(EdwardsPoint, Option<EdwardsPoint>)| {
+        challenge(&pubnonce, link_nonce.as_ref(), &params).into_inner()
+    };
+    let next = |s_i: &Scalar, e_i: &Scalar, key: &EdwardsPoint| {
+        let pubnonce = s_i * &constants::ED25519_BASEPOINT_TABLE + e_i * key;
+        (pubnonce, link.map(|link| s_i * link.base - e_i * link.image))
+    };
+    Ok(walk_ring(negated, my_idx, s, first_s, nonce, sk, start, hash, next, progress))
+}
+
+/// Walk a sorted ring from just after the signer's index, as a verifier
+/// would, and return the proof
+///
+/// This is shared by the provers over every group. `negated` are the
+/// negated keys and `s` the random `s` value for each index; `first_s`, if
+/// given, replaces the one after the signer's. The walk starts from the
+/// state `start` made with `nonce`, hashes each state to a challenge with
+/// `challenge`, and makes the next from an `s` value, challenge and key
+/// with `next`.
+#[allow(clippy::too_many_arguments)]
+fn walk_ring<P, T, C, N, F>(
+    negated: &[P],
+    my_idx: usize,
+    mut s: Vec<Scalar>,
+    first_s: Option<Scalar>,
+    nonce: Scalar,
+    sk: &SecretKey,
+    start: T,
+    mut challenge: C,
+    mut next: N,
+    mut progress: F,
+) -> Proof
+where
+    P: ConditionallySelectable,
+    C: FnMut(&T) -> [u8; 32],
+    N: FnMut(&Scalar, &Scalar, &P) -> T,
+    F: FnMut(usize, usize),
+{
+    let n = negated.len();
+    // Rather than indexing by our position, rotate the keys and `s` values
+    // so the walk always goes from 0 to n - 1, with ours last
+    let mut keys = negated.to_vec();
+    rotate_left_ct(&mut keys, my_idx + 1);
+    rotate_left_ct(&mut s, my_idx + 1);
+    if let Some(first_s) = first_s {
+        s[0] = first_s;
     }
+    // The position of index 0 in the walk, whose challenge starts the proof
+    let first_pos = n - 1 - my_idx;
+    let mut first = [0; 32];
 
-    Ok(ret)
+    let mut state = start;
+    for pos in 0..n {
+        let e_i = challenge(&state);
+        let is_first = (pos as u64).ct_eq(&(first_pos as u64));
+        for (byte, e_byte) in first.iter_mut().zip(&e_i[..]) {
+            byte.conditional_assign(e_byte, is_first);
+        }
+        let e_i = hash_to_scalar(&e_i);
+        if pos == n - 1 {
+            // We have every s value except our own. This one we have to
+            // compute rather than randomly generating
+            s[pos] = nonce + e_i * sk.0; // Scalar addition always reduces
+        } else {
+            // Compute next R value as though we were a verifier. This is
+            // constant time, since the time taken for each `s` value would
+            // otherwise give away where in the proof the walk started.
+            state = next(&s[pos], &e_i, &keys[pos]);
+        }
+        progress(pos + 1, n);
+    }
+
+    rotate_left_ct(&mut s, n - 1 - my_idx);
+    let mut ret = vec![0; 32 * (n + 1)];
+    ret[0..32].copy_from_slice(&first);
+    for (s_i, out) in s.iter().zip(ret[32..].chunks_exact_mut(32)) {
+        out.copy_from_slice(s_i.as_bytes());
+    }
+    Proof::from_vec_unchecked(ret)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testkit;
+    use bitcoin_hashes::hex::FromHex;
+    use rand_chacha::rand_core::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
 
     #[test]
     fn single_key_proof() {
@@ -155,15 +1478,53 @@ mod tests {
         ]);
 
         let pk = sk1.to_public();
-        let proof = prove(&[pk], b"Hello, world!", sk1).unwrap();
+        let proof = prove(&[pk], b"Hello, world!", &sk1).unwrap();
         verify(&proof, &[pk], b"Hello, world!").unwrap();
-        assert!(verify(&proof, &[pk], b"Goodbye, world!").is_err());
+        assert_eq!(verify(&proof, &[pk], b"Goodbye, world!"), Err(VerifyError::BadProof));
     }
 
     #[test]
     fn empty_proof() {
         let proof = b"32 bytes32 bytes32 bytes32 bytes";
-        assert!(verify(&proof[..], &[], b"Goodbye, world!").is_err());
+        assert_eq!(Proof::from_bytes(proof.to_vec()), Err(ProofError::WrongLength { got: 32 }));
+        #[allow(deprecated)]
+        let result = verify_slice(&proof[..], &[], b"Goodbye, world!");
+        assert_eq!(result, Err(VerifyError::EmptyKeySet));
+    }
+
+    #[test]
+    fn wrong_lengths() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let proof = prove(&keys, b"msg", &sks[0]).unwrap();
+        assert_eq!(proof.as_bytes().len(), proof_len(5));
+        assert_eq!(proof_len(1), 64);
+
+        // Off by one key either way, so made for another ring
+        let short = verify(&proof, &keys[..4], b"msg").unwrap_err();
+        assert_eq!(short, VerifyError::WrongProofLength { expected: 160, got: 192, proof_ring_size: Some(5) });
+        assert_eq!(
+            short.to_string(),
+            "proof wrong length: expected 160 bytes, got 192, which is a proof for a ring of 5 keys not 4",
+        );
+        let long = prove(&keys[..1], b"msg", &sks[0]).unwrap();
+        let err = verify(&long, &keys, b"msg").unwrap_err();
+        assert_eq!(err, VerifyError::WrongProofLength { expected: 192, got: 64, proof_ring_size: Some(1) });
+        assert!(err.to_string().ends_with("a ring of 1 key not 5"), "{}", err);
+
+        // Not a multiple of 32 bytes, or too short for any ring, so cut short
+        #[allow(deprecated)]
+        let truncated = verify_slice(&proof.as_bytes()[..150], &keys, b"msg");
+        assert_eq!(truncated, Err(VerifyError::WrongProofLength { expected: 192, got: 150, proof_ring_size: None }));
+        #[allow(deprecated)]
+        let challenge_only = verify_slice(&proof.as_bytes()[..32], &keys, b"msg");
+        assert_eq!(
+            challenge_only,
+            Err(VerifyError::WrongProofLength { expected: 192, got: 32, proof_ring_size: None }),
+        );
+        assert_eq!(
+            challenge_only.unwrap_err().to_string(),
+            "proof wrong length: expected 192 bytes, got 32, which is no ring's proof",
+        );
     }
 
     #[test]
@@ -184,23 +1545,900 @@ mod tests {
         ]);
 
         let mut keys = key_str.iter().map(|key| PublicKey::parse_pk_line(key).unwrap()).collect::<Vec<_>>();
-        assert!(prove(&keys[..keys.len() - 1], b"Hello, world!", sk).is_err()); // my key not present
-        let proof = prove(&keys, b"Hello, world!", sk).unwrap();
+        assert_eq!(prove(&keys[..keys.len() - 1], b"Hello, world!", &sk), Err(ProveError::KeyNotInRing { sk_public: Box::new(sk.to_public()) })); // my key not present
+        let proof = prove(&keys, b"Hello, world!", &sk).unwrap();
         verify(&proof, &keys, b"Hello, world!").unwrap();
+        assert_eq!(proof.ring_size(), keys.len());
 
-        assert!(verify(&proof, &keys, b"Goodbye, world!").is_err()); // wrong message
-        assert!(verify(&proof[..keys.len() - 1], &keys, b"Hello, world!").is_err()); // not enough keys
+        assert_eq!(verify(&proof, &keys, b"Goodbye, world!"), Err(VerifyError::BadProof)); // wrong message
+        assert_eq!(
+            verify(&Proof::from_bytes(proof.as_bytes()[..192].to_vec()).unwrap(), &keys, b"Hello, world!"),
+            Err(VerifyError::WrongProofLength { expected: 224, got: 192, proof_ring_size: Some(5) }),
+        ); // not enough keys
         // Key ordering does not matter
         keys.swap(0, 1);
         verify(&proof, &keys, b"Hello, world!").unwrap();
     }
 
     #[test]
-    fn torsion_key() {
-        assert!(PublicKey::parse_pk_line(
-                "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAII0PQoSjaDulROj7qwNNsJ1cCa+sqlWsKs3e8nemW9J+ apoelstra-torsion"
-        ).is_err()); // FIXME check that we get specifically the torsion error
+    fn repeated_keys() {
+        let (mut sks, mut keys) = testkit::deterministic_keys(b"ringsig tests", 4);
+        let (sk, pk) = (sks.remove(3), keys.remove(3));
+        let duplicate = |pk: PublicKey| ProveError::DuplicateKey(Box::new(pk));
+
+        assert_eq!(prove(&[pk, pk], b"msg", &sk), Err(duplicate(pk)));
+        assert_eq!(duplicate(pk).code(), "duplicate_key");
+
+        // Refused whichever key repeats, and wherever in the list
+        let repeated = [keys[0], pk, keys[1], keys[0]];
+        assert_eq!(prove(&repeated, b"msg", &sk), Err(duplicate(keys[0])));
+        assert_eq!(Ring::new(&repeated).err(), Some(duplicate(keys[0])));
+        assert_eq!(ring_index_of(&repeated, &pk), None);
+        let mine_twice = [pk, keys[2], pk];
+        assert_eq!(prove_many(&mine_twice, &["one", "two"], &sk), Err(duplicate(pk)));
+        assert_eq!(prove_linkable(&mine_twice, b"msg", b"scope", &sk).err(), Some(duplicate(pk)));
+
+        // Verifying refuses the list too, rather than taking it as a set
+        let set = [keys[0], pk, keys[1]];
+        let proof = prove(&set, b"msg", &sk).unwrap();
+        verify(&proof, &set, b"msg").unwrap();
+        assert_eq!(verify(&proof, &repeated, b"msg"), Err(VerifyError::DuplicateKey(Box::new(keys[0]))));
+        assert_eq!(
+            verify(&proof, &[keys[1], keys[1], pk, keys[0]], b"msg"),
+            Err(VerifyError::DuplicateKey(Box::new(keys[1]))),
+        );
+        // The ring id is still that of the set
+        assert_eq!(ring_id(&repeated), ring_id(&set));
+    }
+
+    #[test]
+    fn presorted() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sorted = Ring::new(&keys).unwrap().keys().to_vec();
+        let proof = prove(&keys, b"msg", &sks[2]).unwrap();
+        verify_presorted(&proof, &sorted, b"msg").unwrap();
+        assert_eq!(verify_presorted(&proof, &sorted, b"other"), Err(VerifyError::BadProof));
+        assert_eq!(verify_presorted(&proof, &sorted[1..], b"msg"), verify(&proof, &sorted[1..], b"msg"));
+        assert_eq!(verify_presorted(&proof, &[], b"msg"), Err(VerifyError::EmptyKeySet));
+
+        // Refused as by `verify`, though sorted
+        let repeated = [&sorted[..2], &sorted[1..]].concat();
+        assert_eq!(verify_presorted(&proof, &repeated, b"msg"), Err(VerifyError::DuplicateKey(Box::new(sorted[1]))));
+        let mut weak = sorted.clone();
+        weak.push(SecretKey::from_bytes([0; 32]).to_public());
+        weak.sort_unstable_by_key(PublicKey::serialize);
+        assert_eq!(verify_presorted(&proof, &weak, b"msg"), Err(VerifyError::WeakKey));
+        weak.push(weak[0]);
+        weak.sort_unstable_by_key(PublicKey::serialize);
+        assert_eq!(verify_presorted(&proof, &weak, b"msg"), verify(&proof, &weak, b"msg"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not sorted")]
+    fn presorted_unsorted() {
+        let (sks, mut keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        keys.sort_unstable_by_key(PublicKey::serialize);
+        let proof = prove(&keys, b"msg", &sks[2]).unwrap();
+        keys.swap(0, 1);
+        let _ = verify_presorted(&proof, &keys, b"msg");
+    }
+
+    #[test]
+    fn unprovable_rings() {
+        let (mut sks, mut keys) = testkit::deterministic_keys(b"ringsig tests", 4);
+        let sk = sks.remove(3);
+        keys.truncate(3);
+        assert_eq!(prove(&[], b"msg", &sk), Err(ProveError::EmptyKeySet));
+        match Ring::new(&keys).unwrap().prove(b"msg", &sk) {
+            Err(ProveError::KeyNotInRing { sk_public }) => assert_eq!(*sk_public, sk.to_public()),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn progress_reports_every_key() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        let mut seen = vec![];
+        let proof = prove_with_progress(&keys, b"msg", &sk, |done, total| seen.push((done, total))).unwrap();
+        assert_eq!(seen, (1..=5).map(|i| (i, 5)).collect::<Vec<_>>());
+
+        seen.clear();
+        verify_with_progress(&proof, &keys, b"msg", |done, total| seen.push((done, total))).unwrap();
+        assert_eq!(seen, (1..=5).map(|i| (i, 5)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cancelling() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+        let proof = prove(&keys, b"msg", &sk).unwrap();
+
+        let mut seen = vec![];
+        let stop_after_two = |done, total| {
+            seen.push((done, total));
+            if done == 2 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        };
+        assert_eq!(verify_cancellable(&proof, &keys, b"msg", stop_after_two), Err(VerifyError::Cancelled));
+        assert_eq!(seen, [(1, 5), (2, 5)]);
+        verify_cancellable(&proof, &keys, b"msg", |_, _| ControlFlow::Continue(())).unwrap();
+
+        let (proof, image) = prove_linkable(&keys, b"msg", b"scope", &sk).unwrap();
+        let never = |_, _| ControlFlow::Break(());
+        assert_eq!(verify_linkable_cancellable(&proof, &image, &keys, b"msg", b"scope", never), Err(VerifyError::Cancelled));
+        verify_linkable_cancellable(&proof, &image, &keys, b"msg", b"scope", |_, _| ControlFlow::Continue(())).unwrap();
+    }
+
+    #[test]
+    fn prehashed() {
+        let (sks, mut keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        // Longer than any buffer `io::copy` reads through
+        let message: Vec<u8> = (0..100_000u32).flat_map(u32::to_le_bytes).collect();
+        let path = std::env::temp_dir().join(format!("ringsig-prehashed-test-{}", std::process::id()));
+        std::fs::write(&path, &message).unwrap();
+        let digest = digest_reader(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut eng = MessageDigest::engine();
+        for chunk in message.chunks(1000) {
+            eng.input(chunk);
+        }
+        assert_eq!(MessageDigest::from_engine(eng), digest);
+
+        let proof = prove_prehashed(&keys, &digest, &sk).unwrap();
+        keys.reverse();
+        verify_prehashed(&proof, &keys, &digest).unwrap();
+        let other = digest_reader(&message[1..]).unwrap();
+        assert_eq!(verify_prehashed(&proof, &keys, &other), Err(VerifyError::BadProof));
+
+        // Pre-hashed and raw-message proofs are never interchangeable
+        assert_eq!(verify(&proof, &keys, &message), Err(VerifyError::BadProof));
+        assert_eq!(verify(&proof, &keys, &digest[..]), Err(VerifyError::BadProof));
+        let raw = prove(&keys, &digest[..], &sk).unwrap();
+        assert_eq!(verify_prehashed(&raw, &keys, &digest), Err(VerifyError::BadProof));
+        assert_eq!(
+            prove_prehashed(&keys[1..], &digest, &sk),
+            Err(ProveError::KeyNotInRing { sk_public: Box::new(sk.to_public()) }),
+        );
+    }
+
+    #[test]
+    fn streaming() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        // Longer than any buffer `io::copy` reads through
+        let message: Vec<u8> = (0..100_000u32).flat_map(u32::to_le_bytes).collect();
+        let path = std::env::temp_dir().join(format!("ringsig-streaming-test-{}", std::process::id()));
+        std::fs::write(&path, &message).unwrap();
+        let proof = prove_streaming(&keys, std::fs::File::open(&path).unwrap(), &sk).unwrap();
+        verify(&proof, &keys, &message).unwrap();
+        verify_streaming(&proof, &keys, std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let proof = prove(&keys, &message, &sk).unwrap();
+        verify_streaming(&proof, &keys, io::Cursor::new(&message)).unwrap();
+        assert_eq!(verify_streaming(&proof, &keys, io::Cursor::new(&message[1..])), Err(VerifyError::BadProof));
+
+        // Given the same randomness, the proofs are the same
+        let aux = [3; 32];
+        let ring = Ring::new(&keys).unwrap();
+        let my_idx = find_signer(&ring.pks, &sk).unwrap();
+        let params = finish_param_hash_reader(ring.params.clone(), io::Cursor::new(&message)).unwrap();
+        let fixed = |rng: &mut [u8; 32]| {
+            *rng = aux;
+            Ok(())
+        };
+        let streamed = prove_sorted_with(&ring.negated, my_idx, params, &sk, None, |_, _| {}, fixed, ProveFlags::default());
+        assert_eq!(streamed, prove_with_aux_rand(&keys, &message, &sk, &aux));
+
+        // Only the rest of the message, from where the reader is, is proved
+        let mut cursor = io::Cursor::new(&message);
+        cursor.set_position(4);
+        let proof = prove_streaming(&keys, cursor, &sk).unwrap();
+        verify(&proof, &keys, &message[4..]).unwrap();
+        let proof = prove_streaming(&keys, io::Cursor::new(&[]), &sk).unwrap();
+        verify(&proof, &keys, b"").unwrap();
+    }
+
+    #[test]
+    fn contexts() {
+        let (sks, mut keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        let proof = prove_with_context(&keys, b"msg", b"siteA", &sk).unwrap();
+        keys.reverse();
+        verify_with_context(&proof, &keys, b"msg", b"siteA").unwrap();
+        assert_eq!(verify_with_context(&proof, &keys, b"msg", b"siteB"), Err(VerifyError::BadProof));
+        assert_eq!(verify_with_context(&proof, &keys, b"msg", b""), Err(VerifyError::BadProof));
+        assert_eq!(verify(&proof, &keys, b"msg"), Err(VerifyError::BadProof));
+        let ring = Ring::new(&keys).unwrap();
+        assert_eq!(ring.verify_with_context(&proof, b"msg", b"siteA"), Ok(()));
+        assert_eq!(ring.verify_with_context(&proof, b"msg", b"siteB"), Err(VerifyError::BadProof));
+
+        // The length prefix keeps the context apart from the message
+        let moved = prove_with_context(&keys, b"msgsite", b"A", &sk).unwrap();
+        assert_eq!(verify_with_context(&moved, &keys, b"msg", b"siteA"), Err(VerifyError::BadProof));
+
+        // The empty context is no context at all
+        let plain = prove(&keys, b"msg", &sk).unwrap();
+        assert_eq!(verify_with_context(&plain, &keys, b"msg", b""), Ok(()));
+        let empty = ring.prove_with_context(b"msg", b"", &sk).unwrap();
+        assert_eq!(verify(&empty, &keys, b"msg"), Ok(()));
+        assert_eq!(ring.params_in(b"msg", b""), ring.params(b"msg"));
+
+        // The context follows the message, as it has since contexts were
+        // added, the same by a Ring or not
+        let sorted = ring.keys.iter().copied().zip(ring.keys());
+        let params = sorted_params::<_, VerifyError>(sorted, b"msg", b"siteA").unwrap();
+        assert_eq!(ring.params_in(b"msg", b"siteA"), Ok(params));
+        let mut eng = ParamsHash::engine();
+        eng.input(&ring_size_le(ring.keys.len()).unwrap());
+        ring.keys.iter().for_each(|key| eng.input(key));
+        eng.input(&3u64.to_le_bytes());
+        eng.input(b"msg");
+        eng.input(&5u64.to_le_bytes());
+        eng.input(b"siteA");
+        assert_eq!(params, ParamsHash::from_engine(eng));
+
+        // So proofs already made with a context keep verifying
+        let options = ProveOptions::new().context(b"siteA").aux_rand([3; 32]);
+        let frozen = Proof::from_hex(concat!(
+            "3af27cc3637e14fa636b3de6db20c6feb9b17efcf44cfc96516d4587c7639b00",
+            "cce9b09247a4daf6aaed5cce287e7c6195f812fed2dd24c2c5a11eceee0f1908",
+            "718722f19423c5f3bfd068cc40c499ed53a02383245d6866ae7a0431dbdae406",
+            "08bf1e60f1fd22aa76ef102c24c555416a7a418add7bfb9fda593c40f098770b",
+            "59d167446f27bb38f20164152e5fd5dbc5a91e1d78071c866f19abf1e2803e08",
+            "1f53e78181cffe3ab72412c2a988fc8a27d1941eb37be7f428f007180a59e10b",
+        ))
+        .unwrap();
+        assert_eq!(prove_with(&keys, b"msg", &sk, &options), Ok(frozen.clone()));
+        verify_with_context(&frozen, &keys, b"msg", b"siteA").unwrap();
+    }
+
+    #[test]
+    fn versioned_proofs() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        let proof = prove(&keys, b"msg", &sk).unwrap();
+        for encoding in [ProofEncoding::Legacy, ProofEncoding::Versioned] {
+            let parsed = Proof::from_bytes(proof.encode(encoding)).unwrap();
+            assert_eq!(verify(&parsed, &keys, b"msg"), Ok(()));
+        }
+        let mut future = proof.encode(ProofEncoding::Versioned);
+        future[0] = PROOF_V1 + 1;
+        assert_eq!(Proof::from_bytes(future), Err(ProofError::UnknownVersion(PROOF_V1 + 1)));
+    }
+
+    #[test]
+    fn aux_rand() {
+        let (sks, mut keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        let zero = prove_with_aux_rand(&keys, b"msg", &sk, &[0; 32]).unwrap();
+        verify(&zero, &keys, b"msg").unwrap();
+        assert_eq!(prove_with_aux_rand(&keys, b"msg", &sk, &[0; 32]).unwrap(), zero);
+        keys.reverse();
+        assert_eq!(prove_with_aux_rand(&keys, b"msg", &sk, &[0; 32]).unwrap(), zero);
+
+        let mut seen = vec![zero];
+        for aux in [[1; 32], [0xff; 32], *b"thirty-two bytes of aux random!!"] {
+            let proof = prove_with_aux_rand(&keys, b"msg", &sk, &aux).unwrap();
+            verify(&proof, &keys, b"msg").unwrap();
+            check_canonical(proof.as_bytes()).unwrap();
+            assert!(!seen.contains(&proof));
+            seen.push(proof);
+        }
+        let outside = prove_with_aux_rand(&keys[1..], b"msg", &sk, &[0; 32]);
+        assert_eq!(outside, Err(ProveError::KeyNotInRing { sk_public: Box::new(sk.to_public()) }));
+    }
+
+    #[test]
+    fn known_index() {
+        let (sks, mut keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        let index = ring_index_of(&keys, &sk.to_public()).unwrap();
+        let ring = Ring::new(&keys).unwrap();
+        assert_eq!(ring.keys()[index], sk.to_public());
+        assert_eq!(ring.index_of(&sk.to_public()), Some(index));
+        let outsider = testkit::synthetic_ring(b"ringsig outsider", 1)[0];
+        assert_eq!(ring_index_of(&keys, &outsider), None);
+        keys.reverse();
+        assert_eq!(ring_index_of(&keys, &sk.to_public()), Some(index));
+
+        let proof = prove_with_index(&keys, b"msg", &sk, index).unwrap();
+        verify(&proof, &keys, b"msg").unwrap();
+        assert_eq!(ring.prove_with_index(b"msg", &sk, index).map(|proof| verify(&proof, &keys, b"msg")), Ok(Ok(())));
+        for wrong in [(index + 1) % 5, 5, usize::MAX] {
+            assert_eq!(prove_with_index(&keys, b"msg", &sk, wrong), Err(ProveError::WrongSignerIndex { index: wrong }));
+        }
+
+        // Given the same randomness, it makes the same proof as searching
+        let aux = [3; 32];
+        check_signer(&ring.pks, &sk, index).unwrap();
+        let params = ring.params(b"msg").unwrap();
+        let fixed = |rng: &mut [u8; 32]| {
+            *rng = aux;
+            Ok(())
+        };
+        let with_index = prove_sorted_with(&ring.negated, index, params, &sk, None, |_, _| {}, fixed, ProveFlags::default());
+        assert_eq!(with_index, prove_with_aux_rand(&keys, b"msg", &sk, &aux));
+    }
+
+    /// Randomness of all zero bytes, to compare with [`prove_with_aux_rand`]
+    struct ZeroRng;
+
+    impl RngCore for ZeroRng {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            0
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            self.fill_bytes(dest);
+            Ok(())
+        }
+    }
+
+    impl CryptoRng for ZeroRng {}
+
+    #[test]
+    fn external_rng() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        // A seeded rng gives valid proofs, the same ones every time
+        let proof = prove_with_rng(&keys, b"msg", &sk, &mut ChaCha20Rng::seed_from_u64(1)).unwrap();
+        verify(&proof, &keys, b"msg").unwrap();
+        assert_eq!(prove_with_rng(&keys, b"msg", &sk, &mut ChaCha20Rng::seed_from_u64(1)), Ok(proof.clone()));
+        assert_ne!(prove_with_rng(&keys, b"msg", &sk, &mut ChaCha20Rng::seed_from_u64(2)), Ok(proof));
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        let first = prove_with_rng(&keys, b"msg", &sk, &mut rng).unwrap();
+        assert_ne!(prove_with_rng(&keys, b"msg", &sk, &mut rng), Ok(first));
+
+        // Pinned, so that the order randomness is drawn in cannot change
+        // unnoticed: once for the nonce, then once for each s value in the
+        // order of the sorted ring. The signer is neither first nor last, so
+        // starting anywhere else would give another proof.
+        assert_eq!(ring_index_of(&keys, &keys[3]), Some(2));
+        let pinned = Proof::from_hex(concat!(
+            "14be173ff13591205b13e3fe2f2c346079d889b873166b2e5d38e41a1ca907f2",
+            "70a6dee02bdf053dd48b9847efb577b4612165ac1b95f5fb84c93ef1f7e70d0c",
+            "4aaa5916bd129baee51c8c738d48ddb9b53d2d962f2e07e2b570725cf7608200",
+            "f7fb32fc8a116b5033c3f95b0181bb30b6e62dd78a090b036117c73adfcdbb04",
+            "0a05db319508a9e3442a8e886fb4ca0f5f563332f6e4ba50ab43c5a8af033903",
+            "a608cb0506d7849477c7619bb617ca6acc6391e51be696e5309e905e1b232f00",
+        ))
+        .unwrap();
+        assert_eq!(prove_with_rng(&keys, b"msg", &sks[3], &mut ChaCha20Rng::seed_from_u64(1)), Ok(pinned));
+
+        // No extra entropy is the same as all-zero aux randomness
+        let proof = prove_with_rng(&keys, b"msg", &sk, &mut ZeroRng).unwrap();
+        verify(&proof, &keys, b"msg").unwrap();
+        assert_eq!(prove_with_aux_rand(&keys, b"msg", &sk, &[0; 32]), Ok(proof));
+        assert_eq!(
+            prove_with_rng(&keys[..4], b"msg", &sk, &mut ZeroRng),
+            Err(ProveError::KeyNotInRing { sk_public: Box::new(sk.to_public()) }),
+        );
+    }
+
+    #[test]
+    fn prove_many_messages() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sk = sks[4].clone();
+
+        let messages = ["one", "two", "three"];
+        let proofs = prove_many(&keys, &messages, &sk).unwrap();
+        assert_eq!(proofs.len(), 3);
+        for (proof, message) in proofs.iter().zip(messages) {
+            verify(proof, &keys, message.as_bytes()).unwrap();
+        }
+        assert_eq!(verify(&proofs[0], &keys, b"two"), Err(VerifyError::BadProof));
+        assert_eq!(prove_many(&keys[..4], &messages, &sk), Err(ProveError::KeyNotInRing { sk_public: Box::new(sk.to_public()) }));
     }
-}
 
+    #[test]
+    fn canonical_scalars() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 16);
+        let sk = sks[15].clone();
+        let mut proof = prove(&keys, b"msg", &sk).unwrap().into_bytes();
+        check_canonical(&proof).unwrap();
+        let (linkable, _) = prove_linkable(&keys, b"msg", b"scope", &sk).unwrap();
+        check_canonical(linkable.as_bytes()).unwrap();
+
+        // Adding the group order to an s value gives a proof of the same
+        // statement, which is rejected only when verifying strictly
+        let l = constants::BASEPOINT_ORDER.to_bytes();
+        let mut carry = 0u16;
+        for (byte, l_byte) in proof[64..96].iter_mut().zip(l) {
+            let sum = u16::from(*byte) + u16::from(l_byte) + carry;
+            *byte = sum as u8;
+            carry = sum >> 8;
+        }
+        let malleated = Proof::from_bytes(proof.clone()).unwrap();
+        verify(&malleated, &keys, b"msg").unwrap();
+        Ring::new(&keys).unwrap().verify(&malleated, b"msg").unwrap();
+        let strict = VerifyOptions::new().strict();
+        assert_eq!(verify_with(&malleated, &keys, b"msg", &strict), Err(VerifyError::NonCanonicalScalar { index: 2 }));
+        let sorted = VerifyOptions::new().strict().assume_sorted();
+        let sorted_keys = Ring::new(&keys).unwrap().keys().to_vec();
+        let result = verify_with(&malleated, &sorted_keys, b"msg", &sorted);
+        assert_eq!(result, Err(VerifyError::NonCanonicalScalar { index: 2 }));
+        verify_with(&malleated.canonicalize(), &keys, b"msg", &strict).unwrap();
+        assert_eq!(check_canonical(&proof), Err(VerifyError::NonCanonicalScalar { index: 2 }));
+        assert_eq!(check_canonical(&proof[1..]), Err(VerifyError::Malformed(ProofError::WrongLength { got: proof.len() - 1 })));
+    }
+
+    #[test]
+    fn members() {
+        let keys = testkit::synthetic_ring(b"ringsig tests", 4);
+        assert_eq!(ring_members(&keys[1..3], &keys), [1, 2]);
+        assert_eq!(ring_members(&keys[..1], &keys[1..]), [] as [usize; 0]);
+    }
+
+    #[test]
+    fn prepared_ring() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let ring = Ring::new(&keys).unwrap();
+        assert_eq!(ring.keys().len(), 5);
+        assert!(ring.keys().windows(2).all(|w| w[0].serialize() < w[1].serialize()));
+        assert!(ring.keys().iter().zip(&ring.keys).all(|(pk, key)| pk.serialize() == *key));
+        assert!(ring.keys().iter().zip(&ring.negated).all(|(pk, neg)| pk.0 + neg == EdwardsPoint::identity()));
+
+        // Proofs are interchangeable with those over the slice
+        let proof = ring.prove(b"msg", &sks[3]).unwrap();
+        verify(&proof, &keys, b"msg").unwrap();
+        assert_eq!(ring.verify(&proof, b"other"), Err(VerifyError::BadProof));
+        let proof = prove(&keys, b"msg", &sks[0]).unwrap();
+        ring.verify(&proof, b"msg").unwrap();
+
+        let outside = Ring::new(&keys[1..]).unwrap().prove(b"msg", &sks[0]);
+        assert_eq!(outside, Err(ProveError::KeyNotInRing { sk_public: Box::new(keys[0]) }));
+        assert_eq!(Ring::new(&[]).unwrap().verify(&proof, b"msg"), Err(VerifyError::EmptyKeySet));
+    }
+
+    #[test]
+    fn ring_signer() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let signer = RingSigner::new(&keys, sks[2].clone()).unwrap();
+        assert_eq!(Some(signer.index()), ring_index_of(&keys, &keys[2]));
+        assert_eq!(signer.ring().keys()[signer.index()], keys[2]);
+        for message in [&b"first"[..], b"second", b""] {
+            let proof = signer.sign(message).unwrap();
+            verify(&proof, &keys, message).unwrap();
+            signer.ring().verify(&proof, message).unwrap();
+        }
+        assert_ne!(signer.sign(b"msg").unwrap(), signer.sign(b"msg").unwrap());
 
+        let outside = Ring::new(&keys[1..]).unwrap().signer(sks[0].clone()).err();
+        assert_eq!(outside, Some(ProveError::KeyNotInRing { sk_public: Box::new(keys[0]) }));
+        assert_eq!(RingSigner::new(&[], sks[0].clone()).err(), Some(ProveError::EmptyKeySet));
+        let repeated = [&keys[..], &keys[..1]].concat();
+        assert!(matches!(RingSigner::new(&repeated, sks[0].clone()), Err(ProveError::DuplicateKey(_))));
+    }
+
+    #[test]
+    fn ring_message() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let bound = RingMessage::new(&keys, b"msg").unwrap();
+        assert_eq!(bound.message(), b"msg");
+        assert_eq!(bound.params_hash(), params_hash(&keys, b"msg").unwrap());
+        assert_eq!(bound.ring().keys(), Ring::new(&keys).unwrap().keys());
+
+        // Proofs are interchangeable with those over the slice
+        let proof = bound.prove(&sks[1]).unwrap();
+        verify(&proof, &keys, b"msg").unwrap();
+        bound.verify(&prove(&keys, b"msg", &sks[4]).unwrap()).unwrap();
+        assert_eq!(bound.verify(&prove(&keys, b"other", &sks[4]).unwrap()), Err(VerifyError::BadProof));
+        let other = Ring::new(&keys).unwrap().with_message(b"other").unwrap();
+        assert_eq!(other.verify(&proof), Err(VerifyError::BadProof));
+
+        let outside = RingMessage::new(&keys[1..], b"msg").unwrap().prove(&sks[0]);
+        assert_eq!(outside, Err(ProveError::KeyNotInRing { sk_public: Box::new(keys[0]) }));
+        let repeated = [&keys[..], &keys[..1]].concat();
+        assert!(matches!(RingMessage::new(&repeated, b"msg"), Err(VerifyError::DuplicateKey(_))));
+    }
+
+    #[test]
+    fn batch() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let first = prove(&keys, b"first", &sks[0]).unwrap();
+        let second = prove(&keys, b"second", &sks[4]).unwrap();
+        let short = prove(&keys[..3], b"first", &sks[0]).unwrap();
+        let batch: [(&Proof, &[u8]); 4] =
+            [(&first, b"first"), (&second, b"second"), (&second, b"first"), (&short, b"first")];
+
+        let results = verify_batch(&batch, &keys);
+        let wrong_length = VerifyError::WrongProofLength { expected: 192, got: 128, proof_ring_size: Some(3) };
+        assert_eq!(results, [Ok(()), Ok(()), Err(VerifyError::BadProof), Err(wrong_length)]);
+        let one_by_one: Vec<_> = batch.iter().map(|&(proof, message)| verify(proof, &keys, message)).collect();
+        assert_eq!(results, one_by_one);
+        assert_eq!(Ring::new(&keys).unwrap().verify_batch(&batch), results);
+
+        assert_eq!(verify_batch(&batch[..2], &[]), [Err(VerifyError::EmptyKeySet), Err(VerifyError::EmptyKeySet)]);
+        assert!(verify_batch(&[], &keys).is_empty());
+    }
+
+    #[test]
+    fn prove_in_parallel() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 40);
+        let proof = prove_parallel(&keys, b"msg", &sks[17]).unwrap();
+        verify(&proof, &keys, b"msg").unwrap();
+        assert_eq!(
+            prove_parallel(&keys[..17], b"msg", &sks[17]),
+            Err(ProveError::KeyNotInRing { sk_public: Box::new(keys[17]) }),
+        );
+
+        // The same randomness gives the same proof either way
+        let ring = Ring::new(&keys).unwrap();
+        let my_idx = find_signer(&ring.pks, &sks[17]).unwrap();
+        let params = ring.params(b"msg").unwrap();
+        let proofs: Vec<_> = [false, true]
+            .iter()
+            .map(|&parallel| {
+                let mut rng = ChaCha20Rng::seed_from_u64(1);
+                let flags = ProveFlags { parallel, ..ProveFlags::default() };
+                prove_sorted_with(&ring.negated, my_idx, params, &sks[17], None, |_, _| {}, randomness_from(&mut rng), flags)
+            })
+            .collect();
+        assert_eq!(proofs[0], proofs[1]);
+        assert_eq!(proofs[0], prove_with_rng(&keys, b"msg", &sks[17], &mut ChaCha20Rng::seed_from_u64(1)));
+    }
+
+    #[test]
+    fn batch_parallel() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 4);
+        let messages: Vec<Vec<u8>> = (0..40).map(|n| format!("message {}", n).into_bytes()).collect();
+        let proofs: Vec<_> = messages.iter().zip(sks.iter().cycle()).map(|(m, sk)| prove(&keys, m, sk).unwrap()).collect();
+        // Every third proof is checked against its neighbour's message
+        let batch: Vec<(&Proof, &[u8])> = (0..proofs.len())
+            .map(|n| match n % 3 {
+                2 => (&proofs[n], &messages[n - 1][..]),
+                _ => (&proofs[n], &messages[n][..]),
+            })
+            .collect();
+
+        let results = verify_batch_parallel(&batch, &keys);
+        for (n, result) in results.iter().enumerate() {
+            let expected = if n % 3 == 2 { Err(VerifyError::BadProof) } else { Ok(()) };
+            assert_eq!(*result, expected, "proof {}", n);
+        }
+        assert_eq!(results, verify_batch(&batch, &keys));
+        assert_eq!(verify_batch_parallel(&batch[..2], &[]), [Err(VerifyError::EmptyKeySet), Err(VerifyError::EmptyKeySet)]);
+        assert!(verify_batch_parallel(&[], &keys).is_empty());
+    }
+
+    #[test]
+    fn many() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let small = keys[..3].to_vec();
+        let first = prove(&keys, b"first", &sks[0]).unwrap();
+        let second = prove(&small, b"second", &sks[2]).unwrap();
+        let items: [(&Proof, &[PublicKey], &[u8]); 5] = [
+            (&first, &keys, b"first"),
+            (&second, &small, b"second"),
+            (&second, &keys[..3], b"second"),
+            (&first, &keys, b"second"),
+            (&second, &[], b"second"),
+        ];
+
+        let results = verify_many(&items);
+        let one_by_one: Vec<_> = items.iter().map(|&(proof, pks, message)| verify(proof, pks, message)).collect();
+        assert_eq!(results, one_by_one);
+        assert_eq!(results, [Ok(()), Ok(()), Ok(()), Err(VerifyError::BadProof), Err(VerifyError::EmptyKeySet)]);
+        assert!(verify_many(&[]).is_empty());
+    }
+
+    #[test]
+    fn params_hash_is_of_a_set() {
+        let keys = testkit::synthetic_ring(b"ringsig tests", 3);
+        let reordered = [keys[2], keys[0], keys[1]];
+        assert_eq!(params_hash(&keys, b"msg"), params_hash(&reordered, b"msg"));
+        assert_eq!(
+            params_hash(&[keys[2], keys[0], keys[1], keys[0]], b"msg"),
+            Err(VerifyError::DuplicateKey(Box::new(keys[0]))),
+        );
+        assert_ne!(params_hash(&keys, b"msg"), params_hash(&keys, b"msg2"));
+        assert_ne!(params_hash(&keys, b"msg"), params_hash(&keys[1..], b"msg"));
+        assert_eq!(params_hash(&[SecretKey::from_bytes([0; 32]).to_public()], b"msg"), Err(VerifyError::WeakKey));
+    }
+
+    #[test]
+    fn ring_id_is_a_set() {
+        let pk1 = PublicKey::parse_pk_line("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10").unwrap();
+        let pk2 = PublicKey::parse_pk_line("ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIGMiyoNWxKsdbuZ9EeJA+QTTaKHYtpCrRBlvCez8ykRl").unwrap();
+        assert_eq!(ring_id(&[pk1, pk2]), ring_id(&[pk2, pk1]));
+        assert_eq!(ring_id(&[pk1, pk2]), ring_id(&[pk2, pk1, pk2]));
+        assert_ne!(ring_id(&[pk1, pk2]), ring_id(&[pk1]));
+    }
+
+    #[test]
+    fn byte_order() {
+        // Counts and lengths are committed to little-endian, whatever the platform
+        assert_eq!(ring_size_le(0x0102_0304).unwrap(), [4, 3, 2, 1]);
+        assert_eq!(length_le(0x0102).unwrap(), [2, 1, 0, 0, 0, 0, 0, 0]);
+        if let Ok(len) = usize::try_from(u64::from(u32::MAX) + 1) {
+            assert_eq!(ring_size_le(len), Err(TooLarge::Keys));
+        }
+    }
+
+    #[test]
+    fn known_answers() {
+        // Fixed so that a change of encoding, e.g. on a big-endian target, is caught
+        let unhex = |s: &str| Vec::<u8>::from_hex(s).unwrap();
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 3);
+        assert_eq!(
+            Ring::new(&keys).unwrap().params(b"msg").unwrap()[..],
+            unhex("a9cc771873a6a7fc557faa9ed4a05e23306d104bac9d583bcc0eaf5f5530b2c1"),
+        );
+        assert_eq!(ring_id(&keys)[..], unhex("f6143edaf5fefe34b7cb75cf900435d81dc6ffc7c67b0bcece75680af85f50bf"));
+        // These are shown to users to compare, so are displayed as hex in order
+        assert_eq!(ring_id(&keys).to_string(), "f6143edaf5fefe34b7cb75cf900435d81dc6ffc7c67b0bcece75680af85f50bf");
+        assert_eq!(
+            params_hash(&keys, b"msg").unwrap().to_string(),
+            "a9cc771873a6a7fc557faa9ed4a05e23306d104bac9d583bcc0eaf5f5530b2c1",
+        );
+        assert_eq!(
+            scope_point(b"vote-1").unwrap().compress().to_bytes()[..],
+            unhex("fc76bd457ddf5f0cc959a96256da0575e10a520010f8529fb22706c6fba023dc"),
+        );
+
+        let proof = Proof::from_bytes(unhex(concat!(
+            "e554b01f562e34ffa077f5adc4011d41a0eba65881a930343c0b0745200714c6",
+            "67126aa671357eac187f2c6243b41c5b880ca20d619fb10ce00542fd3a46210f",
+            "a74d3d9e0a241361b6d7f02b0ae659b676cffdf38045d1dc70f0e43825290806",
+            "1cf80ff1223842c47911661c82a4d1a5d6567040702b84170ed76c2fd3d8d202",
+        )))
+        .unwrap();
+        let image = unhex("4963bb25aac67adbbd681889629bc50eab07aef04a28e03f27e255c131e45959");
+        let image: [u8; 32] = image.try_into().unwrap();
+        verify_linkable(&proof, &image, &keys, b"msg", b"vote-1").unwrap();
+        let (_, image_2) = prove_linkable(&keys, b"other", b"vote-1", &sks[1]).unwrap();
+        assert_eq!(image_2, image);
+
+        let json: serde_json::Value = serde_json::from_str(include_str!("../testdata/test-verify.json")).unwrap();
+        let keys: Vec<_> = json["publicKeys"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|key| PublicKey::parse_pk_line(key.as_str().unwrap()).unwrap())
+            .collect();
+        // A proof made before the prover reduced every s value, which must
+        // keep verifying
+        let legacy = Proof::from_hex(json["proof"].as_str().unwrap()).unwrap();
+        let message = json["message"].as_str().unwrap().as_bytes();
+        verify(&legacy, &keys, message).unwrap();
+        let strict = VerifyOptions::new().strict();
+        let result = verify_with(&legacy, &keys, message, &strict);
+        assert_eq!(result, Err(VerifyError::NonCanonicalScalar { index: 1 }));
+        let canonical = Proof::from_hex(concat!(
+            "d8be9b3acaba5a588b35e4cf125c98dfa2c7b0ce22e37c630a83ccd05c83f13b",
+            "f7aa261b77ac79cc7508fdfe969292d58d899a7b57d804522dffeee49950060a",
+            "a89fa2c8447111ece893751bdb91c100ace3637a5f9a2a2f83298f7292065f04",
+            "b8a819616d083b1463dcf39038af4a8fdba0af0d7835beaeb7ab657ca6a7b60a",
+        ))
+        .unwrap();
+        assert_eq!(legacy.canonicalize(), canonical);
+        assert_eq!(canonical.canonicalize(), canonical);
+        verify_with(&canonical, &keys, message, &strict).unwrap();
+    }
+
+    #[test]
+    fn prover_known_answers() {
+        // Proofs by every signer of rings of each size, with fixed randomness,
+        // so that any change to the prover's output is a deliberate one
+        let mut eng = sha256::Hash::engine();
+        for n in 1..=5 {
+            let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", n);
+            let ring = Ring::new(&keys).unwrap();
+            for sk in &sks {
+                let my_idx = find_signer(&ring.pks, sk).unwrap();
+                let params = || ring.params(b"msg").unwrap();
+                let image = sk.0 * scope_point(b"vote-1").unwrap();
+                for link in [None, Some(Link { base: scope_point(b"vote-1").unwrap(), image })] {
+                    let randomness = |rng: &mut [u8; 32]| {
+                        *rng = [0x42; 32];
+                        Ok(())
+                    };
+                    let flags = ProveFlags::default();
+                    let proof = prove_sorted_with(&ring.negated, my_idx, params(), sk, link, |_, _| {}, randomness, flags).unwrap();
+                    eng.input(proof.as_bytes());
+                }
+            }
+        }
+        let expected = Vec::<u8>::from_hex("2763968356f3c210883940ce546f2d1a0f00956ff661efbd62c667036dc26740").unwrap();
+        assert_eq!(sha256::Hash::from_engine(eng)[..], expected[..]);
+    }
+
+    #[test]
+    fn decoys_from_nonce() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let ring = Ring::new(&keys).unwrap();
+        let my_idx = find_signer(&ring.pks, &sks[2]).unwrap();
+        let randomness = |rng: &mut [u8; 32]| {
+            *rng = [0x42; 32];
+            Ok(())
+        };
+        let proofs: Vec<_> = [&b"msg"[..], b"other msg"]
+            .iter()
+            .map(|message| {
+                let params = ring.params(message).unwrap();
+                let flags = ProveFlags::default();
+                let proof = prove_sorted_with(&ring.negated, my_idx, params, &sks[2], None, |_, _| {}, randomness, flags);
+                let proof = proof.unwrap();
+                verify(&proof, &keys, message).unwrap();
+                proof
+            })
+            .collect();
+        // Even with the same randomness, no decoy is shared across messages
+        let decoys = |proof: &Proof| -> Vec<[u8; 32]> {
+            let s = proof.as_bytes()[32..].chunks_exact(32).map(|s_i| s_i.try_into().unwrap());
+            s.enumerate().filter(|&(idx, _)| idx != my_idx).map(|(_, s_i)| s_i).collect()
+        };
+        let (first, second) = (decoys(&proofs[0]), decoys(&proofs[1]));
+        assert_eq!(first.len(), 4);
+        assert!(first.iter().all(|s_i| !second.contains(s_i)));
+    }
+
+    #[test]
+    fn verifier() {
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let message: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+        let proof = prove(&keys, &message, &sks[2]).unwrap();
+        let verifier = || Verifier::new(proof.clone(), &keys, message.len() as u64).unwrap();
+
+        // However the message is split, the result is that of verify
+        for chunk in [1, 7, 64, 999, 1000, 5000] {
+            let mut v = verifier();
+            for piece in message.chunks(chunk) {
+                v.update(piece);
+            }
+            assert_eq!(v.finish(), verify(&proof, &keys, &message));
+        }
+        let mut v = verifier();
+        io::copy(&mut &message[..], &mut v).unwrap();
+        v.finish().unwrap();
+        let mut v = Verifier::new(proof.clone(), &keys, 0).unwrap();
+        v.update(&[]);
+        assert_eq!(v.finish(), Err(VerifyError::BadProof));
+        let empty = prove(&keys, b"", &sks[0]).unwrap();
+        Verifier::new(empty, &keys, 0).unwrap().finish().unwrap();
+
+        // A changed byte fails as verify does
+        let mut changed = message.clone();
+        changed[500] ^= 1;
+        let mut v = verifier();
+        v.update(&changed[..500]);
+        v.update(&changed[500..]);
+        assert_eq!(v.finish(), Err(VerifyError::BadProof));
+
+        // As does a message of another length than was promised
+        let mut v = verifier();
+        v.update(&message[..999]);
+        assert_eq!(v.finish(), Err(VerifyError::WrongMessageLength { expected: 1000, got: 999 }));
+        let mut v = verifier();
+        v.update(&message);
+        v.update(b"!");
+        let err = v.finish().unwrap_err();
+        assert_eq!(err.to_string(), "message wrong length: expected 1000 bytes, got 1001");
+
+        // The ring and proof length are checked up front
+        let err = Verifier::new(proof.clone(), &keys[1..], 1000).err();
+        assert_eq!(err, Some(VerifyError::WrongProofLength { expected: 160, got: 192, proof_ring_size: Some(5) }));
+        assert_eq!(Verifier::new(proof.clone(), &[], 1000).err(), Some(VerifyError::EmptyKeySet));
+    }
+
+    #[test]
+    fn options() {
+        // The default path makes the committed vectors' proofs byte for byte
+        let file = include_str!("../testdata/vectors/vectors.json");
+        let vectors = crate::vectors::Vectors::from_json_str(file).unwrap();
+        let mut checked = 0;
+        for vector in &vectors.vectors {
+            let (sk, aux) = match (&vector.secret_key, &vector.aux_rand) {
+                (Some(sk), Some(aux)) => (sk, aux),
+                _ => continue,
+            };
+            let sk = SecretKey::from_bytes(<[u8; 32]>::from_hex(sk).unwrap());
+            let aux = <[u8; 32]>::from_hex(aux).unwrap();
+            let parse = |pk: &String| PublicKey::parse(&Vec::from_hex(pk).unwrap()).unwrap();
+            let mut pks: Vec<_> = vector.public_keys.iter().map(parse).collect();
+            let message = Vec::from_hex(&vector.message).unwrap();
+            let proof = prove_with(&pks, &message, &sk, &ProveOptions::new().aux_rand(aux)).unwrap();
+            assert_eq!(proof.to_hex(), vector.proof);
+            verify_with(&proof, &pks, &message, &VerifyOptions::new()).unwrap();
+
+            // Sorting up front changes nothing
+            pks.sort_unstable_by_key(PublicKey::serialize);
+            let sorted = prove_with(&pks, &message, &sk, &ProveOptions::new().aux_rand(aux).assume_sorted()).unwrap();
+            assert_eq!(sorted, proof);
+            verify_with(&proof, &pks, &message, &VerifyOptions::new().assume_sorted()).unwrap();
+            checked += 1;
+        }
+        assert!(checked > 0);
+
+        let (sks, keys) = testkit::deterministic_keys(b"ringsig tests", 5);
+        let sorted = Ring::new(&keys).unwrap().keys().to_vec();
+        let proof = prove_with(&keys, b"msg", &sks[1], &ProveOptions::new()).unwrap();
+        verify(&proof, &keys, b"msg").unwrap();
+        let again = prove_with(&keys, b"msg", &sks[1], &ProveOptions::new()).unwrap();
+        assert_ne!(again, proof);
+        let aux = ProveOptions::new().aux_rand([9; 32]);
+        assert_eq!(prove_with(&keys, b"msg", &sks[1], &aux), prove_with_aux_rand(&keys, b"msg", &sks[1], &[9; 32]));
+
+        // A context is as with prove_with_context, sorted or not
+        for options in [ProveOptions::new().context(b"site"), ProveOptions::new().context(b"site").assume_sorted()] {
+            let proof = prove_with(&sorted, b"msg", &sks[1], &options).unwrap();
+            verify_with_context(&proof, &keys, b"msg", b"site").unwrap();
+            assert_eq!(verify(&proof, &keys, b"msg"), Err(VerifyError::BadProof));
+            let context = VerifyOptions::new().context(b"site");
+            verify_with(&proof, &keys, b"msg", &context).unwrap();
+            verify_with(&proof, &sorted, b"msg", &context.assume_sorted()).unwrap();
+            let other = VerifyOptions::new().context(b"other site");
+            assert_eq!(verify_with(&proof, &keys, b"msg", &other), Err(VerifyError::BadProof));
+            assert_eq!(verify_with(&proof, &sorted, b"msg", &other.assume_sorted()), Err(VerifyError::BadProof));
+        }
+        let proof = prove_with_context(&keys, b"msg", b"site", &sks[1]).unwrap();
+        verify_with(&proof, &keys, b"msg", &VerifyOptions::new().context(b"site")).unwrap();
+
+        // Sorted rings are still refused as they would be otherwise
+        let repeated = [&sorted[..1], &sorted[..]].concat();
+        let sorted_options = ProveOptions::new().assume_sorted();
+        let err = prove_with(&repeated, b"msg", &sks[1], &sorted_options).unwrap_err();
+        assert_eq!(err, ProveError::DuplicateKey(Box::new(sorted[0])));
+        let err = verify_with(&proof, &repeated, b"msg", &VerifyOptions::new().assume_sorted()).unwrap_err();
+        assert_eq!(err, VerifyError::DuplicateKey(Box::new(sorted[0])));
+        let outsider = testkit::deterministic_keys(b"ringsig outsider", 1).0.remove(0);
+        let err = prove_with(&sorted, b"msg", &outsider, &sorted_options).unwrap_err();
+        assert_eq!(err, ProveError::KeyNotInRing { sk_public: Box::new(outsider.to_public()) });
+
+        // Each proof version is written as asked, and reads back to a proof
+        // which verifies
+        for encoding in [ProofEncoding::Legacy, ProofEncoding::Versioned] {
+            let options = ProveOptions::new().aux_rand([9; 32]).proof_version(encoding);
+            let proof = prove_with(&keys, b"msg", &sks[1], &options).unwrap();
+            assert_eq!(proof.encoding(), encoding);
+            let bytes = proof.clone().into_bytes();
+            assert_eq!(bytes, proof.encode(encoding));
+            let parsed = Proof::from_bytes(bytes).unwrap();
+            assert_eq!(parsed.encoding(), encoding);
+            assert_eq!(parsed, prove_with_aux_rand(&keys, b"msg", &sks[1], &[9; 32]).unwrap());
+            verify_with(&parsed, &keys, b"msg", &VerifyOptions::new()).unwrap();
+            verify_with(&parsed, &keys, b"msg", &VerifyOptions::new().strict()).unwrap();
+        }
+    }
+
+    #[test]
+    fn identity_in_ring() {
+        let zero = SecretKey::from_bytes([0; 32]);
+        let (sks, mut keys) = testkit::deterministic_keys(b"ringsig tests", 3);
+        let proof = prove(&keys, b"msg", &sks[0]).unwrap();
+        keys.push(zero.to_public());
+        assert_eq!(prove(&keys, b"msg", &zero), Err(ProveError::WeakKey));
+        assert_eq!(prove(&keys, b"msg", &sks[0]), Err(ProveError::WeakKey));
+        assert!(matches!(Ring::new(&keys), Err(ProveError::WeakKey)));
+        assert_eq!(verify(&proof, &keys, b"msg"), Err(VerifyError::WeakKey));
+        assert_eq!(verify_batch(&[(&proof, b"msg")], &keys), [Err(VerifyError::WeakKey)]);
+        assert_eq!(VerifyError::WeakKey.code(), "weak_key");
+    }
+
+    #[test]
+    fn torsion_key() {
+        let err = PublicKey::parse_pk_line(
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAII0PQoSjaDulROj7qwNNsJ1cCa+sqlWsKs3e8nemW9J+ apoelstra-torsion"
+        ).unwrap_err();
+        assert!(matches!(err, keys::Error::TorsionKey(_)), "{}", err);
+    }
+}
@@ -0,0 +1,168 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Linkable proofs
+//!
+//! A linkable proof comes with its signer's key image for a scope, the
+//! secret key times a point derived from the scope. The image depends only
+//! on the key and the scope, so two linkable proofs in one scope by the
+//! same signer have the same image, whatever their rings and messages,
+//! while the proofs still do not reveal which ring member made them.
+//! Anyone can check that a proof commits to its image, but not which ring
+//! member's key the image is of.
+//!
+//! These are re-exported at the crate root.
+
+use std::ops::ControlFlow;
+
+use crate::keys::{PublicKey, SecretKey};
+use crate::{continuing, find_signer, link_of, prove_sorted, scope_point, verify_inner, Link, Proof, ProveError, Ring, VerifyError};
+
+/// Produce a linkable proof, returning it along with the signer's key image
+/// for `scope`
+///
+/// A key image depends only on the secret key and the scope, not on the ring
+/// or message, so two linkable proofs in the same scope by the same signer
+/// can be recognized as such, without revealing which ring member it was.
+pub fn prove_linkable(
+    pks: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+    sk: &SecretKey,
+) -> Result<(Proof, [u8; 32]), ProveError> {
+    prove_linkable_with_progress(pks, message, scope, sk, |_, _| {})
+}
+
+/// Produce a linkable proof, calling `progress` as in
+/// [`prove_with_progress`](crate::prove_with_progress)
+pub fn prove_linkable_with_progress<F: FnMut(usize, usize)>(
+    pks: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+    sk: &SecretKey,
+    progress: F,
+) -> Result<(Proof, [u8; 32]), ProveError> {
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let my_idx = find_signer(&ring.pks, sk)?;
+    let base = scope_point(scope)?;
+    let link = Link { base, image: sk.0 * base };
+    let proof = prove_sorted(&ring.negated, my_idx, ring.params(message)?, sk, Some(link), progress)?;
+    Ok((proof, link.image.compress().to_bytes()))
+}
+
+/// The key image of `sk` for `scope`, as returned with a linkable proof
+/// by [`prove_linkable`]
+pub fn key_image(sk: &SecretKey, scope: &[u8]) -> [u8; 32] {
+    // Only a scope longer than 2^64 bytes could fail to hash
+    let base = scope_point(scope).expect("slice lengths fit in 64 bits");
+    (sk.0 * base).compress().to_bytes()
+}
+
+/// Verify a linkable proof made by [`prove_linkable`] with the given key
+/// image and scope
+pub fn verify_linkable(
+    proof: &Proof,
+    key_image: &[u8; 32],
+    pks: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+) -> Result<(), VerifyError> {
+    verify_linkable_with_progress(proof, key_image, pks, message, scope, |_, _| {})
+}
+
+/// Verify a linkable proof given as bytes, as [`verify_linkable`]
+#[deprecated(note = "parse the bytes with `Proof::from_bytes` and use `verify_linkable`")]
+pub fn verify_linkable_slice(
+    proof: &[u8],
+    key_image: &[u8; 32],
+    pks: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+) -> Result<(), VerifyError> {
+    verify_inner(proof, pks, message, Some(link_of(key_image, scope)?), continuing(|_, _| {}))
+}
+
+/// Verify a linkable proof, calling `progress` as in
+/// [`verify_with_progress`](crate::verify_with_progress)
+pub fn verify_linkable_with_progress<F: FnMut(usize, usize)>(
+    proof: &Proof,
+    key_image: &[u8; 32],
+    pks: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+    progress: F,
+) -> Result<(), VerifyError> {
+    verify_inner(proof.as_bytes(), pks, message, Some(link_of(key_image, scope)?), continuing(progress))
+}
+
+/// Verify a linkable proof, calling `progress` and cancelling as in
+/// [`verify_cancellable`](crate::verify_cancellable)
+pub fn verify_linkable_cancellable<F: FnMut(usize, usize) -> ControlFlow<()>>(
+    proof: &Proof,
+    key_image: &[u8; 32],
+    pks: &[PublicKey],
+    message: &[u8],
+    scope: &[u8],
+    progress: F,
+) -> Result<(), VerifyError> {
+    verify_inner(proof.as_bytes(), pks, message, Some(link_of(key_image, scope)?), progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::verify;
+    use curve25519_dalek::constants;
+    use curve25519_dalek::edwards::CompressedEdwardsY;
+
+    #[test]
+    fn linkable_proof() {
+        let sk = SecretKey::from_bytes([7; 32]);
+        let other = SecretKey::from_bytes([3; 32]);
+        let mut keys: Vec<_> = (1..=4u8).map(|i| SecretKey::from_bytes([i; 32]).to_public()).collect();
+        keys.push(sk.to_public());
+
+        let (proof, image) = prove_linkable(&keys, b"msg", b"vote-1", &sk).unwrap();
+        verify_linkable(&proof, &image, &keys, b"msg", b"vote-1").unwrap();
+        assert_eq!(verify_linkable(&proof, &image, &keys, b"msg", b"vote-2"), Err(VerifyError::BadProof)); // wrong scope
+        assert_eq!(verify_linkable(&proof, &image, &keys, b"other", b"vote-1"), Err(VerifyError::BadProof)); // wrong message
+        assert_eq!(verify(&proof, &keys, b"msg"), Err(VerifyError::BadProof)); // not a plain proof
+
+        // Same signer and scope link, even with a different ring and message
+        let (_, image_2) = prove_linkable(&keys[2..], b"another", b"vote-1", &sk).unwrap();
+        assert_eq!(image, image_2);
+        let (_, image_3) = prove_linkable(&keys, b"msg", b"vote-2", &sk).unwrap();
+        assert_ne!(image, image_3);
+        let (proof_4, image_4) = prove_linkable(&keys, b"msg", b"vote-1", &other).unwrap();
+        assert_ne!(image, image_4);
+        assert_eq!(verify_linkable(&proof_4, &image, &keys, b"msg", b"vote-1"), Err(VerifyError::BadProof)); // wrong image
+
+        // Adding a torsion component to the image is rejected
+        let torsion = constants::EIGHT_TORSION[1];
+        let image_t = (CompressedEdwardsY(image).decompress().unwrap() + torsion).compress().to_bytes();
+        assert_eq!(verify_linkable(&proof, &image_t, &keys, b"msg", b"vote-1"), Err(VerifyError::KeyImageTorsion));
+    }
+
+    #[test]
+    fn key_images() {
+        let sk = SecretKey::from_bytes([7; 32]);
+        let keys: Vec<_> = (5..=7u8).map(|i| SecretKey::from_bytes([i; 32]).to_public()).collect();
+        let (_, image) = prove_linkable(&keys, b"msg", b"vote-1", &sk).unwrap();
+        assert_eq!(key_image(&sk, b"vote-1"), image);
+        assert_ne!(key_image(&sk, b"vote-2"), image);
+        assert_ne!(key_image(&SecretKey::from_bytes([5; 32]), b"vote-1"), image);
+    }
+}
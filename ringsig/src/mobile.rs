@@ -0,0 +1,519 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Bindings for Kotlin and Swift, through UniFFI
+//!
+//! This module is the interface definition: UniFFI reads the annotated items
+//! from the compiled library, so there is no separate UDL file. To generate
+//! the bindings, build the library with the `uniffi` feature and run
+//! `uniffi-bindgen` (version 0.28, matching the `uniffi` dependency) in
+//! library mode:
+//!
+//! ```text
+//! cargo build --release --features uniffi
+//! uniffi-bindgen generate --library target/release/libringsig.so --language kotlin --out-dir out
+//! ```
+//!
+//! For iOS, build a static library for each target instead, e.g. with
+//! `cargo rustc --release --features uniffi --target aarch64-apple-ios --crate-type staticlib`.
+//!
+//! Every call blocks until it is done, and none takes a callback, so
+//! verifying a large ring should be done off the UI thread. Messages are
+//! byte arrays; text should be encoded as UTF-8 first, as the command-line
+//! tool does. Proofs are byte arrays too, not hex.
+//!
+//! # Secret keys
+//!
+//! [`SecretKey`] and [`Ring::prove`] are included for apps which prove as
+//! well as verify, but nothing here zeroes secret material. The armored key
+//! and passphrase given to [`SecretKey::from_openssh`] are copied between the
+//! foreign and Rust heaps, and neither copy is wiped when it is freed; nor is
+//! the key itself when the [`SecretKey`] is destroyed. Apps which cannot
+//! accept that should keep secret keys out of this library, e.g. in the
+//! platform keystore, and use it only to verify.
+
+use std::fmt;
+use std::sync::Arc;
+
+use bitcoin_hashes::hex::ToHex;
+
+use crate::armor::{FromArmor, OpensshKey};
+use crate::bundle::{self, Confession, MultiBundle};
+use crate::keys;
+
+/// Problems with a public or secret key, as [`keys::Error`]
+#[derive(Clone, PartialEq, Eq, Debug, uniffi::Error)]
+pub enum KeyError {
+    /// The key had no data
+    EmptyKey,
+    /// The key had a type but no key
+    NoKey,
+    /// The key had an incorrect type
+    WrongKeyType { expected: String, got: String },
+    /// The key had an incorrect length
+    WrongKeyLength { expected: u64, got: u64 },
+    /// The key was not in the prime order group
+    TorsionKey,
+    /// The key was the identity point, for which anyone can sign
+    WeakKey,
+    /// The key was not a point on the curve
+    InvalidKey,
+    /// The base64 encoding of the key was bad
+    BadBase64 { message: String },
+    /// A secret key did not parse, or the passphrase did not decrypt it
+    BadArmor { message: String },
+    /// A JSON Web Key did not parse
+    BadJwk { message: String },
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            KeyError::EmptyKey => f.write_str("empty key"),
+            KeyError::NoKey => f.write_str("key type without a key"),
+            KeyError::WrongKeyType { ref expected, ref got } => write!(f, "key type was {}, not {}", got, expected),
+            KeyError::WrongKeyLength { expected, got } => write!(f, "key was {} bytes, not {}", got, expected),
+            KeyError::TorsionKey => f.write_str("key is not in the prime order group"),
+            KeyError::WeakKey => f.write_str("key is the identity point"),
+            KeyError::InvalidKey => f.write_str("key is not a point on the curve"),
+            KeyError::BadBase64 { ref message } => write!(f, "bad base64: {}", message),
+            KeyError::BadArmor { ref message } => write!(f, "bad secret key: {}", message),
+            KeyError::BadJwk { ref message } => write!(f, "bad JWK: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+impl From<keys::Error> for KeyError {
+    fn from(e: keys::Error) -> Self {
+        match e {
+            keys::Error::EmptyKey => KeyError::EmptyKey,
+            keys::Error::NoKey => KeyError::NoKey,
+            keys::Error::WrongKeyType { expected, got } => KeyError::WrongKeyType { expected, got },
+            keys::Error::WrongKeyLength { expected, got } => KeyError::WrongKeyLength {
+                expected: u64::try_from(expected).unwrap_or(u64::MAX),
+                got: u64::try_from(got).unwrap_or(u64::MAX),
+            },
+            keys::Error::TorsionKey(_) => KeyError::TorsionKey,
+            keys::Error::WeakKey(_) => KeyError::WeakKey,
+            keys::Error::InvalidKey(_) => KeyError::InvalidKey,
+            keys::Error::Radix64(e) => KeyError::BadBase64 { message: e.to_string() },
+            keys::Error::Armor(message) => KeyError::BadArmor { message },
+            keys::Error::InvalidJwk(message) => KeyError::BadJwk { message },
+        }
+    }
+}
+
+/// Problems proving or verifying
+#[derive(Clone, PartialEq, Eq, Debug, uniffi::Error)]
+pub enum ProofError {
+    /// The proof did not verify, or was malformed
+    Rejected { message: String },
+    /// No proof could be made, e.g. because the secret key is not in the ring
+    Failed { message: String },
+}
+
+impl fmt::Display for ProofError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProofError::Rejected { ref message } | ProofError::Failed { ref message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for ProofError {}
+
+/// Problems with a confession or bundle, as [`bundle::Error`]
+#[derive(Clone, PartialEq, Eq, Debug, uniffi::Error)]
+pub enum BundleError {
+    /// The JSON did not parse, or did not match the schema
+    Json { message: String },
+    /// The version was not one this library reads
+    Version { version: u64 },
+    /// A confession has a field which version 2 bundles cannot represent
+    NotMigratable { field: String },
+    /// The bundle has no proofs
+    NoProofs,
+    /// An entry has no message, and the bundle has no default
+    NoMessage,
+    /// An entry has no ring, and the bundle has no default
+    NoPublicKeys,
+    /// A key in the ring did not parse
+    BadKey { line: String, error: KeyError },
+    /// The proof or key image was not hex of the right length
+    BadHex,
+    /// An entry has only one of `scope` and `keyImage`
+    PartialLink,
+    /// The proof did not verify, or could not be made
+    Proof { message: String },
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BundleError::Json { ref message } => write!(f, "parsing JSON: {}", message),
+            BundleError::Version { version } => write!(f, "unsupported version {}", version),
+            BundleError::NotMigratable { ref field } => write!(f, "version 2 bundles cannot represent `{}`", field),
+            BundleError::NoProofs => f.write_str("bundle contains no proofs"),
+            BundleError::NoMessage => f.write_str("no message, and the bundle has no default"),
+            BundleError::NoPublicKeys => f.write_str("no publicKeys, and the bundle has no default"),
+            BundleError::BadKey { ref line, ref error } => write!(f, "parsing key {}: {}", line, error),
+            BundleError::BadHex => f.write_str("proof or key image is not valid hex"),
+            BundleError::PartialLink => f.write_str("has only one of scope and keyImage"),
+            BundleError::Proof { ref message } => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl From<bundle::Error> for BundleError {
+    fn from(e: bundle::Error) -> Self {
+        match e {
+            bundle::Error::Json(message) => BundleError::Json { message },
+            bundle::Error::Version(version) | bundle::Error::ConfessionVersion(version) => {
+                BundleError::Version { version: u64::try_from(version).unwrap_or(u64::MAX) }
+            }
+            bundle::Error::NotMigratable(field) => BundleError::NotMigratable { field: field.into() },
+            bundle::Error::NoProofs => BundleError::NoProofs,
+            bundle::Error::NoMessage => BundleError::NoMessage,
+            bundle::Error::NoPublicKeys => BundleError::NoPublicKeys,
+            bundle::Error::BadKey(line, e) => BundleError::BadKey { line, error: e.into() },
+            bundle::Error::BadHex => BundleError::BadHex,
+            bundle::Error::PartialLink => BundleError::PartialLink,
+            bundle::Error::Proof(e) => BundleError::Proof { message: e.to_string() },
+            bundle::Error::Prove(e) => BundleError::Proof { message: e.to_string() },
+        }
+    }
+}
+
+/// A public key
+#[derive(Debug, uniffi::Object)]
+pub struct PublicKey(keys::PublicKey);
+
+#[uniffi::export]
+impl PublicKey {
+    /// Parse a line of an `authorized_keys` file, e.g. `ssh-ed25519 AAAA... comment`
+    #[uniffi::constructor]
+    pub fn parse(line: String) -> Result<Arc<Self>, KeyError> {
+        Ok(Arc::new(PublicKey(keys::PublicKey::parse_pk_line(&line)?)))
+    }
+
+    /// Parse the 32 bytes of an ed25519 key
+    #[uniffi::constructor]
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Arc<Self>, KeyError> {
+        Ok(Arc::new(PublicKey(keys::PublicKey::parse(&bytes)?)))
+    }
+
+    /// The 32 bytes of the key
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0.serialize().to_vec()
+    }
+
+    /// The fingerprint OpenSSH shows for the key, e.g. `SHA256:...`
+    pub fn fingerprint(&self) -> String {
+        self.0.fingerprint()
+    }
+}
+
+/// A secret key; see the module documentation for its caveats
+#[derive(uniffi::Object)]
+pub struct SecretKey(keys::SecretKey);
+
+#[uniffi::export]
+impl SecretKey {
+    /// Parse an OpenSSH secret key file, decrypting it with `passphrase` if
+    /// it is encrypted
+    #[uniffi::constructor]
+    pub fn from_openssh(armor: String, passphrase: Vec<u8>) -> Result<Arc<Self>, KeyError> {
+        let bad_armor = |e: crate::armor::Error| KeyError::BadArmor { message: e.to_string() };
+        let sk = OpensshKey::from_armor(&armor).and_then(|key| key.decrypt(&passphrase)).map_err(bad_armor)?;
+        Ok(Arc::new(SecretKey(sk)))
+    }
+
+    /// The public key
+    pub fn public_key(&self) -> Arc<PublicKey> {
+        Arc::new(PublicKey(self.0.to_public()))
+    }
+}
+
+/// A ring of public keys, prepared for proving or verifying many times
+#[derive(uniffi::Object)]
+pub struct Ring(crate::Ring);
+
+#[uniffi::export]
+impl Ring {
+    #[uniffi::constructor]
+    pub fn new(keys: Vec<Arc<PublicKey>>) -> Result<Arc<Self>, ProofError> {
+        let keys: Vec<_> = keys.iter().map(|pk| pk.0).collect();
+        let ring = crate::Ring::new(&keys).map_err(|e| ProofError::Failed { message: e.to_string() })?;
+        Ok(Arc::new(Ring(ring)))
+    }
+
+    /// The keys, in the sorted order proofs use
+    pub fn keys(&self) -> Vec<Arc<PublicKey>> {
+        self.0.keys().iter().map(|pk| Arc::new(PublicKey(*pk))).collect()
+    }
+
+    /// The ring's identifier, in hex, which is the same for any ordering of
+    /// the same keys
+    pub fn id(&self) -> String {
+        crate::ring_id(self.0.keys()).to_hex()
+    }
+
+    pub fn verify(&self, proof: Vec<u8>, message: Vec<u8>) -> Result<(), ProofError> {
+        let rejected = |e: crate::VerifyError| ProofError::Rejected { message: e.to_string() };
+        self.0.verify(&crate::Proof::from_bytes(proof).map_err(|e| rejected(e.into()))?, &message).map_err(rejected)
+    }
+
+    /// Prove that the owner of one of the keys in the ring signed `message`;
+    /// see the module documentation for the caveats of secret keys
+    pub fn prove(&self, message: Vec<u8>, secret_key: Arc<SecretKey>) -> Result<Vec<u8>, ProofError> {
+        let proof = self.0.prove(&message, &secret_key.0).map_err(|e| ProofError::Failed { message: e.to_string() })?;
+        Ok(proof.into_bytes())
+    }
+}
+
+/// The outcome of verifying one entry of a bundle, as [`bundle::Verdict`]
+#[derive(Clone, PartialEq, Eq, Debug, uniffi::Record)]
+pub struct Verdict {
+    pub context: Option<String>,
+    /// The message the entry was checked against, if it had one
+    pub message: Option<String>,
+    /// The ring the entry was checked against, if it had one
+    pub public_keys: Option<Vec<String>>,
+    /// Why the entry did not verify, if it did not
+    pub error: Option<BundleError>,
+}
+
+/// A problem with one field of a confession or bundle, as
+/// [`bundle::FieldError`]
+#[derive(Clone, PartialEq, Eq, Debug, uniffi::Record)]
+pub struct FieldError {
+    /// Where the problem is, e.g. `publicKeys[2]` or `proofs[0].keyImage`
+    pub path: String,
+    pub message: String,
+}
+
+/// Verify a proof of `message` by one of `keys`
+#[uniffi::export]
+pub fn verify(proof: Vec<u8>, keys: Vec<Arc<PublicKey>>, message: Vec<u8>) -> Result<(), ProofError> {
+    let keys: Vec<_> = keys.iter().map(|pk| pk.0).collect();
+    let rejected = |e: crate::VerifyError| ProofError::Rejected { message: e.to_string() };
+    crate::verify(&crate::Proof::from_bytes(proof).map_err(|e| rejected(e.into()))?, &keys, &message).map_err(rejected)
+}
+
+/// Verify every entry of a version 2 bundle, or a version 1 confession as a
+/// bundle of one, failing only if the bundle itself is malformed
+#[uniffi::export]
+pub fn verify_bundle(json: String) -> Result<Vec<Verdict>, BundleError> {
+    let value: serde_json::Value =
+        serde_json::from_str(&json).map_err(|e| BundleError::Json { message: e.to_string() })?;
+    let bundle = if value.get("version") == Some(&serde_json::json!(bundle::MULTI_VERSION)) {
+        MultiBundle::from_json_value(value)?
+    } else {
+        Confession::from_json_value(value)?.into_bundle()?
+    };
+    let verdicts = bundle.verify().into_iter().map(|verdict| Verdict {
+        context: verdict.context,
+        message: verdict.message,
+        public_keys: verdict.public_keys,
+        error: verdict.result.err().map(BundleError::from),
+    });
+    Ok(verdicts.collect())
+}
+
+/// Check a confession (`version` 1) or bundle (`version` 2) against its
+/// schema and then its fields, as [`bundle::validate`]
+#[uniffi::export]
+pub fn validate_bundle(json: String, version: u64) -> Result<Vec<FieldError>, BundleError> {
+    let version = usize::try_from(version).map_err(|_| BundleError::Version { version })?;
+    let problems = bundle::validate(&json, version)?;
+    Ok(problems.into_iter().map(|problem| FieldError { path: problem.path, message: problem.message }).collect())
+}
+
+/// These call the generated `extern "C"` scaffolding, as the Kotlin and Swift
+/// bindings do, rather than the Rust functions behind it
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UniFfiTag;
+    use std::ffi::c_void;
+    use std::mem::ManuallyDrop;
+    use uniffi::{Lift, Lower, RustBuffer, RustCallStatus, RustCallStatusCode};
+
+    const TEST_PK: &str = include_str!("../testdata/test-key.pub");
+    const OTHER_PK: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIKHQ634LrVRQ0bLDLZ5kdjcpmihQBtcJbGoMqCJh6i10";
+
+    fn lower<T: Lower<UniFfiTag>>(value: T) -> T::FfiType {
+        T::lower(value)
+    }
+
+    fn lift<T: Lift<UniFfiTag>>(value: T::FfiType) -> T {
+        T::try_lift(value).unwrap()
+    }
+
+    /// Make a call, returning what it returned or the error it raised
+    fn call<R, E: Lift<UniFfiTag>>(f: impl FnOnce(&mut RustCallStatus) -> R) -> Result<R, E> {
+        let mut status = RustCallStatus::default();
+        let ret = f(&mut status);
+        match status.code {
+            RustCallStatusCode::Success => Ok(ret),
+            RustCallStatusCode::Error => {
+                Err(E::try_lift_from_rust_buffer(ManuallyDrop::into_inner(status.error_buf)).unwrap())
+            }
+            code => panic!("unexpected {:?}: {:?}", code, lift::<String>(ManuallyDrop::into_inner(status.error_buf))),
+        }
+    }
+
+    fn parse(line: &str) -> Result<Arc<PublicKey>, KeyError> {
+        call::<_, KeyError>(|status| uniffi_ringsig_fn_constructor_publickey_parse(lower(line.to_owned()), status))
+            .map(lift)
+    }
+
+    /// Lower a new reference to an object, as the bindings do for `self`
+    fn this<T: Send + Sync + 'static>(object: &Arc<T>) -> *const c_void
+    where
+        Arc<T>: Lower<UniFfiTag, FfiType = *const c_void>,
+    {
+        lower(Arc::clone(object))
+    }
+
+    #[test]
+    fn contract_version() {
+        // What bindings generated by uniffi-bindgen 0.28 check for
+        assert_eq!(crate::ffi_ringsig_uniffi_contract_version(), 26);
+    }
+
+    #[test]
+    fn keys() {
+        let pk = parse(TEST_PK).unwrap();
+        let fingerprint: RustBuffer =
+            call::<_, KeyError>(|status| uniffi_ringsig_fn_method_publickey_fingerprint(this(&pk), status)).unwrap();
+        assert_eq!(lift::<String>(fingerprint), "SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus");
+
+        let bytes: RustBuffer =
+            call::<_, KeyError>(|status| uniffi_ringsig_fn_method_publickey_to_bytes(this(&pk), status)).unwrap();
+        let ptr = call::<_, KeyError>(|status| uniffi_ringsig_fn_constructor_publickey_from_bytes(bytes, status));
+        assert_eq!(lift::<Arc<PublicKey>>(ptr.unwrap()).0, pk.0);
+
+        assert_eq!(parse("").unwrap_err(), KeyError::EmptyKey);
+        assert_eq!(
+            parse("ssh-rsa AAAAB3NzaC1yc2E= rsa").unwrap_err(),
+            KeyError::WrongKeyType { expected: "ssh-ed25519".into(), got: "ssh-rsa".into() },
+        );
+        let ptr = call::<_, KeyError>(|status| {
+            uniffi_ringsig_fn_constructor_publickey_from_bytes(lower(vec![0u8; 31]), status)
+        });
+        assert_eq!(ptr.unwrap_err(), KeyError::WrongKeyLength { expected: 32, got: 31 });
+
+        let armor = include_str!("../testdata/test-key.priv").to_owned();
+        let sk = call::<_, KeyError>(|status| {
+            uniffi_ringsig_fn_constructor_secretkey_from_openssh(lower(armor), lower(Vec::<u8>::new()), status)
+        });
+        let sk: Arc<SecretKey> = lift(sk.unwrap());
+        let ptr = call::<_, KeyError>(|status| uniffi_ringsig_fn_method_secretkey_public_key(this(&sk), status));
+        assert_eq!(lift::<Arc<PublicKey>>(ptr.unwrap()).0, pk.0);
+        let bad = call::<_, KeyError>(|status| {
+            uniffi_ringsig_fn_constructor_secretkey_from_openssh(lower("nope".to_owned()), lower(Vec::<u8>::new()), status)
+        });
+        assert!(matches!(bad, Err(KeyError::BadArmor { .. })));
+    }
+
+    #[test]
+    fn prove_and_verify() {
+        let armor = include_str!("../testdata/test-key.priv").to_owned();
+        let sk = call::<_, KeyError>(|status| {
+            uniffi_ringsig_fn_constructor_secretkey_from_openssh(lower(armor), lower(Vec::<u8>::new()), status)
+        });
+        let sk: Arc<SecretKey> = lift(sk.unwrap());
+        let keys = vec![parse(OTHER_PK).unwrap(), parse(TEST_PK).unwrap()];
+        let ring = call::<_, ProofError>(|status| uniffi_ringsig_fn_constructor_ring_new(lower(keys.clone()), status));
+        let ring: Arc<Ring> = lift(ring.unwrap());
+
+        let id: RustBuffer = call::<_, ProofError>(|status| uniffi_ringsig_fn_method_ring_id(this(&ring), status)).unwrap();
+        let reversed = vec![keys[1].clone(), keys[0].clone()];
+        assert_eq!(lift::<String>(id), crate::ring_id(&[reversed[0].0, reversed[1].0]).to_hex());
+        let sorted: RustBuffer =
+            call::<_, ProofError>(|status| uniffi_ringsig_fn_method_ring_keys(this(&ring), status)).unwrap();
+        assert_eq!(lift::<Vec<Arc<PublicKey>>>(sorted).len(), 2);
+
+        // Messages are arbitrary bytes, not only text
+        let message = vec![0xffu8, 0x00, 0xfe];
+        let proof: RustBuffer = call::<_, ProofError>(|status| {
+            uniffi_ringsig_fn_method_ring_prove(this(&ring), lower(message.clone()), this(&sk), status)
+        })
+        .unwrap();
+        let proof: Vec<u8> = lift(proof);
+
+        call::<_, ProofError>(|status| {
+            uniffi_ringsig_fn_method_ring_verify(this(&ring), lower(proof.clone()), lower(message.clone()), status)
+        })
+        .unwrap();
+        call::<_, ProofError>(|status| {
+            uniffi_ringsig_fn_func_verify(lower(proof.clone()), lower(reversed.clone()), lower(message.clone()), status)
+        })
+        .unwrap();
+        let bad = call::<_, ProofError>(|status| {
+            uniffi_ringsig_fn_func_verify(lower(proof.clone()), lower(reversed.clone()), lower(vec![0xffu8]), status)
+        });
+        assert_eq!(bad, Err(ProofError::Rejected { message: "bad proof".into() }));
+
+        let outsider = call::<_, ProofError>(|status| {
+            uniffi_ringsig_fn_constructor_ring_new(lower(vec![keys[0].clone()]), status)
+        });
+        let outsider: Arc<Ring> = lift(outsider.unwrap());
+        let bad = call::<_, ProofError>(|status| {
+            uniffi_ringsig_fn_method_ring_prove(this(&outsider), lower(message), this(&sk), status)
+        });
+        let message = format!("secret key {} did not match any public key", keys[1].0.fingerprint());
+        assert_eq!(bad.map(lift::<Vec<u8>>), Err(ProofError::Failed { message }));
+    }
+
+    #[test]
+    fn bundles() {
+        let json = include_str!("../testdata/timestamp-bundle.json").to_owned();
+        let verdicts = call::<_, BundleError>(|status| uniffi_ringsig_fn_func_verify_bundle(lower(json.clone()), status));
+        let verdicts: Vec<Verdict> = lift(verdicts.unwrap());
+        assert_eq!(verdicts.len(), 1);
+        assert_eq!(verdicts[0].context.as_deref(), Some("before the vote"));
+        assert_eq!(verdicts[0].error, None);
+
+        let json = include_str!("../testdata/test-verify.json").to_owned();
+        let verdicts = call::<_, BundleError>(|status| uniffi_ringsig_fn_func_verify_bundle(lower(json), status));
+        assert_eq!(lift::<Vec<Verdict>>(verdicts.unwrap())[0].error, None);
+
+        let bad = r#"{"version": 2, "message": "m", "proofs": [{"proof": "00", "publicKeys": [""]}]}"#.to_owned();
+        let verdicts = call::<_, BundleError>(|status| uniffi_ringsig_fn_func_verify_bundle(lower(bad.clone()), status));
+        assert_eq!(
+            lift::<Vec<Verdict>>(verdicts.unwrap())[0].error,
+            Some(BundleError::BadKey { line: "".into(), error: KeyError::EmptyKey }),
+        );
+        let problems = call::<_, BundleError>(|status| {
+            uniffi_ringsig_fn_func_validate_bundle(lower(bad), lower(2u64), status)
+        });
+        let problems: Vec<FieldError> = lift(problems.unwrap());
+        assert_eq!(problems[0], FieldError { path: "proofs[0].publicKeys[0]".into(), message: "empty key".into() });
+
+        let err = call::<_, BundleError>(|status| uniffi_ringsig_fn_func_verify_bundle(lower("{".to_owned()), status));
+        assert!(matches!(err, Err(BundleError::Json { .. })));
+        let err = call::<_, BundleError>(|status| {
+            uniffi_ringsig_fn_func_validate_bundle(lower("{}".to_owned()), lower(3u64), status)
+        });
+        assert_eq!(err.unwrap_err(), BundleError::Version { version: 3 });
+    }
+}
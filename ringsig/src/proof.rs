@@ -0,0 +1,333 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Proofs
+//!
+//! A proof over a ring of `n` keys is `32 * (n + 1)` bytes: the first
+//! challenge, then an `s` value for each key in sorted order. [`Proof`]
+//! keeps those bytes together with the knowledge that their length makes
+//! sense, so that the ring size can be read off before verifying.
+//!
+//! For copying by hand, a proof can also be written in ASCII armor between
+//! [`format::PROOF_BEGIN`] and [`format::PROOF_END`] lines, with a CRC24 checksum line.
+//!
+//! Those bytes carry no version, so a proof of some future layout would
+//! look to an older verifier like one of this layout which fails. A proof
+//! may instead be written with a leading version byte, which makes it one
+//! byte longer than a multiple of 32; parsing tells the two apart by that
+//! length, and refuses a version byte it does not know. A [`Proof`]
+//! remembers which way it was written, and is written back the same way.
+
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use curve25519_dalek::scalar::Scalar;
+
+use crate::armor::{self, FromArmor};
+use crate::format::{self, PROOF_BEGIN, PROOF_END};
+use crate::ProofError;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// The version byte of a proof of the layout above: a challenge and an `s`
+/// value for each key
+///
+/// Version 0 is never used, so that a version byte cannot be a zero byte
+/// left by mistake.
+pub const PROOF_V1: u8 = 1;
+
+/// How a proof is written out by [`Proof::encode`]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum ProofEncoding {
+    /// The bare layout, which every version of this library reads
+    #[default]
+    Legacy,
+    /// The layout after a version byte, [`PROOF_V1`]
+    Versioned,
+}
+
+/// A ring signature, as made by [`crate::prove`]
+///
+/// Two proofs are equal if their bytes are, however each is encoded.
+#[derive(Clone, Debug)]
+pub struct Proof(Vec<u8>, ProofEncoding);
+
+impl Proof {
+    /// Wrap the bytes of a proof, which must be `32 * (n + 1)` bytes long for
+    /// a ring of some positive size `n`, after a version byte if written
+    /// [`ProofEncoding::Versioned`]
+    pub fn from_bytes(mut bytes: Vec<u8>) -> Result<Self, ProofError> {
+        let got = bytes.len();
+        let mut encoding = ProofEncoding::Legacy;
+        if got % 32 == 1 {
+            match bytes[0] {
+                PROOF_V1 => {
+                    bytes.remove(0);
+                    encoding = ProofEncoding::Versioned;
+                }
+                version => return Err(ProofError::UnknownVersion(version)),
+            }
+        }
+        if bytes.len() < 64 || !bytes.len().is_multiple_of(32) {
+            return Err(ProofError::WrongLength { got });
+        }
+        Ok(Proof(bytes, encoding))
+    }
+
+    /// Parse a proof from hex, as made by [`Proof::to_hex`]
+    pub fn from_hex(s: &str) -> Result<Self, ProofError> {
+        let bytes = Vec::<u8>::from_hex(s).map_err(|e| ProofError::InvalidHex(e.to_string()))?;
+        Proof::from_bytes(bytes)
+    }
+
+    /// The number of keys in the ring the proof was made for
+    pub fn ring_size(&self) -> usize {
+        self.0.len() / 32 - 1
+    }
+
+    /// The bytes of the proof without any version byte, as verifying
+    /// reads them
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// The bytes of the proof, written as [`Proof::encoding`] says
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self.1 {
+            ProofEncoding::Legacy => self.0,
+            ProofEncoding::Versioned => self.encode(ProofEncoding::Versioned),
+        }
+    }
+
+    /// The proof in hex, written as [`Proof::encoding`] says
+    pub fn to_hex(&self) -> String {
+        self.encode(self.1).to_hex()
+    }
+
+    /// How the proof was written when parsed or proven, and so how it is
+    /// written out again
+    pub fn encoding(&self) -> ProofEncoding {
+        self.1
+    }
+
+    /// The same proof, to be written out as `encoding` says
+    pub fn with_encoding(self, encoding: ProofEncoding) -> Proof {
+        Proof(self.0, encoding)
+    }
+
+    /// The bytes of the proof, written as `encoding` says
+    ///
+    /// [`Proof::as_bytes`] is always [`ProofEncoding::Legacy`]; the other
+    /// ways of writing a proof use [`Proof::encoding`].
+    pub fn encode(&self, encoding: ProofEncoding) -> Vec<u8> {
+        match encoding {
+            ProofEncoding::Legacy => self.0.clone(),
+            ProofEncoding::Versioned => [&[PROOF_V1][..], &self.0].concat(),
+        }
+    }
+
+    /// Write the proof in ASCII armor, wrapped at 64 characters and ending
+    /// with a CRC24 checksum line
+    pub fn to_armor(&self) -> String {
+        format::armor(PROOF_BEGIN, PROOF_END, &self.encode(self.1))
+    }
+
+    /// The same proof with every `s` value fully reduced
+    ///
+    /// Older versions of this library left the `s` values other than the
+    /// signer's unreduced. [`crate::verify`] accepts such proofs, but each
+    /// then has many encodings, and [`crate::check_canonical`] rejects them.
+    /// Reducing them gives the one encoding of the same proof which passes
+    /// both.
+    pub fn canonicalize(&self) -> Proof {
+        let mut bytes = self.0.clone();
+        for s_i in bytes[32..].chunks_exact_mut(32) {
+            let mut raw = [0; 32];
+            raw.copy_from_slice(s_i);
+            s_i.copy_from_slice(Scalar::from_bits(raw).reduce().as_bytes());
+        }
+        Proof(bytes, self.1)
+    }
+
+    /// Wrap bytes made by proving, whose length is right by construction
+    pub(crate) fn from_vec_unchecked(bytes: Vec<u8>) -> Self {
+        debug_assert!(bytes.len() >= 64 && bytes.len().is_multiple_of(32));
+        Proof(bytes, ProofEncoding::Legacy)
+    }
+}
+
+impl PartialEq for Proof {
+    fn eq(&self, other: &Proof) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for Proof {}
+
+impl Hash for Proof {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl FromArmor for Proof {
+    const BEGIN_STR: &'static str = PROOF_BEGIN;
+    const END_STR: &'static str = PROOF_END;
+
+    fn from_armor(s: &str) -> Result<Self, armor::Error> {
+        let bytes = armor::parse_armor::<Self>(s)?;
+        Ok(Proof::from_bytes(bytes)?)
+    }
+}
+
+impl AsRef<[u8]> for Proof {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Displays the proof in hex, as [`Proof::to_hex`] does
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+/// Parses the proof from hex, as [`Proof::from_hex`] does
+impl FromStr for Proof {
+    type Err = ProofError;
+
+    fn from_str(s: &str) -> Result<Self, ProofError> {
+        Proof::from_hex(s)
+    }
+}
+
+/// Serializes the proof as a hex string, as confessions have it
+#[cfg(feature = "serde")]
+impl serde::Serialize for Proof {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Proof {
+    fn deserialize<D: serde::Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let hex = <std::borrow::Cow<str>>::deserialize(d)?;
+        hex.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths() {
+        assert_eq!(Proof::from_bytes(vec![0; 64]).unwrap().ring_size(), 1);
+        assert_eq!(Proof::from_bytes(vec![0; 32 * 11]).unwrap().ring_size(), 10);
+        assert_eq!(Proof::from_bytes(vec![0; 32]), Err(ProofError::WrongLength { got: 32 }));
+        assert_eq!(Proof::from_bytes(vec![0; 66]), Err(ProofError::WrongLength { got: 66 }));
+        assert_eq!(Proof::from_bytes(vec![]), Err(ProofError::WrongLength { got: 0 }));
+
+        let proof = Proof::from_hex(&"ab".repeat(96)).unwrap();
+        assert_eq!(proof.ring_size(), 2);
+        assert_eq!(proof.as_bytes(), [0xab; 96]);
+        assert_eq!(Proof::from_hex(&proof.to_hex()), Ok(proof));
+        assert!(matches!(Proof::from_hex("zz"), Err(ProofError::InvalidHex(_))));
+        assert!(matches!(Proof::from_hex("abc"), Err(ProofError::InvalidHex(_))));
+        assert_eq!(Proof::from_hex("abcd"), Err(ProofError::WrongLength { got: 2 }));
+    }
+
+    #[test]
+    fn strings() {
+        let proof = Proof::from_hex(&"ab".repeat(96)).unwrap();
+        assert_eq!(proof.to_string(), proof.to_hex());
+        assert_eq!(proof.to_string().parse(), Ok(proof));
+        assert_eq!("abcd".parse::<Proof>(), Err(ProofError::WrongLength { got: 2 }));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let proof = Proof::from_hex(&"ab".repeat(96)).unwrap();
+        let json = serde_json::to_string(&proof).unwrap();
+        assert_eq!(json, format!("\"{}\"", "ab".repeat(96)));
+        assert_eq!(serde_json::from_str::<Proof>(&json).unwrap(), proof);
+        let proofs: Vec<Proof> = serde_json::from_value(serde_json::json!([proof.to_hex(), proof.to_hex()])).unwrap();
+        assert_eq!(proofs, [proof.clone(), proof]);
+        let e = serde_json::from_str::<Proof>("\"abcd\"").unwrap_err();
+        assert_eq!(e.to_string(), ProofError::WrongLength { got: 2 }.to_string());
+        assert!(serde_json::from_str::<Proof>("64").is_err());
+    }
+
+    #[test]
+    fn versions() {
+        for n in [1, 2, 10] {
+            let proof = Proof::from_bytes((0..32 * (n + 1)).map(|i| i as u8).collect()).unwrap();
+            let legacy = proof.encode(ProofEncoding::Legacy);
+            assert_eq!(legacy, proof.as_bytes());
+            assert_eq!(Proof::from_bytes(legacy), Ok(proof.clone()));
+            let versioned = proof.encode(ProofEncoding::Versioned);
+            assert_eq!(versioned.len(), 32 * (n + 1) + 1);
+            assert_eq!((versioned[0], &versioned[1..]), (PROOF_V1, proof.as_bytes()));
+            assert_eq!(Proof::from_hex(&versioned.to_hex()), Ok(proof.clone()));
+            let armored = format::armor(PROOF_BEGIN, PROOF_END, &versioned);
+            assert_eq!(Proof::from_armor(&armored), Ok(proof.clone()));
+            assert_eq!(Proof::from_bytes(versioned.clone()).unwrap().ring_size(), n);
+
+            // Parsing remembers the encoding, and writing keeps it
+            let parsed = Proof::from_bytes(versioned.clone()).unwrap();
+            assert_eq!(parsed.encoding(), ProofEncoding::Versioned);
+            assert_eq!(parsed.to_hex(), versioned.to_hex());
+            assert_eq!(Proof::from_armor(&parsed.to_armor()).unwrap().encoding(), ProofEncoding::Versioned);
+            assert_eq!(parsed.clone().into_bytes(), versioned);
+            assert_eq!(proof.encoding(), ProofEncoding::Legacy);
+            assert_eq!(proof.clone().with_encoding(ProofEncoding::Versioned).to_hex(), versioned.to_hex());
+            assert_eq!(parsed.with_encoding(ProofEncoding::Legacy).into_bytes(), proof.as_bytes());
+        }
+
+        for version in [0, 2, 0xff] {
+            let mut bytes = vec![version];
+            bytes.extend_from_slice(&[7; 64]);
+            assert_eq!(Proof::from_bytes(bytes), Err(ProofError::UnknownVersion(version)));
+        }
+        assert_eq!(ProofError::UnknownVersion(2).code(), "proof_version");
+        assert_eq!(ProofError::UnknownVersion(2).to_string(), "unknown proof version 2");
+        assert_eq!(Proof::from_bytes(vec![PROOF_V1; 33]), Err(ProofError::WrongLength { got: 33 }));
+        assert_eq!(Proof::from_bytes(vec![PROOF_V1]), Err(ProofError::WrongLength { got: 1 }));
+    }
+
+    #[test]
+    fn armor() {
+        for n in [1, 2, 10] {
+            let proof = Proof::from_bytes((0..32 * (n + 1)).map(|i| i as u8).collect()).unwrap();
+            let armored = proof.to_armor();
+            assert!(armored.starts_with(PROOF_BEGIN));
+            assert!(armored.lines().all(|line| line.len() <= 64));
+            assert_eq!(format::classify(&armored), Some(format::Kind::Armor));
+            assert_eq!(format::dearmor(&armored, PROOF_BEGIN, PROOF_END), Ok(proof.as_bytes().to_vec()));
+            assert_eq!(Proof::from_armor(&armored), Ok(proof));
+        }
+
+        let short = format::armor(PROOF_BEGIN, PROOF_END, &[0; 48]);
+        assert_eq!(Proof::from_armor(&short), Err(armor::Error::Proof(ProofError::WrongLength { got: 48 })));
+        let bundle = format::armor(format::BUNDLE_BEGIN, format::BUNDLE_END, &[0; 64]);
+        assert_eq!(Proof::from_armor(&bundle), Err(armor::Error::NoBeginStr));
+        let armored = Proof::from_bytes(vec![7; 64]).unwrap().to_armor();
+        let corrupted = armored.replacen("BwcH", "BwcI", 1);
+        assert!(matches!(Proof::from_armor(&corrupted), Err(armor::Error::Radix64(_))));
+    }
+}
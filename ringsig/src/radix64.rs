@@ -17,6 +17,9 @@
 //! Computes the CRC as specified in RFC 4880 Section 6. Basically a
 //! transliteration of the C code in 6.1 to Rust
 
+use bitcoin_hashes::hex::ToHex;
+use std::fmt;
+
 /// Radix-64 parsing error
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Error {
@@ -26,15 +29,41 @@ pub enum Error {
     NonAsciiString(String),
     /// Some character was not in the radix64 alphabet.
     NonRadix64Character(u8),
+    /// Some character was not in the base64url alphabet
+    NonBase64UrlCharacter(u8),
     /// A character occurred after an = sign
     ExtraData(u8),
+    /// The data was not followed by a CRC
+    MissingCrc,
+    /// The CRC following the data, `expected`, is not that of the data
+    CrcMismatch { expected: [u8; 3], got: [u8; 3] },
 }
 
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::EarlyEof => f.write_str("radix-64 data ended early"),
+            Error::NonAsciiString(ref s) => write!(f, "{:?} is not ASCII", s),
+            Error::NonRadix64Character(b) => write!(f, "character 0x{:02x} is not valid radix-64", b),
+            Error::NonBase64UrlCharacter(b) => write!(f, "character 0x{:02x} is not valid base64url", b),
+            Error::ExtraData(b) => write!(f, "character 0x{:02x} follows the radix-64 padding", b),
+            Error::MissingCrc => f.write_str("radix-64 data has no CRC"),
+            Error::CrcMismatch { expected, got } => {
+                write!(f, "radix-64 CRC mismatch: expected {}, got {}", expected.to_hex(), got.to_hex())
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 const BASE64_CH: [u8; 64] = *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64URL_CH: [u8; 64] = *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
 
 /// Base-64 encodes data
 pub fn base64_encode(mut data: &[u8]) -> String {
-    let mut ret = Vec::with_capacity((data.len() * 4 + 2) / 3);
+    // Only a hint, so saturate rather than overflow on 32-bit systems
+    let mut ret = Vec::with_capacity((data.len() / 3).saturating_add(1).saturating_mul(4));
 
     loop {
         enum Npad {
@@ -56,22 +85,22 @@ pub fn base64_encode(mut data: &[u8]) -> String {
             three[2] & 0x3f,
         ];
 
-        ret.push(BASE64_CH[sext[0] as usize]);
+        ret.push(BASE64_CH[usize::from(sext[0])]);
         match npad {
             Npad::Zero => {
-                ret.push(BASE64_CH[sext[1] as usize]);
-                ret.push(BASE64_CH[sext[2] as usize]);
-                ret.push(BASE64_CH[sext[3] as usize]);
+                ret.push(BASE64_CH[usize::from(sext[1])]);
+                ret.push(BASE64_CH[usize::from(sext[2])]);
+                ret.push(BASE64_CH[usize::from(sext[3])]);
                 data = &data[3..];
             }
             Npad::One => {
-                ret.push(BASE64_CH[sext[1] as usize]);
-                ret.push(BASE64_CH[sext[2] as usize]);
+                ret.push(BASE64_CH[usize::from(sext[1])]);
+                ret.push(BASE64_CH[usize::from(sext[2])]);
                 ret.push(b'=');
                 data = &data[2..];
             }
             Npad::Two => {
-                ret.push(BASE64_CH[sext[1] as usize]);
+                ret.push(BASE64_CH[usize::from(sext[1])]);
                 ret.push(b'=');
                 ret.push(b'=');
                 data = &data[1..];
@@ -83,6 +112,27 @@ pub fn base64_encode(mut data: &[u8]) -> String {
     }
 }
 
+/// Base-64 encodes data with the URL- and filename-safe alphabet of RFC 4648
+/// section 5, without padding or line breaks
+pub fn base64url_encode(data: &[u8]) -> String {
+    // Only a hint, so saturate rather than overflow on 32-bit systems
+    let mut ret = String::with_capacity((data.len() / 3).saturating_add(1).saturating_mul(4));
+    for chunk in data.chunks(3) {
+        let three = [chunk[0], chunk.get(1).copied().unwrap_or(0), chunk.get(2).copied().unwrap_or(0)];
+        let sext = [
+            three[0] >> 2,
+            ((three[0] & 0x03) << 4) + (three[1] >> 4),
+            ((three[1] & 0x0f) << 2) + (three[2] >> 6),
+            three[2] & 0x3f,
+        ];
+        // n bytes need n + 1 characters
+        for &sext in &sext[..chunk.len() + 1] {
+            ret.push(char::from(BASE64URL_CH[usize::from(sext)]));
+        }
+    }
+    ret
+}
+
 /// Computes the CRC and outputs it as a base64 string
 pub fn crc24_bytes(data: &[u8]) -> [u8; 3] {
     const CRC24_INIT: u32 = 0x00B7_04CE;
@@ -90,7 +140,7 @@ pub fn crc24_bytes(data: &[u8]) -> [u8; 3] {
 
     let mut crc = CRC24_INIT;
     for byte in data {
-        crc ^= (*byte as u32) << 16;
+        crc ^= u32::from(*byte) << 16;
         for _ in 0..8 {
             crc <<= 1;
             if crc & 0x0100_0000 != 0 {
@@ -98,11 +148,8 @@ pub fn crc24_bytes(data: &[u8]) -> [u8; 3] {
             }
         }
     }
-    [
-        ((crc >> 16) & 0xff) as u8,
-        ((crc >> 8) & 0xff) as u8,
-        ((crc >> 0) & 0xff) as u8,
-    ]
+    let [_, hi, mid, lo] = crc.to_be_bytes();
+    [hi, mid, lo]
 }
 
 pub fn crc24_string(data: &[u8]) -> String {
@@ -131,10 +178,98 @@ fn base64_decode_ch(ch: u8) -> Result<u8, Error> {
         0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e, 0x2f, 0x30, 0x31, 0x32, 0x33, 0xff, 0xff, 0xff, 0xff,
         0xff, // 112-127
     ];
-    if ch > 128 || TABLE[ch as usize] == 0xff {
-        return Err(Error::NonRadix64Character(ch));
+    match TABLE.get(usize::from(ch)) {
+        Some(&sext) if sext != 0xff => Ok(sext),
+        _ => Err(Error::NonRadix64Character(ch)),
+    }
+}
+
+/// Where the CRC of radix64-encoded data begins, if it ends with one: `=`
+/// followed by four characters, with only whitespace after
+///
+/// Valid data without a CRC never ends this way, as nothing but padding may
+/// follow an `=`.
+pub(crate) fn crc_index(s: &str) -> Option<usize> {
+    let s = s.trim_end();
+    let idx = s.len().checked_sub(5)?;
+    let crc = s.as_bytes().get(idx..)?;
+    if crc[0] == b'=' && crc[1..].iter().all(|&ch| ch != b'=' && ch.is_ascii_graphic()) {
+        Some(idx)
+    } else {
+        None
+    }
+}
+
+/// Decode radix64-encoded data followed by its CRC, as RFC 4880 section 6
+/// has it: `=` and the base64-encoded CRC, normally on its own line
+pub fn radix64_decode_with_crc(s: &str) -> Result<Vec<u8>, Error> {
+    let crc_idx = crc_index(s).ok_or(Error::MissingCrc)?;
+    let data = radix64_decode(&s[..crc_idx])?;
+    let crc = radix64_decode(&s[crc_idx + 1..])?;
+    let expected: [u8; 3] = crc.try_into().map_err(|_| Error::MissingCrc)?;
+    let got = crc24_bytes(&data);
+    if expected != got {
+        return Err(Error::CrcMismatch { expected, got });
+    }
+    Ok(data)
+}
+
+/// Decodes a single base64url character as a 6-bit number
+///
+/// Unlike [`base64_decode_ch`] this rejects '=', which may only appear as
+/// padding at the end
+fn base64url_decode_ch(ch: u8) -> Result<u8, Error> {
+    const TABLE: [u8; 128] = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, // 0-15
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, // 16-31
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x3e, 0xff,
+        0xff, // 32-47
+        0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0xff, 0xff, 0xff, 0xff, 0xff,
+        0xff, // 48-63
+        0xff, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d,
+        0x0e, // 64-79
+        0x0f, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0xff, 0xff, 0xff, 0xff,
+        0x3f, // 80-95
+        0xff, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27,
+        0x28, // 96-111
+        0x29, 0x2a, 0x2b, 0x2c, 0x2d, 0x2e, 0x2f, 0x30, 0x31, 0x32, 0x33, 0xff, 0xff, 0xff, 0xff,
+        0xff, // 112-127
+    ];
+    match TABLE.get(usize::from(ch)) {
+        Some(&sext) if sext != 0xff => Ok(sext),
+        _ => Err(Error::NonBase64UrlCharacter(ch)),
+    }
+}
+
+/// Decode base64url-encoded data, with or without padding
+pub fn base64url_decode(s: &str) -> Result<Vec<u8>, Error> {
+    if !s.is_ascii() {
+        return Err(Error::NonAsciiString(s.to_owned()));
     }
-    Ok(TABLE[ch as usize])
+    let unpadded = s.trim_end_matches('=');
+    match s.len() - unpadded.len() {
+        0 => {}
+        1 | 2 if s.len().is_multiple_of(4) => {}
+        1 | 2 => return Err(Error::EarlyEof),
+        _ => return Err(Error::ExtraData(b'=')),
+    }
+
+    let mut ret = Vec::with_capacity(unpadded.len() / 4 * 3 + 2);
+    for chunk in unpadded.as_bytes().chunks(4) {
+        // A single character holds only 6 bits, not enough for a byte
+        if chunk.len() == 1 {
+            return Err(Error::EarlyEof);
+        }
+        let mut quad = [0; 4];
+        for (sext, &ch) in quad.iter_mut().zip(chunk) {
+            *sext = base64url_decode_ch(ch)?;
+        }
+        let three = [(quad[0] << 2) + (quad[1] >> 4), (quad[1] << 4) + (quad[2] >> 2), (quad[2] << 6) + quad[3]];
+        ret.extend_from_slice(&three[..chunk.len() - 1]);
+    }
+    Ok(ret)
 }
 
 /// Decode radix64-encoded  data
@@ -142,7 +277,7 @@ pub fn radix64_decode(s: &str) -> Result<Vec<u8>, Error> {
     if !s.is_ascii() {
         return Err(Error::NonAsciiString(s.to_owned()));
     }
-    let mut ret = Vec::with_capacity((s.len() * 3 + 3) / 4);
+    let mut ret = Vec::with_capacity(s.len() / 4 * 3 + 3);
 
     let mut iter = s.bytes().filter(|b| !b.is_ascii_whitespace());
     loop {
@@ -155,14 +290,13 @@ pub fn radix64_decode(s: &str) -> Result<Vec<u8>, Error> {
             base64_decode_ch(iter.next().ok_or(Error::EarlyEof)?)?,
             base64_decode_ch(iter.next().ok_or(Error::EarlyEof)?)?,
         ];
-        let skip;
-        match (quad[2] == 0x80, quad[3] == 0x80) {
+        let skip = match (quad[2] == 0x80, quad[3] == 0x80) {
             // 0x80 means "=", see base64_decode_ch
-            (false, false) => skip = 0,
-            (false, true) => skip = 1,
-            (true, true) => skip = 2,
+            (false, false) => 0,
+            (false, true) => 1,
+            (true, true) => 2,
             (true, false) => return Err(Error::ExtraData(quad[3])),
-        }
+        };
         ret.push((quad[0] << 2) + (quad[1] >> 4));
         if skip < 2 {
             ret.push((quad[1] << 4) + (quad[2] >> 2));
@@ -219,4 +353,70 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn base64url() {
+        // From RFC 4648 10, without padding
+        let vectors = [("", ""), ("f", "Zg"), ("fo", "Zm8"), ("foo", "Zm9v"), ("foob", "Zm9vYg"), ("fooba", "Zm9vYmE"), ("foobar", "Zm9vYmFy")];
+        for (data, encoded) in vectors {
+            assert_eq!(base64url_encode(data.as_bytes()), encoded);
+            assert_eq!(base64url_decode(encoded), Ok(data.as_bytes().to_vec()));
+            let padded = format!("{}{}", encoded, "=".repeat((4 - encoded.len() % 4) % 4));
+            assert_eq!(base64url_decode(&padded), Ok(data.as_bytes().to_vec()));
+            assert_eq!(radix64_decode(&padded), Ok(data.as_bytes().to_vec()));
+        }
+
+        // The characters which differ from standard base64
+        let data = [0xfb, 0xff, 0xbf];
+        assert_eq!(base64_encode(&data), "+/+/");
+        assert_eq!(base64url_encode(&data), "-_-_");
+        assert_eq!(base64url_decode("-_-_"), Ok(data.to_vec()));
+        assert_eq!(base64url_decode("-_8"), Ok(vec![0xfb, 0xff]));
+        assert_eq!(base64url_decode("+/+/"), Err(Error::NonBase64UrlCharacter(b'+')));
+
+        // No line breaks, however long
+        let long = base64url_encode(&[0xab; 100]);
+        assert_eq!(long.len(), 134);
+        assert_eq!(base64url_decode(&long), Ok(vec![0xab; 100]));
+
+        assert_eq!(base64url_decode("Z"), Err(Error::EarlyEof));
+        assert_eq!(base64url_decode("Zg="), Err(Error::EarlyEof));
+        assert_eq!(base64url_decode("Zg==="), Err(Error::ExtraData(b'=')));
+        assert_eq!(base64url_decode("Zg==Zg"), Err(Error::NonBase64UrlCharacter(b'=')));
+        assert_eq!(base64url_decode("Zm 9v"), Err(Error::NonBase64UrlCharacter(b' ')));
+        assert_eq!(base64url_decode("Zm9v\u{e9}"), Err(Error::NonAsciiString("Zm9v\u{e9}".to_owned())));
+        assert_eq!(Error::NonBase64UrlCharacter(b'+').to_string(), "character 0x2b is not valid base64url");
+    }
+
+    #[test]
+    fn crc() {
+        let data = b"this is a test sentence";
+        let armored = format!("{}\n={}\n", base64_encode(data), crc24_string(data));
+        assert_eq!(radix64_decode_with_crc(&armored), Ok(data.to_vec()));
+        // The CRC need not have a line of its own
+        assert_eq!(radix64_decode_with_crc(&armored.replace('\n', "")), Ok(data.to_vec()));
+        assert_eq!(radix64_decode_with_crc("=twTO"), Ok(vec![]));
+        assert_eq!(crc24_string(b""), "twTO");
+
+        let corrupted = armored.replacen('d', "e", 1);
+        let err = radix64_decode_with_crc(&corrupted).unwrap_err();
+        let got = crc24_bytes(&radix64_decode(&corrupted[..crc_index(&corrupted).unwrap()]).unwrap());
+        assert_eq!(err, Error::CrcMismatch { expected: crc24_bytes(data), got });
+        assert!(err.to_string().starts_with("radix-64 CRC mismatch: expected "));
+
+        assert_eq!(radix64_decode_with_crc(&base64_encode(data)), Err(Error::MissingCrc));
+        assert_eq!(radix64_decode_with_crc("eA=="), Err(Error::MissingCrc));
+        assert_eq!(radix64_decode_with_crc(""), Err(Error::MissingCrc));
+        assert_eq!(radix64_decode_with_crc("eA==\n=tw!O"), Err(Error::NonRadix64Character(b'!')));
+    }
+
+    #[test]
+    fn decode_characters() {
+        assert_eq!(base64_decode_ch(b'/'), Ok(0x3f));
+        assert_eq!(base64_decode_ch(b'='), Ok(0x80));
+        // One past the end of the table
+        assert_eq!(base64_decode_ch(128), Err(Error::NonRadix64Character(128)));
+        assert_eq!(base64_decode_ch(0xff), Err(Error::NonRadix64Character(0xff)));
+        assert_eq!(Error::NonRadix64Character(0xff).to_string(), "character 0xff is not valid radix-64");
+    }
 }
@@ -0,0 +1,393 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Revoking and Superseding Confessions
+//!
+//! A confession cannot be unsaid, but it can be walked back. A revocation
+//! is an ordinary version 1 confession whose message is a [`Statement`]
+//! about the original, identified by its [`Confession::bundle_hash`]:
+//!
+//! ```text
+//! CREDIBLE CONFESSION REVOCATION v1
+//! revokes <hash>
+//! ```
+//!
+//! or, to replace it with a corrected confession,
+//!
+//! ```text
+//! CREDIBLE CONFESSION REVOCATION v1
+//! supersedes <hash>
+//! with <hash of the replacement>
+//! ```
+//!
+//! The lines are separated by a single `\n`, with none at the end, and the
+//! hashes are in lower-case hex. This encoding is frozen: revocations are
+//! only recognized if their message is exactly the encoding.
+//!
+//! Every key of a revocation's ring must be in the original's ring, so that
+//! it was made by one of the people who could have made the original. The
+//! same ring is the usual choice. Unless the original is linkable, any of
+//! them may revoke it, not only whoever made it; readers must judge that. A
+//! revocation of a linkable confession must be linkable in the same scope
+//! with the same key image, which shows that it was made by the original
+//! signer.
+
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use std::fmt;
+
+use crate::badge;
+use crate::bundle::Confession;
+use crate::hashes::BundleHash;
+use crate::keys::{self, PublicKey, SecretKey};
+
+/// The first line of every statement
+pub const HEADER: &str = "CREDIBLE CONFESSION REVOCATION v1";
+
+/// Revocation-related error
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The message is not a revocation statement
+    NotRevocation,
+    /// The revocation is about a different confession
+    Unrelated,
+    /// A key did not parse
+    BadKey(String, keys::Error),
+    /// A key of the revocation's ring is not in the original's ring
+    NotInRing(String),
+    /// The original is linkable and the revocation is not linked to it
+    NotLinked,
+    /// The revocation's proof did not verify, or could not be made
+    Proof(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NotRevocation => f.write_str("the message is not a revocation statement"),
+            Error::Unrelated => f.write_str("the revocation is about a different confession"),
+            Error::BadKey(ref line, ref e) => write!(f, "parsing key {}: {}", line, e),
+            Error::NotInRing(ref fingerprint) => write!(f, "key {} is not in the original's ring", fingerprint),
+            Error::NotLinked => {
+                f.write_str("the original is linkable, and the revocation is not linked to it in the same scope")
+            }
+            Error::Proof(ref e) => f.write_str(e),
+        }
+    }
+}
+
+impl Error {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Error::NotRevocation => "not_revocation",
+            Error::Unrelated => "unrelated",
+            Error::BadKey(_, ref e) => e.code(),
+            Error::NotInRing(_) => "not_in_ring",
+            Error::NotLinked => "not_linked",
+            Error::Proof(_) => "bad_proof",
+        }
+    }
+}
+
+/// What a revocation does to the original
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Withdraw it
+    Revoke,
+    /// Replace it with the confession with this hash
+    Supersede(BundleHash),
+}
+
+/// The message of a revocation
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Statement {
+    /// The hash of the confession being revoked
+    pub original: BundleHash,
+    pub action: Action,
+}
+
+impl Statement {
+    /// The canonical encoding, used as the revocation's message
+    pub fn encode(&self) -> String {
+        match self.action {
+            Action::Revoke => format!("{}\nrevokes {}", HEADER, self.original.to_hex()),
+            Action::Supersede(replacement) => {
+                format!("{}\nsupersedes {}\nwith {}", HEADER, self.original.to_hex(), replacement.to_hex())
+            }
+        }
+    }
+
+    /// Parse a message, if it is exactly the encoding of a statement
+    pub fn parse(message: &str) -> Option<Statement> {
+        let mut lines = message.split('\n');
+        if lines.next() != Some(HEADER) {
+            return None;
+        }
+        let ret = match (lines.next()?.split_once(' ')?, lines.next()) {
+            (("revokes", original), None) => Statement { original: hash(original)?, action: Action::Revoke },
+            (("supersedes", original), Some(with)) => Statement {
+                original: hash(original)?,
+                action: Action::Supersede(hash(with.strip_prefix("with ")?)?),
+            },
+            _ => return None,
+        };
+        // Rejects upper-case hex, and anything after the last line
+        Some(ret).filter(|ret| ret.encode() == message)
+    }
+}
+
+fn hash(s: &str) -> Option<BundleHash> {
+    BundleHash::from_hex(s).ok()
+}
+
+/// Whether a confession still stands, given the revocations known for it
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RevocationStatus {
+    /// No valid revocation applies to it
+    Standing,
+    /// It was revoked, by the revocation with this index
+    Revoked { by: usize },
+    /// It was superseded, by the revocation with this index
+    Superseded { by: usize, replacement: BundleHash },
+}
+
+fn parse_keys(lines: &[String]) -> Result<Vec<PublicKey>, Error> {
+    lines.iter().map(|line| PublicKey::parse_pk_line(line).map_err(|e| Error::BadKey(line.clone(), e))).collect()
+}
+
+/// Check that every key of `ring` is in the original's ring
+fn check_ring(original: &Confession, ring: &[PublicKey]) -> Result<(), Error> {
+    let original_keys = parse_keys(&original.public_keys)?;
+    match ring.iter().find(|key| !original_keys.contains(key)) {
+        Some(key) => Err(Error::NotInRing(key.fingerprint())),
+        None => Ok(()),
+    }
+}
+
+/// Make a revocation of `original` over `ring`, which must be part of its
+/// ring and contain the key `sk`
+///
+/// If the original is linkable, so is the revocation, and `sk` must be the
+/// key which made the original.
+pub fn revoke(original: &Confession, ring: &[String], sk: &SecretKey) -> Result<Confession, Error> {
+    sign(original, Action::Revoke, ring, sk)
+}
+
+/// Make a revocation of `original` which points readers at `replacement`,
+/// as [`revoke`] does
+pub fn supersede(
+    original: &Confession,
+    replacement: &Confession,
+    ring: &[String],
+    sk: &SecretKey,
+) -> Result<Confession, Error> {
+    sign(original, Action::Supersede(replacement.bundle_hash()), ring, sk)
+}
+
+fn sign(original: &Confession, action: Action, ring: &[String], sk: &SecretKey) -> Result<Confession, Error> {
+    let keys = parse_keys(ring)?;
+    check_ring(original, &keys)?;
+    let statement = Statement { original: original.bundle_hash(), action };
+    let message = statement.encode();
+    let mut ret = Confession::new(ring.to_vec(), message.clone());
+    let message = message.as_bytes();
+    match (&original.scope, &original.key_image) {
+        (Some(scope), Some(key_image)) => {
+            let (proof, image) = crate::prove_linkable(&keys, message, scope.as_bytes(), sk)
+                .map_err(|e| Error::Proof(e.to_string()))?;
+            if image.to_hex() != *key_image {
+                return Err(Error::NotLinked);
+            }
+            ret.proof = Some(proof.to_hex());
+            ret.scope = Some(scope.clone());
+            ret.key_image = Some(image.to_hex());
+        }
+        _ => ret.proof = Some(crate::prove(&keys, message, sk).map_err(|e| Error::Proof(e.to_string()))?.to_hex()),
+    }
+    Ok(ret)
+}
+
+/// Check that `revocation` is a valid revocation of `original`, returning
+/// what it does
+///
+/// The revocation's signed attributes are checked as of its own signing
+/// time, so an expiry does not undo it.
+pub fn check(original: &Confession, revocation: &Confession) -> Result<Action, Error> {
+    let statement = Statement::parse(&revocation.message).ok_or(Error::NotRevocation)?;
+    if statement.original != original.bundle_hash() {
+        return Err(Error::Unrelated);
+    }
+    check_ring(original, &parse_keys(&revocation.public_keys)?)?;
+    if original.scope.is_some() && (revocation.scope != original.scope || revocation.key_image != original.key_image) {
+        return Err(Error::NotLinked);
+    }
+    let at = revocation.attributes.and_then(|attributes| attributes.timestamp).unwrap_or(0);
+    badge::verify_confession(revocation, at).map_err(Error::Proof)?;
+    Ok(statement.action)
+}
+
+/// Find out whether `confession` has been revoked or superseded by any of
+/// `revocations`
+///
+/// Revocations which are about other confessions, or which are not valid,
+/// are ignored. A revocation takes precedence over a supersession, and
+/// otherwise the first valid one counts.
+pub fn check_revocations(confession: &Confession, revocations: &[Confession]) -> RevocationStatus {
+    let actions: Vec<(usize, Action)> = revocations
+        .iter()
+        .enumerate()
+        .filter_map(|(n, revocation)| check(confession, revocation).ok().map(|action| (n, action)))
+        .collect();
+    if let Some(&(by, _)) = actions.iter().find(|(_, action)| *action == Action::Revoke) {
+        return RevocationStatus::Revoked { by };
+    }
+    match actions.first() {
+        Some(&(by, Action::Supersede(replacement))) => RevocationStatus::Superseded { by, replacement },
+        _ => RevocationStatus::Standing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+    use std::slice;
+
+    fn secret(n: usize) -> SecretKey {
+        testkit::deterministic_keypairs(b"revocation tests", 5)[n].secret.secret_key()
+    }
+
+    fn line(n: usize) -> String {
+        testkit::key_line(&secret(n).to_public(), &format!("key-{}", n))
+    }
+
+    fn confession(ring: &[String], message: &str, signer: usize) -> Confession {
+        let keys = parse_keys(ring).unwrap();
+        let mut ret = Confession::new(ring.to_vec(), message.to_owned());
+        ret.proof = Some(crate::prove(&keys, message.as_bytes(), &secret(signer)).unwrap().to_hex());
+        ret
+    }
+
+    #[test]
+    fn encoding() {
+        // Frozen; these strings must never change
+        let original =
+            BundleHash::from_hex("9b0b0a2c6f1e4f1dd3b7e0d4a7b2f1e5c3a9d8e7f6a5b4c3d2e1f0a9b8c7d6e5").unwrap();
+        let replacement =
+            BundleHash::from_hex("00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff").unwrap();
+        let revoke = "CREDIBLE CONFESSION REVOCATION v1\n\
+                      revokes 9b0b0a2c6f1e4f1dd3b7e0d4a7b2f1e5c3a9d8e7f6a5b4c3d2e1f0a9b8c7d6e5";
+        let supersede = "CREDIBLE CONFESSION REVOCATION v1\n\
+                         supersedes 9b0b0a2c6f1e4f1dd3b7e0d4a7b2f1e5c3a9d8e7f6a5b4c3d2e1f0a9b8c7d6e5\n\
+                         with 00112233445566778899aabbccddeeff00112233445566778899aabbccddeeff";
+        assert_eq!(Statement { original, action: Action::Revoke }.encode(), revoke);
+        assert_eq!(Statement { original, action: Action::Supersede(replacement) }.encode(), supersede);
+        assert_eq!(Statement::parse(revoke), Some(Statement { original, action: Action::Revoke }));
+        assert_eq!(Statement::parse(supersede).unwrap().action, Action::Supersede(replacement));
+
+        assert_eq!(Statement::parse(&format!("{}\n", revoke)), None);
+        assert_eq!(Statement::parse(&revoke.replace('\n', "\r\n")), None);
+        assert_eq!(Statement::parse(&revoke.to_uppercase().replace("REVOKES", "revokes")), None);
+        assert_eq!(Statement::parse(&revoke.replace(" v1", " v2")), None);
+        assert_eq!(Statement::parse(&revoke[..revoke.len() - 2]), None);
+        assert_eq!(Statement::parse("an ordinary confession"), None);
+    }
+
+    #[test]
+    fn revoked_and_superseded() {
+        let ring: Vec<String> = (1..=3).map(line).collect();
+        let original = confession(&ring, "I did it", 1);
+        let replacement = confession(&ring, "I did some of it", 1);
+
+        // Any member of the ring may revoke, over the same ring or part of it
+        let revocation = revoke(&original, &ring, &secret(2)).unwrap();
+        assert_eq!(Statement::parse(&revocation.message).unwrap().original, original.bundle_hash());
+        assert_eq!(check(&original, &revocation), Ok(Action::Revoke));
+        let status = check_revocations(&original, slice::from_ref(&revocation));
+        assert_eq!(status, RevocationStatus::Revoked { by: 0 });
+        let partial = revoke(&original, &ring[..2], &secret(1)).unwrap();
+        assert_eq!(check(&original, &partial), Ok(Action::Revoke));
+
+        let supersession = supersede(&original, &replacement, &ring, &secret(1)).unwrap();
+        assert_eq!(
+            check_revocations(&original, slice::from_ref(&supersession)),
+            RevocationStatus::Superseded { by: 0, replacement: replacement.bundle_hash() },
+        );
+        // Revoking wins over superseding
+        assert_eq!(
+            check_revocations(&original, &[supersession, revocation.clone()]),
+            RevocationStatus::Revoked { by: 1 },
+        );
+
+        // Whitespace in the original's JSON does not matter, but its contents do
+        let reparsed = Confession::from_json_str(&format!(" {} \n", original.to_json_string())).unwrap();
+        assert_eq!(check(&reparsed, &revocation), Ok(Action::Revoke));
+        let mut edited = original.clone();
+        edited.message += ".";
+        assert_eq!(check(&edited, &revocation), Err(Error::Unrelated));
+    }
+
+    #[test]
+    fn unrelated_and_invalid() {
+        let ring: Vec<String> = (1..=3).map(line).collect();
+        let original = confession(&ring, "I did it", 1);
+        let other = confession(&ring, "somebody else did it", 3);
+        let revocation = revoke(&other, &ring, &secret(3)).unwrap();
+        assert_eq!(check(&original, &revocation), Err(Error::Unrelated));
+        assert_eq!(check(&original, &other), Err(Error::NotRevocation));
+        assert_eq!(check_revocations(&original, &[revocation, other]), RevocationStatus::Standing);
+        assert_eq!(check_revocations(&original, &[]), RevocationStatus::Standing);
+
+        // An outsider can neither make nor pass off a revocation
+        let outsider_ring = vec![line(1), line(4)];
+        assert_eq!(revoke(&original, &outsider_ring, &secret(4)).unwrap_err().code(), "not_in_ring");
+        let mut forged = Confession::new(outsider_ring.clone(), revoke(&original, &ring, &secret(1)).unwrap().message);
+        let keys = parse_keys(&outsider_ring).unwrap();
+        forged.proof = Some(crate::prove(&keys, forged.message.as_bytes(), &secret(4)).unwrap().to_hex());
+        assert_eq!(check(&original, &forged).unwrap_err().code(), "not_in_ring");
+        assert_eq!(revoke(&original, &ring, &secret(4)).unwrap_err().code(), "bad_proof");
+
+        // A tampered proof
+        let mut tampered = revoke(&original, &ring, &secret(1)).unwrap();
+        tampered.proof = Some(confession(&ring, "another message", 1).proof.unwrap());
+        assert_eq!(check(&original, &tampered).unwrap_err().code(), "bad_proof");
+        assert_eq!(check_revocations(&original, &[tampered]), RevocationStatus::Standing);
+    }
+
+    #[test]
+    fn linkable() {
+        let ring: Vec<String> = (1..=3).map(line).collect();
+        let keys = parse_keys(&ring).unwrap();
+        let mut original = Confession::new(ring.clone(), "I did it".into());
+        let (proof, image) = crate::prove_linkable(&keys, b"I did it", b"scope", &secret(2)).unwrap();
+        original.proof = Some(proof.to_hex());
+        original.scope = Some("scope".into());
+        original.key_image = Some(image.to_hex());
+
+        // Only the original signer can revoke
+        let revocation = revoke(&original, &ring, &secret(2)).unwrap();
+        assert_eq!(revocation.key_image, original.key_image);
+        assert_eq!(check(&original, &revocation), Ok(Action::Revoke));
+        assert_eq!(revoke(&original, &ring, &secret(1)), Err(Error::NotLinked));
+
+        let mut unlinked = revocation.clone();
+        unlinked.scope = None;
+        unlinked.key_image = None;
+        unlinked.proof = Some(crate::prove(&keys, unlinked.message.as_bytes(), &secret(1)).unwrap().to_hex());
+        assert_eq!(check(&original, &unlinked), Err(Error::NotLinked));
+    }
+}
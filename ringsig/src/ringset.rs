@@ -0,0 +1,260 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Rings as Sets of Keys
+//!
+//! Proving fails on a ring which repeats a key, and a key outside the
+//! prime-order subgroup would make a ring whose proofs mean less than they
+//! seem to. A [`RingSet`] is built one key at a time, and refuses either as
+//! it is added, so that a mistake in assembling a ring shows up where the
+//! offending key came from rather than later, at proving time. The finished
+//! set is sorted, and can be passed wherever a slice of keys is taken.
+
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::path::Path;
+use std::{fmt, io};
+
+use bitcoin_hashes::hex::ToHex;
+
+use crate::keys::{self, PublicKey};
+
+/// Error adding a key to a [`RingSetBuilder`]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The key is already in the set
+    Duplicate(Box<PublicKey>),
+    /// The key, whose encoding is given, is not in the prime-order subgroup
+    TorsionKey(Vec<u8>),
+    /// A line did not parse as a key
+    Key(keys::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Duplicate(ref pk) => write!(f, "key {} is already in the ring", pk.fingerprint()),
+            Error::TorsionKey(ref key) => write!(f, "key {} is not in the prime-order group", key.to_hex()),
+            Error::Key(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            Error::Key(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// A short, stable identifier for the kind of error, for machine-readable
+    /// output
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Error::Duplicate(_) => "duplicate_key",
+            Error::TorsionKey(_) => "torsion_key",
+            Error::Key(ref e) => e.code(),
+        }
+    }
+}
+
+impl From<keys::Error> for Error {
+    fn from(e: keys::Error) -> Self {
+        Error::Key(e)
+    }
+}
+
+/// A set of distinct, torsion-free keys, sorted as they are in a proof
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RingSet(Vec<PublicKey>);
+
+impl RingSet {
+    /// Start building a set
+    pub fn builder() -> RingSetBuilder {
+        RingSetBuilder::new()
+    }
+
+    /// Whether `pk` is in the set, found by binary search since the keys
+    /// are sorted
+    pub fn contains(&self, pk: &PublicKey) -> bool {
+        self.0.binary_search(pk).is_ok()
+    }
+}
+
+impl Deref for RingSet {
+    type Target = [PublicKey];
+
+    fn deref(&self) -> &[PublicKey] {
+        &self.0
+    }
+}
+
+impl<'a> From<&'a RingSet> for &'a [PublicKey] {
+    fn from(set: &'a RingSet) -> Self {
+        &set.0
+    }
+}
+
+impl IntoIterator for RingSet {
+    type Item = PublicKey;
+    type IntoIter = std::vec::IntoIter<PublicKey>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a RingSet {
+    type Item = &'a PublicKey;
+    type IntoIter = std::slice::Iter<'a, PublicKey>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// A [`RingSet`] under construction
+#[derive(Clone, Default, Debug)]
+pub struct RingSetBuilder {
+    /// The keys so far, by their serialization
+    keys: BTreeMap<[u8; 32], PublicKey>,
+}
+
+impl RingSetBuilder {
+    pub fn new() -> Self {
+        RingSetBuilder::default()
+    }
+
+    /// Add a key, unless it is already in the set or has a torsion component
+    pub fn add(&mut self, pk: PublicKey) -> Result<(), Error> {
+        let key = pk.serialize();
+        if !pk.0.is_torsion_free() {
+            return Err(Error::TorsionKey(key.to_vec()));
+        }
+        if self.keys.contains_key(&key) {
+            return Err(Error::Duplicate(Box::new(pk)));
+        }
+        self.keys.insert(key, pk);
+        Ok(())
+    }
+
+    /// Add the key of an "id_ed25519.pub" line, parsed by
+    /// [`PublicKey::parse_pk_line`]
+    pub fn add_from_ssh_line(&mut self, line: &str) -> Result<(), Error> {
+        self.add(PublicKey::parse_pk_line(line)?)
+    }
+
+    /// Add every key of an authorized_keys file, read by
+    /// [`keys::read_authorized_keys_file`]
+    ///
+    /// A line which does not parse, or repeats an earlier key, is left out
+    /// rather than failing the whole file. This returns the number of keys
+    /// added and, for each line left out, its line number and why, so that
+    /// the caller can decide whether the ring is still the one intended.
+    /// Only reading the file can fail.
+    pub fn add_from_authorized_keys_file(&mut self, path: &Path) -> Result<(usize, Vec<(usize, Error)>), io::Error> {
+        let mut added = 0;
+        let mut skipped = vec![];
+        for (n, key) in keys::read_authorized_keys_file(path)? {
+            match key.map_err(Error::from).and_then(|pk| self.add(pk)) {
+                Ok(()) => added += 1,
+                Err(e) => skipped.push((n, e)),
+            }
+        }
+        Ok((added, skipped))
+    }
+
+    /// The number of keys added so far
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn build(self) -> RingSet {
+        RingSet(self.keys.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::SecretKey;
+    use std::fs;
+    use curve25519_dalek::constants;
+
+    #[test]
+    fn building() {
+        let sks: Vec<_> = (1..=4u8).map(|i| SecretKey::from_bytes([i; 32])).collect();
+        let keys: Vec<_> = sks.iter().map(SecretKey::to_public).collect();
+        let mut builder = RingSet::builder();
+        for pk in keys.iter().rev() {
+            builder.add(*pk).unwrap();
+        }
+        assert_eq!(builder.add(keys[2]), Err(Error::Duplicate(Box::new(keys[2]))));
+        let torsion = PublicKey(keys[0].0 + constants::EIGHT_TORSION[1]);
+        let err = builder.add(torsion).unwrap_err();
+        assert_eq!(err, Error::TorsionKey(torsion.serialize().to_vec()));
+        assert_eq!(err.code(), "torsion_key");
+        assert_eq!(builder.len(), 4);
+
+        let set = builder.build();
+        assert_eq!(set.len(), 4);
+        assert!(set.windows(2).all(|w| w[0].serialize() < w[1].serialize()));
+        assert!(keys.iter().all(|pk| set.contains(pk)));
+        assert!(!set.contains(&SecretKey::from_bytes([5; 32]).to_public()));
+        assert!(!RingSetBuilder::new().build().contains(&keys[0]));
+        assert_eq!((&set).into_iter().count(), 4);
+
+        let proof = crate::prove(&set, b"msg", &sks[1]).unwrap();
+        crate::verify(&proof, (&set).into(), b"msg").unwrap();
+        crate::verify(&proof, &keys, b"msg").unwrap();
+        assert_eq!(set.into_iter().collect::<Vec<_>>().len(), 4);
+        assert!(RingSetBuilder::new().build().is_empty());
+    }
+
+    #[test]
+    fn from_files() {
+        let keys: Vec<_> = (1..=3u8).map(|i| SecretKey::from_bytes([i; 32]).to_public()).collect();
+        let mut builder = RingSet::builder();
+        builder.add_from_ssh_line(&keys[0].to_ssh_line(Some("first"))).unwrap();
+        let e = builder.add_from_ssh_line("ssh-rsa AAAAB3NzaC1yc2E=").unwrap_err();
+        assert!(matches!(e, Error::Key(keys::Error::WrongKeyType { .. })));
+        assert_eq!(e.code(), "wrong_key_type");
+
+        let path = std::env::temp_dir().join(format!("ringsig-ringset-test-{}", std::process::id()));
+        let file = format!(
+            "# the team\n{}\n\n{} second\nssh-ed25519\n  {}\n",
+            keys[1].to_ssh_line(None),
+            keys[0].to_ssh_line(None),
+            keys[2].to_ssh_line(Some("third")),
+        );
+        fs::write(&path, file).unwrap();
+        let (added, skipped) = builder.add_from_authorized_keys_file(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(added, 2);
+        assert_eq!(skipped, [(4, Error::Duplicate(Box::new(keys[0]))), (5, Error::Key(keys::Error::NoKey))]);
+        assert_eq!(builder.build().len(), 3);
+
+        let e = RingSet::builder().add_from_authorized_keys_file(&path).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+}
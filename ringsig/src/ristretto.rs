@@ -0,0 +1,312 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Ring signatures over Ristretto255
+//!
+//! Ed25519 points have a cofactor of 8, so [`PublicKey::parse`] must
+//! refuse keys with a torsion component, and key images must be checked
+//! the same way. Ristretto255 is a prime-order group built from the same
+//! curve, so every point which decodes is a valid key, and each has only
+//! one encoding.
+//!
+//! [`prove_ristretto`] and [`verify_ristretto`] are [`crate::prove`] and
+//! [`crate::verify`] with Ristretto255 arithmetic, and [`Proof`]s of the
+//! same layout. Their params, nonce and challenge hashes have tags of their
+//! own, so no proof passes as both. A secret key's Ristretto255 public key
+//! is not its ed25519 one, so a ring of SSH keys cannot be used as is: each
+//! member must publish a [`RistrettoPublicKey`].
+//!
+//! [`PublicKey::parse`]: crate::keys::PublicKey::parse
+
+use std::fmt;
+
+use bitcoin_hashes::hex::ToHex;
+use bitcoin_hashes::{Hash, HashEngine};
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_TABLE;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::IsIdentity;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use subtle::{Choice, ConstantTimeEq, CtOption};
+
+use crate::hashes::{RistrettoChallengeHash, RistrettoNonceHash, RistrettoParamsHash};
+use crate::keys::{self, SecretKey};
+use crate::{
+    check_canonical, check_proof_len, hash_to_scalar, length_le, ring_size_le, walk_ring, IdentityInRing, Proof,
+    ProveError, TooLarge, VerifyError,
+};
+
+/// A Ristretto255 public key
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct RistrettoPublicKey(RistrettoPoint);
+
+impl RistrettoPublicKey {
+    /// Parse a public key from its 32-byte encoding
+    ///
+    /// Every point which decodes is in the prime-order group, so the only
+    /// key refused besides those which do not decode is the identity, for
+    /// which anyone can sign.
+    pub fn from_bytes(bytes: [u8; 32]) -> Result<Self, keys::Error> {
+        match CompressedRistretto(bytes).decompress() {
+            Some(pt) if pt.is_identity() => Err(keys::Error::WeakKey(bytes.to_vec())),
+            Some(pt) => Ok(RistrettoPublicKey(pt)),
+            None => Err(keys::Error::InvalidKey(bytes.to_vec())),
+        }
+    }
+
+    /// The 32-byte encoding of the key
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.compress().to_bytes()
+    }
+
+    /// The Ristretto255 public key of `sk`
+    pub fn from_secret_key(sk: &SecretKey) -> Self {
+        RistrettoPublicKey(&sk.0 * &RISTRETTO_BASEPOINT_TABLE)
+    }
+}
+
+impl fmt::Display for RistrettoPublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_bytes().to_hex())
+    }
+}
+
+/// A Ristretto255 ring lists this key more than once
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+struct DuplicateInRing(RistrettoPublicKey);
+
+impl From<DuplicateInRing> for ProveError {
+    fn from(e: DuplicateInRing) -> Self {
+        ProveError::DuplicateRistrettoKey(Box::new(e.0))
+    }
+}
+
+impl From<DuplicateInRing> for VerifyError {
+    fn from(e: DuplicateInRing) -> Self {
+        VerifyError::DuplicateRistrettoKey(Box::new(e.0))
+    }
+}
+
+/// The keys of a ring, sorted, and its params hash with `message`
+///
+/// This refuses the rings [`crate::Ring::new`] does, with the same errors
+/// but for the type of a repeated key.
+fn prepare<E: From<TooLarge> + From<IdentityInRing> + From<DuplicateInRing>>(
+    pks: &[RistrettoPublicKey],
+    message: &[u8],
+) -> Result<(Vec<RistrettoPublicKey>, [u8; 32]), E> {
+    let mut sorted: Vec<([u8; 32], RistrettoPublicKey)> = pks.iter().map(|pk| (pk.to_bytes(), *pk)).collect();
+    sorted.sort_unstable_by_key(|(key, _)| *key);
+    if let Some(pair) = sorted.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+        return Err(DuplicateInRing(pair[0].1).into());
+    }
+    // Only the key of a zero secret key can be the identity
+    if sorted.iter().any(|(_, pk)| pk.0.is_identity()) {
+        return Err(IdentityInRing.into());
+    }
+    let mut eng = RistrettoParamsHash::engine();
+    eng.input(&ring_size_le(sorted.len())?);
+    for (key, _) in &sorted {
+        eng.input(key);
+    }
+    eng.input(&length_le(message.len())?);
+    eng.input(message);
+    let params = RistrettoParamsHash::from_engine(eng).into_inner();
+    Ok((sorted.into_iter().map(|(_, pk)| pk).collect(), params))
+}
+
+fn challenge(pubnonce: &RistrettoPoint, params: &[u8; 32]) -> RistrettoChallengeHash {
+    let mut eng = RistrettoChallengeHash::engine();
+    eng.input(&pubnonce.compress().to_bytes());
+    eng.input(&params[..]);
+    RistrettoChallengeHash::from_engine(eng)
+}
+
+/// Produce a proof of `message` by one of the Ristretto255 keys `pks`, in
+/// any order, with the secret key of one of them, as [`crate::prove`] does
+/// for ed25519 keys
+pub fn prove_ristretto(pks: &[RistrettoPublicKey], message: &[u8], sk: &SecretKey) -> Result<Proof, ProveError> {
+    prove_with_randomness(pks, message, sk, |bytes| OsRng.try_fill_bytes(bytes).map_err(|_| ProveError::Rng))
+}
+
+/// Verify a proof made by [`prove_ristretto`]
+pub fn verify_ristretto(proof: &Proof, pks: &[RistrettoPublicKey], message: &[u8]) -> Result<(), VerifyError> {
+    let (sorted, params) = prepare::<VerifyError>(pks, message)?;
+    let proof = proof.as_bytes();
+    check_proof_len(proof, sorted.len())?;
+    check_canonical(proof)?;
+
+    let mut e_i = RistrettoChallengeHash::from_slice(&proof[..32]).unwrap();
+    for (s_i, pk) in proof[32..].chunks_exact(32).zip(&sorted) {
+        let (e, s) = (hash_to_scalar(e_i.as_inner()), hash_to_scalar(s_i.try_into().unwrap()));
+        let pubnonce = RistrettoPoint::vartime_double_scalar_mul_basepoint(&e, &-pk.0, &s);
+        e_i = challenge(&pubnonce, &params);
+    }
+    if e_i[..] != proof[..32] {
+        return Err(VerifyError::BadProof);
+    }
+    Ok(())
+}
+
+/// Produce a proof as [`prove_ristretto`] does, taking the randomness mixed
+/// into the nonce and every `s` value from `randomness`
+///
+/// This walks the ring as the prover of [`crate::prove`] does, in constant
+/// time.
+fn prove_with_randomness<R: FnMut(&mut [u8; 32]) -> Result<(), ProveError>>(
+    pks: &[RistrettoPublicKey],
+    message: &[u8],
+    sk: &SecretKey,
+    mut randomness: R,
+) -> Result<Proof, ProveError> {
+    let (sorted, params) = prepare::<ProveError>(pks, message)?;
+    let my_idx = find_signer(&sorted, sk)?;
+    let n = sorted.len();
+
+    let mut rng = [0; 32];
+    let mut nonce_eng = RistrettoNonceHash::engine();
+    nonce_eng.input(&params[..]);
+    nonce_eng.input(sk.as_bytes());
+    randomness(&mut rng)?;
+    nonce_eng.input(&rng);
+    let nonce = RistrettoNonceHash::from_engine(nonce_eng);
+
+    // A random `s` value for every index, our own included, hashed from the
+    // nonce rather than the secret key
+    let mut s = Vec::with_capacity(n);
+    for idx in 0..n {
+        let mut s_eng = RistrettoNonceHash::engine();
+        s_eng.input(&u64::try_from(idx).map_err(|_| ProveError::TooManyKeys)?.to_be_bytes());
+        s_eng.input(&params[..]);
+        s_eng.input(&nonce[..]);
+        randomness(&mut rng)?;
+        s_eng.input(&rng);
+        // Reduced, since our own will be and the others must look the same
+        s.push(Scalar::from_bytes_mod_order(RistrettoNonceHash::from_engine(s_eng).into_inner()));
+    }
+
+    let negated: Vec<RistrettoPoint> = sorted.iter().map(|pk| -pk.0).collect();
+    let nonce = hash_to_scalar(nonce.as_inner());
+    let start = &nonce * &RISTRETTO_BASEPOINT_TABLE;
+    let hash = |pubnonce: &RistrettoPoint| challenge(pubnonce, &params).into_inner();
+    let next = |s_i: &Scalar, e_i: &Scalar, key: &RistrettoPoint| s_i * &RISTRETTO_BASEPOINT_TABLE + e_i * key;
+    Ok(walk_ring(&negated, my_idx, s, None, nonce, sk, start, hash, next, |_, _| {}))
+}
+
+/// The index of the signer in a sorted ring, found in constant time as
+/// [`crate::find_signer`] does
+fn find_signer(sorted: &[RistrettoPublicKey], sk: &SecretKey) -> Result<usize, ProveError> {
+    if sorted.is_empty() {
+        return Err(ProveError::EmptyKeySet);
+    }
+    let my_pk = RistrettoPublicKey::from_secret_key(sk);
+    let mut my_idx_opt = CtOption::new(0u64, Choice::from(0));
+    for (i, pk) in sorted.iter().enumerate() {
+        let i = u64::try_from(i).map_err(|_| ProveError::TooManyKeys)?;
+        my_idx_opt = my_idx_opt.or_else(|| CtOption::new(i, pk.0.ct_eq(&my_pk.0)));
+    }
+    match Option::<u64>::from(my_idx_opt) {
+        Some(idx) => usize::try_from(idx).map_err(|_| ProveError::TooManyKeys),
+        None => Err(ProveError::RistrettoKeyNotInRing { sk_public: Box::new(my_pk) }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+    use bitcoin_hashes::sha256;
+
+    #[test]
+    fn round_trip() {
+        let (sks, _) = testkit::deterministic_keys(b"ristretto tests", 5);
+        let pks: Vec<_> = sks.iter().map(RistrettoPublicKey::from_secret_key).collect();
+        for sk in &sks {
+            let proof = prove_ristretto(&pks, b"msg", sk).unwrap();
+            assert_eq!(proof.ring_size(), 5);
+            verify_ristretto(&proof, &pks, b"msg").unwrap();
+            let reversed: Vec<_> = pks.iter().rev().copied().collect();
+            verify_ristretto(&proof, &reversed, b"msg").unwrap();
+            assert_eq!(verify_ristretto(&proof, &pks, b"other"), Err(VerifyError::BadProof));
+            assert_eq!(verify_ristretto(&proof, &pks[1..], b"msg").unwrap_err().code(), "proof_length");
+        }
+        let proof = prove_ristretto(&pks[..1], b"", &sks[0]).unwrap();
+        verify_ristretto(&proof, &pks[..1], b"").unwrap();
+
+        // Nor does a proof pass for an ed25519 ring of the same secret keys
+        let ed25519: Vec<_> = sks.iter().map(SecretKey::to_public).collect();
+        let proof = prove_ristretto(&pks, b"msg", &sks[2]).unwrap();
+        assert_eq!(crate::verify(&proof, &ed25519, b"msg"), Err(VerifyError::BadProof));
+        let proof = crate::prove(&ed25519, b"msg", &sks[2]).unwrap();
+        assert_eq!(verify_ristretto(&proof, &pks, b"msg"), Err(VerifyError::BadProof));
+    }
+
+    #[test]
+    fn keys() {
+        let (sks, _) = testkit::deterministic_keys(b"ristretto tests", 3);
+        let pks: Vec<_> = sks.iter().map(RistrettoPublicKey::from_secret_key).collect();
+        assert_eq!(RistrettoPublicKey::from_bytes(pks[1].to_bytes()), Ok(pks[1]));
+        assert_eq!(pks[1].to_string(), pks[1].to_bytes().to_hex());
+        assert_eq!(RistrettoPublicKey::from_bytes([0; 32]), Err(keys::Error::WeakKey(vec![0; 32])));
+        // Not a canonical field element, so not an encoding of any point
+        assert_eq!(RistrettoPublicKey::from_bytes([0xff; 32]), Err(keys::Error::InvalidKey(vec![0xff; 32])));
+        // Ristretto255 encodings have no sign bit to flip
+        let mut flipped = pks[1].to_bytes();
+        flipped[31] ^= 0x80;
+        assert!(RistrettoPublicKey::from_bytes(flipped).is_err());
+
+        let repeated = [&pks[..], &pks[..1]].concat();
+        let err = prove_ristretto(&repeated, b"msg", &sks[0]).unwrap_err();
+        assert_eq!(err, ProveError::DuplicateRistrettoKey(Box::new(pks[0])));
+        assert_eq!(err.code(), "duplicate_key");
+        let proof = prove_ristretto(&pks, b"msg", &sks[0]).unwrap();
+        let err = verify_ristretto(&proof, &repeated, b"msg").unwrap_err();
+        assert_eq!(err.to_string(), format!("key {} is in the ring more than once", pks[0]));
+
+        assert_eq!(prove_ristretto(&[], b"msg", &sks[0]), Err(ProveError::EmptyKeySet));
+        assert_eq!(verify_ristretto(&proof, &[], b"msg"), Err(VerifyError::EmptyKeySet));
+        let outsider = testkit::deterministic_keys(b"ristretto outsider", 1).0.remove(0);
+        let err = prove_ristretto(&pks, b"msg", &outsider).unwrap_err();
+        assert_eq!(err.code(), "signer_not_in_ring");
+        let zero = SecretKey::from_bytes([0; 32]);
+        let weak = [&pks[..], &[RistrettoPublicKey::from_secret_key(&zero)]].concat();
+        assert_eq!(prove_ristretto(&weak, b"msg", &zero), Err(ProveError::WeakKey));
+        assert_eq!(verify_ristretto(&proof, &weak, b"msg"), Err(VerifyError::WeakKey));
+    }
+
+    #[test]
+    fn prover_known_answers() {
+        // Proofs by every signer of rings of each size, with fixed randomness,
+        // so that any change to the prover's output is a deliberate one
+        let mut eng = sha256::Hash::engine();
+        for n in 1..=5 {
+            let (sks, _) = testkit::deterministic_keys(b"ristretto tests", n);
+            let pks: Vec<_> = sks.iter().map(RistrettoPublicKey::from_secret_key).collect();
+            for sk in &sks {
+                let randomness = |rng: &mut [u8; 32]| {
+                    *rng = [0x42; 32];
+                    Ok(())
+                };
+                let proof = prove_with_randomness(&pks, b"msg", sk, randomness).unwrap();
+                verify_ristretto(&proof, &pks, b"msg").unwrap();
+                eng.input(proof.as_bytes());
+            }
+        }
+        let expected = "9c00c24c697a737649757acd6cbfce03d68f1edabdfe5823cc67ae948ee83dec";
+        assert_eq!(sha256::Hash::from_engine(eng).to_hex(), expected);
+    }
+}
@@ -0,0 +1,211 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Checking JSON against the schemas in `schema/`
+//!
+//! This understands only the parts of JSON Schema (draft 7) which the
+//! generated schemas use: `$ref` into `definitions`, `allOf`, `anyOf`,
+//! `type`, `properties`, `required`, `additionalProperties`, `items`,
+//! `minimum` and `maximum`. Other keywords, such as `format` and
+//! `description`, are ignored.
+
+use serde_json::{Map, Value};
+
+use crate::bundle::FieldError;
+
+/// Check `value` against `root`, returning every place it does not conform
+pub fn check(root: &Value, value: &Value) -> Vec<FieldError> {
+    let mut problems = vec![];
+    check_at(root, root, value, "", &mut problems);
+    problems
+}
+
+fn check_at(root: &Value, schema: &Value, value: &Value, path: &str, problems: &mut Vec<FieldError>) {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        // `true` and `false` schemas; `false` is never generated
+        _ => return,
+    };
+    let mut push = |message: String| problems.push(FieldError { path: path.to_owned(), message });
+
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        match reference.strip_prefix("#/definitions/").and_then(|name| root["definitions"].get(name)) {
+            Some(definition) => check_at(root, definition, value, path, problems),
+            None => problems.push(FieldError { path: path.into(), message: format!("unknown schema {}", reference) }),
+        }
+        return;
+    }
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(ty) => vec![ty],
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.iter().any(|ty| has_type(value, ty)) {
+            let expected: Vec<_> = types.iter().map(|ty| describe(ty)).collect();
+            push(format!("expected {}, not {}", expected.join(" or "), describe(type_of(value))));
+            return;
+        }
+    }
+
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+        if value.as_f64().is_some_and(|n| n < minimum) {
+            push(format!("must be at least {}", minimum));
+        }
+    }
+    if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+        if value.as_f64().is_some_and(|n| n > maximum) {
+            push(format!("must be at most {}", maximum));
+        }
+    }
+
+    if let Value::Object(ref object) = *value {
+        check_object(root, schema, object, path, problems);
+    }
+    if let (Value::Array(ref items), Some(item_schema)) = (value, schema.get("items")) {
+        for (n, item) in items.iter().enumerate() {
+            check_at(root, item_schema, item, &format!("{}[{}]", path, n), problems);
+        }
+    }
+
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for sub in all {
+            check_at(root, sub, value, path, problems);
+        }
+    }
+    if let Some(Value::Array(any)) = schema.get("anyOf") {
+        // Report the problems with the closest alternative, which for an
+        // optional field is the one which is not `null`
+        let mut closest: Option<Vec<FieldError>> = None;
+        for sub in any {
+            let mut sub_problems = vec![];
+            check_at(root, sub, value, path, &mut sub_problems);
+            if closest.as_ref().is_none_or(|closest| sub_problems.len() < closest.len()) {
+                closest = Some(sub_problems);
+            }
+        }
+        problems.extend(closest.unwrap_or_default());
+    }
+}
+
+fn check_object(
+    root: &Value,
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    problems: &mut Vec<FieldError>,
+) {
+    let properties = schema.get("properties").and_then(Value::as_object);
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !object.contains_key(name) {
+                problems.push(FieldError { path: field(path, name), message: "missing".into() });
+            }
+        }
+    }
+    for (name, value) in object {
+        match properties.and_then(|properties| properties.get(name)) {
+            Some(property) => check_at(root, property, value, &field(path, name), problems),
+            None => match schema.get("additionalProperties") {
+                Some(Value::Bool(false)) => {
+                    problems.push(FieldError { path: field(path, name), message: "unknown field".into() })
+                }
+                Some(additional) => check_at(root, additional, value, &field(path, name), problems),
+                None => {}
+            },
+        }
+    }
+}
+
+/// The path of the field `name` of the object at `path`
+fn field(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn has_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        ty => type_of(value) == ty,
+    }
+}
+
+fn type_of(value: &Value) -> &'static str {
+    match *value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(ref n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn describe(ty: &str) -> &str {
+    match ty {
+        "null" => "null",
+        "boolean" => "a boolean",
+        "number" => "a number",
+        "integer" => "an integer",
+        "string" => "a string",
+        "array" => "an array",
+        "object" => "an object",
+        ty => ty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn paths(schema: &Value, value: Value) -> Vec<String> {
+        check(schema, &value).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn keywords() {
+        let schema = json!({
+            "type": "object",
+            "required": ["n"],
+            "properties": {
+                "n": { "type": "integer", "minimum": 1.0, "maximum": 2.0 },
+                "list": { "type": ["array", "null"], "items": { "$ref": "#/definitions/Item" } },
+                "item": { "anyOf": [{ "$ref": "#/definitions/Item" }, { "type": "null" }] },
+            },
+            "additionalProperties": false,
+            "definitions": {
+                "Item": { "type": "object", "properties": { "s": { "type": "string" } } },
+            },
+        });
+        assert!(paths(&schema, json!({ "n": 1, "list": null, "item": { "s": "", "other": 0 } })).is_empty());
+        assert_eq!(paths(&schema, json!([])), ["expected an object, not an array"]);
+        assert_eq!(paths(&schema, json!({ "x": 0 })), ["n: missing", "x: unknown field"]);
+        assert_eq!(paths(&schema, json!({ "n": 3 })), ["n: must be at most 2"]);
+        assert_eq!(paths(&schema, json!({ "n": 1.5 })), ["n: expected an integer, not a number"]);
+        assert_eq!(
+            paths(&schema, json!({ "n": 1, "list": [{ "s": "" }, { "s": 5 }], "item": { "s": true } })),
+            ["item.s: expected a string, not a boolean", "list[1].s: expected a string, not an integer"],
+        );
+        assert_eq!(paths(&schema, json!({ "n": 1, "list": "" })), ["list: expected an array or null, not a string"]);
+    }
+}
@@ -0,0 +1,272 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! OpenSSH Signatures
+//!
+//! A ring with a single member gives its signer no anonymity, so a ring
+//! signature buys nothing over an ordinary one. This module makes and checks
+//! plain ed25519 signatures in OpenSSH's SSHSIG format, described in
+//! PROTOCOL.sshsig in the OpenSSH source, which anybody can check with
+//! `ssh-keygen -Y verify`.
+//!
+//! Signatures are made exactly as OpenSSH makes them, including deriving the
+//! nonce from the prefix of the expanded secret key, so signing the same
+//! message with the same key gives the same output as `ssh-keygen -Y sign`.
+
+use bitcoin_hashes::{sha256, sha512, Hash, HashEngine};
+use curve25519_dalek::constants;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+
+use crate::armor::{
+    check_string_has_ed, check_string_no_prefix, parse_armor, read_string, read_string32, read_u32, Error,
+    FromArmor,
+};
+use crate::keys::{ExpandedSecretKey, PublicKey};
+use crate::radix64::base64_encode;
+
+/// The magic bytes which start both the signature and the signed data
+const MAGIC: &[u8] = b"SSHSIG";
+/// The only version of the format
+const VERSION: u32 = 1;
+/// The hash used for the message when signing; sha256 is also accepted
+const HASH_ALGORITHM: &str = "sha512";
+
+/// Width of the base64 lines in the armor, as OpenSSH writes them
+const LINE_WIDTH: usize = 70;
+
+/// The namespace of signatures made in place of a proof over a ring of one
+/// key, in a confession's `sshsig` field
+pub const CONFESSION_NAMESPACE: &str = "cryptoconfessions";
+
+/// A parsed SSHSIG signature
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Signature {
+    /// The key which made the signature
+    pub public_key: PublicKey,
+    /// The namespace, which keeps signatures for one purpose from being
+    /// used for another
+    pub namespace: String,
+    /// The hash applied to the message before signing
+    pub hash_algorithm: String,
+    signature: [u8; 64],
+}
+
+/// Append an SSH wire-format string
+fn put_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&u32::try_from(data.len()).unwrap().to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+/// The data which is actually signed for a message
+fn signed_data(namespace: &str, hash_algorithm: &str, message: &[u8]) -> Result<Vec<u8>, &'static str> {
+    let hash = match hash_algorithm {
+        "sha512" => sha512::Hash::hash(message).into_inner().to_vec(),
+        "sha256" => sha256::Hash::hash(message).into_inner().to_vec(),
+        _ => return Err("unsupported SSHSIG hash algorithm"),
+    };
+    let mut ret = MAGIC.to_vec();
+    put_string(&mut ret, namespace.as_bytes());
+    put_string(&mut ret, b""); // reserved
+    put_string(&mut ret, hash_algorithm.as_bytes());
+    put_string(&mut ret, &hash);
+    Ok(ret)
+}
+
+/// SHA512 of some byte strings, reduced to a scalar
+fn hash_to_scalar(data: &[&[u8]]) -> Scalar {
+    let mut eng = sha512::Hash::engine();
+    for d in data {
+        eng.input(d);
+    }
+    Scalar::from_bytes_mod_order_wide(&sha512::Hash::from_engine(eng).into_inner())
+}
+
+/// A standard (RFC 8032) ed25519 signature
+fn ed25519_sign(esk: &ExpandedSecretKey, message: &[u8]) -> [u8; 64] {
+    let pk = esk.to_public().serialize();
+    let r = hash_to_scalar(&[&esk.prefix, message]);
+    let big_r = (&r * &constants::ED25519_BASEPOINT_TABLE).compress().to_bytes();
+    let k = hash_to_scalar(&[&big_r, &pk, message]);
+    let s = r + k * esk.secret_key().0;
+
+    let mut ret = [0; 64];
+    ret[..32].copy_from_slice(&big_r);
+    ret[32..].copy_from_slice(s.as_bytes());
+    ret
+}
+
+/// Verify a standard (RFC 8032) ed25519 signature
+fn ed25519_verify(pk: &PublicKey, signature: &[u8; 64], message: &[u8]) -> Result<(), &'static str> {
+    let mut s = [0; 32];
+    s.copy_from_slice(&signature[32..]);
+    let s = Scalar::from_canonical_bytes(s).ok_or("non-canonical signature")?;
+    let big_r = CompressedEdwardsY::from_slice(&signature[..32]);
+    let k = hash_to_scalar(&[big_r.as_bytes(), &pk.serialize(), message]);
+    let check = EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &-pk.0, &s);
+    if check.compress() == big_r {
+        Ok(())
+    } else {
+        Err("bad signature")
+    }
+}
+
+/// Sign a message, returning an armored signature in the form written by
+/// `ssh-keygen -Y sign -n <namespace>`
+pub fn sign_sshsig(expanded_sk: &ExpandedSecretKey, namespace: &str, message: &[u8]) -> String {
+    let data = signed_data(namespace, HASH_ALGORITHM, message).expect("supported hash algorithm");
+    let sig = Signature {
+        public_key: expanded_sk.to_public(),
+        namespace: namespace.to_owned(),
+        hash_algorithm: HASH_ALGORITHM.to_owned(),
+        signature: ed25519_sign(expanded_sk, &data),
+    };
+    sig.to_armor()
+}
+
+impl Signature {
+    /// Encode the signature as armor
+    pub fn to_armor(&self) -> String {
+        let mut blob = MAGIC.to_vec();
+        blob.extend_from_slice(&VERSION.to_be_bytes());
+        put_string(&mut blob, &self.public_key.ssh_blob());
+        put_string(&mut blob, self.namespace.as_bytes());
+        put_string(&mut blob, b""); // reserved
+        put_string(&mut blob, self.hash_algorithm.as_bytes());
+        let mut sig = vec![];
+        put_string(&mut sig, b"ssh-ed25519");
+        put_string(&mut sig, &self.signature);
+        put_string(&mut blob, &sig);
+
+        // Rewrap to OpenSSH's line width
+        let b64: Vec<u8> = base64_encode(&blob).into_bytes().into_iter().filter(|&b| b != b'\n').collect();
+        let mut ret = format!("{}\n", Self::BEGIN_STR);
+        for line in b64.chunks(LINE_WIDTH) {
+            ret += std::str::from_utf8(line).unwrap(); // base64 is ASCII
+            ret.push('\n');
+        }
+        ret += Self::END_STR;
+        ret.push('\n');
+        ret
+    }
+
+    /// Check the signature of a message in the given namespace
+    ///
+    /// This does not check who made the signature; the caller should compare
+    /// [`Self::public_key`] against the key it expects.
+    pub fn verify(&self, namespace: &str, message: &[u8]) -> Result<(), &'static str> {
+        if self.namespace != namespace {
+            return Err("signature is for a different namespace");
+        }
+        let data = signed_data(&self.namespace, &self.hash_algorithm, message)?;
+        ed25519_verify(&self.public_key, &self.signature, &data)
+    }
+}
+
+impl FromArmor for Signature {
+    const BEGIN_STR: &'static str = "-----BEGIN SSH SIGNATURE-----";
+    const END_STR: &'static str = "-----END SSH SIGNATURE-----";
+
+    fn from_armor(s: &str) -> Result<Self, Error> {
+        let data = parse_armor::<Self>(s)?;
+        let mut sl = &data[..];
+        check_string_no_prefix(&mut sl, MAGIC)?;
+        let version = read_u32(&mut sl)?;
+        if version != VERSION {
+            return Err(Error::UnexpectedNumber { expected: VERSION, got: version });
+        }
+        let mut key = read_string(&mut sl)?;
+        check_string_has_ed(&mut key)?;
+        let public_key = PublicKey::parse(&read_string32(&mut key)?)?;
+        let namespace = String::from_utf8_lossy(read_string(&mut sl)?).into_owned();
+        read_string(&mut sl)?; // reserved
+        let hash_algorithm = String::from_utf8_lossy(read_string(&mut sl)?).into_owned();
+
+        let mut sig = read_string(&mut sl)?;
+        check_string_has_ed(&mut sig)?;
+        let sig_len = read_u32(&mut sig)?;
+        if sig_len != 64 {
+            return Err(Error::UnexpectedNumber { expected: 64, got: sig_len });
+        }
+        let signature = *sig.first_chunk::<64>().ok_or(Error::EarlyEof)?;
+        Ok(Signature { public_key, namespace, hash_algorithm, signature })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::armor::OpensshKey;
+    use bitcoin_hashes::hex::FromHex;
+
+    /// Output of `ssh-keygen -Y sign -f testdata/test-key.priv -n cryptoconfessions`
+    /// on the message below
+    const SSH_KEYGEN_SIG: &str = "-----BEGIN SSH SIGNATURE-----
+U1NIU0lHAAAAAQAAADMAAAALc3NoLWVkMjU1MTkAAAAgN22W4ZJjS+/OBx1TcZMh9a2uvw
+oxKTxjWL00l9EKRXUAAAARY3J5cHRvY29uZmVzc2lvbnMAAAAAAAAABnNoYTUxMgAAAFMA
+AAALc3NoLWVkMjU1MTkAAABAJtGnPwQVdtc3QVxHR84Uc+hAfsqXJLrWsbjgdv57aqv22G
+CVxNamRgYISPCvh+2Xaof+S1f3+aQPIn9nLj0pDw==
+-----END SSH SIGNATURE-----
+";
+    const MESSAGE: &[u8] = b"this is an example text";
+
+    #[test]
+    fn rfc8032() {
+        // Test 1 of RFC 8032 section 7.1
+        let seed = <[u8; 32]>::from_hex("9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60").unwrap();
+        let esk = ExpandedSecretKey::from_seed(&seed);
+        assert_eq!(
+            esk.to_public().serialize(),
+            <[u8; 32]>::from_hex("d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a").unwrap(),
+        );
+        let sig = ed25519_sign(&esk, b"");
+        assert_eq!(
+            sig.to_vec(),
+            Vec::<u8>::from_hex(concat!(
+                "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555",
+                "fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+            ))
+            .unwrap(),
+        );
+        assert_eq!(ed25519_verify(&esk.to_public(), &sig, b""), Ok(()));
+        assert!(ed25519_verify(&esk.to_public(), &sig, b"x").is_err());
+    }
+
+    #[test]
+    fn matches_ssh_keygen() {
+        let key = OpensshKey::from_armor(include_str!("../testdata/test-key.priv")).unwrap();
+        let esk = key.decrypt_expanded(b"").unwrap();
+        assert_eq!(sign_sshsig(&esk, "cryptoconfessions", MESSAGE), SSH_KEYGEN_SIG);
+
+        let sig = Signature::from_armor(SSH_KEYGEN_SIG).unwrap();
+        assert_eq!(sig.public_key, key.public_key());
+        assert_eq!(sig.namespace, "cryptoconfessions");
+        assert_eq!(sig.to_armor(), SSH_KEYGEN_SIG);
+        assert_eq!(sig.verify("cryptoconfessions", MESSAGE), Ok(()));
+        assert!(sig.verify("cryptoconfessions", b"this is another text").is_err());
+        assert!(sig.verify("file", MESSAGE).is_err());
+    }
+
+    #[test]
+    fn malformed() {
+        let mut sig = Signature::from_armor(SSH_KEYGEN_SIG).unwrap();
+        sig.signature[0] ^= 1;
+        assert_eq!(sig.verify("cryptoconfessions", MESSAGE), Err("bad signature"));
+        sig.hash_algorithm = "md5".into();
+        assert!(sig.verify("cryptoconfessions", MESSAGE).is_err());
+        assert!(Signature::from_armor(&SSH_KEYGEN_SIG.replace("U1NIU0lH", "U1NIU0lI")).is_err());
+    }
+}
@@ -0,0 +1,158 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Synthetic Keys, Rings and Confessions
+//!
+//! Benchmarks, demos, fuzz corpora and tests all need keys and rings which
+//! are the same every time. This derives them from a seed, and makes
+//! confessions over them whose proofs are reproducible too, using no
+//! randomness from the operating system.
+//!
+//! **These keys are not secret.** Anyone who knows the seed, which is
+//! usually written in source code, knows every secret key, and a proof
+//! made here reveals its signer to anyone who can make it again. Never use
+//! them for anything real.
+//!
+//! This module needs the `testkit` feature, which is off by default so
+//! that no real build can reach these keys. The crate's own tests and
+//! benchmarks turn it on.
+
+use bitcoin_hashes::{sha256, Hash, HashEngine};
+
+use crate::bundle::Confession;
+use crate::keys::{ExpandedSecretKey, PublicKey, SecretKey};
+
+/// The most keys generated at once, so that a mistyped size fails at once
+/// rather than tying up the machine
+pub const MAX_KEYS: usize = 1 << 20;
+
+/// A synthetic key pair
+#[derive(Clone)]
+pub struct Keypair {
+    pub secret: ExpandedSecretKey,
+    pub public: PublicKey,
+}
+
+/// A hash of `seed` for a purpose, and a counter for keys
+fn derive(purpose: &str, seed: &[u8], n: u64) -> [u8; 32] {
+    let mut eng = sha256::Hash::engine();
+    eng.input(purpose.as_bytes());
+    eng.input(&u64::try_from(seed.len()).unwrap().to_le_bytes());
+    eng.input(seed);
+    eng.input(&n.to_le_bytes());
+    sha256::Hash::from_engine(eng).into_inner()
+}
+
+/// The first `n` key pairs derived from `seed`
+///
+/// The first keys are the same whatever `n` is.
+///
+/// # Panics
+///
+/// If `n` is more than [`MAX_KEYS`].
+pub fn deterministic_keypairs(seed: &[u8], n: usize) -> Vec<Keypair> {
+    assert!(n <= MAX_KEYS, "at most {} synthetic keys may be generated at once, not {}", MAX_KEYS, n);
+    (0..n as u64)
+        .map(|index| {
+            let secret = ExpandedSecretKey::from_seed(&derive("ringsig testkit key", seed, index));
+            let public = secret.to_public();
+            Keypair { secret, public }
+        })
+        .collect()
+}
+
+/// The secret keys and public keys of the first `n` key pairs derived from
+/// `seed`, as proving and verifying take them
+pub fn deterministic_keys(seed: &[u8], n: usize) -> (Vec<SecretKey>, Vec<PublicKey>) {
+    deterministic_keypairs(seed, n).into_iter().map(|keypair| (keypair.secret.secret_key(), keypair.public)).unzip()
+}
+
+/// The public keys of the first `n` key pairs derived from `seed`
+pub fn synthetic_ring(seed: &[u8], n: usize) -> Vec<PublicKey> {
+    deterministic_keypairs(seed, n).iter().map(|keypair| keypair.public).collect()
+}
+
+/// A key as a line of a keys file
+pub fn key_line(key: &PublicKey, comment: &str) -> String {
+    key.to_ssh_line(Some(comment))
+}
+
+/// A proven confession over a ring of `ring_size` keys derived from `seed`
+///
+/// The signer is chosen by the seed, and the confession, proof included, is
+/// the same every time.
+///
+/// # Panics
+///
+/// If `ring_size` is zero or more than [`MAX_KEYS`].
+pub fn synthetic_confession(seed: &[u8], ring_size: usize) -> Confession {
+    assert!(ring_size > 0, "a confession needs at least one key");
+    let keypairs = deterministic_keypairs(seed, ring_size);
+    let lines = keypairs.iter().enumerate().map(|(n, keypair)| key_line(&keypair.public, &format!("testkit-{}", n)));
+    let mut ret = Confession::new(lines.collect(), format!("a synthetic confession over a ring of {}", ring_size));
+
+    let signer = u64::from_le_bytes(derive("ringsig testkit signer", seed, 0)[..8].try_into().unwrap());
+    let sk = keypairs[(signer % ring_size as u64) as usize].secret.secret_key();
+    let keys: Vec<PublicKey> = keypairs.iter().map(|keypair| keypair.public).collect();
+    let aux = derive("ringsig testkit randomness", seed, 0);
+    let proof = crate::prove_with_aux_rand(&keys, ret.message.as_bytes(), &sk, &aux).expect("the signer is in the ring");
+    ret.proof = Some(proof.to_hex());
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::badge;
+    use bitcoin_hashes::hex::ToHex;
+
+    #[test]
+    fn deterministic() {
+        let keypairs = deterministic_keypairs(b"ringsig testkit", 3);
+        // Pinned, so that every user's synthetic data stays the same
+        assert_eq!(keypairs[0].public.fingerprint(), "SHA256:t/GvPOjE+VwpcixpD2GtOANYGCrTO5dByKORwaleFVg");
+        assert_eq!(synthetic_ring(b"ringsig testkit", 2), [keypairs[0].public, keypairs[1].public]);
+        assert_ne!(synthetic_ring(b"ringsig testkit!", 1)[0], keypairs[0].public);
+        assert_eq!(keypairs[2].secret.secret_key().to_public(), keypairs[2].public);
+        assert!(deterministic_keypairs(b"", 0).is_empty());
+        let (sks, pks) = deterministic_keys(b"ringsig testkit", 3);
+        assert_eq!(pks, synthetic_ring(b"ringsig testkit", 3));
+        assert_eq!(sks[1].to_public(), pks[1]);
+
+        let line = key_line(&keypairs[1].public, "comment");
+        assert_eq!(PublicKey::parse_pk_line(&line), Ok(keypairs[1].public));
+        assert!(line.ends_with(" comment"));
+    }
+
+    #[test]
+    fn confessions() {
+        for size in [1, 2, 7] {
+            let confession = synthetic_confession(b"ringsig testkit", size);
+            assert_eq!(confession.public_keys.len(), size);
+            assert_eq!(badge::verify_confession(&confession, 0), Ok(()));
+            assert_eq!(synthetic_confession(b"ringsig testkit", size), confession);
+        }
+        let hash = synthetic_confession(b"ringsig testkit", 3).bundle_hash();
+        assert_eq!(hash.to_hex(), "5df1b9ba43f10757c02a638a3c6d88b66e1ac82c673362140c0a3f41ff13a763");
+        assert_ne!(synthetic_confession(b"one seed", 5).proof, synthetic_confession(b"another seed", 5).proof);
+    }
+
+    #[test]
+    #[should_panic(expected = "at most")]
+    fn too_many() {
+        deterministic_keypairs(b"", MAX_KEYS + 1);
+    }
+}
@@ -0,0 +1,240 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Threads of Confessions
+//!
+//! A confession may reply to an earlier one, made with
+//! [`Confession::reply_to`] before proving, so that its signed attributes
+//! commit to the parent's [`Confession::bundle_hash`]. Statements made one
+//! after another thus form a thread which cannot be rearranged: a reply's
+//! proof covers its parent, and through it every earlier statement.
+//!
+//! [`verify_thread`] checks a set of confessions, verifying each and
+//! matching every reply with its parent. A reply whose parent is not in the
+//! set, or does not verify, breaks the thread there. Hashes make a cycle of
+//! replies impossible to construct, but one is detected all the same rather
+//! than followed forever.
+
+use crate::badge;
+use crate::bundle::Confession;
+use crate::hashes::BundleHash;
+
+/// How a confession is linked into the thread
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Link {
+    /// It replies to nothing
+    Root,
+    /// It replies to the confession with this index
+    Parent(usize),
+    /// It replies to a confession which is not in the thread
+    MissingParent(BundleHash),
+    /// It is part of a cycle of replies
+    Cycle,
+}
+
+/// One confession of a thread
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ThreadEntry {
+    pub hash: BundleHash,
+    /// Whether the confession itself verified, as of its signing time
+    pub verified: Result<(), String>,
+    pub link: Link,
+    /// The number of replies between it and its root, if its ancestors are
+    /// all present
+    pub depth: Option<usize>,
+}
+
+/// A place where a thread is broken
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Break {
+    /// The confession with this index did not verify
+    Unverified { index: usize, error: String },
+    /// The confession with this index replies to one which is missing
+    MissingParent { index: usize, parent: BundleHash },
+    /// The confession with this index is part of a cycle of replies
+    Cycle { index: usize },
+}
+
+/// The outcome of [`verify_thread`], with an entry for each confession in
+/// the order given
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ThreadReport {
+    pub entries: Vec<ThreadEntry>,
+    pub breaks: Vec<Break>,
+}
+
+impl ThreadReport {
+    /// Whether every confession verified and every reply found its parent
+    pub fn is_intact(&self) -> bool {
+        self.breaks.is_empty()
+    }
+}
+
+/// Verify a thread of confessions, in any order
+///
+/// Each confession is verified as of its own signing time, if it has one,
+/// so that an expiry does not break the thread.
+pub fn verify_thread(confessions: &[Confession]) -> ThreadReport {
+    let hashes: Vec<BundleHash> = confessions.iter().map(Confession::bundle_hash).collect();
+    let parents: Vec<Option<BundleHash>> = confessions.iter().map(Confession::parent).collect();
+    let (links, depths) = find_links(&hashes, &parents);
+
+    let mut breaks = vec![];
+    let mut entries = vec![];
+    let linked = hashes.into_iter().zip(links).zip(depths);
+    for (index, (confession, ((hash, link), depth))) in confessions.iter().zip(linked).enumerate() {
+        let at = confession.attributes.and_then(|attributes| attributes.timestamp).unwrap_or(0);
+        let verified = badge::verify_confession(confession, at);
+        if let Err(ref error) = verified {
+            breaks.push(Break::Unverified { index, error: error.clone() });
+        }
+        match link {
+            Link::MissingParent(parent) => breaks.push(Break::MissingParent { index, parent }),
+            Link::Cycle => breaks.push(Break::Cycle { index }),
+            Link::Root | Link::Parent(_) => {}
+        }
+        entries.push(ThreadEntry { hash, verified, link, depth });
+    }
+    ThreadReport { entries, breaks }
+}
+
+/// Link each confession to its parent, and find its depth
+fn find_links(hashes: &[BundleHash], parents: &[Option<BundleHash>]) -> (Vec<Link>, Vec<Option<usize>>) {
+    let mut links: Vec<Link> = parents
+        .iter()
+        .map(|parent| match *parent {
+            None => Link::Root,
+            Some(parent) => match hashes.iter().position(|hash| *hash == parent) {
+                Some(index) => Link::Parent(index),
+                None => Link::MissingParent(parent),
+            },
+        })
+        .collect();
+
+    // Walk up from each confession until reaching one whose depth is known,
+    // a root, a missing parent, or a confession already on the walk
+    let mut depths: Vec<Option<Option<usize>>> = vec![None; hashes.len()];
+    for start in 0..hashes.len() {
+        let mut path = vec![];
+        let mut current = start;
+        let mut depth = loop {
+            if let Some(depth) = depths[current] {
+                break depth;
+            }
+            if let Some(position) = path.iter().position(|&index| index == current) {
+                for &index in &path[position..] {
+                    links[index] = Link::Cycle;
+                    depths[index] = Some(None);
+                }
+                path.truncate(position);
+                break None;
+            }
+            match links[current] {
+                Link::Parent(parent) => {
+                    path.push(current);
+                    current = parent;
+                }
+                Link::Root => {
+                    depths[current] = Some(Some(0));
+                    break Some(0);
+                }
+                Link::MissingParent(_) | Link::Cycle => {
+                    depths[current] = Some(None);
+                    break None;
+                }
+            }
+        };
+        for &index in path.iter().rev() {
+            depth = depth.map(|depth| depth + 1);
+            depths[index] = Some(depth);
+        }
+    }
+    (links, depths.into_iter().map(Option::flatten).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+    use bitcoin_hashes::Hash;
+
+    /// A confession by the `signer`th key, replying to `parent` if given
+    fn confession(message: &str, parent: Option<&Confession>, signer: usize) -> Confession {
+        let keypairs = testkit::deterministic_keypairs(b"thread tests", 4);
+        let keys: Vec<_> = keypairs[1..].iter().map(|keypair| keypair.public).collect();
+        let ring = keys.iter().map(|key| testkit::key_line(key, "member")).collect();
+        let mut ret = Confession::new(ring, message.to_owned());
+        if let Some(parent) = parent {
+            ret.reply_to(&parent.bundle_hash());
+        }
+        let signed = ret.attributes.unwrap_or_default().signed_message(message.as_bytes());
+        ret.proof = Some(crate::prove(&keys, &signed, &keypairs[signer].secret.secret_key()).unwrap().to_hex());
+        ret
+    }
+
+    #[test]
+    fn three_deep() {
+        let root = confession("what happened", None, 1);
+        let reply = confession("what happened next", Some(&root), 2);
+        let last = confession("how it ended", Some(&reply), 1);
+        assert_eq!(reply.parent(), Some(root.bundle_hash()));
+
+        // In any order
+        let report = verify_thread(&[last.clone(), root.clone(), reply.clone()]);
+        assert!(report.is_intact(), "{:?}", report);
+        let links: Vec<_> = report.entries.iter().map(|entry| (entry.link.clone(), entry.depth)).collect();
+        assert_eq!(links, [(Link::Parent(2), Some(2)), (Link::Root, Some(0)), (Link::Parent(1), Some(1))]);
+        assert_eq!(report.entries[1].hash, root.bundle_hash());
+
+        // The reply's proof covers its parent, so it cannot be moved elsewhere
+        let mut moved = reply.clone();
+        moved.reply_to(&confession("something else", None, 3).bundle_hash());
+        assert!(badge::verify_confession(&moved, 0).is_err());
+
+        // Editing the middle confession breaks its proof and its child's link
+        let mut edited = reply.clone();
+        edited.message = "what happened after".into();
+        let report = verify_thread(&[root, edited, last]);
+        assert!(!report.is_intact());
+        assert_eq!(report.entries[1].link, Link::Parent(0));
+        assert_eq!(report.entries[2].link, Link::MissingParent(reply.bundle_hash()));
+        assert_eq!(report.entries[2].depth, None);
+        assert_eq!(report.entries[2].verified, Ok(()));
+        assert_eq!(
+            report.breaks,
+            [
+                Break::Unverified { index: 1, error: "bad proof".into() },
+                Break::MissingParent { index: 2, parent: reply.bundle_hash() },
+            ],
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        // Replies cannot really form a cycle, since each commits to the hash
+        // of its parent, so make one up
+        let hashes: Vec<BundleHash> = (0u8..5).map(|n| BundleHash::hash(&[n])).collect();
+        let parents = [None, Some(hashes[2]), Some(hashes[1]), Some(hashes[2]), Some(hashes[4])];
+        let (links, depths) = find_links(&hashes, &parents);
+        assert_eq!(links, [Link::Root, Link::Cycle, Link::Cycle, Link::Parent(2), Link::Cycle]);
+        assert_eq!(depths, [Some(0), None, None, None, None]);
+
+        let parents = [Some(hashes[4]), Some(hashes[0]), Some(hashes[1]), None, Some(hashes[3])];
+        let (links, depths) = find_links(&hashes, &parents);
+        assert_eq!(links, [Link::Parent(4), Link::Parent(0), Link::Parent(1), Link::Root, Link::Parent(3)]);
+        assert_eq!(depths, [Some(2), Some(3), Some(4), Some(0), Some(1)]);
+    }
+}
@@ -0,0 +1,305 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Threshold proofs
+//!
+//! A threshold proof shows that at least `t` distinct members of a ring
+//! signed a message, without showing which. It is `t` linkable proofs, one
+//! by each signer, whose key images share a base point derived from the
+//! ring, the message and `t`. A signer's key image is the same in every one
+//! of them, so `t` different key images mean `t` different signers. As the
+//! base point depends on the message, the key images of one signer differ
+//! between confessions, so do not link them.
+//!
+//! The params of each proof are a tagged hash of the usual params and `t`,
+//! so none of them passes as a plain or linkable proof, nor as part of a
+//! threshold proof for a different `t`.
+//!
+//! In bytes, a threshold proof is `t` as a little-endian u32, then for each
+//! signer a 32-byte key image followed by its proof. The entries are sorted
+//! by key image, so their order says nothing about the order of the keys
+//! given to [`prove_threshold`].
+
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use bitcoin_hashes::{Hash, HashEngine};
+use curve25519_dalek::edwards::EdwardsPoint;
+use std::ops::ControlFlow;
+
+use crate::hashes::{ParamsHash, ThresholdBaseHash, ThresholdParamsHash};
+use crate::keys::{PublicKey, SecretKey};
+use crate::{
+    find_signer, hash_to_point, key_image_point, proof_len, prove_sorted, verify_sorted, Link, ProofError, ProveError,
+    Ring, TooLarge, VerifyError,
+};
+
+/// A proof that some number of ring members signed, as made by
+/// [`prove_threshold`]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ThresholdProof(Vec<u8>, usize);
+
+impl ThresholdProof {
+    /// Wrap the bytes of a threshold proof, which must be a threshold of `t`
+    /// followed by `t` key images and proofs over a ring of the same size
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, ProofError> {
+        let (threshold, rest) = match bytes.split_first_chunk::<4>() {
+            Some((threshold, rest)) => (u32::from_le_bytes(*threshold), rest),
+            None => return Err(ProofError::WrongLength { got: bytes.len() }),
+        };
+        let wrong_length = || ProofError::WrongThresholdLength { threshold, got: bytes.len() };
+        // Each entry is a key image, a challenge and at least one `s` value
+        let entries = usize::try_from(threshold).map_err(|_| wrong_length())?;
+        let entry_len = rest.len().checked_div(entries).unwrap_or(0);
+        if entries == 0 || entry_len < 96 || !entry_len.is_multiple_of(32) || entry_len * entries != rest.len() {
+            return Err(wrong_length());
+        }
+        Ok(ThresholdProof(bytes, entry_len))
+    }
+
+    /// Parse a threshold proof from hex, as made by [`ThresholdProof::to_hex`]
+    pub fn from_hex(s: &str) -> Result<Self, ProofError> {
+        let bytes = Vec::<u8>::from_hex(s).map_err(|e| ProofError::InvalidHex(e.to_string()))?;
+        ThresholdProof::from_bytes(bytes)
+    }
+
+    /// The number of distinct signers the proof shows
+    pub fn threshold(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// The number of keys in the ring the proof was made for
+    pub fn ring_size(&self) -> usize {
+        self.entry_len() / 32 - 2
+    }
+
+    /// The signers' key images, in the order they appear in the proof
+    pub fn key_images(&self) -> impl Iterator<Item = [u8; 32]> + '_ {
+        self.entries().map(|entry| {
+            let mut image = [0; 32];
+            image.copy_from_slice(&entry[..32]);
+            image
+        })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.to_hex()
+    }
+
+    /// The length of each key image and proof, found when parsing
+    fn entry_len(&self) -> usize {
+        self.1
+    }
+
+    /// Each key image followed by its proof
+    fn entries(&self) -> std::slice::ChunksExact<'_, u8> {
+        self.0[4..].chunks_exact(self.entry_len())
+    }
+}
+
+impl AsRef<[u8]> for ThresholdProof {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// The params of each of a threshold proof's proofs, and the base point of
+/// their key images
+fn threshold_params(ring: &Ring, message: &[u8], threshold: usize) -> Result<(ParamsHash, EdwardsPoint), TooLarge> {
+    let mut eng = ThresholdParamsHash::engine();
+    eng.input(&ring.params(message)?[..]);
+    eng.input(&u32::try_from(threshold).map_err(|_| TooLarge::Keys)?.to_le_bytes());
+    let params = ThresholdParamsHash::from_engine(eng);
+    let base = hash_to_point(|ctr| {
+        let mut eng = ThresholdBaseHash::engine();
+        eng.input(&params[..]);
+        eng.input(&ctr.to_le_bytes());
+        ThresholdBaseHash::from_engine(eng).into_inner()
+    });
+    Ok((ParamsHash::from_inner(params.into_inner()), base))
+}
+
+/// Produce a proof that `t` distinct members of the ring signed `message`,
+/// given exactly `t` of their secret keys
+pub fn prove_threshold(
+    pks: &[PublicKey],
+    message: &[u8],
+    sks: &[SecretKey],
+    t: usize,
+) -> Result<ThresholdProof, ProveError> {
+    if t == 0 {
+        return Err(ProveError::ZeroThreshold);
+    }
+    if sks.len() != t {
+        return Err(ProveError::SignerCount { threshold: t, got: sks.len() });
+    }
+    let ring = Ring::prepare::<ProveError>(pks)?;
+    let signers = sks.iter().map(|sk| find_signer(&ring.pks, sk)).collect::<Result<Vec<_>, _>>()?;
+    let mut indices = signers.clone();
+    indices.sort_unstable();
+    if indices.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(ProveError::DuplicateSigner);
+    }
+
+    let (params, base) = threshold_params(&ring, message, t)?;
+    let mut entries = Vec::with_capacity(t);
+    for (sk, my_idx) in sks.iter().zip(signers) {
+        let link = Link { base, image: sk.0 * base };
+        let proof = prove_sorted(&ring.negated, my_idx, params, sk, Some(link), |_, _| {})?;
+        entries.push((link.image.compress().to_bytes(), proof));
+    }
+    entries.sort_unstable_by_key(|(image, _)| *image);
+
+    let mut ret = Vec::from(u32::try_from(t).map_err(|_| ProveError::TooManyKeys)?.to_le_bytes());
+    for (image, proof) in entries {
+        ret.extend_from_slice(&image);
+        ret.extend_from_slice(proof.as_bytes());
+    }
+    Ok(ThresholdProof(ret, 32 + proof_len(ring.pks.len())))
+}
+
+/// Verify a threshold proof made by [`prove_threshold`], which must be by
+/// at least `t` distinct members of the ring
+pub fn verify_threshold(
+    proof: &ThresholdProof,
+    pks: &[PublicKey],
+    message: &[u8],
+    t: usize,
+) -> Result<(), VerifyError> {
+    if proof.threshold() < t {
+        return Err(VerifyError::ThresholdNotMet { needed: t, got: proof.threshold() });
+    }
+    let ring = Ring::prepare::<VerifyError>(pks)?;
+    let (params, base) = threshold_params(&ring, message, proof.threshold())?;
+
+    let mut images = Vec::with_capacity(proof.threshold());
+    for entry in proof.entries() {
+        let mut key_image = [0; 32];
+        key_image.copy_from_slice(&entry[..32]);
+        let image = key_image_point(&key_image)?;
+        let link = Link { base, image };
+        verify_sorted(&entry[32..], ring.negated.iter().copied(), params, Some(link), |_, _| ControlFlow::Continue(()))?;
+        // Several encodings may decode to the same point, so compare the
+        // points rather than the bytes
+        images.push(image.compress().to_bytes());
+    }
+    images.sort_unstable();
+    if images.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(VerifyError::DuplicateKeyImage);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    #[test]
+    fn prove_and_verify() {
+        let (sks, pks) = testkit::deterministic_keys(b"threshold tests", 5);
+        let signers = [sks[3].clone(), sks[0].clone(), sks[4].clone()];
+        let proof = prove_threshold(&pks, b"msg", &signers, 3).unwrap();
+        assert_eq!((proof.threshold(), proof.ring_size()), (3, 5));
+        assert_eq!(proof.as_bytes().len(), 4 + 3 * (32 + 32 * 6));
+        let images: Vec<_> = proof.key_images().collect();
+        assert!(images.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert_eq!(verify_threshold(&proof, &pks, b"msg", 3), Ok(()));
+        assert_eq!(verify_threshold(&proof, &pks, b"msg", 2), Ok(()));
+        assert_eq!(verify_threshold(&proof, &pks, b"msg", 4), Err(VerifyError::ThresholdNotMet { needed: 4, got: 3 }));
+        assert_eq!(verify_threshold(&proof, &pks, b"other", 3), Err(VerifyError::BadProof));
+        assert_eq!(
+            verify_threshold(&proof, &pks[1..], b"msg", 3),
+            Err(VerifyError::WrongProofLength { expected: 160, got: 192, proof_ring_size: Some(5) }),
+        );
+        assert_eq!(ThresholdProof::from_hex(&proof.to_hex()), Ok(proof.clone()));
+
+        // The same signers, in any order, give the same key images, which
+        // differ from message to message
+        let reordered = [sks[4].clone(), sks[3].clone(), sks[0].clone()];
+        let again = prove_threshold(&pks, b"msg", &reordered, 3).unwrap();
+        assert_eq!(again.key_images().collect::<Vec<_>>(), images);
+        let other = prove_threshold(&pks, b"other", &signers, 3).unwrap();
+        assert!(other.key_images().all(|image| !images.contains(&image)));
+    }
+
+    #[test]
+    fn threshold_of_one() {
+        let (sks, pks) = testkit::deterministic_keys(b"threshold tests", 3);
+        let proof = prove_threshold(&pks, b"msg", &sks[1..2], 1).unwrap();
+        assert_eq!((proof.threshold(), proof.ring_size()), (1, 3));
+        assert_eq!(verify_threshold(&proof, &pks, b"msg", 1), Ok(()));
+        assert_eq!(verify_threshold(&proof, &pks, b"msg", 0), Ok(()));
+
+        // It is a linkable proof, but its params are the threshold's own,
+        // so it passes as neither a plain nor a linkable proof
+        let inner = crate::Proof::from_bytes(proof.as_bytes()[36..].to_vec()).unwrap();
+        assert_eq!(crate::verify(&inner, &pks, b"msg"), Err(VerifyError::BadProof));
+        let image = proof.key_images().next().unwrap();
+        assert_eq!(crate::verify_linkable(&inner, &image, &pks, b"msg", b""), Err(VerifyError::BadProof));
+        // Nor as part of a threshold proof for another threshold
+        let mut relabeled = proof.as_bytes().to_vec();
+        relabeled.extend_from_within(4..);
+        relabeled[..4].copy_from_slice(&2u32.to_le_bytes());
+        let relabeled = ThresholdProof::from_bytes(relabeled).unwrap();
+        assert_eq!(verify_threshold(&relabeled, &pks, b"msg", 2), Err(VerifyError::BadProof));
+    }
+
+    #[test]
+    fn one_signer_twice() {
+        let (sks, pks) = testkit::deterministic_keys(b"threshold tests", 4);
+        assert_eq!(prove_threshold(&pks, b"msg", &[sks[2].clone(), sks[2].clone()], 2), Err(ProveError::DuplicateSigner));
+        assert_eq!(prove_threshold(&pks, b"msg", &sks[..2], 3), Err(ProveError::SignerCount { threshold: 3, got: 2 }));
+        assert_eq!(prove_threshold(&pks, b"msg", &[], 0), Err(ProveError::ZeroThreshold));
+
+        // A forger holding one key makes both proofs with it, as
+        // `prove_threshold` would if it did not refuse
+        let ring = Ring::new(&pks).unwrap();
+        let (params, base) = threshold_params(&ring, b"msg", 2).unwrap();
+        let sk = &sks[2];
+        let link = Link { base, image: sk.0 * base };
+        let my_idx = find_signer(&ring.pks, sk).unwrap();
+        let mut forged = 2u32.to_le_bytes().to_vec();
+        for _ in 0..2 {
+            let proof = prove_sorted(&ring.negated, my_idx, params, sk, Some(link), |_, _| {}).unwrap();
+            forged.extend_from_slice(&link.image.compress().to_bytes());
+            forged.extend_from_slice(proof.as_bytes());
+        }
+        let forged = ThresholdProof::from_bytes(forged).unwrap();
+        assert_eq!(verify_threshold(&forged, &pks, b"msg", 2), Err(VerifyError::DuplicateKeyImage));
+        assert_eq!(verify_threshold(&forged, &pks, b"msg", 2).unwrap_err().code(), "duplicate_key_image");
+    }
+
+    #[test]
+    fn lengths() {
+        let proof = |t: u32, len: usize| ThresholdProof::from_bytes([&t.to_le_bytes()[..], &vec![0; len]].concat());
+        assert_eq!(proof(1, 96).unwrap().ring_size(), 1);
+        assert_eq!(proof(2, 2 * 128).unwrap().ring_size(), 2);
+        assert_eq!(proof(0, 0), Err(ProofError::WrongThresholdLength { threshold: 0, got: 4 }));
+        assert_eq!(proof(1, 64), Err(ProofError::WrongThresholdLength { threshold: 1, got: 68 }));
+        assert_eq!(proof(2, 96 + 128), Err(ProofError::WrongThresholdLength { threshold: 2, got: 228 }));
+        assert_eq!(proof(3, 2 * 96), Err(ProofError::WrongThresholdLength { threshold: 3, got: 196 }));
+        assert_eq!(proof(u32::MAX, 96), Err(ProofError::WrongThresholdLength { threshold: u32::MAX, got: 100 }));
+        assert_eq!(ThresholdProof::from_bytes(vec![1, 0]), Err(ProofError::WrongLength { got: 2 }));
+    }
+}
@@ -0,0 +1,562 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Timestamping Bundles
+//!
+//! A confession is often only worth something if it provably existed before
+//! some event. A version 2 bundle may therefore carry attestations from
+//! timestamping services in its `timestamps` array, each over the bundle's
+//! [`BundleHash`]: a tagged hash of the bundle with its timestamps removed,
+//! so that adding one attestation does not invalidate the others.
+//!
+//! Services are reached through the [`TimestampProvider`] trait. This module
+//! implements it for RFC 3161 time-stamp authorities with [`Rfc3161`], which
+//! sends its requests through a [`Transport`] supplied by the caller, since
+//! the library itself does no networking. A [`Canned`] transport replays a
+//! fixed reply, for tests or for replies obtained some other way.
+//!
+//! # Limitations
+//!
+//! Verifying an RFC 3161 attestation checks its structure, that it is a
+//! time-stamp token for the bundle hash, and reads the time from it. It does
+//! **not** check the authority's signature or certificate chain, so it shows
+//! only that somebody claimed the time, not that a trusted authority did.
+//! Where that matters, save the token and check it with `openssl ts -verify`
+//! against the authority's certificates.
+
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use bitcoin_hashes::Hash;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::attributes;
+pub use crate::hashes::BundleHash;
+
+/// The kind of attestations made by RFC 3161 time-stamp authorities
+pub const KIND_RFC3161: &str = "rfc3161";
+/// The media type of RFC 3161 requests, for HTTP transports
+pub const RFC3161_REQUEST_TYPE: &str = "application/timestamp-query";
+
+/// DER encoding of the SHA-256 algorithm identifier, 2.16.840.1.101.3.4.2.1
+const OID_SHA256: &[u8] = &[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+/// DER encoding of the CMS signed-data content type, 1.2.840.113549.1.7.2
+const OID_SIGNED_DATA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x07, 0x02];
+/// DER encoding of the TSTInfo content type, 1.2.840.113549.1.9.16.1.4
+const OID_TST_INFO: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x10, 0x01, 0x04];
+
+// DER tags
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const NULL: u8 = 0x05;
+const OID: u8 = 0x06;
+const UTF8_STRING: u8 = 0x0c;
+const GENERALIZED_TIME: u8 = 0x18;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const EXPLICIT_0: u8 = 0xa0;
+
+/// Timestamping error
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The service could not be reached
+    Transport(String),
+    /// The service refused the request, with its status and any explanation
+    Rejected { status: u64, text: Option<String> },
+    /// The reply or attestation is not validly encoded
+    Malformed(&'static str),
+    /// The attestation uses a hash other than SHA-256
+    UnsupportedHash,
+    /// The attestation is for some other digest
+    WrongDigest,
+    /// The reply does not echo the request's nonce
+    WrongNonce,
+    /// The attestation is of a kind this library does not know
+    UnknownKind(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Transport(ref e) => write!(f, "contacting timestamping service: {}", e),
+            Error::Rejected { status, text: Some(ref text) } => {
+                write!(f, "timestamping service refused the request (status {}): {}", status, text)
+            }
+            Error::Rejected { status, text: None } => {
+                write!(f, "timestamping service refused the request (status {})", status)
+            }
+            Error::Malformed(what) => write!(f, "malformed timestamp: {}", what),
+            Error::UnsupportedHash => f.write_str("timestamp does not use SHA-256"),
+            Error::WrongDigest => f.write_str("timestamp is for a different bundle"),
+            Error::WrongNonce => f.write_str("timestamp reply does not match the request's nonce"),
+            Error::UnknownKind(ref kind) => write!(f, "unknown kind of timestamp `{}`", kind),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// A short, stable identifier for the error, for machine consumers
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Error::Transport(_) => "timestamp_transport",
+            Error::Rejected { .. } => "timestamp_rejected",
+            Error::Malformed(_) | Error::UnsupportedHash => "bad_timestamp",
+            Error::WrongDigest | Error::WrongNonce => "wrong_timestamp",
+            Error::UnknownKind(_) => "unknown_timestamp_kind",
+        }
+    }
+}
+
+/// An attestation that a bundle existed at some time
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+pub struct Attestation {
+    /// The kind of service which made it, e.g. [`KIND_RFC3161`]
+    pub kind: String,
+    /// The attestation itself, hex-encoded; for RFC 3161, a DER
+    /// `TimeStampToken`
+    pub token: String,
+    /// Which service made it, for information only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+}
+
+/// A digest which has been sent to a service, with its reply
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Submission {
+    pub digest: BundleHash,
+    /// The nonce sent with the request, if any
+    pub nonce: Option<u64>,
+    pub reply: Vec<u8>,
+}
+
+/// A timestamping service
+pub trait TimestampProvider {
+    /// Send a digest to the service
+    fn submit(&self, digest: &BundleHash) -> Result<Submission, Error>;
+
+    /// Extract the attestation from the service's reply, checking that it is
+    /// for the submitted digest
+    fn retrieve(&self, submission: &Submission) -> Result<Attestation, Error>;
+
+    /// Check that an attestation is for a digest, returning the time it
+    /// attests to, in seconds since the Unix epoch
+    fn verify(&self, digest: &BundleHash, attestation: &Attestation) -> Result<u64, Error> {
+        verify(digest, attestation)
+    }
+}
+
+/// Obtain an attestation for a digest from a service
+pub fn timestamp<P: TimestampProvider + ?Sized>(provider: &P, digest: &BundleHash) -> Result<Attestation, Error> {
+    provider.retrieve(&provider.submit(digest)?)
+}
+
+/// Check that an attestation of any known kind is for a digest, returning
+/// the time it attests to
+///
+/// See the module documentation for what is not checked.
+pub fn verify(digest: &BundleHash, attestation: &Attestation) -> Result<u64, Error> {
+    match &attestation.kind[..] {
+        KIND_RFC3161 => {
+            let token = Vec::<u8>::from_hex(&attestation.token).map_err(|_| Error::Malformed("token is not hex"))?;
+            let info = parse_token(&token)?;
+            if info.digest != digest.into_inner() {
+                return Err(Error::WrongDigest);
+            }
+            Ok(info.time)
+        }
+        kind => Err(Error::UnknownKind(kind.to_owned())),
+    }
+}
+
+/// Carries requests to a service and brings back its replies
+pub trait Transport {
+    /// Send a request, returning the reply
+    fn send(&self, request: &[u8]) -> Result<Vec<u8>, String>;
+}
+
+impl<F: Fn(&[u8]) -> Result<Vec<u8>, String>> Transport for F {
+    fn send(&self, request: &[u8]) -> Result<Vec<u8>, String> {
+        self(request)
+    }
+}
+
+/// A transport which ignores the request and replies with fixed data
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Canned(pub Vec<u8>);
+
+impl Transport for Canned {
+    fn send(&self, _: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(self.0.clone())
+    }
+}
+
+/// An RFC 3161 time-stamp authority
+pub struct Rfc3161<T> {
+    transport: T,
+    /// Whether to send a random nonce, which the reply must echo
+    use_nonce: bool,
+    service: Option<String>,
+}
+
+impl<T: Transport> Rfc3161<T> {
+    /// An authority reached through `transport`, sending a nonce with every
+    /// request
+    pub fn new(transport: T) -> Self {
+        Rfc3161 { transport, use_nonce: true, service: None }
+    }
+
+    /// Name the authority, e.g. by its URL, in the attestations it makes
+    pub fn with_service(mut self, service: &str) -> Self {
+        self.service = Some(service.to_owned());
+        self
+    }
+}
+
+impl Rfc3161<Canned> {
+    /// A stand-in authority which always replies with `reply`, obtained
+    /// beforehand for a request without a nonce
+    pub fn canned(reply: Vec<u8>) -> Self {
+        Rfc3161 { transport: Canned(reply), use_nonce: false, service: None }
+    }
+}
+
+impl<T: Transport> TimestampProvider for Rfc3161<T> {
+    fn submit(&self, digest: &BundleHash) -> Result<Submission, Error> {
+        let nonce = if self.use_nonce {
+            let mut bytes = [0; 8];
+            getrandom::getrandom(&mut bytes).map_err(|e| Error::Transport(format!("generating nonce: {}", e)))?;
+            Some(u64::from_be_bytes(bytes))
+        } else {
+            None
+        };
+        let reply = self.transport.send(&request(digest, nonce)).map_err(Error::Transport)?;
+        Ok(Submission { digest: *digest, nonce, reply })
+    }
+
+    fn retrieve(&self, submission: &Submission) -> Result<Attestation, Error> {
+        let token = parse_reply(&submission.reply)?;
+        let info = parse_token(token)?;
+        if info.digest != submission.digest.into_inner() {
+            return Err(Error::WrongDigest);
+        }
+        // A nonce we did not ask for is harmless, but one we did ask for must
+        // come back unchanged
+        if submission.nonce.is_some() && info.nonce != submission.nonce {
+            return Err(Error::WrongNonce);
+        }
+        Ok(Attestation { kind: KIND_RFC3161.to_owned(), token: token.to_hex(), service: self.service.clone() })
+    }
+}
+
+/// Encode one DER element
+fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+    let mut ret = vec![tag];
+    let len = contents.len();
+    if len < 0x80 {
+        ret.push(len as u8);
+    } else {
+        let bytes = len.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&b| b == 0).count();
+        ret.push(0x80 | (bytes.len() - skip) as u8);
+        ret.extend_from_slice(&bytes[skip..]);
+    }
+    ret.extend_from_slice(contents);
+    ret
+}
+
+/// Encode a non-negative DER integer
+fn der_uint(n: u64) -> Vec<u8> {
+    let bytes = n.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count().min(7);
+    let mut contents = vec![];
+    if bytes[skip] & 0x80 != 0 {
+        contents.push(0);
+    }
+    contents.extend_from_slice(&bytes[skip..]);
+    der(INTEGER, &contents)
+}
+
+/// Encode an RFC 3161 `TimeStampReq` for a digest, asking for the
+/// authority's certificate to be included in the token
+pub fn request(digest: &BundleHash, nonce: Option<u64>) -> Vec<u8> {
+    let algorithm = der(SEQUENCE, &[der(OID, OID_SHA256), der(NULL, &[])].concat());
+    let imprint = der(SEQUENCE, &[algorithm, der(OCTET_STRING, &digest[..])].concat());
+    let mut contents = [der_uint(1), imprint].concat();
+    if let Some(nonce) = nonce {
+        contents.extend(der_uint(nonce));
+    }
+    contents.extend(der(BOOLEAN, &[0xff]));
+    der(SEQUENCE, &contents)
+}
+
+/// Reads consecutive DER elements
+struct Der<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Der<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Der { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn peek_tag(&self) -> Option<u8> {
+        self.data.first().copied()
+    }
+
+    /// Read the next element, returning its tag, its contents, and its whole
+    /// encoding
+    fn read(&mut self) -> Result<(u8, &'a [u8], &'a [u8]), Error> {
+        let truncated = Error::Malformed("truncated");
+        let (&tag, rest) = self.data.split_first().ok_or(truncated.clone())?;
+        let (&first, rest) = rest.split_first().ok_or(truncated.clone())?;
+        let (len, rest) = if first < 0x80 {
+            (usize::from(first), rest)
+        } else {
+            let n = usize::from(first & 0x7f);
+            if n == 0 || n > 4 || rest.len() < n {
+                return Err(Error::Malformed("bad length"));
+            }
+            let len = rest[..n].iter().fold(0, |acc, &b| (acc << 8) | usize::from(b));
+            (len, &rest[n..])
+        };
+        if rest.len() < len {
+            return Err(truncated);
+        }
+        let header = self.data.len() - rest.len();
+        let whole = &self.data[..header + len];
+        self.data = &rest[len..];
+        Ok((tag, &rest[..len], whole))
+    }
+
+    /// Read the next element, which must have the given tag
+    fn expect(&mut self, tag: u8, what: &'static str) -> Result<&'a [u8], Error> {
+        match self.read()? {
+            (t, contents, _) if t == tag => Ok(contents),
+            _ => Err(Error::Malformed(what)),
+        }
+    }
+
+    /// Read the next element if it has the given tag
+    fn optional(&mut self, tag: u8) -> Result<Option<&'a [u8]>, Error> {
+        if self.peek_tag() == Some(tag) {
+            self.read().map(|(_, contents, _)| Some(contents))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Decode a non-negative integer which fits in 64 bits
+fn read_uint(contents: &[u8]) -> Option<u64> {
+    let contents = match contents {
+        [0, rest @ ..] => rest,
+        [first, ..] if first & 0x80 != 0 => return None,
+        _ => contents,
+    };
+    if contents.len() > 8 {
+        return None;
+    }
+    Some(contents.iter().fold(0, |acc, &b| (acc << 8) | u64::from(b)))
+}
+
+/// Extract the token from an RFC 3161 `TimeStampResp`
+fn parse_reply(reply: &[u8]) -> Result<&[u8], Error> {
+    let mut outer = Der::new(reply);
+    let mut resp = Der::new(outer.expect(SEQUENCE, "reply is not a TimeStampResp")?);
+    let mut status_info = Der::new(resp.expect(SEQUENCE, "reply has no status")?);
+    let status = read_uint(status_info.expect(INTEGER, "reply has no status")?).ok_or(Error::Malformed("bad status"))?;
+    // 0 is granted, and 1 granted with modifications
+    if status > 1 {
+        let text = match status_info.optional(SEQUENCE)? {
+            Some(strings) => {
+                let mut strings = Der::new(strings);
+                let mut text = vec![];
+                while !strings.is_empty() {
+                    text.push(String::from_utf8_lossy(strings.expect(UTF8_STRING, "bad status text")?).into_owned());
+                }
+                Some(text.join("; "))
+            }
+            None => None,
+        };
+        return Err(Error::Rejected { status, text });
+    }
+    match resp.read() {
+        Ok((SEQUENCE, _, token)) => Ok(token),
+        _ => Err(Error::Malformed("reply has no token")),
+    }
+}
+
+/// What a time-stamp token attests to
+struct TstInfo {
+    digest: [u8; 32],
+    time: u64,
+    nonce: Option<u64>,
+}
+
+/// Parse a `GeneralizedTime` such as `20240102030405Z` or
+/// `20240102030405.123Z`, dropping any fraction of a second
+fn parse_time(contents: &[u8]) -> Result<u64, Error> {
+    let bad = Error::Malformed("bad time");
+    let s = std::str::from_utf8(contents).map_err(|_| bad.clone())?;
+    if s.len() < 15 || !s.ends_with('Z') || !s.is_char_boundary(14) {
+        return Err(bad);
+    }
+    let (digits, fraction) = s[..s.len() - 1].split_at(14);
+    if !(fraction.is_empty() || (fraction.starts_with('.') && fraction[1..].bytes().all(|b| b.is_ascii_digit()))) {
+        return Err(bad);
+    }
+    let rfc3339 = format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &digits[0..4],
+        &digits[4..6],
+        &digits[6..8],
+        &digits[8..10],
+        &digits[10..12],
+        &digits[12..14],
+    );
+    attributes::parse_time(&rfc3339, 0).map_err(|_| bad)
+}
+
+/// Parse a `TimeStampToken`, a CMS `ContentInfo` holding a `TSTInfo`
+fn parse_token(token: &[u8]) -> Result<TstInfo, Error> {
+    let mut outer = Der::new(token);
+    let mut content_info = Der::new(outer.expect(SEQUENCE, "token is not a ContentInfo")?);
+    if !outer.is_empty() {
+        return Err(Error::Malformed("trailing data after token"));
+    }
+    if content_info.expect(OID, "token has no content type")? != OID_SIGNED_DATA {
+        return Err(Error::Malformed("token is not signed data"));
+    }
+    let mut content = Der::new(content_info.expect(EXPLICIT_0, "token has no content")?);
+    let mut signed_data = Der::new(content.expect(SEQUENCE, "token has no signed data")?);
+    signed_data.expect(INTEGER, "signed data has no version")?;
+    signed_data.expect(SET, "signed data has no digest algorithms")?;
+    let mut encap = Der::new(signed_data.expect(SEQUENCE, "signed data has no content")?);
+    if encap.expect(OID, "signed data has no content type")? != OID_TST_INFO {
+        return Err(Error::Malformed("signed data is not a TSTInfo"));
+    }
+    let mut explicit = Der::new(encap.expect(EXPLICIT_0, "signed data has no TSTInfo")?);
+    let mut info = Der::new(explicit.expect(OCTET_STRING, "signed data has no TSTInfo")?);
+    let mut info = Der::new(info.expect(SEQUENCE, "TSTInfo is not a sequence")?);
+
+    info.expect(INTEGER, "TSTInfo has no version")?;
+    info.expect(OID, "TSTInfo has no policy")?;
+    let mut imprint = Der::new(info.expect(SEQUENCE, "TSTInfo has no message imprint")?);
+    let mut algorithm = Der::new(imprint.expect(SEQUENCE, "message imprint has no algorithm")?);
+    if algorithm.expect(OID, "message imprint has no algorithm")? != OID_SHA256 {
+        return Err(Error::UnsupportedHash);
+    }
+    let digest = imprint.expect(OCTET_STRING, "message imprint has no digest")?;
+    let digest = <[u8; 32]>::try_from(digest).map_err(|_| Error::Malformed("digest is not 32 bytes"))?;
+    info.expect(INTEGER, "TSTInfo has no serial number")?;
+    let time = parse_time(info.expect(GENERALIZED_TIME, "TSTInfo has no time")?)?;
+    info.optional(SEQUENCE)?; // accuracy
+    info.optional(BOOLEAN)?; // ordering
+    let nonce = match info.optional(INTEGER)? {
+        Some(nonce) => Some(read_uint(nonce).ok_or(Error::WrongNonce)?),
+        None => None,
+    };
+    Ok(TstInfo { digest, time, nonce })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A reply from `openssl ts -reply` to a request, without a nonce, for
+    /// [`DIGEST`]
+    const REPLY: &[u8] = include_bytes!("../testdata/tsa-reply.tsr");
+    /// The bundle hash of `testdata/timestamp-bundle.json`
+    const DIGEST: &str = "67e98e934acbbc963b7506bd1aa8920bb5d544def73033a110606f92d8bc23fb";
+    /// The time in [`REPLY`]
+    const TIME: &str = "2026-10-16T15:45:12Z";
+
+    fn digest() -> BundleHash {
+        BundleHash::from_hex(DIGEST).unwrap()
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(der_uint(0), [0x02, 0x01, 0x00]);
+        assert_eq!(der_uint(0x80), [0x02, 0x02, 0x00, 0x80]);
+        assert_eq!(der_uint(u64::MAX)[..3], [0x02, 0x09, 0x00]);
+        for n in [0, 1, 0x7f, 0x80, 0x1234, u64::MAX] {
+            let encoded = der_uint(n);
+            let mut reader = Der::new(&encoded);
+            assert_eq!(read_uint(reader.expect(INTEGER, "").unwrap()), Some(n));
+        }
+        let long = der(OCTET_STRING, &[7; 300]);
+        assert_eq!(long[..4], [0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(Der::new(&long).expect(OCTET_STRING, "").unwrap(), &[7; 300][..]);
+
+        // Matches `openssl ts -query -sha256 -cert -no_nonce`
+        assert_eq!(
+            request(&digest(), None).to_hex(),
+            format!("30390201013031300d060960864801650304020105000420{}0101ff", DIGEST),
+        );
+    }
+
+    #[test]
+    fn canned_reply() {
+        let tsa = Rfc3161::canned(REPLY.to_vec());
+        let attestation = timestamp(&tsa, &digest()).unwrap();
+        assert_eq!(attestation.kind, KIND_RFC3161);
+        let time = tsa.verify(&digest(), &attestation).unwrap();
+        assert_eq!(attributes::format_time(time), TIME);
+
+        let other = BundleHash::hash(b"something else");
+        assert_eq!(timestamp(&tsa, &other), Err(Error::WrongDigest));
+        assert_eq!(verify(&other, &attestation), Err(Error::WrongDigest));
+
+        // The canned reply has no nonce, so a request with one is refused
+        let tsa = Rfc3161::new(Canned(REPLY.to_vec()));
+        assert_eq!(timestamp(&tsa, &digest()), Err(Error::WrongNonce));
+    }
+
+    #[test]
+    fn bad_replies() {
+        // status rejection (2), with a reason
+        let rejection = [0x30, 0x0b, 0x30, 0x09, 0x02, 0x01, 0x02, 0x30, 0x04, 0x0c, 0x02, b'n', b'o'];
+        let tsa = Rfc3161::canned(rejection.to_vec());
+        assert_eq!(timestamp(&tsa, &digest()), Err(Error::Rejected { status: 2, text: Some("no".into()) }));
+
+        let tsa = Rfc3161::canned(REPLY[..REPLY.len() - 1].to_vec());
+        assert!(matches!(timestamp(&tsa, &digest()), Err(Error::Malformed(_))));
+
+        let failing = Rfc3161::new(|_: &[u8]| Err("connection refused".to_owned()));
+        assert_eq!(timestamp(&failing, &digest()), Err(Error::Transport("connection refused".into())));
+
+        let attestation = Attestation { kind: "ots".into(), token: "".into(), service: None };
+        assert_eq!(verify(&digest(), &attestation), Err(Error::UnknownKind("ots".into())));
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time(b"19700101000000Z"), Ok(0));
+        assert_eq!(parse_time(b"20240229123456.789Z"), parse_time(b"20240229123456Z"));
+        assert!(parse_time(b"20240230123456Z").is_err());
+        assert!(parse_time(b"20240229123456").is_err());
+        assert!(parse_time(b"2024022912345Z").is_err());
+    }
+}
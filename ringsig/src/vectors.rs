@@ -0,0 +1,284 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Test Vectors for Other Implementations
+//!
+//! Any other implementation of proving and verifying, such as one in
+//! JavaScript, must agree with this one byte for byte. This makes a set of
+//! vectors which it can check itself against: rings of 1, 2, 5 and 20 keys,
+//! listed unsorted, with empty, ASCII, non-ASCII and binary messages, and
+//! for each the params hash, the exact proof and whether it verifies. A
+//! few more are proofs which must not verify, each with the
+//! [`VerifyError::code`] a verifier gives.
+//!
+//! Proofs are made with [`prove_with_aux_rand`], so everything is the same
+//! every time. `cargo run --features testkit --bin gen-vectors` writes them to
+//! testdata/vectors/vectors.json, and a test checks that the file is what
+//! this makes and that every vector in it still holds. Keys, messages and proofs are all hex, keys
+//! as serialized and secret keys as the scalar used to prove.
+//!
+//! The keys come from [`crate::testkit`], so this needs the `testkit`
+//! feature. **These keys are not secret.**
+
+use bitcoin_hashes::hex::{FromHex, ToHex};
+use bitcoin_hashes::{sha256, Hash};
+use curve25519_dalek::constants;
+use serde::{Deserialize, Serialize};
+
+use crate::keys::{PublicKey, SecretKey};
+use crate::testkit;
+use crate::{params_hash, prove_with_aux_rand, verify, Proof, VerifyError};
+
+/// The version of the vectors file, to be bumped if its layout changes
+pub const VECTORS_VERSION: usize = 1;
+
+/// The seed of the keys used in the vectors
+const SEED: &[u8] = b"ringsig test vectors";
+
+/// The ring sizes, each with the index of its signer in the listed order
+const RINGS: &[(usize, usize)] = &[(1, 0), (2, 1), (5, 3), (20, 11)];
+
+/// The messages proven over every ring, with a description of each
+const MESSAGES: &[(&str, &[u8])] = &[
+    ("an empty message", b""),
+    ("an ASCII message", b"Hello, world!"),
+    ("a non-ASCII message", "ünïcödé ✓ 日本語\r\n".as_bytes()),
+    ("a binary message", &[0x00, 0xff, 0x80, 0x7f, 0x0a, 0x00]),
+];
+
+/// A file of test vectors
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Vectors {
+    /// Always [`VECTORS_VERSION`]
+    pub version: usize,
+    pub vectors: Vec<Vector>,
+}
+
+/// A proof over a ring, and what verifying it gives
+#[derive(Clone, PartialEq, Eq, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct Vector {
+    pub description: String,
+    /// The ring, in the order given to the prover or verifier
+    pub public_keys: Vec<String>,
+    /// The signer's secret key, for proofs which can be made again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_key: Option<String>,
+    /// The randomness the proof was made with, for proofs which can be
+    /// made again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aux_rand: Option<String>,
+    pub message: String,
+    /// The params hash, or `None` if the ring has none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params_hash: Option<String>,
+    pub proof: String,
+    /// `ok`, or the code of the error verifying gives
+    pub result: String,
+}
+
+impl Vectors {
+    /// Parse a vectors file
+    pub fn from_json_str(s: &str) -> Result<Self, String> {
+        let ret: Vectors = serde_json::from_str(s).map_err(|e| e.to_string())?;
+        if ret.version != VECTORS_VERSION {
+            return Err(format!("unknown vectors version {}", ret.version));
+        }
+        Ok(ret)
+    }
+
+    /// Output as JSON, a vector to a line so that changes diff well
+    pub fn to_json_string(&self) -> String {
+        let vectors: Vec<String> =
+            self.vectors.iter().map(|v| serde_json::to_string(v).expect("serializing JSON")).collect();
+        format!("{{\"version\":{},\"vectors\":[\n{}\n]}}\n", self.version, vectors.join(",\n"))
+    }
+
+    /// Check every vector against this implementation, failing on the first
+    /// which does not hold
+    pub fn check(&self) -> Result<(), String> {
+        for (index, vector) in self.vectors.iter().enumerate() {
+            vector.check().map_err(|e| format!("vector {} ({}): {}", index, vector.description, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl Vector {
+    /// Check that the params hash, verification result and, where it can
+    /// be made again, the proof, are as this implementation has them
+    pub fn check(&self) -> Result<(), String> {
+        let pks = self
+            .public_keys
+            .iter()
+            .map(|hex| PublicKey::parse(&unhex(hex)?).map_err(|e| format!("key {}: {}", hex, e)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let message = unhex(&self.message)?;
+        let proof = Proof::from_bytes(unhex(&self.proof)?).map_err(|e| e.to_string())?;
+
+        let hash = params_hash(&pks, &message).ok().map(|hash| hash.to_hex());
+        if hash != self.params_hash {
+            return Err(format!("params hash is {:?}, not {:?}", hash, self.params_hash));
+        }
+        let result = result_of(verify(&proof, &pks, &message));
+        if result != self.result {
+            return Err(format!("verifying gives {}, not {}", result, self.result));
+        }
+        if let (Some(sk), Some(aux)) = (&self.secret_key, &self.aux_rand) {
+            let sk = SecretKey::from_bytes(unhex_32(sk)?);
+            let proven = prove_with_aux_rand(&pks, &message, &sk, &unhex_32(aux)?).map_err(|e| e.to_string())?;
+            if proven.to_hex() != self.proof {
+                return Err(format!("proving gives {}", proven.to_hex()));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn unhex(hex: &str) -> Result<Vec<u8>, String> {
+    Vec::from_hex(hex).map_err(|e| format!("bad hex {}: {}", hex, e))
+}
+
+fn unhex_32(hex: &str) -> Result<[u8; 32], String> {
+    <[u8; 32]>::from_hex(hex).map_err(|e| format!("bad hex {}: {}", hex, e))
+}
+
+fn result_of(result: Result<(), VerifyError>) -> String {
+    match result {
+        Ok(()) => "ok".to_owned(),
+        Err(e) => e.code().to_owned(),
+    }
+}
+
+/// A vector of `proof` over `pks`, which does not say how to make it again
+fn unprovable(description: &str, pks: &[PublicKey], message: &[u8], proof: Vec<u8>) -> Vector {
+    let proof = Proof::from_bytes(proof).expect("a whole proof");
+    Vector {
+        description: description.to_owned(),
+        public_keys: pks.iter().map(|pk| pk.serialize().to_hex()).collect(),
+        secret_key: None,
+        aux_rand: None,
+        message: message.to_hex(),
+        params_hash: params_hash(pks, message).ok().map(|hash| hash.to_hex()),
+        proof: proof.to_hex(),
+        result: result_of(verify(&proof, pks, message)),
+    }
+}
+
+/// Make the vectors, which are the same every time
+pub fn generate() -> Vectors {
+    let keypairs = testkit::deterministic_keypairs(SEED, RINGS.iter().map(|&(size, _)| size).max().unwrap_or(0));
+    let mut vectors = vec![];
+    for &(size, signer) in RINGS {
+        let pks: Vec<PublicKey> = keypairs[..size].iter().map(|keypair| keypair.public).collect();
+        let sk = keypairs[signer].secret.secret_key();
+        for (what, message) in MESSAGES {
+            let description = format!("{} over a ring of {} by key {}", what, size, signer);
+            let aux = sha256::Hash::hash(description.as_bytes()).into_inner();
+            let proof = prove_with_aux_rand(&pks, message, &sk, &aux).expect("the signer is in the ring");
+            vectors.push(Vector {
+                public_keys: pks.iter().map(|pk| pk.serialize().to_hex()).collect(),
+                secret_key: Some(sk.as_bytes().to_hex()),
+                aux_rand: Some(aux.to_hex()),
+                message: message.to_hex(),
+                params_hash: Some(params_hash(&pks, message).expect("a usable ring").to_hex()),
+                proof: proof.to_hex(),
+                result: result_of(verify(&proof, &pks, message)),
+                description,
+            });
+        }
+    }
+
+    // Variations on a proof of the ASCII message over the ring of 5
+    let base = vectors.iter().find(|v| v.public_keys.len() == 5 && v.message == b"Hello, world!".to_hex());
+    let base = base.expect("a ring of 5");
+    let pks: Vec<PublicKey> = keypairs[..5].iter().map(|keypair| keypair.public).collect();
+    let proof = Vec::from_hex(&base.proof).expect("hex");
+
+    let reversed: Vec<PublicKey> = pks.iter().rev().copied().collect();
+    vectors.push(unprovable("the ring listed in reverse", &reversed, b"Hello, world!", proof.clone()));
+    vectors.push(unprovable("another message", &pks, b"Hello, world?", proof.clone()));
+    let mut flipped = proof.clone();
+    flipped[0] ^= 1;
+    vectors.push(unprovable("a bit of the challenge flipped", &pks, b"Hello, world!", flipped));
+    let mut malleated = proof.clone();
+    let mut carry = 0u16;
+    for (byte, l_byte) in malleated[64..96].iter_mut().zip(constants::BASEPOINT_ORDER.to_bytes()) {
+        let sum = u16::from(*byte) + u16::from(l_byte) + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    vectors.push(unprovable("the group order added to an s value", &pks, b"Hello, world!", malleated));
+    vectors.push(unprovable("a key left out of the ring", &pks[..4], b"Hello, world!", proof.clone()));
+    let repeated = [&pks[..], &pks[..1]].concat();
+    vectors.push(unprovable("a key listed twice", &repeated, b"Hello, world!", proof));
+
+    Vectors { version: VECTORS_VERSION, vectors }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The committed vectors, which other implementations check against
+    const FILE: &str = include_str!("../testdata/vectors/vectors.json");
+
+    #[test]
+    fn committed() {
+        let vectors = Vectors::from_json_str(FILE).unwrap();
+        vectors.check().unwrap();
+        // Making them again gives the same file, so nothing about them, from
+        // the key derivation to the proving randomness, has moved
+        assert_eq!(generate(), vectors, "run `cargo run --features testkit --bin gen-vectors` only if the change is deliberate");
+        assert_eq!(vectors.to_json_string(), FILE);
+    }
+
+    #[test]
+    fn coverage() {
+        let vectors = generate();
+        let results: Vec<&str> = vectors.vectors.iter().map(|v| v.result.as_str()).collect();
+        assert_eq!(results.iter().filter(|&&r| r == "ok").count(), RINGS.len() * MESSAGES.len() + 2);
+        // An unreduced s value is accepted, as proofs by older versions have them
+        assert_eq!(
+            results[RINGS.len() * MESSAGES.len()..],
+            ["ok", "bad_proof", "bad_proof", "ok", "proof_length", "duplicate_key"],
+        );
+        let sizes: Vec<usize> = vectors.vectors.iter().map(|v| v.public_keys.len()).collect();
+        assert!([1, 2, 5, 20].iter().all(|size| sizes.contains(size)));
+    }
+
+    #[test]
+    fn catches_changes() {
+        let mut vectors = generate();
+        vectors.vectors[3].aux_rand = Some([1; 32].to_hex());
+        assert!(vectors.check().unwrap_err().starts_with("vector 3 (a binary message over a ring of 1 by key 0): proving gives"));
+
+        let mut vectors = generate();
+        vectors.vectors[6].message = "00".into();
+        assert!(vectors.check().unwrap_err().contains("params hash"));
+
+        let mut vectors = generate();
+        let last = vectors.vectors.len() - 1;
+        vectors.vectors[last].result = "ok".into();
+        assert!(vectors.check().unwrap_err().ends_with("verifying gives duplicate_key, not ok"));
+
+        assert_eq!(
+            Vectors::from_json_str(r#"{"version":2,"vectors":[]}"#),
+            Err("unknown vectors version 2".to_owned()),
+        );
+    }
+}
@@ -18,25 +18,66 @@ use wasm_bindgen::prelude::*;
 
 use bitcoin_hashes::hex::{FromHex, ToHex};
 use crate::armor::FromArmor;
-use crate::keys::{PublicKey, SecretKey};
+use crate::bundle::{self, Confession, MultiBundle};
+use crate::keys::{Error as KeyError, PublicKey, SecretKey};
+use crate::{Proof, ProofError, ProveError, VerifyError};
+use std::ops::ControlFlow;
+
+/// How many keys [`verify_with_progress`] checks between calls to its
+/// callback; calling into JavaScript for every key would slow it down
+pub const PROGRESS_INTERVAL: usize = 64;
+
+/// Describe a proving error to someone using the web page
+fn prove_message(e: ProveError) -> String {
+    match e {
+        ProveError::EmptyKeySet => "there are no public keys in the ring".to_owned(),
+        ProveError::KeyNotInRing { sk_public } => format!(
+            "your secret key, whose public key is {}, does not match any of the public keys in the ring",
+            sk_public.fingerprint(),
+        ),
+        e => e.to_string(),
+    }
+}
+
+/// Describe a verifying error to someone using the web page
+fn verify_message(e: VerifyError) -> String {
+    match e {
+        VerifyError::EmptyKeySet => "there are no public keys in the ring".to_owned(),
+        VerifyError::WrongProofLength { expected, proof_ring_size: Some(size), .. } => format!(
+            "the proof was made for a ring of {} keys, but this ring has {}",
+            size,
+            expected / 32 - 1,
+        ),
+        VerifyError::WrongProofLength { expected, got, proof_ring_size: None } => format!(
+            "the proof is {} hex characters long, but a proof for this ring is {}; it may have been cut short",
+            2 * got,
+            2 * expected,
+        ),
+        VerifyError::Malformed(ProofError::InvalidHex(_)) => "the proof is not valid hex".to_owned(),
+        VerifyError::BadProof => "the proof is not valid for this message and ring".to_owned(),
+        VerifyError::Cancelled => "verification was cancelled".to_owned(),
+        e => e.to_string(),
+    }
+}
+
+/// Parse the ring's SSH public key lines
+fn parse_keys(pks: &[String]) -> Result<Vec<PublicKey>, String> {
+    pks.iter().map(|key| key.parse()).collect::<Result<Vec<_>, _>>().map_err(|e: KeyError| e.to_string())
+}
 
 pub fn prove_internal(
     pks: &[String],
     msg: &str,
     sk: &str,
 ) -> Result<String, String> {
-    let pks = pks
-        .iter()
-        .map(|key| PublicKey::parse_pk_line(key))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("{:?}", e))?; // FIXME don't use debug
+    let pks = parse_keys(pks)?;
 
     let sk = SecretKey::from_armor(sk)
-        .map_err(|e| format!("{:?}", e))?; // FIXME don't use debug
+        .map_err(|e| e.to_string())?;
 
-    match crate::prove(&pks, msg.as_bytes(), sk) {
-        Ok(proof) => Ok(proof.to_hex()),
-        Err(e) => Err(format!("{:?}", e)), // FIXME don't use debug
+    match crate::prove(&pks, msg.as_bytes(), &sk) {
+        Ok(proof) => Ok(proof.to_string()),
+        Err(e) => Err(prove_message(e)),
     }
 }
 
@@ -51,7 +92,7 @@ pub fn prove(
         .iter()
         .map(|v| v.as_string().unwrap_or("js unknown".to_owned()))
         .collect();
-    let ret = js_sys::Array::new(); // FIXME this doesn't need to be mutable?? how does push work??
+    let ret = js_sys::Array::new();
     match prove_internal(&pks_rust, msg, sk) {
         Ok(s) => {
             ret.push(&JsValue::from_str(&s));
@@ -70,17 +111,61 @@ pub fn verify_internal(
     pks: &[String],
     msg: &str,
 ) -> Result<(), String> {
-    let pks = pks
-        .iter()
-        .map(|key| PublicKey::parse_pk_line(key))
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("{:?}", e))?; // FIXME don't use debug
+    verify_cancellable_internal(proof, pks, msg, |_, _| ControlFlow::Continue(()))
+}
 
-    let proof = Vec::<u8>::from_hex(proof)
-        .map_err(|e| e.to_string())?;
+/// Verify as [`verify_internal`] does, calling `progress` as
+/// [`crate::verify_cancellable`] does
+pub fn verify_cancellable_internal<F: FnMut(usize, usize) -> ControlFlow<()>>(
+    proof: &str,
+    pks: &[String],
+    msg: &str,
+    progress: F,
+) -> Result<(), String> {
+    let pks = parse_keys(pks)?;
 
-    crate::verify(&proof, &pks, msg.as_bytes())
-        .map_err(|e| e.to_owned())
+    let proof: Proof = proof.parse()
+        .map_err(|e: ProofError| verify_message(e.into()))?;
+
+    crate::verify_cancellable(&proof, &pks, msg.as_bytes(), progress)
+        .map_err(verify_message)
+}
+
+/// The ring id and params hash of a ring and message, as hex; see
+/// [`crate::ring_id`] and [`crate::params_hash`]
+pub fn ring_ids_internal(pks: &[String], msg: &str) -> Result<(String, String), String> {
+    let pks = parse_keys(pks)?;
+
+    let params_hash = crate::params_hash(&pks, msg.as_bytes()).map_err(verify_message)?;
+    Ok((crate::ring_id(&pks).to_hex(), params_hash.to_hex()))
+}
+
+/// Identifies a ring and message, so that the page can show them for
+/// comparing with another verifier's. Returns `[ringId, paramsHash, error]`,
+/// with the error empty, or the ids empty if there was one.
+#[wasm_bindgen]
+pub fn ring_ids(
+    pks: js_sys::Array,
+    msg: &str,
+) -> js_sys::Array {
+    let pks_rust: Vec<String> = pks
+        .iter()
+        .map(|v| v.as_string().unwrap_or("js unknown".to_owned()))
+        .collect();
+    let ret = js_sys::Array::new();
+    match ring_ids_internal(&pks_rust, msg) {
+        Ok((ring_id, params_hash)) => {
+            ret.push(&JsValue::from_str(&ring_id));
+            ret.push(&JsValue::from_str(&params_hash));
+            ret.push(&JsValue::from_str(""));
+        },
+        Err(e) => {
+            ret.push(&JsValue::from_str(""));
+            ret.push(&JsValue::from_str(""));
+            ret.push(&JsValue::from_str(&e));
+        },
+    }
+    ret
 }
 
 /// Verifies a proof. Returns an error string. If the proof is good, returns the empty string.
@@ -101,6 +186,110 @@ pub fn verify(
     }
 }
 
+/// Verifies a proof as `verify` does, calling `progress(done, total)` every
+/// [`PROGRESS_INTERVAL`] keys and at the end, so that the page can show a
+/// progress bar for a large ring. If `progress` returns `false` or throws,
+/// verification stops and the error says it was cancelled.
+#[wasm_bindgen]
+pub fn verify_with_progress(
+    proof: &str,
+    pks: js_sys::Array,
+    msg: &str,
+    progress: &js_sys::Function,
+) -> String {
+    let pks_rust: Vec<String> = pks
+        .iter()
+        .map(|v| v.as_string().unwrap_or("js unknown".to_owned()))
+        .collect();
+    let update = |done: usize, total: usize| {
+        if done != total && !done.is_multiple_of(PROGRESS_INTERVAL) {
+            return ControlFlow::Continue(());
+        }
+        match progress.call2(&JsValue::NULL, &JsValue::from(done), &JsValue::from(total)) {
+            Ok(ret) if ret.as_bool() == Some(false) => ControlFlow::Break(()),
+            Ok(_) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }
+    };
+
+    match verify_cancellable_internal(proof, &pks_rust, msg, update) {
+        Ok(()) => "".to_owned(),
+        Err(e) => e,
+    }
+}
+
+/// Parses a version 2 bundle, or a version 1 confession as a bundle of one
+fn parse_bundle(json: &str) -> Result<MultiBundle, bundle::Error> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|e| bundle::Error::Json(e.to_string()))?;
+    if value.get("version") == Some(&serde_json::json!(bundle::MULTI_VERSION)) {
+        MultiBundle::from_json_value(value)
+    } else {
+        Confession::from_json_value(value)?.into_bundle()
+    }
+}
+
+/// Verifies a version 2 bundle of several proofs, or a version 1 confession.
+/// Returns a JSON array with, for each entry, its `context` if any and an
+/// `error` string which is empty if the entry verified. If the bundle itself
+/// is malformed, returns the error as a plain (non-JSON) string.
+#[wasm_bindgen]
+pub fn verify_bundle(json: &str) -> String {
+    let bundle = match parse_bundle(json) {
+        Ok(bundle) => bundle,
+        Err(e) => return e.to_string(),
+    };
+    let verdicts: Vec<_> = bundle
+        .verify()
+        .into_iter()
+        .map(|verdict| serde_json::json!({
+            "context": verdict.context,
+            "error": verdict.result.err().map(|e| e.to_string()).unwrap_or_default(),
+        }))
+        .collect();
+    serde_json::Value::from(verdicts).to_string()
+}
+
+/// Checks the fields of a version 1 confession without verifying its proof.
+/// Returns a JSON array of `{"path": ..., "message": ...}` objects, empty if
+/// nothing is wrong. If the confession does not parse, returns the error as a
+/// plain (non-JSON) string.
+#[wasm_bindgen]
+pub fn validate_confession(json: &str) -> String {
+    let confession = match Confession::from_json_str(json) {
+        Ok(confession) => confession,
+        Err(e) => return e.to_string(),
+    };
+    let problems: Vec<_> = confession
+        .validate()
+        .into_iter()
+        .map(|problem| serde_json::json!({ "path": problem.path, "message": problem.message }))
+        .collect();
+    serde_json::Value::from(problems).to_string()
+}
+
+/// Checks a confession (`version` 1) or bundle (`version` 2) against its JSON
+/// Schema, and then its fields as `validate_confession` does. Returns the
+/// problems in the same form as `validate_confession`.
+#[wasm_bindgen]
+pub fn validate_bundle(json: &str, version: usize) -> String {
+    let problems = match bundle::validate(json, version) {
+        Ok(problems) => problems,
+        Err(e) => return e.to_string(),
+    };
+    let problems: Vec<_> = problems
+        .into_iter()
+        .map(|problem| serde_json::json!({ "path": problem.path, "message": problem.message }))
+        .collect();
+    serde_json::Value::from(problems).to_string()
+}
+
+/// The JSON Schema of confessions (`version` 1) or bundles (`version` 2), or
+/// an empty string for other versions
+#[wasm_bindgen]
+pub fn bundle_schema(version: usize) -> String {
+    bundle::schema_json(version).unwrap_or_default().to_owned()
+}
+
 #[wasm_bindgen]
 pub fn is_secret_key(data: &str) -> bool {
     SecretKey::from_armor(data).is_ok()
@@ -108,7 +297,18 @@ pub fn is_secret_key(data: &str) -> bool {
 
 #[wasm_bindgen]
 pub fn is_proof(data: &str) -> bool {
-    data.len() % 32 == 0 && Vec::<u8>::from_hex(data).is_ok()
+    data.len().is_multiple_of(32) && Vec::<u8>::from_hex(data).is_ok()
+}
+
+/// Parses a public key pasted in any format [`PublicKey::parse_any`]
+/// understands, returning it as an SSH public key line. Throws a message
+/// saying why each format failed if none did.
+#[wasm_bindgen]
+pub fn parse_any_pubkey(s: &str) -> Result<String, JsValue> {
+    match PublicKey::parse_any(s) {
+        Ok(pk) => Ok(pk.to_ssh_line(None)),
+        Err(e) => Err(JsValue::from_str(&e.to_string())),
+    }
 }
 
 #[wasm_bindgen]
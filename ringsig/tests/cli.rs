@@ -0,0 +1,1164 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! End-to-end tests which run the command-line tool
+
+use ringsig::armor::FromArmor;
+use ringsig::keys::PublicKey;
+use ringsig::sshsig::Signature;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+mod common;
+use common::{Scratch, RING};
+
+/// The public half of testdata/test-key.priv
+const TEST_PK: &str = RING[1];
+/// Two keys unrelated to the test key
+const OTHER_PKS: [&str; 2] = ["ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAyxLYuvSF14BJXejP+Qx6yLH1MXr/HGOcLZU55TodKS", RING[0]];
+
+impl Scratch {
+    /// Write a confession with the given ring, returning its path
+    fn confession(&self, ring: &[&str]) -> PathBuf {
+        let json = serde_json::json!({
+            "version": 1,
+            "publicKeys": ring,
+            "message": "this is an example text",
+        });
+        self.write("confession.json", &json.to_string())
+    }
+
+    /// Prove a confession over the given ring with the test key, returning
+    /// the output JSON
+    ///
+    /// The proof has no signed attributes, so converts to every format.
+    fn prove(&self, ring: &[&str]) -> String {
+        let file = self.confession(ring);
+        let out = self.run(&["prove", file.to_str().unwrap(), test_key().to_str().unwrap(), "--no-timestamp"]);
+        assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        stdout(&out)
+    }
+
+    /// Convert `input` with the given extra arguments, returning the output
+    fn convert(&self, input: &str, args: &[&str]) -> Output {
+        let path = self.write("convert-input", input);
+        self.run(&[&["convert", path.to_str().unwrap()], args].concat())
+    }
+}
+
+fn test_key() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/test-key.priv")
+}
+
+fn stdout(out: &Output) -> String {
+    String::from_utf8(out.stdout.clone()).unwrap()
+}
+
+#[test]
+fn dry_run_would_succeed() {
+    let scratch = Scratch::new("cli");
+    let file = scratch.confession(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]);
+    let key = test_key();
+    let args = [
+        "prove",
+        file.to_str().unwrap(),
+        key.to_str().unwrap(),
+        "--dry-run",
+    ];
+
+    let out = scratch.run(&args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let text = stdout(&out);
+    assert!(text.contains("would sign with SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus"), "{}", text);
+    assert!(text.contains("key is member 2 of ring 099be536a336dc8b (3 members)"), "{}", text);
+    assert!(text.contains("proving would succeed"), "{}", text);
+    assert!(!text.contains("proof"), "{}", text);
+
+    let out = scratch.run(&[&args[..], &["--format", "json"]].concat());
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let text = stdout(&out);
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(json["wouldSucceed"], true);
+    assert_eq!(json["position"], 1);
+    assert_eq!(json["ring"]["size"], 3);
+    assert_eq!(json["ring"]["id"], "099be536a336dc8b7725f15d3888f9148e782b6240086645099f93dbe840a367");
+    assert!(!text.contains("proof"), "{}", text);
+}
+
+#[test]
+fn dry_run_not_in_ring() {
+    let scratch = Scratch::new("cli");
+    let file = scratch.confession(&OTHER_PKS);
+    let key = test_key();
+    let args = [
+        "prove",
+        file.to_str().unwrap(),
+        key.to_str().unwrap(),
+        "--dry-run",
+        "--format",
+        "json",
+    ];
+
+    let out = scratch.run(&args);
+    assert_eq!(out.status.code(), Some(3), "{:?}", out);
+    let text = stdout(&out);
+    let json: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(json["wouldSucceed"], false);
+    assert_eq!(json["position"], serde_json::Value::Null);
+    assert!(!text.contains("proof"), "{}", text);
+    assert!(!String::from_utf8_lossy(&out.stderr).contains("proof"));
+
+    // A real proof attempt fails the same way
+    let out = scratch.run(&args[..3]);
+    assert_eq!(out.status.code(), Some(3), "{:?}", out);
+    assert!(stdout(&out).is_empty());
+}
+
+fn json(s: &str) -> serde_json::Value {
+    serde_json::from_str(s).unwrap()
+}
+
+#[test]
+fn convert_roundtrips() {
+    let scratch = Scratch::new("cli");
+    let original = scratch.prove(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]);
+    let convert = |input: &str, to: &str| {
+        let out = scratch.convert(input, &["--to", to]);
+        assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        stdout(&out)
+    };
+
+    // Formats which carry the whole confession convert losslessly
+    let full = ["json", "clearsign", "bundle"];
+    for from in full {
+        for to in full {
+            let a = convert(&original, from);
+            let b = convert(&a, to);
+            assert_eq!(json(&convert(&b, "json")), json(&original), "{} -> {}", from, to);
+            assert_eq!(convert(&b, from), a, "{} -> {} -> {}", from, to, from);
+        }
+    }
+
+    // As do the bare proof formats, between themselves
+    let hex = convert(&original, "hex");
+    assert_eq!(hex.trim(), json(&original)["proof"]);
+    for from in ["hex", "armor"] {
+        for to in ["hex", "armor"] {
+            let a = convert(&hex, from);
+            assert_eq!(convert(&convert(&a, to), from), a, "{} -> {}", from, to);
+        }
+    }
+}
+
+#[test]
+fn convert_needs_ring_and_message() {
+    let scratch = Scratch::new("cli");
+    let ring = [OTHER_PKS[0], TEST_PK, OTHER_PKS[1]];
+    let original = scratch.prove(&ring);
+    let armor = stdout(&scratch.convert(&original, &["--to", "armor"]));
+
+    let out = scratch.convert(&armor, &["--to", "clearsign"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--ring"), "{:?}", out);
+
+    let ring_file = scratch.write("ring.keys", &ring.join("\n"));
+    let message_file = scratch.write("message.txt", "this is an example text");
+    let ring_arg = ["--ring", ring_file.to_str().unwrap()];
+    let out = scratch.convert(&armor, &[&["--to", "clearsign"], &ring_arg[..]].concat());
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--message-file"), "{:?}", out);
+
+    let args = [&["--to", "json", "--message-file", message_file.to_str().unwrap()], &ring_arg[..]].concat();
+    let out = scratch.convert(&armor, &args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(json(&stdout(&out)), json(&original));
+
+    // Supplying a ring the input already has is an error, not an override
+    let out = scratch.convert(&original, &[&["--to", "bundle"], &ring_arg[..]].concat());
+    assert_eq!(out.status.code(), Some(1));
+}
+
+#[test]
+fn prove_armor() {
+    let scratch = Scratch::new("cli");
+    let ring = [OTHER_PKS[0], TEST_PK, OTHER_PKS[1]];
+    let file = scratch.confession(&ring);
+    let key = test_key();
+    let prove = |extra: &[&str]| scratch.run(&[&["prove", file.to_str().unwrap(), key.to_str().unwrap()], extra].concat());
+
+    let out = prove(&["--armor"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let armor = stdout(&out);
+    assert!(armor.starts_with("-----BEGIN CREDIBLE CONFESSION-----\n"), "{}", armor);
+    assert_eq!(ringsig::Proof::from_armor(&armor).unwrap().ring_size(), 3);
+
+    // It signs no timestamp, so the ring and message are all a verifier needs
+    let ring_file = scratch.write("ring.keys", &ring.join("\n"));
+    let message_file = scratch.write("message.txt", "this is an example text");
+    let args = ["--to", "json", "--ring", ring_file.to_str().unwrap(), "--message-file", message_file.to_str().unwrap()];
+    let out = scratch.convert(&armor, &args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let converted = scratch.write("converted.json", &stdout(&out));
+    let out = scratch.run(&["verify", converted.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+
+    for extra in [&["--linkable", "--scope", "s"][..], &["--timestamp"], &["--expires", "30d"]] {
+        let out = prove(&[&["--armor"], extra].concat());
+        assert_eq!(out.status.code(), Some(2), "{:?}", out);
+    }
+    let out = scratch.run(&["verify", converted.to_str().unwrap(), "--armor"]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn verify_shows_params_hash() {
+    let scratch = Scratch::new("cli");
+    let path = scratch.write("proven.json", &scratch.prove(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]));
+    // Fixed by the ring and message alone, whichever tool computes it
+    let expected = "3c0a10b53b663f5ee0ff6bbc2fd9309b990bb1b013e4edeb951e9d36cd4b2599";
+
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains(&format!("\nparams hash {}\n", expected)), "{}", stdout(&out));
+    let out = scratch.run(&["verify", path.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(json(&stdout(&out))["paramsHash"], expected);
+}
+
+#[test]
+fn verify_shows_comments() {
+    let scratch = Scratch::new("cli");
+    let path = scratch.write("proven.json", &scratch.prove(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]));
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains("\n  F+kgkEB9  apoelstra@sultana\n"), "{}", stdout(&out));
+    assert_eq!(stdout(&out).matches("  (no comment)\n").count(), 2, "{}", stdout(&out));
+    let out = scratch.run(&["verify", path.to_str().unwrap(), "--format", "json"]);
+    let report = json(&stdout(&out));
+    let comments: Vec<_> = report["ring"]["members"].as_array().unwrap().iter().map(|m| m["comment"].clone()).collect();
+    assert_eq!(comments, [serde_json::Value::Null, "apoelstra@sultana".into(), serde_json::Value::Null]);
+}
+
+#[test]
+fn verify_explain() {
+    let scratch = Scratch::new("cli");
+    let mut proven = json(&scratch.prove(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]));
+    let path = scratch.write("proven.json", &proven.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap(), "--explain"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(out.stderr.is_empty(), "{:?}", out);
+
+    proven["message"] = "a different message".into();
+    let path = scratch.write("changed.json", &proven.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert!(!String::from_utf8_lossy(&out.stderr).contains("challenge"), "{:?}", out);
+    let out = scratch.run(&["verify", path.to_str().unwrap(), "--explain"]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    let challenge = &proven["proof"].as_str().unwrap()[..64];
+    assert!(stderr.contains("ring of 3 keys; the proof is for a ring of 3 keys\n"), "{}", stderr);
+    assert!(stderr.contains(&format!("challenge in proof   {}\n", challenge)), "{}", stderr);
+    assert!(stderr.contains("challenge recomputed "), "{}", stderr);
+    assert!(stderr.contains("the chain of challenges does not close"), "{}", stderr);
+
+    let out = scratch.run(&["prove", "--explain"]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn verify_wrong_ring_size() {
+    let scratch = Scratch::new("cli");
+    let mut proven = json(&scratch.prove(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]));
+    proven["publicKeys"].as_array_mut().unwrap().remove(0);
+    let path = scratch.write("fewer.json", &proven.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("proof was created for a ring of 3 keys but 2 keys were provided"), "{}", stderr);
+}
+
+#[test]
+fn prove_versioned() {
+    let scratch = Scratch::new("cli");
+    let file = scratch.confession(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]);
+    let key = test_key();
+    let out = scratch.run(&["prove", file.to_str().unwrap(), key.to_str().unwrap(), "--versioned-proof"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let proven = json(&stdout(&out));
+    let proof = proven["proof"].as_str().unwrap();
+    assert_eq!(proof.len(), 2 * (1 + 32 * 4));
+    assert!(proof.starts_with("01"), "{}", proof);
+
+    let path = scratch.write("proven.json", &proven.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let out = scratch.run(&["verify", path.to_str().unwrap(), "--versioned-proof"]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn ring_management() {
+    let scratch = Scratch::new("cli");
+    let ring_file = scratch.write("ring.keys", "# our team\n");
+    let ring = ring_file.to_str().unwrap();
+
+    for line in [OTHER_PKS[0], TEST_PK, OTHER_PKS[1]] {
+        let out = scratch.run(&["ring", "add", line, "--ring", ring]);
+        assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        assert!(stdout(&out).starts_with("added SHA256:"), "{:?}", out);
+    }
+    // Adding a key again, even with a different comment, changes nothing
+    let before = fs::read_to_string(ring).unwrap();
+    let out = scratch.run(&["ring", "add", &format!("{} other comment", OTHER_PKS[0]), "--ring", ring]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).starts_with("already present: SHA256:"), "{:?}", out);
+    assert_eq!(fs::read_to_string(ring).unwrap(), before);
+
+    // Torsioned keys need --force
+    let torsion =
+        "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAII0PQoSjaDulROj7qwNNsJ1cCa+sqlWsKs3e8nemW9J+ apoelstra-torsion";
+    let out = scratch.run(&["ring", "add", torsion, "--ring", ring]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--force"), "{:?}", out);
+    assert_eq!(fs::read_to_string(ring).unwrap(), before);
+
+    let out = scratch.run(&["ring", "remove", "--fingerprint", "SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus", "--ring", ring]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(
+        fs::read_to_string(ring).unwrap(),
+        format!("# our team\n{}\n{}\n", OTHER_PKS[0], OTHER_PKS[1]),
+    );
+    let out = scratch.run(&["ring", "list", "--ring", ring]);
+    assert_eq!(stdout(&out).lines().count(), 2);
+
+    // The file is still a plain keys file usable as a ring
+    let file = scratch.confession(&[]);
+    let key = test_key();
+    let out = scratch.run(&["prove", file.to_str().unwrap(), key.to_str().unwrap(), "--ring", ring, "--dry-run"]);
+    assert_eq!(out.status.code(), Some(3), "{:?}", out);
+    assert!(stdout(&out).contains("(2 members)"), "{:?}", out);
+}
+
+#[test]
+fn import_known_hosts() {
+    let scratch = Scratch::new("cli");
+    let known_hosts = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/known_hosts");
+    let ring_file = scratch.dir.join("fleet.keys");
+    let import = |extra: &[&str]| {
+        let args = [&["ring", "import-known-hosts", known_hosts.to_str().unwrap(), "--ring", ring_file.to_str().unwrap()], extra];
+        let out = scratch.run(&args.concat());
+        assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        stdout(&out)
+    };
+
+    let out = import(&["--host-filter", "web*"]);
+    assert_eq!(
+        out,
+        "added SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus  web1.example.com,10.0.0.1\n\
+         already present: SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus  web3.example.com\n\
+         skipped line 4: not an ed25519 key (ssh-rsa web2.example.com)\n\
+         skipped line 5: hashed host name; pass --include-hashed to import it \
+         (ssh-ed25519 |1|JfKTdBh7rNbXkVAQCRp4OQoPfmI=|USECr3SWf1JUPsms5AqfD5QfxkM=)\n",
+    );
+    let out = import(&["--include-hashed"]);
+    assert_eq!(
+        out,
+        "already present: SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus  web1.example.com,10.0.0.1\n\
+         added SHA256:WXHxRS14zAIheHbbTePKvggMmQkdwTO7PYe7wVV+vNY  [db.example.com]:2222\n\
+         added SHA256:k/ZdsVp1or0yBIzVaRs0+9IIk4RZyJbumg+zd9LVNn4  |1|JfKTdBh7rNbXkVAQCRp4OQoPfmI=|USECr3SWf1JUPsms5AqfD5QfxkM=\n\
+         already present: SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus  web3.example.com\n\
+         skipped line 4: not an ed25519 key (ssh-rsa web2.example.com)\n",
+    );
+    assert_eq!(fs::read_to_string(&ring_file).unwrap().lines().count(), 3);
+
+    // The result is a ring file like any other
+    let file = scratch.confession(&[]);
+    let out = scratch.run(&[
+        "prove",
+        file.to_str().unwrap(),
+        test_key().to_str().unwrap(),
+        "--ring",
+        ring_file.to_str().unwrap(),
+        "--no-timestamp",
+    ]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let proven = scratch.write("proven.json", &stdout(&out));
+    let out = scratch.run(&["verify", proven.to_str().unwrap(), "--verbose"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains("web1.example.com,10.0.0.1"), "{}", stdout(&out));
+}
+
+#[test]
+fn prove_batch() {
+    let scratch = Scratch::new("cli");
+    let ring_file = scratch.write("ring.keys", &[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]].join("\n"));
+    scratch.write("release.txt", "release 1.0 is good\n");
+    let manifest = scratch.write(
+        "manifest.jsonl",
+        "{\"id\": \"one\", \"message\": \"first\"}\n\
+         {\"id\": \"two\", \"message\": \"second\"}\n\
+         {\"id\": \"three\", \"messageFile\": \"missing.txt\"}\n\
+         {\"id\": \"four\", \"messageFile\": \"release.txt\"}\n\
+         {\"id\": \"five\", \"message\": \"fifth\"}\n",
+    );
+    let out_dir = scratch.dir.join("proofs");
+    let key = test_key();
+    for jobs in ["1", "3"] {
+        let out = scratch.run(&[
+            "prove-batch",
+            manifest.to_str().unwrap(),
+            "--ring",
+            ring_file.to_str().unwrap(),
+            "--key",
+            key.to_str().unwrap(),
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+            "--jobs",
+            jobs,
+        ]);
+        assert_eq!(out.status.code(), Some(1), "{:?}", out);
+        let text = stdout(&out);
+        assert!(text.contains("FAILED  three: reading missing.txt"), "{}", text);
+        assert!(text.ends_with("4 succeeded, 1 failed\n"), "{}", text);
+
+        for id in ["one", "two", "four", "five"] {
+            let bundle = out_dir.join(format!("{}.json", id));
+            let out = scratch.run(&["verify", bundle.to_str().unwrap()]);
+            assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        }
+        assert!(!out_dir.join("three.json").exists());
+        let four = json(&fs::read_to_string(out_dir.join("four.json")).unwrap());
+        assert_eq!(four["message"], "release 1.0 is good\n");
+        fs::remove_dir_all(&out_dir).unwrap();
+    }
+}
+
+#[test]
+fn bench_smallest() {
+    let scratch = Scratch::new("cli");
+    let out = scratch.run(&["bench", "--sizes", "2", "--iters", "1", "--format", "json"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let report = json(&stdout(&out));
+    assert_eq!(report["iterations"], 1);
+    assert_eq!(report["results"][0]["size"], 2);
+    for field in ["proveMs", "verifyMs", "ringSetupMs", "ringProveMs", "ringVerifyMs"] {
+        assert!(report["results"][0][field].as_f64().unwrap() >= 0.0, "{}", report);
+    }
+
+    let out = scratch.run(&["bench", "--sizes", "1,2", "--iters", "1"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(stdout(&out).lines().count(), 4);
+    assert_eq!(scratch.run(&["bench", "--sizes", "0"]).status.code(), Some(2));
+}
+
+#[test]
+fn init_then_prove() {
+    let scratch = Scratch::new("cli");
+    let ring_file = scratch.write("ring.keys", &[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]].join("\n"));
+    let out_file = scratch.dir.join("confession.json");
+    let init = |extra: &[&str]| {
+        let args = ["init", "--ring", ring_file.to_str().unwrap(), "--out", out_file.to_str().unwrap()];
+        scratch.run(&[&args[..], extra].concat())
+    };
+
+    let out = init(&["--message", "we did it"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let scaffold = json(&fs::read_to_string(&out_file).unwrap());
+    assert_eq!(scaffold["message"], "we did it");
+    assert_eq!(scaffold["publicKeys"].as_array().unwrap().len(), 3);
+    assert!(scaffold.get("proof").is_none());
+
+    // Refuses to overwrite without --force
+    let out = init(&["--message", "changed"]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert_eq!(json(&fs::read_to_string(&out_file).unwrap())["message"], "we did it");
+    let out = init(&["--force"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_ne!(json(&fs::read_to_string(&out_file).unwrap())["message"], "we did it");
+    let out = init(&["--force", "--message", "we did it"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+
+    let key = test_key();
+    let out = scratch.run(&["prove", out_file.to_str().unwrap(), key.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let proven = scratch.write("proven.json", &stdout(&out));
+    let out = scratch.run(&["verify", proven.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).starts_with("we did it\n"), "{}", stdout(&out));
+
+    // An empty ring cannot be scaffolded
+    let empty = scratch.write("empty.keys", "# nobody\n");
+    let other = scratch.dir.join("other.json");
+    let out = scratch.run(&["init", "--ring", empty.to_str().unwrap(), "--out", other.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert!(!other.exists());
+}
+
+#[test]
+fn linkable_scan() {
+    let scratch = Scratch::new("cli");
+    let second_pk = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/test-key-2.pub")).unwrap();
+    let second_key = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/test-key-2.priv");
+    let ring = [OTHER_PKS[0], TEST_PK, second_pk.trim(), OTHER_PKS[1]];
+    let bundles = scratch.dir.join("bundles");
+    fs::create_dir(&bundles).unwrap();
+
+    let prove = |name: &str, ring: &[&str], key: &Path, extra: &[&str]| {
+        let file = scratch.confession(ring);
+        let out = scratch.run(&[&["prove", file.to_str().unwrap(), key.to_str().unwrap()], extra].concat());
+        assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        fs::write(bundles.join(name), stdout(&out)).unwrap();
+    };
+    let linkable = ["--linkable", "--scope", "vote-1"];
+    prove("a.json", &ring, &test_key(), &linkable);
+    prove("b.json", &ring[1..], &test_key(), &linkable); // a different ring still links
+    prove("c.json", &ring, &second_key, &linkable);
+    prove("d.json", &ring, &test_key(), &[]);
+    prove("e.json", &ring, &test_key(), &["--linkable", "--scope", "vote-2"]);
+
+    let a = json(&fs::read_to_string(bundles.join("a.json")).unwrap());
+    assert_eq!(a["scope"], "vote-1");
+    let image = a["keyImage"].as_str().unwrap().to_owned();
+    let out = scratch.run(&["verify", bundles.join("a.json").to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains(&format!("linkable in scope \"vote-1\" with key image {}\n", image)), "{}", stdout(&out));
+
+    let out = scratch.run(&["link-scan", bundles.to_str().unwrap(), "--scope", "vote-1"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(
+        stdout(&out),
+        format!(
+            "scope \"vote-1\": 3 bundles from 2 signers\n  \
+               SAME SIGNER (key image {}): a.json, b.json\n\
+             note: skipped bundles in other scopes: e.json\n\
+             note: skipped bundles which are not linkable: d.json\n",
+            &image[..16],
+        ),
+    );
+
+    // A tampered key image does not verify and is not counted
+    let c = json(&fs::read_to_string(bundles.join("c.json")).unwrap());
+    let tampered = a.to_string().replace(&image, c["keyImage"].as_str().unwrap());
+    fs::write(bundles.join("f.json"), tampered).unwrap();
+    let out = scratch.run(&["link-scan", bundles.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let scan = json(&stdout(&out));
+    assert_eq!(scan["scopes"][0]["scope"], "vote-1");
+    assert_eq!(scan["scopes"][0]["linked"][0]["files"], serde_json::json!(["a.json", "b.json"]));
+    assert_eq!(scan["scopes"][1]["scope"], "vote-2");
+    assert_eq!(scan["notLinkable"], serde_json::json!(["d.json"]));
+    assert_eq!(scan["invalid"][0]["file"], "f.json");
+}
+
+#[test]
+fn multi_proof_bundle() {
+    let scratch = Scratch::new("cli");
+    let ring = [OTHER_PKS[0], TEST_PK, OTHER_PKS[1]];
+    let proven = json(&scratch.prove(&ring));
+    let bundle = serde_json::json!({
+        "version": 2,
+        "message": proven["message"],
+        "publicKeys": ring,
+        "proofs": [
+            { "proof": proven["proof"], "context": "original" },
+            { "proof": proven["proof"], "message": "a different message" },
+        ],
+    });
+    let path = scratch.write("bundle.json", &bundle.to_string());
+
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert_eq!(
+        stdout(&out),
+        "entry 1 (\"original\"): VERIFIED against 3 keys\n\
+         entry 2: FAILED: bad proof\n\
+         1 of 2 entries verified\n",
+    );
+
+    let out = scratch.run(&["verify", path.to_str().unwrap(), "--any", "--format", "json"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let report = json(&stdout(&out));
+    assert_eq!(report["verified"], false);
+    assert_eq!(report["passed"], 1);
+    assert_eq!(report["entries"][0]["context"], "original");
+    assert_eq!(report["entries"][1]["error"], "bad proof");
+
+    let out = scratch.run(&["prove", path.to_str().unwrap(), test_key().to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn timestamped_bundle() {
+    let scratch = Scratch::new("cli");
+    let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+    let bundle = fs::read_to_string(testdata.join("timestamp-bundle.json")).unwrap();
+    let path = scratch.write("bundle.json", &bundle);
+    let path = path.to_str().unwrap();
+
+    let out = scratch.run(&["verify", path, "--check-timestamps"]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert!(stdout(&out).ends_with("the bundle has no timestamps\n"), "{:?}", out);
+
+    // The canned reply was made by `openssl ts -reply` for this bundle
+    let tsa = format!("file:{}", testdata.join("tsa-reply.tsr").display());
+    let out = scratch.run(&["timestamp", path, "--tsa", &tsa]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(stdout(&out), format!("timestamped {} at 2026-10-16T15:45:12Z (it now has 1 timestamp)\n", path));
+
+    let out = scratch.run(&["verify", path, "--check-timestamps"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(
+        stdout(&out),
+        "entry 1 (\"before the vote\"): VERIFIED against 2 keys\n\
+         1 of 1 entries verified\n\
+         timestamp 1: 2026-10-16T15:45:12Z (rfc3161; the authority's signature was not checked)\n",
+    );
+
+    // Any change to the bundle invalidates its timestamps
+    let mut tampered = json(&fs::read_to_string(path).unwrap());
+    tampered["proofs"][0]["context"] = "after the vote".into();
+    let tampered = scratch.write("tampered.json", &tampered.to_string());
+    let out = scratch.run(&["verify", tampered.to_str().unwrap(), "--check-timestamps", "--format", "json"]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    let report = json(&stdout(&out));
+    assert_eq!(report["verified"], true);
+    assert_eq!(report["timestamps"][0]["code"], "wrong_timestamp");
+
+    // A reply for another bundle is refused, and the bundle left alone
+    let out = scratch.run(&["timestamp", tampered.to_str().unwrap(), "--tsa", &tsa]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert_eq!(json(&fs::read_to_string(&tampered).unwrap())["timestamps"].as_array().unwrap().len(), 1);
+
+    let out = scratch.run(&["verify", path, "--tsa", &tsa]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn signed_attributes() {
+    let scratch = Scratch::new("cli");
+    let file = scratch.confession(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]);
+    let prove = |extra: &[&str]| {
+        let out = scratch.run(&[&["prove", file.to_str().unwrap(), test_key().to_str().unwrap()], extra].concat());
+        assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        json(&stdout(&out))
+    };
+    let verify = |name: &str, proven: &serde_json::Value, extra: &[&str]| {
+        let path = scratch.write(name, &proven.to_string());
+        scratch.run(&[&["verify", path.to_str().unwrap()], extra].concat())
+    };
+
+    // Timestamped by default, and unexpired
+    let proven = prove(&["--expires", "30d"]);
+    let timestamp = proven["attributes"]["timestamp"].as_u64().unwrap();
+    assert_eq!(proven["attributes"]["expires"].as_u64().unwrap(), timestamp + 30 * 86400);
+    let out = verify("unexpired.json", &proven, &[]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains("\nsigned at "), "{}", stdout(&out));
+    assert!(stdout(&out).contains("\nexpires at "), "{}", stdout(&out));
+    let out = verify("unexpired.json", &proven, &["--at", "2999-01-01"]);
+    assert_eq!(out.status.code(), Some(5), "{:?}", out);
+
+    // Expired, unless checked at an earlier time
+    let proven = prove(&["--no-timestamp", "--expires", "2001-01-01"]);
+    assert_eq!(proven["attributes"], serde_json::json!({ "expires": 978307200 }));
+    let out = verify("expired.json", &proven, &[]);
+    assert_eq!(out.status.code(), Some(5), "{:?}", out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("proof expired at 2001-01-01T00:00:00Z"), "{:?}", out);
+    let out = verify("expired.json", &proven, &["--at", "2000-06-01", "--format", "json"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(json(&stdout(&out))["attributes"]["expires"], "2001-01-01T00:00:00Z");
+
+    // The attributes are covered by the proof
+    let mut tampered = proven;
+    tampered["attributes"]["expires"] = 4102444800u64.into();
+    let out = verify("tampered.json", &tampered, &[]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+
+    let out = scratch.run(&["prove", file.to_str().unwrap(), test_key().to_str().unwrap(), "--expires", "2001-01-01"]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn strict_rules() {
+    let scratch = Scratch::new("cli");
+    let proven = json(&scratch.prove(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]));
+    let check = |name: &str, fixture: &serde_json::Value, strict_code: i32| {
+        let path = scratch.write(name, &fixture.to_string());
+        let out = scratch.run(&["verify", path.to_str().unwrap()]);
+        assert_eq!(out.status.code(), Some(0), "{}: {:?}", name, out);
+        let out = scratch.run(&["verify", path.to_str().unwrap(), "--strict"]);
+        assert_eq!(out.status.code(), Some(strict_code), "{}: {:?}", name, out);
+    };
+    check("valid.json", &proven, 0);
+
+    // Add the group order to the first s value
+    let mut proof: Vec<u8> = (0..proven["proof"].as_str().unwrap().len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&proven["proof"].as_str().unwrap()[i..i + 2], 16).unwrap())
+        .collect();
+    let order: [u8; 32] = [
+        0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+    ];
+    let mut carry = 0u16;
+    for (byte, order_byte) in proof[32..64].iter_mut().zip(order) {
+        let sum = u16::from(*byte) + u16::from(order_byte) + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    let mut fixture = proven.clone();
+    fixture["proof"] = proof.iter().map(|b| format!("{:02x}", b)).collect::<String>().into();
+    check("non-canonical.json", &fixture, 10);
+    let out = scratch.run(&["verify", scratch.dir.join("non-canonical.json").to_str().unwrap(), "--strict"]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("non-canonical scalar at index 1"), "{:?}", out);
+
+    let mut fixture = proven.clone();
+    fixture.as_object_mut().unwrap().remove("version");
+    check("legacy.json", &fixture, 11);
+
+    // A repeated key is refused, as the library refuses it
+    let mut duplicated = proven.clone();
+    duplicated["publicKeys"] = serde_json::json!([OTHER_PKS[0], TEST_PK, OTHER_PKS[1], OTHER_PKS[0]]);
+    let path = scratch.write("duplicate.json", &duplicated.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("in the ring more than once"), "{:?}", out);
+    let out = scratch.run(&["verify", path.to_str().unwrap(), "--strict"]);
+    assert_eq!(out.status.code(), Some(12), "{:?}", out);
+
+    let mut fixture = proven;
+    fixture["comment"] = "hello".into();
+    check("unknown-field.json", &fixture, 13);
+}
+
+#[test]
+fn problematic_ring_keys() {
+    let scratch = Scratch::new("cli");
+    let file = scratch.confession(&[OTHER_PKS[0], TEST_PK]);
+    let ring = scratch.write(
+        "ring.keys",
+        &format!(
+            "{}\n# a comment\n{}\n{}\nssh-rsa AAAAB3NzaC1yc2EAAAADAQABAAAAgQC7 someone\n",
+            OTHER_PKS[1],
+            TEST_PK,
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAII0PQoSjaDulROj7qwNNsJ1cCa+sqlWsKs3e8nemW9J+ apoelstra-torsion",
+        ),
+    );
+    let prove = |extra: &[&str]| {
+        let key = test_key();
+        let args = ["prove", file.to_str().unwrap(), key.to_str().unwrap(), "--ring", ring.to_str().unwrap()];
+        scratch.run(&[&args[..], extra].concat())
+    };
+
+    // Unusable keys need --force
+    let out = prove(&[]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(err.contains("warning: 3 problems with the ring:"), "{}", err);
+    assert!(err.contains(&format!("{} line 3: repeats publicKeys line 2 (", ring.display())), "{}", err);
+    assert!(err.contains(&format!("{} line 4: key has a torsion component", ring.display())), "{}", err);
+    assert!(err.contains(&format!("{} line 5: not an ed25519 key (ssh-rsa)", ring.display())), "{}", err);
+    assert!(err.contains("2 keys could not be used"), "{}", err);
+
+    // With it, the proof is over the remaining keys, and records the rest
+    let out = prove(&["--force", "--no-timestamp"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let proven = json(&stdout(&out));
+    assert_eq!(proven["publicKeys"], serde_json::json!([OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]));
+    let skipped = proven["skippedKeys"].as_array().unwrap();
+    let lines: Vec<_> = skipped.iter().map(|s| s["line"].as_u64().unwrap()).collect();
+    assert_eq!(lines, [3, 4, 5]);
+    assert_eq!(skipped[2]["reason"], "not an ed25519 key (ssh-rsa)");
+
+    // A repeated key alone leaves the ring unchanged, so needs no --force
+    let repeat = scratch.write("repeat.keys", &format!("{}\n", OTHER_PKS[0]));
+    let out = scratch.run(&[
+        "prove",
+        file.to_str().unwrap(),
+        test_key().to_str().unwrap(),
+        "--ring",
+        repeat.to_str().unwrap(),
+        "--no-timestamp",
+    ]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("warning: 1 problem with the ring:"), "{:?}", out);
+    let reason = json(&stdout(&out))["skippedKeys"][0]["reason"].clone();
+    assert!(reason.as_str().unwrap().starts_with("repeats publicKeys line 1 "), "{}", reason);
+
+    let path = scratch.write("proven.json", &proven.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains(&format!("note: {} line 4 was left out of the ring", ring.display())), "{:?}", out);
+    let out = scratch.run(&["verify", path.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(json(&stdout(&out))["skippedKeys"], proven["skippedKeys"]);
+}
+
+#[test]
+fn sshsig_for_single_key() {
+    let scratch = Scratch::new("cli");
+    let prove = |ring: &[&str], extra: &[&str]| {
+        let file = scratch.confession(ring);
+        let key = test_key();
+        let args = ["prove", file.to_str().unwrap(), key.to_str().unwrap(), "--sshsig-when-single"];
+        scratch.run(&[&args[..], extra].concat())
+    };
+
+    let out = prove(&[TEST_PK], &[]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let proven = json(&stdout(&out));
+    assert_eq!(proven["proof"], serde_json::Value::Null);
+    assert_eq!(proven["attributes"], serde_json::Value::Null);
+    let armor = proven["sshsig"].as_str().unwrap();
+    let message = "this is an example text";
+
+    // OpenSSH's own verifier accepts it, if it is installed
+    let allowed = scratch.write("allowed_signers", &format!("confessor {}\n", TEST_PK));
+    let sig = scratch.write("confession.sig", armor);
+    let ssh_keygen = Command::new("ssh-keygen")
+        .args(["-Y", "verify", "-f", allowed.to_str().unwrap(), "-I", "confessor", "-n", "cryptoconfessions", "-s"])
+        .arg(&sig)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    match ssh_keygen {
+        Ok(mut child) => {
+            child.stdin.take().unwrap().write_all(message.as_bytes()).unwrap();
+            let out = child.wait_with_output().unwrap();
+            assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        }
+        Err(_) => {
+            let sig = Signature::from_armor(armor).unwrap();
+            assert_eq!(sig.public_key, PublicKey::parse_pk_line(TEST_PK).unwrap());
+            assert_eq!(sig.verify("cryptoconfessions", message.as_bytes()), Ok(()));
+        }
+    }
+
+    let path = scratch.write("proven.json", &proven.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let signer = "SUCCESSFULLY VERIFIED SSHSIG SIGNATURE by SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus";
+    assert!(stdout(&out).contains(signer), "{}", stdout(&out));
+    let mut tampered = proven.clone();
+    tampered["message"] = "this is another text".into();
+    let path = scratch.write("tampered.json", &tampered.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    let mut tampered = proven;
+    tampered["publicKeys"] = serde_json::json!([OTHER_PKS[0]]);
+    let path = scratch.write("tampered.json", &tampered.to_string());
+    let out = scratch.run(&["verify", path.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+
+    // Larger rings still get a proof, and an sshsig cannot carry attributes
+    let out = prove(&[OTHER_PKS[0], TEST_PK], &[]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(json(&stdout(&out))["proof"].is_string());
+    let out = prove(&[TEST_PK], &["--expires", "30d"]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn multiple_matching_keys() {
+    let scratch = Scratch::new("cli");
+    let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+    let ssh = scratch.dir.join(".ssh");
+    fs::create_dir(&ssh).unwrap();
+    for name in ["test-key.priv", "test-key.pub", "test-key-2.priv"] {
+        fs::copy(testdata.join(name), ssh.join(name.replace(".priv", ""))).unwrap();
+    }
+    let second_pk = fs::read_to_string(testdata.join("test-key-2.pub")).unwrap();
+    let second_fp = "SHA256:WXHxRS14zAIheHbbTePKvggMmQkdwTO7PYe7wVV+vNY";
+    let file = scratch.confession(&[OTHER_PKS[0], TEST_PK, second_pk.trim()]);
+    let prove = |extra: &[&str]| scratch.run(&[&["prove", file.to_str().unwrap(), "--dry-run"], extra].concat());
+
+    let out = prove(&[]);
+    assert_eq!(out.status.code(), Some(4), "{:?}", out);
+    let err = String::from_utf8(out.stderr).unwrap();
+    assert!(err.contains("2 different secret keys"), "{}", err);
+    assert!(err.contains("SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus  apoelstra@sultana"), "{}", err);
+    assert!(err.contains(&format!("{}  (no comment)", second_fp)), "{}", err);
+
+    let out = prove(&["--fingerprint", second_fp]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains(&format!("would sign with {}", second_fp)), "{}", stdout(&out));
+
+    let out = prove(&["--first-match"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains("would sign with SHA256:F+kgkEB9C24jyIln/jH+UgRmHR2tUJk58Ly89RHIIus"), "{}", stdout(&out));
+}
+
+#[test]
+#[cfg(feature = "age")]
+fn encrypted_confession() {
+    let scratch = Scratch::new("cli");
+    let testdata = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+    let identity = testdata.join("age-identity.txt");
+    let other_identity = testdata.join("age-identity-2.txt");
+    let recipient = "age1h6ddxn98maayz7d42kv3s8dxjmrnv4mvc893py2xdwld6fxv04sqx4qt9r";
+
+    let file = scratch.confession(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]);
+    let key = test_key();
+    let prove = [
+        "prove",
+        file.to_str().unwrap(),
+        key.to_str().unwrap(),
+        "--encrypt-to",
+        recipient,
+    ];
+    let out = scratch.run(&prove);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let sealed = scratch.dir.join("confession.json.age");
+    assert_eq!(stdout(&out), format!("wrote encrypted confession to {}\n", sealed.display()));
+    // Nothing but the input, the config and the sealed file
+    let mut files: Vec<_> = fs::read_dir(&scratch.dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+    files.sort();
+    assert_eq!(files, ["confession.json", "confession.json.age", "config.toml"]);
+    assert!(!fs::read(&sealed).unwrap().windows(7).any(|w| w == b"example"));
+
+    let out = scratch.run(&prove);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+
+    let out = scratch.run(&["verify", sealed.to_str().unwrap(), "--decrypt-identity", identity.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).starts_with("this is an example text\n"), "{}", stdout(&out));
+
+    let out = scratch.run(&["verify", sealed.to_str().unwrap(), "--decrypt-identity", other_identity.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    let err = String::from_utf8(out.stderr).unwrap();
+    assert!(err.contains("does not match any recipient"), "{}", err);
+}
+
+#[test]
+fn secret_key_from_stdin() {
+    let scratch = Scratch::new("cli");
+    let file = scratch.confession(&[TEST_PK, OTHER_PKS[0]]);
+    let key = fs::read_to_string(test_key()).unwrap();
+    let out = scratch.run_with_input(&["prove", file.to_str().unwrap(), "--key", "-", "--no-timestamp"], &key);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let proven = scratch.write("proven.json", &stdout(&out));
+    assert_eq!(scratch.run(&["verify", proven.to_str().unwrap()]).status.code(), Some(0));
+
+    // Standard input can only be read once
+    let out = scratch.run_with_input(&["prove", "-", "-", "--no-timestamp"], &key);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn badge() {
+    let scratch = Scratch::new("cli");
+    let file = scratch.confession(&[OTHER_PKS[0], TEST_PK, OTHER_PKS[1]]);
+    // With the signing time, which the badge must take into account
+    let out = scratch.run(&["prove", file.to_str().unwrap(), test_key().to_str().unwrap(), "--expires", "30d"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let proven = scratch.write("proven.json", &stdout(&out));
+    let svg = scratch.dir.join("badge.svg");
+
+    let args = ["badge", proven.to_str().unwrap(), "--out", svg.to_str().unwrap(), "--label", "maintainers"];
+    let out = scratch.run(&args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains(": verified: 1 of 3 keys · ring "), "{}", stdout(&out));
+    let image = fs::read_to_string(&svg).unwrap();
+    assert!(image.starts_with("<svg ") && image.contains(">maintainers</text>"), "{}", image);
+
+    let out = scratch.run(&["badge", proven.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let report = json(&stdout(&out));
+    assert_eq!(report["verified"], true);
+    assert_eq!(report["label"], "confession");
+    assert_eq!(report["rings"][0]["size"], 3);
+    assert!(report["svg"].as_str().unwrap().starts_with("<svg "));
+
+    // Failures are still rendered, and expiry is checked
+    let out = scratch.run(&["badge", proven.to_str().unwrap(), "--at", "2100-01-01", "--out", svg.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("proof expired"), "{:?}", out);
+    assert!(fs::read_to_string(&svg).unwrap().contains("FAILED: ring of 3 keys"));
+
+    let out = scratch.run(&["verify", proven.to_str().unwrap(), "--label", "x"]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn revoke() {
+    let scratch = Scratch::new("cli");
+    let ring = [OTHER_PKS[0], TEST_PK, OTHER_PKS[1]];
+    let original = scratch.write("original.json", &scratch.prove(&ring));
+    let key = test_key();
+    let dir = scratch.dir.join("revocations");
+    fs::create_dir_all(&dir).unwrap();
+    let revocation = dir.join("revocation.json");
+
+    // Nothing to find yet, except a file which is not a confession
+    fs::write(dir.join("notes.json"), "not json").unwrap();
+    let args = ["verify", original.to_str().unwrap(), "--revocations", dir.to_str().unwrap()];
+    let out = scratch.run(&args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).ends_with("not revoked by any of the 0 confessions checked\n"), "{}", stdout(&out));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("warning: skipping"), "{:?}", out);
+
+    let args = ["revoke", original.to_str().unwrap(), key.to_str().unwrap(), "--out", revocation.to_str().unwrap()];
+    let out = scratch.run(&args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let made = json(&fs::read_to_string(&revocation).unwrap());
+    assert_eq!(made["publicKeys"], json(&fs::read_to_string(&original).unwrap())["publicKeys"]);
+    assert!(made["message"].as_str().unwrap().starts_with("CREDIBLE CONFESSION REVOCATION v1\nrevokes "));
+    let out = scratch.run(&["verify", revocation.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+
+    // The original still verifies, and is reported as revoked
+    let args = ["verify", original.to_str().unwrap(), "--revocations", dir.to_str().unwrap()];
+    let out = scratch.run(&args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).ends_with("REVOKED by revocation.json\n"), "{}", stdout(&out));
+    let out = scratch.run(&[&args[..], &["--format", "json"]].concat());
+    let report = json(&stdout(&out));
+    assert_eq!(report["verified"], true);
+    assert_eq!(report["revocation"], serde_json::json!({ "status": "revoked", "checked": 1, "by": "revocation.json" }));
+
+    // A supersession, in a directory of its own
+    let replacement = scratch.write("replacement.json", &scratch.prove(&ring));
+    let superseding = scratch.dir.join("superseding");
+    fs::create_dir_all(&superseding).unwrap();
+    let out = scratch.run(&[
+        "revoke",
+        original.to_str().unwrap(),
+        key.to_str().unwrap(),
+        "--supersede",
+        replacement.to_str().unwrap(),
+        "--out",
+        superseding.join("supersession.json").to_str().unwrap(),
+    ]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let args = ["verify", original.to_str().unwrap(), "--revocations", superseding.to_str().unwrap()];
+    let report = json(&stdout(&scratch.run(&[&args[..], &["--format", "json"]].concat())));
+    assert_eq!(report["revocation"]["status"], "superseded");
+    assert_eq!(report["revocation"]["by"], "supersession.json");
+    assert_eq!(report["revocation"]["replacement"].as_str().unwrap().len(), 64);
+
+    // The revocations say nothing about the replacement, which is a different confession
+    let args = ["verify", replacement.to_str().unwrap(), "--revocations", dir.to_str().unwrap()];
+    let out = scratch.run(&args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).ends_with("not revoked by any of the 1 confessions checked\n"), "{}", stdout(&out));
+
+    // A key outside the ring cannot revoke
+    let outsider = scratch.write("outsider.json", &serde_json::json!({
+        "version": 1,
+        "publicKeys": OTHER_PKS,
+        "message": "someone else's confession",
+        "proof": "00",
+    }).to_string());
+    let out = scratch.run(&["revoke", outsider.to_str().unwrap(), key.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(3), "{:?}", out);
+
+    let out = scratch.run(&["prove", original.to_str().unwrap(), "--revocations", dir.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+    let out = scratch.run(&["verify", original.to_str().unwrap(), "--supersede", replacement.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn thread() {
+    let scratch = Scratch::new("cli");
+    let ring = [OTHER_PKS[0], TEST_PK, OTHER_PKS[1]];
+    let dir = scratch.dir.join("thread");
+    fs::create_dir_all(&dir).unwrap();
+    let key = test_key();
+    let file = scratch.confession(&ring);
+    let mut parent: Option<PathBuf> = None;
+    for name in ["1-root.json", "2-reply.json", "3-reply.json"] {
+        let mut args = vec!["prove", file.to_str().unwrap(), key.to_str().unwrap()];
+        if let Some(ref parent) = parent {
+            args.extend(["--reply-to", parent.to_str().unwrap()]);
+        }
+        let out = scratch.run(&args);
+        assert_eq!(out.status.code(), Some(0), "{:?}", out);
+        fs::write(dir.join(name), stdout(&out)).unwrap();
+        parent = Some(dir.join(name));
+    }
+
+    let out = scratch.run(&["verify", dir.join("3-reply.json").to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains("in reply to the confession with hash "), "{}", stdout(&out));
+
+    let out = scratch.run(&["thread", "verify", dir.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert_eq!(
+        stdout(&out),
+        "1-root.json: VERIFIED, thread root\n\
+         2-reply.json: VERIFIED, replies to 1-root.json (depth 1)\n\
+         3-reply.json: VERIFIED, replies to 2-reply.json (depth 2)\n\
+         thread intact: 3 confessions\n",
+    );
+
+    // Tampering with the middle confession breaks it and the link to it
+    let middle = dir.join("2-reply.json");
+    let mut value = json(&fs::read_to_string(&middle).unwrap());
+    value["message"] = "an edited message".into();
+    fs::write(&middle, value.to_string()).unwrap();
+    let out = scratch.run(&["thread", "verify", dir.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+    let text = stdout(&out);
+    assert!(text.contains("2-reply.json: FAILED: bad proof, replies to 1-root.json (depth 1)\n"), "{}", text);
+    assert!(text.contains("3-reply.json: VERIFIED, BROKEN: replies to "), "{}", text);
+    assert!(text.ends_with("thread broken in 2 places\n"), "{}", text);
+
+    let out = scratch.run(&["thread", "verify", dir.to_str().unwrap(), "--format", "json"]);
+    let report = json(&stdout(&out));
+    assert_eq!(report["intact"], false);
+    assert_eq!(report["confessions"][1]["parent"], "1-root.json");
+    assert_eq!(report["confessions"][2]["link"], "missing-parent");
+    assert_eq!(report["confessions"][2]["depth"], serde_json::Value::Null);
+
+    let out = scratch.run(&["verify", middle.to_str().unwrap(), "--reply-to", middle.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
+
+#[test]
+fn authorized_keys() {
+    let scratch = Scratch::new("cli");
+    let file = scratch.confession(&[OTHER_PKS[0]]);
+    let keys = format!("# ops team\n\n{} alice@laptop\n  # rotated\n{}\n", TEST_PK, OTHER_PKS[1]);
+    let authorized = scratch.write("authorized_keys", &keys);
+    let key = test_key();
+    let args = ["prove", file.to_str().unwrap(), key.to_str().unwrap(), "--authorized-keys", authorized.to_str().unwrap()];
+
+    let out = scratch.run(&[&args[..], &["--dry-run"]].concat());
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    assert!(stdout(&out).contains("(3 members)"), "{:?}", out);
+
+    let out = scratch.run(&args);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let proven = scratch.write("proven.json", &stdout(&out));
+    let out = scratch.run(&["verify", proven.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+
+    // Keys missing from the file are merged in when verifying
+    let mut trimmed = json(&fs::read_to_string(&proven).unwrap());
+    trimmed["publicKeys"] = serde_json::json!([OTHER_PKS[0]]);
+    let trimmed = scratch.write("trimmed.json", &trimmed.to_string());
+    let out = scratch.run(&["verify", trimmed.to_str().unwrap()]);
+    assert_ne!(out.status.code(), Some(0), "{:?}", out);
+    let out = scratch.run(&["verify", trimmed.to_str().unwrap(), "--authorized-keys", authorized.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+
+    let out = scratch.run(&["ring", "list", "--authorized-keys", authorized.to_str().unwrap()]);
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+}
@@ -0,0 +1,189 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! Compatibility with confessions and bundles made by earlier releases
+//!
+//! testdata/compat/ holds artifacts which `freeze-vectors` made once and
+//! which were checked in. Whatever else changes, they must keep verifying,
+//! both by the library and by `ringsig-cli verify`, with `--strict` too
+//! wherever it applies. Their hashes are pinned here so that they cannot be
+//! quietly regenerated: if one stops verifying, that is a compatibility
+//! break, not a stale fixture.
+
+use bitcoin_hashes::hex::ToHex;
+use bitcoin_hashes::{sha256, Hash};
+use ringsig::badge::Badge;
+use ringsig::bundle;
+use ringsig::format::{self, Clearsigned, Kind};
+use ringsig::hashes::BundleHash;
+use ringsig::revocation::{self, Action, RevocationStatus, Statement};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::slice;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+/// Every frozen artifact, with its SHA256
+const FROZEN: &[(&str, &str)] = &[
+    ("armored-bundle.txt", "f7abc06218ed829d10c0bc9fd88c6a72496bb9e9a3925bf4604a55d8ffac4ff2"),
+    ("clearsign.txt", "7668d002c431ba95348211a60ff1c1dc2da7b6ac8538154af73f68f1feb921c5"),
+    ("v1-attributes.json", "a40faaf997c48fd203d699a732d99d5f3abb830f9da3b7afbce9097ecba94abd"),
+    ("v1-empty-message.json", "71473eead5ca0240e4c7c57e156864ed815c46e501f2fcc9c326ef8e0b71ab0b"),
+    ("v1-key-oddities.json", "998901f63eebd3bb8c1e5e65b33fd04b9cf70355fd3771d1f9a20ece52d0f0d8"),
+    ("v1-linkable.json", "c9ca50f1682cb2c79fcb5336000a6c79b5828bdb64bde3ada4f3516419d23745"),
+    ("v1-message-oddities.json", "95a6506bf5f4f614cde379fd94cc874479ed3f86c5fa6285f4072a2bed90ca38"),
+    ("v1-ring-1.json", "f25ab5d0759241b607af9afc4a2cf8661dc035b1d703c8d8a5e44c710fb31bab"),
+    ("v1-ring-16.json", "961d909921b93471684fb833446c6127edc9e0745028123fce932fe589a23cd8"),
+    ("v1-ring-2.json", "250449d6c4fe2f8aa662e73fcf86a8a1ddbd1fd3877b6e001a93632aadb6ee76"),
+    ("v1-ring-3.json", "a25a6ab3ba38798e170c779df571bec3bc2321e5d394305c9815986c10987a88"),
+    ("v1-ring-64.json", "5a5c4e5bbeef9e2bddd892f5a1ec0110c16bc0455078730bda9219d513952398"),
+    ("v1-revocation.json", "92ec7108b2b2d3ba2909cd7ba302fff5f967decc386ac6e45b88a51f7bac4f3b"),
+    ("v1-skipped-keys.json", "da0eaf9034d19e32e1862858dba10983243a945c13f12cd86f679f4bea43d635"),
+    ("v1-sshsig.json", "8cc8e2902ac5b164583dc8ef02261422ff9fe1c92bb7d0932012878003ffaaef"),
+    ("v1-supersession.json", "dbd511399fbf7625069661d6efb0036bfc9333505a7d7174e45bcefaeaf199bc"),
+    ("v2-bundle-mixed.json", "33137ff469ca7757ceb3a01bdb965a25f9c40265330a31be2d5d02efc766e974"),
+    ("v2-bundle.json", "99245cac9676f48c6ef740591a50927d4360bfa259c3aeaaa5f334d29e1c0c49"),
+];
+
+fn compat_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/compat")
+}
+
+/// The JSON of an artifact, unwrapping the text formats
+fn json(name: &str) -> String {
+    let data = fs::read_to_string(compat_dir().join(name)).unwrap();
+    match format::classify(&data) {
+        Some(Kind::Json) => data,
+        Some(Kind::Bundle) => {
+            let json = format::dearmor(&data, format::BUNDLE_BEGIN, format::BUNDLE_END).unwrap();
+            String::from_utf8(json).unwrap()
+        }
+        Some(Kind::Clearsign) => {
+            let clearsigned = Clearsigned::decode(&data).unwrap();
+            let mut confession = bundle::Confession::new(clearsigned.public_keys, clearsigned.message);
+            confession.proof = Some(clearsigned.proof.to_hex());
+            confession.to_json_string()
+        }
+        kind => panic!("{}: unexpected format {:?}", name, kind),
+    }
+}
+
+fn version(json: &str) -> usize {
+    let value: serde_json::Value = serde_json::from_str(json).unwrap();
+    value["version"].as_u64().map_or(bundle::CONFESSION_VERSION, |v| usize::try_from(v).unwrap())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+#[test]
+fn frozen() {
+    assert!(FROZEN.len() >= 12);
+    for (name, hash) in FROZEN {
+        let data = fs::read(compat_dir().join(name)).unwrap();
+        let actual = sha256::Hash::hash(&data).to_string();
+        assert_eq!(actual, *hash, "{} has changed; frozen artifacts must never be regenerated", name);
+    }
+    for entry in fs::read_dir(compat_dir()).unwrap() {
+        let name = entry.unwrap().file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let data = fs::read(compat_dir().join(&name)).unwrap();
+        assert!(
+            FROZEN.iter().any(|(frozen, _)| *frozen == name),
+            "{} is not in FROZEN; add (\"{}\", \"{}\")",
+            name,
+            name,
+            sha256::Hash::hash(&data),
+        );
+    }
+}
+
+#[test]
+fn library_verifies() {
+    for (name, _) in FROZEN {
+        let json = json(name);
+        assert_eq!(bundle::validate(&json, version(&json)), Ok(vec![]), "{}", name);
+        let badge = Badge::check(&json, now(), None);
+        assert!(badge.verified(), "{}: {}", name, badge.text());
+    }
+}
+
+#[test]
+fn revocations() {
+    // A revocation refers to the hash of the original's JSON, without the
+    // trailing newline
+    let original = fs::read_to_string(compat_dir().join("v1-sshsig.json")).unwrap();
+    let hash = BundleHash::hash(original.trim_end().as_bytes());
+    let original = bundle::Confession::from_json_str(&original).unwrap();
+    assert_eq!(original.bundle_hash(), hash);
+
+    let revocation = bundle::Confession::from_json_str(&json("v1-revocation.json")).unwrap();
+    let supersession = bundle::Confession::from_json_str(&json("v1-supersession.json")).unwrap();
+    assert_eq!(revocation.message, Statement { original: hash, action: Action::Revoke }.encode());
+    let replacement = match Statement::parse(&supersession.message) {
+        Some(Statement { original, action: Action::Supersede(replacement) }) if original == hash => replacement,
+        statement => panic!("unexpected statement {:?}", statement),
+    };
+
+    let status = revocation::check_revocations(&original, slice::from_ref(&revocation));
+    assert_eq!(status, RevocationStatus::Revoked { by: 0 });
+    assert_eq!(
+        revocation::check_revocations(&original, slice::from_ref(&supersession)),
+        RevocationStatus::Superseded { by: 0, replacement },
+    );
+    let unrelated = bundle::Confession::from_json_str(&json("v1-ring-1.json")).unwrap();
+    assert_eq!(revocation::check_revocations(&unrelated, &[revocation, supersession]), RevocationStatus::Standing);
+}
+
+#[test]
+fn cli_verifies() {
+    let dir = env::temp_dir().join(format!("ringsig-compat-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("config.toml"), "").unwrap();
+    let run = |args: &[&str]| -> Output {
+        Command::new(env!("CARGO_BIN_EXE_ringsig-cli"))
+            .args(args)
+            .env("HOME", &dir)
+            .env("RINGSIG_CONFIG", dir.join("config.toml"))
+            .env_remove("SSH_AUTH_SOCK")
+            .output()
+            .unwrap()
+    };
+
+    for (name, _) in FROZEN {
+        let mut path = compat_dir().join(name);
+        // The text formats must still convert, and then verify
+        if !name.ends_with(".json") {
+            let out = run(&["convert", path.to_str().unwrap(), "--to", "json"]);
+            assert_eq!(out.status.code(), Some(0), "{}: {:?}", name, out);
+            path = dir.join(format!("{}.json", name));
+            fs::write(&path, &out.stdout).unwrap();
+        }
+        let out = run(&["verify", path.to_str().unwrap()]);
+        assert_eq!(out.status.code(), Some(0), "{}: {:?}", name, out);
+
+        // Strict mode applies only to version 1 ring proofs
+        let json = fs::read_to_string(&path).unwrap();
+        if version(&json) == bundle::CONFESSION_VERSION && !json.contains("\"sshsig\"") {
+            let out = run(&["verify", path.to_str().unwrap(), "--strict"]);
+            assert_eq!(out.status.code(), Some(0), "{} --strict: {:?}", name, out);
+        }
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
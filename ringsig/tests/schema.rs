@@ -0,0 +1,83 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+
+//! Checks of the fixtures in testdata/ against the published JSON Schemas
+
+use ringsig::bundle::{self, FieldError};
+use std::fs;
+use std::path::Path;
+
+/// Every JSON fixture, with its contents
+fn fixtures() -> Vec<(String, String)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata");
+    let mut ret: Vec<_> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| (path.file_name().unwrap().to_string_lossy().into_owned(), fs::read_to_string(&path).unwrap()))
+        .collect();
+    ret.sort();
+    ret
+}
+
+/// The version of a fixture; confessions from before versioning have none
+fn version(json: &str) -> usize {
+    let value: serde_json::Value = serde_json::from_str(json).unwrap();
+    value["version"].as_u64().map_or(bundle::CONFESSION_VERSION, |v| usize::try_from(v).unwrap())
+}
+
+#[test]
+fn fixtures_conform() {
+    let fixtures = fixtures();
+    assert!(fixtures.iter().any(|(_, json)| version(json) == bundle::CONFESSION_VERSION));
+    assert!(fixtures.iter().any(|(_, json)| version(json) == bundle::MULTI_VERSION));
+    for (name, json) in fixtures {
+        assert_eq!(bundle::validate(&json, version(&json)), Ok(vec![]), "{}", name);
+    }
+}
+
+#[test]
+fn broken_fixture() {
+    let json = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/timestamp-bundle.json")).unwrap();
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["proofs"][0]["keyImage"] = 5.into();
+    value["proofs"][0]["extra"] = true.into();
+    value["timestamps"] = serde_json::json!([{ "kind": "rfc3161" }]);
+    let problems: Vec<_> = bundle::validate(&value.to_string(), 2).unwrap().iter().map(ToString::to_string).collect();
+    assert_eq!(
+        problems,
+        [
+            "proofs[0].extra: unknown field",
+            "proofs[0].keyImage: expected a string or null, not an integer",
+            "timestamps[0].token: missing",
+        ],
+    );
+
+    // A version 1 confession is not a version 2 bundle
+    let json = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/test-verify.json")).unwrap();
+    let problems = bundle::validate(&json, 2).unwrap();
+    assert!(problems.contains(&FieldError { path: "proofs".into(), message: "missing".into() }), "{:?}", problems);
+
+    // Once the schema is satisfied, the fields themselves are checked
+    value = serde_json::from_str(&json).unwrap();
+    value["keyImage"] = "00".into();
+    let problems = bundle::validate(&value.to_string(), 1).unwrap();
+    assert_eq!(problems[0], FieldError { path: "keyImage".into(), message: "not 32 bytes of hex".into() });
+
+    assert!(matches!(bundle::validate("{", 1), Err(bundle::Error::Json(_))));
+    assert_eq!(bundle::validate("{}", 3), Err(bundle::Error::Version(3)));
+}
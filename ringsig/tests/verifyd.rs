@@ -0,0 +1,132 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! End-to-end tests which run the verification server
+
+#![cfg(feature = "server")]
+
+use ringsig::armor::FromArmor;
+use ringsig::keys::{PublicKey, SecretKey};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+
+mod common;
+use common::RING;
+
+/// The server, on an ephemeral port, which is killed when dropped
+struct Verifyd {
+    child: Child,
+    addr: String,
+}
+
+impl Verifyd {
+    fn start(max_body: usize) -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_ringsig-verifyd"))
+            .args(["--listen", "127.0.0.1:0", "--max-body", &max_body.to_string()])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap()).read_line(&mut line).unwrap();
+        let addr = line.trim().strip_prefix("listening on http://").expect("address announced").to_owned();
+        Verifyd { child, addr }
+    }
+
+    /// Make a request, returning the status and JSON body
+    fn request(&self, method: &str, path: &str, body: &str) -> (u16, serde_json::Value) {
+        let mut stream = TcpStream::connect(&self.addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            method,
+            path,
+            self.addr,
+            body.len(),
+            body,
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response.split(' ').nth(1).unwrap().parse().unwrap();
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        (status, serde_json::from_str(body).unwrap())
+    }
+}
+
+impl Drop for Verifyd {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+fn bundle() -> serde_json::Value {
+    let sk = SecretKey::from_armor(include_str!("../testdata/test-key.priv")).unwrap();
+    let keys: Vec<_> = RING.iter().map(|line| PublicKey::parse_pk_line(line).unwrap()).collect();
+    let proof = ringsig::prove(&keys, b"hello", &sk).unwrap();
+    serde_json::json!({
+        "version": 2,
+        "message": "hello",
+        "publicKeys": RING,
+        "proofs": [
+            { "proof": proof.to_hex(), "context": "good" },
+            { "proof": proof.to_hex(), "message": "goodbye" },
+        ],
+    })
+}
+
+#[test]
+fn endpoints() {
+    let server = Verifyd::start(4096);
+
+    let (status, body) = server.request("GET", "/healthz", "");
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ok");
+
+    let (status, body) = server.request("POST", "/verify", &bundle().to_string());
+    assert_eq!(status, 200, "{}", body);
+    assert_eq!(body["verified"], false);
+    assert_eq!(body["passed"], 1);
+    assert_eq!(body["entries"][0]["context"], "good");
+    assert_eq!(body["entries"][1]["code"], "bad_proof");
+
+    let (status, body) = server.request("POST", "/verify", r#"{"version": 1}"#);
+    assert_eq!(status, 400);
+    assert_eq!(body["error"]["code"], "bad_json");
+
+    let inspect = serde_json::json!({ "publicKeys": [RING[0], RING[1], RING[0]] });
+    let (status, body) = server.request("POST", "/ring/inspect", &inspect.to_string());
+    assert_eq!(status, 200);
+    assert_eq!(body["size"], 2);
+    assert_eq!(body["problems"][0]["code"], "duplicate_key");
+
+    let (status, body) = server.request("GET", "/nowhere", "");
+    assert_eq!(status, 404);
+    assert_eq!(body["error"]["code"], "not_found");
+}
+
+#[test]
+fn over_limit() {
+    let server = Verifyd::start(64);
+    let (status, body) = server.request("POST", "/verify", &bundle().to_string());
+    assert_eq!(status, 413);
+    assert_eq!(body["error"]["code"], "too_large");
+
+    // The server carries on afterwards
+    let (status, _) = server.request("GET", "/healthz", "");
+    assert_eq!(status, 200);
+}
@@ -0,0 +1,127 @@
+// Crypto Confessions
+// Written in 2022 by
+//   Andrew Poelstra <cryptoconfessions@wpsoftware.net>
+//   or David Vorick <cryptoconfessions@wpsoftware.net>
+//   or Liam Eagen <cryptoconfessions@wpsoftware.net>
+//
+// To the extent possible under law, the author(s) have dedicated all
+// copyright and related and neighboring rights to this software to
+// the public domain worldwide. This software is distributed without
+// any warranty.
+//
+// You should have received a copy of the CC0 Public Domain Dedication
+// along with this software.
+// If not, see <http://creativecommons.org/publicdomain/zero/1.0/>.
+//
+
+//! End-to-end tests which build the command-line tool for WASI and run it
+//! under wasmtime
+//!
+//! These are skipped, saying so, unless both `wasmtime` and the Rust target
+//! `wasm32-wasip1` are installed.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::OnceLock;
+use std::{env, fs};
+
+mod common;
+use common::RING;
+
+const TARGET: &str = "wasm32-wasip1";
+
+/// Why the tests cannot run here, if they cannot
+fn missing() -> Option<String> {
+    let wasmtime = Command::new("wasmtime").arg("--version").output();
+    if !wasmtime.is_ok_and(|out| out.status.success()) {
+        return Some("wasmtime is not installed".into());
+    }
+    let sysroot = Command::new("rustc").args(["--print", "sysroot"]).output().ok()?;
+    let sysroot = String::from_utf8(sysroot.stdout).ok()?;
+    if !Path::new(sysroot.trim()).join("lib/rustlib").join(TARGET).exists() {
+        return Some(format!("the {} target is not installed", TARGET));
+    }
+    None
+}
+
+/// Build the tool for WASI once, returning the module
+fn module() -> &'static Path {
+    static MODULE: OnceLock<PathBuf> = OnceLock::new();
+    MODULE.get_or_init(|| {
+        let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("wasi");
+        let out = Command::new(env!("CARGO"))
+            .args(["build", "--bin", "ringsig-cli", "--target", TARGET, "--manifest-path"])
+            .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "building for {}: {}", TARGET, String::from_utf8_lossy(&out.stderr));
+        target_dir.join(TARGET).join("debug/ringsig-cli.wasm")
+    })
+}
+
+/// Run the tool under wasmtime with `dir` preopened as `/work`, and with
+/// `input` on standard input
+fn run(dir: &Path, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new("wasmtime")
+        .arg("run")
+        .arg("--dir")
+        .arg(format!("{}::/work", dir.display()))
+        .arg(module())
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The tool may exit without reading it all
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn prove_and_verify_under_wasmtime() {
+    if let Some(reason) = missing() {
+        eprintln!("skipping: {}", reason);
+        return;
+    }
+    let dir = env::temp_dir().join(format!("ringsig-wasi-test-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let confession = serde_json::json!({ "version": 1, "publicKeys": RING, "message": "sandboxed" });
+    fs::write(dir.join("confession.json"), confession.to_string()).unwrap();
+    fs::write(dir.join("config.toml"), "").unwrap();
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/test-key.priv"), dir.join("test-key.priv")).unwrap();
+    fs::copy(Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/test-verify.json"), dir.join("fixed.json")).unwrap();
+
+    let out = run(&dir, &["verify", "/work/fixed.json"], "");
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+
+    // The key from a preopened directory, and from standard input
+    let out = run(&dir, &["prove", "/work/confession.json", "/work/test-key.priv", "--no-timestamp"], "");
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    fs::write(dir.join("proven.json"), &out.stdout).unwrap();
+    let key = fs::read_to_string(dir.join("test-key.priv")).unwrap();
+    let out = run(&dir, &["prove", "/work/confession.json", "--key", "-", "--no-timestamp"], &key);
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+
+    // The proof made in the sandbox verifies both inside and outside it
+    let out = run(&dir, &["verify", "/work/proven.json"], "");
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+    let out = Command::new(env!("CARGO_BIN_EXE_ringsig-cli"))
+        .arg("verify")
+        .arg(dir.join("proven.json"))
+        .env("RINGSIG_CONFIG", dir.join("config.toml"))
+        .output()
+        .unwrap();
+    assert_eq!(out.status.code(), Some(0), "{:?}", out);
+
+    // Missing capabilities are errors, not traps
+    let out = run(&dir, &["prove", "/work/confession.json", "--no-timestamp"], "");
+    assert_eq!(out.status.code(), Some(2), "{:?}", out);
+    let out = run(&dir, &["verify", "/elsewhere/fixed.json"], "");
+    assert_eq!(out.status.code(), Some(1), "{:?}", out);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -325,6 +325,11 @@ async function postWorkerMessage(messageData) {
 // handleWorkerMessage will process any messages coming from the webworker.
 function handleWorkerMessage(event) {
   const nonce = event.data.nonce
+  // The worker reports progress while verifying, which this page does not
+  // show; wait for the response itself.
+  if (event.data.progress) {
+    return
+  }
   activeQueries[nonce](event.data)
   delete activeQueries[nonce]
 }
@@ -80,6 +80,8 @@ async function performVerification() {
     publicKeys,
     message: skylinkData.message,
     proof: skylinkData.proof,
+  }, function(progress) {
+    setVerificationStatus("#FCD083", `Verifying: ${progress.done} of ${progress.total} keys checked`)
   })
   const isValidProof = isValidProofResp.isValidProof
   if (isValidProof !== "") {
@@ -87,8 +89,10 @@ async function performVerification() {
     return
   }
 
-  // Download complete, update the verification status to processing.
-  setVerificationStatus("#79C78E", "Proof is Valid")
+  // Download complete, update the verification status to processing. The ids
+  // are shown so that the ring and message can be compared with another
+  // verifier's.
+  setVerificationStatus("#79C78E", `Proof is Valid<br />ring ${isValidProofResp.ringId}<br />params hash ${isValidProofResp.paramsHash}`)
 
   // Enable the download button.
   document.getElementById("downloadMessage").disabled = false
@@ -215,10 +219,12 @@ const getWorker = new Promise((resolve) => {
 })
 
 // postWorkerMessage is an abstraction around worker communications to make
-// simple query-response interactions painless.
+// simple query-response interactions painless. If onProgress is given, it is
+// called with any progress the worker reports before the response.
 let workerNonce = 0
 let activeQueries = {}
-async function postWorkerMessage(messageData) {
+let progressHandlers = {}
+async function postWorkerMessage(messageData, onProgress) {
   // Get a unique nonce for this message.
   const nonce = workerNonce
   workerNonce += 1
@@ -226,6 +232,9 @@ async function postWorkerMessage(messageData) {
 
   // Send the message to the worker with the nonce.
   const worker = await getWorker
+  if (onProgress) {
+    progressHandlers[nonce] = onProgress
+  }
   worker.postMessage(messageData)
 
   // Craft the promise that will be resolved by handleWorkerMessage when a
@@ -238,8 +247,15 @@ async function postWorkerMessage(messageData) {
 // handleWorkerMessage will process any messages coming from the webworker.
 function handleWorkerMessage(event) {
   const nonce = event.data.nonce
+  if (event.data.progress) {
+    if (progressHandlers[nonce]) {
+      progressHandlers[nonce](event.data.progress)
+    }
+    return
+  }
   activeQueries[nonce](event.data)
   delete activeQueries[nonce]
+  delete progressHandlers[nonce]
 }
 
 // escapeHtml is a helper function that will sanitize
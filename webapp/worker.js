@@ -1,6 +1,6 @@
 // Import the wasm stuff and bind it.
 importScripts("./pkg/ringsig.js");
-const {prove, is_acceptable_pubkey, is_secret_key, verify} = wasm_bindgen;
+const {prove, is_acceptable_pubkey, is_secret_key, ring_ids, verify_with_progress} = wasm_bindgen;
 
 // init returns a promise that resolves when the wasm bindings have completed.
 async function init() {
@@ -33,10 +33,23 @@ onmessage = async function(event) {
   }
 
   // Check whether the caller wants to know whether the input is a valid proof.
+  // Progress is reported along the way, since large rings take a while.
   if (event.data.method === "verify") {
-    const isValidProof = verify(event.data.proof, event.data.publicKeys, event.data.message)
+    const nonce = event.data.nonce
+    const progress = function(done, total) {
+      postMessage({
+        progress: {done, total},
+        nonce,
+      })
+    }
+    const isValidProof = verify_with_progress(event.data.proof, event.data.publicKeys, event.data.message, progress)
+    // The ids let users check that they are verifying the same ring and
+    // message as someone else.
+    const [ringId, paramsHash] = ring_ids(event.data.publicKeys, event.data.message)
     postMessage({
       isValidProof,
+      ringId,
+      paramsHash,
       nonce: event.data.nonce,
     })
     return